@@ -4,19 +4,19 @@
 
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 use std::{fs::{File, OpenOptions}, io::Write, sync::Mutex};
-use std::{cell::RefCell, collections::HashMap};
+use std::{cell::RefCell, collections::{HashMap, HashSet}};
 use makepad_widgets::*;
-use matrix_sdk::{RoomState, ruma::{OwnedEventId, OwnedMxcUri, OwnedRoomId, OwnedUserId, RoomId, UserId, events::room::message::RoomMessageEventContent}};
+use matrix_sdk::{RoomState, ruma::{MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedMxcUri, OwnedRoomId, OwnedTransactionId, OwnedUserId, RoomId, UserId, events::room::message::RoomMessageEventContent}};
 use serde::{Deserialize, Serialize};
 use url::Url;
 use crate::{
-    avatar_cache::{self, AvatarCacheEntry, clear_avatar_cache}, home::{
+    audio_playback, avatar_cache::{self, AvatarCacheEntry, clear_avatar_cache}, bookmarks::BookmarksAction, unsent_messages::UnsentMessagesAction, chat_export::ChatExportAction, forward_message::ForwardMessageAction, home::{
         add_room::{CreateRoomModalAction, CreateRoomModalWidgetRefExt, StartChatModalAction, StartChatModalWidgetRefExt},
         bot_binding_modal::{BotBindingModalAction, BotBindingModalWidgetRefExt},
-        event_source_modal::{EventSourceModalAction, EventSourceModalWidgetRefExt}, invite_modal::{InviteModalAction, InviteModalWidgetRefExt, mark_invite_modal_closed}, invite_screen::{InviteScreenWidgetRefExt, LeaveRoomResultAction}, main_desktop_ui::MainDesktopUiAction, navigation_tab_bar::{NavigationBarAction, SelectedTab}, new_message_context_menu::NewMessageContextMenuWidgetRefExt, room_context_menu::RoomContextMenuWidgetRefExt, room_screen::{InviteAction, MessageAction, RoomScreenWidgetRefExt, TimelineUpdate, clear_timeline_states}, rooms_list::{RoomsListAction, RoomsListRef, RoomsListUpdate, clear_all_invited_rooms, enqueue_rooms_list_update}, rooms_list_header::RoomsListHeaderAction, space_lobby::SpaceLobbyScreenWidgetRefExt, spaces_bar::SpacesBarRef
+        edit_history_modal::{EditHistoryModalAction, EditHistoryModalWidgetRefExt}, event_source_modal::{EventSourceModalAction, EventSourceModalWidgetRefExt}, invite_modal::{InviteModalAction, InviteModalWidgetRefExt, mark_invite_modal_closed}, invite_screen::{InviteScreenWidgetRefExt, LeaveRoomResultAction}, main_desktop_ui::MainDesktopUiAction, navigation_tab_bar::{NavigationBarAction, SelectedTab}, new_message_context_menu::NewMessageContextMenuWidgetRefExt, export_chat_modal::{ExportChatModalAction, ExportChatModalWidgetRefExt}, forward_message_modal::{ForwardMessageModalAction, ForwardMessageModalWidgetRefExt}, jump_to_date::{JumpToDateModalAction, JumpToDateModalWidgetRefExt}, schedule_message_modal::{ScheduleMessageModalAction, ScheduleMessageModalWidgetRefExt, format_send_at}, poll_composer::{CreatePollModalAction, CreatePollModalWidgetRefExt}, room_context_menu::RoomContextMenuWidgetRefExt, room_screen::{InviteAction, MessageAction, RoomScreenWidgetRefExt, TimelineUpdate, clear_timeline_states}, rooms_list::{RoomsListAction, RoomsListRef, RoomsListUpdate, clear_all_invited_rooms, enqueue_rooms_list_update}, rooms_list_header::RoomsListHeaderAction, saved_messages::{SavedMessagesModalAction, SavedMessagesModalWidgetRefExt}, search_messages::{SearchMessagesModalAction, SearchMessagesModalWidgetRefExt}, space_lobby::SpaceLobbyScreenWidgetRefExt, spaces_bar::SpacesBarRef, unsent_messages_modal::{UnsentMessagesModalAction, UnsentMessagesModalWidgetRefExt}
     }, i18n::{AppLanguage, tr_fmt, tr_key}, join_leave_room_modal::{
         JoinLeaveModalKind, JoinLeaveRoomModalAction, JoinLeaveRoomModalWidgetRefExt
-    }, login::login_screen::LoginAction, logout::logout_confirm_modal::{LogoutAction, LogoutConfirmModalAction, LogoutConfirmModalWidgetRefExt}, persistence, profile::{user_profile::UserProfile, user_profile_cache::clear_user_profile_cache}, room::{BasicRoomDetails, FetchedRoomAvatar}, shared::{avatar::{AvatarState, AvatarWidgetRefExt}, confirmation_modal::{ConfirmationModalContent, ConfirmationModalWidgetRefExt}, file_upload_modal::{FilePreviewerAction, FileUploadModalWidgetRefExt}, image_viewer::{ImageViewerAction, LoadState}, popup_list::{PopupKind, enqueue_popup_notification}, room_filter_input_bar::FilterAction}, sliding_sync::{DirectMessageRoomAction, MatrixRequest, RemoteDirectorySearchKind, RemoteDirectorySearchResult, TimelineKind, AccountSwitchAction, current_user_id, get_client, submit_async_request, get_timeline_update_sender}, utils::RoomNameId, verification::VerificationAction, verification_modal::{
+    }, live_location, login::login_screen::LoginAction, logout::logout_confirm_modal::{LogoutAction, LogoutConfirmModalAction, LogoutConfirmModalWidgetRefExt}, persistence, profile::{user_profile::UserProfile, user_profile_cache::clear_user_profile_cache}, room::{BasicRoomDetails, FetchedRoomAvatar}, scheduled_messages::{ScheduledMessagesAction, due_local_messages}, shared::{avatar::{AvatarState, AvatarWidgetRefExt}, confirmation_modal::{ConfirmationModalContent, ConfirmationModalWidgetRefExt}, file_upload_modal::{FilePreviewerAction, FileUploadModalWidgetRefExt}, image_viewer::{ImageViewerAction, LoadState}, popup_list::{PopupKind, enqueue_popup_notification}, room_filter_input_bar::FilterAction}, sliding_sync::{DirectMessageRoomAction, JumpToDateAction, MatrixRequest, RemoteDirectorySearchKind, RemoteDirectorySearchResult, TimelineKind, AccountSwitchAction, current_user_id, get_client, submit_async_request, get_timeline_update_sender}, utils::RoomNameId, verification::VerificationAction, verification_modal::{
         VerificationModalAction,
         VerificationModalWidgetRefExt,
     }
@@ -187,7 +187,18 @@ script_mod! {
                                     }
                                     padding: Inset{top: 15, left: 15, right: 15, bottom: 15}
 
-                                    room_filter_input_bar := RoomFilterInputBar {}
+                                    View {
+                                        width: Fill,
+                                        height: Fit,
+                                        flow: Right
+                                        spacing: 5
+                                        align: Align{y: 0.5}
+
+                                        room_filter_input_bar := RoomFilterInputBar {}
+                                        search_messages_button := SearchMessagesButton {}
+                                        saved_messages_button := SavedMessagesButton {}
+                                        unsent_messages_button := UnsentMessagesButton {}
+                                    }
 
                                     search_results_title := Label {
                                         width: Fill,
@@ -277,6 +288,60 @@ script_mod! {
                             }
                         }
 
+                        search_messages_modal := Modal {
+                            content +: {
+                                search_messages_modal_inner := SearchMessagesModal {}
+                            }
+                        }
+
+                        saved_messages_modal := Modal {
+                            content +: {
+                                saved_messages_modal_inner := SavedMessagesModal {}
+                            }
+                        }
+
+                        unsent_messages_modal := Modal {
+                            content +: {
+                                unsent_messages_modal_inner := UnsentMessagesModal {}
+                            }
+                        }
+
+                        create_poll_modal := Modal {
+                            content +: {
+                                create_poll_modal_inner := CreatePollModal {}
+                            }
+                        }
+
+                        jump_to_date_modal := Modal {
+                            content +: {
+                                jump_to_date_modal_inner := JumpToDateModal {}
+                            }
+                        }
+
+                        schedule_message_modal := Modal {
+                            content +: {
+                                schedule_message_modal_inner := ScheduleMessageModal {}
+                            }
+                        }
+
+                        export_chat_modal := Modal {
+                            content +: {
+                                export_chat_modal_inner := ExportChatModal {}
+                            }
+                        }
+
+                        forward_message_modal := Modal {
+                            content +: {
+                                forward_message_modal_inner := ForwardMessageModal {}
+                            }
+                        }
+
+                        edit_history_modal := Modal {
+                            content +: {
+                                edit_history_modal_inner := EditHistoryModal {}
+                            }
+                        }
+
                         // Show the logout confirmation modal.
                         logout_confirm_modal := Modal {
                             content +: {
@@ -381,6 +446,10 @@ pub struct App {
     #[rust] room_filter_modal_results: Vec<RoomFilterResultTarget>,
     #[rust(Timer::empty())] room_filter_debounce_timer: Timer,
     #[rust] pending_room_filter_keywords: String,
+    /// The interval at which locally-scheduled messages are checked and sent once they're due.
+    #[rust(Timer::empty())] scheduled_messages_timer: Timer,
+    /// The IDs of the scheduled messages that are currently being sent by the local scheduler.
+    #[rust] sending_scheduled_messages: HashSet<OwnedTransactionId>,
 }
 
 impl ScriptHook for App {
@@ -597,6 +666,9 @@ pub fn cleanup_old_logs(max_logs_to_keep: usize) {
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 const MAX_LOG_FILES_TO_KEEP: usize = 10;
 
+/// How often to check whether any locally-scheduled messages are due to be sent.
+const SCHEDULED_MESSAGES_CHECK_INTERVAL_SECS: f64 = 15.0;
+
 impl MatchEvent for App {
     fn handle_startup(&mut self, cx: &mut Cx) {
         // only init logging/tracing once
@@ -641,6 +713,8 @@ impl MatchEvent for App {
         self.update_login_visibility(cx);
         self.sync_app_language(cx);
 
+        self.scheduled_messages_timer = cx.start_interval(SCHEDULED_MESSAGES_CHECK_INTERVAL_SECS);
+
         log!("App::Startup: starting matrix sdk loop");
         let _tokio_rt_handle = crate::sliding_sync::start_matrix_tokio().unwrap();
 
@@ -661,11 +735,19 @@ impl MatchEvent for App {
             let keywords = std::mem::take(&mut self.pending_room_filter_keywords);
             self.update_room_filter_modal_results(cx, &keywords);
         }
+        if self.scheduled_messages_timer.is_timer(event).is_some() && self.app_state.logged_in {
+            self.send_due_scheduled_messages();
+        }
     }
 
     fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions) {
         self.sync_app_language(cx);
 
+        // Keep the current user's live location shares up to date, regardless of which room is shown.
+        for action in actions {
+            live_location::handle_action(cx, action);
+        }
+
         let invite_confirmation_modal_inner = self.ui.confirmation_modal(cx, ids!(invite_confirmation_modal_inner));
         if let Some(_accepted) = invite_confirmation_modal_inner.closed(actions) {
             self.ui.modal(cx, ids!(invite_confirmation_modal)).close(cx);
@@ -754,6 +836,14 @@ impl MatchEvent for App {
             );
         }
 
+        if self.clicked_mobile_room_date_button(cx, actions)
+            && let Some(SelectedRoom::JoinedRoom { room_name_id }) = self.app_state.selected_room.as_ref()
+        {
+            cx.action(JumpToDateModalAction::Open {
+                timeline_kind: TimelineKind::MainRoom { room_id: room_name_id.room_id().clone() },
+            });
+        }
+
         for action in actions {
             match action.downcast_ref() {
                 Some(LogoutConfirmModalAction::Open) => {
@@ -974,11 +1064,24 @@ impl MatchEvent for App {
                 continue;
             }
 
-            // Handle an action requesting to open the new message context menu.
-            if let MessageAction::OpenMessageContextMenu { details, abs_pos, opening_gesture } = action.as_widget_action().cast() {
+            // Handle an action requesting to open the new message context menu,
+            // optionally with its reaction input already shown (from the message action bar).
+            let open_context_menu = match action.as_widget_action().cast() {
+                MessageAction::OpenMessageContextMenu { details, abs_pos, opening_gesture } => {
+                    Some((details, abs_pos, opening_gesture, false))
+                }
+                MessageAction::OpenReactionInput { details, abs_pos, opening_gesture } => {
+                    Some((details, abs_pos, opening_gesture, true))
+                }
+                _ => None,
+            };
+            if let Some((details, abs_pos, opening_gesture, show_reaction_input)) = open_context_menu {
                 self.ui.callout_tooltip(cx, ids!(app_tooltip)).hide(cx);
                 let new_message_context_menu = self.ui.new_message_context_menu(cx, ids!(new_message_context_menu));
-                let expected_dimensions = new_message_context_menu.show(cx, details, self.app_state.app_language, opening_gesture);
+                let mut expected_dimensions = new_message_context_menu.show(cx, details, self.app_state.app_language, opening_gesture);
+                if show_reaction_input {
+                    expected_dimensions = new_message_context_menu.show_reaction_input(cx);
+                }
                 // Use the overlay container's rect (not the window's) to correctly position
                 // the context menu relative to the body area, which excludes the caption bar.
                 let rect = self.ui.view(cx, ids!(overlay_container)).area().rect(cx);
@@ -1114,6 +1217,8 @@ impl MatchEvent for App {
                     self.app_state.logged_in = logged_in_actual;
                     // Initialize the global translation config so RoomInputBar can access it.
                     crate::room::translation::set_global_config(&self.app_state.translation);
+                    crate::local_search_index::set_enabled(self.app_state.local_search_index_enabled);
+                    crate::map_tiles::set_global_config(cx, &self.app_state.map_tiles);
                     if removed_room_bindings > 0 {
                         if let Some(user_id) = current_user_id() {
                             if let Err(e) = persistence::save_app_state(self.app_state.clone(), user_id) {
@@ -1413,6 +1518,278 @@ impl MatchEvent for App {
                 _ => {}
             }
 
+            // Handle SearchMessagesModalAction to open/close the message search modal.
+            match action.downcast_ref() {
+                Some(SearchMessagesModalAction::Open { room_name_id }) => {
+                    // The global search button is also shown within the room filter modal.
+                    self.ui.modal(cx, ids!(room_filter_modal)).close(cx);
+                    self.ui.search_messages_modal(cx, ids!(search_messages_modal_inner))
+                        .show(cx, room_name_id.clone(), self.app_state.app_language);
+                    self.ui.modal(cx, ids!(search_messages_modal)).open(cx);
+                    continue;
+                }
+                Some(SearchMessagesModalAction::Close) => {
+                    self.ui.modal(cx, ids!(search_messages_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle SavedMessagesModalAction to open/close the list of saved messages.
+            match action.downcast_ref() {
+                Some(SavedMessagesModalAction::Open) => {
+                    // The saved messages button is also shown within the room filter modal.
+                    self.ui.modal(cx, ids!(room_filter_modal)).close(cx);
+                    self.ui.saved_messages_modal(cx, ids!(saved_messages_modal_inner))
+                        .show(cx, self.app_state.app_language);
+                    self.ui.modal(cx, ids!(saved_messages_modal)).open(cx);
+                    continue;
+                }
+                Some(SavedMessagesModalAction::Close) => {
+                    self.ui.modal(cx, ids!(saved_messages_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle UnsentMessagesModalAction to open/close the list of unsent messages.
+            match action.downcast_ref() {
+                Some(UnsentMessagesModalAction::Open { room_id }) => {
+                    // The unsent messages button is also shown within the room filter modal.
+                    self.ui.modal(cx, ids!(room_filter_modal)).close(cx);
+                    self.ui.unsent_messages_modal(cx, ids!(unsent_messages_modal_inner))
+                        .show(cx, self.app_state.app_language, room_id.clone());
+                    self.ui.modal(cx, ids!(unsent_messages_modal)).open(cx);
+                    continue;
+                }
+                Some(UnsentMessagesModalAction::Close) => {
+                    self.ui.modal(cx, ids!(unsent_messages_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle CreatePollModalAction to open/close the poll composer modal.
+            match action.downcast_ref() {
+                Some(CreatePollModalAction::Open { timeline_kind }) => {
+                    self.ui.create_poll_modal(cx, ids!(create_poll_modal_inner))
+                        .show(cx, timeline_kind.clone(), self.app_state.app_language);
+                    self.ui.modal(cx, ids!(create_poll_modal)).open(cx);
+                    continue;
+                }
+                Some(CreatePollModalAction::Close) => {
+                    self.ui.modal(cx, ids!(create_poll_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle JumpToDateModalAction to open/close the jump-to-date modal.
+            match action.downcast_ref() {
+                Some(JumpToDateModalAction::Open { timeline_kind }) => {
+                    self.ui.jump_to_date_modal(cx, ids!(jump_to_date_modal_inner))
+                        .show(cx, timeline_kind.clone(), self.app_state.app_language);
+                    self.ui.modal(cx, ids!(jump_to_date_modal)).open(cx);
+                    continue;
+                }
+                Some(JumpToDateModalAction::Close) => {
+                    self.ui.modal(cx, ids!(jump_to_date_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle ScheduleMessageModalAction to open/close the "send later" modal.
+            // The `DraftScheduled` action is handled by the `RoomInputBar` that the draft came from.
+            match action.downcast_ref() {
+                Some(ScheduleMessageModalAction::Open { timeline_kind, text, content }) => {
+                    self.ui.schedule_message_modal(cx, ids!(schedule_message_modal_inner)).show(
+                        cx,
+                        timeline_kind.clone(),
+                        text.clone(),
+                        content.as_ref().clone(),
+                        self.app_state.app_language,
+                    );
+                    self.ui.modal(cx, ids!(schedule_message_modal)).open(cx);
+                    continue;
+                }
+                Some(ScheduleMessageModalAction::Close) => {
+                    self.ui.modal(cx, ids!(schedule_message_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Keep the list of scheduled messages up to date.
+            if let Some(scheduled_action) = action.downcast_ref::<ScheduledMessagesAction>() {
+                self.handle_scheduled_messages_action(scheduled_action);
+                self.ui.redraw(cx);
+                continue;
+            }
+
+            // Handle ExportChatModalAction to open/close the chat export modal.
+            match action.downcast_ref() {
+                Some(ExportChatModalAction::Open(room_name_id)) => {
+                    self.ui.export_chat_modal(cx, ids!(export_chat_modal_inner))
+                        .show(cx, room_name_id.clone(), self.app_state.app_language);
+                    self.ui.modal(cx, ids!(export_chat_modal)).open(cx);
+                    continue;
+                }
+                Some(ExportChatModalAction::Close) => {
+                    self.ui.modal(cx, ids!(export_chat_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle the progress and result of an export running in the background,
+            // which must be shown even if the export modal was closed in the meantime.
+            if let Some(export_action) = action.downcast_ref::<ChatExportAction>() {
+                self.ui.export_chat_modal(cx, ids!(export_chat_modal_inner))
+                    .handle_export_action(cx, export_action);
+                let app_language = self.app_state.app_language;
+                match export_action {
+                    ChatExportAction::Progress { .. } => {}
+                    ChatExportAction::Finished { path, .. } => {
+                        enqueue_popup_notification(
+                            tr_fmt(app_language, "export_chat_modal.popup.finished", &[("path", &path.display().to_string())]),
+                            PopupKind::Success,
+                            None,
+                        );
+                    }
+                    ChatExportAction::Cancelled { .. } => {
+                        enqueue_popup_notification(
+                            tr_key(app_language, "export_chat_modal.popup.cancelled"),
+                            PopupKind::Info,
+                            Some(4.0),
+                        );
+                    }
+                    ChatExportAction::Failed { error, .. } => {
+                        enqueue_popup_notification(
+                            tr_fmt(app_language, "export_chat_modal.popup.failed", &[("error", error)]),
+                            PopupKind::Error,
+                            None,
+                        );
+                    }
+                }
+                continue;
+            }
+
+            // Handle ForwardMessageModalAction to open/close the forward message modal.
+            match action.downcast_ref() {
+                Some(ForwardMessageModalAction::Open { content, preview_text }) => {
+                    self.ui.forward_message_modal(cx, ids!(forward_message_modal_inner))
+                        .show(cx, content.as_ref().clone(), preview_text, self.app_state.app_language);
+                    self.ui.modal(cx, ids!(forward_message_modal)).open(cx);
+                    continue;
+                }
+                Some(ForwardMessageModalAction::Close) => {
+                    self.ui.modal(cx, ids!(forward_message_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Handle the result of forwarding a message in the background.
+            if let Some(forward_action) = action.downcast_ref::<ForwardMessageAction>() {
+                let app_language = self.app_state.app_language;
+                match forward_action {
+                    ForwardMessageAction::Sent { num_sent, num_failed: 0 } => {
+                        enqueue_popup_notification(
+                            tr_fmt(app_language, "forward_message_modal.popup.sent", &[("count", &num_sent.to_string())]),
+                            PopupKind::Success,
+                            Some(4.0),
+                        );
+                    }
+                    ForwardMessageAction::Sent { num_sent, num_failed } => {
+                        enqueue_popup_notification(
+                            tr_fmt(app_language, "forward_message_modal.popup.partially_failed", &[
+                                ("count", &num_sent.to_string()),
+                                ("failed", &num_failed.to_string()),
+                            ]),
+                            PopupKind::Warning,
+                            None,
+                        );
+                    }
+                    ForwardMessageAction::Failed { error } => {
+                        enqueue_popup_notification(
+                            tr_fmt(app_language, "forward_message_modal.popup.failed", &[("error", error)]),
+                            PopupKind::Error,
+                            None,
+                        );
+                    }
+                }
+                continue;
+            }
+
+            // Handle the result of (un-)bookmarking a message.
+            // Other bookmark actions are handled by the `SavedMessagesModal`.
+            match action.downcast_ref() {
+                Some(BookmarksAction::Changed { bookmarked }) => {
+                    enqueue_popup_notification(
+                        tr_key(
+                            self.app_state.app_language,
+                            if *bookmarked { "saved_messages.popup.saved" } else { "saved_messages.popup.removed" },
+                        ),
+                        PopupKind::Success,
+                        Some(3.0),
+                    );
+                    continue;
+                }
+                Some(BookmarksAction::Failed { error }) => {
+                    enqueue_popup_notification(
+                        tr_fmt(self.app_state.app_language, "saved_messages.popup.failed", &[("error", error)]),
+                        PopupKind::Error,
+                        None,
+                    );
+                    continue;
+                }
+                // Redraw such that each message's context menu knows whether it is bookmarked.
+                Some(BookmarksAction::Updated) => self.ui.redraw(cx),
+                _ => {}
+            }
+
+            // Other unsent message actions are handled by the `UnsentMessagesModal`.
+            match action.downcast_ref() {
+                Some(UnsentMessagesAction::Failed { error }) => {
+                    enqueue_popup_notification(
+                        tr_fmt(self.app_state.app_language, "unsent_messages.popup.failed", &[("error", error)]),
+                        PopupKind::Error,
+                        None,
+                    );
+                    continue;
+                }
+                // Redraw such that the rooms list and composers show which rooms have unsent messages.
+                Some(UnsentMessagesAction::Updated) => self.ui.redraw(cx),
+                _ => {}
+            }
+
+            // A successful jump to a date is handled by the `RoomScreen` showing that timeline.
+            if let Some(JumpToDateAction::Failed { not_found, error, .. }) = action.downcast_ref() {
+                let key = if *not_found { "jump_to_date.popup.not_found" } else { "jump_to_date.popup.failed" };
+                enqueue_popup_notification(
+                    tr_fmt(self.app_state.app_language, key, &[("error", error)]),
+                    PopupKind::Error,
+                    None,
+                );
+                continue;
+            }
+
+            // Handle EditHistoryModalAction to open/close the edit history modal.
+            match action.downcast_ref() {
+                Some(EditHistoryModalAction::Open { room_id, event_id }) => {
+                    self.ui.edit_history_modal(cx, ids!(edit_history_modal_inner))
+                        .show(cx, room_id.clone(), event_id.clone(), self.app_state.app_language);
+                    self.ui.modal(cx, ids!(edit_history_modal)).open(cx);
+                    continue;
+                }
+                Some(EditHistoryModalAction::Close) => {
+                    self.ui.modal(cx, ids!(edit_history_modal)).close(cx);
+                    continue;
+                }
+                _ => {}
+            }
+
             // Handle EventSourceModalAction to open/close the event source modal.
             match action.downcast_ref() {
                 Some(EventSourceModalAction::Open { room_id, event_id, original_json }) => {
@@ -1498,6 +1875,7 @@ fn clear_all_app_state(cx: &mut Cx) {
     clear_all_invited_rooms(cx);
     clear_timeline_states(cx);
     clear_avatar_cache(cx);
+    audio_playback::stop();
 }
 
 impl AppMain for App {
@@ -1520,6 +1898,7 @@ impl AppMain for App {
         // RoomInputBar depends on these Home widgets; preload them before room::script_mod.
         crate::home::location_preview::script_mod(vm);
         crate::home::tombstone_footer::script_mod(vm);
+        crate::home::poll_composer::script_mod(vm);
         crate::home::editing_pane::script_mod(vm);
         crate::home::upload_progress::script_mod(vm);
         crate::room::script_mod(vm);
@@ -1565,7 +1944,10 @@ impl AppMain for App {
                 }
             }
         }
-        
+        if let Event::AudioDevices(devices) = event {
+            audio_playback::handle_audio_devices(cx, devices);
+        }
+
         // Forward events to the MatchEvent trait implementation.
         self.match_event(cx, event);
         let scope = &mut Scope::with_data(&mut self.app_state);
@@ -1642,6 +2024,91 @@ impl App {
         });
     }
 
+    /// Sends each locally-scheduled message whose time has come.
+    fn send_due_scheduled_messages(&mut self) {
+        let due = due_local_messages(&self.app_state.scheduled_messages, MilliSecondsSinceUnixEpoch::now());
+        for id in due {
+            if !self.sending_scheduled_messages.insert(id.clone()) {
+                continue;
+            }
+            if let Some(scheduled) = self.app_state.scheduled_messages.iter().find(|m| m.id == id) {
+                submit_async_request(MatrixRequest::SendScheduledMessageNow { scheduled: scheduled.clone() });
+            }
+        }
+
+        // Messages scheduled on the homeserver are sent by the homeserver itself,
+        // so we can forget about them once their time has passed.
+        let now = MilliSecondsSinceUnixEpoch::now();
+        let num_scheduled = self.app_state.scheduled_messages.len();
+        self.app_state.scheduled_messages.retain(|m| !m.is_scheduled_on_server() || m.send_at > now);
+        if self.app_state.scheduled_messages.len() != num_scheduled {
+            self.save_scheduled_messages();
+        }
+    }
+
+    /// Saves the app state in the background after its list of scheduled messages has changed.
+    ///
+    /// Scheduled messages must be saved right away rather than only upon shutdown,
+    /// otherwise a crash could cause a message to be sent twice, or cause a message
+    /// (and the ID needed to cancel its delayed event on the homeserver) to be forgotten.
+    fn save_scheduled_messages(&self) {
+        if let Some(user_id) = current_user_id() {
+            persistence::save_app_state_in_background(self.app_state.clone(), user_id);
+        }
+    }
+
+    /// Updates the list of scheduled messages based on the given result of a scheduled message request.
+    fn handle_scheduled_messages_action(&mut self, action: &ScheduledMessagesAction) {
+        let app_language = self.app_state.app_language;
+        let scheduled_messages = &mut self.app_state.scheduled_messages;
+        match action {
+            ScheduledMessagesAction::Scheduled(scheduled) => {
+                self.sending_scheduled_messages.remove(&scheduled.id);
+                enqueue_popup_notification(
+                    tr_fmt(app_language, "schedule_message_modal.popup.scheduled", &[
+                        ("time", &format_send_at(scheduled.send_at)),
+                    ]),
+                    PopupKind::Success,
+                    Some(4.0),
+                );
+                match scheduled_messages.iter_mut().find(|m| m.id == scheduled.id) {
+                    Some(existing) => *existing = scheduled.clone(),
+                    None => scheduled_messages.push(scheduled.clone()),
+                }
+                self.save_scheduled_messages();
+            }
+            ScheduledMessagesAction::Sent { id } => {
+                self.sending_scheduled_messages.remove(id);
+                scheduled_messages.retain(|m| &m.id != id);
+                self.save_scheduled_messages();
+            }
+            ScheduledMessagesAction::Cancelled { id } => {
+                scheduled_messages.retain(|m| &m.id != id);
+                self.save_scheduled_messages();
+                enqueue_popup_notification(
+                    tr_key(app_language, "schedule_message_modal.popup.cancelled"),
+                    PopupKind::Success,
+                    Some(3.0),
+                );
+            }
+            ScheduledMessagesAction::Failed { id, error } => {
+                // Only a failure to send a message is shown in the list of scheduled messages,
+                // as a message that failed to be scheduled or cancelled is left unchanged.
+                if self.sending_scheduled_messages.remove(id)
+                    && let Some(scheduled) = scheduled_messages.iter_mut().find(|m| &m.id == id)
+                {
+                    scheduled.last_error = Some(error.clone());
+                    self.save_scheduled_messages();
+                }
+                enqueue_popup_notification(
+                    tr_fmt(app_language, "schedule_message_modal.popup.failed", &[("error", error)]),
+                    PopupKind::Error,
+                    None,
+                );
+            }
+        }
+    }
+
     fn update_login_visibility(&self, cx: &mut Cx) {
         let show_login = self.app_state.adding_account || self.auth_ui_state == AuthUiState::LoggedOut;
         let show_home = self.auth_ui_state != AuthUiState::LoggedOut;
@@ -1694,6 +2161,20 @@ impl App {
         None
     }
 
+    /// Returns whether the "jump to date" button in a mobile room header was clicked.
+    fn clicked_mobile_room_date_button(&self, cx: &mut Cx, actions: &Actions) -> bool {
+        Self::ROOM_VIEW_IDS.iter().any(|view_id| {
+            let button_path = &[
+                *view_id,
+                live_id!(header),
+                live_id!(content),
+                live_id!(button_container),
+                live_id!(date_button),
+            ];
+            self.ui.button(cx, button_path).clicked(actions)
+        })
+    }
+
     fn set_room_filter_modal_empty_state(
         &self,
         cx: &mut Cx,
@@ -2051,6 +2532,14 @@ impl App {
             right_button.set_text(cx, "");
             right_button.reset_hover(cx);
         }
+        // Jumping to a date is only supported for a room's main timeline.
+        let date_button_path = &[view_id, live_id!(header), live_id!(content), live_id!(button_container), live_id!(date_button)];
+        let show_date_button = matches!(selected_room, SelectedRoom::JoinedRoom { .. });
+        let date_button = self.ui.button(cx, date_button_path);
+        date_button.set_visible(cx, show_date_button);
+        if show_date_button {
+            date_button.reset_hover(cx);
+        }
 
         // Save the current selected_room onto the navigation stack before replacing it.
         if let Some(prev) = self.app_state.selected_room.take() {
@@ -2101,6 +2590,17 @@ pub struct AppState {
     /// Translation API configuration.
     #[serde(default)]
     pub translation: crate::room::translation::TranslationConfig,
+    /// Whether the user has opted in to the on-device search index for encrypted rooms.
+    #[serde(default)]
+    pub local_search_index_enabled: bool,
+    /// The tile server used to draw map previews of shared locations.
+    #[serde(default)]
+    pub map_tiles: crate::map_tiles::MapTileConfig,
+    /// The user's emoji skin tone and recently-used emoji.
+    pub emoji_picker: crate::shared::emoji_picker::EmojiPickerState,
+    /// Messages that are scheduled to be sent later, in any room.
+    #[serde(default)]
+    pub scheduled_messages: Vec<crate::scheduled_messages::ScheduledMessage>,
 }
 
 /// Local bot integration settings persisted per Matrix account.
@@ -0,0 +1,528 @@
+//! Decoding and playback of audio clips, e.g., audio and voice messages.
+//!
+//! Audio files are decoded into mono PCM samples on the [`cpu_worker`](crate::cpu_worker),
+//! which also computes a waveform summary for files that don't specify one themselves.
+//! Only one clip is played at a time: starting a clip stops any other playing clip.
+//!
+//! Most voice messages are Ogg/Opus files. Symphonia demuxes those, but has no Opus decoder,
+//! so their packets are decoded with libopus instead (see [`decode_opus()`]).
+//! Clips that still can't be decoded can be opened in an external app from the audio player.
+
+use std::{collections::VecDeque, io::Cursor, sync::{Arc, LazyLock, Mutex, atomic::{AtomicBool, AtomicU32, Ordering}}, time::{Duration, Instant}};
+
+use hashbrown::{HashMap, HashSet};
+use makepad_widgets::{AudioDevicesEvent, Cx, CxMediaApi, error, log};
+use audiopus::{Channels, MutSignals, SampleRate, coder::Decoder as OpusDecoder, packet::Packet as OpusPacket};
+use matrix_sdk::ruma::OwnedMxcUri;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{CODEC_TYPE_NULL, CODEC_TYPE_OPUS, DecoderOptions},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, Packet},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::cpu_worker::{self, CpuJob};
+
+/// The number of bars in a waveform summary of an audio clip.
+pub const WAVEFORM_BARS: usize = 48;
+
+/// The sample rate assumed for the audio output device until its actual rate has been measured.
+const DEFAULT_OUTPUT_SAMPLE_RATE: u32 = 48_000;
+
+/// The sample rates that audio output devices commonly run at.
+const STANDARD_SAMPLE_RATES: [u32; 9] = [8_000, 16_000, 22_050, 24_000, 32_000, 44_100, 48_000, 88_200, 96_000];
+
+/// How long to count the frames requested by the audio output callback
+/// before deriving the output device's sample rate from them.
+const SAMPLE_RATE_MEASUREMENT_PERIOD: Duration = Duration::from_millis(500);
+
+/// The maximum number of decoded audio clips to keep in memory.
+///
+/// Decoded PCM samples are large (about 10 MB per minute of audio),
+/// so we only keep the most recently decoded clips around.
+const MAX_DECODED_CLIPS: usize = 4;
+
+/// The sample rate that Opus audio is decoded at.
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// The maximum duration of a single Opus packet.
+const MAX_OPUS_PACKET_DURATION: Duration = Duration::from_millis(120);
+
+/// An audio clip decoded into mono PCM samples.
+#[derive(Debug)]
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+/// The playback state of the clip that is currently playing (or paused).
+#[derive(Copy, Clone, Debug)]
+pub struct PlaybackStatus {
+    pub position: Duration,
+    pub duration: Duration,
+    pub is_playing: bool,
+}
+
+/// An action posted when an audio clip has been decoded or failed to decode.
+#[derive(Clone, Debug)]
+pub struct AudioDecodedAction {
+    pub mxc_uri: OwnedMxcUri,
+}
+
+/// The clip currently being played, which is shared with the audio output thread.
+struct ActivePlayback {
+    mxc_uri: OwnedMxcUri,
+    audio: Arc<DecodedAudio>,
+    /// The current position in `audio.samples`, which may be fractional due to resampling.
+    position: f64,
+    is_playing: bool,
+}
+
+#[derive(Default)]
+struct DecoderState {
+    /// The most recently decoded clips, with the most recent one at the back.
+    decoded: VecDeque<(OwnedMxcUri, Arc<DecodedAudio>)>,
+    /// Waveforms computed from decoded clips, which are kept even after
+    /// the decoded clip itself has been evicted from `decoded`.
+    waveforms: HashMap<OwnedMxcUri, Arc<[f32]>>,
+    decoding: HashSet<OwnedMxcUri>,
+    failed: HashMap<OwnedMxcUri, String>,
+    /// The clip to start playing as soon as it has been decoded.
+    play_when_decoded: Option<OwnedMxcUri>,
+}
+
+static DECODER_STATE: LazyLock<Mutex<DecoderState>> = LazyLock::new(Mutex::default);
+static PLAYBACK: Mutex<Option<ActivePlayback>> = Mutex::new(None);
+static AUDIO_OUTPUT_INITIALIZED: AtomicBool = AtomicBool::new(false);
+/// The sample rate of the current audio output device, in Hz.
+static OUTPUT_SAMPLE_RATE: AtomicU32 = AtomicU32::new(DEFAULT_OUTPUT_SAMPLE_RATE);
+static SAMPLE_RATE_MEASUREMENT: Mutex<SampleRateMeasurement> = Mutex::new(SampleRateMeasurement::new());
+
+/// Measures the output device's sample rate from the number of frames
+/// that the audio output callback requests over time.
+///
+/// Makepad doesn't tell us the rate at which it opened the output device,
+/// and it differs between devices (most commonly 44.1 kHz vs. 48 kHz).
+struct SampleRateMeasurement {
+    /// When the first measured callback occurred.
+    started: Option<Instant>,
+    /// The number of frames requested since `started`, excluding the most recent callback.
+    frames: u64,
+    is_done: bool,
+}
+
+impl SampleRateMeasurement {
+    const fn new() -> Self {
+        Self { started: None, frames: 0, is_done: false }
+    }
+
+    /// Records a callback requesting `frame_count` frames,
+    /// returning the measured sample rate once enough time has passed.
+    fn record(&mut self, frame_count: usize) -> Option<u32> {
+        if self.is_done {
+            return None;
+        }
+        let now = Instant::now();
+        let Some(started) = self.started else {
+            self.started = Some(now);
+            self.frames = frame_count as u64;
+            return None;
+        };
+        let elapsed = now.duration_since(started);
+        if elapsed >= SAMPLE_RATE_MEASUREMENT_PERIOD {
+            self.is_done = true;
+            return Some(nearest_standard_sample_rate(self.frames as f64 / elapsed.as_secs_f64()));
+        }
+        self.frames += frame_count as u64;
+        None
+    }
+}
+
+/// Returns the standard sample rate closest to the given measured rate.
+fn nearest_standard_sample_rate(measured: f64) -> u32 {
+    STANDARD_SAMPLE_RATES.iter()
+        .copied()
+        .min_by(|a, b| (*a as f64 - measured).abs().total_cmp(&(*b as f64 - measured).abs()))
+        .unwrap_or(DEFAULT_OUTPUT_SAMPLE_RATE)
+}
+
+/// Selects the default audio output device and starts the audio output callback.
+///
+/// This must be called upon every `Event::AudioDevices`.
+pub fn handle_audio_devices(cx: &mut Cx, devices: &AudioDevicesEvent) {
+    cx.use_audio_outputs(&devices.default_output());
+    // The new output device may run at a different sample rate, so measure it again.
+    *SAMPLE_RATE_MEASUREMENT.lock().unwrap() = SampleRateMeasurement::new();
+    if AUDIO_OUTPUT_INITIALIZED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    cx.audio_output(0, |_info, output| {
+        output.zero();
+        if let Some(sample_rate) = SAMPLE_RATE_MEASUREMENT.try_lock().ok()
+            .and_then(|mut measurement| measurement.record(output.frame_count()))
+        {
+            log!("Measured the audio output sample rate as {sample_rate} Hz");
+            OUTPUT_SAMPLE_RATE.store(sample_rate, Ordering::Relaxed);
+        }
+        // Never block the audio thread; just output silence for this buffer
+        // if the UI thread is currently modifying the playback state.
+        let Ok(mut playback) = PLAYBACK.try_lock() else { return };
+        let Some(active) = playback.as_mut().filter(|active| active.is_playing) else { return };
+
+        // Resample the clip to the output device's sample rate via linear interpolation.
+        let step = active.audio.sample_rate as f64 / OUTPUT_SAMPLE_RATE.load(Ordering::Relaxed) as f64;
+        let samples = &active.audio.samples;
+        for frame in 0..output.frame_count() {
+            let index = active.position as usize;
+            if index + 1 >= samples.len() {
+                // Rewind to the start once the end of the clip has been reached.
+                active.is_playing = false;
+                active.position = 0.0;
+                break;
+            }
+            let fraction = (active.position - index as f64) as f32;
+            let sample = samples[index] + (samples[index + 1] - samples[index]) * fraction;
+            for channel in 0..output.channel_count() {
+                output.channel_mut(channel)[frame] = sample;
+            }
+            active.position += step;
+        }
+    });
+}
+
+/// Decodes the given audio file on a background thread, unless it has already been decoded.
+///
+/// If `play` is `true`, the clip will be played once it is decoded.
+pub fn decode(cx: &mut Cx, mxc_uri: &OwnedMxcUri, data: Arc<[u8]>, mimetype: Option<String>, play: bool) {
+    let mut state = DECODER_STATE.lock().unwrap();
+    if let Some(audio) = decoded_audio(&mut state, mxc_uri) {
+        drop(state);
+        if play {
+            start_playback(mxc_uri.clone(), audio);
+        }
+        return;
+    }
+    if play {
+        state.play_when_decoded = Some(mxc_uri.clone());
+    }
+    if state.failed.contains_key(mxc_uri) || !state.decoding.insert(mxc_uri.clone()) {
+        return;
+    }
+    drop(state);
+    cpu_worker::spawn_cpu_job(cx, CpuJob::DecodeAudio(DecodeAudioJob {
+        mxc_uri: mxc_uri.clone(),
+        data,
+        mimetype,
+    }));
+}
+
+/// Returns the given clip if it has been decoded, marking it as the most recently used clip.
+fn decoded_audio(state: &mut DecoderState, mxc_uri: &OwnedMxcUri) -> Option<Arc<DecodedAudio>> {
+    let index = state.decoded.iter().position(|(uri, _)| uri == mxc_uri)?;
+    let entry = state.decoded.remove(index)?;
+    let audio = Arc::clone(&entry.1);
+    state.decoded.push_back(entry);
+    Some(audio)
+}
+
+/// Returns whether the given clip is currently being decoded.
+pub fn is_decoding(mxc_uri: &OwnedMxcUri) -> bool {
+    DECODER_STATE.lock().unwrap().decoding.contains(mxc_uri)
+}
+
+/// Returns the error that occurred while decoding the given clip, if any.
+pub fn decode_error(mxc_uri: &OwnedMxcUri) -> Option<String> {
+    DECODER_STATE.lock().unwrap().failed.get(mxc_uri).cloned()
+}
+
+/// Returns the waveform computed from the given clip, if it has been decoded.
+pub fn computed_waveform(mxc_uri: &OwnedMxcUri) -> Option<Arc<[f32]>> {
+    DECODER_STATE.lock().unwrap().waveforms.get(mxc_uri).cloned()
+}
+
+/// Resumes playing the given clip if it is still decoded, starting it from the beginning
+/// if it isn't the current clip.
+///
+/// Returns `false` if the clip must be (re-)decoded before it can be played.
+pub fn play(mxc_uri: &OwnedMxcUri) -> bool {
+    {
+        let mut playback = PLAYBACK.lock().unwrap();
+        if let Some(active) = playback.as_mut().filter(|active| &active.mxc_uri == mxc_uri) {
+            active.is_playing = true;
+            return true;
+        }
+    }
+    let audio = decoded_audio(&mut DECODER_STATE.lock().unwrap(), mxc_uri);
+    match audio {
+        Some(audio) => {
+            start_playback(mxc_uri.clone(), audio);
+            true
+        }
+        None => false,
+    }
+}
+
+fn start_playback(mxc_uri: OwnedMxcUri, audio: Arc<DecodedAudio>) {
+    log!("Playing audio clip {mxc_uri} ({:.1} sec)", audio.duration().as_secs_f64());
+    *PLAYBACK.lock().unwrap() = Some(ActivePlayback {
+        mxc_uri,
+        audio,
+        position: 0.0,
+        is_playing: true,
+    });
+}
+
+/// Pauses the given clip, if it is currently playing.
+pub fn pause(mxc_uri: &OwnedMxcUri) {
+    if let Some(active) = PLAYBACK.lock().unwrap().as_mut().filter(|active| &active.mxc_uri == mxc_uri) {
+        active.is_playing = false;
+    }
+}
+
+/// Seeks to the given fraction (from `0.0` to `1.0`) of the given clip,
+/// if it is the current clip.
+pub fn seek(mxc_uri: &OwnedMxcUri, fraction: f64) {
+    if let Some(active) = PLAYBACK.lock().unwrap().as_mut().filter(|active| &active.mxc_uri == mxc_uri) {
+        active.position = fraction.clamp(0.0, 1.0) * active.audio.samples.len() as f64;
+    }
+}
+
+/// Stops and unloads the current clip, e.g., upon logout.
+pub fn stop() {
+    *PLAYBACK.lock().unwrap() = None;
+}
+
+/// Returns the playback status of the given clip, if it is the current clip.
+pub fn playback_status(mxc_uri: &OwnedMxcUri) -> Option<PlaybackStatus> {
+    let playback = PLAYBACK.lock().unwrap();
+    let active = playback.as_ref().filter(|active| &active.mxc_uri == mxc_uri)?;
+    let sample_rate = active.audio.sample_rate.max(1) as f64;
+    Some(PlaybackStatus {
+        position: Duration::from_secs_f64(active.position / sample_rate),
+        duration: active.audio.duration(),
+        is_playing: active.is_playing,
+    })
+}
+
+
+/// A job that decodes an audio clip and computes its waveform.
+pub struct DecodeAudioJob {
+    pub mxc_uri: OwnedMxcUri,
+    pub data: Arc<[u8]>,
+    pub mimetype: Option<String>,
+}
+
+/// Runs the given [`DecodeAudioJob`]; this should be called on a background thread.
+pub fn run_decode_audio_job(job: DecodeAudioJob) {
+    let DecodeAudioJob { mxc_uri, data, mimetype } = job;
+    let result = decode_audio(data, mimetype.as_deref());
+
+    let mut state = DECODER_STATE.lock().unwrap();
+    state.decoding.remove(&mxc_uri);
+    let play = state.play_when_decoded.as_ref() == Some(&mxc_uri);
+    if play {
+        state.play_when_decoded = None;
+    }
+    match result {
+        Ok(audio) => {
+            let audio = Arc::new(audio);
+            state.waveforms.insert(mxc_uri.clone(), compute_waveform(&audio.samples, WAVEFORM_BARS).into());
+            state.decoded.push_back((mxc_uri.clone(), Arc::clone(&audio)));
+            while state.decoded.len() > MAX_DECODED_CLIPS {
+                state.decoded.pop_front();
+            }
+            drop(state);
+            if play {
+                start_playback(mxc_uri.clone(), audio);
+            }
+        }
+        Err(e) => {
+            error!("Failed to decode audio clip {mxc_uri}: {e}");
+            state.failed.insert(mxc_uri.clone(), e);
+        }
+    }
+    Cx::post_action(AudioDecodedAction { mxc_uri });
+}
+
+/// Decodes the given audio file into mono PCM samples.
+fn decode_audio(data: Arc<[u8]>, mimetype: Option<&str>) -> Result<DecodedAudio, String> {
+    let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+    let mut hint = Hint::new();
+    if let Some(mimetype) = mimetype {
+        hint.mime_type(mimetype);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("unsupported audio format: {e}"))?;
+    let mut format = probed.format;
+    let track = format.tracks().iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| "the file has no audio track".to_string())?;
+    let track_id = track.id;
+    if track.codec_params.codec == CODEC_TYPE_OPUS {
+        let channels = track.codec_params.channels.map_or(1, |channels| channels.count());
+        // The Ogg demuxer reports the Opus header's pre-skip as the codec delay.
+        let pre_skip = track.codec_params.delay.unwrap_or(0) as usize;
+        return decode_opus(format, track_id, channels, pre_skip);
+    }
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(DEFAULT_OUTPUT_SAMPLE_RATE);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("unsupported audio codec: {e}"))?;
+
+    let mut samples = Vec::new();
+    while let Some(packet) = next_packet_of_track(&mut *format, track_id)? {
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Skip over corrupt packets instead of failing the whole file.
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+        let spec = *decoded.spec();
+        sample_rate = spec.rate;
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(mix_to_mono(buffer.samples(), channels));
+    }
+
+    if samples.is_empty() {
+        return Err("the file contains no audio samples".into());
+    }
+    Ok(DecodedAudio { samples, sample_rate })
+}
+
+/// Decodes the Opus packets of the given track into mono PCM samples using libopus.
+///
+/// The first `pre_skip` samples are the decoder's priming samples, which are discarded.
+fn decode_opus(
+    mut format: Box<dyn FormatReader>,
+    track_id: u32,
+    channels: usize,
+    pre_skip: usize,
+) -> Result<DecodedAudio, String> {
+    let opus_channels = match channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        _ => return Err(format!("Opus audio with {channels} channels is not supported")),
+    };
+    let mut decoder = OpusDecoder::new(SampleRate::Hz48000, opus_channels)
+        .map_err(|e| format!("failed to create Opus decoder: {e}"))?;
+    let max_frames_per_packet = (OPUS_SAMPLE_RATE as u128 * MAX_OPUS_PACKET_DURATION.as_millis() / 1000) as usize;
+    let mut buffer = vec![0.0f32; max_frames_per_packet * channels];
+
+    let mut samples = Vec::new();
+    while let Some(packet) = next_packet_of_track(&mut *format, track_id)? {
+        // Skip over empty and corrupt packets instead of failing the whole file.
+        let Ok(input) = OpusPacket::try_from(&packet.data[..]) else { continue };
+        let output = MutSignals::try_from(&mut buffer[..]).map_err(|e| e.to_string())?;
+        let Ok(frame_count) = decoder.decode_float(Some(input), output, false) else { continue };
+        samples.extend(mix_to_mono(&buffer[.. frame_count * channels], channels));
+    }
+    samples.drain(.. pre_skip.min(samples.len()));
+
+    if samples.is_empty() {
+        return Err("the file contains no audio samples".into());
+    }
+    Ok(DecodedAudio { samples, sample_rate: OPUS_SAMPLE_RATE })
+}
+
+/// Returns the next packet of the given track, or `None` at the end of the file.
+fn next_packet_of_track(format: &mut dyn FormatReader, track_id: u32) -> Result<Option<Packet>, String> {
+    loop {
+        match format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => return Ok(Some(packet)),
+            Ok(_) => continue,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Mixes the given interleaved samples with `channels` channels down to mono.
+fn mix_to_mono(interleaved: &[f32], channels: usize) -> impl Iterator<Item = f32> + '_ {
+    interleaved.chunks(channels).map(move |frame| frame.iter().sum::<f32>() / channels as f32)
+}
+
+/// Summarizes the given samples into `num_bars` peak amplitudes,
+/// normalized such that the loudest bar has an amplitude of `1.0`.
+pub fn compute_waveform(samples: &[f32], num_bars: usize) -> Vec<f32> {
+    if samples.is_empty() || num_bars == 0 {
+        return Vec::new();
+    }
+    let chunk_len = samples.len().div_ceil(num_bars);
+    let peaks: Vec<f32> = samples.chunks(chunk_len)
+        .map(|chunk| chunk.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs())))
+        .collect();
+    normalize(peaks)
+}
+
+/// Resamples the given waveform (e.g., from an event's MSC3245 metadata)
+/// to `num_bars` amplitudes, normalized such that the loudest bar has an amplitude of `1.0`.
+pub fn resample_waveform(amplitudes: &[f32], num_bars: usize) -> Vec<f32> {
+    if amplitudes.is_empty() || num_bars == 0 {
+        return Vec::new();
+    }
+    let resampled = (0..num_bars)
+        .map(|bar| {
+            let start = bar * amplitudes.len() / num_bars;
+            let end = ((bar + 1) * amplitudes.len() / num_bars).max(start + 1);
+            amplitudes[start..end].iter().copied().fold(0.0f32, f32::max)
+        })
+        .collect();
+    normalize(resampled)
+}
+
+fn normalize(mut amplitudes: Vec<f32>) -> Vec<f32> {
+    let max = amplitudes.iter().copied().fold(0.0f32, f32::max);
+    if max > 0.0 {
+        amplitudes.iter_mut().for_each(|amplitude| *amplitude /= max);
+    }
+    amplitudes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_waveform_takes_normalized_peak_of_each_chunk() {
+        let samples = [0.1, -0.2, 0.4, 0.0, -0.8, 0.2];
+        assert_eq!(compute_waveform(&samples, 3), vec![0.25, 0.5, 1.0]);
+        assert!(compute_waveform(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn measured_sample_rate_snaps_to_standard_rate() {
+        assert_eq!(nearest_standard_sample_rate(44_530.0), 44_100);
+        assert_eq!(nearest_standard_sample_rate(47_200.0), 48_000);
+        assert_eq!(nearest_standard_sample_rate(0.0), 8_000);
+    }
+
+    #[test]
+    fn decodes_ogg_opus_voice_message() {
+        // Half a second of a 440 Hz tone, encoded by libopus as a mono Ogg/Opus file.
+        let data: Arc<[u8]> = include_bytes!("../tests/fixtures/voice_message.ogg").as_slice().into();
+        let audio = decode_audio(data, Some("audio/ogg")).unwrap();
+        assert_eq!(audio.sample_rate, OPUS_SAMPLE_RATE);
+        // 25 packets of 20 ms each, minus the 312 samples of pre-skip.
+        assert_eq!(audio.samples.len(), 25 * 960 - 312);
+        let peak = audio.samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((0.3 .. 0.7).contains(&peak), "unexpected peak amplitude {peak}");
+    }
+
+    #[test]
+    fn resample_waveform_handles_fewer_and_more_bars() {
+        assert_eq!(resample_waveform(&[1.0, 3.0, 2.0, 4.0], 2), vec![0.75, 1.0]);
+        assert_eq!(resample_waveform(&[2.0, 4.0], 4), vec![0.5, 0.5, 1.0, 1.0]);
+    }
+}
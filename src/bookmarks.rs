@@ -0,0 +1,315 @@
+//! Bookmarked ("saved") messages from any room.
+//!
+//! Bookmarks are stored in the current user's global account data under the custom
+//! [`BOOKMARKS_EVENT_TYPE`] event type, such that they are synced across all of their devices.
+//! The latest known bookmarks are cached here so that the UI can check
+//! whether a message is bookmarked without waiting on the homeserver.
+//!
+//! Only the IDs of bookmarked messages are stored in the account data,
+//! which is not end-to-end encrypted; their content is fetched locally when needed.
+
+use std::sync::Mutex;
+
+use makepad_widgets::{Cx, error, log};
+use matrix_sdk::{
+    Client,
+    ruma::{
+        EventId, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId,
+        events::{AnyGlobalAccountDataEvent, AnyGlobalAccountDataEventContent, GlobalAccountDataEventType},
+        serde::Raw,
+    },
+};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+
+use crate::{
+    event_preview::{BeforeText, TextPreview, text_preview_of_raw_timeline_event, text_preview_of_timeline_item},
+    sliding_sync::{fetch_global_account_data, get_room_timeline},
+    utils::{self, RoomNameId},
+};
+
+/// The event type of the global account data that holds the current user's bookmarks.
+pub const BOOKMARKS_EVENT_TYPE: &str = "org.robius.robrix.bookmarks";
+
+/// A single bookmarked message.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub room_id: OwnedRoomId,
+    pub event_id: OwnedEventId,
+    /// The root event of the thread that the message was bookmarked in, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_root_event_id: Option<OwnedEventId>,
+    /// When this message was bookmarked.
+    pub saved_ts: MilliSecondsSinceUnixEpoch,
+}
+
+/// The content of the [`BOOKMARKS_EVENT_TYPE`] account data.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct BookmarksEventContent {
+    /// All bookmarks, in the order they were added.
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+}
+
+/// A bookmarked message along with the details needed to display it in the list of saved messages.
+#[derive(Clone, Debug)]
+pub struct BookmarkPreview {
+    pub bookmark: Bookmark,
+    pub room_name_id: RoomNameId,
+    /// When the bookmarked message was sent, if it could be fetched.
+    pub timestamp: Option<MilliSecondsSinceUnixEpoch>,
+    /// A plaintext preview of the message, prefixed by the sender's name.
+    pub preview: String,
+}
+
+/// Actions related to bookmarks that are posted to the UI thread.
+#[derive(Clone, Debug)]
+pub enum BookmarksAction {
+    /// The set of bookmarks has changed, either locally or on another device.
+    Updated,
+    /// The user successfully bookmarked (`true`) or un-bookmarked (`false`) a message.
+    Changed { bookmarked: bool },
+    /// The previews of all bookmarks were fetched, ordered from newest to oldest bookmark.
+    PreviewsLoaded(Vec<BookmarkPreview>),
+    /// Bookmarking or un-bookmarking a message failed.
+    Failed { error: String },
+}
+
+/// The latest known bookmarks of the current user.
+static BOOKMARKS: Mutex<Vec<Bookmark>> = Mutex::new(Vec::new());
+
+/// Returns whether the given message is bookmarked.
+pub fn is_bookmarked(room_id: &RoomId, event_id: &EventId) -> bool {
+    BOOKMARKS.lock().unwrap()
+        .iter()
+        .any(|b| b.room_id == room_id && b.event_id == event_id)
+}
+
+/// Returns all bookmarks, ordered from newest to oldest.
+pub fn all_bookmarks() -> Vec<Bookmark> {
+    BOOKMARKS.lock().unwrap().iter().rev().cloned().collect()
+}
+
+fn set_cached_bookmarks(bookmarks: Vec<Bookmark>) {
+    let mut cached = BOOKMARKS.lock().unwrap();
+    if *cached != bookmarks {
+        *cached = bookmarks;
+        Cx::post_action(BookmarksAction::Updated);
+    }
+}
+
+/// Adds the given bookmark (if `bookmarked` is true) or removes it (if false).
+///
+/// Returns `true` if the list of bookmarks was changed.
+fn apply_bookmark_change(bookmarks: &mut Vec<Bookmark>, bookmark: Bookmark, bookmarked: bool) -> bool {
+    let existing = bookmarks.iter()
+        .position(|b| b.room_id == bookmark.room_id && b.event_id == bookmark.event_id);
+    match (existing, bookmarked) {
+        (None, true) => {
+            bookmarks.push(bookmark);
+            true
+        }
+        (Some(index), false) => {
+            bookmarks.remove(index);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Loads the current user's bookmarks and keeps them up to date
+/// with changes made on other devices.
+pub fn handle_bookmarks_account_data(client: &Client) {
+    // Forget the bookmarks of any previously logged-in account.
+    BOOKMARKS.lock().unwrap().clear();
+
+    let initial_client = client.clone();
+    Handle::current().spawn(async move {
+        if let Some(content) = fetch_global_account_data::<BookmarksEventContent>(
+            &initial_client,
+            BOOKMARKS_EVENT_TYPE,
+        ).await {
+            log!("Loaded {} bookmarks.", content.bookmarks.len());
+            set_cached_bookmarks(content.bookmarks);
+        }
+    });
+
+    client.add_event_handler(|ev: Raw<AnyGlobalAccountDataEvent>| async move {
+        if ev.get_field::<String>("type").ok().flatten().as_deref() != Some(BOOKMARKS_EVENT_TYPE) {
+            return;
+        }
+        match ev.get_field::<BookmarksEventContent>("content") {
+            Ok(Some(content)) => set_cached_bookmarks(content.bookmarks),
+            Ok(None) => { }
+            Err(e) => error!("Failed to deserialize {BOOKMARKS_EVENT_TYPE} account data: {e:?}"),
+        }
+    });
+}
+
+/// Bookmarks (if `bookmarked` is true) or un-bookmarks the given message,
+/// saving the updated bookmarks to the current user's account data.
+pub async fn set_bookmarked(client: Client, bookmark: Bookmark, bookmarked: bool) {
+    // Start from the latest bookmarks on the homeserver, such that a change
+    // made on another device since our last sync is not overwritten.
+    let mut bookmarks = fetch_global_account_data::<BookmarksEventContent>(&client, BOOKMARKS_EVENT_TYPE)
+        .await
+        .map(|content| content.bookmarks)
+        .unwrap_or_else(|| BOOKMARKS.lock().unwrap().clone());
+    if !apply_bookmark_change(&mut bookmarks, bookmark, bookmarked) {
+        set_cached_bookmarks(bookmarks);
+        Cx::post_action(BookmarksAction::Changed { bookmarked });
+        return;
+    }
+
+    let content = BookmarksEventContent { bookmarks };
+    let raw_content = match serde_json::value::to_raw_value(&content) {
+        Ok(json) => Raw::<AnyGlobalAccountDataEventContent>::from_json(json),
+        Err(e) => {
+            error!("Failed to serialize bookmarks: {e:?}");
+            Cx::post_action(BookmarksAction::Failed { error: e.to_string() });
+            return;
+        }
+    };
+    match client.account()
+        .set_account_data_raw(GlobalAccountDataEventType::from(BOOKMARKS_EVENT_TYPE), raw_content)
+        .await
+    {
+        Ok(_) => {
+            set_cached_bookmarks(content.bookmarks);
+            Cx::post_action(BookmarksAction::Changed { bookmarked });
+        }
+        Err(e) => {
+            error!("Failed to save bookmarks to account data: {e:?}");
+            Cx::post_action(BookmarksAction::Failed { error: e.to_string() });
+        }
+    }
+}
+
+/// Fetches the previews of all bookmarked messages, posting them as
+/// a [`BookmarksAction::PreviewsLoaded`] action.
+pub async fn fetch_bookmark_previews(client: Client) {
+    let bookmarks = all_bookmarks();
+    let mut previews = Vec::with_capacity(bookmarks.len());
+    for bookmark in bookmarks {
+        previews.push(fetch_bookmark_preview(&client, bookmark).await);
+    }
+    Cx::post_action(BookmarksAction::PreviewsLoaded(previews));
+}
+
+/// Fetches the preview of the given bookmarked message.
+///
+/// The message is taken from its room's timeline if it has already been loaded there,
+/// otherwise it is fetched from the local event cache or the homeserver.
+async fn fetch_bookmark_preview(client: &Client, bookmark: Bookmark) -> BookmarkPreview {
+    let Some(room) = client.get_room(&bookmark.room_id) else {
+        return BookmarkPreview {
+            room_name_id: RoomNameId::empty(bookmark.room_id.clone()),
+            bookmark,
+            timestamp: None,
+            preview: String::new(),
+        };
+    };
+    let room_name_id = RoomNameId::from_room(&room).await;
+
+    if let Some(timeline) = get_room_timeline(&bookmark.room_id)
+        && let Some(event_tl_item) = timeline.item_by_event_id(&bookmark.event_id).await
+    {
+        let sender_username = utils::get_or_fetch_event_sender(&event_tl_item, None);
+        let preview = text_preview_of_timeline_item(
+            event_tl_item.content(),
+            event_tl_item.sender(),
+            &sender_username,
+        ).format_with(&sender_username, false);
+        return BookmarkPreview {
+            bookmark,
+            room_name_id,
+            timestamp: Some(event_tl_item.timestamp()),
+            preview: utils::replace_linebreaks_separators(&preview, false).into_owned(),
+        };
+    }
+
+    let (timestamp, preview) = match room.load_or_fetch_event(&bookmark.event_id, None).await {
+        Ok(event) => {
+            let raw_event = event.raw();
+            let sender = raw_event.get_field::<OwnedUserId>("sender").ok().flatten();
+            let sender_name = match sender.as_ref() {
+                Some(sender) => room.get_member_no_sync(sender).await.ok().flatten()
+                    .and_then(|rm| rm.display_name().map(ToOwned::to_owned))
+                    .unwrap_or_else(|| sender.to_string()),
+                None => String::new(),
+            };
+            let preview = text_preview_of_raw_timeline_event(raw_event, &sender_name)
+                .unwrap_or_else(|| {
+                    let event_type = raw_event.get_field::<String>("type").ok().flatten();
+                    TextPreview::from((
+                        event_type.unwrap_or_else(|| "unknown event type".to_string()),
+                        BeforeText::UsernameWithColon,
+                    ))
+                })
+                .format_with(&sender_name, false);
+            (
+                raw_event.get_field::<MilliSecondsSinceUnixEpoch>("origin_server_ts").ok().flatten(),
+                utils::replace_linebreaks_separators(&preview, false).into_owned(),
+            )
+        }
+        Err(e) => {
+            error!("Failed to fetch bookmarked event {} in room {}: {e:?}", bookmark.event_id, bookmark.room_id);
+            (None, String::new())
+        }
+    };
+    BookmarkPreview { bookmark, room_name_id, timestamp, preview }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use matrix_sdk::ruma::{owned_event_id, owned_room_id, uint};
+
+    use super::*;
+
+    fn bookmark(event_id: OwnedEventId) -> Bookmark {
+        Bookmark {
+            room_id: owned_room_id!("!room:example.org"),
+            event_id,
+            thread_root_event_id: None,
+            saved_ts: MilliSecondsSinceUnixEpoch(uint!(1_700_000_000_000)),
+        }
+    }
+
+    #[test]
+    fn apply_bookmark_change_adds_and_removes_once() {
+        let mut bookmarks = Vec::new();
+        let first = bookmark(owned_event_id!("$first:example.org"));
+        let second = bookmark(owned_event_id!("$second:example.org"));
+
+        assert!(apply_bookmark_change(&mut bookmarks, first.clone(), true));
+        assert!(apply_bookmark_change(&mut bookmarks, second.clone(), true));
+        // Bookmarking an already-bookmarked message does nothing.
+        assert!(!apply_bookmark_change(&mut bookmarks, first.clone(), true));
+        assert_eq!(bookmarks, vec![first.clone(), second.clone()]);
+
+        assert!(apply_bookmark_change(&mut bookmarks, first.clone(), false));
+        assert!(!apply_bookmark_change(&mut bookmarks, first, false));
+        assert_eq!(bookmarks, vec![second]);
+    }
+
+    #[test]
+    fn bookmarks_content_round_trips_through_json() {
+        let mut thread_bookmark = bookmark(owned_event_id!("$reply:example.org"));
+        thread_bookmark.thread_root_event_id = Some(owned_event_id!("$root:example.org"));
+        let content = BookmarksEventContent {
+            bookmarks: vec![bookmark(owned_event_id!("$first:example.org")), thread_bookmark],
+        };
+        let json = serde_json::to_value(&content).unwrap();
+        // The thread root is omitted for bookmarks outside of a thread.
+        assert!(json["bookmarks"][0].get("thread_root_event_id").is_none());
+        let parsed: BookmarksEventContent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.bookmarks, content.bookmarks);
+    }
+
+    #[test]
+    fn missing_bookmarks_field_is_empty() {
+        let parsed: BookmarksEventContent = serde_json::from_str("{}").unwrap();
+        assert!(parsed.bookmarks.is_empty());
+    }
+}
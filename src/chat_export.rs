@@ -0,0 +1,494 @@
+//! Exporting a room's full history to a file, e.g., to archive a room for compliance purposes.
+//!
+//! An export builds its own timeline for the room and paginates it backwards
+//! all the way to the start of the room, such that the timeline shown in a `RoomScreen`
+//! is left untouched. That timeline decrypts events just like any other one,
+//! so messages in encrypted rooms are exported in plaintext.
+//!
+//! A room can be exported as a self-contained HTML file, a JSON Lines file
+//! with one message per line, or a plain-text transcript (see [`ChatExportFormat`]).
+//! Attachments can optionally be downloaded into a directory next to the exported file.
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{Arc, atomic::{AtomicBool, Ordering}},
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local};
+use makepad_widgets::{Cx, error, log};
+use matrix_sdk::{
+    Client,
+    media::{MediaFormat, MediaRequestParameters},
+    ruma::{
+        MilliSecondsSinceUnixEpoch, OwnedRoomId,
+        events::room::{MediaSource, message::MessageType},
+    },
+};
+use matrix_sdk_ui::timeline::{EventTimelineItem, MsgLikeKind, RoomExt, TimelineFocus, TimelineItemContent};
+use serde::Serialize;
+
+use crate::{app_data_dir, event_preview::plaintext_body_of_timeline_item, sliding_sync::download_mxc_file, utils};
+
+/// The name of the directory (within the app data directory) that exported rooms are saved to.
+const EXPORTS_DIR_NAME: &str = "exports";
+/// The number of events requested from the homeserver in each backwards pagination.
+const EXPORT_PAGINATION_BATCH_SIZE: u16 = 100;
+/// The maximum length of a room name or attachment name when used as part of a file name.
+const MAX_FILE_NAME_LEN: usize = 64;
+
+/// The file formats that a room's history can be exported to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChatExportFormat {
+    /// A single HTML file with inline styles, which can be opened in any web browser.
+    #[default]
+    Html,
+    /// A JSON Lines file, in which each line is one [`ExportedMessage`].
+    JsonLines,
+    /// A plain-text transcript with one message per line.
+    PlainText,
+}
+
+impl ChatExportFormat {
+    /// Returns the file extension used for exports in this format.
+    pub fn file_extension(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::JsonLines => "jsonl",
+            Self::PlainText => "txt",
+        }
+    }
+}
+
+/// The options chosen by the user for exporting a room's history.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChatExportOptions {
+    pub format: ChatExportFormat,
+    /// Whether to download each message's attachment (image, file, video or audio)
+    /// into a directory next to the exported file.
+    pub include_attachments: bool,
+}
+
+/// Actions emitted by a room export running in the background.
+#[derive(Clone, Debug)]
+pub enum ChatExportAction {
+    /// The export has loaded the given number of events from the room's history so far.
+    Progress {
+        room_id: OwnedRoomId,
+        num_events: usize,
+    },
+    /// The export completed successfully and was saved to the given file.
+    Finished {
+        room_id: OwnedRoomId,
+        path: PathBuf,
+    },
+    /// The export was cancelled by the user before it completed.
+    Cancelled {
+        room_id: OwnedRoomId,
+    },
+    /// The export failed with the given error.
+    Failed {
+        room_id: OwnedRoomId,
+        error: String,
+    },
+}
+
+/// A single event from a room's history, as it is written to an export.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event_id: Option<String>,
+    /// The user ID of the sender.
+    pub sender: String,
+    /// The display name of the sender at the time this message was sent, if known.
+    pub sender_name: String,
+    pub timestamp: MilliSecondsSinceUnixEpoch,
+    /// The plaintext body of this message, or a description of a non-message event.
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<ExportedAttachment>,
+}
+
+/// An attachment of an [`ExportedMessage`].
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedAttachment {
+    /// The attachment's original file name.
+    pub name: String,
+    /// The path of the downloaded attachment, relative to the exported file,
+    /// or `None` if attachments weren't downloaded or this one failed to download.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Exports the full history of the given room to a file, posting [`ChatExportAction`]s
+/// to report its progress and result.
+///
+/// Setting the given `cancel_token` to `true` stops the export as soon as possible,
+/// without writing anything (other than already-downloaded attachments) to disk.
+pub async fn export_room_history(
+    client: Client,
+    room_id: OwnedRoomId,
+    room_name: String,
+    options: ChatExportOptions,
+    cancel_token: Arc<AtomicBool>,
+) {
+    log!("Starting export of room {room_id} ({options:?})...");
+    let action = match run_export(&client, &room_id, &room_name, options, &cancel_token).await {
+        Ok(Some(path)) => {
+            log!("Exported room {room_id} to {}", path.display());
+            ChatExportAction::Finished { room_id, path }
+        }
+        Ok(None) => {
+            log!("Export of room {room_id} was cancelled.");
+            ChatExportAction::Cancelled { room_id }
+        }
+        Err(e) => {
+            error!("Failed to export room {room_id}: {e:?}");
+            ChatExportAction::Failed { room_id, error: e.to_string() }
+        }
+    };
+    Cx::post_action(action);
+}
+
+fn is_cancelled(cancel_token: &AtomicBool) -> bool {
+    cancel_token.load(Ordering::Relaxed)
+}
+
+/// Runs the export, returning the path of the exported file,
+/// or `None` if the export was cancelled.
+async fn run_export(
+    client: &Client,
+    room_id: &OwnedRoomId,
+    room_name: &str,
+    options: ChatExportOptions,
+    cancel_token: &AtomicBool,
+) -> anyhow::Result<Option<PathBuf>> {
+    let room = client.get_room(room_id)
+        .ok_or_else(|| anyhow!("room {room_id} was not found"))?;
+    let timeline = room.timeline_builder()
+        .with_focus(TimelineFocus::Live {
+            // Unlike the RoomScreen, exports include thread replies inline.
+            hide_threaded_events: false,
+        })
+        .build()
+        .await?;
+
+    loop {
+        if is_cancelled(cancel_token) {
+            return Ok(None);
+        }
+        let reached_start = timeline.paginate_backwards(EXPORT_PAGINATION_BATCH_SIZE).await?;
+        Cx::post_action(ChatExportAction::Progress {
+            room_id: room_id.clone(),
+            num_events: timeline.items().await.len(),
+        });
+        if reached_start {
+            break;
+        }
+    }
+
+    let export_path = export_file_path(room_name, options.format, Local::now());
+    let attachments_dir = attachments_dir_path(&export_path);
+    let mut messages = Vec::new();
+    for item in timeline.items().await.iter() {
+        let Some(event) = item.as_event() else { continue };
+        if is_cancelled(cancel_token) {
+            return Ok(None);
+        }
+        let mut attachment = None;
+        if let Some((name, source)) = attachment_of(event) {
+            let path = if options.include_attachments {
+                let file_name = format!("{:05}_{}", messages.len(), sanitize_file_name(&name));
+                match save_attachment(client, &source, &attachments_dir, &file_name).await {
+                    Ok(()) => Some(relative_attachment_path(&attachments_dir, &file_name)),
+                    Err(e) => {
+                        error!("Failed to download attachment {name:?} while exporting room {room_id}: {e:?}");
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            attachment = Some(ExportedAttachment { name, path });
+        }
+        messages.push(ExportedMessage {
+            event_id: event.event_id().map(ToString::to_string),
+            sender: event.sender().to_string(),
+            sender_name: utils::get_or_fetch_event_sender(event, None),
+            timestamp: event.timestamp(),
+            body: plaintext_body_of_timeline_item(event),
+            attachment,
+        });
+    }
+
+    let content = match options.format {
+        ChatExportFormat::Html => render_html(room_name, &messages),
+        ChatExportFormat::JsonLines => render_json_lines(&messages)?,
+        ChatExportFormat::PlainText => render_plain_text(room_name, &messages),
+    };
+    if let Some(parent) = export_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(&export_path, content).await?;
+    Ok(Some(export_path))
+}
+
+/// Returns the name and media source of the given event's attachment, if it has one.
+fn attachment_of(event: &EventTimelineItem) -> Option<(String, MediaSource)> {
+    let TimelineItemContent::MsgLike(msg_like) = event.content() else { return None };
+    let MsgLikeKind::Message(message) = &msg_like.kind else { return None };
+    match message.msgtype() {
+        MessageType::Image(content) => Some((content.filename().to_owned(), content.source.clone())),
+        MessageType::File(content) => Some((content.filename().to_owned(), content.source.clone())),
+        MessageType::Video(content) => Some((content.filename().to_owned(), content.source.clone())),
+        MessageType::Audio(content) => Some((content.filename().to_owned(), content.source.clone())),
+        _ => None,
+    }
+}
+
+/// Downloads the attachment from the given media source and saves it as `dir/file_name`.
+async fn save_attachment(
+    client: &Client,
+    source: &MediaSource,
+    dir: &Path,
+    file_name: &str,
+) -> anyhow::Result<()> {
+    let data = match source {
+        MediaSource::Plain(mxc_uri) => download_mxc_file(client, mxc_uri).await?.1,
+        // Encrypted attachments must be decrypted, which only the SDK's media API can do.
+        MediaSource::Encrypted(_) => client.media()
+            .get_media_content(
+                &MediaRequestParameters { source: source.clone(), format: MediaFormat::File },
+                false,
+            )
+            .await?,
+    };
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(dir.join(file_name), data).await?;
+    Ok(())
+}
+
+/// Returns the path that an export of the given room, started at the given time, is saved to.
+fn export_file_path(room_name: &str, format: ChatExportFormat, now: DateTime<Local>) -> PathBuf {
+    app_data_dir().join(EXPORTS_DIR_NAME).join(format!(
+        "{}_{}.{}",
+        sanitize_file_name(room_name),
+        now.format("%Y-%m-%d_%H-%M-%S"),
+        format.file_extension(),
+    ))
+}
+
+/// Returns the directory that an export's attachments are saved to,
+/// which sits next to the exported file and is named after it.
+fn attachments_dir_path(export_path: &Path) -> PathBuf {
+    let stem = export_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    export_path.with_file_name(format!("{stem}_attachments"))
+}
+
+/// Returns the path of an attachment relative to the exported file, using forward slashes
+/// such that it can be used as a link in an HTML export on any platform.
+fn relative_attachment_path(attachments_dir: &Path, file_name: &str) -> String {
+    let dir_name = attachments_dir.file_name().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    format!("{dir_name}/{file_name}")
+}
+
+/// Replaces all characters that aren't safe to use in a file name on every platform.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .take(MAX_FILE_NAME_LEN)
+        .collect();
+    // Avoid hidden files and names that consist only of dots, e.g., `..`.
+    let sanitized = sanitized.trim_start_matches('.');
+    if sanitized.is_empty() {
+        String::from("room")
+    } else {
+        sanitized.to_owned()
+    }
+}
+
+/// Formats the given timestamp in the local time zone.
+fn format_timestamp(timestamp: MilliSecondsSinceUnixEpoch) -> String {
+    utils::unix_time_millis_to_datetime(timestamp)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| timestamp.get().to_string())
+}
+
+/// Returns the display name of the given message's sender, falling back to their user ID.
+fn display_name_of(message: &ExportedMessage) -> &str {
+    if message.sender_name.is_empty() { &message.sender } else { &message.sender_name }
+}
+
+/// Renders the given messages as a plain-text transcript.
+fn render_plain_text(room_name: &str, messages: &[ExportedMessage]) -> String {
+    let mut output = String::new();
+    let _ = writeln!(output, "{room_name}");
+    let _ = writeln!(output, "Exported on {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    output.push('\n');
+    for message in messages {
+        let _ = write!(output, "[{}] {}: ", format_timestamp(message.timestamp), display_name_of(message));
+        // Indent the continuation lines of multi-line messages to keep each message distinct.
+        let mut lines = message.body.lines();
+        output.push_str(lines.next().unwrap_or_default());
+        output.push('\n');
+        for line in lines {
+            let _ = writeln!(output, "    {line}");
+        }
+        if let Some(attachment) = &message.attachment {
+            let _ = writeln!(output, "    [Attachment: {}]", attachment.path.as_deref().unwrap_or(&attachment.name));
+        }
+    }
+    output
+}
+
+/// Renders the given messages as JSON Lines, with one serialized [`ExportedMessage`] per line.
+fn render_json_lines(messages: &[ExportedMessage]) -> serde_json::Result<String> {
+    let mut output = String::new();
+    for message in messages {
+        output.push_str(&serde_json::to_string(message)?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// The inline stylesheet of an HTML export, such that it doesn't depend on any other files.
+const HTML_EXPORT_STYLE: &str = "\
+body { font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; max-width: 860px; margin: 24px auto; padding: 0 16px; color: #1C274C; }
+h1 { font-size: 22px; margin-bottom: 4px; }
+.exported { color: #888; font-size: 13px; margin-bottom: 24px; }
+.message { padding: 8px 0; border-bottom: 1px solid #EEE; }
+.sender { font-weight: 600; }
+.sender-id, .time { color: #888; font-size: 12px; margin-left: 6px; }
+.body { margin-top: 4px; white-space: pre-wrap; word-wrap: break-word; }
+.attachment { margin-top: 4px; font-size: 13px; }
+.attachment img { display: block; max-width: 320px; max-height: 320px; margin-top: 4px; border-radius: 4px; }
+";
+
+/// Renders the given messages as a self-contained HTML document.
+fn render_html(room_name: &str, messages: &[ExportedMessage]) -> String {
+    let room_name = htmlize::escape_text(room_name);
+    let mut output = String::new();
+    let _ = write!(
+        output,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{room_name}</title>\n<style>\n{HTML_EXPORT_STYLE}</style>\n</head>\n<body>\n<h1>{room_name}</h1>\n<div class=\"exported\">Exported on {}</div>\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+    );
+    for message in messages {
+        let _ = write!(
+            output,
+            "<div class=\"message\">\n<div><span class=\"sender\">{}</span><span class=\"sender-id\">{}</span><span class=\"time\">{}</span></div>\n<div class=\"body\">{}</div>\n",
+            htmlize::escape_text(display_name_of(message)),
+            htmlize::escape_text(&message.sender),
+            format_timestamp(message.timestamp),
+            htmlize::escape_text(&message.body),
+        );
+        if let Some(attachment) = &message.attachment {
+            let name = htmlize::escape_text(&attachment.name);
+            match attachment.path.as_deref() {
+                Some(path) => {
+                    let path = htmlize::escape_attribute(path);
+                    let _ = write!(output, "<div class=\"attachment\"><a href=\"{path}\">{name}</a>");
+                    if is_image_file_name(&attachment.name) {
+                        let alt = htmlize::escape_attribute(&attachment.name);
+                        let _ = write!(output, "<img src=\"{path}\" alt=\"{alt}\">");
+                    }
+                    output.push_str("</div>\n");
+                }
+                None => {
+                    let _ = writeln!(output, "<div class=\"attachment\">[Attachment: {name}]</div>");
+                }
+            }
+        }
+        output.push_str("</div>\n");
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+/// Returns whether the given file name has the extension of an image that browsers can show.
+fn is_image_file_name(name: &str) -> bool {
+    let Some((_, extension)) = name.rsplit_once('.') else { return false };
+    matches!(
+        extension.to_ascii_lowercase().as_str(),
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "svg"
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use matrix_sdk::ruma::uint;
+
+    use super::*;
+
+    fn message(sender_name: &str, body: &str, attachment: Option<ExportedAttachment>) -> ExportedMessage {
+        ExportedMessage {
+            event_id: Some(String::from("$event:example.org")),
+            sender: String::from("@alice:example.org"),
+            sender_name: sender_name.to_owned(),
+            timestamp: MilliSecondsSinceUnixEpoch(uint!(1_700_000_000_000)),
+            body: body.to_owned(),
+            attachment,
+        }
+    }
+
+    #[test]
+    fn sanitize_file_name_replaces_unsafe_characters() {
+        assert_eq!(sanitize_file_name("Project / Room: #1"), "Project___Room___1");
+        assert_eq!(sanitize_file_name("../secret"), "_secret");
+        assert_eq!(sanitize_file_name("..."), "room");
+        assert_eq!(sanitize_file_name("项目 讨论"), "项目_讨论");
+        assert_eq!(sanitize_file_name(&"a".repeat(100)).len(), MAX_FILE_NAME_LEN);
+    }
+
+    #[test]
+    fn attachments_are_saved_next_to_the_export() {
+        let export_path = Path::new("/tmp/exports/Room_2024-01-01_00-00-00.html");
+        let attachments_dir = attachments_dir_path(export_path);
+        assert_eq!(attachments_dir, Path::new("/tmp/exports/Room_2024-01-01_00-00-00_attachments"));
+        assert_eq!(
+            relative_attachment_path(&attachments_dir, "00001_cat.png"),
+            "Room_2024-01-01_00-00-00_attachments/00001_cat.png",
+        );
+    }
+
+    #[test]
+    fn plain_text_indents_multiline_messages() {
+        let messages = [
+            message("Alice", "first line\nsecond line", None),
+            message("", "hi", Some(ExportedAttachment { name: String::from("cat.png"), path: None })),
+        ];
+        let output = render_plain_text("Room", &messages);
+        assert!(output.contains("Alice: first line\n    second line\n"));
+        assert!(output.contains("@alice:example.org: hi\n    [Attachment: cat.png]\n"));
+    }
+
+    #[test]
+    fn json_lines_has_one_message_per_line() {
+        let messages = [message("Alice", "a\nb", None), message("Alice", "c", None)];
+        let output = render_json_lines(&messages).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["body"], "a\nb");
+        assert_eq!(first["sender"], "@alice:example.org");
+        assert!(first.get("attachment").is_none());
+    }
+
+    #[test]
+    fn html_escapes_message_content() {
+        let messages = [message(
+            "<b>Alice</b>",
+            "<script>alert(1)</script>",
+            Some(ExportedAttachment { name: String::from("cat.png"), path: Some(String::from("dir/00000_cat.png")) }),
+        )];
+        let output = render_html("Room & Co", &messages);
+        assert!(output.contains("<title>Room &amp; Co</title>"));
+        assert!(output.contains("&lt;script&gt;"));
+        assert!(!output.contains("<script>"));
+        assert!(output.contains("&lt;b&gt;Alice&lt;/b&gt;"));
+        assert!(output.contains("<img src=\"dir/00000_cat.png\""));
+    }
+}
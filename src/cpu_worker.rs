@@ -7,6 +7,7 @@
 use makepad_widgets::{Cx, CxOsApi};
 use std::sync::{atomic::AtomicBool, mpsc::Sender, Arc};
 use crate::{
+    audio_playback::{self, DecodeAudioJob},
     room::member_search::{self, search_room_members_streaming_with_sort, PrecomputedMemberSort},
     shared::mentionable_text_input::SearchResult,
     sliding_sync::TimelineKind,
@@ -16,6 +17,7 @@ use matrix_sdk::room::RoomMember;
 pub enum CpuJob {
     SearchRoomMembers(SearchRoomMembersJob),
     PrecomputeMemberSort(PrecomputeMemberSortJob),
+    DecodeAudio(DecodeAudioJob),
 }
 
 /// Action posted back to UI thread when precomputed sort is ready.
@@ -79,5 +81,6 @@ pub fn spawn_cpu_job(cx: &mut Cx, job: CpuJob) {
     cx.spawn_thread(move || match job {
         CpuJob::SearchRoomMembers(params) => run_member_search(params),
         CpuJob::PrecomputeMemberSort(params) => run_precompute_sort(params),
+        CpuJob::DecodeAudio(params) => audio_playback::run_decode_audio_job(params),
     });
 }
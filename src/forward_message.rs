@@ -0,0 +1,222 @@
+//! Forwarding a message to one or more other rooms.
+//!
+//! A forwarded message is re-sent as a new message with the same content as the original,
+//! via a regular [`MatrixRequest::SendMessage`] to each target room.
+//!
+//! Unencrypted media is forwarded by simply reusing its `mxc://` URI.
+//! Encrypted media, however, can only be decrypted with keys that belong to the original event,
+//! so it is downloaded and decrypted once, and then re-uploaded separately for each target room:
+//! encrypted again for encrypted rooms, and in plaintext for unencrypted rooms.
+
+use std::io::Cursor;
+
+use makepad_widgets::{Cx, error, log};
+use matrix_sdk::{
+    Client,
+    media::{MediaFormat, MediaRequestParameters},
+    ruma::{
+        OwnedRoomId, RoomId,
+        events::room::{MediaSource, message::{MessageType, RoomMessageEventContent}},
+    },
+};
+
+use crate::sliding_sync::{MatrixRequest, TimelineKind, submit_async_request};
+
+/// The result of forwarding a message, which is posted to the UI thread.
+#[derive(Clone, Debug)]
+pub enum ForwardMessageAction {
+    /// The forwarded message was submitted for sending to `num_sent` rooms.
+    ///
+    /// It could not be forwarded to `num_failed` rooms, because re-uploading its media failed.
+    Sent {
+        num_sent: usize,
+        num_failed: usize,
+    },
+    /// The message could not be forwarded to any room,
+    /// e.g., because its encrypted media could not be downloaded.
+    Failed {
+        error: String,
+    },
+}
+
+/// Returns the content of a message that forwards the given message type.
+///
+/// Only the message type itself is kept, such that the forwarded message is not
+/// a reply to (or an edit of) an event in the original room.
+pub fn forwarded_content(msgtype: &MessageType) -> RoomMessageEventContent {
+    RoomMessageEventContent::new(msgtype.clone())
+}
+
+/// Forwards the given message content to each of the given rooms.
+///
+/// The result is posted as a [`ForwardMessageAction`].
+pub async fn forward_message(
+    client: Client,
+    content: RoomMessageEventContent,
+    target_room_ids: Vec<OwnedRoomId>,
+) {
+    // Download and decrypt encrypted media only once, no matter how many rooms it is forwarded to.
+    let encrypted_media = match media_of(&content.msgtype) {
+        Some((source @ MediaSource::Encrypted(_), mimetype)) => {
+            let mime_type = mimetype
+                .and_then(|m| m.parse().ok())
+                .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+            let request = MediaRequestParameters { source: source.clone(), format: MediaFormat::File };
+            match client.media().get_media_content(&request, true).await {
+                Ok(data) => Some((mime_type, data)),
+                Err(e) => {
+                    error!("Failed to download encrypted media of forwarded message: {e:?}");
+                    Cx::post_action(ForwardMessageAction::Failed { error: e.to_string() });
+                    return;
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut num_failed = 0;
+    for room_id in &target_room_ids {
+        let mut message = content.clone();
+        if let Some((mime_type, data)) = encrypted_media.as_ref() {
+            match reupload_media(&client, room_id, mime_type, data.clone()).await {
+                Ok(new_source) => replace_media_source(&mut message.msgtype, new_source),
+                Err(e) => {
+                    error!("Failed to re-upload media of forwarded message to room {room_id}: {e:?}");
+                    num_failed += 1;
+                    continue;
+                }
+            }
+        }
+        log!("Forwarding message to room {room_id}...");
+        submit_async_request(MatrixRequest::SendMessage {
+            timeline_kind: TimelineKind::MainRoom { room_id: room_id.clone() },
+            message,
+            replied_to: None,
+            target_user_id: None,
+            explicit_room: false,
+            #[cfg(feature = "tsp")]
+            sign_with_tsp: false,
+        });
+    }
+
+    Cx::post_action(ForwardMessageAction::Sent {
+        num_sent: target_room_ids.len() - num_failed,
+        num_failed,
+    });
+}
+
+/// Uploads the given media such that it can be sent to the given room.
+///
+/// The media is encrypted unless the room is known to be unencrypted.
+async fn reupload_media(
+    client: &Client,
+    room_id: &RoomId,
+    mime_type: &mime::Mime,
+    data: Vec<u8>,
+) -> anyhow::Result<MediaSource> {
+    let is_unencrypted = client.get_room(room_id).is_some_and(|room| {
+        let encryption_state = room.encryption_state();
+        !encryption_state.is_unknown() && !encryption_state.is_encrypted()
+    });
+    if is_unencrypted {
+        let response = client.media().upload(mime_type, data, None).await?;
+        Ok(MediaSource::Plain(response.content_uri))
+    } else {
+        let file = client.upload_encrypted_file(&mut Cursor::new(data)).await?;
+        Ok(MediaSource::Encrypted(Box::new(file)))
+    }
+}
+
+/// Returns the source and MIME type of the given message's media, if it has any.
+fn media_of(msgtype: &MessageType) -> Option<(&MediaSource, Option<&str>)> {
+    match msgtype {
+        MessageType::Image(c) => Some((&c.source, c.info.as_ref().and_then(|i| i.mimetype.as_deref()))),
+        MessageType::Video(c) => Some((&c.source, c.info.as_ref().and_then(|i| i.mimetype.as_deref()))),
+        MessageType::Audio(c) => Some((&c.source, c.info.as_ref().and_then(|i| i.mimetype.as_deref()))),
+        MessageType::File(c) => Some((&c.source, c.info.as_ref().and_then(|i| i.mimetype.as_deref()))),
+        _ => None,
+    }
+}
+
+/// Replaces the source of the given message's media with `new_source`.
+///
+/// The media's thumbnail is removed, as it was encrypted along with the original media.
+fn replace_media_source(msgtype: &mut MessageType, new_source: MediaSource) {
+    match msgtype {
+        MessageType::Image(c) => {
+            c.source = new_source;
+            if let Some(info) = c.info.as_mut() {
+                info.thumbnail_source = None;
+                info.thumbnail_info = None;
+            }
+        }
+        MessageType::Video(c) => {
+            c.source = new_source;
+            if let Some(info) = c.info.as_mut() {
+                info.thumbnail_source = None;
+                info.thumbnail_info = None;
+            }
+        }
+        MessageType::Audio(c) => c.source = new_source,
+        MessageType::File(c) => {
+            c.source = new_source;
+            if let Some(info) = c.info.as_mut() {
+                info.thumbnail_source = None;
+                info.thumbnail_info = None;
+            }
+        }
+        _ => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use matrix_sdk::ruma::{
+        OwnedMxcUri, mxc_uri,
+        events::room::{ImageInfo, ThumbnailInfo, message::ImageMessageEventContent},
+    };
+
+    use super::*;
+
+    fn image(source: MediaSource) -> MessageType {
+        let mut info = ImageInfo::new();
+        info.mimetype = Some(String::from("image/png"));
+        info.thumbnail_source = Some(MediaSource::Plain(mxc_uri!("mxc://example.org/thumb").to_owned()));
+        info.thumbnail_info = Some(Box::new(ThumbnailInfo::new()));
+        let mut content = ImageMessageEventContent::new(String::from("cat.png"), source);
+        content.info = Some(Box::new(info));
+        MessageType::Image(content)
+    }
+
+    #[test]
+    fn forwarded_content_keeps_only_the_message_type() {
+        let msgtype = MessageType::text_plain("hello");
+        let content = forwarded_content(&msgtype);
+        assert_eq!(content.body(), "hello");
+        assert!(content.relates_to.is_none());
+    }
+
+    #[test]
+    fn media_of_returns_source_and_mime_type() {
+        let uri: OwnedMxcUri = mxc_uri!("mxc://example.org/cat").to_owned();
+        let msgtype = image(MediaSource::Plain(uri.clone()));
+        let (source, mimetype) = media_of(&msgtype).unwrap();
+        assert!(matches!(source, MediaSource::Plain(u) if *u == uri));
+        assert_eq!(mimetype, Some("image/png"));
+        assert!(media_of(&MessageType::text_plain("hello")).is_none());
+    }
+
+    #[test]
+    fn replace_media_source_drops_thumbnail() {
+        let mut msgtype = image(MediaSource::Plain(mxc_uri!("mxc://example.org/old").to_owned()));
+        let new_uri: OwnedMxcUri = mxc_uri!("mxc://example.org/new").to_owned();
+        replace_media_source(&mut msgtype, MediaSource::Plain(new_uri.clone()));
+        let MessageType::Image(content) = msgtype else { panic!("expected an image") };
+        assert!(matches!(content.source, MediaSource::Plain(u) if u == new_uri));
+        let info = content.info.unwrap();
+        assert!(info.thumbnail_source.is_none());
+        assert!(info.thumbnail_info.is_none());
+        assert_eq!(info.mimetype.as_deref(), Some("image/png"));
+    }
+}
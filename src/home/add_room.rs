@@ -1627,7 +1627,9 @@ impl Widget for AddRoomScreen {
             if let AddRoomState::Parsed { room_or_alias_id, via } = &self.state {
                 for action in actions {
                     match action.downcast_ref() {
-                        Some(RoomPreviewAction::Fetched(Ok(frp))) => {
+                        Some(RoomPreviewAction::Fetched { room_or_alias_id: fetched_id, .. })
+                            if fetched_id != room_or_alias_id => {}
+                        Some(RoomPreviewAction::Fetched { result: Ok(frp), .. }) => {
                             let room_or_alias_id = room_or_alias_id.clone();
                             let via = via.clone();
                             self.state = AddRoomState::FetchedRoomPreview {
@@ -1641,7 +1643,7 @@ impl Widget for AddRoomScreen {
                             self.redraw(cx);
                             break;
                         }
-                        Some(RoomPreviewAction::Fetched(Err(e))) => {
+                        Some(RoomPreviewAction::Fetched { result: Err(e), .. }) => {
                             let error_text = e.to_string();
                             let err_str = tr_fmt(self.app_language, "add_room.popup.fetch_error", &[
                                 ("error", error_text.as_str()),
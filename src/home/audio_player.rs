@@ -0,0 +1,494 @@
+//! An inline player for audio and voice messages in a room timeline.
+//!
+//! The player shows a play/pause button, a waveform that can be clicked to seek,
+//! and the current position and duration of the clip.
+//! The waveform is taken from the message's MSC3245 voice-message metadata if present,
+//! otherwise it is computed once the audio file has been fetched and decoded.
+
+use std::time::Duration;
+
+use bytesize::ByteSize;
+use makepad_widgets::*;
+use matrix_sdk::ruma::{
+    OwnedMxcUri,
+    events::room::{MediaSource, message::AudioMessageEventContent},
+};
+
+use crate::{
+    audio_playback::{self, AudioDecodedAction, WAVEFORM_BARS},
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    sliding_sync::{MatrixRequest, submit_async_request},
+    utils::format_media_duration,
+};
+
+/// How often the player is redrawn while a clip is playing or loading, in seconds.
+const PLAYBACK_REDRAW_INTERVAL: f64 = 0.1;
+
+/// The minimum height of a waveform bar, as a fraction of the waveform's height,
+/// such that silent parts of a clip are still visible.
+const MIN_BAR_HEIGHT: f64 = 0.12;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    // A single bar of an audio waveform, colored based on whether it has been played.
+    let DrawWaveformBar = set_type_default() do #(DrawWaveformBar::script_shader(vm)){
+        ..mod.draw.DrawQuad
+    }
+
+    mod.widgets.AudioWaveform = #(AudioWaveform::register_widget(vm)) {
+        width: Fill,
+        height: 28,
+        bar_spacing: 2.0
+
+        draw_bar: DrawWaveformBar {
+            played: 0.0
+            played_color: instance((COLOR_ACTIVE_PRIMARY))
+            unplayed_color: instance(#B8BEC6)
+
+            pixel: fn() {
+                let sdf = Sdf2d.viewport(self.pos * self.rect_size);
+                sdf.box(
+                    0.0,
+                    0.0,
+                    self.rect_size.x,
+                    self.rect_size.y,
+                    min(self.rect_size.x * 0.5, 1.5)
+                );
+                sdf.fill(mix(self.unplayed_color, self.played_color, self.played));
+                return sdf.result;
+            }
+        }
+    }
+
+    mod.widgets.AudioPlayer = #(AudioPlayer::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 5.0
+        padding: Inset{ top: 2.0, bottom: 2.0 }
+
+        title_label := Label {
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (MESSAGE_TEXT_COLOR),
+                text_style: theme.font_bold { font_size: 10.5 },
+            }
+            text: ""
+        }
+
+        controls := RoundedView {
+            width: Fill { max: 480 },
+            height: Fit,
+            flow: Right,
+            spacing: 10.0
+            align: Align{ y: 0.5 }
+            padding: Inset{ left: 6, right: 10, top: 6, bottom: 6 }
+            show_bg: true
+            draw_bg +: {
+                color: #F6F7F9
+                border_radius: 5.0
+                border_size: 1.0
+                border_color: #DDE1E6
+            }
+
+            play_button := RobrixIconButton {
+                width: 32,
+                height: 32,
+                padding: 0,
+                spacing: 0,
+                align: Align{ x: 0.5, y: 0.5 }
+                draw_bg +: { border_radius: 16.0 }
+                draw_icon +: { svg: (ICON_PLAY) }
+                icon_walk: Walk{ width: 14, height: 14, margin: Inset{ left: 2 } }
+            }
+
+            pause_button := RobrixIconButton {
+                visible: false
+                width: 32,
+                height: 32,
+                padding: 0,
+                spacing: 0,
+                align: Align{ x: 0.5, y: 0.5 }
+                draw_bg +: { border_radius: 16.0 }
+                draw_icon +: { svg: (ICON_PAUSE) }
+                icon_walk: Walk{ width: 14, height: 14 }
+            }
+
+            waveform := mod.widgets.AudioWaveform { }
+
+            time_label := Label {
+                width: Fit,
+                height: Fit,
+                draw_text +: {
+                    color: (COLOR_MESSAGE_NOTICE_TEXT),
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+                }
+                text: ""
+            }
+        }
+
+        status_label := Label {
+            visible: false
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (COLOR_MESSAGE_NOTICE_TEXT),
+                text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+            }
+            text: ""
+        }
+
+        // Shown if the clip can't be decoded, e.g., because its codec isn't supported,
+        // such that the user can still play it in an external app.
+        open_externally_button := RobrixNeutralIconButton {
+            visible: false
+            padding: Inset{ left: 10, right: 10, top: 6, bottom: 6 }
+            draw_icon +: { svg: (ICON_EXTERNAL_LINK) }
+            icon_walk: Walk{ width: 14, height: 14 }
+            text: ""
+        }
+
+        caption_label := Label {
+            visible: false
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (MESSAGE_TEXT_COLOR),
+                text_style: MESSAGE_TEXT_STYLE { font_size: 10.0 },
+            }
+            text: ""
+        }
+    }
+}
+
+#[derive(Script, ScriptHook)]
+#[repr(C)]
+pub struct DrawWaveformBar {
+    #[deref] draw_super: DrawQuad,
+    /// `1.0` if this bar lies before the current playback position, otherwise `0.0`.
+    #[live] played: f32,
+}
+
+/// Actions emitted by an `AudioWaveform` based on user interaction with it.
+#[derive(Clone, Debug, Default)]
+pub enum AudioWaveformAction {
+    /// The user clicked or dragged on the waveform to seek to the given fraction of the clip.
+    Seek(f64),
+    #[default]
+    None,
+}
+
+/// A waveform summary of an audio clip, which also shows the playback progress.
+#[derive(Script, ScriptHook, Widget)]
+pub struct AudioWaveform {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] draw_bar: DrawWaveformBar,
+    #[live] bar_spacing: f64,
+    #[walk] walk: Walk,
+
+    /// The normalized amplitude of each bar, from `0.0` to `1.0`.
+    #[rust] amplitudes: Vec<f32>,
+    /// The playback progress, from `0.0` to `1.0`.
+    #[rust] progress: f64,
+}
+
+impl Widget for AudioWaveform {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        let seek_position = match event.hits(cx, self.area) {
+            Hit::FingerDown(fe) if fe.is_primary_hit() => Some(fe.abs.x),
+            Hit::FingerMove(fe) => Some(fe.abs.x),
+            Hit::FingerHoverIn(_) => {
+                cx.set_cursor(MouseCursor::Hand);
+                None
+            }
+            _ => None,
+        };
+        if let Some(abs_x) = seek_position {
+            let rect = self.area.rect(cx);
+            if rect.size.x > 0.0 {
+                let fraction = ((abs_x - rect.pos.x) / rect.size.x).clamp(0.0, 1.0);
+                cx.widget_action(self.widget_uid(), AudioWaveformAction::Seek(fraction));
+            }
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, _scope: &mut Scope, walk: Walk) -> DrawStep {
+        let rect = cx.walk_turtle_with_area(&mut self.area, walk);
+        // Draw flat bars until the clip's waveform is known.
+        let num_bars = if self.amplitudes.is_empty() { WAVEFORM_BARS } else { self.amplitudes.len() };
+        let total_spacing = self.bar_spacing * (num_bars - 1) as f64;
+        let bar_width = ((rect.size.x - total_spacing) / num_bars as f64).max(1.0);
+        for bar in 0..num_bars {
+            let amplitude = self.amplitudes.get(bar).copied().unwrap_or(0.0) as f64;
+            let height = rect.size.y * amplitude.clamp(MIN_BAR_HEIGHT, 1.0);
+            let bar_center = (bar as f64 + 0.5) / num_bars as f64;
+            self.draw_bar.played = if bar_center <= self.progress { 1.0 } else { 0.0 };
+            self.draw_bar.draw_abs(cx, Rect {
+                pos: dvec2(
+                    rect.pos.x + bar as f64 * (bar_width + self.bar_spacing),
+                    rect.pos.y + (rect.size.y - height) / 2.0,
+                ),
+                size: dvec2(bar_width, height),
+            });
+        }
+        DrawStep::done()
+    }
+}
+
+impl AudioWaveformRef {
+    /// Sets the waveform's amplitudes and the playback progress (from `0.0` to `1.0`).
+    fn set_waveform(&self, amplitudes: &[f32], progress: f64) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        if inner.amplitudes != amplitudes {
+            inner.amplitudes = amplitudes.to_vec();
+        }
+        inner.progress = progress.clamp(0.0, 1.0);
+    }
+
+    /// Returns the fraction of the clip to seek to, if the user clicked on this waveform.
+    fn seeked(&self, actions: &Actions) -> Option<f64> {
+        match actions.find_widget_action(self.widget_uid()).cast() {
+            AudioWaveformAction::Seek(fraction) => Some(fraction),
+            AudioWaveformAction::None => None,
+        }
+    }
+}
+
+/// Actions emitted by an `AudioPlayer` based on user interaction with it.
+#[derive(Clone, Debug, Default)]
+pub enum AudioPlayerAction {
+    /// The user wants to play a clip that must first be fetched and decoded.
+    PlayRequested {
+        mxc_uri: OwnedMxcUri,
+        mimetype: Option<String>,
+    },
+    #[default]
+    None,
+}
+
+/// The details of the audio clip shown in an `AudioPlayer`.
+#[derive(Clone, Debug)]
+struct AudioClip {
+    mxc_uri: OwnedMxcUri,
+    mimetype: Option<String>,
+    /// The duration specified in the message's metadata, if any.
+    duration: Option<Duration>,
+    /// The waveform specified in the message's MSC3245 metadata, if any.
+    waveform: Option<Vec<f32>>,
+}
+
+/// An inline player for an audio or voice message.
+#[derive(Script, ScriptHook, Widget)]
+pub struct AudioPlayer {
+    #[deref] view: View,
+
+    /// The clip shown in this player, which is `None` if it cannot be played.
+    #[rust] clip: Option<AudioClip>,
+    #[rust] app_language: AppLanguage,
+    /// Whether the user pressed play and we're waiting for the clip to be fetched and decoded.
+    #[rust] awaiting_playback: bool,
+    /// A timer that redraws this player while its clip is playing or loading.
+    #[rust] redraw_timer: Timer,
+}
+
+impl Widget for AudioPlayer {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if self.redraw_timer.is_event(event).is_some() {
+            self.view.redraw(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.update_playback_ui(cx);
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for AudioPlayer {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let Some(clip) = self.clip.as_ref() else { return };
+
+        for action in actions {
+            if let Some(AudioDecodedAction { mxc_uri }) = action.downcast_ref() {
+                if mxc_uri == &clip.mxc_uri {
+                    self.view.redraw(cx);
+                }
+            }
+        }
+
+        if self.view.button(cx, ids!(play_button)).clicked(actions) {
+            if !audio_playback::play(&clip.mxc_uri) {
+                let action = AudioPlayerAction::PlayRequested {
+                    mxc_uri: clip.mxc_uri.clone(),
+                    mimetype: clip.mimetype.clone(),
+                };
+                self.awaiting_playback = true;
+                cx.widget_action(self.widget_uid(), action);
+            }
+            cx.stop_timer(self.redraw_timer);
+            self.redraw_timer = cx.start_interval(PLAYBACK_REDRAW_INTERVAL);
+            self.view.redraw(cx);
+        }
+
+        if self.view.button(cx, ids!(pause_button)).clicked(actions) {
+            audio_playback::pause(&clip.mxc_uri);
+            self.view.redraw(cx);
+        }
+
+        if let Some(fraction) = self.view.audio_waveform(cx, ids!(waveform)).seeked(actions) {
+            audio_playback::seek(&clip.mxc_uri, fraction);
+            self.view.redraw(cx);
+        }
+
+        if self.view.button(cx, ids!(open_externally_button)).clicked(actions) {
+            submit_async_request(MatrixRequest::DownloadAndSaveFile {
+                mxc_uri: clip.mxc_uri.clone(),
+                app_language: self.app_language,
+            });
+        }
+    }
+}
+
+impl AudioPlayer {
+    /// Populates this player with the given audio message.
+    fn set_audio(&mut self, cx: &mut Cx, app_language: AppLanguage, audio: &AudioMessageEventContent) {
+        self.app_language = app_language;
+        self.awaiting_playback = false;
+        cx.stop_timer(self.redraw_timer);
+
+        let title = if audio.voice.is_some() {
+            tr_key(app_language, "audio_player.voice_message").to_string()
+        } else {
+            audio.filename().to_string()
+        };
+        let title = match audio.info.as_ref().and_then(|info| info.size) {
+            Some(bytes) => format!("{title}  ({})", ByteSize::b(bytes.into())),
+            None => title,
+        };
+        self.view.label(cx, ids!(title_label)).set_text(cx, &title);
+        self.view.button(cx, ids!(open_externally_button))
+            .set_text(cx, tr_key(app_language, "audio_player.button.open_externally"));
+
+        let caption_label = self.view.label(cx, ids!(caption_label));
+        let caption = audio.caption();
+        caption_label.set_visible(cx, caption.is_some());
+        caption_label.set_text(cx, caption.unwrap_or_default());
+
+        // MSC3245 amplitudes range from 0 to 1024, but we only care about their relative values.
+        let waveform = audio.audio.as_ref()
+            .filter(|details| !details.waveform.is_empty())
+            .map(|details| {
+                let amplitudes: Vec<f32> = details.waveform.iter()
+                    .map(|amplitude| u64::from(amplitude.get()) as f32)
+                    .collect();
+                audio_playback::resample_waveform(&amplitudes, WAVEFORM_BARS)
+            });
+        let duration = audio.audio.as_ref()
+            .map(|details| details.duration)
+            .or_else(|| audio.info.as_ref().and_then(|info| info.duration));
+
+        self.clip = match &audio.source {
+            MediaSource::Plain(mxc_uri) => Some(AudioClip {
+                mxc_uri: mxc_uri.clone(),
+                mimetype: audio.info.as_ref().and_then(|info| info.mimetype.clone()),
+                duration,
+                waveform,
+            }),
+            MediaSource::Encrypted(_) => {
+                self.view.audio_waveform(cx, ids!(waveform))
+                    .set_waveform(waveform.as_deref().unwrap_or_default(), 0.0);
+                self.view.label(cx, ids!(time_label))
+                    .set_text(cx, &duration.map(format_media_duration).unwrap_or_default());
+                None
+            }
+        };
+
+        let is_playable = self.clip.is_some();
+        self.view.button(cx, ids!(play_button)).set_enabled(cx, is_playable);
+        let status_label = self.view.label(cx, ids!(status_label));
+        status_label.set_visible(cx, !is_playable);
+        if !is_playable {
+            status_label.set_text(cx, tr_key(app_language, "audio_player.status.encrypted_not_supported"));
+        }
+        self.view.redraw(cx);
+    }
+
+    /// Updates the buttons, waveform, and labels based on the clip's current playback status.
+    fn update_playback_ui(&mut self, cx: &mut Cx) {
+        let Some(clip) = self.clip.as_ref() else { return };
+        let status = audio_playback::playback_status(&clip.mxc_uri);
+        let is_playing = status.as_ref().is_some_and(|status| status.is_playing);
+        let error = audio_playback::decode_error(&clip.mxc_uri);
+        if is_playing || error.is_some() {
+            self.awaiting_playback = false;
+        }
+        if !is_playing && !self.awaiting_playback {
+            cx.stop_timer(self.redraw_timer);
+        }
+
+        self.view.button(cx, ids!(play_button)).set_visible(cx, !is_playing);
+        self.view.button(cx, ids!(pause_button)).set_visible(cx, is_playing);
+
+        let duration = status.as_ref().map(|status| status.duration).or(clip.duration);
+        let position = status.as_ref().map(|status| status.position);
+        let time_text = match (position, duration) {
+            (Some(position), Some(duration)) => format!("{} / {}", format_media_duration(position), format_media_duration(duration)),
+            (None, Some(duration)) => format_media_duration(duration),
+            (_, None) => String::new(),
+        };
+        self.view.label(cx, ids!(time_label)).set_text(cx, &time_text);
+
+        let progress = match (position, duration) {
+            (Some(position), Some(duration)) if !duration.is_zero() => position.as_secs_f64() / duration.as_secs_f64(),
+            _ => 0.0,
+        };
+        let computed_waveform;
+        let waveform = match clip.waveform.as_deref() {
+            Some(waveform) => waveform,
+            None => {
+                computed_waveform = audio_playback::computed_waveform(&clip.mxc_uri);
+                computed_waveform.as_deref().unwrap_or_default()
+            }
+        };
+        self.view.audio_waveform(cx, ids!(waveform)).set_waveform(waveform, progress);
+
+        self.view.button(cx, ids!(open_externally_button)).set_visible(cx, error.is_some());
+        let status_text = if let Some(error) = error {
+            Some(tr_fmt(self.app_language, "audio_player.status.failed", &[("error", &error)]))
+        } else if self.awaiting_playback {
+            Some(tr_key(self.app_language, "audio_player.status.loading").to_string())
+        } else {
+            None
+        };
+        let status_label = self.view.label(cx, ids!(status_label));
+        status_label.set_visible(cx, status_text.is_some());
+        if let Some(status_text) = status_text {
+            status_label.set_text(cx, &status_text);
+        }
+    }
+}
+
+impl AudioPlayerRef {
+    /// See [`AudioPlayer::set_audio()`].
+    pub fn set_audio(&self, cx: &mut Cx, app_language: AppLanguage, audio: &AudioMessageEventContent) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.set_audio(cx, app_language, audio);
+    }
+
+    /// Returns the clip that the user wants to play, if it must first be fetched and decoded.
+    pub fn play_requested(&self, actions: &Actions) -> Option<(OwnedMxcUri, Option<String>)> {
+        match actions.find_widget_action(self.widget_uid()).cast() {
+            AudioPlayerAction::PlayRequested { mxc_uri, mimetype } => Some((mxc_uri, mimetype)),
+            AudioPlayerAction::None => None,
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! A modal that shows the full edit history of an edited message.
+//!
+//! The history is fetched via the `/relations` API, see [`MatrixRequest::FetchEditHistory`].
+//! Each revision is shown alongside its timestamp and a word-level diff
+//! against the revision that preceded it.
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+use crate::{
+    app::AppState,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    sliding_sync::{EditHistoryAction, MatrixRequest, MessageRevision, submit_async_request},
+    utils::unix_time_millis_to_datetime,
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+
+    // A single revision in the list of a message's revisions.
+    let EditRevisionEntry = RoundedView {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 6
+        margin: Inset{bottom: 6}
+        padding: Inset{top: 10, right: 12, bottom: 10, left: 12}
+
+        show_bg: true
+        draw_bg +: {
+            color: #F8FAFD
+            border_radius: 4.0
+            border_size: 1.0
+            border_color: #D8E0EA
+        }
+
+        header_row := View {
+            width: Fill
+            height: Fit
+            flow: Right
+            spacing: 8
+
+            revision_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: USERNAME_TEXT_STYLE { font_size: 10 }
+                    color: #1F1F1F
+                }
+                text: ""
+            }
+
+            time := Label {
+                width: Fit
+                height: Fit
+                draw_text +: {
+                    text_style: TIMESTAMP_TEXT_STYLE { font_size: 7.5 }
+                    color: (TIMESTAMP_TEXT_COLOR)
+                }
+                text: ""
+            }
+        }
+
+        body_html := mod.widgets.MessageHtml {
+            font_size: 10.0
+            body: ""
+        }
+    }
+
+    mod.widgets.EditHistoryModal = #(EditHistoryModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 520
+            height: Fit
+            flow: Down
+            spacing: 8
+            padding: Inset{top: 20, right: 25, bottom: 20, left: 25}
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 4.0
+            }
+
+            title_view := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{y: 0.5}
+
+                title := Label {
+                    width: Fill, height: Fit,
+                    draw_text +: {
+                        text_style: TITLE_TEXT {font_size: 13},
+                        color: #000
+                    }
+                    text: "Edit History"
+                }
+
+                close_button := RobrixNeutralIconButton {
+                    width: Fit,
+                    height: Fit,
+                    spacing: 0,
+                    padding: 12,
+                    draw_icon.svg: (ICON_CLOSE)
+                    icon_walk: Walk{width: 14, height: 14}
+                    text: ""
+                }
+            }
+
+            status_label := Label {
+                width: Fill,
+                height: Fit,
+                flow: Flow.Right{wrap: true},
+                margin: Inset{left: 1}
+                draw_text +: {
+                    text_style: REGULAR_TEXT {font_size: 9.5}
+                    color: #6D7682
+                }
+                text: ""
+            }
+
+            revisions_list := PortalList {
+                width: Fill
+                height: 420
+                flow: Down
+                max_pull_down: 0.0
+
+                Revision := EditRevisionEntry {}
+            }
+        }
+    }
+}
+
+
+/// Actions emitted by other widgets to show or hide the `EditHistoryModal`.
+#[derive(Clone, Debug)]
+pub enum EditHistoryModalAction {
+    /// Open the modal to show the edit history of the given message.
+    Open {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+    },
+    /// Close the modal.
+    Close,
+}
+
+/// The state of the edit history shown in an `EditHistoryModal`.
+#[derive(Clone, Debug, Default)]
+enum EditHistoryState {
+    /// Waiting for the edit history to be fetched from the homeserver.
+    #[default]
+    Loading,
+    /// The edit history was fetched successfully.
+    Loaded,
+    /// Fetching the edit history failed with the given error.
+    Failed(String),
+}
+
+/// A modal that lists every revision of an edited message, newest first.
+#[derive(Script, ScriptHook, Widget)]
+pub struct EditHistoryModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    #[rust] room_id: Option<OwnedRoomId>,
+    /// The ID of the original (edited) message.
+    #[rust] event_id: Option<OwnedEventId>,
+    #[rust] state: EditHistoryState,
+    /// The message's revisions, in ascending order of their timestamps.
+    #[rust] revisions: Vec<MessageRevision>,
+    /// The HTML body of each revision in `revisions`,
+    /// which highlights the changes since the previous revision.
+    #[rust] revision_htmls: Vec<String>,
+}
+
+impl Widget for EditHistoryModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let status_text = self.status_text();
+        let status_label = self.view.label(cx, ids!(status_label));
+        status_label.set_visible(cx, !status_text.is_empty());
+        status_label.set_text(cx, &status_text);
+
+        while let Some(widget) = self.view.draw_walk(cx, scope, walk).step() {
+            let portal_list_ref = widget.as_portal_list();
+            let Some(mut list) = portal_list_ref.borrow_mut() else { continue };
+
+            let num_revisions = self.revisions.len();
+            list.set_item_range(cx, 0, num_revisions);
+            while let Some(item_id) = list.next_visible_item(cx) {
+                // Show the newest revision first.
+                let Some(revision_index) = num_revisions.checked_sub(item_id + 1) else { continue };
+                let Some(revision) = self.revisions.get(revision_index) else { continue };
+                let item = list.item(cx, item_id, id!(Revision));
+                let revision_text = if revision_index == 0 {
+                    tr_key(self.app_language, "edit_history.revision.original").to_string()
+                } else {
+                    tr_fmt(
+                        self.app_language,
+                        "edit_history.revision.edit",
+                        &[("number", &revision_index.to_string())],
+                    )
+                };
+                item.label(cx, ids!(revision_label)).set_text(cx, &revision_text);
+                item.label(cx, ids!(time)).set_text(
+                    cx,
+                    &unix_time_millis_to_datetime(revision.timestamp)
+                        .map(|dt| dt.format("%a %b %-d, %Y, %r").to_string())
+                        .unwrap_or_default(),
+                );
+                let body_html = self.revision_htmls.get(revision_index).map(String::as_str).unwrap_or_default();
+                item.html(cx, ids!(body_html)).set_text(cx, body_html);
+                item.draw_all(cx, &mut Scope::empty());
+            }
+        }
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for EditHistoryModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let close_clicked = self.view.button(cx, ids!(close_button)).clicked(actions);
+        if close_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // an `EditHistoryModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if close_clicked {
+                cx.action(EditHistoryModalAction::Close);
+            }
+            return;
+        }
+
+        for action in actions {
+            match action.downcast_ref() {
+                Some(EditHistoryAction::Loaded { room_id, event_id, revisions }) => {
+                    if !self.is_current_message(room_id, event_id) {
+                        continue;
+                    }
+                    self.revisions = revisions.clone();
+                    self.revision_htmls = self.revision_htmls_for(revisions);
+                    self.state = EditHistoryState::Loaded;
+                    self.redraw(cx);
+                }
+                Some(EditHistoryAction::Failed { room_id, event_id, error }) => {
+                    if !self.is_current_message(room_id, event_id) {
+                        continue;
+                    }
+                    self.state = EditHistoryState::Failed(error.clone());
+                    self.redraw(cx);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+impl EditHistoryModal {
+    /// Shows the modal and starts fetching the edit history of the given message.
+    pub fn show(
+        &mut self,
+        cx: &mut Cx,
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+        app_language: AppLanguage,
+    ) {
+        self.app_language = app_language;
+        self.room_id = Some(room_id.clone());
+        self.event_id = Some(event_id.clone());
+        self.state = EditHistoryState::Loading;
+        self.revisions.clear();
+        self.revision_htmls.clear();
+        self.update_static_texts(cx);
+        submit_async_request(MatrixRequest::FetchEditHistory { room_id, event_id });
+
+        self.view.portal_list(cx, ids!(revisions_list)).set_first_id_and_scroll(0, 0.0);
+        self.view.button(cx, ids!(close_button)).reset_hover(cx);
+        self.view.redraw(cx);
+    }
+
+    /// Returns whether the given edit history response belongs to the message shown in this modal,
+    /// such that responses for previously-shown messages can be ignored.
+    fn is_current_message(&self, room_id: &OwnedRoomId, event_id: &OwnedEventId) -> bool {
+        matches!(self.state, EditHistoryState::Loading)
+            && self.room_id.as_ref() == Some(room_id)
+            && self.event_id.as_ref() == Some(event_id)
+    }
+
+    /// Returns the HTML body of each of the given revisions.
+    ///
+    /// The original content is shown as-is, whereas each edit is shown as a diff
+    /// against the most recent previous revision that could be decrypted.
+    /// Revisions that could not be decrypted are left empty.
+    fn revision_htmls_for(&self, revisions: &[MessageRevision]) -> Vec<String> {
+        let mut previous_body: Option<&str> = None;
+        revisions.iter()
+            .map(|revision| {
+                let Some(body) = revision.body.as_deref() else {
+                    return format!(
+                        "<i>{}</i>",
+                        tr_key(self.app_language, "edit_history.revision.unable_to_decrypt"),
+                    );
+                };
+                let html = match previous_body {
+                    Some(previous) => diff_to_html(&diff_words(previous, body)),
+                    None => escape_text_html(body),
+                };
+                previous_body = Some(body);
+                html
+            })
+            .collect()
+    }
+
+    fn status_text(&self) -> String {
+        match &self.state {
+            EditHistoryState::Loading => tr_key(self.app_language, "edit_history.status.loading").to_string(),
+            EditHistoryState::Loaded if self.revisions.len() <= 1 => tr_key(self.app_language, "edit_history.status.no_edits").to_string(),
+            EditHistoryState::Loaded => String::new(),
+            EditHistoryState::Failed(error) => tr_fmt(
+                self.app_language,
+                "edit_history.status.failed",
+                &[("error", error)],
+            ),
+        }
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(title))
+            .set_text(cx, tr_key(self.app_language, "edit_history.title"));
+        // The "unable to decrypt" placeholders are translated when the revisions are rendered.
+        if matches!(self.state, EditHistoryState::Loaded) {
+            self.revision_htmls = self.revision_htmls_for(&self.revisions);
+        }
+    }
+}
+
+impl EditHistoryModalRef {
+    /// See [`EditHistoryModal::show()`].
+    pub fn show(
+        &self,
+        cx: &mut Cx,
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+        app_language: AppLanguage,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, room_id, event_id, app_language);
+    }
+}
+
+
+/// Whether a segment of a diff is present in the old text, the new text, or both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DiffKind {
+    Unchanged,
+    Added,
+    Removed,
+}
+
+/// The maximum number of cells in the LCS table used by [`diff_words()`].
+///
+/// Longer texts are shown as having been completely replaced.
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// Splits the given text into alternating runs of whitespace and non-whitespace.
+fn tokenize_words(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut prev_is_whitespace = None;
+    for (i, ch) in text.char_indices() {
+        let is_whitespace = ch.is_whitespace();
+        if prev_is_whitespace.is_some_and(|prev| prev != is_whitespace) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        prev_is_whitespace = Some(is_whitespace);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Computes a word-level diff from `old` to `new`,
+/// merging adjacent segments of the same kind.
+fn diff_words(old: &str, new: &str) -> Vec<(DiffKind, String)> {
+    let old_tokens = tokenize_words(old);
+    let new_tokens = tokenize_words(new);
+
+    // Skip the common prefix and suffix, which are usually the bulk of an edited message.
+    let prefix_len = old_tokens.iter().zip(&new_tokens)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix_len = old_tokens[prefix_len..].iter().rev()
+        .zip(new_tokens[prefix_len..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old_tokens[prefix_len .. old_tokens.len() - suffix_len];
+    let new_mid = &new_tokens[prefix_len .. new_tokens.len() - suffix_len];
+
+    let mut segments: Vec<(DiffKind, String)> = Vec::new();
+    let mut push = |kind: DiffKind, token: &str| match segments.last_mut() {
+        Some((last_kind, text)) if *last_kind == kind => text.push_str(token),
+        _ => segments.push((kind, token.to_string())),
+    };
+
+    old_tokens[..prefix_len].iter().for_each(|t| push(DiffKind::Unchanged, t));
+
+    let (n, m) = (old_mid.len(), new_mid.len());
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        old_mid.iter().for_each(|t| push(DiffKind::Removed, t));
+        new_mid.iter().for_each(|t| push(DiffKind::Added, t));
+    } else {
+        // `lcs[i][j]` is the length of the longest common subsequence of `old_mid[i..]` and `new_mid[j..]`.
+        let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_mid[i] == new_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if old_mid[i] == new_mid[j] {
+                push(DiffKind::Unchanged, old_mid[i]);
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                push(DiffKind::Removed, old_mid[i]);
+                i += 1;
+            } else {
+                push(DiffKind::Added, new_mid[j]);
+                j += 1;
+            }
+        }
+        old_mid[i..].iter().for_each(|t| push(DiffKind::Removed, t));
+        new_mid[j..].iter().for_each(|t| push(DiffKind::Added, t));
+    }
+
+    old_tokens[old_tokens.len() - suffix_len ..].iter().for_each(|t| push(DiffKind::Unchanged, t));
+    segments
+}
+
+/// Escapes the given plaintext such that it can be shown in an `Html` widget, preserving line breaks.
+fn escape_text_html(text: &str) -> String {
+    htmlize::escape_text(text).replace('\n', "<br>")
+}
+
+/// Renders the given diff segments as HTML, with removed text struck through in red
+/// and added text in green.
+fn diff_to_html(segments: &[(DiffKind, String)]) -> String {
+    const COLOR_ADDED: &str = "#1A7F37";
+    const COLOR_REMOVED: &str = "#CF222E";
+
+    let mut out = String::new();
+    for (kind, text) in segments {
+        let escaped = escape_text_html(text);
+        match kind {
+            DiffKind::Unchanged => out.push_str(&escaped),
+            DiffKind::Added => {
+                out.push_str("<font color=\"");
+                out.push_str(COLOR_ADDED);
+                out.push_str("\">");
+                out.push_str(&escaped);
+                out.push_str("</font>");
+            }
+            DiffKind::Removed => {
+                out.push_str("<font color=\"");
+                out.push_str(COLOR_REMOVED);
+                out.push_str("\"><del>");
+                out.push_str(&escaped);
+                out.push_str("</del></font>");
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffKind, diff_to_html, diff_words};
+
+    #[test]
+    fn diff_words_marks_replaced_words() {
+        let segments = diff_words("the quick brown fox", "the slow brown fox jumps");
+        assert_eq!(segments, vec![
+            (DiffKind::Unchanged, "the ".to_string()),
+            (DiffKind::Removed, "quick".to_string()),
+            (DiffKind::Added, "slow".to_string()),
+            (DiffKind::Unchanged, " brown fox".to_string()),
+            (DiffKind::Added, " jumps".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn diff_words_of_identical_texts_is_unchanged() {
+        assert_eq!(
+            diff_words("no changes here", "no changes here"),
+            vec![(DiffKind::Unchanged, "no changes here".to_string())],
+        );
+        assert!(diff_words("", "").is_empty());
+    }
+
+    #[test]
+    fn diff_to_html_escapes_and_highlights_changes() {
+        let html = diff_to_html(&diff_words("a <b>\nc", "a <i>\nc"));
+        assert!(html.starts_with("a "));
+        assert!(html.contains("<font color=\"#CF222E\"><del>&lt;b&gt;</del></font>"));
+        assert!(html.contains("<font color=\"#1A7F37\">&lt;i&gt;</font>"));
+        assert!(html.ends_with("<br>c"));
+    }
+}
@@ -28,8 +28,7 @@ script_mod! {
         padding: 0,
         margin: Inset{ top: 5 }
 
-        // TODO: re-enable this once we have implemented the edit history modal
-        // cursor: MouseCursor.Hand,
+        cursor: MouseCursor.Hand,
 
         edit_html := Html {
             width: Fit, height: Fit
@@ -59,11 +58,11 @@ impl Widget for EditedIndicator {
         let should_hover_in = match event.hits(cx, area) {
             Hit::FingerLongPress(_)
             | Hit::FingerHoverIn(..) => true,
-            // TODO: show edit history modal on click
-            // Hit::FingerUp(fue) if fue.is_over && fue.is_primary_hit() => {
-            //     log!("todo: show edit history.");
-            //     false
-            // },
+            Hit::FingerUp(fue) if fue.is_over && fue.is_primary_hit() => {
+                cx.widget_action(self.widget_uid(), TooltipAction::HoverOut);
+                cx.widget_action(self.widget_uid(), EditedIndicatorAction::ShowEditHistory);
+                false
+            }
             Hit::FingerHoverOut(_) => {
                 cx.widget_action(self.widget_uid(),  TooltipAction::HoverOut);
                 false
@@ -118,6 +117,14 @@ impl EditedIndicatorRef {
             inner.set_latest_edit(cx, event_tl_item);
         }
     }
+
+    /// Returns whether this indicator was clicked, i.e., whether the edit history should be shown.
+    pub fn clicked(&self, actions: &Actions) -> bool {
+        matches!(
+            actions.find_widget_action(self.widget_uid()).cast_ref(),
+            EditedIndicatorAction::ShowEditHistory,
+        )
+    }
 }
 
 
@@ -3,7 +3,7 @@ use matrix_sdk::{
     room::edit::EditedContent,
     ruma::{
         events::{
-            poll::unstable_start::{UnstablePollAnswer, UnstablePollStartContentBlock},
+            poll::unstable_start::UnstablePollStartContentBlock,
             room::message::{FormattedBody, MessageType, RoomMessageEventContentWithoutRelation},
         },
     },
@@ -12,6 +12,7 @@ use matrix_sdk_ui::timeline::{EventTimelineItem, MsgLikeKind, TimelineEventItemI
 
 use crate::shared::mentionable_text_input::{MentionableTextInputWidgetExt, MentionableTextInputWidgetRefExt};
 use crate::{
+    home::poll_composer::{poll_answers_from_drafts, PollAnswerDraft, PollAnswersEditorWidgetExt, PollAnswersEditorWidgetRefExt, MAX_POLL_ANSWERS, MIN_POLL_ANSWERS},
     shared::popup_list::{enqueue_popup_notification, PopupKind},
     sliding_sync::{submit_async_request, MatrixRequest, TimelineKind},
 };
@@ -88,6 +89,26 @@ script_mod! {
             height: Fit{max: FitBound.Rel{base: Base.Full, factor: 0.75}}
             margin: Inset{ bottom: 5, top: 5 }
         }
+
+        // Only shown when editing a poll, in which case the text input above edits its question.
+        poll_answers_view := View {
+            visible: false,
+            width: Fill,
+            height: Fit,
+            flow: Down,
+            spacing: 6,
+            margin: Inset{ bottom: 5 }
+
+            poll_answers_editor := PollAnswersEditor { }
+
+            add_poll_answer_button := RobrixNeutralIconButton {
+                width: Fit
+                padding: Inset{top: 8, bottom: 8, left: 12, right: 12}
+                draw_icon.svg: (ICON_ADD)
+                icon_walk: Walk{width: 12, height: 12, margin: Inset{right: -2}}
+                text: "Add answer"
+            }
+        }
     }
 
 
@@ -225,6 +246,16 @@ impl Widget for EditingPane {
 
             let Some(info) = self.info.as_ref() else { return };
 
+            if self.button(cx, ids!(add_poll_answer_button)).clicked(actions)
+                && !self.poll_answers_editor(cx, ids!(poll_answers_editor)).add_answer(cx)
+            {
+                enqueue_popup_notification(
+                    format!("A poll cannot have more than {MAX_POLL_ANSWERS} answers."),
+                    PopupKind::Error,
+                    Some(4.0),
+                );
+            }
+
             if self.button(cx, ids!(accept_button)).clicked(actions)
                 || edit_text_input.returned(actions).is_some_and(|(_, m)| m.is_primary())
             {
@@ -331,18 +362,20 @@ impl Widget for EditingPane {
 
                             MsgLikeKind::Poll(poll) => {
                                 let poll_result = poll.results();
-                                let poll_answers = poll_result.answers;
-                                // TODO: support editing poll answers. For now, just keep the same answers.
-                                let Ok(new_poll_answers) = poll_answers
-                                    .into_iter()
-                                    .map(|answer| UnstablePollAnswer::new(answer.id, answer.text))
-                                    .collect::<Vec<_>>()
-                                    .try_into()
-                                else {
+                                if edited_text.is_empty() {
+                                    enqueue_popup_notification(
+                                        "A poll must have a question.",
+                                        PopupKind::Error,
+                                        Some(4.0),
+                                    );
+                                    return;
+                                }
+                                let answer_drafts = self.poll_answers_editor(cx, ids!(poll_answers_editor)).answers();
+                                let Some(new_poll_answers) = poll_answers_from_drafts(answer_drafts) else {
                                     enqueue_popup_notification(
-                                        "Failed to obtain existing poll answers while editing poll.",
+                                        format!("A poll must have between {MIN_POLL_ANSWERS} and {MAX_POLL_ANSWERS} answers."),
                                         PopupKind::Error,
-                                        None,
+                                        Some(4.0),
                                     );
                                     return;
                                 };
@@ -499,10 +532,21 @@ impl EditingPane {
 
         let edit_text_input = self.mentionable_text_input(cx, ids!(editing_content.edit_text_input));
 
+        let poll_answers_editor = self.poll_answers_editor(cx, ids!(poll_answers_editor));
         if let Some(message) = event_tl_item.content().as_message() {
             edit_text_input.set_text(cx, message.body());
+            poll_answers_editor.set_answers(cx, Vec::new());
+            self.view(cx, ids!(poll_answers_view)).set_visible(cx, false);
         } else if let Some(poll) = event_tl_item.content().as_poll() {
-            edit_text_input.set_text(cx, &poll.results().question);
+            let poll_result = poll.results();
+            edit_text_input.set_text(cx, &poll_result.question);
+            poll_answers_editor.set_answers(
+                cx,
+                poll_result.answers.into_iter()
+                    .map(|answer| PollAnswerDraft { id: Some(answer.id), text: answer.text })
+                    .collect(),
+            );
+            self.view(cx, ids!(poll_answers_view)).set_visible(cx, true);
         } else {
             enqueue_popup_notification(
                 "That message cannot be edited.",
@@ -546,6 +590,11 @@ impl EditingPane {
                 .as_mentionable_text_input()
                 .text_input_ref()
                 .save_state(),
+            poll_answers: info.event_tl_item.content().as_poll().map(|_|
+                self.child_by_path(ids!(poll_answers_editor))
+                    .as_poll_answers_editor()
+                    .answers()
+            ),
         })
     }
 
@@ -556,10 +605,13 @@ impl EditingPane {
         editing_pane_state: EditingPaneState,
         timeline_kind: TimelineKind,
     ) {
-        let EditingPaneState { event_tl_item, text_input_state } = editing_pane_state;
+        let EditingPaneState { event_tl_item, text_input_state, poll_answers } = editing_pane_state;
         self.mentionable_text_input(cx, ids!(editing_content.edit_text_input))
             .text_input_ref()
             .restore_state(cx, text_input_state);
+        self.view(cx, ids!(poll_answers_view)).set_visible(cx, poll_answers.is_some());
+        self.poll_answers_editor(cx, ids!(poll_answers_editor))
+            .set_answers(cx, poll_answers.unwrap_or_default());
         self.info = Some(EditingPaneInfo {
             event_tl_item,
             timeline_kind,
@@ -673,4 +725,6 @@ impl EditingPaneRef {
 pub struct EditingPaneState {
     event_tl_item: EventTimelineItem,
     text_input_state: TextInputState,
+    /// The edited answers, if the event being edited is a poll.
+    poll_answers: Option<Vec<PollAnswerDraft>>,
 }
@@ -1,10 +1,11 @@
+use crate::avatar_cache::{self, AvatarCacheEntry};
 use crate::home::room_screen::RoomScreenTooltipActions;
 use crate::profile::user_profile_cache;
 use crate::sliding_sync::{current_user_id, submit_async_request, MatrixRequest, TimelineKind};
 use indexmap::IndexMap;
 use makepad_widgets::*;
-use crate::{LivePtr, widget_ref_from_live_ptr};
-use matrix_sdk::ruma::{OwnedRoomId, OwnedUserId};
+use crate::{LivePtr, utils, widget_ref_from_live_ptr};
+use matrix_sdk::ruma::{OwnedMxcUri, OwnedRoomId, OwnedUserId};
 use matrix_sdk_ui::timeline::{ReactionInfo, ReactionsByKeyBySender, TimelineEventItemId};
 
 const EMOJI_BORDER_COLOR_INCLUDE_SELF: Vec4 = Vec4 {
@@ -96,7 +97,61 @@ script_mod! {
                 }
             }
         }
-    }
+
+        // A reaction with a custom emoji image (an `mxc://` URI) as its key.
+        // This must look the same as the above `item` template.
+        image_item: View {
+            width: Fit,
+            height: Fit,
+            padding: Inset{ top: 4, bottom: 4, left: 6, right: 6 },
+            margin: Inset{ top: 3, bottom: 3, left: 0, right: 6 },
+            flow: Right,
+            spacing: 5,
+            align: Align{ y: 0.5 }
+            show_bg: true
+
+            draw_bg +: {
+                reaction_bg_color: instance(mod.widgets.COLOR_BUTTON_GREY)
+                reaction_border_color: instance(#001A11)
+                color_hover: #fef65b
+                hover: instance(0.0)
+                border_size: 1.5
+                border_radius: 3.0
+
+                get_color: fn() -> vec4 {
+                    return mix(self.reaction_bg_color, mix(self.reaction_bg_color, self.color_hover, 0.2), self.hover)
+                }
+
+                pixel: fn() {
+                    let sdf = Sdf2d.viewport(self.pos * self.rect_size)
+                    sdf.box(
+                        self.border_size,
+                        self.border_size,
+                        self.rect_size.x - self.border_size * 2.0,
+                        self.rect_size.y - self.border_size * 2.0,
+                        max(1.0, self.border_radius)
+                    )
+                    sdf.fill_keep(self.get_color())
+                    if self.border_size > 0.0 {
+                        sdf.stroke(self.reaction_border_color, self.border_size)
+                    }
+                    return sdf.result;
+                }
+            }
+
+            image := Image {
+                width: 16, height: 16,
+                fit: ImageFit.Smallest,
+            }
+
+            count := Label {
+                draw_text +: {
+                    text_style: REGULAR_TEXT {font_size: 10},
+                    color: #000000
+                }
+                text: ""
+            }
+        }
 
 }
 #[derive(Clone, Debug)]
@@ -111,12 +166,24 @@ pub struct ReactionData {
     pub room_id: OwnedRoomId,
 }
 
+impl ReactionData {
+    /// Returns the `mxc://` URI of this reaction's custom emoji image,
+    /// if this reaction is a custom emoji rather than a regular emoji.
+    pub fn custom_emoji_uri(&self) -> Option<OwnedMxcUri> {
+        self.reaction.starts_with("mxc://").then(|| self.reaction.as_str().into())
+    }
+}
+
 #[derive(Script, ScriptHook, Widget)]
 pub struct ReactionList {
     #[uid] uid: WidgetUid,
     #[redraw] #[rust] area: Area,
     #[live] item: Option<LivePtr>,
-    #[rust] children: Vec<(ButtonRef, ReactionData)>,
+    #[live] image_item: Option<LivePtr>,
+    /// Each reaction's widget, which is either an `item` button or an `image_item` view.
+    #[rust] children: Vec<(WidgetRef, ReactionData)>,
+    /// Whether any custom emoji reaction images are still being fetched.
+    #[rust] images_pending: bool,
     #[layout] layout: Layout,
     #[walk] walk: Walk,
 
@@ -134,6 +201,11 @@ impl Widget for ReactionList {
     }
 
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        // The avatar cache signals the UI once a requested custom emoji image has been fetched.
+        if matches!(event, Event::Signal) && self.images_pending {
+            self.load_reaction_images(cx);
+        }
+
         for (button_ref, reaction_data) in self.children.iter() {
             let button_area = button_ref.area();
             // Note: the `break` statements are used to break out of the loop over
@@ -197,12 +269,28 @@ impl Widget for ReactionList {
 }
 
 impl ReactionList {
+    /// Loads the images of any custom emoji reactions that haven't yet been loaded.
+    fn load_reaction_images(&mut self, cx: &mut Cx) {
+        self.images_pending = false;
+        for (widget, reaction_data) in self.children.iter() {
+            let Some(mxc_uri) = reaction_data.custom_emoji_uri() else { continue };
+            match avatar_cache::get_or_fetch_avatar(cx, &mxc_uri) {
+                AvatarCacheEntry::Loaded(data) => {
+                    let _ = utils::load_png_or_jpg(&widget.image(cx, ids!(image)), cx, &data);
+                }
+                AvatarCacheEntry::Requested => self.images_pending = true,
+                AvatarCacheEntry::Failed => { }
+            }
+        }
+        self.redraw(cx);
+    }
+
     /// Deals with to any event/hit that triggers a hover-in action.
     fn do_hover_in(
         &self,
         cx: &mut Cx,
         _scope: &mut Scope,
-        button_ref: &ButtonRef,
+        button_ref: &WidgetRef,
         reaction_data: ReactionData,
     ) {
         cx.widget_action(
@@ -222,7 +310,7 @@ impl ReactionList {
         &self,
         cx: &mut Cx,
         _scope: &mut Scope,
-        button_ref: &ButtonRef,
+        button_ref: &WidgetRef,
     ) {
         cx.widget_action(self.widget_uid(),  RoomScreenTooltipActions::HoverOut);
         let mut button_ref = button_ref.clone();
@@ -288,11 +376,18 @@ impl ReactionListRef {
                 reaction_senders: reaction_senders.clone(),
                 room_id: timeline_kind.room_id().clone(),
             };
-            let mut button = widget_ref_from_live_ptr(cx, inner.item).as_button();
-            button.set_text(cx, &format!("{}  {}",
-                reaction_data.reaction,
-                reaction_senders.len()
-            ));
+            let mut button = if reaction_data.custom_emoji_uri().is_some() {
+                let image_item = widget_ref_from_live_ptr(cx, inner.image_item);
+                image_item.label(cx, ids!(count)).set_text(cx, &reaction_senders.len().to_string());
+                image_item
+            } else {
+                let button = widget_ref_from_live_ptr(cx, inner.item);
+                button.as_button().set_text(cx, &format!("{}  {}",
+                    reaction_data.reaction,
+                    reaction_senders.len()
+                ));
+                button
+            };
             let (bg_color, border_color) = if reaction_data.includes_user {
                 (EMOJI_BG_COLOR_INCLUDE_SELF, EMOJI_BORDER_COLOR_INCLUDE_SELF)
             } else {
@@ -308,6 +403,7 @@ impl ReactionListRef {
         }
         inner.timeline_kind = Some(timeline_kind);
         inner.timeline_event_id = Some(timeline_event_item_id);
+        inner.load_reaction_images(cx);
     }
 
     /// Returns any `RoomScreenTooltipActions` that occurred in the given list of `actions`.
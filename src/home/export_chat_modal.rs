@@ -0,0 +1,366 @@
+//! A modal dialog for exporting a room's full history to a file,
+//! which shows the progress of the export and allows cancelling it.
+//!
+//! The export itself runs in the background (see [`crate::chat_export`]),
+//! so this modal can be closed and reopened while an export is in progress.
+
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use makepad_widgets::*;
+
+use crate::{
+    chat_export::{ChatExportAction, ChatExportFormat, ChatExportOptions},
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    sliding_sync::{MatrixRequest, submit_async_request},
+    utils::RoomNameId,
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.ExportChatModalLabel = Label {
+        width: Fill
+        height: Fit
+        draw_text +: {
+            text_style: REGULAR_TEXT { font_size: 10.5 }
+            color: #333
+        }
+        text: ""
+    }
+
+    mod.widgets.ExportChatModal = #(ExportChatModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 430
+            height: Fit
+            align: Align{x: 0.5}
+            flow: Down
+            padding: Inset{top: 26, right: 22, bottom: 18, left: 22}
+            spacing: 14
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 6.0
+            }
+
+            title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 13 }
+                    color: #000
+                }
+                text: "Export Chat"
+            }
+
+            body := mod.widgets.ExportChatModalLabel {
+                text: ""
+            }
+
+            format_label := mod.widgets.ExportChatModalLabel {
+                text: "Format"
+            }
+
+            format_radio_buttons := View {
+                width: Fit
+                height: Fit
+                spacing: 20
+
+                format_html := RadioButtonFlat {
+                    text: "HTML"
+                    animator: { active: { default: on } }
+                }
+                format_json := RadioButtonFlat {
+                    text: "JSON Lines"
+                }
+                format_text := RadioButtonFlat {
+                    text: "Plain Text"
+                }
+            }
+
+            attachments_row := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{y: 0.5}
+                spacing: 4
+
+                attachments_toggle := Toggle {
+                    width: Fit
+                    height: Fit
+                    padding: 6
+                    text: ""
+                    active: false
+                    draw_bg +: {
+                        size: 18.0
+                        color_active: (COLOR_ACTIVE_PRIMARY)
+                        border_color_active: (COLOR_ACTIVE_PRIMARY)
+                        mark_color_active: #fff
+                    }
+                }
+
+                attachments_label := mod.widgets.ExportChatModalLabel {
+                    text: "Also download attachments"
+                }
+            }
+
+            status_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.2 }
+                    color: #000
+                }
+                text: ""
+            }
+
+            buttons := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{x: 1.0, y: 0.5}
+                spacing: 16
+
+                cancel_button := RobrixNeutralIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORBIDDEN)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Close"
+                }
+
+                export_button := RobrixPositiveIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_UPLOAD)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Export"
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ExportChatModalAction {
+    /// Open the modal to export the given room.
+    Open(RoomNameId),
+    /// Close the modal. This does not cancel an export that is in progress.
+    Close,
+}
+
+/// An export that is running in the background.
+struct RunningExport {
+    room_name_id: RoomNameId,
+    cancel_token: Arc<AtomicBool>,
+}
+
+#[derive(Script, ScriptHook, Widget)]
+pub struct ExportChatModal {
+    #[deref]
+    view: View,
+    /// The room that will be exported when the user clicks the export button.
+    #[rust]
+    room_name_id: Option<RoomNameId>,
+    #[rust]
+    format: ChatExportFormat,
+    /// The export that is currently in progress, if any.
+    ///
+    /// Only one room can be exported at a time.
+    #[rust]
+    running_export: Option<RunningExport>,
+    #[rust]
+    app_language: AppLanguage,
+}
+
+impl Widget for ExportChatModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for ExportChatModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let cancel_button = self.view.button(cx, ids!(buttons.cancel_button));
+        let export_button = self.view.button(cx, ids!(buttons.export_button));
+
+        if cancel_button.clicked(actions) {
+            // While an export is running, the cancel button cancels it instead of closing the modal.
+            if let Some(running_export) = self.running_export.as_ref() {
+                running_export.cancel_token.store(true, Ordering::Relaxed);
+                self.set_status(cx, tr_key(self.app_language, "export_chat_modal.status.cancelling"));
+                cancel_button.set_enabled(cx, false);
+                return;
+            }
+            cx.action(ExportChatModalAction::Close);
+            return;
+        }
+        if actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed))) {
+            cx.action(ExportChatModalAction::Close);
+            return;
+        }
+
+        let format_radio_buttons = self.view.radio_button_set(cx, ids_array!(
+            format_radio_buttons.format_html,
+            format_radio_buttons.format_json,
+            format_radio_buttons.format_text,
+        ));
+        match format_radio_buttons.selected(cx, actions) {
+            Some(0) => self.format = ChatExportFormat::Html,
+            Some(1) => self.format = ChatExportFormat::JsonLines,
+            Some(2) => self.format = ChatExportFormat::PlainText,
+            _ => { }
+        }
+
+        if export_button.clicked(actions) && self.running_export.is_none() {
+            let Some(room_name_id) = self.room_name_id.clone() else { return };
+            let cancel_token = Arc::new(AtomicBool::new(false));
+            submit_async_request(MatrixRequest::ExportRoomHistory {
+                room_id: room_name_id.room_id().clone(),
+                room_name: room_name_id.to_string(),
+                options: ChatExportOptions {
+                    format: self.format,
+                    include_attachments: self.view
+                        .check_box(cx, ids!(attachments_row.attachments_toggle))
+                        .active(cx),
+                },
+                cancel_token: cancel_token.clone(),
+            });
+            self.running_export = Some(RunningExport { room_name_id, cancel_token });
+            self.set_status(cx, tr_key(self.app_language, "export_chat_modal.status.starting"));
+            self.update_buttons(cx);
+        }
+    }
+}
+
+impl ExportChatModal {
+    /// Populates this modal to export the given room.
+    ///
+    /// If an export is already in progress, its progress is shown instead.
+    pub fn show(&mut self, cx: &mut Cx, room_name_id: RoomNameId, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.update_static_texts(cx);
+        if self.running_export.is_some() {
+            self.update_buttons(cx);
+            return;
+        }
+
+        self.view.label(cx, ids!(body)).set_text(
+            cx,
+            &tr_fmt(app_language, "export_chat_modal.body", &[("room", &room_name_id.to_string())]),
+        );
+        self.room_name_id = Some(room_name_id);
+        self.format = ChatExportFormat::default();
+        self.view
+            .radio_button(cx, ids!(format_radio_buttons.format_html))
+            .select(cx, &mut Scope::empty());
+        self.view
+            .check_box(cx, ids!(attachments_row.attachments_toggle))
+            .set_active(cx, false);
+        self.set_status(cx, "");
+        self.update_buttons(cx);
+    }
+
+    /// Updates this modal to reflect the progress or result of the running export.
+    fn handle_export_action(&mut self, cx: &mut Cx, action: &ChatExportAction) {
+        let Some(running_export) = self.running_export.as_ref() else { return };
+        let room_id = match action {
+            ChatExportAction::Progress { room_id, .. }
+            | ChatExportAction::Finished { room_id, .. }
+            | ChatExportAction::Cancelled { room_id }
+            | ChatExportAction::Failed { room_id, .. } => room_id,
+        };
+        if running_export.room_name_id.room_id() != room_id {
+            return;
+        }
+
+        if let ChatExportAction::Progress { num_events, .. } = action {
+            if !running_export.cancel_token.load(Ordering::Relaxed) {
+                self.set_status(cx, &tr_fmt(
+                    self.app_language,
+                    "export_chat_modal.status.progress",
+                    &[("count", &num_events.to_string())],
+                ));
+            }
+            return;
+        }
+        self.running_export = None;
+        self.set_status(cx, "");
+        self.update_buttons(cx);
+    }
+
+    fn set_status(&mut self, cx: &mut Cx, status: &str) {
+        self.view.label(cx, ids!(status_label)).set_text(cx, status);
+        self.view.redraw(cx);
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        let app_language = self.app_language;
+        self.view
+            .label(cx, ids!(title))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.title"));
+        self.view
+            .label(cx, ids!(format_label))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.format.label"));
+        self.view
+            .radio_button(cx, ids!(format_radio_buttons.format_html))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.format.html"));
+        self.view
+            .radio_button(cx, ids!(format_radio_buttons.format_json))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.format.json"));
+        self.view
+            .radio_button(cx, ids!(format_radio_buttons.format_text))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.format.text"));
+        self.view
+            .label(cx, ids!(attachments_row.attachments_label))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.include_attachments"));
+        self.view
+            .button(cx, ids!(buttons.export_button))
+            .set_text(cx, tr_key(app_language, "export_chat_modal.button.export"));
+    }
+
+    /// Updates the buttons to reflect whether an export is in progress.
+    fn update_buttons(&mut self, cx: &mut Cx) {
+        let is_exporting = self.running_export.is_some();
+        let cancel_button = self.view.button(cx, ids!(buttons.cancel_button));
+        let export_button = self.view.button(cx, ids!(buttons.export_button));
+        cancel_button.set_text(cx, tr_key(
+            self.app_language,
+            if is_exporting { "export_chat_modal.button.cancel_export" } else { "export_chat_modal.button.close" },
+        ));
+        cancel_button.set_enabled(cx, true);
+        export_button.set_enabled(cx, !is_exporting);
+        cancel_button.reset_hover(cx);
+        export_button.reset_hover(cx);
+        self.view.redraw(cx);
+    }
+}
+
+impl ExportChatModalRef {
+    /// See [`ExportChatModal::show()`].
+    pub fn show(&self, cx: &mut Cx, room_name_id: RoomNameId, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.show(cx, room_name_id, app_language);
+    }
+
+    /// See [`ExportChatModal::handle_export_action()`].
+    pub fn handle_export_action(&self, cx: &mut Cx, action: &ChatExportAction) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.handle_export_action(cx, action)
+    }
+}
@@ -0,0 +1,487 @@
+//! A modal dialog for forwarding a message to one or more other rooms.
+//!
+//! The modal shows a searchable list of the user's joined rooms,
+//! filtered with the same [`RoomDisplayFilterBuilder`] logic as the main rooms list,
+//! in which the user can select any number of target rooms.
+//!
+//! [`RoomDisplayFilterBuilder`]: crate::room::room_display_filter::RoomDisplayFilterBuilder
+
+use std::collections::HashSet;
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::{OwnedRoomId, events::room::message::RoomMessageEventContent};
+
+use crate::{
+    LivePtr, widget_ref_from_live_ptr,
+    home::rooms_list::RoomsListRef,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    room::FetchedRoomAvatar,
+    shared::{avatar::AvatarWidgetRefExt, styles::*},
+    sliding_sync::{MatrixRequest, submit_async_request},
+    utils::{self, RoomNameId},
+};
+
+/// The maximum number of rooms shown in the room list at once.
+const MAX_ROOMS_SHOWN: usize = 50;
+
+const COLOR_ROOM_BG: Vec4 = vec4(0.0, 0.0, 0.0, 0.0);
+const COLOR_ROOM_BG_HOVER: Vec4 = COLOR_BG_PREVIEW;
+const COLOR_ROOM_BG_SELECTED: Vec4 = COLOR_BG_PREVIEW_HOVER;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.ForwardRoomList = #(ForwardRoomList::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 2.0
+
+        room: RoundedView {
+            width: Fill,
+            height: 44,
+            flow: Right,
+            align: Align{y: 0.5}
+            spacing: 8
+            padding: Inset{left: 8, right: 8, top: 5, bottom: 5}
+            show_bg: true
+            draw_bg +: {
+                color: #0000
+                border_radius: 4.0
+            }
+
+            avatar := Avatar { width: 30, height: 30 }
+
+            name_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    color: (COLOR_TEXT)
+                    text_style: REGULAR_TEXT {font_size: 10}
+                }
+                text: ""
+            }
+
+            checkmark := Icon {
+                visible: false
+                width: Fit,
+                height: Fit,
+                draw_icon +: {
+                    svg: (ICON_CHECKMARK),
+                    color: (COLOR_ACTIVE_PRIMARY),
+                }
+                icon_walk: Walk{ width: 16, height: 16 }
+            }
+        }
+    }
+
+    mod.widgets.ForwardMessageModal = #(ForwardMessageModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 430
+            height: Fit
+            align: Align{x: 0.5}
+            flow: Down
+            padding: Inset{top: 26, right: 22, bottom: 18, left: 22}
+            spacing: 12
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 6.0
+            }
+
+            title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 13 }
+                    color: #000
+                }
+                text: "Forward Message"
+            }
+
+            preview := RoundedView {
+                width: Fill
+                height: Fit
+                padding: 10
+                show_bg: true
+                draw_bg +: {
+                    color: (COLOR_BG_PREVIEW)
+                    border_radius: 4.0
+                }
+
+                preview_label := Label {
+                    width: Fill
+                    height: Fit
+                    draw_text +: {
+                        text_style: REGULAR_TEXT { font_size: 10 }
+                        color: #333
+                    }
+                    text: ""
+                }
+            }
+
+            search_input := RobrixTextInput {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_TEXT {font_size: 11},
+                    color: #000
+                }
+                empty_text: "",
+            }
+
+            rooms_scroll := ScrollYView {
+                width: Fill
+                height: 260
+                flow: Down
+
+                room_list := mod.widgets.ForwardRoomList { }
+
+                no_rooms_label := Label {
+                    visible: false
+                    width: Fill
+                    height: Fit
+                    margin: Inset{top: 10, left: 1}
+                    draw_text +: {
+                        text_style: REGULAR_TEXT {font_size: 9.5}
+                        color: #6D7682
+                    }
+                    text: ""
+                }
+            }
+
+            selected_count_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.2 }
+                    color: #000
+                }
+                text: ""
+            }
+
+            buttons := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{x: 1.0, y: 0.5}
+                spacing: 16
+
+                cancel_button := RobrixNeutralIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORBIDDEN)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Cancel"
+                }
+
+                forward_button := RobrixPositiveIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORWARD)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Forward"
+                }
+            }
+        }
+    }
+}
+
+/// Widget actions emitted by a [`ForwardRoomList`].
+#[derive(Clone, Debug, Default)]
+pub enum ForwardRoomListAction {
+    /// The user selected or deselected a room; the set of selected rooms has changed.
+    SelectionChanged,
+    #[default]
+    None,
+}
+
+/// A list of rooms in which the user can select any number of rooms.
+///
+/// Rooms remain selected when they are not shown, e.g., while the list is filtered.
+#[derive(Script, ScriptHook, Widget)]
+pub struct ForwardRoomList {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] room: Option<LivePtr>,
+    /// Each row's widget alongside the ID of the room it shows.
+    #[rust] children: Vec<(WidgetRef, OwnedRoomId)>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+
+    #[rust] selected: HashSet<OwnedRoomId>,
+}
+
+impl Widget for ForwardRoomList {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+        for (widget, _room_id) in self.children.iter() {
+            widget.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        let mut toggled = None;
+        for (widget, room_id) in self.children.iter() {
+            // Note: the `break` statements are used to break out of the loop over
+            // all rooms, since a hit event can only occur on one room.
+            match event.hits(cx, widget.area()) {
+                Hit::FingerHoverIn(_) => {
+                    if !self.selected.contains(room_id) {
+                        set_room_bg_color(cx, widget, COLOR_ROOM_BG_HOVER);
+                    }
+                    cx.set_cursor(MouseCursor::Hand);
+                    break;
+                }
+                Hit::FingerHoverOut(_) => {
+                    if !self.selected.contains(room_id) {
+                        set_room_bg_color(cx, widget, COLOR_ROOM_BG);
+                    }
+                    break;
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.is_primary_hit() && fe.was_tap() => {
+                    toggled = Some(room_id.clone());
+                    break;
+                }
+                _ => {}
+            }
+        }
+        if let Some(room_id) = toggled {
+            if !self.selected.remove(&room_id) {
+                self.selected.insert(room_id);
+            }
+            self.update_room_styles(cx);
+            cx.widget_action(self.widget_uid(), ForwardRoomListAction::SelectionChanged);
+        }
+    }
+}
+
+impl ForwardRoomList {
+    /// Updates the background and checkmark of each room to show whether it's selected.
+    fn update_room_styles(&mut self, cx: &mut Cx) {
+        for (widget, room_id) in self.children.iter() {
+            let is_selected = self.selected.contains(room_id);
+            set_room_bg_color(cx, widget, if is_selected { COLOR_ROOM_BG_SELECTED } else { COLOR_ROOM_BG });
+            widget.widget(cx, ids!(checkmark)).set_visible(cx, is_selected);
+        }
+        self.redraw(cx);
+    }
+}
+
+impl ForwardRoomListRef {
+    /// Shows the given rooms, keeping the current selection.
+    fn set_rooms(&self, cx: &mut Cx, rooms: Vec<(RoomNameId, FetchedRoomAvatar)>) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.children.clear();
+        for (room_name_id, room_avatar) in rooms {
+            let widget = widget_ref_from_live_ptr(cx, inner.room);
+            let avatar_ref = widget.avatar(cx, ids!(avatar));
+            let fallback_text = room_name_id.name_for_avatar().unwrap_or("?");
+            match &room_avatar {
+                FetchedRoomAvatar::Text(text) => {
+                    avatar_ref.show_text(cx, None, None, text);
+                }
+                FetchedRoomAvatar::Image(image_data) => {
+                    let res = avatar_ref.show_image(
+                        cx,
+                        None,
+                        |cx, img_ref| utils::load_png_or_jpg(&img_ref, cx, image_data),
+                    );
+                    if res.is_err() {
+                        avatar_ref.show_text(cx, None, None, fallback_text);
+                    }
+                }
+            }
+            widget.label(cx, ids!(name_label)).set_text(cx, &room_name_id.to_string());
+            inner.children.push((widget, room_name_id.room_id().clone()));
+        }
+        inner.update_room_styles(cx);
+    }
+
+    /// Deselects all rooms.
+    fn clear_selection(&self, cx: &mut Cx) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.selected.clear();
+        inner.update_room_styles(cx);
+    }
+
+    /// Returns the IDs of all selected rooms.
+    fn selected_room_ids(&self) -> Vec<OwnedRoomId> {
+        self.borrow()
+            .map(|inner| inner.selected.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Sets the background color of the given room row, e.g., to show that it's selected.
+fn set_room_bg_color(cx: &mut Cx, room_ref: &WidgetRef, color: Vec4) {
+    let mut room_ref = room_ref.clone();
+    script_apply_eval!(cx, room_ref, {
+        draw_bg.color: #(color)
+    });
+}
+
+
+/// Actions handled by the top-level App to show or hide the [`ForwardMessageModal`].
+#[derive(Clone, Debug)]
+pub enum ForwardMessageModalAction {
+    /// Open the modal to forward a message with the given content.
+    Open {
+        content: Box<RoomMessageEventContent>,
+        /// A plaintext preview of the message being forwarded.
+        preview_text: String,
+    },
+    /// Close the modal.
+    Close,
+}
+
+#[derive(Script, ScriptHook, Widget)]
+pub struct ForwardMessageModal {
+    #[deref]
+    view: View,
+    /// The content of the message to be forwarded.
+    #[rust]
+    content: Option<RoomMessageEventContent>,
+    #[rust]
+    app_language: AppLanguage,
+}
+
+impl Widget for ForwardMessageModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for ForwardMessageModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let cancel_button = self.view.button(cx, ids!(buttons.cancel_button));
+        let forward_button = self.view.button(cx, ids!(buttons.forward_button));
+        let room_list = self.view.forward_room_list(cx, ids!(rooms_scroll.room_list));
+
+        if cancel_button.clicked(actions)
+            || actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            cx.action(ForwardMessageModalAction::Close);
+            return;
+        }
+
+        if let Some(keywords) = self.view.text_input(cx, ids!(search_input)).changed(actions) {
+            self.show_matching_rooms(cx, &keywords);
+        }
+
+        if let ForwardRoomListAction::SelectionChanged = actions.find_widget_action(room_list.widget_uid()).cast() {
+            self.update_selected_count(cx);
+        }
+
+        if forward_button.clicked(actions) {
+            let target_room_ids = room_list.selected_room_ids();
+            if target_room_ids.is_empty() { return }
+            let Some(content) = self.content.take() else { return };
+            submit_async_request(MatrixRequest::ForwardMessage { content, target_room_ids });
+            cx.action(ForwardMessageModalAction::Close);
+        }
+    }
+}
+
+impl ForwardMessageModal {
+    /// Populates this modal to forward a message with the given content.
+    pub fn show(
+        &mut self,
+        cx: &mut Cx,
+        content: RoomMessageEventContent,
+        preview_text: &str,
+        app_language: AppLanguage,
+    ) {
+        self.app_language = app_language;
+        self.content = Some(content);
+        self.update_static_texts(cx);
+        self.view.label(cx, ids!(preview.preview_label)).set_text(cx, preview_text);
+
+        let search_input = self.view.text_input(cx, ids!(search_input));
+        search_input.set_text(cx, "");
+        search_input.set_key_focus(cx);
+        self.view.forward_room_list(cx, ids!(rooms_scroll.room_list)).clear_selection(cx);
+        self.show_matching_rooms(cx, "");
+        self.update_selected_count(cx);
+    }
+
+    /// Shows the joined rooms that match the given search keywords.
+    fn show_matching_rooms(&mut self, cx: &mut Cx, keywords: &str) {
+        let rooms = cx.get_global::<RoomsListRef>().get_sendable_joined_rooms(keywords, MAX_ROOMS_SHOWN);
+        let no_rooms_label = self.view.label(cx, ids!(rooms_scroll.no_rooms_label));
+        no_rooms_label.set_visible(cx, rooms.is_empty());
+        if rooms.is_empty() {
+            no_rooms_label.set_text(cx, tr_key(self.app_language, "forward_message_modal.no_rooms"));
+        }
+        self.view.forward_room_list(cx, ids!(rooms_scroll.room_list)).set_rooms(cx, rooms);
+        self.view.redraw(cx);
+    }
+
+    /// Updates the label showing how many rooms are selected, and enables the forward button
+    /// only if at least one room is selected.
+    fn update_selected_count(&mut self, cx: &mut Cx) {
+        let num_selected = self.view
+            .forward_room_list(cx, ids!(rooms_scroll.room_list))
+            .selected_room_ids()
+            .len();
+        let text = if num_selected == 0 {
+            tr_key(self.app_language, "forward_message_modal.none_selected").to_string()
+        } else {
+            tr_fmt(
+                self.app_language,
+                "forward_message_modal.num_selected",
+                &[("count", &num_selected.to_string())],
+            )
+        };
+        self.view.label(cx, ids!(selected_count_label)).set_text(cx, &text);
+        let forward_button = self.view.button(cx, ids!(buttons.forward_button));
+        forward_button.set_enabled(cx, num_selected > 0);
+        forward_button.reset_hover(cx);
+        self.view.redraw(cx);
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        let app_language = self.app_language;
+        self.view
+            .label(cx, ids!(title))
+            .set_text(cx, tr_key(app_language, "forward_message_modal.title"));
+        self.view
+            .text_input(cx, ids!(search_input))
+            .set_empty_text(cx, tr_key(app_language, "forward_message_modal.search_placeholder").to_string());
+        self.view
+            .button(cx, ids!(buttons.cancel_button))
+            .set_text(cx, tr_key(app_language, "forward_message_modal.button.cancel"));
+        self.view
+            .button(cx, ids!(buttons.forward_button))
+            .set_text(cx, tr_key(app_language, "forward_message_modal.button.forward"));
+    }
+}
+
+impl ForwardMessageModalRef {
+    /// See [`ForwardMessageModal::show()`].
+    pub fn show(
+        &self,
+        cx: &mut Cx,
+        content: RoomMessageEventContent,
+        preview_text: &str,
+        app_language: AppLanguage,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.show(cx, content, preview_text, app_language);
+    }
+}
@@ -134,6 +134,19 @@ script_mod! {
                         button_spacer := View {
                             width: Fill, height: Fill
                         }
+                        date_button := ButtonFlatterIcon {
+                            visible: false
+                            width: 44, height: Fill,
+                            padding: 0,
+                            margin: 0
+                            draw_icon +: {
+                                color: (ROOM_NAME_TEXT_COLOR)
+                                svg: (ICON_CALENDAR)
+                            }
+                            icon_walk: Walk{width: 16, height: Fit}
+                            spacing: 0
+                            text: ""
+                        }
                         right_button := ButtonFlatterIcon {
                             visible: false
                             width: 56, height: Fill,
@@ -151,7 +164,7 @@ script_mod! {
                     title_container +: {
                     width: Fill
                     height: Fill
-                    padding: Inset{top: 0, left: 56, right: 56}
+                    padding: Inset{top: 0, left: 100, right: 100}
                     align: Align{x: 0.5, y: 0.5}
                     title +: {
                         width: Fill
@@ -479,7 +492,7 @@ impl Widget for HomeScreen {
                             if let Some(settings_page) = self.update_active_page_from_selection(cx, app_state) {
                                 settings_page
                                     .settings_screen(cx, ids!(settings_screen))
-                                    .populate(cx, None, &app_state.bot_settings, &app_state.translation, app_state.app_language);
+                                    .populate(cx, None, &app_state.bot_settings, &app_state.translation, app_state.local_search_index_enabled, app_state.app_language);
                                 self.view.redraw(cx);
                             } else {
                                 error!("BUG: failed to set active page to show settings screen.");
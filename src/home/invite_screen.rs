@@ -314,6 +314,7 @@ impl Widget for InviteScreen {
                 if modifiers.shift {
                     submit_async_request(MatrixRequest::JoinRoom {
                         room_id: info.room_id().clone(),
+                        via: Vec::new(),
                     });
                     self.has_shown_confirmation = false;
                 } else {
@@ -0,0 +1,587 @@
+//! A modal with a month calendar for jumping to the messages sent on a given date.
+//!
+//! The `JumpToDateModal` is opened from the room header on mobile
+//! and from the `RoomContextMenu` in the rooms list.
+//! Once a date is chosen, the homeserver is asked (via [`MatrixRequest::JumpToDate`])
+//! for the first event on that date, which the `RoomScreen` then jumps to
+//! using the same machinery as jumping to a replied-to message.
+
+use chrono::{Datelike, Local, Months, NaiveDate, TimeZone};
+use makepad_widgets::*;
+use matrix_sdk::ruma::{MilliSecondsSinceUnixEpoch, UInt};
+
+use crate::{
+    LivePtr, widget_ref_from_live_ptr,
+    app::AppState,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    shared::styles::*,
+    sliding_sync::{submit_async_request, MatrixRequest, TimelineKind},
+};
+
+/// The number of cells in a calendar page: six weeks of seven days.
+const NUM_CALENDAR_CELLS: usize = 42;
+
+const COLOR_DAY_BG: Vec4 = vec4(0.0, 0.0, 0.0, 0.0);
+const COLOR_DAY_BG_HOVER: Vec4 = COLOR_BG_PREVIEW;
+const COLOR_DAY_BG_SELECTED: Vec4 = COLOR_ACTIVE_PRIMARY;
+/// #1C274C, the same as `COLOR_TEXT` in the styles DSL.
+const COLOR_DAY_TEXT: Vec4 = vec4(0.11, 0.153, 0.298, 1.0);
+const COLOR_DAY_TEXT_SELECTED: Vec4 = COLOR_PRIMARY;
+const COLOR_DAY_TEXT_DISABLED: Vec4 = COLOR_FG_DISABLED;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.WeekdayLabel = Label {
+        width: 36,
+        height: Fit,
+        align: Align{x: 0.5}
+        draw_text +: {
+            text_style: REGULAR_TEXT { font_size: 9.5 }
+            color: (COLOR_DISABLED_TEXT)
+        }
+        text: ""
+    }
+
+    mod.widgets.CalendarGrid = #(CalendarGrid::register_widget(vm)) {
+        width: 264,
+        height: Fit,
+        flow: Flow.Right{wrap: true},
+        spacing: 2.0
+        line_spacing: 2.0
+
+        day: RoundedView {
+            width: 36,
+            height: 32,
+            align: Align{x: 0.5, y: 0.5}
+            show_bg: true
+            draw_bg +: {
+                color: #0000
+                border_radius: 4.0
+            }
+
+            day_label := Label {
+                width: Fit,
+                height: Fit,
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 11 }
+                    color: (COLOR_TEXT)
+                }
+                text: ""
+            }
+        }
+    }
+
+    mod.widgets.JumpToDateModal = #(JumpToDateModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: Fit
+            height: Fit
+            align: Align{x: 0.5}
+            flow: Down
+            padding: Inset{top: 24, right: 24, bottom: 20, left: 24}
+            spacing: 12
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 6.0
+            }
+
+            title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 13 }
+                    color: #000
+                }
+                text: "Jump to Date"
+            }
+
+            month_row := View {
+                width: 264
+                height: Fit
+                flow: Right
+                align: Align{x: 0.5, y: 0.5}
+
+                prev_month_button := RobrixNeutralIconButton {
+                    width: Fit
+                    padding: Inset{top: 6, bottom: 6, left: 10, right: 10}
+                    icon_walk: Walk{width: 0, height: 0}
+                    spacing: 0
+                    text: "‹"
+                }
+
+                month_label := Label {
+                    width: Fill
+                    height: Fit
+                    align: Align{x: 0.5}
+                    draw_text +: {
+                        text_style: REGULAR_TEXT { font_size: 11.5 }
+                        color: #000
+                    }
+                    text: ""
+                }
+
+                next_month_button := RobrixNeutralIconButton {
+                    width: Fit
+                    padding: Inset{top: 6, bottom: 6, left: 10, right: 10}
+                    icon_walk: Walk{width: 0, height: 0}
+                    spacing: 0
+                    text: "›"
+                }
+            }
+
+            weekdays := View {
+                width: 264
+                height: Fit
+                flow: Right
+                spacing: 2.0
+
+                weekday_0 := mod.widgets.WeekdayLabel { }
+                weekday_1 := mod.widgets.WeekdayLabel { }
+                weekday_2 := mod.widgets.WeekdayLabel { }
+                weekday_3 := mod.widgets.WeekdayLabel { }
+                weekday_4 := mod.widgets.WeekdayLabel { }
+                weekday_5 := mod.widgets.WeekdayLabel { }
+                weekday_6 := mod.widgets.WeekdayLabel { }
+            }
+
+            day_grid := mod.widgets.CalendarGrid { }
+
+            selected_date_label := Label {
+                width: 264
+                height: Fit
+                flow: Flow.Right{wrap: true},
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.5 }
+                    color: #333
+                }
+                text: ""
+            }
+
+            buttons := View {
+                width: 264
+                height: Fit
+                flow: Right
+                align: Align{x: 1.0, y: 0.5}
+                spacing: 12
+
+                cancel_button := RobrixNeutralIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORBIDDEN)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Cancel"
+                }
+
+                jump_button := RobrixPositiveIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_JUMP)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Jump"
+                }
+            }
+        }
+    }
+}
+
+/// Returns the first day of the month that contains the given date.
+pub fn start_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Returns the first day of the month that is `delta` months after (or before) the given month.
+pub fn shift_month(month_start: NaiveDate, delta: i32) -> NaiveDate {
+    let shifted = if delta >= 0 {
+        month_start.checked_add_months(Months::new(delta.unsigned_abs()))
+    } else {
+        month_start.checked_sub_months(Months::new(delta.unsigned_abs()))
+    };
+    shifted.map(start_of_month).unwrap_or(month_start)
+}
+
+/// Returns the cells of the calendar page for the month starting at `month_start`:
+/// six weeks starting on Monday, where cells outside of that month are `None`.
+pub fn calendar_cells(month_start: NaiveDate) -> [Option<NaiveDate>; NUM_CALENDAR_CELLS] {
+    let mut cells = [None; NUM_CALENDAR_CELLS];
+    let offset = month_start.weekday().num_days_from_monday() as usize;
+    for (cell, date) in cells.iter_mut().skip(offset).zip(month_start.iter_days()) {
+        if date.month() != month_start.month() {
+            break;
+        }
+        *cell = Some(date);
+    }
+    cells
+}
+
+/// Returns the timestamp of midnight at the start of the given date in the local time zone.
+pub fn start_of_local_day(date: NaiveDate) -> Option<MilliSecondsSinceUnixEpoch> {
+    let midnight = Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest()?;
+    UInt::try_from(midnight.timestamp_millis()).ok().map(MilliSecondsSinceUnixEpoch)
+}
+
+/// Widget actions emitted by a [`CalendarGrid`].
+#[derive(Clone, Debug, Default)]
+pub enum CalendarGridAction {
+    /// The user clicked on the given date.
+    Selected(NaiveDate),
+    #[default]
+    None,
+}
+
+/// A grid of the days in a single month, laid out in weeks starting on Monday.
+#[derive(Script, ScriptHook, Widget)]
+pub struct CalendarGrid {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] day: Option<LivePtr>,
+    /// Each cell's widget alongside its date, which is `None` for cells outside of the month.
+    #[rust] children: Vec<(WidgetRef, Option<NaiveDate>)>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+
+    #[rust] selected: Option<NaiveDate>,
+    /// The earliest date that can be selected, if any.
+    #[rust] min_date: Option<NaiveDate>,
+    /// The latest date that can be selected, if any.
+    #[rust] max_date: Option<NaiveDate>,
+}
+
+impl Widget for CalendarGrid {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+        for (widget, _date) in self.children.iter() {
+            widget.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        for (widget, date) in self.children.iter() {
+            let Some(date) = date.filter(|d| self.is_selectable(*d)) else { continue };
+            // Note: the `break` statements are used to break out of the loop over
+            // all days, since a hit event can only occur on one day.
+            match event.hits(cx, widget.area()) {
+                Hit::FingerHoverIn(_) => {
+                    if self.selected != Some(date) {
+                        set_day_bg_color(cx, widget, COLOR_DAY_BG_HOVER);
+                    }
+                    cx.set_cursor(MouseCursor::Hand);
+                    break;
+                }
+                Hit::FingerHoverOut(_) => {
+                    if self.selected != Some(date) {
+                        set_day_bg_color(cx, widget, COLOR_DAY_BG);
+                    }
+                    break;
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.is_primary_hit() && fe.was_tap() => {
+                    cx.widget_action(self.widget_uid(), CalendarGridAction::Selected(date));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl CalendarGrid {
+    fn is_selectable(&self, date: NaiveDate) -> bool {
+        self.min_date.is_none_or(|min_date| date >= min_date)
+            && self.max_date.is_none_or(|max_date| date <= max_date)
+    }
+
+    /// Updates the colors of each day to show which day is selected and which can't be selected.
+    fn update_day_styles(&mut self, cx: &mut Cx) {
+        for (widget, date) in self.children.iter() {
+            let Some(date) = *date else { continue };
+            let (bg_color, text_color) = if self.selected == Some(date) {
+                (COLOR_DAY_BG_SELECTED, COLOR_DAY_TEXT_SELECTED)
+            } else if self.is_selectable(date) {
+                (COLOR_DAY_BG, COLOR_DAY_TEXT)
+            } else {
+                (COLOR_DAY_BG, COLOR_DAY_TEXT_DISABLED)
+            };
+            set_day_bg_color(cx, widget, bg_color);
+            let mut label = widget.label(cx, ids!(day_label));
+            script_apply_eval!(cx, label, {
+                draw_text +: { color: #(text_color) }
+            });
+        }
+        self.redraw(cx);
+    }
+}
+
+impl CalendarGridRef {
+    /// Shows the days of the month starting at `month_start`,
+    /// highlighting the `selected` date and disallowing dates outside of `min_date..=max_date`.
+    pub fn set_month(
+        &self,
+        cx: &mut Cx,
+        month_start: NaiveDate,
+        selected: Option<NaiveDate>,
+        min_date: Option<NaiveDate>,
+        max_date: Option<NaiveDate>,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.selected = selected;
+        inner.min_date = min_date;
+        inner.max_date = max_date;
+        inner.children.clear();
+        for date in calendar_cells(month_start) {
+            let widget = widget_ref_from_live_ptr(cx, inner.day);
+            let text = date.map(|d| d.day().to_string()).unwrap_or_default();
+            widget.label(cx, ids!(day_label)).set_text(cx, &text);
+            inner.children.push((widget, date));
+        }
+        inner.update_day_styles(cx);
+    }
+
+    /// Highlights the given date as selected.
+    pub fn set_selected(&self, cx: &mut Cx, selected: Option<NaiveDate>) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.selected = selected;
+        inner.update_day_styles(cx);
+    }
+}
+
+/// Sets the background color of the given day, e.g., to show that it's selected.
+fn set_day_bg_color(cx: &mut Cx, day_ref: &WidgetRef, color: Vec4) {
+    let mut day_ref = day_ref.clone();
+    script_apply_eval!(cx, day_ref, {
+        draw_bg.color: #(color)
+    });
+}
+
+/// Returns the widget ID of the label for the given day of the week (0 = Monday).
+pub fn weekday_label_id(weekday: usize) -> &'static [LiveId] {
+    match weekday {
+        0 => ids!(weekdays.weekday_0),
+        1 => ids!(weekdays.weekday_1),
+        2 => ids!(weekdays.weekday_2),
+        3 => ids!(weekdays.weekday_3),
+        4 => ids!(weekdays.weekday_4),
+        5 => ids!(weekdays.weekday_5),
+        _ => ids!(weekdays.weekday_6),
+    }
+}
+
+/// Actions emitted by other widgets to show or hide the `JumpToDateModal`.
+#[derive(Clone, Debug)]
+pub enum JumpToDateModalAction {
+    /// Open the modal to jump to a date in the given timeline.
+    Open {
+        timeline_kind: TimelineKind,
+    },
+    /// Close the modal.
+    Close,
+}
+
+/// A modal for choosing a date to jump to in a room's timeline.
+#[derive(Script, ScriptHook, Widget)]
+pub struct JumpToDateModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    /// The timeline that will jump to the chosen date.
+    #[rust] timeline_kind: Option<TimelineKind>,
+    /// The first day of the month currently shown in the calendar.
+    #[rust] displayed_month: Option<NaiveDate>,
+    #[rust] selected_date: Option<NaiveDate>,
+}
+
+impl Widget for JumpToDateModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for JumpToDateModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let cancel_clicked = self.view.button(cx, ids!(buttons.cancel_button)).clicked(actions);
+        if cancel_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // a `JumpToDateModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if cancel_clicked {
+                cx.action(JumpToDateModalAction::Close);
+            }
+            return;
+        }
+
+        if self.view.button(cx, ids!(month_row.prev_month_button)).clicked(actions) {
+            self.shift_displayed_month(cx, -1);
+        }
+        if self.view.button(cx, ids!(month_row.next_month_button)).clicked(actions) {
+            self.shift_displayed_month(cx, 1);
+        }
+
+        let day_grid = self.view.calendar_grid(cx, ids!(day_grid));
+        if let CalendarGridAction::Selected(date) = actions.find_widget_action(day_grid.widget_uid()).cast() {
+            self.selected_date = Some(date);
+            day_grid.set_selected(cx, Some(date));
+            self.update_selected_date_label(cx);
+        }
+
+        if self.view.button(cx, ids!(buttons.jump_button)).clicked(actions) {
+            let Some(timeline_kind) = self.timeline_kind.clone() else { return };
+            let Some(timestamp) = self.selected_date.and_then(start_of_local_day) else { return };
+            submit_async_request(MatrixRequest::JumpToDate { timeline_kind, timestamp });
+            cx.action(JumpToDateModalAction::Close);
+        }
+    }
+}
+
+impl JumpToDateModal {
+    /// Shows the calendar for the current month, which will jump to a date in the given timeline.
+    pub fn show(&mut self, cx: &mut Cx, timeline_kind: TimelineKind, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.timeline_kind = Some(timeline_kind);
+        let today = Local::now().date_naive();
+        self.selected_date = Some(today);
+        self.displayed_month = Some(start_of_month(today));
+        self.update_static_texts(cx);
+        self.update_calendar(cx);
+        self.view.button(cx, ids!(buttons.jump_button)).reset_hover(cx);
+        self.view.button(cx, ids!(buttons.cancel_button)).reset_hover(cx);
+        self.view.redraw(cx);
+    }
+
+    fn shift_displayed_month(&mut self, cx: &mut Cx, delta: i32) {
+        let today = Local::now().date_naive();
+        let Some(displayed_month) = self.displayed_month else { return };
+        let shifted = shift_month(displayed_month, delta);
+        // Don't allow showing months in the future, as they can't contain any messages yet.
+        if shifted > today {
+            return;
+        }
+        self.displayed_month = Some(shifted);
+        self.update_calendar(cx);
+    }
+
+    fn update_calendar(&mut self, cx: &mut Cx) {
+        let Some(displayed_month) = self.displayed_month else { return };
+        let today = Local::now().date_naive();
+        let month_name = tr_key(self.app_language, month_name_key(displayed_month.month()));
+        self.view.label(cx, ids!(month_row.month_label)).set_text(cx, &tr_fmt(
+            self.app_language,
+            "jump_to_date.month_label",
+            &[("month", month_name), ("year", &displayed_month.year().to_string())],
+        ));
+        self.view.button(cx, ids!(month_row.next_month_button))
+            .set_enabled(cx, shift_month(displayed_month, 1) <= today);
+        self.view.calendar_grid(cx, ids!(day_grid))
+            .set_month(cx, displayed_month, self.selected_date, None, Some(today));
+        self.update_selected_date_label(cx);
+        self.view.redraw(cx);
+    }
+
+    fn update_selected_date_label(&mut self, cx: &mut Cx) {
+        let text = match self.selected_date {
+            Some(date) => tr_fmt(self.app_language, "jump_to_date.selected_date", &[
+                ("date", &date.format("%Y-%m-%d").to_string()),
+            ]),
+            None => tr_key(self.app_language, "jump_to_date.no_date_selected").to_string(),
+        };
+        self.view.label(cx, ids!(selected_date_label)).set_text(cx, &text);
+        self.view.button(cx, ids!(buttons.jump_button)).set_enabled(cx, self.selected_date.is_some());
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(title))
+            .set_text(cx, tr_key(self.app_language, "jump_to_date.title"));
+        for (index, weekday) in tr_key(self.app_language, "jump_to_date.weekdays").split(',').take(7).enumerate() {
+            self.view.label(cx, weekday_label_id(index)).set_text(cx, weekday.trim());
+        }
+        self.view.button(cx, ids!(buttons.cancel_button))
+            .set_text(cx, tr_key(self.app_language, "jump_to_date.button.cancel"));
+        self.view.button(cx, ids!(buttons.jump_button))
+            .set_text(cx, tr_key(self.app_language, "jump_to_date.button.jump"));
+        if self.displayed_month.is_some() {
+            self.update_calendar(cx);
+        }
+    }
+}
+
+/// Returns the i18n key of the name of the given month (1 = January).
+pub fn month_name_key(month: u32) -> &'static str {
+    match month {
+        1 => "jump_to_date.month.1",
+        2 => "jump_to_date.month.2",
+        3 => "jump_to_date.month.3",
+        4 => "jump_to_date.month.4",
+        5 => "jump_to_date.month.5",
+        6 => "jump_to_date.month.6",
+        7 => "jump_to_date.month.7",
+        8 => "jump_to_date.month.8",
+        9 => "jump_to_date.month.9",
+        10 => "jump_to_date.month.10",
+        11 => "jump_to_date.month.11",
+        _ => "jump_to_date.month.12",
+    }
+}
+
+impl JumpToDateModalRef {
+    /// See [`JumpToDateModal::show()`].
+    pub fn show(&self, cx: &mut Cx, timeline_kind: TimelineKind, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, timeline_kind, app_language);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn calendar_cells_start_on_monday() {
+        // March 1st, 2024 was a Friday.
+        let cells = calendar_cells(date(2024, 3, 1));
+        assert!(cells[..4].iter().all(Option::is_none));
+        assert_eq!(cells[4], Some(date(2024, 3, 1)));
+        assert_eq!(cells[4 + 30], Some(date(2024, 3, 31)));
+        assert!(cells[4 + 31 ..].iter().all(Option::is_none));
+        assert_eq!(cells.iter().flatten().count(), 31);
+    }
+
+    #[test]
+    fn calendar_cells_fit_months_spanning_six_weeks() {
+        // September 1st, 2024 was a Sunday, so September spans six calendar rows.
+        let cells = calendar_cells(date(2024, 9, 1));
+        assert_eq!(cells[6], Some(date(2024, 9, 1)));
+        assert_eq!(cells[NUM_CALENDAR_CELLS - 7], Some(date(2024, 9, 30)));
+    }
+
+    #[test]
+    fn shift_month_wraps_across_years() {
+        assert_eq!(shift_month(date(2024, 1, 1), -1), date(2023, 12, 1));
+        assert_eq!(shift_month(date(2024, 12, 1), 1), date(2025, 1, 1));
+        assert_eq!(shift_month(date(2024, 3, 1), 0), date(2024, 3, 1));
+        assert_eq!(start_of_month(date(2024, 2, 29)), date(2024, 2, 1));
+    }
+}
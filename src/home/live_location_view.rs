@@ -0,0 +1,231 @@
+//! A view that renders a live location share (MSC3489) in a room timeline.
+//!
+//! It shows the sharer's latest reported position on a small map, which is updated as new beacons arrive,
+//! along with a countdown until the share expires.
+//! The sharer can also stop their own live location share from this view.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::OwnedRoomId;
+use matrix_sdk_ui::timeline::LiveLocationState;
+
+use crate::{
+    home::room_screen::location_html,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    live_location,
+    shared::{html_or_plaintext::HtmlOrPlaintextWidgetExt, map_thumbnail::MapThumbnailWidgetExt},
+    utils::unix_time_millis_to_datetime,
+};
+
+/// How often the countdown until a live location share expires is updated, in seconds.
+const COUNTDOWN_INTERVAL_SECS: f64 = 30.0;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.LiveLocationView = #(LiveLocationView::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 5.0
+        padding: Inset{ top: 2, bottom: 2, right: 10 }
+
+        title_label := Label {
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (MESSAGE_TEXT_COLOR),
+                text_style: theme.font_bold { font_size: 11.0 },
+            }
+            text: ""
+        }
+
+        map_thumbnail := mod.widgets.MapThumbnail { }
+        location := HtmlOrPlaintext { }
+
+        footer := View {
+            width: Fill,
+            height: Fit,
+            flow: Right,
+            spacing: 10.0
+            align: Align{ y: 0.5 }
+
+            status_label := Label {
+                width: Fill,
+                height: Fit,
+                flow: Flow.Right{wrap: true},
+                draw_text +: {
+                    color: (COLOR_MESSAGE_NOTICE_TEXT),
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+                }
+                text: ""
+            }
+
+            stop_sharing_button := RobrixNegativeIconButton {
+                visible: false
+                padding: Inset{ top: 6, bottom: 6, left: 12, right: 12 }
+                icon_walk: Walk{ width: 0, height: 0 }
+                spacing: 0
+                text: "Stop Sharing"
+            }
+        }
+    }
+}
+
+/// A view that displays a live location share and counts down until it expires.
+#[derive(Script, ScriptHook, Widget)]
+pub struct LiveLocationView {
+    #[deref] view: View,
+
+    #[rust] room_id: Option<OwnedRoomId>,
+    /// When this live location share expires, if it's still live.
+    #[rust] expires_at: Option<SystemTime>,
+    /// The time at which the latest location was reported, formatted for display.
+    #[rust] last_updated: Option<String>,
+    #[rust] countdown_timer: Timer,
+    #[rust] app_language: AppLanguage,
+}
+
+impl Widget for LiveLocationView {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if self.countdown_timer.is_event(event).is_some() {
+            self.update_status(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for LiveLocationView {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let stop_sharing_button = self.view.button(cx, ids!(stop_sharing_button));
+        if stop_sharing_button.clicked(actions) {
+            let Some(room_id) = self.room_id.clone() else { return };
+            live_location::stop_live_location_share(room_id);
+            stop_sharing_button.set_enabled(cx, false);
+        }
+    }
+}
+
+impl LiveLocationView {
+    /// Populates this view with the latest state of the given live location share.
+    fn set_live_location(
+        &mut self,
+        cx: &mut Cx,
+        app_language: AppLanguage,
+        room_id: &OwnedRoomId,
+        is_own: bool,
+        live_location: &LiveLocationState,
+    ) {
+        self.app_language = app_language;
+        self.room_id = Some(room_id.clone());
+
+        let beacon_info = live_location.beacon_info();
+        let started_at = UNIX_EPOCH + Duration::from_millis(u64::from(beacon_info.ts.get()));
+        let expires_at = started_at + beacon_info.timeout;
+        let is_live = live_location.is_live() && expires_at > SystemTime::now();
+        self.expires_at = is_live.then_some(expires_at);
+
+        let title = match beacon_info.description.as_deref().filter(|d| !d.trim().is_empty()) {
+            Some(description) => tr_fmt(app_language, "live_location_view.title.with_description", &[
+                ("description", description),
+            ]),
+            None => tr_key(app_language, "live_location_view.title").to_string(),
+        };
+        self.view.label(cx, ids!(title_label)).set_text(cx, &title);
+
+        let location_ref = self.view.html_or_plaintext(cx, ids!(location));
+        let latest_location = live_location.latest_location();
+        let map_thumbnail = self.view.map_thumbnail(cx, ids!(map_thumbnail));
+        match latest_location {
+            Some(beacon) => { map_thumbnail.set_geo_uri(cx, beacon.geo_uri()); }
+            None => map_thumbnail.set_visible(cx, false),
+        }
+        match latest_location.and_then(|beacon| location_html(app_language, beacon.geo_uri())) {
+            Some(html_body) => location_ref.show_html(cx, html_body),
+            None => location_ref.show_plaintext(cx, tr_key(app_language, "live_location_view.waiting_for_location")),
+        }
+        self.last_updated = latest_location
+            .and_then(|beacon| unix_time_millis_to_datetime(beacon.ts()))
+            .map(|datetime| datetime.format("%H:%M").to_string());
+
+        let stop_sharing_button = self.view.button(cx, ids!(stop_sharing_button));
+        stop_sharing_button.set_text(cx, tr_key(app_language, "live_location_view.button.stop_sharing"));
+        stop_sharing_button.set_visible(cx, is_own && is_live);
+        stop_sharing_button.set_enabled(cx, true);
+
+        cx.stop_timer(self.countdown_timer);
+        self.countdown_timer = if is_live {
+            cx.start_interval(COUNTDOWN_INTERVAL_SECS)
+        } else {
+            Timer::empty()
+        };
+        self.update_status(cx);
+    }
+
+    /// Updates the status label to show how much longer this live location share lasts.
+    fn update_status(&mut self, cx: &mut Cx) {
+        let time_left = self.expires_at
+            .and_then(|expires_at| expires_at.duration_since(SystemTime::now()).ok());
+        let mut status = match time_left {
+            Some(time_left) => tr_fmt(self.app_language, "live_location_view.status.live", &[
+                ("time_left", &format_time_left(self.app_language, time_left)),
+            ]),
+            None => {
+                // The share has expired since it was last populated.
+                if self.expires_at.take().is_some() {
+                    cx.stop_timer(self.countdown_timer);
+                    self.countdown_timer = Timer::empty();
+                    self.view.button(cx, ids!(stop_sharing_button)).set_visible(cx, false);
+                }
+                tr_key(self.app_language, "live_location_view.status.ended").to_string()
+            }
+        };
+        if let Some(last_updated) = self.last_updated.as_deref() {
+            status.push_str(" · ");
+            status.push_str(&tr_fmt(self.app_language, "live_location_view.status.last_updated", &[
+                ("time", last_updated),
+            ]));
+        }
+        self.view.label(cx, ids!(status_label)).set_text(cx, &status);
+        self.view.redraw(cx);
+    }
+}
+
+/// Formats the given remaining duration of a live location share, rounded up to the next minute.
+fn format_time_left(app_language: AppLanguage, time_left: Duration) -> String {
+    let total_minutes = time_left.as_secs().div_ceil(60);
+    let (hours, minutes) = (total_minutes / 60, total_minutes % 60);
+    if hours > 0 {
+        tr_fmt(app_language, "live_location_view.time_left.hours", &[
+            ("hours", &hours.to_string()),
+            ("minutes", &minutes.to_string()),
+        ])
+    } else {
+        tr_fmt(app_language, "live_location_view.time_left.minutes", &[
+            ("minutes", &minutes.max(1).to_string()),
+        ])
+    }
+}
+
+impl LiveLocationViewRef {
+    /// See [`LiveLocationView::set_live_location()`].
+    pub fn set_live_location(
+        &self,
+        cx: &mut Cx,
+        app_language: AppLanguage,
+        room_id: &OwnedRoomId,
+        is_own: bool,
+        live_location: &LiveLocationState,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.set_live_location(cx, app_language, room_id, is_own, live_location);
+    }
+}
@@ -1,5 +1,7 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
 use makepad_widgets::*;
-use matrix_sdk::ruma::OwnedEventId;
+use matrix_sdk::ruma::{OwnedEventId, OwnedUserId};
 
 use crate::{app::AppState, i18n::{AppLanguage, tr_fmt, tr_key}, sliding_sync::TimelineRequestSender};
 
@@ -103,6 +105,23 @@ pub enum LoadingPaneState {
         /// cancelled the request, so that it can stop looking for the target event.
         request_sender: TimelineRequestSender,
     },
+    /// A Matrix link is being resolved, e.g., a room alias is being looked up
+    /// or the preview of an unknown room is being fetched.
+    OpeningMatrixLink {
+        /// The displayable room ID or alias that the link points to.
+        target: String,
+    },
+    /// A user's recent messages are being removed (redacted) in bulk.
+    RemovingRecentMessages {
+        sender: OwnedUserId,
+        /// The number of messages processed so far, which is only used to display progress.
+        processed: usize,
+        /// The total number of messages to remove, or `None` if they're still being looked up.
+        total: Option<usize>,
+        /// Set to `true` when the user stops the removal, such that the background task
+        /// doesn't redact any further messages.
+        cancelled: Arc<AtomicBool>,
+    },
     /// The loading pane is displaying an error message until the user closes it.
     Error(String),
     /// The LoadingPane is not doing anything and can be hidden.
@@ -119,6 +138,9 @@ pub struct LoadingPane {
 }
 impl Drop for LoadingPane {
     fn drop(&mut self) {
+        if let LoadingPaneState::RemovingRecentMessages { cancelled, .. } = &self.state {
+            cancelled.store(true, Ordering::Relaxed);
+        }
         if let LoadingPaneState::BackwardsPaginateUntilEvent { target_event_id, request_sender, .. } = &self.state {
             warning!("Dropping LoadingPane with target_event_id: {}", target_event_id);
             request_sender.send_if_modified(|requests| {
@@ -188,6 +210,10 @@ impl Widget for LoadingPane {
             }
         };
         if close_pane {
+            if let LoadingPaneState::RemovingRecentMessages { sender, cancelled, .. } = &self.state {
+                log!("LoadingPane: stopping the removal of recent messages by {sender}");
+                cancelled.store(true, Ordering::Relaxed);
+            }
             if let LoadingPaneState::BackwardsPaginateUntilEvent { target_event_id, request_sender, .. } = &self.state {
                 let _did_send = request_sender.send_if_modified(|requests| {
                     let initial_len = requests.len();
@@ -232,6 +258,34 @@ impl LoadingPane {
                     tr_key(self.app_language, "loading_pane.button.cancel").to_string(),
                 )
             }
+            LoadingPaneState::OpeningMatrixLink { target } => (
+                tr_key(self.app_language, "loading_pane.title.opening_link").to_string(),
+                Some(tr_fmt(self.app_language, "loading_pane.status.opening_link", &[
+                    ("target", target.as_str()),
+                ])),
+                tr_key(self.app_language, "loading_pane.button.cancel").to_string(),
+            ),
+            LoadingPaneState::RemovingRecentMessages { sender, processed, total, .. } => {
+                let status = match total {
+                    Some(total) => {
+                        let processed_str = processed.to_string();
+                        let total_str = total.to_string();
+                        tr_fmt(self.app_language, "loading_pane.status.removing_messages", &[
+                            ("sender", sender.as_str()),
+                            ("processed", processed_str.as_str()),
+                            ("total", total_str.as_str()),
+                        ])
+                    }
+                    None => tr_fmt(self.app_language, "loading_pane.status.finding_messages", &[
+                        ("sender", sender.as_str()),
+                    ]),
+                };
+                (
+                    tr_key(self.app_language, "loading_pane.title.removing_messages").to_string(),
+                    Some(status),
+                    tr_key(self.app_language, "loading_pane.button.stop").to_string(),
+                )
+            }
             LoadingPaneState::Error(error_message) => (
                 tr_key(self.app_language, "loading_pane.title.error").to_string(),
                 Some(error_message.clone()),
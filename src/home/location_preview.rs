@@ -1,16 +1,21 @@
-//! The `LocationPreview` is a small view that shows the current location
-//! and allows the user to send their location to a room.
+//! The `LocationPreview` is a small view that shows the current location on a small map
+//! and allows the user to send their location to a room,
+//! or to start sharing their live location there for a chosen duration.
 //!
 //! This view is not visible by default, only when the user requests it
 //! by clicking on the location button in the message input bar.
 //! The `RoomScreen` widget then shows this view above the message input bar.
 
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use makepad_widgets::*;
 use robius_location::Coordinates;
 
-use crate::location::{get_latest_location, request_location_update, LocationAction, LocationRequest, LocationUpdate};
+use crate::{
+    live_location::LIVE_LOCATION_DURATIONS,
+    shared::map_thumbnail::MapThumbnailWidgetExt,
+    location::{get_latest_location, request_location_update, LocationAction, LocationRequest, LocationUpdate},
+};
 
 script_mod! {
     use mod.prelude.widgets.*
@@ -60,6 +65,10 @@ script_mod! {
             text: "➡ Fetching current location..."
         }
 
+        map_thumbnail := mod.widgets.MapThumbnail {
+            margin: Inset{left: 10, bottom: 7}
+        }
+
         View {
             width: Fill, height: Fit
             flow: Flow.Right{wrap: true},
@@ -86,6 +95,47 @@ script_mod! {
                 text: "Yes"
             }
         }
+
+        live_location_row := View {
+            width: Fill, height: Fit
+            flow: Flow.Right{wrap: true},
+            spacing: 10
+            align: Align{x: 0.0, y: 0.5}
+
+            Label {
+                width: Fit,
+                height: Fit,
+                draw_text +: {
+                    color: (MESSAGE_TEXT_COLOR),
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 10.0 },
+                }
+                text: "Or share your live location for:"
+            }
+
+            live_location_15m_button := RobrixNeutralIconButton {
+                padding: Inset{top: 8, bottom: 8, left: 12, right: 12}
+                margin: 0
+                icon_walk: Walk{width: 0, height: 0}
+                spacing: 0
+                text: "15 minutes"
+            }
+
+            live_location_1h_button := RobrixNeutralIconButton {
+                padding: Inset{top: 8, bottom: 8, left: 12, right: 12}
+                margin: 0
+                icon_walk: Walk{width: 0, height: 0}
+                spacing: 0
+                text: "1 hour"
+            }
+
+            live_location_8h_button := RobrixNeutralIconButton {
+                padding: Inset{top: 8, bottom: 8, left: 12, right: 12}
+                margin: 0
+                icon_walk: Walk{width: 0, height: 0}
+                spacing: 0
+                text: "8 hours"
+            }
+        }
     }
 }
 
@@ -106,12 +156,15 @@ impl Widget for LocationPreview {
                     Some(LocationAction::Update(LocationUpdate { coordinates, time })) => {
                         self.coords = Some(Ok(*coordinates));
                         self.timestamp = *time;
+                        self.map_thumbnail(cx, ids!(map_thumbnail))
+                            .set_location(cx, coordinates.latitude, coordinates.longitude);
                         self.button(cx, ids!(send_location_button)).set_enabled(cx, true);
                         needs_redraw = true;
                     }
                     Some(LocationAction::Error(e)) => {
                         self.coords = Some(Err(*e));
                         self.timestamp = None;
+                        self.map_thumbnail(cx, ids!(map_thumbnail)).set_visible(cx, false);
                         self.button(cx, ids!(send_location_button)).set_enabled(cx, false);
                         needs_redraw = true;
                     }
@@ -119,8 +172,8 @@ impl Widget for LocationPreview {
                 }
             }
 
-            // NOTE: the send location button click event is handled
-            //       in the RoomScreen handle_event function.
+            // NOTE: the send location and live location button click events are handled
+            //       in the RoomInputBar's handle_actions function.
 
             // Handle the cancel location button being clicked.
             if self.button(cx, ids!(cancel_location_button)).clicked(actions) {
@@ -151,11 +204,13 @@ impl Widget for LocationPreview {
 
 
 impl LocationPreview {
-    fn show(&mut self) {
+    fn show(&mut self, cx: &mut Cx) {
         request_location_update(LocationRequest::UpdateOnce);
         if let Some(loc) = get_latest_location() {
             self.coords = Some(Ok(loc.coordinates));
             self.timestamp = loc.time;
+            self.map_thumbnail(cx, ids!(map_thumbnail))
+                .set_location(cx, loc.coordinates.latitude, loc.coordinates.longitude);
         }
         self.visible = true;
     }
@@ -166,6 +221,20 @@ impl LocationPreview {
         self.visible = false;
     }
 
+    /// Returns the duration of the live location share that the user chose to start, if any.
+    fn live_location_duration_clicked(&self, cx: &mut Cx, actions: &Actions) -> Option<Duration> {
+        [
+            ids!(live_location_15m_button),
+            ids!(live_location_1h_button),
+            ids!(live_location_8h_button),
+        ]
+        .into_iter()
+        .zip(LIVE_LOCATION_DURATIONS)
+        .find_map(|(button_id, duration)|
+            self.view.button(cx, button_id).clicked(actions).then_some(duration)
+        )
+    }
+
     pub fn get_current_data(&self) -> Option<(Coordinates, Option<SystemTime>)> {
         self.coords
             .as_ref()
@@ -175,9 +244,9 @@ impl LocationPreview {
 }
 
 impl LocationPreviewRef {
-    pub fn show(&self) {
+    pub fn show(&self, cx: &mut Cx) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.show();
+            inner.show(cx);
         }
     }
 
@@ -187,6 +256,11 @@ impl LocationPreviewRef {
         }
     }
 
+    /// See [`LocationPreview::live_location_duration_clicked()`].
+    pub fn live_location_duration_clicked(&self, cx: &mut Cx, actions: &Actions) -> Option<Duration> {
+        self.borrow().and_then(|inner| inner.live_location_duration_clicked(cx, actions))
+    }
+
     pub fn get_current_data(&self) -> Option<(Coordinates, Option<SystemTime>)> {
         self.borrow().and_then(|inner| inner.get_current_data())
     }
@@ -0,0 +1,354 @@
+//! A floating bar of quick-action buttons that is shown on desktop
+//! when the user hovers over a message in a room timeline.
+//!
+//! Each button emits the same [`MessageAction`] as its counterpart in the
+//! `NewMessageContextMenu`, so right-clicking a message is no longer the only way
+//! to act on it. The "more" button opens that full context menu.
+
+use makepad_widgets::*;
+
+use crate::i18n::{AppLanguage, tr_key};
+
+use super::{
+    ContextMenuOpenGesture,
+    new_message_context_menu::{MessageAbilities, MessageDetails},
+    room_screen::MessageAction,
+};
+
+const BUTTON_SIZE: f64 = 30.0; // KEEP IN SYNC WITH BUTTON_SIZE BELOW
+const BAR_PADDING: f64 = 3.0;  // KEEP IN SYNC WITH BAR_PADDING BELOW
+const BAR_SPACING: f64 = 2.0;  // KEEP IN SYNC WITH BAR_SPACING BELOW
+/// How far from the right edge of the message the action bar is shown.
+const BAR_RIGHT_INSET: f64 = 15.0;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+
+    mod.widgets.MESSAGE_ACTION_BAR_BUTTON_SIZE = 30  // KEEP IN SYNC WITH BUTTON_SIZE ABOVE
+    mod.widgets.MESSAGE_ACTION_BAR_PADDING = 3       // KEEP IN SYNC WITH BAR_PADDING ABOVE
+    mod.widgets.MESSAGE_ACTION_BAR_SPACING = 2       // KEEP IN SYNC WITH BAR_SPACING ABOVE
+
+    mod.widgets.MessageActionBarButton = RobrixIconButton {
+        width: (mod.widgets.MESSAGE_ACTION_BAR_BUTTON_SIZE)
+        height: (mod.widgets.MESSAGE_ACTION_BAR_BUTTON_SIZE)
+        margin: 0,
+        padding: 7,
+        spacing: 0,
+        align: Align{x: 0.5, y: 0.5}
+        icon_walk: Walk{width: 16, height: 16, margin: 0}
+        // Override the blue default back to neutral, just like the message context menu.
+        draw_bg +: { color: (COLOR_PRIMARY), color_hover: #EBEBEB, color_down: #DCDCDC }
+        draw_icon.color: #000
+        text: ""
+    }
+
+    mod.widgets.MessageActionBar = #(MessageActionBar::register_widget(vm)) {
+        visible: false,
+        width: Fill,
+        height: Fill,
+        flow: Overlay,
+        // Align to top-left such that our coordinate adjustment
+        // when showing this bar will work correctly.
+        align: Align{x: 0, y: 0}
+
+        bar := RoundedShadowView {
+            width: Fit,
+            height: Fit,
+            flow: Right,
+            padding: (mod.widgets.MESSAGE_ACTION_BAR_PADDING)
+            spacing: (mod.widgets.MESSAGE_ACTION_BAR_SPACING)
+            align: Align{y: 0.5}
+            cursor: MouseCursor.Default,
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 5.0
+                border_size: 0.5
+                border_color: #CCC
+                shadow_color: #0003
+                shadow_radius: 6.0
+                shadow_offset: vec2(0.0, 1.0)
+            }
+
+            react_button := mod.widgets.MessageActionBarButton {
+                draw_icon +: { svg: (ICON_ADD_REACTION) }
+            }
+            reply_button := mod.widgets.MessageActionBarButton {
+                draw_icon +: { svg: (ICON_REPLY) }
+            }
+            thread_button := mod.widgets.MessageActionBarButton {
+                draw_icon +: { svg: crate_resource("self://resources/icons/double_chat.svg") }
+            }
+            edit_button := mod.widgets.MessageActionBarButton {
+                draw_icon +: { svg: (ICON_EDIT) }
+            }
+            pin_button := mod.widgets.MessageActionBarButton {
+                draw_icon +: { svg: (ICON_PIN) }
+            }
+            more_button := mod.widgets.MessageActionBarButton {
+                draw_icon +: { svg: (ICON_MORE) }
+            }
+        }
+    }
+}
+
+/// The buttons in the message action bar, in the order that they're shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ActionBarButton {
+    React,
+    Reply,
+    Thread,
+    Edit,
+    Pin,
+    More,
+}
+
+impl ActionBarButton {
+    const ALL: [ActionBarButton; 6] = [
+        ActionBarButton::React,
+        ActionBarButton::Reply,
+        ActionBarButton::Thread,
+        ActionBarButton::Edit,
+        ActionBarButton::Pin,
+        ActionBarButton::More,
+    ];
+
+    fn id(&self) -> &'static [LiveId] {
+        match self {
+            ActionBarButton::React => ids!(react_button),
+            ActionBarButton::Reply => ids!(reply_button),
+            ActionBarButton::Thread => ids!(thread_button),
+            ActionBarButton::Edit => ids!(edit_button),
+            ActionBarButton::Pin => ids!(pin_button),
+            ActionBarButton::More => ids!(more_button),
+        }
+    }
+
+    /// Returns whether this button should be shown for the given message,
+    /// using the same conditions as the message context menu.
+    fn is_shown_for(&self, details: &MessageDetails) -> bool {
+        match self {
+            ActionBarButton::React => details.abilities.contains(MessageAbilities::CanReact),
+            ActionBarButton::Reply => details.abilities.contains(MessageAbilities::CanReplyTo),
+            ActionBarButton::Thread => !details.is_thread_timeline && details.event_id().is_some(),
+            ActionBarButton::Edit => details.abilities.contains(MessageAbilities::CanEdit),
+            ActionBarButton::Pin => details.abilities.intersects(MessageAbilities::CanPin | MessageAbilities::CanUnpin),
+            ActionBarButton::More => true,
+        }
+    }
+
+    /// Returns the i18n key of this button's tooltip text for the given message.
+    fn tooltip_key(&self, details: &MessageDetails) -> &'static str {
+        match self {
+            ActionBarButton::React => "new_message_context_menu.button.add_reaction",
+            ActionBarButton::Reply => "new_message_context_menu.button.reply",
+            ActionBarButton::Thread if details.thread_root_event_id.is_some() => "new_message_context_menu.button.open_thread",
+            ActionBarButton::Thread => "new_message_context_menu.button.reply_in_thread",
+            ActionBarButton::Edit => "new_message_context_menu.button.edit_message",
+            ActionBarButton::Pin if details.abilities.contains(MessageAbilities::CanPin) => "new_message_context_menu.button.pin_message",
+            ActionBarButton::Pin => "new_message_context_menu.button.unpin_message",
+            ActionBarButton::More => "message_action_bar.button.more",
+        }
+    }
+}
+
+/// A floating bar of quick-action buttons for the currently-hovered message.
+#[derive(Script, ScriptHook, Widget)]
+pub struct MessageActionBar {
+    #[source] source: ScriptObjectRef,
+    #[deref] view: View,
+    /// The details of the message that this bar is currently shown for.
+    #[rust] details: Option<MessageDetails>,
+    /// The on-screen rect of the message that this bar is currently shown for.
+    #[rust] message_rect: Rect,
+    #[rust] app_language: AppLanguage,
+}
+
+impl Widget for MessageActionBar {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        if self.details.is_none() {
+            self.visible = false;
+        }
+        self.view.draw_walk(cx, scope, walk)
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if !self.visible { return; }
+        let Some(details) = self.details.clone() else { return };
+
+        for button in ActionBarButton::ALL {
+            let button_area = self.view.button(cx, button.id()).area();
+            match event.hits(cx, button_area) {
+                Hit::FingerHoverIn(_) => {
+                    cx.widget_action(
+                        self.widget_uid(),
+                        TooltipAction::HoverIn {
+                            text: tr_key(self.app_language, button.tooltip_key(&details)).to_string(),
+                            widget_rect: button_area.rect(cx),
+                            options: CalloutTooltipOptions {
+                                position: TooltipPosition::Top,
+                                ..Default::default()
+                            },
+                        },
+                    );
+                }
+                Hit::FingerHoverOut(_) => {
+                    cx.widget_action(self.widget_uid(), TooltipAction::HoverOut);
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.is_primary_hit() && fe.was_tap() => {
+                    cx.widget_action(self.widget_uid(), TooltipAction::HoverOut);
+                    let button_rect = button_area.rect(cx);
+                    self.emit_button_action(cx, button, details.clone(), button_rect, &fe);
+                    self.hide(cx);
+                    return;
+                }
+                _ => { }
+            }
+        }
+
+        self.view.handle_event(cx, event, scope);
+
+        // Hide this bar once the pointer leaves it, unless it moved back onto the message.
+        if let Hit::FingerHoverOut(fho) = event.hits(cx, self.view(cx, ids!(bar)).area()) {
+            if !self.message_rect.contains(fho.abs) {
+                self.hide(cx);
+            }
+        }
+    }
+}
+
+impl MessageActionBar {
+    /// Emits the `MessageAction` corresponding to the given clicked button.
+    fn emit_button_action(
+        &self,
+        cx: &mut Cx,
+        button: ActionBarButton,
+        details: MessageDetails,
+        button_rect: Rect,
+        finger_up: &FingerUpEvent,
+    ) {
+        let room_screen_widget_uid = details.room_screen_widget_uid;
+        // Show any menus opened from this bar just beneath the clicked button.
+        let abs_pos = dvec2(button_rect.pos.x, button_rect.pos.y + button_rect.size.y);
+        let action = match button {
+            ActionBarButton::React => MessageAction::OpenReactionInput {
+                details,
+                abs_pos,
+                opening_gesture: ContextMenuOpenGesture::from_finger_up(finger_up),
+            },
+            ActionBarButton::Reply => MessageAction::Reply(details),
+            ActionBarButton::Thread => {
+                let Some(thread_root_event_id) = details.thread_root_event_id.clone()
+                    .or_else(|| details.event_id().cloned())
+                else {
+                    return;
+                };
+                MessageAction::OpenThread(thread_root_event_id)
+            }
+            ActionBarButton::Edit => MessageAction::Edit(details),
+            ActionBarButton::Pin if details.abilities.contains(MessageAbilities::CanPin) => MessageAction::Pin(details),
+            ActionBarButton::Pin => MessageAction::Unpin(details),
+            ActionBarButton::More => MessageAction::OpenMessageContextMenu {
+                details,
+                abs_pos,
+                opening_gesture: ContextMenuOpenGesture::from_finger_up(finger_up),
+            },
+        };
+        cx.widget_action(room_screen_widget_uid, action);
+    }
+
+    /// Shows this action bar for the given message.
+    ///
+    /// The bar is placed at the top-right corner of the given `message_rect`,
+    /// relative to the `container_rect` that this bar is drawn within.
+    pub fn show(
+        &mut self,
+        cx: &mut Cx,
+        details: MessageDetails,
+        message_rect: Rect,
+        container_rect: Rect,
+        app_language: AppLanguage,
+    ) {
+        let mut num_visible_buttons = 0;
+        for button in ActionBarButton::ALL {
+            let is_shown = button.is_shown_for(&details);
+            let button_ref = self.view.button(cx, button.id());
+            button_ref.set_visible(cx, is_shown);
+            button_ref.reset_hover(cx);
+            num_visible_buttons += is_shown as usize;
+        }
+        let bar_width = num_visible_buttons as f64 * BUTTON_SIZE
+            + num_visible_buttons.saturating_sub(1) as f64 * BAR_SPACING
+            + 2.0 * BAR_PADDING;
+        let bar_height = BUTTON_SIZE + 2.0 * BAR_PADDING;
+
+        // Overlap the top edge of the message, but keep the bar within the container.
+        let left = (message_rect.pos.x + message_rect.size.x - container_rect.pos.x - bar_width - BAR_RIGHT_INSET)
+            .clamp(0.0, (container_rect.size.x - bar_width).max(0.0));
+        let top = (message_rect.pos.y - container_rect.pos.y - bar_height / 2.0)
+            .clamp(0.0, (container_rect.size.y - bar_height).max(0.0));
+        let margin = Inset { left, top, right: 0.0, bottom: 0.0 };
+        let mut bar = self.view.view(cx, ids!(bar));
+        script_apply_eval!(cx, bar, {
+            margin: #(margin)
+        });
+
+        self.details = Some(details);
+        self.message_rect = message_rect;
+        self.app_language = app_language;
+        self.visible = true;
+        self.redraw(cx);
+    }
+
+    /// Hides this action bar.
+    pub fn hide(&mut self, cx: &mut Cx) {
+        if !self.visible { return; }
+        self.visible = false;
+        self.details = None;
+        self.redraw(cx);
+    }
+
+    /// Returns the timeline item index of the message that this bar is currently shown for.
+    pub fn shown_item_id(&self) -> Option<usize> {
+        self.visible.then(|| self.details.as_ref().map(|d| d.item_id)).flatten()
+    }
+
+    /// Returns `true` if the given absolute position is within this bar's visible area.
+    pub fn contains(&self, cx: &mut Cx, abs_pos: DVec2) -> bool {
+        self.visible && self.view.view(cx, ids!(bar)).area().rect(cx).contains(abs_pos)
+    }
+}
+
+impl MessageActionBarRef {
+    /// See [`MessageActionBar::show()`].
+    pub fn show(
+        &self,
+        cx: &mut Cx,
+        details: MessageDetails,
+        message_rect: Rect,
+        container_rect: Rect,
+        app_language: AppLanguage,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, details, message_rect, container_rect, app_language);
+    }
+
+    /// See [`MessageActionBar::hide()`].
+    pub fn hide(&self, cx: &mut Cx) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.hide(cx);
+    }
+
+    /// See [`MessageActionBar::shown_item_id()`].
+    pub fn shown_item_id(&self) -> Option<usize> {
+        self.borrow().and_then(|inner| inner.shown_item_id())
+    }
+
+    /// See [`MessageActionBar::contains()`].
+    pub fn contains(&self, cx: &mut Cx, abs_pos: DVec2) -> bool {
+        self.borrow().is_some_and(|inner| inner.contains(cx, abs_pos))
+    }
+}
@@ -1,9 +1,11 @@
 use makepad_widgets::{ScriptVm, event::{DigitId, FingerDownEvent, FingerLongPressEvent, FingerUpEvent}};
 
 pub mod add_room;
+pub mod audio_player;
 pub mod bot_binding_modal;
 pub mod create_bot_modal;
 pub mod delete_bot_modal;
+pub mod edit_history_modal;
 pub mod edited_indicator;
 pub mod editing_pane;
 pub mod event_source_modal;
@@ -13,6 +15,7 @@ pub mod invite_screen;
 pub mod light_themed_dock;
 pub mod tombstone_footer;
 pub mod loading_pane;
+pub mod live_location_view;
 pub mod location_preview;
 pub mod main_desktop_ui;
 pub mod main_mobile_ui;
@@ -22,6 +25,7 @@ pub mod rooms_list;
 pub mod rooms_list_entry;
 pub mod rooms_list_header;
 pub mod rooms_sidebar;
+pub mod saved_messages;
 pub mod search_messages;
 pub mod space_lobby;
 pub mod spaces_bar;
@@ -29,6 +33,15 @@ pub mod navigation_tab_bar;
 pub mod welcome_screen;
 pub mod event_reaction_list;
 pub mod new_message_context_menu;
+pub mod message_action_bar;
+pub mod poll_composer;
+pub mod export_chat_modal;
+pub mod forward_message_modal;
+pub mod jump_to_date;
+pub mod schedule_message_modal;
+pub mod unsent_messages_modal;
+pub mod poll_view;
+pub mod report_message_modal;
 pub mod room_context_menu;
 pub mod link_preview;
 pub mod room_image_viewer;
@@ -60,6 +73,15 @@ impl ContextMenuOpenGesture {
         }
     }
 
+    /// For menus opened by a click (e.g., a button), whose gesture has already ended.
+    /// Because the `FingerUp` has already been delivered, this never consumes a later one.
+    pub fn from_finger_up(event: &FingerUpEvent) -> Self {
+        Self {
+            digit_id: event.digit_id,
+            capture_time: event.capture_time,
+        }
+    }
+
     fn matches_finger_up(&self, event: &FingerUpEvent) -> bool {
         self.digit_id == event.digit_id
             && self.capture_time == event.capture_time
@@ -83,22 +105,35 @@ pub fn consume_context_menu_opening_finger_up(
 
 pub fn script_mod(vm: &mut ScriptVm) {
     search_messages::script_mod(vm);
+    saved_messages::script_mod(vm);
     loading_pane::script_mod(vm);
     location_preview::script_mod(vm);
     add_room::script_mod(vm);
     bot_binding_modal::script_mod(vm);
     create_bot_modal::script_mod(vm);
     delete_bot_modal::script_mod(vm);
+    report_message_modal::script_mod(vm);
+    export_chat_modal::script_mod(vm);
+    forward_message_modal::script_mod(vm);
     space_lobby::script_mod(vm);
     link_preview::script_mod(vm);
     event_reaction_list::script_mod(vm);
+    poll_view::script_mod(vm);
+    live_location_view::script_mod(vm);
+    audio_player::script_mod(vm);
     room_read_receipt::script_mod(vm);
     rooms_list_entry::script_mod(vm);
     rooms_list_header::script_mod(vm);
     rooms_list::script_mod(vm);
     edited_indicator::script_mod(vm);
+    edit_history_modal::script_mod(vm);
+    poll_composer::script_mod(vm);
+    jump_to_date::script_mod(vm);
+    schedule_message_modal::script_mod(vm);
+    unsent_messages_modal::script_mod(vm);
     editing_pane::script_mod(vm);
     new_message_context_menu::script_mod(vm);
+    message_action_bar::script_mod(vm);
     event_source_modal::script_mod(vm);
     room_context_menu::script_mod(vm);
     invite_modal::script_mod(vm);
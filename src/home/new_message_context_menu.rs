@@ -4,9 +4,9 @@
 use bitflags::bitflags;
 use makepad_widgets::*;
 use matrix_sdk::ruma::OwnedEventId;
-use matrix_sdk_ui::timeline::{EventTimelineItem, MsgLikeContent, TimelineEventItemId};
+use matrix_sdk_ui::timeline::{EventTimelineItem, MsgLikeContent, MsgLikeKind, TimelineEventItemId};
 
-use crate::{i18n::{AppLanguage, tr_key}, sliding_sync::UserPowerLevels};
+use crate::{i18n::{AppLanguage, tr_key}, shared::emoji_picker::{EMOJI_PICKER_HEIGHT, EmojiPickerWidgetExt}, sliding_sync::UserPowerLevels};
 
 use super::{ContextMenuOpenGesture, consume_context_menu_opening_finger_up, room_screen::MessageAction};
 
@@ -110,6 +110,12 @@ script_mod! {
                 }
             }
 
+            // Shown below the reaction input once the react_button is clicked.
+            emoji_picker := EmojiPicker {
+                visible: false
+                margin: Inset{top: 4, bottom: 4}
+            }
+
             reply_button := mod.widgets.NewMessageContextMenuButton {
                 draw_icon +: { svg: (ICON_REPLY) }
                 icon_walk +: { margin: Inset{top: 1, right: 3}}
@@ -139,6 +145,16 @@ script_mod! {
                 text: "" // set dynamically to "Pin Message" or "Unpin Message"
             }
 
+            forward_button := mod.widgets.NewMessageContextMenuButton {
+                draw_icon +: { svg: (ICON_FORWARD) }
+                text: "Forward"
+            }
+
+            bookmark_button := mod.widgets.NewMessageContextMenuButton {
+                draw_icon +: { svg: (ICON_BOOKMARK) }
+                text: "" // set dynamically to "Save Message" or "Remove from Saved"
+            }
+
             copy_text_button := mod.widgets.NewMessageContextMenuButton {
                 draw_icon +: { svg: (ICON_COPY) }
                 text: "Copy Text"
@@ -170,22 +186,31 @@ script_mod! {
                 width: Fill,
             }
 
-            // report_button = ContextMenuButton {
-            //     draw_icon +: {
-            //         svg: (ICON_TRASH) // TODO: ICON_REPORT/WARNING/FLAG
-            //         color: (COLOR_FG_DANGER_RED),
-            //     }
-            //     icon_walk +: { margin: Inset{left: -2, right: 3} }
-            //
-            //     draw_bg +: {
-            //         border_color: (COLOR_FG_DANGER_RED),
-            //         color: (COLOR_BG_DANGER_RED)
-            //     }
-            //     text: "Report"
-            //     draw_text +: {
-            //         color: (COLOR_FG_DANGER_RED),
-            //     }
-            // }
+            report_button := mod.widgets.NewMessageContextMenuButton {
+                draw_icon +: {
+                    svg: (ICON_WARNING)
+                    color: (COLOR_FG_DANGER_RED),
+                }
+                draw_bg +: {
+                    border_color: (COLOR_FG_DANGER_RED),
+                    color: (COLOR_BG_DANGER_RED)
+                }
+                draw_text.color: (COLOR_FG_DANGER_RED),
+                text: "Report"
+            }
+
+            remove_recent_button := mod.widgets.NewMessageContextMenuButton {
+                draw_icon +: {
+                    svg: (ICON_TRASH)
+                    color: (COLOR_FG_DANGER_RED),
+                }
+                draw_bg +: {
+                    border_color: (COLOR_FG_DANGER_RED),
+                    color: (COLOR_BG_DANGER_RED)
+                }
+                draw_text.color: (COLOR_FG_DANGER_RED),
+                text: "Remove Recent Messages"
+            }
 
             // Note: we don't yet support deleting others' messages via admin/moderator power levels.
             //       For now we only consider whether its the user's own message.
@@ -214,7 +239,7 @@ bitflags! {
     ///
     /// This is used to determine which buttons to show in the message context menu.
     #[derive(Copy, Clone, Debug)]
-    pub struct MessageAbilities: u8 {
+    pub struct MessageAbilities: u16 {
         /// Whether the user can react to this message.
         const CanReact = 1 << 0;
         /// Whether the user can reply to this message.
@@ -231,15 +256,28 @@ bitflags! {
         const CanDelete = 1 << 5;
         /// Whether this message contains HTML content that the user can copy.
         const HasHtml = 1 << 6;
+        /// Whether the user can report this message to their homeserver administrators.
+        const CanReport = 1 << 7;
+        /// Whether the user can remove (redact) the recent messages of this message's sender,
+        /// i.e., a moderator acting on another user's message.
+        const CanRemoveRecentMessages = 1 << 8;
+        /// Whether this message can be forwarded to other rooms.
+        const CanForward = 1 << 9;
+        /// Whether the user can bookmark (or un-bookmark) this message.
+        const CanBookmark = 1 << 10;
+        /// Whether this message is currently bookmarked.
+        /// This should only be set along with `CanBookmark`.
+        const IsBookmarked = 1 << 11;
     }
 }
 impl MessageAbilities {
     pub fn from_user_power_and_event(
         user_power_levels: &UserPowerLevels,
         event_tl_item: &EventTimelineItem,
-        _message: &MsgLikeContent,
+        message: &MsgLikeContent,
         pinned_events: &[OwnedEventId],
         has_html: bool,
+        is_bookmarked: bool,
     ) -> Self {
         let mut abilities = Self::empty();
         abilities.set(Self::CanEdit, event_tl_item.is_editable());
@@ -257,6 +295,17 @@ impl MessageAbilities {
         }
         abilities.set(Self::CanReact, user_power_levels.can_send_reaction());
         abilities.set(Self::HasHtml, has_html);
+        // Only other users' messages that have been sent (i.e., have an event ID) can be reported.
+        abilities.set(Self::CanReport, !event_tl_item.is_own() && event_tl_item.event_id().is_some());
+        abilities.set(Self::CanRemoveRecentMessages, !event_tl_item.is_own() && user_power_levels.can_redact_others());
+        // Only regular messages that have been sent can be forwarded.
+        abilities.set(
+            Self::CanForward,
+            event_tl_item.event_id().is_some() && matches!(message.kind, MsgLikeKind::Message(_)),
+        );
+        // Only messages that have been sent can be bookmarked.
+        abilities.set(Self::CanBookmark, event_tl_item.event_id().is_some());
+        abilities.set(Self::IsBookmarked, event_tl_item.event_id().is_some() && is_bookmarked);
         abilities
     }
 
@@ -302,6 +351,8 @@ pub struct NewMessageContextMenu {
     #[rust] details: Option<MessageDetails>,
     #[rust] app_language: AppLanguage,
     #[rust] pending_open_gesture: Option<ContextMenuOpenGesture>,
+    /// The expected height of this menu when it was last shown, without the emoji picker.
+    #[rust] expected_height: f64,
 }
 
 impl Widget for NewMessageContextMenu {
@@ -330,8 +381,11 @@ impl Widget for NewMessageContextMenu {
                 Hit::KeyUp(key) => key.key_code == KeyCode::Escape,
                 Hit::FingerDown(fde) => {
                     let reaction_text_input = self.view.text_input(cx, ids!(reaction_input_view.reaction_text_input));
+                    let emoji_search_area = self.view.emoji_picker(cx, ids!(emoji_picker)).search_input_area(cx);
                     if reaction_text_input.area().rect(cx).contains(fde.abs) {
                         reaction_text_input.set_key_focus(cx);
+                    } else if emoji_search_area.rect(cx).contains(fde.abs) {
+                        cx.set_key_focus(emoji_search_area);
                     } else {
                         cx.set_key_focus(area);
                     }
@@ -346,7 +400,11 @@ impl Widget for NewMessageContextMenu {
                 }
                 // Ignore zero-scroll events: macOS trackpad generates FingerScroll(0,0)
                 // on two-finger press (right-click), which would incorrectly dismiss the menu.
-                Hit::FingerScroll(fse) => fse.scroll.x != 0.0 || fse.scroll.y != 0.0,
+                // Scrolling within the emoji picker scrolls its list of emoji instead.
+                Hit::FingerScroll(fse) => {
+                    (fse.scroll.x != 0.0 || fse.scroll.y != 0.0)
+                        && !self.view.emoji_picker(cx, ids!(emoji_picker)).area().rect(cx).contains(fse.abs)
+                }
                 _ => false,
             }
         };
@@ -366,7 +424,17 @@ impl WidgetMatchEvent for NewMessageContextMenu {
 
         let reaction_text_input = self.view.text_input(cx, ids!(reaction_input_view.reaction_text_input));
         let reaction_send_button = self.view.button(cx, ids!(reaction_input_view.reaction_send_button));
-        if reaction_send_button.clicked(actions)
+        if let Some(emoji) = self.view.emoji_picker(cx, ids!(emoji_picker)).picked(actions) {
+            cx.widget_action(
+                details.room_screen_widget_uid,
+                MessageAction::React {
+                    details: details.clone(),
+                    reaction: emoji,
+                },
+            );
+            close_menu = true;
+        }
+        else if reaction_send_button.clicked(actions)
             || reaction_text_input.returned(actions).is_some()
         {
             cx.widget_action(
@@ -382,12 +450,7 @@ impl WidgetMatchEvent for NewMessageContextMenu {
             close_menu = true;
         }
         else if self.button(cx, ids!(react_button)).clicked(actions) {
-            // Show a box to allow the user to input the reaction.
-            // In the future, we'll show an emoji chooser.
-            self.view.button(cx, ids!(react_button)).set_visible(cx, false);
-            self.view.view(cx, ids!(reaction_input_view)).set_visible(cx, true);
-            self.text_input(cx, ids!(reaction_input_view.reaction_text_input)).set_key_focus(cx);
-            self.redraw(cx);
+            self.show_reaction_input(cx);
             close_menu = false;
         }
         else if self.button(cx, ids!(reply_button)).clicked(actions) {
@@ -427,6 +490,20 @@ impl WidgetMatchEvent for NewMessageContextMenu {
             }
             close_menu = true;
         }
+        else if self.button(cx, ids!(forward_button)).clicked(actions) {
+            cx.widget_action(
+                details.room_screen_widget_uid,
+                MessageAction::Forward(details.clone()),
+            );
+            close_menu = true;
+        }
+        else if self.button(cx, ids!(bookmark_button)).clicked(actions) {
+            cx.widget_action(
+                details.room_screen_widget_uid,
+                MessageAction::ToggleBookmark(details.clone()),
+            );
+            close_menu = true;
+        }
         else if self.button(cx, ids!(copy_text_button)).clicked(actions) {
             cx.widget_action(
                 details.room_screen_widget_uid, 
@@ -462,24 +539,26 @@ impl WidgetMatchEvent for NewMessageContextMenu {
             );
             close_menu = true;
         }
-        // else if self.button(cx, ids!(report_button)).clicked(actions) {
-        //     cx.widget_action(
-        //         details.room_screen_widget_uid,
-        //         &scope.path,
-        //         // TODO: display a dialog to confirm the report reason.
-        //         MessageAction::Report {
-        //             event_id: details.event_id.clone(),
-        //             item_id: details.item_id,
-        //         },
-        //     );
-        //    close_menu = true;
-        // }
+        else if self.button(cx, ids!(report_button)).clicked(actions) {
+            cx.widget_action(
+                details.room_screen_widget_uid,
+                MessageAction::Report(details.clone()),
+            );
+            close_menu = true;
+        }
+        else if self.button(cx, ids!(remove_recent_button)).clicked(actions) {
+            cx.widget_action(
+                details.room_screen_widget_uid,
+                MessageAction::RemoveRecentMessages(details.clone()),
+            );
+            close_menu = true;
+        }
         else if self.button(cx, ids!(delete_button)).clicked(actions) {
             cx.widget_action(
                 details.room_screen_widget_uid, 
                 MessageAction::Redact {
                     details: details.clone(),
-                    // TODO: show a Modal to confirm deletion, and get the reason.
+                    // The reason is entered by the user in the deletion confirmation modal.
                     reason: None,
                 },
             );
@@ -503,6 +582,8 @@ impl NewMessageContextMenu {
             .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.reply"));
         self.view.button(cx, ids!(edit_message_button))
             .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.edit_message"));
+        self.view.button(cx, ids!(forward_button))
+            .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.forward"));
         self.view.button(cx, ids!(copy_text_button))
             .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.copy_text"));
         self.view.button(cx, ids!(copy_html_button))
@@ -513,6 +594,10 @@ impl NewMessageContextMenu {
             .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.view_source"));
         self.view.button(cx, ids!(jump_to_related_button))
             .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.jump_related"));
+        self.view.button(cx, ids!(report_button))
+            .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.report"));
+        self.view.button(cx, ids!(remove_recent_button))
+            .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.remove_recent"));
         self.view.button(cx, ids!(delete_button))
             .set_text(cx, tr_key(self.app_language, "new_message_context_menu.button.delete"));
     }
@@ -534,9 +619,25 @@ impl NewMessageContextMenu {
         cx.set_key_focus(self.view.area());
 
         // log!("Showing context menu for message: {:?}", self.details);
-        let height = self.set_button_visibility(cx);
+        self.expected_height = self.set_button_visibility(cx);
 
-        dvec2(MENU_WIDTH, height)
+        dvec2(MENU_WIDTH, self.expected_height)
+    }
+
+    /// Replaces the "Add Reaction" button with a box to allow the user to input the reaction,
+    /// and shows an emoji picker below it.
+    ///
+    /// Returns the expected (approximate) dimensions of the context menu
+    /// now that the emoji picker is shown.
+    pub fn show_reaction_input(&mut self, cx: &mut Cx) -> DVec2 {
+        self.view.button(cx, ids!(react_button)).set_visible(cx, false);
+        self.view.view(cx, ids!(reaction_input_view)).set_visible(cx, true);
+        let emoji_picker = self.view.emoji_picker(cx, ids!(emoji_picker));
+        emoji_picker.reset(cx);
+        emoji_picker.set_visible(cx, true);
+        self.text_input(cx, ids!(reaction_input_view.reaction_text_input)).set_key_focus(cx);
+        self.redraw(cx);
+        dvec2(MENU_WIDTH, self.expected_height + EMOJI_PICKER_HEIGHT)
     }
 
     /// Sets up all of the buttons based this context menu's inner details.
@@ -550,12 +651,15 @@ impl NewMessageContextMenu {
         let thread_button = self.view.button(cx, ids!(thread_button));
         let edit_button = self.view.button(cx, ids!(edit_message_button));
         let pin_button = self.view.button(cx, ids!(pin_button));
+        let forward_button = self.view.button(cx, ids!(forward_button));
+        let bookmark_button = self.view.button(cx, ids!(bookmark_button));
         let copy_text_button = self.view.button(cx, ids!(copy_text_button));
         let copy_html_button = self.view.button(cx, ids!(copy_html_button));
         let copy_link_button = self.view.button(cx, ids!(copy_link_to_message_button));
         let view_source_button = self.view.button(cx, ids!(view_source_button));
         let jump_to_related_button = self.view.button(cx, ids!(jump_to_related_button));
-        // let report_button = self.view.button(cx, ids!(report_button));
+        let report_button = self.view.button(cx, ids!(report_button));
+        let remove_recent_button = self.view.button(cx, ids!(remove_recent_button));
         let delete_button = self.view.button(cx, ids!(delete_button));
 
         // Determine which buttons should be shown.
@@ -567,14 +671,17 @@ impl NewMessageContextMenu {
         let show_divider_after_react_reply = show_react || show_reply_to || show_thread;
         let show_edit = details.abilities.contains(MessageAbilities::CanEdit);
         let show_pin: bool;
+        let show_forward = details.abilities.contains(MessageAbilities::CanForward);
+        let show_bookmark = details.abilities.contains(MessageAbilities::CanBookmark);
         let show_copy_text = true;
         let show_copy_html = details.abilities.contains(MessageAbilities::HasHtml);
         let show_copy_link = true;
         let show_view_source = true;
         let show_jump_to_related = details.related_event_id.is_some();
-        // let show_report = true;
+        let show_report = details.abilities.contains(MessageAbilities::CanReport);
         let show_delete = details.abilities.contains(MessageAbilities::CanDelete);
-        let show_divider_before_report_delete = show_delete; // || show_report;
+        let show_remove_recent = details.abilities.contains(MessageAbilities::CanRemoveRecentMessages);
+        let show_divider_before_report_delete = show_delete || show_report || show_remove_recent;
 
         // Actually set the buttons' visibility.
         self.view.view(cx, ids!(react_view)).set_visible(cx, show_react);
@@ -598,10 +705,21 @@ impl NewMessageContextMenu {
             show_pin = false;
         }
         pin_button.set_visible(cx, show_pin);
+        forward_button.set_visible(cx, show_forward);
+        bookmark_button.set_visible(cx, show_bookmark);
+        bookmark_button.set_text(cx, tr_key(
+            self.app_language,
+            if details.abilities.contains(MessageAbilities::IsBookmarked) {
+                "new_message_context_menu.button.remove_bookmark"
+            } else {
+                "new_message_context_menu.button.bookmark"
+            },
+        ));
         copy_html_button.set_visible(cx, show_copy_html);
         jump_to_related_button.set_visible(cx, show_jump_to_related);
         self.view.view(cx, ids!(divider_before_report_delete)).set_visible(cx, show_divider_before_report_delete);
-        // report_button.set_visible(cx, show_report);
+        report_button.set_visible(cx, show_report);
+        remove_recent_button.set_visible(cx, show_remove_recent);
         delete_button.set_visible(cx, show_delete);
 
         // Reset the hover state of each button.
@@ -610,17 +728,21 @@ impl NewMessageContextMenu {
         thread_button.reset_hover(cx);
         edit_button.reset_hover(cx);
         pin_button.reset_hover(cx);
+        forward_button.reset_hover(cx);
+        bookmark_button.reset_hover(cx);
         copy_text_button.reset_hover(cx);
         copy_html_button.reset_hover(cx);
         copy_link_button.reset_hover(cx);
         view_source_button.reset_hover(cx);
         jump_to_related_button.reset_hover(cx);
-        // report_button.reset_hover(cx);
+        report_button.reset_hover(cx);
+        remove_recent_button.reset_hover(cx);
         delete_button.reset_hover(cx);
 
         // Reset reaction input view stuff.
         self.view.view(cx, ids!(reaction_input_view)).set_visible(cx, false); // hide until the react_button is clicked
         self.text_input(cx, ids!(reaction_input_view.reaction_text_input)).set_text(cx, "");
+        self.view.emoji_picker(cx, ids!(emoji_picker)).set_visible(cx, false);
 
         self.redraw(cx);
 
@@ -630,12 +752,15 @@ impl NewMessageContextMenu {
             + show_thread as u8
             + show_edit as u8
             + show_pin as u8
+            + show_forward as u8
+            + show_bookmark as u8
             + show_copy_text as u8
             + show_copy_html as u8
             + show_copy_link as u8
             + show_view_source as u8
             + show_jump_to_related as u8
-            // + show_report as u8
+            + show_report as u8
+            + show_remove_recent as u8
             + show_delete as u8;
 
         // Calculate and return the total expected height:
@@ -667,4 +792,10 @@ impl NewMessageContextMenuRef {
         let Some(mut inner) = self.borrow_mut() else { return DVec2::default()};
         inner.show(cx, details, app_language, opening_gesture)
     }
+
+    /// See [`NewMessageContextMenu::show_reaction_input()`].
+    pub fn show_reaction_input(&self, cx: &mut Cx) -> DVec2 {
+        let Some(mut inner) = self.borrow_mut() else { return DVec2::default() };
+        inner.show_reaction_input(cx)
+    }
 }
@@ -0,0 +1,570 @@
+//! Widgets for composing a new poll and for editing the answers of an existing poll.
+//!
+//! The `CreatePollModal` is opened from the `RoomInputBar`'s "more actions" popup
+//! and sends an `m.poll.start` event via [`MatrixRequest::SendPoll`].
+//! The `PollAnswersEditor` is shared with the `EditingPane`,
+//! which uses it to edit the answers of the current user's own polls.
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::events::poll::{
+    start::PollKind,
+    unstable_start::{UnstablePollAnswer, UnstablePollAnswers},
+};
+
+use crate::{
+    LivePtr, widget_ref_from_live_ptr,
+    app::AppState,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    sliding_sync::{submit_async_request, MatrixRequest, TimelineKind},
+};
+
+/// The minimum number of answers that a poll must have.
+pub const MIN_POLL_ANSWERS: usize = 2;
+/// The maximum number of answers that a poll can have.
+pub const MAX_POLL_ANSWERS: usize = 20;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.PollAnswersEditor = #(PollAnswersEditor::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 6.0
+
+        answer: View {
+            width: Fill,
+            height: Fit,
+            flow: Right,
+            spacing: 6.0
+            align: Align{ y: 0.5 }
+
+            answer_input := RobrixTextInput {
+                width: Fill
+                padding: 8
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 11 }
+                    color: #000
+                }
+                empty_text: ""
+            }
+
+            remove_button := RobrixNeutralIconButton {
+                width: Fit,
+                height: Fit,
+                padding: 10,
+                spacing: 0,
+                draw_icon.svg: (ICON_CLOSE)
+                icon_walk: Walk{width: 12, height: 12, margin: 0}
+                text: ""
+            }
+        }
+    }
+
+    mod.widgets.CreatePollModal = #(CreatePollModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 448
+            height: Fit
+            align: Align{x: 0.5}
+            flow: Down
+            padding: Inset{top: 28, right: 24, bottom: 20, left: 24}
+            spacing: 12
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 6.0
+            }
+
+            title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 13 }
+                    color: #000
+                }
+                text: "Create Poll"
+            }
+
+            question_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.5 }
+                    color: #333
+                }
+                text: "Question"
+            }
+
+            question_input := RobrixTextInput {
+                width: Fill
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 11.5 }
+                    color: #000
+                }
+                empty_text: ""
+            }
+
+            answers_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.5 }
+                    color: #333
+                }
+                text: "Answers"
+            }
+
+            ScrollYView {
+                width: Fill
+                height: Fit{max: 320}
+
+                answers_editor := mod.widgets.PollAnswersEditor {}
+            }
+
+            add_answer_button := RobrixNeutralIconButton {
+                width: Fit
+                padding: Inset{top: 8, bottom: 8, left: 12, right: 12}
+                draw_icon.svg: (ICON_ADD)
+                icon_walk: Walk{width: 12, height: 12, margin: Inset{right: -2}}
+                text: "Add answer"
+            }
+
+            undisclosed_row := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{y: 0.5}
+                spacing: (SPACE_XS)
+
+                undisclosed_switch := Toggle {
+                    width: Fit
+                    height: Fit
+                    padding: Inset{top: (SPACE_SM), right: (SPACE_SM), bottom: (SPACE_SM), left: (SPACE_SM)}
+                    text: ""
+                    active: false
+                    draw_bg +: {
+                        size: 18.0
+                        color_active: (COLOR_ACTIVE_PRIMARY)
+                        border_color_active: (COLOR_ACTIVE_PRIMARY)
+                        mark_color_active: #fff
+                    }
+                }
+
+                undisclosed_label := Label {
+                    width: Fill
+                    height: Fit
+                    flow: Flow.Right{wrap: true},
+                    draw_text +: {
+                        text_style: REGULAR_TEXT { font_size: 10.5 }
+                        color: #333
+                    }
+                    text: "Hide results until the poll ends"
+                }
+            }
+
+            status_label := Label {
+                width: Fill
+                height: Fit
+                flow: Flow.Right{wrap: true},
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.5 }
+                    color: (COLOR_FG_DANGER_RED)
+                }
+                text: ""
+            }
+
+            buttons := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{x: 1.0, y: 0.5}
+                spacing: 16
+
+                cancel_button := RobrixNeutralIconButton {
+                    width: 110
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORBIDDEN)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Cancel"
+                }
+
+                create_button := RobrixPositiveIconButton {
+                    width: 130
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_CHECKMARK)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Create Poll"
+                }
+            }
+        }
+    }
+}
+
+/// An answer being composed in a `PollAnswersEditor`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PollAnswerDraft {
+    /// The ID of the existing poll answer that this draft edits, if any.
+    pub id: Option<String>,
+    pub text: String,
+}
+
+/// Converts the given answer drafts into the answers of an `m.poll.start` event.
+///
+/// Blank answers are ignored. Drafts of existing answers keep their answer ID,
+/// such that votes already cast for them remain valid after an edit;
+/// all other answers are given a new random ID.
+///
+/// Returns `None` if there are fewer than [`MIN_POLL_ANSWERS`]
+/// or more than [`MAX_POLL_ANSWERS`] non-blank answers.
+pub fn poll_answers_from_drafts(drafts: Vec<PollAnswerDraft>) -> Option<UnstablePollAnswers> {
+    let drafts: Vec<PollAnswerDraft> = drafts.into_iter()
+        .map(|draft| PollAnswerDraft { text: draft.text.trim().to_owned(), ..draft })
+        .filter(|draft| !draft.text.is_empty())
+        .collect();
+    if !(MIN_POLL_ANSWERS..=MAX_POLL_ANSWERS).contains(&drafts.len()) {
+        return None;
+    }
+
+    let mut used_ids: Vec<String> = drafts.iter().filter_map(|draft| draft.id.clone()).collect();
+    let answers: Vec<UnstablePollAnswer> = drafts.into_iter()
+        .map(|draft| {
+            let id = draft.id.unwrap_or_else(|| {
+                let new_id = loop {
+                    let id = random_answer_id();
+                    if !used_ids.contains(&id) { break id; }
+                };
+                used_ids.push(new_id.clone());
+                new_id
+            });
+            UnstablePollAnswer::new(id, draft.text)
+        })
+        .collect();
+    answers.try_into().ok()
+}
+
+fn random_answer_id() -> String {
+    use rand::{Rng, thread_rng};
+    thread_rng()
+        .sample_iter(rand::distributions::Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect()
+}
+
+/// A list of text inputs for composing a poll's answers,
+/// each of which can be removed as long as at least [`MIN_POLL_ANSWERS`] remain.
+#[derive(Script, ScriptHook, Widget)]
+pub struct PollAnswersEditor {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] answer: Option<LivePtr>,
+    /// Each answer's widget alongside the ID of the existing answer that it edits, if any.
+    #[rust] children: Vec<(WidgetRef, Option<String>)>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+
+    #[rust] app_language: AppLanguage,
+}
+
+impl Widget for PollAnswersEditor {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_placeholders(cx);
+        }
+        cx.begin_turtle(walk, self.layout);
+        for (answer_ref, _) in self.children.iter() {
+            answer_ref.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        for (answer_ref, _) in self.children.iter() {
+            answer_ref.handle_event(cx, event, scope);
+        }
+
+        if let Event::Actions(actions) = event
+            && self.children.len() > MIN_POLL_ANSWERS
+            && let Some(index) = self.children.iter().position(|(answer_ref, _)|
+                answer_ref.button(cx, ids!(remove_button)).clicked(actions)
+            )
+        {
+            self.children.remove(index);
+            self.update_placeholders(cx);
+            self.redraw(cx);
+        }
+    }
+}
+
+impl PollAnswersEditor {
+    /// Appends a new answer input with the given draft's text.
+    fn push_answer(&mut self, cx: &mut Cx, draft: PollAnswerDraft) {
+        let answer_ref = widget_ref_from_live_ptr(cx, self.answer);
+        answer_ref.text_input(cx, ids!(answer_input)).set_text(cx, &draft.text);
+        self.children.push((answer_ref, draft.id));
+    }
+
+    /// Numbers each answer's placeholder text and only allows removing answers
+    /// if there are more than the minimum number of answers.
+    fn update_placeholders(&mut self, cx: &mut Cx) {
+        let can_remove = self.children.len() > MIN_POLL_ANSWERS;
+        for (index, (answer_ref, _)) in self.children.iter().enumerate() {
+            answer_ref.text_input(cx, ids!(answer_input)).set_empty_text(
+                cx,
+                tr_fmt(self.app_language, "poll_composer.answer.placeholder", &[("index", &(index + 1).to_string())]),
+            );
+            answer_ref.button(cx, ids!(remove_button)).set_visible(cx, can_remove);
+        }
+    }
+}
+
+impl PollAnswersEditorRef {
+    /// Replaces all answer inputs with the given drafts,
+    /// padded with empty answers up to the minimum number of answers.
+    pub fn set_answers(&self, cx: &mut Cx, drafts: Vec<PollAnswerDraft>) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.children.clear();
+        let num_drafts = drafts.len();
+        for draft in drafts.into_iter().take(MAX_POLL_ANSWERS) {
+            inner.push_answer(cx, draft);
+        }
+        for _ in num_drafts..MIN_POLL_ANSWERS {
+            inner.push_answer(cx, PollAnswerDraft::default());
+        }
+        inner.update_placeholders(cx);
+        inner.redraw(cx);
+    }
+
+    /// Adds an empty answer input, unless the maximum number of answers has been reached.
+    ///
+    /// Returns `true` if an answer input was added.
+    pub fn add_answer(&self, cx: &mut Cx) -> bool {
+        let Some(mut inner) = self.borrow_mut() else { return false };
+        if inner.children.len() >= MAX_POLL_ANSWERS {
+            return false;
+        }
+        inner.push_answer(cx, PollAnswerDraft::default());
+        inner.update_placeholders(cx);
+        if let Some((answer_ref, _)) = inner.children.last() {
+            answer_ref.text_input(cx, ids!(answer_input)).set_key_focus(cx);
+        }
+        inner.redraw(cx);
+        true
+    }
+
+    /// Returns the current content of all answer inputs.
+    pub fn answers(&self) -> Vec<PollAnswerDraft> {
+        let Some(inner) = self.borrow() else { return Vec::new() };
+        inner.children.iter()
+            .map(|(answer_ref, id)| PollAnswerDraft {
+                id: id.clone(),
+                text: answer_ref.child_by_path(ids!(answer_input)).as_text_input().text(),
+            })
+            .collect()
+    }
+
+    /// Returns whether any answer input was changed by the given actions.
+    pub fn changed(&self, actions: &Actions) -> bool {
+        let Some(inner) = self.borrow() else { return false };
+        inner.children.iter().any(|(answer_ref, _)|
+            answer_ref.child_by_path(ids!(answer_input)).as_text_input().changed(actions).is_some()
+        )
+    }
+}
+
+
+/// Actions emitted by other widgets to show or hide the `CreatePollModal`.
+#[derive(Clone, Debug)]
+pub enum CreatePollModalAction {
+    /// Open the modal to create a new poll in the given timeline.
+    Open {
+        timeline_kind: TimelineKind,
+    },
+    /// Close the modal.
+    Close,
+}
+
+/// A modal for composing a new poll: its question, its answers,
+/// and whether its results are visible before the poll ends.
+#[derive(Script, ScriptHook, Widget)]
+pub struct CreatePollModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    /// The timeline that the new poll will be sent to.
+    #[rust] timeline_kind: Option<TimelineKind>,
+}
+
+impl Widget for CreatePollModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for CreatePollModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let cancel_clicked = self.view.button(cx, ids!(buttons.cancel_button)).clicked(actions);
+        if cancel_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // a `CreatePollModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if cancel_clicked {
+                cx.action(CreatePollModalAction::Close);
+            }
+            return;
+        }
+
+        let question_input = self.view.text_input(cx, ids!(question_input));
+        let answers_editor = self.view.poll_answers_editor(cx, ids!(answers_editor));
+
+        if question_input.changed(actions).is_some() || answers_editor.changed(actions) {
+            self.set_status(cx, "");
+        }
+
+        if self.view.button(cx, ids!(add_answer_button)).clicked(actions) {
+            if !answers_editor.add_answer(cx) {
+                self.set_status(cx, &tr_fmt(
+                    self.app_language,
+                    "poll_composer.error.too_many_answers",
+                    &[("max", &MAX_POLL_ANSWERS.to_string())],
+                ));
+            }
+            self.view.redraw(cx);
+        }
+
+        if self.view.button(cx, ids!(buttons.create_button)).clicked(actions) {
+            let question = question_input.text().trim().to_owned();
+            if question.is_empty() {
+                self.set_status(cx, tr_key(self.app_language, "poll_composer.error.no_question"));
+                return;
+            }
+            let Some(answers) = poll_answers_from_drafts(answers_editor.answers()) else {
+                self.set_status(cx, &tr_fmt(
+                    self.app_language,
+                    "poll_composer.error.answer_count",
+                    &[("min", &MIN_POLL_ANSWERS.to_string()), ("max", &MAX_POLL_ANSWERS.to_string())],
+                ));
+                return;
+            };
+            let Some(timeline_kind) = self.timeline_kind.clone() else { return };
+            let kind = if self.view.check_box(cx, ids!(undisclosed_switch)).active(cx) {
+                PollKind::Undisclosed
+            } else {
+                PollKind::Disclosed
+            };
+            submit_async_request(MatrixRequest::SendPoll {
+                timeline_kind,
+                question,
+                answers,
+                kind,
+            });
+            cx.action(CreatePollModalAction::Close);
+        }
+    }
+}
+
+impl CreatePollModal {
+    /// Shows an empty poll composer that will send its poll to the given timeline.
+    pub fn show(&mut self, cx: &mut Cx, timeline_kind: TimelineKind, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.timeline_kind = Some(timeline_kind);
+        self.update_static_texts(cx);
+        self.view.text_input(cx, ids!(question_input)).set_text(cx, "");
+        self.view.poll_answers_editor(cx, ids!(answers_editor)).set_answers(cx, Vec::new());
+        self.view.check_box(cx, ids!(undisclosed_switch)).set_active(cx, false);
+        self.set_status(cx, "");
+        self.view.button(cx, ids!(buttons.create_button)).reset_hover(cx);
+        self.view.button(cx, ids!(buttons.cancel_button)).reset_hover(cx);
+        self.view.text_input(cx, ids!(question_input)).set_key_focus(cx);
+        self.view.redraw(cx);
+    }
+
+    fn set_status(&mut self, cx: &mut Cx, text: &str) {
+        self.view.label(cx, ids!(status_label)).set_text(cx, text);
+        self.view.redraw(cx);
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(title))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.title"));
+        self.view.label(cx, ids!(question_label))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.question.label"));
+        self.view.text_input(cx, ids!(question_input))
+            .set_empty_text(cx, tr_key(self.app_language, "poll_composer.question.placeholder").to_string());
+        self.view.label(cx, ids!(answers_label))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.answers.label"));
+        self.view.button(cx, ids!(add_answer_button))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.button.add_answer"));
+        self.view.label(cx, ids!(undisclosed_label))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.undisclosed.label"));
+        self.view.button(cx, ids!(buttons.cancel_button))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.button.cancel"));
+        self.view.button(cx, ids!(buttons.create_button))
+            .set_text(cx, tr_key(self.app_language, "poll_composer.button.create"));
+    }
+}
+
+impl CreatePollModalRef {
+    /// See [`CreatePollModal::show()`].
+    pub fn show(&self, cx: &mut Cx, timeline_kind: TimelineKind, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, timeline_kind, app_language);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draft(id: Option<&str>, text: &str) -> PollAnswerDraft {
+        PollAnswerDraft { id: id.map(str::to_owned), text: text.to_owned() }
+    }
+
+    #[test]
+    fn poll_answers_keep_existing_ids_and_skip_blank_answers() {
+        let answers = poll_answers_from_drafts(vec![
+            draft(Some("a"), " Yes "),
+            draft(None, "   "),
+            draft(None, "No"),
+            draft(None, "Maybe"),
+        ]).expect("three non-blank answers should be valid");
+
+        assert_eq!(answers.len(), 3);
+        assert_eq!(answers[0].id, "a");
+        assert_eq!(answers[0].text, "Yes");
+        assert_eq!(answers[1].text, "No");
+        assert!(!answers[1].id.is_empty());
+        assert_ne!(answers[1].id, answers[2].id);
+    }
+
+    #[test]
+    fn poll_answers_require_between_min_and_max_answers() {
+        assert!(poll_answers_from_drafts(vec![draft(None, "Only one"), draft(None, "")]).is_none());
+        let too_many = (0..=MAX_POLL_ANSWERS).map(|i| draft(None, &i.to_string())).collect();
+        assert!(poll_answers_from_drafts(too_many).is_none());
+    }
+}
@@ -0,0 +1,482 @@
+//! A view that renders a poll in a room timeline, including its question,
+//! answers, live vote tallies, and which answer(s) the current user voted for.
+//!
+//! Clicking on an answer casts (or changes) the current user's vote,
+//! and the sender of the poll can also end it from this view.
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::{OwnedEventId, events::poll::start::PollKind};
+use matrix_sdk_ui::timeline::PollState;
+
+use crate::{
+    LivePtr, widget_ref_from_live_ptr,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    shared::{progress_bar::ProgressBarWidgetRefExt, styles::*},
+    sliding_sync::{current_user_id, submit_async_request, MatrixRequest, TimelineKind},
+};
+
+/// The fallback text sent in the `m.poll.end` event when ending a poll,
+/// which is shown by clients that don't support polls.
+const POLL_END_FALLBACK_TEXT: &str = "The poll has ended.";
+
+const COLOR_POLL_ANSWER_BG: Vec4 = vec4(0.965, 0.969, 0.976, 1.0);
+const COLOR_POLL_ANSWER_BG_HOVER: Vec4 = vec4(0.925, 0.937, 0.957, 1.0);
+const COLOR_POLL_ANSWER_BG_SELECTED: Vec4 = COLOR_BG_PREVIEW;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.PollAnswerList = #(PollAnswerList::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 5.0
+
+        answer: RoundedView {
+            width: Fill,
+            height: Fit,
+            flow: Down,
+            spacing: 5.0
+            padding: Inset{ left: 10, right: 10, top: 7, bottom: 7 }
+            cursor: MouseCursor.Hand
+            show_bg: true
+            draw_bg +: {
+                color: #F6F7F9
+                border_radius: 5.0
+                border_size: 1.0
+                border_color: #DDE1E6
+            }
+
+            View {
+                width: Fill,
+                height: Fit,
+                flow: Right,
+                spacing: 8.0
+                align: Align{ y: 0.5 }
+
+                selection_label := Label {
+                    width: Fit,
+                    height: Fit,
+                    draw_text +: {
+                        color: (COLOR_ACTIVE_PRIMARY),
+                        text_style: MESSAGE_TEXT_STYLE { font_size: 11.0 },
+                    }
+                    text: "○"
+                }
+
+                answer_text := Label {
+                    width: Fill,
+                    height: Fit,
+                    flow: Flow.Right{wrap: true},
+                    draw_text +: {
+                        color: (MESSAGE_TEXT_COLOR),
+                        text_style: MESSAGE_TEXT_STYLE {},
+                    }
+                    text: ""
+                }
+
+                vote_count_label := Label {
+                    width: Fit,
+                    height: Fit,
+                    draw_text +: {
+                        color: (COLOR_MESSAGE_NOTICE_TEXT),
+                        text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+                    }
+                    text: ""
+                }
+            }
+
+            tally_bar := ProgressBar {
+                width: Fill,
+                height: 5,
+            }
+        }
+    }
+
+    mod.widgets.PollView = #(PollView::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 7.0
+        padding: Inset{ top: 2, bottom: 2, right: 10 }
+
+        question_label := Label {
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (MESSAGE_TEXT_COLOR),
+                text_style: theme.font_bold { font_size: 11.0 },
+            }
+            text: ""
+        }
+
+        hint_label := Label {
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (COLOR_MESSAGE_NOTICE_TEXT),
+                text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+            }
+            text: ""
+        }
+
+        answer_list := mod.widgets.PollAnswerList {}
+
+        footer := View {
+            width: Fill,
+            height: Fit,
+            flow: Right,
+            spacing: 10.0
+            align: Align{ y: 0.5 }
+
+            footer_label := Label {
+                width: Fill,
+                height: Fit,
+                flow: Flow.Right{wrap: true},
+                draw_text +: {
+                    color: (COLOR_MESSAGE_NOTICE_TEXT),
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+                }
+                text: ""
+            }
+
+            end_poll_button := RobrixNeutralIconButton {
+                visible: false
+                padding: Inset{ top: 6, bottom: 6, left: 12, right: 12 }
+                icon_walk: Walk{ width: 0, height: 0 }
+                spacing: 0
+                text: "End Poll"
+            }
+        }
+    }
+}
+
+/// The details of a poll needed to vote on it or end it.
+#[derive(Clone, Debug)]
+struct PollInfo {
+    timeline_kind: TimelineKind,
+    /// The ID of the `m.poll.start` event, which is `None` if the poll hasn't been sent yet.
+    poll_start_event_id: Option<OwnedEventId>,
+    /// The IDs of the answers that the current user has voted for.
+    own_votes: Vec<String>,
+    max_selections: usize,
+    has_ended: bool,
+}
+
+/// A list of a poll's answers, which can be clicked on to vote for them.
+#[derive(Script, ScriptHook, Widget)]
+pub struct PollAnswerList {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] answer: Option<LivePtr>,
+    /// Each answer's widget alongside that answer's ID.
+    #[rust] children: Vec<(WidgetRef, String)>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+
+    #[rust] poll_info: Option<PollInfo>,
+}
+
+impl Widget for PollAnswerList {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+        for (answer_ref, _) in self.children.iter() {
+            answer_ref.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        let Some(poll_info) = self.poll_info.as_ref() else { return };
+        let can_vote = !poll_info.has_ended && poll_info.poll_start_event_id.is_some();
+        if !can_vote {
+            return;
+        }
+
+        let mut voted_answer_id = None;
+        for (answer_ref, answer_id) in self.children.iter() {
+            let is_selected = poll_info.own_votes.contains(answer_id);
+            // Note: the `break` statements are used to break out of the loop over
+            // all answers, since a hit event can only occur on one answer.
+            match event.hits(cx, answer_ref.area()) {
+                Hit::FingerDown(_) => {
+                    cx.set_key_focus(answer_ref.area());
+                    break;
+                }
+                Hit::FingerHoverIn(_) => {
+                    if !is_selected {
+                        set_answer_bg_color(cx, answer_ref, COLOR_POLL_ANSWER_BG_HOVER);
+                    }
+                    cx.set_cursor(MouseCursor::Hand);
+                    break;
+                }
+                Hit::FingerHoverOut(_) => {
+                    if !is_selected {
+                        set_answer_bg_color(cx, answer_ref, COLOR_POLL_ANSWER_BG);
+                    }
+                    break;
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.is_primary_hit() && fe.was_tap() => {
+                    voted_answer_id = Some(answer_id.clone());
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(answer_id) = voted_answer_id {
+            self.vote_for(cx, answer_id);
+        }
+    }
+}
+
+impl PollAnswerList {
+    /// Casts the current user's vote for the given answer, replacing their previous vote.
+    ///
+    /// For polls that allow multiple selections, this instead toggles whether
+    /// the given answer is among the current user's selections.
+    /// Deselecting the user's only selected answer is ignored, because a response
+    /// without any answers is treated as a spoiled vote rather than a retraction.
+    fn vote_for(&mut self, cx: &mut Cx, answer_id: String) {
+        let Some(poll_info) = self.poll_info.as_mut() else { return };
+        let Some(poll_start_event_id) = poll_info.poll_start_event_id.clone() else { return };
+
+        let new_votes = if poll_info.max_selections <= 1 {
+            if poll_info.own_votes == [answer_id.clone()] {
+                return;
+            }
+            vec![answer_id]
+        } else {
+            let mut votes = poll_info.own_votes.clone();
+            if let Some(pos) = votes.iter().position(|id| id == &answer_id) {
+                if votes.len() == 1 {
+                    return;
+                }
+                votes.remove(pos);
+            } else if votes.len() < poll_info.max_selections {
+                votes.push(answer_id);
+            } else {
+                return;
+            }
+            votes
+        };
+
+        submit_async_request(MatrixRequest::SendPollResponse {
+            timeline_kind: poll_info.timeline_kind.clone(),
+            poll_start_event_id,
+            answer_ids: new_votes.clone(),
+        });
+
+        // Optimistically update the selected answers before the timeline is updated.
+        poll_info.own_votes = new_votes;
+        for (answer_ref, answer_id) in self.children.iter() {
+            set_answer_selected(cx, answer_ref, poll_info.own_votes.contains(answer_id));
+        }
+        self.redraw(cx);
+    }
+}
+
+impl PollAnswerListRef {
+    /// Replaces the displayed answers with the answers of the given poll.
+    fn set_answers(
+        &self,
+        cx: &mut Cx,
+        poll_state: &PollState,
+        poll_info: PollInfo,
+        show_tallies: bool,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        let results = poll_state.results();
+        let max_votes = results.answers.iter()
+            .map(|answer| results.votes.get(&answer.id).map_or(0, |voters| voters.len()))
+            .max()
+            .unwrap_or(0);
+        let total_voters = count_distinct_voters(poll_state);
+
+        inner.children.clear();
+        for answer in &results.answers {
+            let num_votes = results.votes.get(&answer.id).map_or(0, |voters| voters.len());
+            let answer_ref = widget_ref_from_live_ptr(cx, inner.answer);
+            answer_ref.label(cx, ids!(answer_text)).set_text(cx, &answer.text);
+
+            let vote_count_label = answer_ref.label(cx, ids!(vote_count_label));
+            let tally_bar = answer_ref.child_by_path(ids!(tally_bar));
+            vote_count_label.set_visible(cx, show_tallies);
+            tally_bar.set_visible(cx, show_tallies);
+            if show_tallies {
+                vote_count_label.set_text(cx, &num_votes.to_string());
+                let fraction = if total_voters == 0 { 0.0 } else { num_votes as f32 / total_voters as f32 };
+                tally_bar.as_progress_bar().set_progress(cx, fraction);
+            }
+
+            let is_selected = poll_info.own_votes.contains(&answer.id);
+            set_answer_selected(cx, &answer_ref, is_selected);
+            // Once a poll has ended, mark its winning answer(s) instead of our own votes.
+            if poll_info.has_ended && show_tallies && max_votes > 0 && num_votes == max_votes {
+                answer_ref.label(cx, ids!(selection_label)).set_text(cx, "★");
+            }
+            inner.children.push((answer_ref, answer.id.clone()));
+        }
+        inner.poll_info = Some(poll_info);
+        inner.redraw(cx);
+    }
+}
+
+/// A view that displays a poll and allows the user to vote on it.
+#[derive(Script, ScriptHook, Widget)]
+pub struct PollView {
+    #[deref] view: View,
+
+    #[rust] timeline_kind: Option<TimelineKind>,
+    #[rust] poll_start_event_id: Option<OwnedEventId>,
+}
+
+impl Widget for PollView {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for PollView {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let end_poll_button = self.view.button(cx, ids!(end_poll_button));
+        if end_poll_button.clicked(actions) {
+            let (Some(timeline_kind), Some(poll_start_event_id)) =
+                (self.timeline_kind.clone(), self.poll_start_event_id.clone())
+            else {
+                return;
+            };
+            submit_async_request(MatrixRequest::EndPoll {
+                timeline_kind,
+                poll_start_event_id,
+                fallback_text: POLL_END_FALLBACK_TEXT.to_string(),
+            });
+            end_poll_button.set_enabled(cx, false);
+        }
+    }
+}
+
+impl PollView {
+    /// Populates this view with the content and current results of the given poll.
+    fn set_poll(
+        &mut self,
+        cx: &mut Cx,
+        app_language: AppLanguage,
+        timeline_kind: &TimelineKind,
+        poll_start_event_id: Option<OwnedEventId>,
+        is_own_poll: bool,
+        poll_state: &PollState,
+    ) {
+        let results = poll_state.results();
+        let own_user_id = current_user_id();
+        let own_votes: Vec<String> = own_user_id.as_ref()
+            .map(|own_user_id| results.votes.iter()
+                .filter(|(_, voters)| voters.iter().any(|voter| voter.as_str() == own_user_id.as_str()))
+                .map(|(answer_id, _)| answer_id.clone())
+                .collect()
+            )
+            .unwrap_or_default();
+        let has_ended = results.end_time.is_some();
+        let is_disclosed = matches!(results.kind, PollKind::Disclosed);
+        // Undisclosed polls only reveal their results once they have ended.
+        let show_tallies = is_disclosed || has_ended;
+        let max_selections = usize::try_from(u64::from(results.max_selections)).unwrap_or(1).max(1);
+        let total_voters = count_distinct_voters(poll_state);
+
+        self.view.label(cx, ids!(question_label)).set_text(cx, &results.question);
+
+        let hint = if has_ended {
+            tr_key(app_language, "poll_view.hint.ended").to_string()
+        } else if max_selections > 1 {
+            tr_fmt(app_language, "poll_view.hint.select_multiple", &[("max", &max_selections.to_string())])
+        } else if is_disclosed {
+            tr_key(app_language, "poll_view.hint.disclosed").to_string()
+        } else {
+            tr_key(app_language, "poll_view.hint.undisclosed").to_string()
+        };
+        self.view.label(cx, ids!(hint_label)).set_text(cx, &hint);
+
+        let footer_text = if show_tallies {
+            tr_fmt(app_language, "poll_view.footer.total_votes", &[("count", &total_voters.to_string())])
+        } else {
+            tr_fmt(app_language, "poll_view.footer.votes_hidden", &[("count", &total_voters.to_string())])
+        };
+        self.view.label(cx, ids!(footer_label)).set_text(cx, &footer_text);
+
+        let end_poll_button = self.view.button(cx, ids!(end_poll_button));
+        end_poll_button.set_text(cx, tr_key(app_language, "poll_view.button.end_poll"));
+        end_poll_button.set_visible(cx, is_own_poll && !has_ended && poll_start_event_id.is_some());
+        end_poll_button.set_enabled(cx, true);
+
+        self.view.poll_answer_list(cx, ids!(answer_list)).set_answers(
+            cx,
+            poll_state,
+            PollInfo {
+                timeline_kind: timeline_kind.clone(),
+                poll_start_event_id: poll_start_event_id.clone(),
+                own_votes,
+                max_selections,
+                has_ended,
+            },
+            show_tallies,
+        );
+
+        self.timeline_kind = Some(timeline_kind.clone());
+        self.poll_start_event_id = poll_start_event_id;
+        self.view.redraw(cx);
+    }
+}
+
+impl PollViewRef {
+    /// See [`PollView::set_poll()`].
+    pub fn set_poll(
+        &self,
+        cx: &mut Cx,
+        app_language: AppLanguage,
+        timeline_kind: &TimelineKind,
+        poll_start_event_id: Option<OwnedEventId>,
+        is_own_poll: bool,
+        poll_state: &PollState,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.set_poll(cx, app_language, timeline_kind, poll_start_event_id, is_own_poll, poll_state);
+    }
+}
+
+/// Returns the number of distinct users who have voted in the given poll.
+fn count_distinct_voters(poll_state: &PollState) -> usize {
+    let results = poll_state.results();
+    let mut voters: Vec<&str> = results.votes.values()
+        .flat_map(|voters| voters.iter().map(|voter| voter.as_str()))
+        .collect();
+    voters.sort_unstable();
+    voters.dedup();
+    voters.len()
+}
+
+fn set_answer_bg_color(cx: &mut Cx, answer_ref: &WidgetRef, color: Vec4) {
+    let mut answer_ref = answer_ref.clone();
+    script_apply_eval!(cx, answer_ref, {
+        draw_bg.color: #(color)
+    });
+}
+
+/// Marks the given answer as selected (or not) by the current user.
+fn set_answer_selected(cx: &mut Cx, answer_ref: &WidgetRef, is_selected: bool) {
+    answer_ref.label(cx, ids!(selection_label))
+        .set_text(cx, if is_selected { "◉" } else { "○" });
+    set_answer_bg_color(
+        cx,
+        answer_ref,
+        if is_selected { COLOR_POLL_ANSWER_BG_SELECTED } else { COLOR_POLL_ANSWER_BG },
+    );
+}
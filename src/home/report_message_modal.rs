@@ -0,0 +1,354 @@
+//! A modal dialog for reporting a message to the homeserver administrators,
+//! with a reason, a severity, and an option to also ignore the message's sender.
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::{OwnedEventId, OwnedUserId};
+
+use crate::i18n::{AppLanguage, tr_fmt, tr_key};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.ReportMessageModalLabel = Label {
+        width: Fill
+        height: Fit
+        draw_text +: {
+            text_style: REGULAR_TEXT { font_size: 10.5 }
+            color: #333
+        }
+        text: ""
+    }
+
+    mod.widgets.ReportMessageModal = #(ReportMessageModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 430
+            height: Fit
+            align: Align{x: 0.5}
+            flow: Down
+            padding: Inset{top: 26, right: 22, bottom: 18, left: 22}
+            spacing: 14
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 6.0
+            }
+
+            title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 13 }
+                    color: #000
+                }
+                text: "Report Message"
+            }
+
+            body := mod.widgets.ReportMessageModalLabel {
+                text: ""
+            }
+
+            reason_input := RobrixTextInput {
+                width: Fill
+                height: Fit
+                padding: 10
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 11.5 }
+                    color: #000
+                }
+                empty_text: "Describe why you are reporting this message"
+            }
+
+            severity_label := mod.widgets.ReportMessageModalLabel {
+                text: "Severity"
+            }
+
+            severity_radio_buttons := View {
+                width: Fit
+                height: Fit
+                spacing: 20
+
+                severity_low := RadioButtonFlat {
+                    text: "Low"
+                }
+                severity_medium := RadioButtonFlat {
+                    text: "Medium"
+                    animator: { active: { default: on } }
+                }
+                severity_high := RadioButtonFlat {
+                    text: "High"
+                }
+            }
+
+            ignore_sender_row := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{y: 0.5}
+                spacing: 4
+
+                ignore_sender_toggle := Toggle {
+                    width: Fit
+                    height: Fit
+                    padding: 6
+                    text: ""
+                    active: false
+                    draw_bg +: {
+                        size: 18.0
+                        color_active: (COLOR_ACTIVE_PRIMARY)
+                        border_color_active: (COLOR_ACTIVE_PRIMARY)
+                        mark_color_active: #fff
+                    }
+                }
+
+                ignore_sender_label := mod.widgets.ReportMessageModalLabel {
+                    text: "Also ignore this user"
+                }
+            }
+
+            status_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10.2 }
+                    color: #000
+                }
+                text: ""
+            }
+
+            buttons := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{x: 1.0, y: 0.5}
+                spacing: 16
+
+                cancel_button := RobrixNeutralIconButton {
+                    width: 110
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORBIDDEN)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Cancel"
+                }
+
+                report_button := RobrixNegativeIconButton {
+                    width: 130
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_WARNING)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Report"
+                }
+            }
+        }
+    }
+}
+
+/// How offensive the user considers a reported message to be.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportSeverity {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl ReportSeverity {
+    /// Returns the report score for this severity,
+    /// which ranges from 0 (inoffensive) to -100 (most offensive).
+    pub fn score(self) -> i8 {
+        match self {
+            ReportSeverity::Low => -25,
+            ReportSeverity::Medium => -50,
+            ReportSeverity::High => -100,
+        }
+    }
+}
+
+/// A request to report a message, as entered by the user in the [`ReportMessageModal`].
+#[derive(Clone, Debug)]
+pub struct ReportMessageRequest {
+    pub event_id: OwnedEventId,
+    pub sender: OwnedUserId,
+    pub reason: String,
+    pub severity: ReportSeverity,
+    /// Whether to also ignore the sender of the reported message.
+    pub ignore_sender: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum ReportMessageModalAction {
+    Close,
+    Submit(ReportMessageRequest),
+}
+
+#[derive(Script, ScriptHook, Widget)]
+pub struct ReportMessageModal {
+    #[deref]
+    view: View,
+    #[rust]
+    is_showing_error: bool,
+    /// The event ID and sender of the message being reported.
+    #[rust]
+    reported_message: Option<(OwnedEventId, OwnedUserId)>,
+    #[rust]
+    severity: ReportSeverity,
+    #[rust]
+    app_language: AppLanguage,
+}
+
+impl Widget for ReportMessageModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for ReportMessageModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let cancel_button = self.view.button(cx, ids!(buttons.cancel_button));
+        let report_button = self.view.button(cx, ids!(buttons.report_button));
+        let reason_input = self.view.text_input(cx, ids!(reason_input));
+        let mut status_label = self.view.label(cx, ids!(status_label));
+
+        if cancel_button.clicked(actions)
+            || actions
+                .iter()
+                .any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            cx.action(ReportMessageModalAction::Close);
+            return;
+        }
+
+        let severity_radio_buttons = self.view.radio_button_set(cx, ids_array!(
+            severity_radio_buttons.severity_low,
+            severity_radio_buttons.severity_medium,
+            severity_radio_buttons.severity_high,
+        ));
+        match severity_radio_buttons.selected(cx, actions) {
+            Some(0) => self.severity = ReportSeverity::Low,
+            Some(1) => self.severity = ReportSeverity::Medium,
+            Some(2) => self.severity = ReportSeverity::High,
+            _ => { }
+        }
+
+        if self.is_showing_error && reason_input.changed(actions).is_some() {
+            self.is_showing_error = false;
+            status_label.set_text(cx, "");
+            self.view.redraw(cx);
+        }
+
+        if report_button.clicked(actions) || reason_input.returned(actions).is_some() {
+            let Some((event_id, sender)) = self.reported_message.clone() else { return };
+            let reason = reason_input.text().trim().to_string();
+            if reason.is_empty() {
+                self.is_showing_error = true;
+                let error_text = tr_key(self.app_language, "report_message_modal.error.empty_reason");
+                script_apply_eval!(cx, status_label, {
+                    text: #(error_text)
+                    draw_text +: {
+                        color: mod.widgets.COLOR_FG_DANGER_RED
+                    }
+                });
+                self.view.redraw(cx);
+                return;
+            }
+            cx.action(ReportMessageModalAction::Submit(ReportMessageRequest {
+                event_id,
+                sender,
+                reason,
+                severity: self.severity,
+                ignore_sender: self.view.check_box(cx, ids!(ignore_sender_row.ignore_sender_toggle)).active(cx),
+            }));
+        }
+    }
+}
+
+impl ReportMessageModal {
+    /// Resets and populates this modal to report the given message.
+    ///
+    /// The `sender_name` is the displayable name of the message's sender.
+    pub fn show(
+        &mut self,
+        cx: &mut Cx,
+        event_id: OwnedEventId,
+        sender: OwnedUserId,
+        sender_name: &str,
+        app_language: AppLanguage,
+    ) {
+        self.is_showing_error = false;
+        self.reported_message = Some((event_id, sender));
+        self.severity = ReportSeverity::default();
+        self.app_language = app_language;
+
+        self.view
+            .label(cx, ids!(title))
+            .set_text(cx, tr_key(app_language, "report_message_modal.title"));
+        self.view.label(cx, ids!(body)).set_text(
+            cx,
+            &tr_fmt(app_language, "report_message_modal.body", &[("sender", sender_name)]),
+        );
+        let reason_input = self.view.text_input(cx, ids!(reason_input));
+        reason_input.set_text(cx, "");
+        reason_input.set_empty_text(cx, tr_key(app_language, "report_message_modal.input.reason_placeholder").to_string());
+        self.view
+            .label(cx, ids!(severity_label))
+            .set_text(cx, tr_key(app_language, "report_message_modal.severity.label"));
+        self.view
+            .radio_button(cx, ids!(severity_radio_buttons.severity_low))
+            .set_text(cx, tr_key(app_language, "report_message_modal.severity.low"));
+        self.view
+            .radio_button(cx, ids!(severity_radio_buttons.severity_medium))
+            .set_text(cx, tr_key(app_language, "report_message_modal.severity.medium"));
+        self.view
+            .radio_button(cx, ids!(severity_radio_buttons.severity_high))
+            .set_text(cx, tr_key(app_language, "report_message_modal.severity.high"));
+        self.view
+            .radio_button(cx, ids!(severity_radio_buttons.severity_medium))
+            .select(cx, &mut Scope::empty());
+        self.view
+            .check_box(cx, ids!(ignore_sender_row.ignore_sender_toggle))
+            .set_active(cx, false);
+        self.view.label(cx, ids!(ignore_sender_row.ignore_sender_label)).set_text(
+            cx,
+            &tr_fmt(app_language, "report_message_modal.ignore_sender", &[("sender", sender_name)]),
+        );
+        self.view.label(cx, ids!(status_label)).set_text(cx, "");
+
+        let cancel_button = self.view.button(cx, ids!(buttons.cancel_button));
+        let report_button = self.view.button(cx, ids!(buttons.report_button));
+        cancel_button.set_text(cx, tr_key(app_language, "report_message_modal.button.cancel"));
+        report_button.set_text(cx, tr_key(app_language, "report_message_modal.button.report"));
+        cancel_button.set_enabled(cx, true);
+        report_button.set_enabled(cx, true);
+        cancel_button.reset_hover(cx);
+        report_button.reset_hover(cx);
+        self.view.redraw(cx);
+    }
+}
+
+impl ReportMessageModalRef {
+    /// See [`ReportMessageModal::show()`].
+    pub fn show(
+        &self,
+        cx: &mut Cx,
+        event_id: OwnedEventId,
+        sender: OwnedUserId,
+        sender_name: &str,
+        app_language: AppLanguage,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.show(cx, event_id, sender, sender_name, app_language);
+    }
+}
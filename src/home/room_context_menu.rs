@@ -3,7 +3,7 @@
 
 use makepad_widgets::*;
 use matrix_sdk::ruma::OwnedRoomId;
-use crate::{app::AppState, home::{bot_binding_modal::BotBindingModalAction, invite_modal::InviteModalAction}, i18n::{AppLanguage, tr_key}, shared::popup_list::{PopupKind, enqueue_popup_notification}, sliding_sync::{MatrixRequest, submit_async_request}, utils::RoomNameId};
+use crate::{app::{AppState, AppStateAction}, home::{bot_binding_modal::BotBindingModalAction, export_chat_modal::ExportChatModalAction, invite_modal::InviteModalAction, jump_to_date::JumpToDateModalAction}, i18n::{AppLanguage, tr_key}, room::BasicRoomDetails, shared::popup_list::{PopupKind, enqueue_popup_notification}, sliding_sync::{MatrixRequest, TimelineKind, submit_async_request}, utils::RoomNameId};
 
 use super::{ContextMenuOpenGesture, consume_context_menu_opening_finger_up};
 
@@ -106,6 +106,16 @@ script_mod! {
                 text: "Manage Bots"
             }
 
+            export_button := mod.widgets.RoomContextMenuButton {
+                draw_icon +: { svg: (ICON_UPLOAD) }
+                text: "Export Chat"
+            }
+
+            jump_to_date_button := mod.widgets.RoomContextMenuButton {
+                draw_icon +: { svg: (ICON_CALENDAR) }
+                text: "Jump to Date"
+            }
+
             divider2 := LineH {
                 margin: Inset{top: 3, bottom: 3}
                 width: Fill,
@@ -263,9 +273,23 @@ impl WidgetMatchEvent for RoomContextMenu {
             cx.action(BotBindingModalAction::Open(details.room_name_id.clone()));
             close_menu = true;
         }
+        else if self.button(cx, ids!(export_button)).clicked(actions) {
+            cx.action(ExportChatModalAction::Open(details.room_name_id.clone()));
+            close_menu = true;
+        }
+        else if self.button(cx, ids!(jump_to_date_button)).clicked(actions) {
+            // The room must be shown in order for its timeline to jump to the chosen date.
+            cx.action(AppStateAction::NavigateToRoom {
+                room_to_close: None,
+                destination_room: BasicRoomDetails::Name(details.room_name_id.clone()),
+            });
+            cx.action(JumpToDateModalAction::Open {
+                timeline_kind: TimelineKind::MainRoom { room_id: details.room_name_id.room_id().clone() },
+            });
+            close_menu = true;
+        }
         else if self.button(cx, ids!(leave_button)).clicked(actions) {
             use crate::join_leave_room_modal::{JoinLeaveRoomModalAction, JoinLeaveModalKind};
-            use crate::room::BasicRoomDetails;
             let room_details = BasicRoomDetails::Name(details.room_name_id.clone());
             cx.action(JoinLeaveRoomModalAction::Open {
                 kind: JoinLeaveModalKind::LeaveRoom(room_details),
@@ -325,6 +349,10 @@ impl RoomContextMenu {
             .set_text(cx, tr_key(self.app_language, "room_context_menu.button.notifications"));
         self.button(cx, ids!(invite_button))
             .set_text(cx, tr_key(self.app_language, "room_context_menu.button.invite"));
+        self.button(cx, ids!(export_button))
+            .set_text(cx, tr_key(self.app_language, "room_context_menu.button.export_chat"));
+        self.button(cx, ids!(jump_to_date_button))
+            .set_text(cx, tr_key(self.app_language, "room_context_menu.button.jump_to_date"));
         self.button(cx, ids!(leave_button))
             .set_text(cx, tr_key(self.app_language, "room_context_menu.button.leave_room"));
 
@@ -341,13 +369,15 @@ impl RoomContextMenu {
         self.button(cx, ids!(notifications_button)).reset_hover(cx);
         self.button(cx, ids!(invite_button)).reset_hover(cx);
         bot_binding_button.reset_hover(cx);
+        self.button(cx, ids!(export_button)).reset_hover(cx);
+        self.button(cx, ids!(jump_to_date_button)).reset_hover(cx);
         self.button(cx, ids!(leave_button)).reset_hover(cx);
         
         self.redraw(cx);
         
         // Calculate height (rudimentary) - sum of visible buttons + padding
-        // 8 or 9 buttons * 35.0 + 2 dividers * ~10.0 + padding
-        ((if details.app_service_enabled { 9.0 } else { 8.0 }) * BUTTON_HEIGHT) + 20.0 + 10.0 // approx
+        // 10 or 11 buttons * 35.0 + 2 dividers * ~10.0 + padding
+        ((if details.app_service_enabled { 11.0 } else { 10.0 }) * BUTTON_HEIGHT) + 20.0 + 10.0 // approx
     }
 
     fn close(&mut self, cx: &mut Cx) {
@@ -3,7 +3,7 @@ use matrix_sdk_ui::timeline::EventTimelineItem;
 use matrix_sdk::{
     media::MediaFormat,
     reqwest::StatusCode,
-    ruma::events::room::{message::MessageType, MediaSource},
+    ruma::{OwnedMxcUri, events::room::{message::{MessageType, VideoMessageEventContent}, MediaSource}},
 };
 
 use crate::{media_cache::{MediaCache, MediaCacheEntry}, shared::image_viewer::{ImageViewerAction, ImageViewerError, LoadState}};
@@ -30,13 +30,7 @@ pub fn populate_matrix_image_modal(
             cx.action(ImageViewerAction::Show(LoadState::Loaded(data)));
         }
         (MediaCacheEntry::Failed(status_code), MediaFormat::File) => {
-            let error = match status_code {
-                StatusCode::NOT_FOUND => ImageViewerError::NotFound,
-                StatusCode::INTERNAL_SERVER_ERROR => ImageViewerError::ConnectionFailed,
-                StatusCode::PARTIAL_CONTENT => ImageViewerError::BadData,
-                StatusCode::UNAUTHORIZED => ImageViewerError::Unauthorized,
-                _ => ImageViewerError::Unknown,
-            };
+            let error = image_viewer_error_from_status(status_code);
             cx.action(ImageViewerAction::Show(LoadState::Error(error)));
             // Remove failed media entry from cache for MediaFormat::File so as to start all over again from loading Thumbnail.
             media_cache.remove_cache_entry(&mxc_uri, Some(MediaFormat::File));
@@ -45,6 +39,69 @@ pub fn populate_matrix_image_modal(
     }
 }
 
+/// Populates the image viewer modal with the given video file such that it will be played.
+///
+/// This works like [`populate_matrix_image_modal()`], but the video file is always fetched
+/// and stored in full, and it is then saved to a file called `file_name` for playback.
+///
+/// Returns `true` if the video file is still being fetched, in which case this function
+/// should be called again once the media cache reports that the fetch has completed.
+pub fn populate_matrix_video_modal(
+    cx: &mut Cx,
+    mxc_uri: &OwnedMxcUri,
+    file_name: &str,
+    media_cache: &mut MediaCache,
+) -> bool {
+    match media_cache.try_get_media_or_fetch(mxc_uri, MediaFormat::File) {
+        (MediaCacheEntry::Loaded(data), MediaFormat::File) => {
+            cx.action(ImageViewerAction::Show(LoadState::LoadedVideo {
+                data,
+                file_name: file_name.to_string(),
+            }));
+            false
+        }
+        (MediaCacheEntry::Failed(status_code), MediaFormat::File) => {
+            let error = image_viewer_error_from_status(status_code);
+            cx.action(ImageViewerAction::Show(LoadState::Error(error)));
+            media_cache.remove_cache_entry(mxc_uri, Some(MediaFormat::File));
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Returns the name of the file that the given video should be saved to for playback.
+///
+/// The name is derived from the video's MXC URI such that it is unique,
+/// and its extension is derived from the video's mimetype or original filename,
+/// as platform video players typically rely on the extension to detect the container format.
+pub fn video_playback_file_name(mxc_uri: &OwnedMxcUri, video: &VideoMessageEventContent) -> String {
+    let mimetype = video.info.as_ref().and_then(|info| info.mimetype.as_deref());
+    let extension = match mimetype {
+        Some("video/mp4") => "mp4",
+        Some("video/webm") => "webm",
+        Some("video/quicktime") => "mov",
+        Some("video/x-matroska") => "mkv",
+        _ => video.filename().rsplit_once('.')
+            .map(|(_, ext)| ext)
+            .filter(|ext| !ext.is_empty() && ext.len() <= 4 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("mp4"),
+    };
+    // Server names may include a port, but colons aren't allowed in filenames on all platforms.
+    let server_name = mxc_uri.server_name().map(|s| s.as_str()).unwrap_or_default().replace(':', "_");
+    format!("video_{server_name}_{}.{extension}", mxc_uri.media_id().unwrap_or_default())
+}
+
+fn image_viewer_error_from_status(status_code: StatusCode) -> ImageViewerError {
+    match status_code {
+        StatusCode::NOT_FOUND => ImageViewerError::NotFound,
+        StatusCode::INTERNAL_SERVER_ERROR => ImageViewerError::ConnectionFailed,
+        StatusCode::PARTIAL_CONTENT => ImageViewerError::BadData,
+        StatusCode::UNAUTHORIZED => ImageViewerError::Unauthorized,
+        _ => ImageViewerError::Unknown,
+    }
+}
+
 /// Gets image name and file size in bytes from an event timeline item.
 pub fn get_image_name_and_filesize(event_tl_item: &EventTimelineItem) -> (String, u64) {
     if let Some(message) = event_tl_item.content().as_message() {
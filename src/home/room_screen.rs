@@ -1,7 +1,7 @@
 //! The `RoomScreen` widget is the UI view that displays a single room or thread's timeline
 //! of events (messages，state changes, etc.), along with an input bar at the bottom.
 
-use std::{borrow::Cow, cell::{Cell, RefCell}, ops::{DerefMut, Range}, sync::Arc, time::Duration};
+use std::{borrow::Cow, cell::{Cell, RefCell}, ops::{DerefMut, Range}, sync::{Arc, atomic::Ordering}, time::Duration};
 
 use bytesize::ByteSize;
 use hashbrown::{HashMap, HashSet};
@@ -9,7 +9,7 @@ use imbl::Vector;
 use makepad_widgets::{image_cache::ImageBuffer, *};
 use matrix_sdk::{
     OwnedServerName, media::{MediaFormat, MediaRequestParameters}, room::{RoomMember, RoomMemberRole}, ruma::{
-        EventId, MatrixToUri, MatrixUri, OwnedEventId, OwnedMxcUri, OwnedRoomId, UserId, events::{
+        EventId, MatrixToUri, MatrixUri, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedMxcUri, OwnedRoomId, UserId, events::{
             receipt::Receipt,
             room::{
                 ImageInfo, MediaSource, message::{
@@ -21,23 +21,32 @@ use matrix_sdk::{
     }
 };
 use matrix_sdk_ui::timeline::{
-    self, EmbeddedEvent, EncryptedMessage, EventTimelineItem, InReplyToDetails, LiveLocationState, MemberProfileChange, MembershipChange, MsgLikeContent, MsgLikeKind, OtherMessageLike, PollState, RoomMembershipChange, TimelineDetails, TimelineEventItemId, TimelineItem, TimelineItemContent, TimelineItemKind, VirtualTimelineItem
+    self, EmbeddedEvent, EncryptedMessage, EventTimelineItem, InReplyToDetails, MemberProfileChange, MembershipChange, MsgLikeContent, MsgLikeKind, OtherMessageLike, RoomMembershipChange, TimelineDetails, TimelineEventItemId, TimelineItem, TimelineItemContent, TimelineItemKind, VirtualTimelineItem
 };
-use ruma::{OwnedUserId, api::client::receipt::create_receipt::v3::ReceiptType, events::{AnySyncMessageLikeEvent, AnySyncTimelineEvent, SyncMessageLikeEvent}};
+use ruma::{OwnedRoomAliasId, OwnedRoomOrAliasId, OwnedUserId, api::client::receipt::create_receipt::v3::ReceiptType, events::{AnySyncMessageLikeEvent, AnySyncTimelineEvent, SyncMessageLikeEvent}};
 
 use matrix_sdk_ui::sync_service::State;
 use crate::{
-    app::{AppState, AppStateAction, ConfirmDeleteAction, SelectedRoom}, avatar_cache, event_preview::{plaintext_body_of_timeline_item, text_preview_of_encrypted_message, text_preview_of_member_profile_change, text_preview_of_other_message_like, text_preview_of_other_state, text_preview_of_room_membership_change, text_preview_of_timeline_item}, home::{bot_binding_modal::BotBindingModalAction, create_bot_modal::{CreateBotModalAction, CreateBotModalWidgetExt}, delete_bot_modal::{DeleteBotModalAction, DeleteBotModalWidgetExt}, edited_indicator::EditedIndicatorWidgetRefExt, invite_modal::InviteModalAction, link_preview::{LinkPreviewCache, LinkPreviewRef, LinkPreviewWidgetRefExt}, loading_pane::{LoadingPaneState, LoadingPaneWidgetExt}, room_image_viewer::{get_image_name_and_filesize, populate_matrix_image_modal}, rooms_list::{RoomsListAction, RoomsListRef}, rooms_list_header::RoomsListHeaderAction, tombstone_footer::SuccessorRoomDetails}, i18n::{AppLanguage, tr_fmt, tr_key}, media_cache::{MediaCache, MediaCacheEntry}, profile::{
+    app::{AppState, AppStateAction, ConfirmDeleteAction, SelectedRoom}, audio_playback, avatar_cache, event_preview::{plaintext_body_of_timeline_item, text_preview_of_encrypted_message, text_preview_of_member_profile_change, text_preview_of_other_message_like, text_preview_of_other_state, text_preview_of_room_membership_change, text_preview_of_timeline_item}, home::{bot_binding_modal::BotBindingModalAction, create_bot_modal::{CreateBotModalAction, CreateBotModalWidgetExt}, delete_bot_modal::{DeleteBotModalAction, DeleteBotModalWidgetExt}, edit_history_modal::EditHistoryModalAction, edited_indicator::EditedIndicatorWidgetRefExt, invite_modal::InviteModalAction, link_preview::{LinkPreviewCache, LinkPreviewRef, LinkPreviewWidgetRefExt}, loading_pane::{LoadingPaneState, LoadingPaneWidgetExt}, room_image_viewer::{get_image_name_and_filesize, populate_matrix_image_modal, populate_matrix_video_modal, video_playback_file_name}, rooms_list::{RoomsListAction, RoomsListRef}, rooms_list_header::RoomsListHeaderAction, tombstone_footer::SuccessorRoomDetails}, i18n::{AppLanguage, tr_fmt, tr_key}, media_cache::{MediaCache, MediaCacheEntry}, profile::{
         user_profile::{ShowUserProfileAction, UserProfile, UserProfileAndRoomId, UserProfilePaneInfo, UserProfileSlidingPaneRef, UserProfileSlidingPaneWidgetExt},
         user_profile_cache,
     },
-    room::{BasicRoomDetails, room_input_bar::{RoomInputBarState, RoomInputBarWidgetRefExt}, translation, typing_notice::TypingNoticeWidgetExt},
+    room::{BasicRoomDetails, RoomPreviewAction, composer_drafts, room_input_bar::{RoomInputBarState, RoomInputBarWidgetRefExt}, translation, typing_notice::TypingNoticeWidgetExt},
     shared::{
         avatar::{AvatarState, AvatarWidgetExt, AvatarWidgetRefExt}, confirmation_modal::{ConfirmationModalAction, ConfirmationModalContent, ConfirmationModalWidgetExt}, html_or_plaintext::{HtmlOrPlaintextRef, HtmlOrPlaintextWidgetRefExt, RobrixHtmlLinkAction}, image_viewer::{ImageViewerAction, ImageViewerMetaData, LoadState}, jump_to_bottom_button::{JumpToBottomButtonWidgetExt, UnreadMessageCount}, popup_list::{PopupKind, enqueue_popup_notification}, restore_status_view::RestoreStatusViewWidgetExt, styles::*, text_or_image::{TextOrImageAction, TextOrImageRef, TextOrImageWidgetRefExt}, timestamp::TimestampWidgetRefExt
     },
-    sliding_sync::{BackwardsPaginateUntilEventRequest, FetchedRoomThread, MatrixRequest, PaginationDirection, RoomThreadsAction, TimelineEndpoints, TimelineKind, TimelineRequestSender, UserPowerLevels, current_user_id, get_client, submit_async_request, take_timeline_endpoints}, utils::{self, ImageFormat, MEDIA_THUMBNAIL_FORMAT, RoomNameId, unix_time_millis_to_datetime}
+    sliding_sync::{BackwardsPaginateUntilEventRequest, FetchedRoomThread, JumpToDateAction, MatrixRequest, PaginationDirection, RemoveRecentMessagesAction, ResolveRoomAliasAction, RoomThreadsAction, TimelineEndpoints, TimelineKind, TimelineRequestSender, UserPowerLevels, current_user_id, get_client, submit_async_request, take_timeline_endpoints}, utils::{self, ImageFormat, MEDIA_THUMBNAIL_FORMAT, RoomNameId, unix_time_millis_to_datetime}
 };
 use crate::home::event_reaction_list::ReactionListWidgetRefExt;
+use crate::home::audio_player::AudioPlayerWidgetRefExt;
+use crate::home::message_action_bar::MessageActionBarWidgetExt;
+use crate::home::report_message_modal::{ReportMessageModalAction, ReportMessageModalWidgetExt};
+use crate::home::forward_message_modal::ForwardMessageModalAction;
+use crate::forward_message;
+use crate::bookmarks::{self, Bookmark};
+use crate::home::live_location_view::LiveLocationViewWidgetRefExt;
+use crate::shared::map_thumbnail::MapThumbnailWidgetRefExt;
+use crate::home::poll_view::PollViewWidgetRefExt;
 use crate::home::room_read_receipt::AvatarRowWidgetRefExt;
 use crate::home::streaming_animation::StreamingAnimState;
 use crate::room::room_input_bar::RoomInputBarWidgetExt;
@@ -964,6 +973,10 @@ fn is_room_info_action_modal_open() -> bool {
 }
 
 
+/// The maximum number of a user's recent messages that are removed
+/// by the "remove recent messages" moderation action.
+const RECENT_MESSAGES_TO_REMOVE: usize = 50;
+
 /// #FFF4E5
 const COLOR_THREAD_SUMMARY_BG: Vec4 = vec4(1.0, 0.957, 0.898, 1.0);
 /// #FFEACC
@@ -1624,38 +1637,9 @@ script_mod! {
                 border_color: (mod.widgets.COLOR_BOT_CODE_BORDER)
             }
 
-            code_view := mod.widgets.CodeView {
-                keep_cursor_at_end: false
+            code_view := mod.widgets.MessageCodeView {
                 editor +: {
-                    width: Fill
-                    height: Fit
                     margin: Inset{ left: 12.0, right: 12.0, top: 10.0, bottom: 10.0 }
-                    draw_bg +: { color: #0000 }
-                    draw_text +: {
-                        text_style: mod.widgets.MESSAGE_CODE_TEXT_STYLE {
-                            font_size: (MESSAGE_FONT_SIZE - 0.5)
-                            line_spacing: (MESSAGE_TEXT_LINE_SPACING)
-                        }
-                    }
-                    token_colors +: {
-                        whitespace: #x6a737d
-                        delimiter: #x24292e
-                        delimiter_highlight: #x005cc5
-                        error_decoration: #xcb2431
-                        warning_decoration: #xb08800
-                        unknown: #x24292e
-                        branch_keyword: #xd73a49
-                        constant: #x005cc5
-                        identifier: #x24292e
-                        loop_keyword: #xd73a49
-                        number: #x005cc5
-                        other_keyword: #xd73a49
-                        punctuator: #x24292e
-                        string: #x22863a
-                        function: #x6f42c1
-                        typename: #xe36209
-                        comment: #x6a737d
-                    }
                 }
             }
         }
@@ -2273,6 +2257,221 @@ script_mod! {
         }
     }
 
+    // The details of a video message shown below its poster frame, with a button to play it.
+    mod.widgets.VideoDetails = View {
+        width: Fill,
+        height: Fit,
+        flow: Right,
+        spacing: 8.0
+        margin: Inset{ top: 4.0 }
+        align: Align{ y: 0.5 }
+
+        play_video_button := RobrixIconButton {
+            padding: Inset{ top: 5, bottom: 5, left: 10, right: 12 }
+            spacing: 6
+            draw_icon +: { svg: (ICON_PLAY) }
+            icon_walk: Walk{ width: 10, height: 10 }
+            text: "Play"
+        }
+
+        video_details_label := Label {
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            draw_text +: {
+                color: (COLOR_MESSAGE_NOTICE_TEXT),
+                text_style: MESSAGE_TEXT_STYLE { font_size: 9.5 },
+            }
+            text: ""
+        }
+    }
+
+    // The view used for each video message in a room's timeline.
+    mod.widgets.VideoMessage = mod.widgets.Message {
+        body +: {
+            content +: {
+                width: Fill,
+                height: Fit
+                padding: Inset{ left: 10.0 }
+
+                message := TextOrImage { }
+                video_details := mod.widgets.VideoDetails { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+    // The view used for a condensed video message that came right after another message
+    // from the same sender, and thus doesn't need to display the sender's profile again.
+    mod.widgets.CondensedVideoMessage = mod.widgets.CondensedMessage {
+        body +: {
+            content +: {
+                message := TextOrImage { }
+                video_details := mod.widgets.VideoDetails { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+    // The view used for each poll in a room's timeline.
+    mod.widgets.PollMessage = mod.widgets.Message {
+        body +: {
+            content +: {
+                message := mod.widgets.PollView { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+    // The view used for a condensed poll that came right after another message
+    // from the same sender, and thus doesn't need to display the sender's profile again.
+    mod.widgets.CondensedPollMessage = mod.widgets.CondensedMessage {
+        body +: {
+            content +: {
+                message := mod.widgets.PollView { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+
+    // The view used for each location message in a room's timeline,
+    // which shows a small map of the location above the location's links.
+    mod.widgets.LocationMessage = mod.widgets.Message {
+        body +: {
+            content +: {
+                map_thumbnail := mod.widgets.MapThumbnail { margin: Inset{ top: 4, bottom: 4 } }
+                message := HtmlOrPlaintext { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+    // The view used for a condensed location message that came right after another message
+    // from the same sender, and thus doesn't need to display the sender's profile again.
+    mod.widgets.CondensedLocationMessage = mod.widgets.CondensedMessage {
+        body +: {
+            content +: {
+                map_thumbnail := mod.widgets.MapThumbnail { margin: Inset{ top: 4, bottom: 4 } }
+                message := HtmlOrPlaintext { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+
+    // The view used for each live location share in a room's timeline.
+    mod.widgets.LiveLocationMessage = mod.widgets.Message {
+        body +: {
+            content +: {
+                message := mod.widgets.LiveLocationView { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+    // The view used for a condensed live location share that came right after another message
+    // from the same sender, and thus doesn't need to display the sender's profile again.
+    mod.widgets.CondensedLiveLocationMessage = mod.widgets.CondensedMessage {
+        body +: {
+            content +: {
+                message := mod.widgets.LiveLocationView { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+
+    // The view used for each audio or voice message in a room's timeline.
+    mod.widgets.AudioMessage = mod.widgets.Message {
+        body +: {
+            content +: {
+                message := mod.widgets.AudioPlayer { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
+
+    // The view used for a condensed audio or voice message that came right after another message
+    // from the same sender, and thus doesn't need to display the sender's profile again.
+    mod.widgets.CondensedAudioMessage = mod.widgets.CondensedMessage {
+        body +: {
+            content +: {
+                message := mod.widgets.AudioPlayer { }
+                View {
+                    width: Fill,
+                    height: Fit,
+                    flow: Right,
+                    reaction_list := mod.widgets.ReactionList { }
+                    avatar_row := mod.widgets.AvatarRow {}
+                }
+                thread_root_summary := mod.widgets.ThreadRootSummary {}
+            }
+        }
+    }
 
     // The view used for each state event (non-messages) in a room's timeline.
     // The timestamp, profile picture, and text are all very small.
@@ -3364,6 +3563,12 @@ script_mod! {
             CondensedMessage := mod.widgets.CondensedMessage {}
             ImageMessage := mod.widgets.ImageMessage {}
             CondensedImageMessage := mod.widgets.CondensedImageMessage {}
+            VideoMessage := mod.widgets.VideoMessage {}
+            CondensedVideoMessage := mod.widgets.CondensedVideoMessage {}
+            PollMessage := mod.widgets.PollMessage {}
+            CondensedPollMessage := mod.widgets.CondensedPollMessage {}
+            AudioMessage := mod.widgets.AudioMessage {}
+            CondensedAudioMessage := mod.widgets.CondensedAudioMessage {}
             SmallStateEvent := mod.widgets.SmallStateEvent {}
             Empty := mod.widgets.Empty {}
             DateDivider := mod.widgets.DateDivider {}
@@ -3374,6 +3579,9 @@ script_mod! {
         // A jump to bottom button (with an unread message badge) that is shown
         // when the timeline is not at the bottom.
         jump_to_bottom_button := JumpToBottomButton { }
+
+        // A floating bar of quick actions for the hovered message, shown on desktop only.
+        message_action_bar := mod.widgets.MessageActionBar { }
     }
 
     mod.widgets.TranslationLangPopupButton = RobrixIconButton {
@@ -3520,31 +3728,17 @@ script_mod! {
                 }
             }
 
-            leave_room_confirm_modal := Modal {
+            report_message_modal := Modal {
                 content +: {
-                    leave_room_confirm_modal_inner := mod.widgets.NegativeConfirmationModal {}
+                    report_message_modal_inner := mod.widgets.ReportMessageModal {}
                 }
             }
 
-
-            /*
-             * TODO: add the action bar back in as a series of floating buttons.
-             *
-            message_action_bar_popup := PopupNotification {
-                align: Align{x: 0.0, y: 0.0}
-                content: {
-                    height: Fit,
-                    width: Fit,
-                    show_bg: false,
-                    align: Align{
-                        x: 0.5,
-                        y: 0.5
-                    }
-
-                    message_action_bar := MessageActionBar {}
+            leave_room_confirm_modal := Modal {
+                content +: {
+                    leave_room_confirm_modal_inner := mod.widgets.NegativeConfirmationModal {}
                 }
             }
-            */
         }
     }
 }
@@ -4333,6 +4527,8 @@ pub struct RoomScreen {
     #[rust] octos_action_button_contexts: HashMap<WidgetUid, OctosActionButtonContext>,
     #[rust] disabled_octos_action_source_event_ids: HashSet<OwnedEventId>,
     #[rust] selected_octos_action_by_source_event_id: HashMap<OwnedEventId, SelectedOctosActionState>,
+    /// The clicked Matrix link whose target room is currently being resolved, if any.
+    #[rust] pending_matrix_link: Option<PendingMatrixLink>,
 }
 
 impl Drop for RoomScreen {
@@ -4378,9 +4574,16 @@ impl Widget for RoomScreen {
         let loading_pane = self.loading_pane(cx, ids!(loading_pane));
         set_room_info_action_modal_open(
             self.view.modal(cx, ids!(report_room_modal)).is_open()
+                || self.view.modal(cx, ids!(report_message_modal)).is_open()
                 || self.view.modal(cx, ids!(leave_room_confirm_modal)).is_open()
         );
 
+        // Persist the draft of the currently-shown timeline before the app exits,
+        // as it is otherwise only saved when the timeline is hidden.
+        if let Event::Shutdown = event {
+            self.save_composer_draft();
+        }
+
         // Streaming animation frame handler
         if let Some(_ne) = self.streaming_next_frame.is_event(event) {
             #[cfg(debug_assertions)]
@@ -4509,8 +4712,13 @@ impl Widget for RoomScreen {
                         .collect();
 
                     let mut tooltip_text = utils::human_readable_list(&tooltip_text_arr, MAX_VISIBLE_AVATARS_IN_READ_RECEIPT);
+                    let reaction_text = if reaction_data.custom_emoji_uri().is_some() {
+                        tr_key(self.app_language, "room_screen.tooltip.custom_emoji_reaction")
+                    } else {
+                        reaction_data.reaction.as_str()
+                    };
                     tooltip_text.push_str(&tr_fmt(self.app_language, "room_screen.tooltip.reacted_with_suffix", &[
-                        ("reaction", reaction_data.reaction.as_str()),
+                        ("reaction", reaction_text),
                     ]));
                     cx.widget_action(
                         room_screen_widget_uid, 
@@ -4560,6 +4768,10 @@ impl Widget for RoomScreen {
                 let content_message = wr.text_or_image(cx, ids!(content.message));
                 if let TextOrImageAction::Clicked(mxc_uri) = actions.find_widget_action(content_message.widget_uid()).cast() {
                     let texture = content_message.get_texture(cx);
+                    // The poster frame of a video message is also an image.
+                    if self.handle_video_click(cx, texture.clone(), index) {
+                        continue;
+                    }
                     self.handle_image_click(
                         cx,
                         mxc_uri,
@@ -4569,6 +4781,35 @@ impl Widget for RoomScreen {
                     continue;
                 }
 
+                // Handle the play button of a video message being clicked.
+                if wr.button(cx, ids!(play_video_button)).clicked(actions) {
+                    let texture = content_message.get_texture(cx);
+                    self.handle_video_click(cx, texture, index);
+                    continue;
+                }
+
+                // Handle the play button of an audio message whose file hasn't been decoded yet.
+                if let Some((mxc_uri, mimetype)) = wr.audio_player(cx, ids!(content.message)).play_requested(actions) {
+                    let Some(tl) = self.tl_state.as_mut() else { continue };
+                    play_audio_once_fetched(cx, tl, self.app_language, mxc_uri, mimetype);
+                    continue;
+                }
+
+                // Handle the "(edited)" indicator being clicked: show the message's edit history.
+                if wr.edited_indicator(cx, ids!(profile.edited_indicator)).clicked(actions) {
+                    let Some(tl) = self.tl_state.as_ref() else { continue };
+                    if let Some(event_id) = tl.items.get(index)
+                        .and_then(|item| item.as_event())
+                        .and_then(|event_tl_item| event_tl_item.event_id())
+                    {
+                        cx.action(EditHistoryModalAction::Open {
+                            room_id: tl.kind.room_id().clone(),
+                            event_id: event_id.to_owned(),
+                        });
+                    }
+                    continue;
+                }
+
                 // Handle the invite_user_button (in a SmallStateEvent) being clicked.
                 if wr.button(cx, ids!(invite_user_button)).clicked(actions) {
                     let Some(tl) = self.tl_state.as_ref() else { continue };
@@ -4595,25 +4836,48 @@ impl Widget for RoomScreen {
                 }
             }
 
+            // Handle the result of a jump to a date, which re-focuses the timeline
+            // on the event that the homeserver found for that date.
+            for action in actions {
+                if let Some(JumpToDateAction::Found { timeline_kind, event_id }) = action.downcast_ref()
+                    && self.timeline_kind.as_ref() == Some(timeline_kind)
+                {
+                    request_jump_to_event(cx, timeline_kind.clone(), event_id.clone());
+                }
+            }
+
             self.handle_message_actions(cx, actions, &portal_list, &loading_pane);
+            self.jump_to_pending_target_event(cx, &portal_list, &loading_pane);
 
             for action in actions {
                 if let Some(RoomsListAction::Selected(selected_room)) = action.downcast_ref() {
                     if self.timeline_kind.as_ref() != selected_room.timeline_kind().as_ref() {
                         self.close_report_room_modal(cx);
+                        self.close_report_message_modal(cx);
                         self.close_leave_room_confirm_modal(cx);
                     }
                 }
                 if let Some(AppStateAction::RoomFocused(selected_room)) = action.downcast_ref() {
                     if self.timeline_kind.as_ref() != selected_room.timeline_kind().as_ref() {
                         self.close_report_room_modal(cx);
+                        self.close_report_message_modal(cx);
                         self.close_leave_room_confirm_modal(cx);
                     }
                 }
                 if let Some(AppStateAction::FocusNone) = action.downcast_ref() {
                     self.close_report_room_modal(cx);
+                    self.close_report_message_modal(cx);
                     self.close_leave_room_confirm_modal(cx);
                 }
+                self.handle_pending_matrix_link_action(cx, action);
+                self.handle_remove_recent_messages_action(cx, action);
+
+                // Don't start playing a video whose viewer was closed while its file was being fetched.
+                if let Some(ImageViewerAction::Hide) = action.downcast_ref() {
+                    if let Some(tl) = self.tl_state.as_mut() {
+                        tl.pending_video_playback = None;
+                    }
+                }
 
                 // Handle actions related to restoring the previously-saved state of rooms.
                 if let Some(AppStateAction::RoomLoadedSuccessfully { room_name_id, ..}) = action.downcast_ref() {
@@ -4675,6 +4939,27 @@ impl Widget for RoomScreen {
                         );
                     }
                 }
+                if let Some(ReportEventResultAction::Sent { room_id, .. }) = action.downcast_ref() {
+                    if self.room_name_id.as_ref().is_some_and(|rn| rn.room_id() == room_id) {
+                        enqueue_popup_notification(
+                            tr_key(self.app_language, "room_screen.popup.message.report_sent"),
+                            PopupKind::Success,
+                            Some(4.0),
+                        );
+                    }
+                }
+                if let Some(ReportEventResultAction::Failed { room_id, error, .. }) = action.downcast_ref() {
+                    if self.room_name_id.as_ref().is_some_and(|rn| rn.room_id() == room_id) {
+                        let error_text = error.to_string();
+                        enqueue_popup_notification(
+                            tr_fmt(self.app_language, "room_screen.popup.message.report_failed", &[
+                                ("error", error_text.as_str()),
+                            ]),
+                            PopupKind::Error,
+                            Some(5.0),
+                        );
+                    }
+                }
                 if let Some(ReportRoomResultAction::Sent { room_id }) = action.downcast_ref() {
                     if self.room_name_id.as_ref().is_some_and(|rn| rn.room_id() == room_id) {
                         enqueue_popup_notification(
@@ -4840,13 +5125,10 @@ impl Widget for RoomScreen {
                 }
             }
 
-            /*
-            // close message action bar if scrolled.
+            // Close the message action bar if scrolled, as its message will have moved.
             if portal_list.scrolled(actions) {
-                let message_action_bar_popup = self.popup_notification(cx, ids!(message_action_bar_popup));
-                message_action_bar_popup.close(cx);
+                self.view.message_action_bar(cx, ids!(timeline.message_action_bar)).hide(cx);
             }
-            */
 
             // Set visibility of loading message banner based of pagination logic
             self.send_pagination_request_based_on_scroll_pos(cx, actions, &portal_list);
@@ -4912,6 +5194,7 @@ impl Widget for RoomScreen {
         //
         let room_info_action_modal_open =
             self.view.modal(cx, ids!(report_room_modal)).is_open()
+            || self.view.modal(cx, ids!(report_message_modal)).is_open()
             || self.view.modal(cx, ids!(leave_room_confirm_modal)).is_open();
         let is_interactive_hit = utils::is_interactive_hit_event(event);
         let is_pane_shown: bool;
@@ -5169,6 +5452,27 @@ impl Widget for RoomScreen {
                     None => {}
                 }
 
+                match action.downcast_ref::<ReportMessageModalAction>() {
+                    Some(ReportMessageModalAction::Close) => {
+                        self.close_report_message_modal(cx);
+                        return false;
+                    }
+                    Some(ReportMessageModalAction::Submit(request)) => {
+                        if let Some(room_id) = self.room_id().cloned() {
+                            submit_async_request(MatrixRequest::ReportEvent {
+                                room_id,
+                                event_id: request.event_id.clone(),
+                                reason: request.reason.clone(),
+                                score: request.severity.score(),
+                                ignore_sender: request.ignore_sender.then(|| request.sender.clone()),
+                            });
+                        }
+                        self.close_report_message_modal(cx);
+                        return false;
+                    }
+                    None => {}
+                }
+
                 match action.downcast_ref::<ReportRoomModalAction>() {
                     Some(ReportRoomModalAction::Close) => {
                         self.close_report_room_modal(cx);
@@ -5240,51 +5544,14 @@ impl Widget for RoomScreen {
                     );
                 }
 
-                /*
-                match action.as_widget_action().widget_uid_eq(room_screen_widget_uid).cast() {
-                    MessageAction::ActionBarClose => {
-                        let message_action_bar_popup = self.popup_notification(cx, ids!(message_action_bar_popup));
-                        let message_action_bar = message_action_bar_popup.message_action_bar(cx, ids!(message_action_bar));
-
-                        // close only if the active message is requesting it to avoid double closes.
-                        if let Some(message_widget_uid) = message_action_bar.message_widget_uid() {
-                            if action.as_widget_action().widget_uid_eq(message_widget_uid).is_some() {
-                                message_action_bar_popup.close(cx);
-                            }
-                        }
-                    }
-                    MessageAction::ActionBarOpen { item_id, message_rect } => {
-                        let message_action_bar_popup = self.popup_notification(cx, ids!(message_action_bar_popup));
-                        let message_action_bar = message_action_bar_popup.message_action_bar(cx, ids!(message_action_bar));
-
-                        let margin_x = 50.;
-
-                        let coords = dvec2(
-                            (message_rect.pos.x + message_rect.size.x) - margin_x,
-                            message_rect.pos.y,
-                        );
-
-                        script_apply_eval!(cx, message_action_bar_popup, {
-                            content +: { margin +: { left: #(coords.x), top: #(coords.y) } }
-                        });
-
-                        if let Some(message_widget_uid) = action.as_widget_action().map(|a| a.widget_uid) {
-                            message_action_bar_popup.open(cx);
-                            message_action_bar.initialize_with_data(cx, widget_uid, message_widget_uid, item_id);
-                        }
-                    }
-                    _ => {}
-                }
-                */
-
-                // Keep all unhandled actions so we can add them back to the global action list below.
-                true
-            });
-            self.handle_translation_lang_popup_actions(cx, &actions_generated_within_this_room_screen);
-            // Add back any unhandled actions to the global action list.
-            cx.extend_actions(actions_generated_within_this_room_screen);
-        }
-    }
+                // Keep all unhandled actions so we can add them back to the global action list below.
+                true
+            });
+            self.handle_translation_lang_popup_actions(cx, &actions_generated_within_this_room_screen);
+            // Add back any unhandled actions to the global action list.
+            cx.extend_actions(actions_generated_within_this_room_screen);
+        }
+    }
 
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
@@ -5378,6 +5645,8 @@ impl Widget for RoomScreen {
                                     match &msg_like_content.kind {
                                         MsgLikeKind::Message(_)
                                         | MsgLikeKind::Sticker(_)
+                                        | MsgLikeKind::Poll(_)
+                                        | MsgLikeKind::LiveLocation(_)
                                         | MsgLikeKind::Redacted => {
                                             let prev_event = tl_idx.checked_sub(1).and_then(|i| tl_items.get(i));
                                             populate_message_view(
@@ -5406,17 +5675,6 @@ impl Widget for RoomScreen {
                                                 &self.selected_octos_action_by_source_event_id,
                                             )
                                         },
-                                        // TODO: properly implement `Poll` as a regular Message-like timeline item.
-                                        MsgLikeKind::Poll(poll_state) => populate_small_state_event(
-                                            cx,
-                                            list,
-                                            item_id,
-                                            &tl_state.kind,
-                                            self.app_language,
-                                            event_tl_item,
-                                            poll_state,
-                                            item_drawn_status,
-                                        ),
                                         MsgLikeKind::UnableToDecrypt(utd) => populate_small_state_event(
                                             cx,
                                             list,
@@ -5427,16 +5685,6 @@ impl Widget for RoomScreen {
                                             utd,
                                             item_drawn_status,
                                         ),
-                                        MsgLikeKind::LiveLocation(live_loc) => populate_small_state_event(
-                                            cx,
-                                            list,
-                                            item_id,
-                                            &tl_state.kind,
-                                            app_language,
-                                            event_tl_item,
-                                            live_loc,
-                                            item_drawn_status,
-                                        ),
                                         MsgLikeKind::Other(other) => populate_small_state_event(
                                             cx,
                                             list,
@@ -5840,6 +6088,10 @@ impl RoomScreen {
         self.view.modal(cx, ids!(report_room_modal)).close(cx);
     }
 
+    fn close_report_message_modal(&self, cx: &mut Cx) {
+        self.view.modal(cx, ids!(report_message_modal)).close(cx);
+    }
+
     fn close_leave_room_confirm_modal(&self, cx: &mut Cx) {
         self.view.modal(cx, ids!(leave_room_confirm_modal)).close(cx);
     }
@@ -5897,6 +6149,7 @@ impl RoomScreen {
         self.close_create_bot_modal(cx);
         self.close_delete_bot_modal(cx);
         self.close_report_room_modal(cx);
+        self.close_report_message_modal(cx);
         self.close_leave_room_confirm_modal(cx);
     }
 
@@ -6514,8 +6767,22 @@ impl RoomScreen {
                 },
                 TimelineUpdate::MediaFetched(request) => {
                     log!("process_timeline_updates(): media fetched for room {}", tl.kind.room_id());
-                    // Set Image to image viewer modal if the media is not a thumbnail.
-                    if let (MediaFormat::File, media_source) = (request.format, request.source) {
+                    let pending_audio = tl.pending_audio_playback.take_if(|(mxc_uri, _)|
+                        matches!(&request.source, MediaSource::Plain(uri) if uri == mxc_uri)
+                    );
+                    let pending_video = tl.pending_video_playback.take_if(|(mxc_uri, _)|
+                        matches!(&request.source, MediaSource::Plain(uri) if uri == mxc_uri)
+                    );
+                    if let Some((mxc_uri, mimetype)) = pending_audio {
+                        // Play the pending audio clip now that its file has been fetched.
+                        play_audio_once_fetched(cx, tl, self.app_language, mxc_uri, mimetype);
+                    } else if let Some((mxc_uri, file_name)) = pending_video {
+                        // Play the pending video in the image viewer modal now that its file has been fetched.
+                        if populate_matrix_video_modal(cx, &mxc_uri, &file_name, &mut tl.media_cache) {
+                            tl.pending_video_playback = Some((mxc_uri, file_name));
+                        }
+                    } else if let (MediaFormat::File, media_source) = (request.format, request.source) {
+                        // Set Image to image viewer modal if the media is not a thumbnail.
                         populate_matrix_image_modal(cx, media_source, &mut tl.media_cache);
                     }
                     // Here, to be most efficient, we could redraw only the media items in the timeline,
@@ -6600,7 +6867,8 @@ impl RoomScreen {
                 TimelineUpdate::LinkPreviewFetched => {}
                 TimelineUpdate::FileUploadConfirmed(file_data) => {
                     let room_input_bar = self.view.room_input_bar(cx, ids!(room_input_bar));
-                    if let Some(replied_to) = room_input_bar.handle_file_upload_confirmed(cx, &file_data.name) {
+                    if let Some(replied_to) = room_input_bar.handle_file_upload_confirmed(cx, &file_data.name, &tl.kind) {
+                        tl.draft_reply_event_id = None;
                         submit_async_request(MatrixRequest::SendAttachment {
                             timeline_kind: tl.kind.clone(),
                             file_data,
@@ -6618,9 +6886,9 @@ impl RoomScreen {
                     self.view.room_input_bar(cx, ids!(room_input_bar))
                         .set_upload_abort_handle(handle);
                 }
-                TimelineUpdate::FileUploadError { error, file_data } => {
+                TimelineUpdate::FileUploadError { error, file_data, replied_to } => {
                     self.view.room_input_bar(cx, ids!(room_input_bar))
-                        .show_upload_error(cx, &error, file_data);
+                        .show_upload_error(cx, &error, file_data, replied_to);
                 }
                 TimelineUpdate::FileUploadComplete => {
                     self.view.room_input_bar(cx, ids!(room_input_bar))
@@ -6629,6 +6897,21 @@ impl RoomScreen {
             }
         }
 
+        // Show the reply preview of a restored draft once its replied-to event is available.
+        if num_updates > 0
+            && let Some(draft_reply_event_id) = tl.draft_reply_event_id.as_deref()
+            && let Some(event_tl_item) = tl.items.iter()
+                .rev()
+                .filter_map(|item| item.as_event())
+                .find(|ev| ev.event_id() == Some(draft_reply_event_id))
+                .cloned()
+        {
+            tl.draft_reply_event_id = None;
+            let replied_to_info = EmbeddedEvent::from_timeline_item(&event_tl_item);
+            self.view.room_input_bar(cx, ids!(room_input_bar))
+                .show_replying_to(cx, (event_tl_item, replied_to_info), &tl.kind);
+        }
+
         if should_continue_backwards_pagination {
             tl.backwards_pagination_in_flight = true;
             submit_async_request(MatrixRequest::PaginateTimeline {
@@ -6667,7 +6950,7 @@ impl RoomScreen {
     ) -> bool {
         // A closure that handles both MatrixToUri and MatrixUri links,
         // and returns whether the link was handled.
-        let mut handle_matrix_link = |id: &MatrixId, _via: &[OwnedServerName]| -> bool {
+        let mut handle_matrix_link = |id: &MatrixId, via: &[OwnedServerName]| -> bool {
             match id {
                 MatrixId::User(user_id) => {
                     let Some(room_name_id) = self.room_name_id.as_ref() else {
@@ -6692,46 +6975,22 @@ impl RoomScreen {
                                 room_id: room_name_id.room_id().clone(),
                             },
                             room_name: room_name_id.to_string(),
-                            // TODO: use the extra `via` parameters
                             room_member: None,
                         },
                     );
                     true
                 }
                 MatrixId::Room(room_id) => {
-                    if self.room_name_id.as_ref().is_some_and(|r| r.room_id() == room_id) {
-                        enqueue_popup_notification(
-                            tr_key(self.app_language, "room_screen.popup.already_viewing_room"),
-                            PopupKind::Info,
-                            Some(4.0),
-                        );
-                        return true;
-                    }
-                    if let Some(room_name_id) = cx.get_global::<RoomsListRef>().get_room_name(room_id) {
-                        cx.action(AppStateAction::NavigateToRoom {
-                            room_to_close: None,
-                            destination_room: BasicRoomDetails::Name(room_name_id),
-                        });
-                        return true;
-                    } else {
-                        log!("TODO: fetch and display room preview for room {}", room_id);
-                    }
-                    false
+                    self.open_matrix_link(cx, room_id.clone().into(), None, via.to_vec());
+                    true
                 }
                 MatrixId::RoomAlias(room_alias) => {
-                    log!("TODO: open room alias {}", room_alias);
-                    // TODO: open a room loading screen that shows a spinner
-                    //       while our background async task calls Client::resolve_room_alias()
-                    //       and then either jumps to the room if known, or fetches and displays
-                    //       a room preview for that room.
-                    false
+                    self.open_matrix_link(cx, room_alias.clone().into(), None, via.to_vec());
+                    true
                 }
-                MatrixId::Event(room_id, event_id) => {
-                    log!("TODO: open event {} in room {}", event_id, room_id);
-                    // TODO: this requires the same first step as the `MatrixId::Room` case above,
-                    //       but then we need to call Room::event_with_context() to get the event
-                    //       and its context (surrounding events ?).
-                    false
+                MatrixId::Event(room_or_alias_id, event_id) => {
+                    self.open_matrix_link(cx, room_or_alias_id.clone(), Some(event_id.clone()), via.to_vec());
+                    true
                 }
                 _ => false,
             }
@@ -6839,6 +7098,51 @@ impl RoomScreen {
         populate_matrix_image_modal(cx, media_source, &mut tl_state.media_cache);
     }
 
+
+    /// Opens the video message at the given index in the image viewer modal and plays it,
+    /// showing the given poster frame `texture` while the video file is being fetched.
+    ///
+    /// Returns `false` if the item at the given index is not a video message.
+    fn handle_video_click(
+        &mut self,
+        cx: &mut Cx,
+        texture: Option<Texture>,
+        item_id: usize,
+    ) -> bool {
+        let Some(tl_state) = self.tl_state.as_mut() else { return false };
+        let Some(event_tl_item) = tl_state.items.get(item_id).and_then(|item| item.as_event()) else { return false };
+        let Some(MessageType::Video(video)) = event_tl_item.content().as_message().map(|message| message.msgtype()) else {
+            return false;
+        };
+        let MediaSource::Plain(mxc_uri) = &video.source else {
+            enqueue_popup_notification(
+                tr_key(self.app_language, "room_screen.video.encrypted_not_supported").to_string(),
+                PopupKind::Error,
+                None,
+            );
+            return true;
+        };
+
+        let file_name = video_playback_file_name(mxc_uri, video);
+        cx.action(ImageViewerAction::Show(LoadState::Loading(
+            texture,
+            Some(ImageViewerMetaData {
+                image_name: video.filename().to_string(),
+                image_file_size: video.info.as_ref().and_then(|info| info.size).map(u64::from).unwrap_or(0),
+                timestamp: unix_time_millis_to_datetime(event_tl_item.timestamp()),
+                avatar_parameter: Some((
+                    tl_state.kind.clone(),
+                    event_tl_item.clone(),
+                )),
+            }),
+        )));
+
+        let mxc_uri = mxc_uri.clone();
+        tl_state.pending_video_playback = populate_matrix_video_modal(cx, &mxc_uri, &file_name, &mut tl_state.media_cache)
+            .then_some((mxc_uri, file_name));
+        true
+    }
+
     /// Looks up the event specified by the given message details in the given timeline.
     ///
     /// This will first try an instant index-based lookup via `details.item_id`,
@@ -6939,7 +7243,9 @@ impl RoomScreen {
                     });
                 }
                 MessageAction::Reply(details) => {
-                    let Some(tl) = self.tl_state.as_ref() else { return };
+                    let Some(tl) = self.tl_state.as_mut() else { return };
+                    // A newly-chosen reply target replaces that of a restored draft.
+                    tl.draft_reply_event_id = None;
                     if let Some(event_tl_item) = Self::find_event_in_timeline(&tl.items, details).cloned() {
                         let replied_to_info = EmbeddedEvent::from_timeline_item(&event_tl_item);
                         self.view.room_input_bar(cx, ids!(room_input_bar))
@@ -7005,7 +7311,9 @@ impl RoomScreen {
                     }
                 }
                 MessageAction::MessageSubmittedLocally => {
-                    let Some(tl) = self.tl_state.as_ref() else { continue };
+                    let Some(tl) = self.tl_state.as_mut() else { continue };
+                    // The sent message used up the reply target of a restored draft, if any.
+                    tl.draft_reply_event_id = None;
                     let last_item_idx = tl.items.len().saturating_sub(1);
                     portal_list.set_first_id_and_scroll(last_item_idx, 0.0);
                     portal_list.set_tail_range(true);
@@ -7013,6 +7321,10 @@ impl RoomScreen {
                         .update_visibility(cx, true);
                     self.redraw(cx);
                 }
+                MessageAction::ReplyCanceled => {
+                    let Some(tl) = self.tl_state.as_mut() else { continue };
+                    tl.draft_reply_event_id = None;
+                }
                 MessageAction::Pin(details) => {
                     let Some(tl) = self.tl_state.as_ref() else { return };
                     if let Some(event_id) = details.event_id() {
@@ -7201,10 +7513,50 @@ impl RoomScreen {
                         title_text: tr_key(app_language, "room_screen.modal.delete_message.title").into(),
                         body_text: tr_key(app_language, "room_screen.modal.delete_message.body").into(),
                         accept_button_text: Some(tr_key(app_language, "room_screen.modal.delete_message.accept").into()),
-                        on_accept_clicked: Some(Box::new(move |_cx| {
+                        input_empty_text: Some(tr_key(app_language, "room_screen.modal.delete_message.reason_placeholder").to_string().into()),
+                        on_accept_clicked_with_input: Some(Box::new(move |_cx, entered_reason| {
                             submit_async_request(MatrixRequest::RedactMessage {
                                 timeline_kind,
                                 timeline_event_id,
+                                reason: reason.or(entered_reason),
+                            });
+                        })),
+                        ..Default::default()
+                    };
+                    cx.action(ConfirmDeleteAction::Show(RefCell::new(Some(content))));
+                }
+                MessageAction::RemoveRecentMessages(details) => {
+                    let Some(tl) = self.tl_state.as_ref() else { continue };
+                    let Some(event_tl_item) = Self::find_event_in_timeline(&tl.items, details) else {
+                        enqueue_popup_notification(
+                            tr_key(self.app_language, "room_screen.popup.message.remove_recent_not_found"),
+                            PopupKind::Error,
+                            Some(5.0),
+                        );
+                        continue;
+                    };
+                    let sender = event_tl_item.sender().to_owned();
+                    let sender_name = if let TimelineDetails::Ready(profile) = event_tl_item.sender_profile() {
+                        profile.display_name.clone().unwrap_or_else(|| sender.to_string())
+                    } else {
+                        sender.to_string()
+                    };
+                    let room_id = tl.kind.room_id().clone();
+                    let app_language = self.app_language;
+                    let count = RECENT_MESSAGES_TO_REMOVE.to_string();
+                    let content = ConfirmationModalContent {
+                        title_text: tr_key(app_language, "room_screen.modal.remove_recent_messages.title").into(),
+                        body_text: tr_fmt(app_language, "room_screen.modal.remove_recent_messages.body", &[
+                            ("count", count.as_str()),
+                            ("sender", sender_name.as_str()),
+                        ]).into(),
+                        accept_button_text: Some(tr_key(app_language, "room_screen.modal.remove_recent_messages.accept").into()),
+                        input_empty_text: Some(tr_key(app_language, "room_screen.modal.delete_message.reason_placeholder").to_string().into()),
+                        on_accept_clicked_with_input: Some(Box::new(move |_cx, reason| {
+                            submit_async_request(MatrixRequest::RemoveRecentMessages {
+                                room_id,
+                                sender,
+                                limit: RECENT_MESSAGES_TO_REMOVE,
                                 reason,
                             });
                         })),
@@ -7212,18 +7564,79 @@ impl RoomScreen {
                     };
                     cx.action(ConfirmDeleteAction::Show(RefCell::new(Some(content))));
                 }
-                // MessageAction::Report(details) => {
-                //     // TODO
-                // }
+                MessageAction::Report(details) => {
+                    let Some(tl) = self.tl_state.as_ref() else { continue };
+                    let Some(event_tl_item) = Self::find_event_in_timeline(&tl.items, details) else {
+                        enqueue_popup_notification(
+                            tr_key(self.app_language, "room_screen.popup.message.report_not_found"),
+                            PopupKind::Error,
+                            Some(5.0),
+                        );
+                        continue;
+                    };
+                    let Some(event_id) = event_tl_item.event_id().map(ToOwned::to_owned) else { continue };
+                    let sender = event_tl_item.sender().to_owned();
+                    let sender_name = if let TimelineDetails::Ready(profile) = event_tl_item.sender_profile() {
+                        profile.display_name.clone().unwrap_or_else(|| sender.to_string())
+                    } else {
+                        sender.to_string()
+                    };
+                    self.view
+                        .report_message_modal(cx, ids!(report_message_modal_inner))
+                        .show(cx, event_id, sender, &sender_name, self.app_language);
+                    self.view.modal(cx, ids!(report_message_modal)).open(cx);
+                }
+                MessageAction::Forward(details) => {
+                    let Some(tl) = self.tl_state.as_ref() else { continue };
+                    let Some(event_tl_item) = Self::find_event_in_timeline(&tl.items, details) else {
+                        enqueue_popup_notification(
+                            tr_key(self.app_language, "room_screen.popup.message.forward_not_found"),
+                            PopupKind::Error,
+                            Some(5.0),
+                        );
+                        continue;
+                    };
+                    let Some(message) = event_tl_item.content().as_message() else { continue };
+                    cx.action(ForwardMessageModalAction::Open {
+                        content: Box::new(forward_message::forwarded_content(message.msgtype())),
+                        preview_text: plaintext_body_of_timeline_item(event_tl_item),
+                    });
+                }
+                MessageAction::ToggleBookmark(details) => {
+                    let Some(tl) = self.tl_state.as_ref() else { continue };
+                    let Some(event_id) = details.event_id() else { continue };
+                    submit_async_request(MatrixRequest::SetBookmark {
+                        bookmark: Bookmark {
+                            room_id: tl.kind.room_id().clone(),
+                            event_id: event_id.clone(),
+                            thread_root_event_id: tl.kind.thread_root_event_id().cloned(),
+                            saved_ts: MilliSecondsSinceUnixEpoch::now(),
+                        },
+                        bookmarked: !details.abilities.contains(MessageAbilities::IsBookmarked),
+                    });
+                }
 
                 // This is handled within the Message widget itself.
                 MessageAction::HighlightMessage(..) => { }
-                // This is handled by the top-level App itself.
+                // These are handled by the top-level App itself.
                 MessageAction::OpenMessageContextMenu { .. } => { }
-                // This isn't yet handled, as we need to completely redesign it.
-                MessageAction::ActionBarOpen { .. } => { }
-                // This isn't yet handled, as we need to completely redesign it.
-                MessageAction::ActionBarClose => { }
+                MessageAction::OpenReactionInput { .. } => { }
+                MessageAction::ActionBarOpen { details, message_rect } => {
+                    let container_rect = portal_list.area().rect(cx);
+                    self.view.message_action_bar(cx, ids!(timeline.message_action_bar))
+                        .show(cx, details.clone(), *message_rect, container_rect, self.app_language);
+                }
+                MessageAction::ActionBarClose { item_id, abs_pos } => {
+                    let message_action_bar = self.view.message_action_bar(cx, ids!(timeline.message_action_bar));
+                    // Only close the bar if it's still shown for the message that was un-hovered
+                    // (another message may have already been hovered over),
+                    // and keep it open if the pointer moved from the message onto the bar itself.
+                    if message_action_bar.shown_item_id() == Some(*item_id)
+                        && !message_action_bar.contains(cx, *abs_pos)
+                    {
+                        message_action_bar.hide(cx);
+                    }
+                }
                 MessageAction::ToggleAppServiceActions => { }
                 MessageAction::None => { }
             }
@@ -7372,6 +7785,238 @@ impl RoomScreen {
         self.redraw(cx);
     }
 
+    /// Opens the room (and optionally jumps to the event) that a clicked Matrix link points to.
+    ///
+    /// Room aliases are first resolved into a room ID, while showing the loading pane.
+    fn open_matrix_link(
+        &mut self,
+        cx: &mut Cx,
+        target: OwnedRoomOrAliasId,
+        event_id: Option<OwnedEventId>,
+        via: Vec<OwnedServerName>,
+    ) {
+        if let Ok(room_id) = OwnedRoomId::try_from(target.as_str()) {
+            self.open_room_of_matrix_link(cx, room_id, event_id, via);
+            return;
+        }
+        let Ok(room_alias) = OwnedRoomAliasId::try_from(target.as_str()) else { return };
+        let loading_pane = self.view.loading_pane(cx, ids!(loading_pane));
+        loading_pane.set_state(cx, LoadingPaneState::OpeningMatrixLink { target: room_alias.to_string() });
+        loading_pane.show(cx);
+        self.pending_matrix_link = Some(PendingMatrixLink { target, event_id, via });
+        submit_async_request(MatrixRequest::ResolveRoomAlias(room_alias));
+    }
+
+    /// Opens the given room (and optionally jumps to the given event) on behalf of a clicked Matrix link.
+    ///
+    /// If the room is known, we navigate to it directly.
+    /// Otherwise, we fetch the room's preview (via the given servers) while showing the loading pane,
+    /// and then show that preview such that the user can join the room.
+    fn open_room_of_matrix_link(
+        &mut self,
+        cx: &mut Cx,
+        room_id: OwnedRoomId,
+        event_id: Option<OwnedEventId>,
+        via: Vec<OwnedServerName>,
+    ) {
+        let loading_pane = self.view.loading_pane(cx, ids!(loading_pane));
+        let main_timeline_kind = TimelineKind::MainRoom { room_id: room_id.clone() };
+        if self.timeline_kind.as_ref() == Some(&main_timeline_kind) {
+            loading_pane.set_state(cx, LoadingPaneState::None);
+            match event_id {
+                Some(event_id) => {
+                    request_jump_to_event(cx, main_timeline_kind, event_id);
+                    let portal_list = self.portal_list(cx, ids!(timeline.list));
+                    self.jump_to_pending_target_event(cx, &portal_list, &loading_pane);
+                }
+                None => enqueue_popup_notification(
+                    tr_key(self.app_language, "room_screen.popup.already_viewing_room"),
+                    PopupKind::Info,
+                    Some(4.0),
+                ),
+            }
+            return;
+        }
+
+        if let Some(room_name_id) = cx.get_global::<RoomsListRef>().get_room_name(&room_id) {
+            loading_pane.set_state(cx, LoadingPaneState::None);
+            if let Some(event_id) = event_id {
+                request_jump_to_event(cx, main_timeline_kind, event_id);
+            }
+            cx.action(AppStateAction::NavigateToRoom {
+                room_to_close: None,
+                destination_room: BasicRoomDetails::Name(room_name_id),
+            });
+            return;
+        }
+
+        loading_pane.set_state(cx, LoadingPaneState::OpeningMatrixLink { target: room_id.to_string() });
+        loading_pane.show(cx);
+        self.pending_matrix_link = Some(PendingMatrixLink {
+            target: room_id.clone().into(),
+            event_id,
+            via: via.clone(),
+        });
+        submit_async_request(MatrixRequest::GetRoomPreview { room_or_alias_id: room_id.into(), via });
+    }
+
+    /// Shows the progress and result of removing a user's recent messages in this room.
+    fn handle_remove_recent_messages_action(&mut self, cx: &mut Cx, action: &Action) {
+        let Some(remove_action) = action.downcast_ref::<RemoveRecentMessagesAction>() else { return };
+        // Only the main timeline of the affected room shows this progress,
+        // not any of its thread-focused timelines.
+        let Some(TimelineKind::MainRoom { room_id }) = self.timeline_kind.as_ref() else { return };
+        let loading_pane = self.view.loading_pane(cx, ids!(loading_pane));
+        match remove_action {
+            RemoveRecentMessagesAction::Progress { room_id: r, sender, processed, total, cancelled } if r == room_id => {
+                // The user already stopped the removal, so don't re-show the loading pane.
+                if cancelled.load(Ordering::Relaxed) { return; }
+                let current_state = loading_pane.take_state();
+                // Don't replace any other task that the loading pane is currently showing.
+                if !matches!(current_state, LoadingPaneState::None | LoadingPaneState::RemovingRecentMessages { .. }) {
+                    loading_pane.set_state(cx, current_state);
+                    return;
+                }
+                loading_pane.set_state(cx, LoadingPaneState::RemovingRecentMessages {
+                    sender: sender.clone(),
+                    processed: *processed,
+                    total: *total,
+                    cancelled: cancelled.clone(),
+                });
+                if !loading_pane.is_currently_shown(cx) {
+                    loading_pane.show(cx);
+                }
+            }
+            RemoveRecentMessagesAction::Finished { room_id: r, sender, removed, failed, was_cancelled } if r == room_id => {
+                let current_state = loading_pane.take_state();
+                if matches!(current_state, LoadingPaneState::RemovingRecentMessages { .. }) {
+                    loading_pane.set_state(cx, LoadingPaneState::None);
+                } else {
+                    loading_pane.set_state(cx, current_state);
+                }
+                let removed_str = removed.to_string();
+                let failed_str = failed.to_string();
+                let key = match (*was_cancelled, *failed > 0) {
+                    (true, _) => "room_screen.popup.remove_recent_messages.stopped",
+                    (false, true) => "room_screen.popup.remove_recent_messages.partially_done",
+                    (false, false) => "room_screen.popup.remove_recent_messages.done",
+                };
+                enqueue_popup_notification(
+                    tr_fmt(self.app_language, key, &[
+                        ("removed", removed_str.as_str()),
+                        ("failed", failed_str.as_str()),
+                        ("sender", sender.as_str()),
+                    ]),
+                    if *failed > 0 { PopupKind::Warning } else { PopupKind::Success },
+                    Some(5.0),
+                );
+            }
+            RemoveRecentMessagesAction::Failed { room_id: r, sender, error } if r == room_id => {
+                let current_state = loading_pane.take_state();
+                if matches!(current_state, LoadingPaneState::RemovingRecentMessages { .. }) {
+                    loading_pane.set_state(cx, LoadingPaneState::Error(tr_fmt(
+                        self.app_language,
+                        "room_screen.popup.remove_recent_messages.failed",
+                        &[("sender", sender.as_str()), ("error", error.as_str())],
+                    )));
+                } else {
+                    loading_pane.set_state(cx, current_state);
+                }
+            }
+            _ => { }
+        }
+    }
+
+    /// Handles the result of resolving the room alias or fetching the room preview
+    /// for the currently-pending Matrix link, if any.
+    fn handle_pending_matrix_link_action(&mut self, cx: &mut Cx, action: &Action) {
+        let Some(pending) = self.pending_matrix_link.as_ref() else { return };
+        let loading_pane = self.view.loading_pane(cx, ids!(loading_pane));
+        // The user closed the loading pane, which cancels opening the link.
+        if !loading_pane.is_currently_shown(cx) {
+            self.pending_matrix_link = None;
+            return;
+        }
+
+        match action.downcast_ref() {
+            Some(ResolveRoomAliasAction::Resolved { room_alias, room_id, servers })
+                if room_alias.as_str() == pending.target.as_str() =>
+            {
+                let Some(PendingMatrixLink { event_id, mut via, .. }) = self.pending_matrix_link.take() else { return };
+                // Prefer the servers given in the link itself, followed by those known to the alias.
+                for server in servers {
+                    if !via.contains(server) {
+                        via.push(server.clone());
+                    }
+                }
+                self.open_room_of_matrix_link(cx, room_id.clone(), event_id, via);
+                return;
+            }
+            Some(ResolveRoomAliasAction::Failed { room_alias, error })
+                if room_alias.as_str() == pending.target.as_str() =>
+            {
+                self.pending_matrix_link = None;
+                loading_pane.set_state(cx, LoadingPaneState::Error(tr_fmt(
+                    self.app_language,
+                    "room_screen.matrix_link.resolve_alias_failed",
+                    &[("alias", room_alias.as_str()), ("error", error.as_str())],
+                )));
+                return;
+            }
+            _ => {}
+        }
+
+        match action.downcast_ref() {
+            Some(RoomPreviewAction::Fetched { room_or_alias_id, result: Ok(frp) })
+                if *room_or_alias_id == pending.target =>
+            {
+                let Some(PendingMatrixLink { event_id, .. }) = self.pending_matrix_link.take() else { return };
+                loading_pane.set_state(cx, LoadingPaneState::None);
+                if let Some(event_id) = event_id {
+                    request_jump_to_event(
+                        cx,
+                        TimelineKind::MainRoom { room_id: frp.room_name_id.room_id().clone() },
+                        event_id,
+                    );
+                }
+                // If we haven't joined the room, this shows the room preview in a join modal.
+                cx.action(AppStateAction::NavigateToRoom {
+                    room_to_close: None,
+                    destination_room: frp.clone().into(),
+                });
+            }
+            Some(RoomPreviewAction::Fetched { room_or_alias_id, result: Err(error) })
+                if *room_or_alias_id == pending.target =>
+            {
+                let Some(pending) = self.pending_matrix_link.take() else { return };
+                let error = error.to_string();
+                loading_pane.set_state(cx, LoadingPaneState::Error(tr_fmt(
+                    self.app_language,
+                    "room_screen.matrix_link.preview_failed",
+                    &[("room", pending.target.as_str()), ("error", error.as_str())],
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    /// Jumps to this timeline's pending target event, if any.
+    ///
+    /// See [`request_jump_to_event()`].
+    fn jump_to_pending_target_event(
+        &mut self,
+        cx: &mut Cx,
+        portal_list: &PortalListRef,
+        loading_pane: &LoadingPaneRef,
+    ) {
+        if self.tl_state.is_none() { return }
+        let Some(timeline_kind) = self.timeline_kind.as_ref() else { return };
+        let Some(target_event_id) = PENDING_JUMP_TARGETS.with_borrow_mut(|targets| targets.remove(timeline_kind)) else {
+            return;
+        };
+        self.jump_to_event(cx, &target_event_id, None, portal_list, loading_pane);
+    }
+
     /// Shows the user profile sliding pane with the given avatar info.
     fn show_user_profile(
         &mut self,
@@ -7670,6 +8315,14 @@ impl RoomScreen {
                 None
             };
 
+            // Restore the composer draft that was persisted for this timeline in a prior session.
+            let draft = composer_drafts::get_draft(&kind);
+            let draft_reply_event_id = draft.as_ref().and_then(|d| d.replying_to_event_id.clone());
+            let saved_state = SavedState {
+                room_input_bar_state: draft.map(RoomInputBarState::from_draft).unwrap_or_default(),
+                ..Default::default()
+            };
+
             let tl_state = TimelineUiState {
                 kind,
                 // Initially, we assume the user has all power levels by default.
@@ -7694,7 +8347,10 @@ impl RoomScreen {
                 link_preview_cache: LinkPreviewCache::new(Some(update_sender)),
                 fetched_thread_summaries: HashMap::new(),
                 pending_thread_summary_fetches: HashSet::new(),
-                saved_state: SavedState::default(),
+                pending_audio_playback: None,
+                pending_video_playback: None,
+                saved_state,
+                draft_reply_event_id,
                 message_highlight_animation_state: MessageHighlightAnimationState::default(),
                 streaming_messages: HashMap::new(),
                 last_scrolled_index: usize::MAX,
@@ -7801,6 +8457,14 @@ impl RoomScreen {
         // we can proceed to processing pending background updates.
         self.process_timeline_updates(cx, &self.portal_list(cx, ids!(list)), None);
 
+        // If another part of the app requested to jump to an event in this timeline
+        // (e.g., a message search result), do so now that the timeline is shown.
+        self.jump_to_pending_target_event(
+            cx,
+            &self.portal_list(cx, ids!(list)),
+            &self.loading_pane(cx, ids!(loading_pane)),
+        );
+
         self.redraw(cx);
     }
 
@@ -7845,6 +8509,7 @@ impl RoomScreen {
             return;
         };
 
+        self.save_composer_draft_of(&tl);
         let portal_list = self.child_by_path(ids!(timeline.list)).as_portal_list();
         let room_input_bar = self.child_by_path(ids!(room_input_bar)).as_room_input_bar();
         log!("Saving state for room {:?}\n\t{:?}\n\tfirst_id: {:?}, scroll: {}", self.room_name_id.as_ref().map(|r| r.display_name()), self.timeline_kind, portal_list.first_id(), portal_list.scroll_position());
@@ -7861,6 +8526,24 @@ impl RoomScreen {
         TIMELINE_STATES.with_borrow_mut(|ts| ts.insert(tl.kind.clone(), tl));
     }
 
+    /// Persists the current contents of this room's message composer as a draft.
+    fn save_composer_draft(&self) {
+        if let Some(tl) = self.tl_state.as_ref() {
+            self.save_composer_draft_of(tl);
+        }
+    }
+
+    /// Persists the current contents of the message composer as a draft for the given timeline.
+    fn save_composer_draft_of(&self, tl: &TimelineUiState) {
+        let room_input_bar = self.child_by_path(ids!(room_input_bar)).as_room_input_bar();
+        let mut draft = room_input_bar.composer_draft(&tl.kind);
+        // Keep the reply target of a restored draft whose event hasn't been found yet.
+        if draft.replying_to_event_id.is_none() {
+            draft.replying_to_event_id = tl.draft_reply_event_id.clone();
+        }
+        composer_drafts::set_draft(draft);
+    }
+
     /// Restores the previously-saved visual UI state of this room.
     ///
     /// Note: this accepts a direct reference to the timeline's UI state,
@@ -8241,6 +8924,9 @@ pub enum TimelineUpdate {
     FileUploadError {
         error: String,
         file_data: crate::shared::file_upload_modal::FileData,
+        /// The reply metadata that the failed upload was sent with,
+        /// such that retrying it sends it as the same reply.
+        replied_to: Option<matrix_sdk::room::reply::Reply>,
     },
     /// File upload completed successfully.
     FileUploadComplete,
@@ -8254,6 +8940,40 @@ thread_local! {
         RefCell::new(HashMap::new());
 }
 
+thread_local! {
+    /// The set of events that should be jumped to once their timeline is shown,
+    /// e.g., after the user clicks on a message search result.
+    ///
+    /// This is only useful when accessed from the main UI thread.
+    static PENDING_JUMP_TARGETS: RefCell<HashMap<TimelineKind, OwnedEventId>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Requests that the given timeline jump to (and highlight) the given event.
+///
+/// The jump occurs once a RoomScreen shows that timeline, or upon the next action
+/// if that timeline is already being shown.
+/// If the event isn't yet in the timeline, the timeline will be paginated backwards
+/// until the event is found, just like jumping to a replied-to message.
+///
+/// This function requires passing in a reference to `Cx`,
+/// which isn't used, but acts as a guarantee that this function
+/// must only be called by the main UI thread.
+pub fn request_jump_to_event(_cx: &mut Cx, timeline_kind: TimelineKind, event_id: OwnedEventId) {
+    PENDING_JUMP_TARGETS.with_borrow_mut(|targets| targets.insert(timeline_kind, event_id));
+}
+
+/// A clicked Matrix link whose target room is still being resolved.
+struct PendingMatrixLink {
+    /// The room alias that is being resolved,
+    /// or the ID of the unknown room whose preview is being fetched.
+    target: OwnedRoomOrAliasId,
+    /// The event that the link points to, which is jumped to once the room is shown.
+    event_id: Option<OwnedEventId>,
+    /// The servers through which the room can be previewed and joined.
+    via: Vec<OwnedServerName>,
+}
+
 /// The UI-side state of a single room's timeline, which is only accessed/updated by the UI thread.
 ///
 /// This struct should only include states that need to be persisted for a given room
@@ -8333,11 +9053,22 @@ struct TimelineUiState {
     fetched_thread_summaries: HashMap<OwnedEventId, FetchedThreadSummary>,
     /// Set of thread roots currently being fetched to avoid duplicate in-flight requests.
     pending_thread_summary_fetches: HashSet<OwnedEventId>,
+    /// The audio clip (and its mimetype) that the user pressed play on,
+    /// which will be played once its file has been fetched.
+    pending_audio_playback: Option<(OwnedMxcUri, Option<String>)>,
+    /// The video clip (and the name of the file it will be saved to for playback)
+    /// that the user opened, which will be played once its file has been fetched.
+    pending_video_playback: Option<(OwnedMxcUri, String)>,
 
     /// The states relevant to the UI display of this timeline that are saved upon
     /// a `Hide` action and restored upon a `Show` action.
     saved_state: SavedState,
 
+    /// The event that a persisted composer draft was replying to, if any.
+    ///
+    /// The reply preview for this event is shown once the event is found in this timeline's `items`.
+    draft_reply_event_id: Option<OwnedEventId>,
+
     /// The state of the message highlight animation.
     ///
     /// We need to run the animation once the scrolling, triggered by the click of of a
@@ -8813,14 +9544,16 @@ fn populate_message_view(
                 MessageType::Location(location) => {
                     has_html_body = false;
                     let template = if use_compact_view {
-                        id!(CondensedMessage)
+                        id!(CondensedLocationMessage)
                     } else {
-                        id!(Message)
+                        id!(LocationMessage)
                     };
                     let (item, existed) = list.item_with_existed(cx, item_id, template);
                     if existed && item_drawn_status.content_drawn {
                         (item, true)
                     } else {
+                        item.map_thumbnail(cx, ids!(content.map_thumbnail))
+                            .set_geo_uri(cx, &location.geo_uri);
                         let html_or_plaintext_ref =
                             item.html_or_plaintext(cx, ids!(content.message));
                         let is_location_fully_drawn = populate_location_message_content(
@@ -8859,43 +9592,38 @@ fn populate_message_view(
                 MessageType::Audio(audio) => {
                     has_html_body = audio.formatted.as_ref().is_some_and(|f| f.format == MessageFormat::Html);
                     let template = if use_compact_view {
-                        id!(CondensedMessage)
+                        id!(CondensedAudioMessage)
                     } else {
-                        id!(Message)
+                        id!(AudioMessage)
                     };
                     let (item, existed) = list.item_with_existed(cx, item_id, template);
                     if existed && item_drawn_status.content_drawn {
                         (item, true)
                     } else {
-                        let html_or_plaintext_ref =
-                            item.html_or_plaintext(cx, ids!(content.message));
-                        new_drawn_status.content_drawn = populate_audio_message_content(
-                            cx,
-                            &html_or_plaintext_ref,
-                            app_language,
-                            audio,
-                        );
+                        item.audio_player(cx, ids!(content.message))
+                            .set_audio(cx, app_language, audio);
+                        new_drawn_status.content_drawn = true;
                         (item, false)
                     }
                 }
                 MessageType::Video(video) => {
                     has_html_body = video.formatted.as_ref().is_some_and(|f| f.format == MessageFormat::Html);
                     let template = if use_compact_view {
-                        id!(CondensedMessage)
+                        id!(CondensedVideoMessage)
                     } else {
-                        id!(Message)
+                        id!(VideoMessage)
                     };
                     let (item, existed) = list.item_with_existed(cx, item_id, template);
                     if existed && item_drawn_status.content_drawn {
                         (item, true)
                     } else {
-                        let html_or_plaintext_ref =
-                            item.html_or_plaintext(cx, ids!(content.message));
                         new_drawn_status.content_drawn = populate_video_message_content(
                             cx,
-                            &html_or_plaintext_ref,
+                            &item.text_or_image(cx, ids!(content.message)),
+                            &item.view(cx, ids!(content.video_details)),
                             app_language,
                             video,
+                            media_cache,
                         );
                         (item, false)
                     }
@@ -8991,6 +9719,51 @@ fn populate_message_view(
                 }
             }
         } 
+        MsgLikeKind::Poll(poll_state) => {
+            has_html_body = false;
+            let template = if use_compact_view {
+                id!(CondensedPollMessage)
+            } else {
+                id!(PollMessage)
+            };
+            let (item, existed) = list.item_with_existed(cx, item_id, template);
+            if existed && item_drawn_status.content_drawn {
+                (item, true)
+            } else {
+                item.poll_view(cx, ids!(content.message)).set_poll(
+                    cx,
+                    app_language,
+                    timeline_kind,
+                    event_tl_item.event_id().map(ToOwned::to_owned),
+                    event_tl_item.is_own(),
+                    poll_state,
+                );
+                new_drawn_status.content_drawn = true;
+                (item, false)
+            }
+        }
+        MsgLikeKind::LiveLocation(live_location) => {
+            has_html_body = false;
+            let template = if use_compact_view {
+                id!(CondensedLiveLocationMessage)
+            } else {
+                id!(LiveLocationMessage)
+            };
+            let (item, existed) = list.item_with_existed(cx, item_id, template);
+            if existed && item_drawn_status.content_drawn {
+                (item, true)
+            } else {
+                item.live_location_view(cx, ids!(content.message)).set_live_location(
+                    cx,
+                    app_language,
+                    timeline_kind.room_id(),
+                    event_tl_item.is_own(),
+                    live_location,
+                );
+                new_drawn_status.content_drawn = true;
+                (item, false)
+            }
+        }
         // Handle messages that have been redacted (deleted).
         MsgLikeKind::Redacted => {
             has_html_body = false;
@@ -9099,6 +9872,8 @@ fn populate_message_view(
             msg_like_content,
             pinned_events,
             has_html_body,
+            event_tl_item.event_id()
+                .is_some_and(|event_id| bookmarks::is_bookmarked(timeline_kind.room_id(), event_id)),
         ),
         should_be_highlighted: event_tl_item.is_highlighted(),
     };
@@ -9226,7 +10001,7 @@ fn populate_message_view(
 /// Draws the Html or plaintext body of the given Text or Notice message into the `message_content_widget`.
 /// Also populates link previews if a link_preview_ref is provided.
 /// Returns whether the text items were fully drawn.
-fn populate_text_message_content(
+pub fn populate_text_message_content(
     cx: &mut Cx,
     message_content_widget: &HtmlOrPlaintextRef,
     app_language: AppLanguage,
@@ -9696,107 +10471,107 @@ fn populate_file_message_content(
     true
 }
 
-/// Draws an audio message's content into the given `message_content_widget`.
+/// Fetches and decodes the given audio clip such that it will be played once it is ready.
 ///
-/// Returns whether the audio message content was fully drawn.
-fn populate_audio_message_content(
+/// If the clip's file is still being fetched, it is saved as the timeline's pending audio clip,
+/// and this function should be called again once that fetch has completed.
+fn play_audio_once_fetched(
     cx: &mut Cx,
-    message_content_widget: &HtmlOrPlaintextRef,
+    tl: &mut TimelineUiState,
     app_language: AppLanguage,
-    audio: &AudioMessageEventContent,
-) -> bool {
-    // Display the file name, human-readable size, caption, and a button to download it.
-    let filename = htmlize::escape_text(audio.filename());
-    let (duration, mime, size) = audio
-        .info
-        .as_ref()
-        .map(|info| (
-            info.duration
-                .map(|d| format!("  {:.2} sec,", d.as_secs_f64()))
-                .unwrap_or_default(),
-            info.mimetype
-                .as_ref()
-                .map(|m| format!("  {m},"))
-                .unwrap_or_default(),
-            info.size
-                .map(|bytes| format!("  ({}),", ByteSize::b(bytes.into())))
-                .unwrap_or_default(),
-        ))
-        .unwrap_or_default();
-    let caption = audio.formatted_caption()
-        .map(|fb| format!("<br><i>{}</i>", fb.body))
-        .or_else(|| audio.caption().map(|c| format!("<br><i>{c}</i>")))
-        .unwrap_or_default();
-
-    // TODO: add an audio to play the audio file
-
-    message_content_widget.show_html(
-        cx,
-        tr_fmt(app_language, "room_screen.audio.preview_html", &[
-            ("filename", &filename),
-            ("mime", mime.as_str()),
-            ("duration", duration.as_str()),
-            ("size", size.as_str()),
-            ("caption", caption.as_str()),
-        ]),
-    );
-    true
+    mxc_uri: OwnedMxcUri,
+    mimetype: Option<String>,
+) {
+    tl.pending_audio_playback = None;
+    match tl.media_cache.try_get_media_or_fetch(&mxc_uri, MediaFormat::File) {
+        (MediaCacheEntry::Loaded(data), MediaFormat::File) => {
+            audio_playback::decode(cx, &mxc_uri, data, mimetype, true);
+        }
+        (MediaCacheEntry::Failed(status_code), MediaFormat::File) => {
+            enqueue_popup_notification(
+                tr_fmt(app_language, "audio_player.popup.fetch_failed", &[("error", &status_code.to_string())]),
+                PopupKind::Error,
+                None,
+            );
+            // Remove the failed entry such that the next attempt will re-fetch the file.
+            tl.media_cache.remove_cache_entry(&mxc_uri, Some(MediaFormat::File));
+        }
+        _ => tl.pending_audio_playback = Some((mxc_uri, mimetype)),
+    }
 }
 
-
-/// Draws a video message's content into the given `message_content_widget`.
+/// Draws a video message's content: its poster frame (thumbnail) and its details.
+///
+/// Clicking the poster frame or the play button opens the video in the image viewer modal.
 ///
 /// Returns whether the video message content was fully drawn.
 fn populate_video_message_content(
     cx: &mut Cx,
-    message_content_widget: &HtmlOrPlaintextRef,
+    text_or_image_ref: &TextOrImageRef,
+    video_details_ref: &ViewRef,
     app_language: AppLanguage,
     video: &VideoMessageEventContent,
+    media_cache: &mut MediaCache,
 ) -> bool {
-    // Display the file name, human-readable size, caption, and a button to download it.
-    let filename = htmlize::escape_text(video.filename());
-    let (duration, mime, size, dimensions) = video
-        .info
-        .as_ref()
-        .map(|info| (
-            info.duration
-                .map(|d| format!("  {:.2} sec,", d.as_secs_f64()))
-                .unwrap_or_default(),
-            info.mimetype
-                .as_ref()
-                .map(|m| format!("  {m},"))
-                .unwrap_or_default(),
-            info.size
-                .map(|bytes| format!("  ({}),", ByteSize::b(bytes.into())))
-                .unwrap_or_default(),
-            info.width.and_then(|width|
-                info.height.map(|height| format!("  {width}x{height},"))
-            ).unwrap_or_default(),
-        ))
-        .unwrap_or_default();
-    let caption = video.formatted_caption()
-        .map(|fb| format!("<br><i>{}</i>", fb.body))
-        .or_else(|| video.caption().map(|c| format!("<br><i>{c}</i>")))
-        .unwrap_or_default();
+    let mut details = vec![video.filename().to_string()];
+    if let Some(info) = video.info.as_deref() {
+        if let Some(duration) = info.duration {
+            details.push(utils::format_media_duration(duration));
+        }
+        if let (Some(width), Some(height)) = (info.width, info.height) {
+            details.push(format!("{width}x{height}"));
+        }
+        if let Some(bytes) = info.size {
+            details.push(ByteSize::b(bytes.into()).to_string());
+        }
+    }
+    let mut details_text = details.join(" · ");
+    if let Some(caption) = video.caption() {
+        details_text.push('\n');
+        details_text.push_str(caption);
+    }
+    video_details_ref.label(cx, ids!(video_details_label)).set_text(cx, &details_text);
 
-    // TODO: add an video to play the video file
+    let play_button = video_details_ref.button(cx, ids!(play_video_button));
+    play_button.set_text(cx, tr_key(app_language, "room_screen.video.play"));
+    // We don't yet support fetching encrypted media.
+    let is_playable = matches!(video.source, MediaSource::Plain(_));
+    play_button.set_enabled(cx, is_playable);
+    if !is_playable {
+        text_or_image_ref.show_text(cx, tr_key(app_language, "room_screen.video.encrypted_not_supported"));
+        return true;
+    }
 
-    message_content_widget.show_html(
-        cx,
-        tr_fmt(app_language, "room_screen.video.preview_html", &[
-            ("filename", &filename),
-            ("mime", mime.as_str()),
-            ("duration", duration.as_str()),
-            ("size", size.as_str()),
-            ("dimensions", dimensions.as_str()),
-            ("caption", caption.as_str()),
-        ]),
-    );
-    true
+    let poster_source = video.info.as_ref().and_then(|info| info.thumbnail_source.as_ref());
+    let Some(MediaSource::Plain(poster_uri)) = poster_source else {
+        text_or_image_ref.show_text(cx, tr_key(app_language, "room_screen.video.no_preview"));
+        return true;
+    };
+    match media_cache.try_get_media_or_fetch(poster_uri, MEDIA_THUMBNAIL_FORMAT.into()) {
+        (MediaCacheEntry::Loaded(data), _media_format) => {
+            // The poster frame's source is the video itself, such that clicking on it plays the video.
+            let show_image_result = text_or_image_ref.show_image(cx, Some(video.source.clone()), |cx, img| {
+                utils::load_png_or_jpg(&img, cx, &data)
+                    .map(|()| img.size_in_pixels(cx).unwrap_or_default())
+            });
+            if let Err(e) = show_image_result {
+                error!("Failed to display poster frame of video {:?}: {e:?}", video.filename());
+                text_or_image_ref.show_text(cx, tr_key(app_language, "room_screen.video.no_preview"));
+            }
+            true
+        }
+        (MediaCacheEntry::Requested, _media_format) => {
+            text_or_image_ref.show_text(cx, tr_key(app_language, "room_screen.video.loading_preview"));
+            false
+        }
+        (MediaCacheEntry::Failed(_status_code), _media_format) => {
+            text_or_image_ref.show_text(cx, tr_key(app_language, "room_screen.video.no_preview"));
+            true
+        }
+    }
 }
 
 
-
 /// Draws the given location message's content into the `message_content_widget`.
 ///
 /// Returns whether the location message content was fully drawn.
@@ -9806,37 +10581,7 @@ fn populate_location_message_content(
     app_language: AppLanguage,
     location: &LocationMessageEventContent,
 ) -> bool {
-    let coords = location.geo_uri
-        .get(utils::GEO_URI_SCHEME.len() ..)
-        .and_then(|s| {
-            let mut iter = s.split(',');
-            if let (Some(lat), Some(long)) = (iter.next(), iter.next()) {
-                Some((lat, long))
-            } else {
-                None
-            }
-        });
-    if let Some((lat, long)) = coords {
-        let short_lat = lat.find('.').and_then(|dot| lat.get(..dot + 7)).unwrap_or(lat);
-        let short_long = long.find('.').and_then(|dot| long.get(..dot + 7)).unwrap_or(long);
-        let safe_lat = htmlize::escape_attribute(lat);
-        let safe_long = htmlize::escape_attribute(long);
-        let safe_geo_uri = htmlize::escape_attribute(&location.geo_uri);
-        let safe_short_lat = htmlize::escape_text(short_lat);
-        let safe_short_long = htmlize::escape_text(short_long);
-        let html_body = format!(
-            "{} <a href=\"{}\">{safe_short_lat},{safe_short_long}</a><br>\
-            <ul>\
-            <li><a href=\"https://www.openstreetmap.org/?mlat={safe_lat}&amp;mlon={safe_long}#map=15/{safe_lat}/{safe_long}\">{}</a></li>\
-            <li><a href=\"https://www.google.com/maps/search/?api=1&amp;query={safe_lat},{safe_long}\">{}</a></li>\
-            <li><a href=\"https://maps.apple.com/?ll={safe_lat},{safe_long}&amp;q={safe_lat},{safe_long}\">{}</a></li>\
-            </ul>",
-            tr_key(app_language, "room_screen.location.label"),
-            safe_geo_uri,
-            tr_key(app_language, "room_screen.location.open_osm"),
-            tr_key(app_language, "room_screen.location.open_google_maps"),
-            tr_key(app_language, "room_screen.location.open_apple_maps"),
-        );
+    if let Some(html_body) = location_html(app_language, &location.geo_uri) {
         message_content_widget.show_html(cx, html_body);
     } else {
         let escaped_body = htmlize::escape_text(&location.body);
@@ -9854,6 +10599,42 @@ fn populate_location_message_content(
     true
 }
 
+/// Returns HTML that shows the coordinates of the given `geo:` URI
+/// along with links to open that location in various map apps,
+/// or `None` if the URI doesn't contain valid coordinates.
+pub fn location_html(app_language: AppLanguage, geo_uri: &str) -> Option<String> {
+    let (lat, long) = geo_uri
+        .get(utils::GEO_URI_SCHEME.len() ..)
+        .and_then(|s| {
+            let mut iter = s.split(',');
+            if let (Some(lat), Some(long)) = (iter.next(), iter.next()) {
+                Some((lat, long))
+            } else {
+                None
+            }
+        })?;
+    let short_lat = lat.find('.').and_then(|dot| lat.get(..dot + 7)).unwrap_or(lat);
+    let short_long = long.find('.').and_then(|dot| long.get(..dot + 7)).unwrap_or(long);
+    let safe_lat = htmlize::escape_attribute(lat);
+    let safe_long = htmlize::escape_attribute(long);
+    let safe_geo_uri = htmlize::escape_attribute(geo_uri);
+    let safe_short_lat = htmlize::escape_text(short_lat);
+    let safe_short_long = htmlize::escape_text(short_long);
+    Some(format!(
+        "{} <a href=\"{}\">{safe_short_lat},{safe_short_long}</a><br>\
+        <ul>\
+        <li><a href=\"https://www.openstreetmap.org/?mlat={safe_lat}&amp;mlon={safe_long}#map=15/{safe_lat}/{safe_long}\">{}</a></li>\
+        <li><a href=\"https://www.google.com/maps/search/?api=1&amp;query={safe_lat},{safe_long}\">{}</a></li>\
+        <li><a href=\"https://maps.apple.com/?ll={safe_lat},{safe_long}&amp;q={safe_lat},{safe_long}\">{}</a></li>\
+        </ul>",
+        tr_key(app_language, "room_screen.location.label"),
+        safe_geo_uri,
+        tr_key(app_language, "room_screen.location.open_osm"),
+        tr_key(app_language, "room_screen.location.open_google_maps"),
+        tr_key(app_language, "room_screen.location.open_apple_maps"),
+    ))
+}
+
 
 /// Draws the given redacted message's content into the `message_content_widget`.
 ///
@@ -10207,28 +10988,6 @@ impl SmallStateEventContent for EncryptedMessage {
     }
 }
 
-// For other message-like content (custom message-like events).
-impl SmallStateEventContent for LiveLocationState {
-    fn populate_item_content(
-        &self,
-        cx: &mut Cx,
-        _list: &mut PortalList,
-        _item_id: usize,
-        item: WidgetRef,
-        _event_tl_item: &EventTimelineItem,
-        username: &str,
-        _item_drawn_status: ItemDrawnStatus,
-        mut new_drawn_status: ItemDrawnStatus,
-    ) -> (WidgetRef, ItemDrawnStatus) {
-        item.label(cx, ids!(content)).set_text(
-            cx,
-            &format!("{username} shared a live location."),
-        );
-        new_drawn_status.content_drawn = true;
-        (item, new_drawn_status)
-    }
-}
-
 impl SmallStateEventContent for OtherMessageLike {
     fn populate_item_content(
         &self,
@@ -10250,29 +11009,6 @@ impl SmallStateEventContent for OtherMessageLike {
     }
 }
 
-// TODO: once we properly display polls, we should remove this,
-//       because Polls shouldn't be displayed using the SmallStateEvent widget.
-impl SmallStateEventContent for PollState {
-    fn populate_item_content(
-        &self,
-        cx: &mut Cx,
-        _list: &mut PortalList,
-        _item_id: usize,
-        item: WidgetRef,
-        _event_tl_item: &EventTimelineItem,
-        _username: &str,
-        _item_drawn_status: ItemDrawnStatus,
-        mut new_drawn_status: ItemDrawnStatus,
-    ) -> (WidgetRef, ItemDrawnStatus) {
-        item.label(cx, ids!(content)).set_text(
-            cx,
-            self.fallback_text().unwrap_or_else(|| self.results().question).as_str(),
-        );
-        new_drawn_status.content_drawn = true;
-        (item, new_drawn_status)
-    }
-}
-
 impl SmallStateEventContent for timeline::OtherState {
     fn populate_item_content(
         &self,
@@ -10470,6 +11206,20 @@ pub enum InviteResultAction {
     },
 }
 
+/// The result of reporting an event (message) in a room.
+#[derive(Debug)]
+pub enum ReportEventResultAction {
+    Sent {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+    },
+    Failed {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+        error: matrix_sdk::Error,
+    },
+}
+
 /// The result of reporting a room.
 #[derive(Debug)]
 pub enum ReportRoomResultAction {
@@ -10511,6 +11261,8 @@ pub enum MessageAction {
     EditLatest,
     /// The user submitted a new local message and the timeline should follow the live tail.
     MessageSubmittedLocally,
+    /// The user canceled replying to a message in the message composer.
+    ReplyCanceled,
     /// The user clicked the "pin" button on a message.
     Pin(MessageDetails),
     /// The user clicked the "unpin" button on a message.
@@ -10538,8 +11290,15 @@ pub enum MessageAction {
         reason: Option<String>,
     },
 
-    // /// The user clicked the "report" button on a message.
-    // Report(MessageDetails),
+    /// The user clicked the "report" button on a message.
+    Report(MessageDetails),
+    /// The user clicked the "forward" button on a message.
+    Forward(MessageDetails),
+    /// The user clicked the "save message" (or "remove from saved") button on a message.
+    ToggleBookmark(MessageDetails),
+    /// The user (a moderator) clicked the "remove recent messages" button on a message,
+    /// which removes the recent messages sent by that message's sender.
+    RemoveRecentMessages(MessageDetails),
 
     /// The message at the given item index in the timeline should be highlighted.
     HighlightMessage(usize),
@@ -10552,18 +11311,30 @@ pub enum MessageAction {
         abs_pos: DVec2,
         opening_gesture: ContextMenuOpenGesture,
     },
+    /// The user clicked the "react" button in the message action bar,
+    /// so we should show the context menu with its reaction input already open.
+    OpenReactionInput {
+        details: MessageDetails,
+        /// The absolute position where we should show the context menu.
+        abs_pos: DVec2,
+        opening_gesture: ContextMenuOpenGesture,
+    },
     ToggleTranslationLangPopup {
         button_rect: Rect,
     },
-    /// The user requested opening the message action bar
+    /// The user hovered over a message, so we should show the message action bar for it.
     ActionBarOpen {
-        /// At the given timeline item index
-        item_id: usize,
+        details: MessageDetails,
         /// The message rect, so the action bar can be positioned relative to it
         message_rect: Rect,
     },
-    /// The user requested closing the message action bar
-    ActionBarClose,
+    /// The user stopped hovering over a message, so we should hide its message action bar.
+    ActionBarClose {
+        /// The timeline item index of the message that was hovered over.
+        item_id: usize,
+        /// The absolute position of the pointer when it left the message.
+        abs_pos: DVec2,
+    },
     /// The user requested toggling the in-room app service quick actions card.
     ToggleAppServiceActions,
     ShowThreadsPane,
@@ -10907,11 +11678,26 @@ impl Widget for Message {
             }
             Hit::FingerHoverIn(..) => {
                 self.animator_play(cx, ids!(hover.on));
-                // TODO: here, show the "action bar" buttons upon hover-in
+                // On mobile, the context menu (via long press) is the only way to act on a message.
+                if cx.display_context.is_desktop() {
+                    cx.widget_action(
+                        details.room_screen_widget_uid,
+                        MessageAction::ActionBarOpen {
+                            details: details.clone(),
+                            message_rect: message_view_area.rect(cx),
+                        },
+                    );
+                }
             }
-            Hit::FingerHoverOut(_fho) => {
+            Hit::FingerHoverOut(fho) => {
                 self.animator_play(cx, ids!(hover.off));
-                // TODO: here, hide the "action bar" buttons upon hover-out
+                cx.widget_action(
+                    details.room_screen_widget_uid,
+                    MessageAction::ActionBarClose {
+                        item_id: details.item_id,
+                        abs_pos: fho.abs,
+                    },
+                );
             }
             _ => { }
         }
@@ -10966,6 +11752,7 @@ pub fn clear_timeline_states(_cx: &mut Cx) {
     TIMELINE_STATES.with_borrow_mut(|states| {
         states.clear();
     });
+    PENDING_JUMP_TARGETS.with_borrow_mut(|targets| targets.clear());
 }
 
 #[cfg(test)]
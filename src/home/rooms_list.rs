@@ -1822,6 +1822,33 @@ impl RoomsListRef {
         }
         items
     }
+
+    /// Returns the joined rooms that messages can be sent to and that match `keywords`,
+    /// up to `max_results`.
+    ///
+    /// Rooms are matched using a [`RoomDisplayFilterBuilder`] filter, just like the rooms list,
+    /// and are ordered by their latest message, newest first.
+    /// Tombstoned rooms are excluded. If `keywords` is empty, all joined rooms are returned.
+    pub fn get_sendable_joined_rooms(&self, keywords: &str, max_results: usize) -> Vec<(RoomNameId, FetchedRoomAvatar)> {
+        let Some(inner) = self.borrow() else { return Vec::new(); };
+        let keywords = keywords.trim();
+        let (filter, _) = if keywords.is_empty() {
+            (RoomDisplayFilter::default(), None)
+        } else {
+            RoomDisplayFilterBuilder::new()
+                .set_keywords(keywords.into())
+                .set_filter_criteria(RoomFilterCriteria::All)
+                .build()
+        };
+        let mut rooms = inner.all_joined_rooms.values()
+            .filter(|jr| !jr.is_tombstoned && (filter)(*jr))
+            .collect::<Vec<_>>();
+        rooms.sort_by(|a, b| b.latest.as_ref().map(|(ts, _)| *ts).cmp(&a.latest.as_ref().map(|(ts, _)| *ts)));
+        rooms.into_iter()
+            .take(max_results)
+            .map(|jr| (jr.room_name_id.clone(), jr.room_avatar.clone()))
+            .collect()
+    }
 }
 
 pub struct RoomsListScopeProps {
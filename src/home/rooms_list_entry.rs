@@ -4,10 +4,10 @@ use matrix_sdk::ruma::OwnedRoomId;
 use crate::{
     app::AppState,
     i18n::{AppLanguage, tr_fmt, tr_key},
-    room::FetchedRoomAvatar, shared::{
+    room::{FetchedRoomAvatar, composer_drafts}, shared::{
         avatar::AvatarWidgetExt,
         html_or_plaintext::HtmlOrPlaintextWidgetExt, unread_badge::UnreadBadgeWidgetExt as _,
-    }, utils::{self, relative_format}
+    }, unsent_messages, utils::{self, relative_format}
 };
 
 use super::{ContextMenuOpenGesture, rooms_list::{InvitedRoomInfo, InviterInfo, JoinedRoomInfo, RoomsListScopeProps}};
@@ -32,6 +32,38 @@ script_mod! {
         }
     }
 
+    // A pencil icon to be displayed in the RoomsListEntry when the room has an unsent draft.
+    mod.widgets.DraftIcon = View {
+        width: Fit, height: Fit,
+        visible: false,
+
+        Icon {
+            width: 19, height: 19,
+            align: Align{x: 0.5, y: 0.5}
+            draw_icon +: {
+                svg: (ICON_EDIT)
+                color: (TIMESTAMP_TEXT_COLOR)
+            }
+            icon_walk: Walk{ width: 13, height: 13 }
+        }
+    }
+
+    // A warning icon to be displayed in the RoomsListEntry when messages in the room failed to send.
+    mod.widgets.UnsentIcon = View {
+        width: Fit, height: Fit,
+        visible: false,
+
+        Icon {
+            width: 19, height: 19,
+            align: Align{x: 0.5, y: 0.5}
+            draw_icon +: {
+                svg: (ICON_WARNING)
+                color: (COLOR_FG_DANGER_RED)
+            }
+            icon_walk: Walk{ width: 14, height: 14 }
+        }
+    }
+
     mod.widgets.RoomName = Label {
         width: Fill, height: Fit
         flow: Flow.Right{wrap: false},
@@ -165,6 +197,8 @@ script_mod! {
                 align: Align{x: 0.5, y: 0.5}
                 avatar := Avatar {}
                 room_name := mod.widgets.RoomName {}
+                unsent_icon := mod.widgets.UnsentIcon {}
+                draft_icon := mod.widgets.DraftIcon {}
                 unread_badge := UnreadBadge {}
                 tombstone_icon := mod.widgets.TombstoneIcon {}
             }
@@ -192,6 +226,8 @@ script_mod! {
                         View {
                             width: Fit, height: Fit
                             align: Align{ x: 1.0 }
+                            unsent_icon := mod.widgets.UnsentIcon {}
+                            draft_icon := mod.widgets.DraftIcon {}
                             unread_badge := UnreadBadge {}
                             tombstone_icon := mod.widgets.TombstoneIcon {}
                         }
@@ -358,6 +394,17 @@ impl RoomsListEntryContent {
         self.draw_common(cx, &room_info.room_avatar, room_info.is_selected);
         // Show tombstone icon if the room is tombstoned
         self.view.view(cx, ids!(tombstone_icon)).set_visible(cx, room_info.is_tombstoned);
+        // Show the draft icon if the room has an unsent draft, unless that draft
+        // is already visible in the composer of the currently-selected room.
+        self.view.view(cx, ids!(draft_icon)).set_visible(
+            cx,
+            !room_info.is_selected && composer_drafts::room_has_draft(room_info.room_name_id.room_id()),
+        );
+        // Show the unsent icon if any messages in the room failed to send.
+        self.view.view(cx, ids!(unsent_icon)).set_visible(
+            cx,
+            unsent_messages::num_failed_messages(Some(room_info.room_name_id.room_id())) > 0,
+        );
     }
 
     /// Populates this RoomsListEntry with info about an invited room.
@@ -13,6 +13,7 @@ use crate::{
     avatar_cache,
     home::navigation_tab_bar::{NavigationBarAction, SelectedTab},
     i18n::{AppLanguage, tr_key},
+    map_tiles,
     profile::user_profile_cache,
     shared::{
         image_viewer::{ImageViewerAction, ImageViewerError, LoadState},
@@ -194,6 +195,7 @@ impl Widget for RoomsListHeader {
                             // by RoomScreen in response to the StateUpdate action.
                             user_profile_cache::clear_all_pending_requests();
                             avatar_cache::clear_all_pending_and_failed_requests();
+                            map_tiles::clear_all_pending_and_failed_requests();
                             // Now that we're no longer offline, we also need to tell the
                             // ProfileIcon to refresh itself and fetch our own user's profile again.
                             SignalToUI::set_ui_signal();
@@ -2,7 +2,7 @@
 //!
 //! It differs in what content it includes based on the adaptive view:
 //! * On a narrow mobile view, it acts as the root_view of StackNavigation
-//!   * It includes a title label, a search bar, and the RoomsList.
+//!   * It includes a title label, a search bar, a message search button, and the RoomsList.
 //! * On a wide desktop view, it acts as a permanent tab that is on the left side of the dock.
 //!   * It only includes a title label and the RoomsList, because the SearcBar
 //!     is at the top of the HomeScreen in Desktop view.
@@ -113,6 +113,9 @@ script_mod! {
                         room_filter_input_bar := RoomFilterInputBar {}
                     }
 
+                    search_messages_button := SearchMessagesButton {}
+                    saved_messages_button := SavedMessagesButton {}
+                    unsent_messages_button := UnsentMessagesButton {}
                 }
             }
 
@@ -0,0 +1,315 @@
+//! UI widgets for listing the user's saved (bookmarked) messages.
+//!
+//! Bookmarks are stored in account data such that they are synced across devices,
+//! see the [`bookmarks`](crate::bookmarks) module.
+
+use makepad_widgets::*;
+use crate::{
+    app::AppState,
+    bookmarks::{BookmarkPreview, BookmarksAction},
+    home::search_messages::open_event_in_room,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    sliding_sync::{MatrixRequest, submit_async_request},
+    utils,
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+
+    mod.widgets.SavedMessagesButton = set_type_default() do #(SavedMessagesButton::register_widget(vm)) {
+        ..mod.widgets.RoundedView
+
+        width: Fit,
+        height: 35,
+        margin: 0
+
+        draw_bg +: {
+            color: (COLOR_PRIMARY)
+            color_hover: (COLOR_PRIMARY_DARKER)
+            border_radius: 4.0
+            border_color: (COLOR_SECONDARY)
+            border_size: 1.0
+        }
+        draw_icon +: {
+            svg: (ICON_BOOKMARK)
+            color: (COLOR_TEXT)
+        }
+        icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -1, right: -2} }
+
+        text: "Saved"
+        draw_text +: {
+            color: (COLOR_TEXT)
+        }
+    }
+
+    mod.widgets.SavedMessagesModal = #(SavedMessagesModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 480
+            height: Fit
+            flow: Down
+            spacing: 8
+            padding: Inset{top: 20, right: 25, bottom: 20, left: 25}
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 4.0
+            }
+
+            title_view := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{y: 0.5}
+
+                title := Label {
+                    width: Fill, height: Fit,
+                    draw_text +: {
+                        text_style: TITLE_TEXT {font_size: 13},
+                        color: #000
+                    }
+                    text: "Saved Messages"
+                }
+
+                close_button := RobrixNeutralIconButton {
+                    width: Fit,
+                    height: Fit,
+                    spacing: 0,
+                    padding: 12,
+                    draw_icon.svg: (ICON_CLOSE)
+                    icon_walk: Walk{width: 14, height: 14}
+                    text: ""
+                }
+            }
+
+            status_label := Label {
+                width: Fill,
+                height: Fit,
+                flow: Flow.Right{wrap: true},
+                margin: Inset{left: 1}
+                draw_text +: {
+                    text_style: REGULAR_TEXT {font_size: 9.5}
+                    color: #6D7682
+                }
+                text: ""
+            }
+
+            saved_list := PortalList {
+                width: Fill
+                height: 360
+                flow: Down
+                max_pull_down: 0.0
+
+                SavedMessage := SearchResultEntry {}
+            }
+        }
+    }
+}
+
+
+#[derive(Script, ScriptHook, Widget)]
+pub struct SavedMessagesButton {
+    #[deref] button: Button,
+    #[rust] app_language: AppLanguage,
+}
+
+impl Widget for SavedMessagesButton {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        let app_language = scope.data.get::<AppState>()
+            .map(|app_state| app_state.app_language)
+            .unwrap_or_default();
+        if self.app_language != app_language {
+            self.set_app_language(cx, app_language);
+        }
+        self.button.handle_event(cx, event, scope);
+
+        if let Event::Actions(actions) = event {
+            if self.button.clicked(actions) {
+                cx.action(SavedMessagesModalAction::Open);
+            }
+        }
+    }
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let app_language = scope.data.get::<AppState>()
+            .map(|app_state| app_state.app_language)
+            .unwrap_or_default();
+        if self.app_language != app_language {
+            self.set_app_language(cx, app_language);
+        }
+        self.button.draw_walk(cx, scope, walk)
+    }
+}
+
+impl SavedMessagesButton {
+    fn set_app_language(&mut self, cx: &mut Cx, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.button.set_text(cx, tr_key(self.app_language, "saved_messages.button.text"));
+    }
+}
+
+
+/// Actions emitted by other widgets to show or hide the `SavedMessagesModal`.
+#[derive(Clone, Debug)]
+pub enum SavedMessagesModalAction {
+    /// Open the modal to show the list of saved messages.
+    Open,
+    /// Close the modal.
+    Close,
+}
+
+/// A modal that lists all of the user's saved (bookmarked) messages.
+///
+/// Clicking a saved message opens its room (or thread)
+/// and jumps to that message in its timeline.
+#[derive(Script, ScriptHook, Widget)]
+pub struct SavedMessagesModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    /// Whether this modal is currently shown, such that it should refresh its list
+    /// whenever the set of bookmarks changes.
+    #[rust] is_shown: bool,
+    /// Whether we're waiting for the previews of the saved messages to be fetched.
+    #[rust] is_loading: bool,
+    #[rust] previews: Vec<BookmarkPreview>,
+}
+
+impl Widget for SavedMessagesModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let status_text = self.status_text();
+        self.view.label(cx, ids!(status_label)).set_text(cx, &status_text);
+
+        while let Some(widget) = self.view.draw_walk(cx, scope, walk).step() {
+            let portal_list_ref = widget.as_portal_list();
+            let Some(mut list) = portal_list_ref.borrow_mut() else { continue };
+
+            list.set_item_range(cx, 0, self.previews.len());
+            while let Some(item_id) = list.next_visible_item(cx) {
+                let Some(saved) = self.previews.get(item_id) else { continue };
+                let item = list.item(cx, item_id, id!(SavedMessage));
+                item.label(cx, ids!(room_name)).set_text(cx, &saved.room_name_id.to_string());
+                item.label(cx, ids!(time)).set_text(
+                    cx,
+                    &saved.timestamp.and_then(utils::relative_format).unwrap_or_default(),
+                );
+                let preview = if saved.preview.is_empty() {
+                    tr_key(self.app_language, "saved_messages.modal.unavailable")
+                } else {
+                    saved.preview.as_str()
+                };
+                item.label(cx, ids!(preview)).set_text(cx, preview);
+                item.draw_all(cx, &mut Scope::empty());
+            }
+        }
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for SavedMessagesModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let close_clicked = self.view.button(cx, ids!(close_button)).clicked(actions);
+        if close_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            self.is_shown = false;
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // a `SavedMessagesModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if close_clicked {
+                cx.action(SavedMessagesModalAction::Close);
+            }
+            return;
+        }
+
+        // Handle a saved message being clicked.
+        let saved_list = self.view.portal_list(cx, ids!(saved_list));
+        for (index, item) in saved_list.items_with_actions(actions) {
+            if item.button(cx, ids!(click_button)).clicked(actions)
+                && let Some(saved) = self.previews.get(index).cloned()
+            {
+                self.is_shown = false;
+                open_event_in_room(
+                    cx,
+                    self.widget_uid(),
+                    saved.room_name_id,
+                    saved.bookmark.event_id,
+                    saved.bookmark.thread_root_event_id,
+                );
+                cx.action(SavedMessagesModalAction::Close);
+                return;
+            }
+        }
+
+        for action in actions {
+            match action.downcast_ref() {
+                Some(BookmarksAction::PreviewsLoaded(previews)) if self.is_shown => {
+                    self.previews = previews.clone();
+                    self.is_loading = false;
+                    self.redraw(cx);
+                }
+                // Keep the list up to date if a message was (un-)bookmarked on another device.
+                Some(BookmarksAction::Updated) if self.is_shown => {
+                    submit_async_request(MatrixRequest::FetchBookmarkPreviews);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl SavedMessagesModal {
+    /// Shows the modal and starts fetching the previews of all saved messages.
+    pub fn show(&mut self, cx: &mut Cx, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.is_shown = true;
+        self.is_loading = true;
+        self.previews.clear();
+        self.update_static_texts(cx);
+        submit_async_request(MatrixRequest::FetchBookmarkPreviews);
+
+        self.view.button(cx, ids!(close_button)).reset_hover(cx);
+        self.view.portal_list(cx, ids!(saved_list)).set_first_id_and_scroll(0, 0.0);
+        self.view.redraw(cx);
+    }
+
+    fn status_text(&self) -> String {
+        if self.is_loading {
+            tr_key(self.app_language, "saved_messages.modal.status.loading").to_string()
+        } else if self.previews.is_empty() {
+            tr_key(self.app_language, "saved_messages.modal.status.empty").to_string()
+        } else {
+            tr_fmt(
+                self.app_language,
+                "saved_messages.modal.status.count",
+                &[("count", &self.previews.len().to_string())],
+            )
+        }
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(title))
+            .set_text(cx, tr_key(self.app_language, "saved_messages.modal.title"));
+    }
+}
+
+impl SavedMessagesModalRef {
+    /// See [`SavedMessagesModal::show()`].
+    pub fn show(&self, cx: &mut Cx, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, app_language);
+    }
+}
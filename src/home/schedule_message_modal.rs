@@ -0,0 +1,680 @@
+//! A modal for scheduling a message to be sent later, and for managing
+//! the messages that are already scheduled in a room.
+//!
+//! The `ScheduleMessageModal` is opened from the `RoomInputBar`'s "more actions" popup,
+//! pre-filled with the message that the user has typed so far.
+//! The actual scheduling is done in the background, see the [`scheduled_messages`] module.
+//!
+//! [`scheduled_messages`]: crate::scheduled_messages
+
+use chrono::{Datelike, Duration, Local, NaiveDate, Timelike};
+use makepad_widgets::*;
+use matrix_sdk::ruma::{
+    MilliSecondsSinceUnixEpoch, TransactionId,
+    events::{relation::Thread, room::message::{Relation, RoomMessageEventContent}},
+};
+
+use crate::{
+    app::AppState,
+    home::jump_to_date::{CalendarGridAction, CalendarGridWidgetRefExt, month_name_key, shift_month, start_of_month, weekday_label_id},
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    scheduled_messages::{ScheduledMessage, local_timestamp, parse_time_of_day},
+    sliding_sync::{MatrixRequest, TimelineKind, submit_async_request},
+    utils,
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    // A single message in the list of messages scheduled in a room.
+    let ScheduledMessageEntry = RoundedView {
+        width: Fill
+        height: Fit
+        flow: Right
+        align: Align{y: 0.5}
+        spacing: 6
+        margin: Inset{bottom: 6}
+        padding: Inset{top: 8, right: 8, bottom: 8, left: 12}
+
+        show_bg: true
+        draw_bg +: {
+            color: #F8FAFD
+            border_radius: 4.0
+            border_size: 1.0
+            border_color: #D8E0EA
+        }
+
+        info := View {
+            width: Fill
+            height: Fit
+            flow: Down
+            spacing: 3
+
+            send_at := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TIMESTAMP_TEXT_STYLE { font_size: 8 }
+                    color: (TIMESTAMP_TEXT_COLOR)
+                }
+                text: ""
+            }
+
+            preview := Label {
+                width: Fill
+                height: Fit
+                flow: Flow.Right{wrap: true}
+                draw_text +: {
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 10.0 }
+                    color: (COLOR_TEXT)
+                }
+                text: ""
+            }
+
+            error := Label {
+                visible: false
+                width: Fill
+                height: Fit
+                flow: Flow.Right{wrap: true}
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 9 }
+                    color: (COLOR_FG_DANGER_RED)
+                }
+                text: ""
+            }
+        }
+
+        edit_button := RobrixNeutralIconButton {
+            width: Fit
+            padding: Inset{top: 6, bottom: 6, left: 8, right: 8}
+            draw_icon.svg: (ICON_EDIT)
+            icon_walk: Walk{width: 13, height: 13, margin: Inset{right: -2}}
+            draw_text +: { text_style: REGULAR_TEXT { font_size: 9 } }
+            text: "Edit"
+        }
+
+        send_now_button := RobrixPositiveIconButton {
+            width: Fit
+            padding: Inset{top: 6, bottom: 6, left: 8, right: 8}
+            draw_icon.svg: (ICON_SEND)
+            icon_walk: Walk{width: 13, height: 13, margin: Inset{right: -2}}
+            draw_text +: { text_style: REGULAR_TEXT { font_size: 9 } }
+            text: "Send now"
+        }
+
+        delete_button := RobrixNegativeIconButton {
+            width: Fit
+            padding: Inset{top: 6, bottom: 6, left: 8, right: 8}
+            draw_icon.svg: (ICON_TRASH)
+            icon_walk: Walk{width: 13, height: 13, margin: Inset{right: -2}}
+            draw_text +: { text_style: REGULAR_TEXT { font_size: 9 } }
+            text: "Cancel"
+        }
+    }
+
+    mod.widgets.ScheduleMessageModal = #(ScheduleMessageModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 560
+            height: Fit
+            flow: Down
+            padding: Inset{top: 24, right: 24, bottom: 20, left: 24}
+            spacing: 12
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 6.0
+            }
+
+            title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 13 }
+                    color: #000
+                }
+                text: "Send Later"
+            }
+
+            message_input := RobrixTextInput {
+                width: Fill
+                height: Fit{max: 120}
+                is_multiline: true
+                draw_text +: {
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 10.5 }
+                    color: #000
+                }
+                empty_text: ""
+            }
+
+            picker_row := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                spacing: 20
+
+                calendar := View {
+                    width: 264
+                    height: Fit
+                    flow: Down
+                    spacing: 8
+
+                    month_row := View {
+                        width: 264
+                        height: Fit
+                        flow: Right
+                        align: Align{x: 0.5, y: 0.5}
+
+                        prev_month_button := RobrixNeutralIconButton {
+                            width: Fit
+                            padding: Inset{top: 6, bottom: 6, left: 10, right: 10}
+                            icon_walk: Walk{width: 0, height: 0}
+                            spacing: 0
+                            text: "‹"
+                        }
+
+                        month_label := Label {
+                            width: Fill
+                            height: Fit
+                            align: Align{x: 0.5}
+                            draw_text +: {
+                                text_style: REGULAR_TEXT { font_size: 11.5 }
+                                color: #000
+                            }
+                            text: ""
+                        }
+
+                        next_month_button := RobrixNeutralIconButton {
+                            width: Fit
+                            padding: Inset{top: 6, bottom: 6, left: 10, right: 10}
+                            icon_walk: Walk{width: 0, height: 0}
+                            spacing: 0
+                            text: "›"
+                        }
+                    }
+
+                    weekdays := View {
+                        width: 264
+                        height: Fit
+                        flow: Right
+                        spacing: 2.0
+
+                        weekday_0 := mod.widgets.WeekdayLabel { }
+                        weekday_1 := mod.widgets.WeekdayLabel { }
+                        weekday_2 := mod.widgets.WeekdayLabel { }
+                        weekday_3 := mod.widgets.WeekdayLabel { }
+                        weekday_4 := mod.widgets.WeekdayLabel { }
+                        weekday_5 := mod.widgets.WeekdayLabel { }
+                        weekday_6 := mod.widgets.WeekdayLabel { }
+                    }
+
+                    day_grid := mod.widgets.CalendarGrid { }
+                }
+
+                time_column := View {
+                    width: Fill
+                    height: Fit
+                    flow: Down
+                    spacing: 8
+
+                    time_label := Label {
+                        width: Fill
+                        height: Fit
+                        draw_text +: {
+                            text_style: REGULAR_TEXT { font_size: 10.5 }
+                            color: #333
+                        }
+                        text: "Time (HH:MM)"
+                    }
+
+                    time_input := RobrixTextInput {
+                        width: 100
+                        draw_text +: {
+                            text_style: REGULAR_TEXT { font_size: 11 }
+                            color: #000
+                        }
+                        empty_text: "09:00"
+                    }
+
+                    send_at_label := Label {
+                        width: Fill
+                        height: Fit
+                        flow: Flow.Right{wrap: true},
+                        draw_text +: {
+                            text_style: REGULAR_TEXT { font_size: 10 }
+                            color: #6D7682
+                        }
+                        text: ""
+                    }
+                }
+            }
+
+            buttons := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                align: Align{x: 1.0, y: 0.5}
+                spacing: 12
+
+                cancel_button := RobrixNeutralIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_FORBIDDEN)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Cancel"
+                }
+
+                schedule_button := RobrixPositiveIconButton {
+                    width: Fit
+                    align: Align{x: 0.5, y: 0.5}
+                    padding: 12
+                    draw_icon.svg: (ICON_SEND)
+                    icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -2, right: -1}}
+                    text: "Schedule"
+                }
+            }
+
+            LineH { margin: Inset{top: 4, bottom: 4} }
+
+            pending_title := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 11 }
+                    color: #000
+                }
+                text: "Scheduled messages in this room"
+            }
+
+            pending_empty_label := Label {
+                width: Fill
+                height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 9.5 }
+                    color: #6D7682
+                }
+                text: ""
+            }
+
+            pending_list := PortalList {
+                width: Fill
+                height: 180
+                flow: Down
+                max_pull_down: 0.0
+
+                ScheduledMessage := ScheduledMessageEntry {}
+            }
+        }
+    }
+}
+
+/// Actions emitted by other widgets to show or hide the `ScheduleMessageModal`,
+/// and by the modal itself once a draft message was scheduled.
+#[derive(Clone, Debug)]
+pub enum ScheduleMessageModalAction {
+    /// Open the modal to schedule a message to be sent to the given timeline.
+    ///
+    /// The modal is pre-filled with the given Markdown `text` and its `content`,
+    /// which is what the user has typed so far (and may be empty).
+    Open {
+        timeline_kind: TimelineKind,
+        text: String,
+        content: Box<RoomMessageEventContent>,
+    },
+    /// The draft message that was typed into the given timeline's `RoomInputBar`
+    /// was scheduled, so it should be cleared from that input bar.
+    DraftScheduled {
+        timeline_kind: TimelineKind,
+    },
+    /// Close the modal.
+    Close,
+}
+
+/// A modal for choosing when a message should be sent,
+/// which also lists the messages already scheduled in the same room.
+#[derive(Script, ScriptHook, Widget)]
+pub struct ScheduleMessageModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    /// The timeline that the message will be sent to.
+    #[rust] timeline_kind: Option<TimelineKind>,
+    /// The Markdown text and content that the modal was opened with,
+    /// which are used to keep the original message's mentions.
+    #[rust] original: Option<(String, RoomMessageEventContent)>,
+    /// The previously-scheduled message that is being edited, if any.
+    #[rust] editing: Option<ScheduledMessage>,
+    /// The first day of the month currently shown in the calendar.
+    #[rust] displayed_month: Option<NaiveDate>,
+    #[rust] selected_date: Option<NaiveDate>,
+    /// The messages scheduled in this modal's room, ordered by when they'll be sent.
+    #[rust] pending: Vec<ScheduledMessage>,
+}
+
+impl Widget for ScheduleMessageModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        if let (Some(app_state), Some(timeline_kind)) = (scope.data.get::<AppState>(), self.timeline_kind.as_ref()) {
+            self.pending = app_state.scheduled_messages.iter()
+                .filter(|m| &m.room_id == timeline_kind.room_id())
+                .cloned()
+                .collect();
+            self.pending.sort_by_key(|m| m.send_at);
+        }
+        let pending_empty_label = self.view.label(cx, ids!(pending_empty_label));
+        pending_empty_label.set_visible(cx, self.pending.is_empty());
+        pending_empty_label.set_text(cx, tr_key(self.app_language, "schedule_message_modal.pending.empty"));
+        self.view.portal_list(cx, ids!(pending_list)).set_visible(cx, !self.pending.is_empty());
+
+        while let Some(widget) = self.view.draw_walk(cx, scope, walk).step() {
+            let portal_list_ref = widget.as_portal_list();
+            let Some(mut list) = portal_list_ref.borrow_mut() else { continue };
+
+            list.set_item_range(cx, 0, self.pending.len());
+            while let Some(item_id) = list.next_visible_item(cx) {
+                let Some(scheduled) = self.pending.get(item_id) else { continue };
+                let item = list.item(cx, item_id, id!(ScheduledMessage));
+                let send_at = format_send_at(scheduled.send_at);
+                item.label(cx, ids!(send_at)).set_text(cx, &tr_fmt(
+                    self.app_language,
+                    if scheduled.is_scheduled_on_server() {
+                        "schedule_message_modal.pending.sent_by_server"
+                    } else {
+                        "schedule_message_modal.pending.sent_by_robrix"
+                    },
+                    &[("time", &send_at)],
+                ));
+                item.label(cx, ids!(preview)).set_text(cx, &scheduled.text);
+                let error_label = item.label(cx, ids!(error));
+                error_label.set_visible(cx, scheduled.last_error.is_some());
+                if let Some(error) = scheduled.last_error.as_deref() {
+                    error_label.set_text(cx, &tr_fmt(
+                        self.app_language,
+                        "schedule_message_modal.pending.failed",
+                        &[("error", error)],
+                    ));
+                }
+                item.button(cx, ids!(edit_button))
+                    .set_text(cx, tr_key(self.app_language, "schedule_message_modal.pending.button.edit"));
+                item.button(cx, ids!(send_now_button))
+                    .set_text(cx, tr_key(self.app_language, "schedule_message_modal.pending.button.send_now"));
+                item.button(cx, ids!(delete_button))
+                    .set_text(cx, tr_key(self.app_language, "schedule_message_modal.pending.button.cancel"));
+                item.draw_all(cx, &mut Scope::empty());
+            }
+        }
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for ScheduleMessageModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let cancel_clicked = self.view.button(cx, ids!(buttons.cancel_button)).clicked(actions);
+        if cancel_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // a `ScheduleMessageModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if cancel_clicked {
+                cx.action(ScheduleMessageModalAction::Close);
+            }
+            return;
+        }
+
+        if self.view.button(cx, ids!(month_row.prev_month_button)).clicked(actions) {
+            self.shift_displayed_month(cx, -1);
+        }
+        if self.view.button(cx, ids!(month_row.next_month_button)).clicked(actions) {
+            self.shift_displayed_month(cx, 1);
+        }
+
+        let day_grid = self.view.calendar_grid(cx, ids!(day_grid));
+        if let CalendarGridAction::Selected(date) = actions.find_widget_action(day_grid.widget_uid()).cast() {
+            self.selected_date = Some(date);
+            day_grid.set_selected(cx, Some(date));
+            self.update_send_at_label(cx);
+        }
+
+        if self.view.text_input(cx, ids!(time_input)).changed(actions).is_some()
+            || self.view.text_input(cx, ids!(message_input)).changed(actions).is_some()
+        {
+            self.update_send_at_label(cx);
+        }
+
+        if self.view.button(cx, ids!(buttons.schedule_button)).clicked(actions) {
+            self.schedule(cx);
+            return;
+        }
+
+        // Handle one of the buttons of a scheduled message being clicked.
+        let pending_list = self.view.portal_list(cx, ids!(pending_list));
+        for (index, item) in pending_list.items_with_actions(actions) {
+            let Some(scheduled) = self.pending.get(index).cloned() else { continue };
+            if item.button(cx, ids!(edit_button)).clicked(actions) {
+                self.start_editing(cx, scheduled);
+            } else if item.button(cx, ids!(send_now_button)).clicked(actions) {
+                submit_async_request(MatrixRequest::SendScheduledMessageNow { scheduled });
+            } else if item.button(cx, ids!(delete_button)).clicked(actions) {
+                if self.editing.as_ref().is_some_and(|m| m.id == scheduled.id) {
+                    self.editing = None;
+                    self.update_static_texts(cx);
+                }
+                submit_async_request(MatrixRequest::CancelScheduledMessage { scheduled });
+            }
+        }
+    }
+}
+
+impl ScheduleMessageModal {
+    /// Shows the modal, pre-filled with the given draft message for the given timeline.
+    pub fn show(
+        &mut self,
+        cx: &mut Cx,
+        timeline_kind: TimelineKind,
+        text: String,
+        content: RoomMessageEventContent,
+        app_language: AppLanguage,
+    ) {
+        self.app_language = app_language;
+        self.timeline_kind = Some(timeline_kind);
+        self.editing = None;
+        self.view.text_input(cx, ids!(message_input)).set_text(cx, &text);
+        self.original = Some((text, content));
+
+        // By default, schedule the message for the start of the next hour.
+        let next_hour = Local::now() + Duration::hours(1);
+        let next_hour = next_hour.with_minute(0).unwrap_or(next_hour);
+        self.set_send_at(cx, next_hour.date_naive(), &next_hour.format("%H:%M").to_string());
+
+        self.update_static_texts(cx);
+        self.view.button(cx, ids!(buttons.schedule_button)).reset_hover(cx);
+        self.view.button(cx, ids!(buttons.cancel_button)).reset_hover(cx);
+        self.view.portal_list(cx, ids!(pending_list)).set_first_id_and_scroll(0, 0.0);
+        self.view.text_input(cx, ids!(message_input)).set_key_focus(cx);
+        self.view.redraw(cx);
+    }
+
+    /// Fills in this modal's fields with the given scheduled message, such that it can be edited.
+    fn start_editing(&mut self, cx: &mut Cx, scheduled: ScheduledMessage) {
+        self.view.text_input(cx, ids!(message_input)).set_text(cx, &scheduled.text);
+        self.original = Some((scheduled.text.clone(), scheduled.content.clone()));
+        if let Some(send_at) = utils::unix_time_millis_to_datetime(scheduled.send_at) {
+            self.set_send_at(cx, send_at.date_naive(), &send_at.format("%H:%M").to_string());
+        }
+        self.editing = Some(scheduled);
+        self.update_static_texts(cx);
+        self.view.redraw(cx);
+    }
+
+    /// Selects the given date in the calendar and fills in the given time of day.
+    fn set_send_at(&mut self, cx: &mut Cx, date: NaiveDate, time: &str) {
+        self.selected_date = Some(date);
+        self.displayed_month = Some(start_of_month(date));
+        self.view.text_input(cx, ids!(time_input)).set_text(cx, time);
+        self.update_calendar(cx);
+    }
+
+    /// Returns when the message should be sent, based on the selected date and the entered time.
+    fn send_at(&self, cx: &mut Cx) -> Option<MilliSecondsSinceUnixEpoch> {
+        let time = parse_time_of_day(&self.view.text_input(cx, ids!(time_input)).text())?;
+        local_timestamp(self.selected_date?, time)
+    }
+
+    /// Schedules the entered message (or saves the edited one), and closes this modal.
+    fn schedule(&mut self, cx: &mut Cx) {
+        let Some(timeline_kind) = self.timeline_kind.clone() else { return };
+        let text = self.view.text_input(cx, ids!(message_input)).text().trim().to_owned();
+        let Some(send_at) = self.send_at(cx) else { return };
+        if text.is_empty() || send_at <= MilliSecondsSinceUnixEpoch::now() {
+            return;
+        }
+
+        // Keep the original content (e.g., its mentions) unless the user changed the text.
+        let mut content = match self.original.take() {
+            Some((original_text, original_content)) if original_text == text => original_content,
+            original => {
+                let mut content = RoomMessageEventContent::text_markdown(&text);
+                content.mentions = original.and_then(|(_, c)| c.mentions);
+                content
+            }
+        };
+        let editing = self.editing.take();
+        let thread_root_event_id = match editing.as_ref() {
+            Some(scheduled) => scheduled.thread_root_event_id.clone(),
+            None => timeline_kind.thread_root_event_id().cloned(),
+        };
+        if let Some(thread_root_event_id) = thread_root_event_id.clone() {
+            content.relates_to = Some(Relation::Thread(Thread::without_fallback(thread_root_event_id)));
+        }
+
+        let is_new_draft = editing.is_none();
+        let scheduled = ScheduledMessage {
+            id: editing.as_ref().map_or_else(TransactionId::new, |m| m.id.clone()),
+            room_id: timeline_kind.room_id().clone(),
+            thread_root_event_id,
+            text,
+            content,
+            send_at,
+            delay_id: editing.and_then(|m| m.delay_id),
+            last_error: None,
+        };
+        submit_async_request(MatrixRequest::ScheduleMessage { scheduled });
+        if is_new_draft {
+            cx.action(ScheduleMessageModalAction::DraftScheduled { timeline_kind });
+        }
+        cx.action(ScheduleMessageModalAction::Close);
+    }
+
+    fn shift_displayed_month(&mut self, cx: &mut Cx, delta: i32) {
+        let today = Local::now().date_naive();
+        let Some(displayed_month) = self.displayed_month else { return };
+        let shifted = shift_month(displayed_month, delta);
+        // Don't allow showing months in the past, as messages can't be sent back in time.
+        if shifted < start_of_month(today) {
+            return;
+        }
+        self.displayed_month = Some(shifted);
+        self.update_calendar(cx);
+    }
+
+    fn update_calendar(&mut self, cx: &mut Cx) {
+        let Some(displayed_month) = self.displayed_month else { return };
+        let today = Local::now().date_naive();
+        let month_name = tr_key(self.app_language, month_name_key(displayed_month.month()));
+        self.view.label(cx, ids!(month_row.month_label)).set_text(cx, &tr_fmt(
+            self.app_language,
+            "jump_to_date.month_label",
+            &[("month", month_name), ("year", &displayed_month.year().to_string())],
+        ));
+        self.view.button(cx, ids!(month_row.prev_month_button))
+            .set_enabled(cx, displayed_month > start_of_month(today));
+        self.view.calendar_grid(cx, ids!(day_grid))
+            .set_month(cx, displayed_month, self.selected_date, Some(today), None);
+        self.update_send_at_label(cx);
+        self.view.redraw(cx);
+    }
+
+    /// Shows when the message will be sent (or why it can't be scheduled),
+    /// and enables the schedule button only if the message can be scheduled.
+    fn update_send_at_label(&mut self, cx: &mut Cx) {
+        let send_at = self.send_at(cx);
+        let text = match send_at {
+            None => tr_key(self.app_language, "schedule_message_modal.error.invalid_time").to_string(),
+            Some(send_at) if send_at <= MilliSecondsSinceUnixEpoch::now() =>
+                tr_key(self.app_language, "schedule_message_modal.error.past_time").to_string(),
+            Some(send_at) => tr_fmt(
+                self.app_language,
+                "schedule_message_modal.send_at",
+                &[("time", &format_send_at(send_at))],
+            ),
+        };
+        self.view.label(cx, ids!(send_at_label)).set_text(cx, &text);
+
+        let has_text = !self.view.text_input(cx, ids!(message_input)).text().trim().is_empty();
+        let is_in_future = send_at.is_some_and(|t| t > MilliSecondsSinceUnixEpoch::now());
+        self.view.button(cx, ids!(buttons.schedule_button)).set_enabled(cx, has_text && is_in_future);
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        let is_editing = self.editing.is_some();
+        self.view.label(cx, ids!(title)).set_text(cx, tr_key(
+            self.app_language,
+            if is_editing { "schedule_message_modal.title_edit" } else { "schedule_message_modal.title" },
+        ));
+        self.view.text_input(cx, ids!(message_input))
+            .set_empty_text(cx, tr_key(self.app_language, "schedule_message_modal.message_placeholder").to_string());
+        self.view.label(cx, ids!(time_label))
+            .set_text(cx, tr_key(self.app_language, "schedule_message_modal.time_label"));
+        for (index, weekday) in tr_key(self.app_language, "jump_to_date.weekdays").split(',').take(7).enumerate() {
+            self.view.label(cx, weekday_label_id(index)).set_text(cx, weekday.trim());
+        }
+        self.view.button(cx, ids!(buttons.cancel_button))
+            .set_text(cx, tr_key(self.app_language, "schedule_message_modal.button.cancel"));
+        self.view.button(cx, ids!(buttons.schedule_button)).set_text(cx, tr_key(
+            self.app_language,
+            if is_editing { "schedule_message_modal.button.save" } else { "schedule_message_modal.button.schedule" },
+        ));
+        self.view.label(cx, ids!(pending_title))
+            .set_text(cx, tr_key(self.app_language, "schedule_message_modal.pending.title"));
+        if self.displayed_month.is_some() {
+            self.update_calendar(cx);
+        }
+    }
+}
+
+/// Formats the given time at which a message will be sent, in the local time zone.
+pub fn format_send_at(send_at: MilliSecondsSinceUnixEpoch) -> String {
+    utils::unix_time_millis_to_datetime(send_at)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+impl ScheduleMessageModalRef {
+    /// See [`ScheduleMessageModal::show()`].
+    pub fn show(
+        &self,
+        cx: &mut Cx,
+        timeline_kind: TimelineKind,
+        text: String,
+        content: RoomMessageEventContent,
+        app_language: AppLanguage,
+    ) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, timeline_kind, text, content, app_language);
+    }
+}
@@ -1,8 +1,18 @@
-
 //! UI widgets for searching messages in one or more rooms.
+//!
+//! Searching is done by the homeserver via the `/search` API,
+//! see [`MatrixRequest::SearchMessages`].
 
 use makepad_widgets::*;
-use crate::{app::AppState, i18n::{AppLanguage, tr_key}};
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+use crate::{
+    app::{AppState, AppStateAction, SelectedRoom},
+    home::{room_screen::request_jump_to_event, rooms_list::RoomsListAction},
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    room::BasicRoomDetails,
+    sliding_sync::{MatrixRequest, MessageSearchResult, SearchMessagesAction, TimelineKind, submit_async_request},
+    utils::{self, RoomNameId},
+};
 
 script_mod! {
     use mod.prelude.widgets.*
@@ -15,36 +25,224 @@ script_mod! {
         width: Fit,
         height: 35,
         margin: 0
-        enabled: false
 
         draw_bg +: {
-            color: (COLOR_BG_DISABLED)
-            // color: (COLOR_ROBRIX_PURPLE) // or `color: (COLOR_ACTIVE_PRIMARY)`
-            // color_hover: (COLOR_PRIMARY_DARKER) // make it whiter (this value is mixed in with `color`)
+            color: (COLOR_PRIMARY)
+            color_hover: (COLOR_PRIMARY_DARKER)
             border_radius: 4.0
             border_color: (COLOR_SECONDARY)
             border_size: 1.0
         }
         draw_icon +: {
             svg: (ICON_SEARCH)
-            color: (COLOR_FG_DISABLED)
-            // color: (COLOR_PRIMARY),
-            // color_hover: (COLOR_PRIMARY),
+            color: (COLOR_TEXT)
         }
         icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -1, right: -2} }
 
-        // text: "Search Messages"
-        text: "Search (TODO)"
+        text: "Search Messages"
         draw_text +: {
-            color: (COLOR_FG_DISABLED)
-            // color: (COLOR_PRIMARY),
-            // color_hover: (COLOR_PRIMARY),
+            color: (COLOR_TEXT)
+        }
+    }
+
+    // A single clickable entry in a list of messages from various rooms,
+    // e.g., message search results or saved messages.
+    mod.widgets.SearchResultEntry = View {
+        width: Fill
+        height: Fit
+        flow: Overlay
+        margin: Inset{bottom: 6}
+
+        RoundedView {
+            width: Fill
+            height: Fit
+            flow: Down
+            spacing: 4
+            padding: Inset{top: 10, right: 12, bottom: 10, left: 12}
+
+            show_bg: true
+            draw_bg +: {
+                color: #F8FAFD
+                border_radius: 4.0
+                border_size: 1.0
+                border_color: #D8E0EA
+            }
+
+            title_row := View {
+                width: Fill
+                height: Fit
+                flow: Right
+                spacing: 8
+
+                room_name := Label {
+                    width: Fill
+                    height: Fit
+                    draw_text +: {
+                        text_style: USERNAME_TEXT_STYLE { font_size: 10 }
+                        color: #1F1F1F
+                    }
+                    text: ""
+                }
+
+                time := Label {
+                    width: Fit
+                    height: Fit
+                    draw_text +: {
+                        text_style: TIMESTAMP_TEXT_STYLE { font_size: 7.5 }
+                        color: (TIMESTAMP_TEXT_COLOR)
+                    }
+                    text: ""
+                }
+            }
+
+            preview := Label {
+                width: Fill
+                height: Fit
+                flow: Flow.Right{wrap: true}
+                draw_text +: {
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 10.0 }
+                    color: (COLOR_TEXT)
+                }
+                text: ""
+            }
+        }
+
+        click_button := RobrixNeutralIconButton {
+            width: Fill
+            height: Fill
+            text: ""
+            icon_walk: Walk{width: 0, height: 0}
+            draw_bg +: {
+                color: #0000
+                color_hover: #0000000A
+                color_down: #00000014
+                border_size: 0.0
+            }
         }
     }
 
-    
+    mod.widgets.SearchMessagesModal = #(SearchMessagesModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 480
+            height: Fit
+            flow: Down
+            spacing: 8
+            padding: Inset{top: 20, right: 25, bottom: 20, left: 25}
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 4.0
+            }
+
+            title_view := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{y: 0.5}
+
+                title := Label {
+                    width: Fill, height: Fit,
+                    draw_text +: {
+                        text_style: TITLE_TEXT {font_size: 13},
+                        color: #000
+                    }
+                    text: "Search Messages"
+                }
+
+                close_button := RobrixNeutralIconButton {
+                    width: Fit,
+                    height: Fit,
+                    spacing: 0,
+                    padding: 12,
+                    draw_icon.svg: (ICON_CLOSE)
+                    icon_walk: Walk{width: 14, height: 14}
+                    text: ""
+                }
+            }
+
+            scope_view := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{y: 0.5}
+                spacing: 8
+
+                scope_label := Label {
+                    width: Fill, height: Fit,
+                    flow: Flow.Right{wrap: true},
+                    draw_text +: {
+                        text_style: REGULAR_TEXT {font_size: 10},
+                        color: #6D7682
+                    }
+                    text: ""
+                }
+
+                scope_button := RobrixNeutralIconButton {
+                    visible: false
+                    width: Fit,
+                    padding: Inset{left: 10, right: 10, top: 6, bottom: 6}
+                    text: ""
+                }
+            }
+
+            input_row := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{y: 0.5}
+                spacing: 8
+
+                search_input := RobrixTextInput {
+                    width: Fill
+                    draw_text +: {
+                        text_style: REGULAR_TEXT {font_size: 11},
+                        color: #000
+                    }
+                    empty_text: "",
+                }
+
+                search_button := RobrixIconButton {
+                    width: Fit,
+                    padding: Inset{left: 12, right: 12, top: 10, bottom: 10}
+                    draw_icon.svg: (ICON_SEARCH)
+                    icon_walk: Walk{width: 14, height: 14, margin: Inset{right: -2}}
+                    text: ""
+                }
+            }
+
+            status_label := Label {
+                width: Fill,
+                height: Fit,
+                flow: Flow.Right{wrap: true},
+                margin: Inset{left: 1}
+                draw_text +: {
+                    text_style: REGULAR_TEXT {font_size: 9.5}
+                    color: #6D7682
+                }
+                text: ""
+            }
+
+            results_list := PortalList {
+                width: Fill
+                height: 360
+                flow: Down
+                max_pull_down: 0.0
+
+                SearchResult := SearchResultEntry {}
+            }
+
+            load_more_button := RobrixNeutralIconButton {
+                visible: false
+                width: Fill,
+                padding: 10,
+                text: ""
+            }
+        }
+    }
 }
 
+
 #[derive(Script, ScriptHook, Widget)]
 pub struct SearchMessagesButton {
     #[deref] button: Button,
@@ -63,8 +261,7 @@ impl Widget for SearchMessagesButton {
 
         if let Event::Actions(actions) = event {
             if self.button.clicked(actions) {
-
-                // cx.action(AddRoomAction::SearchMessagesButtonClicked);
+                cx.action(SearchMessagesModalAction::Open { room_name_id: None });
             }
         }
     }
@@ -82,11 +279,360 @@ impl Widget for SearchMessagesButton {
 impl SearchMessagesButton {
     fn set_app_language(&mut self, cx: &mut Cx, app_language: AppLanguage) {
         self.app_language = app_language;
-        self.button.set_text(cx, tr_key(self.app_language, "search_messages.button.todo"));
+        self.button.set_text(cx, tr_key(self.app_language, "search_messages.button.text"));
+    }
+}
+
+
+/// Actions emitted by other widgets to show or hide the `SearchMessagesModal`.
+#[derive(Clone, Debug)]
+pub enum SearchMessagesModalAction {
+    /// Open the modal to search for messages.
+    ///
+    /// If `room_name_id` is `Some`, the search is initially limited to that room,
+    /// otherwise all joined rooms are searched.
+    Open {
+        room_name_id: Option<RoomNameId>,
+    },
+    /// Close the modal.
+    Close,
+}
+
+/// The state of the current search in a `SearchMessagesModal`.
+#[derive(Clone, Debug, Default)]
+enum SearchState {
+    /// No search has been submitted yet.
+    #[default]
+    Idle,
+    /// Waiting for a page of search results from the homeserver.
+    Searching,
+    /// The latest search request completed successfully.
+    Loaded,
+    /// The latest search request failed with the given error.
+    Failed(String),
+}
+
+/// A modal that searches for messages using the homeserver's `/search` API
+/// and displays the results.
+///
+/// Clicking a result opens that result's room (or thread)
+/// and jumps to the matching message in its timeline.
+#[derive(Script, ScriptHook, Widget)]
+pub struct SearchMessagesModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    /// The room that this modal was opened from, if any.
+    #[rust] room_name_id: Option<RoomNameId>,
+    /// Whether to search all joined rooms instead of only `room_name_id`.
+    #[rust] search_all_rooms: bool,
+    /// The search term of the most recently-submitted search.
+    #[rust] search_term: String,
+    #[rust] state: SearchState,
+    #[rust] results: Vec<MessageSearchResult>,
+    /// The server's estimated total number of results for the current search.
+    #[rust] count: Option<u64>,
+    /// The token used to fetch the next page of results, if there are more.
+    #[rust] next_batch: Option<String>,
+}
+
+impl Widget for SearchMessagesModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.button(cx, ids!(load_more_button)).set_visible(
+            cx,
+            self.next_batch.is_some() && matches!(self.state, SearchState::Loaded),
+        );
+        let status_text = self.status_text();
+        self.view.label(cx, ids!(status_label)).set_text(cx, &status_text);
+
+        while let Some(widget) = self.view.draw_walk(cx, scope, walk).step() {
+            let portal_list_ref = widget.as_portal_list();
+            let Some(mut list) = portal_list_ref.borrow_mut() else { continue };
+
+            list.set_item_range(cx, 0, self.results.len());
+            while let Some(item_id) = list.next_visible_item(cx) {
+                let Some(result) = self.results.get(item_id) else { continue };
+                let item = list.item(cx, item_id, id!(SearchResult));
+                item.label(cx, ids!(room_name)).set_text(cx, &result.room_name_id.to_string());
+                item.label(cx, ids!(time)).set_text(
+                    cx,
+                    &utils::relative_format(result.timestamp).unwrap_or_default(),
+                );
+                item.label(cx, ids!(preview)).set_text(cx, &result.preview);
+                item.draw_all(cx, &mut Scope::empty());
+            }
+        }
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for SearchMessagesModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let close_clicked = self.view.button(cx, ids!(close_button)).clicked(actions);
+        if close_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // a `SearchMessagesModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if close_clicked {
+                cx.action(SearchMessagesModalAction::Close);
+            }
+            return;
+        }
+
+        // Handle a new search being submitted.
+        let search_input = self.view.text_input(cx, ids!(search_input));
+        if let Some(search_term) = self.view.button(cx, ids!(search_button)).clicked(actions)
+            .then(|| search_input.text())
+            .or_else(|| search_input.returned(actions).map(|(text, _)| text))
+        {
+            self.start_search(cx, search_term.trim().to_owned());
+        }
+
+        // Handle the search scope being toggled between this room and all rooms.
+        if self.view.button(cx, ids!(scope_button)).clicked(actions) {
+            self.search_all_rooms = !self.search_all_rooms;
+            self.update_static_texts(cx);
+            let search_term = self.search_term.clone();
+            self.start_search(cx, search_term);
+        }
+
+        if self.view.button(cx, ids!(load_more_button)).clicked(actions) {
+            self.request_next_page(cx);
+        }
+
+        // Handle a search result being clicked.
+        let results_list = self.view.portal_list(cx, ids!(results_list));
+        for (index, item) in results_list.items_with_actions(actions) {
+            if item.button(cx, ids!(click_button)).clicked(actions)
+                && let Some(result) = self.results.get(index).cloned()
+            {
+                open_search_result(cx, self.widget_uid(), result);
+                cx.action(SearchMessagesModalAction::Close);
+                return;
+            }
+        }
+
+        for action in actions {
+            match action.downcast_ref() {
+                Some(SearchMessagesAction::Loaded { room_id, search_term, from, results, count, next_batch }) => {
+                    if !self.is_current_search(room_id.as_ref(), search_term, from.as_ref()) {
+                        continue;
+                    }
+                    if from.is_none() {
+                        self.results.clear();
+                    }
+                    self.results.extend(results.iter().cloned());
+                    self.count = *count;
+                    self.next_batch = next_batch.clone();
+                    self.state = SearchState::Loaded;
+                    self.redraw(cx);
+                }
+                Some(SearchMessagesAction::Failed { room_id, search_term, from, error }) => {
+                    if !self.is_current_search(room_id.as_ref(), search_term, from.as_ref()) {
+                        continue;
+                    }
+                    self.state = SearchState::Failed(error.clone());
+                    self.redraw(cx);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+impl SearchMessagesModal {
+    /// Shows the modal, resetting it to search within the given room (if any).
+    pub fn show(&mut self, cx: &mut Cx, room_name_id: Option<RoomNameId>, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.search_all_rooms = room_name_id.is_none();
+        self.room_name_id = room_name_id;
+        self.search_term.clear();
+        self.state = SearchState::Idle;
+        self.results.clear();
+        self.count = None;
+        self.next_batch = None;
+        self.update_static_texts(cx);
+
+        let search_input = self.view.text_input(cx, ids!(search_input));
+        search_input.set_text(cx, "");
+        search_input.set_key_focus(cx);
+        self.view.button(cx, ids!(close_button)).reset_hover(cx);
+        self.view.button(cx, ids!(search_button)).reset_hover(cx);
+        self.view.button(cx, ids!(scope_button)).reset_hover(cx);
+        self.view.button(cx, ids!(load_more_button)).reset_hover(cx);
+        self.view.redraw(cx);
+    }
+
+    /// Returns the room to be searched, or `None` if all joined rooms should be searched.
+    fn room_id_to_search(&self) -> Option<&OwnedRoomId> {
+        if self.search_all_rooms {
+            None
+        } else {
+            self.room_name_id.as_ref().map(|rn| rn.room_id())
+        }
+    }
+
+    /// Returns whether the given search response belongs to the current search,
+    /// such that responses to outdated searches can be ignored.
+    fn is_current_search(
+        &self,
+        room_id: Option<&OwnedRoomId>,
+        search_term: &str,
+        from: Option<&String>,
+    ) -> bool {
+        matches!(self.state, SearchState::Searching)
+            && room_id == self.room_id_to_search()
+            && search_term == self.search_term
+            && (from.is_none() || from == self.next_batch.as_ref())
+    }
+
+    /// Starts a new search for the given `search_term`, discarding all prior results.
+    fn start_search(&mut self, cx: &mut Cx, search_term: String) {
+        self.search_term = search_term;
+        self.results.clear();
+        self.count = None;
+        self.next_batch = None;
+        if self.search_term.is_empty() {
+            self.state = SearchState::Idle;
+        } else {
+            self.state = SearchState::Searching;
+            submit_async_request(MatrixRequest::SearchMessages {
+                room_id: self.room_id_to_search().cloned(),
+                search_term: self.search_term.clone(),
+                next_batch: None,
+            });
+        }
+        self.view.portal_list(cx, ids!(results_list)).set_first_id_and_scroll(0, 0.0);
+        self.redraw(cx);
+    }
+
+    /// Requests the next page of results for the current search, if there is one.
+    fn request_next_page(&mut self, cx: &mut Cx) {
+        if matches!(self.state, SearchState::Searching) { return }
+        let Some(next_batch) = self.next_batch.clone() else { return };
+        self.state = SearchState::Searching;
+        submit_async_request(MatrixRequest::SearchMessages {
+            room_id: self.room_id_to_search().cloned(),
+            search_term: self.search_term.clone(),
+            next_batch: Some(next_batch),
+        });
+        self.redraw(cx);
+    }
+
+    fn status_text(&self) -> String {
+        match &self.state {
+            SearchState::Idle => tr_key(self.app_language, "search_messages.modal.status.prompt").to_string(),
+            SearchState::Searching => tr_key(self.app_language, "search_messages.modal.status.searching").to_string(),
+            SearchState::Loaded if self.results.is_empty() => tr_fmt(
+                self.app_language,
+                "search_messages.modal.status.no_results",
+                &[("term", &self.search_term)],
+            ),
+            SearchState::Loaded => {
+                let count = self.count.unwrap_or(self.results.len() as u64).to_string();
+                tr_fmt(
+                    self.app_language,
+                    "search_messages.modal.status.count",
+                    &[("count", &count), ("term", &self.search_term)],
+                )
+            }
+            SearchState::Failed(error) => tr_fmt(
+                self.app_language,
+                "search_messages.modal.status.failed",
+                &[("error", error)],
+            ),
+        }
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(title))
+            .set_text(cx, tr_key(self.app_language, "search_messages.modal.title"));
+        self.view.text_input(cx, ids!(search_input))
+            .set_empty_text(cx, tr_key(self.app_language, "search_messages.modal.input.placeholder").to_string());
+        self.view.button(cx, ids!(search_button))
+            .set_text(cx, tr_key(self.app_language, "search_messages.modal.button.search"));
+        self.view.button(cx, ids!(load_more_button))
+            .set_text(cx, tr_key(self.app_language, "search_messages.modal.button.load_more"));
+
+        let scope_text = match self.room_name_id.as_ref() {
+            Some(room_name_id) if !self.search_all_rooms => tr_fmt(
+                self.app_language,
+                "search_messages.modal.scope.room",
+                &[("room", &room_name_id.to_string())],
+            ),
+            _ => tr_key(self.app_language, "search_messages.modal.scope.all_rooms").to_string(),
+        };
+        self.view.label(cx, ids!(scope_label)).set_text(cx, &scope_text);
+
+        // The scope can only be toggled if this modal was opened from within a room.
+        let scope_button = self.view.button(cx, ids!(scope_button));
+        scope_button.set_visible(cx, self.room_name_id.is_some());
+        scope_button.set_text(cx, tr_key(
+            self.app_language,
+            if self.search_all_rooms {
+                "search_messages.modal.button.search_this_room"
+            } else {
+                "search_messages.modal.button.search_all_rooms"
+            },
+        ));
     }
 }
 
-#[derive(Debug)]
-pub enum AddRoomAction {
-    SearchMessagesButtonClicked,
+impl SearchMessagesModalRef {
+    /// See [`SearchMessagesModal::show()`].
+    pub fn show(&self, cx: &mut Cx, room_name_id: Option<RoomNameId>, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, room_name_id, app_language);
+    }
+}
+
+/// Navigates to the room (or thread) of the given search result
+/// and jumps to the result's event within that timeline.
+fn open_search_result(cx: &mut Cx, widget_uid: WidgetUid, result: MessageSearchResult) {
+    open_event_in_room(cx, widget_uid, result.room_name_id, result.event_id, result.thread_root_event_id);
+}
+
+/// Navigates to the given room (or to the given thread within that room)
+/// and jumps to the given event within that timeline.
+pub fn open_event_in_room(
+    cx: &mut Cx,
+    widget_uid: WidgetUid,
+    room_name_id: RoomNameId,
+    event_id: OwnedEventId,
+    thread_root_event_id: Option<OwnedEventId>,
+) {
+    let room_id = room_name_id.room_id().clone();
+    let timeline_kind = match thread_root_event_id.clone() {
+        Some(thread_root_event_id) => TimelineKind::Thread { room_id, thread_root_event_id },
+        None => TimelineKind::MainRoom { room_id },
+    };
+    // This must be requested *before* navigating to the timeline,
+    // such that the RoomScreen can jump to the event as soon as it shows the timeline.
+    request_jump_to_event(cx, timeline_kind, event_id);
+
+    match thread_root_event_id {
+        Some(thread_root_event_id) => cx.widget_action(
+            widget_uid,
+            RoomsListAction::Selected(SelectedRoom::Thread {
+                room_name_id,
+                thread_root_event_id,
+            }),
+        ),
+        None => cx.action(AppStateAction::NavigateToRoom {
+            room_to_close: None,
+            destination_room: BasicRoomDetails::Name(room_name_id),
+        }),
+    }
 }
@@ -0,0 +1,516 @@
+//! UI widgets for viewing the outgoing messages that haven't yet been sent,
+//! and for retrying, editing, or discarding them.
+//!
+//! The unsent messages themselves are tracked by the [`unsent_messages`](crate::unsent_messages) module.
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::{OwnedRoomId, OwnedTransactionId};
+use crate::{
+    app::AppState,
+    home::rooms_list::RoomsListRef,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    sliding_sync::{MatrixRequest, submit_async_request},
+    unsent_messages::{self, UnsentMessage, UnsentMessagesAction},
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+
+    mod.widgets.UnsentMessagesButton = set_type_default() do #(UnsentMessagesButton::register_widget(vm)) {
+        ..mod.widgets.RoundedView
+
+        width: Fit,
+        height: 35,
+        margin: 0
+
+        draw_bg +: {
+            color: (COLOR_BG_DANGER_RED)
+            color_hover: (COLOR_BG_DANGER_RED)
+            border_radius: 4.0
+            border_color: (COLOR_FG_DANGER_RED)
+            border_size: 1.0
+        }
+        draw_icon +: {
+            svg: (ICON_WARNING)
+            color: (COLOR_FG_DANGER_RED)
+        }
+        icon_walk: Walk{width: 16, height: 16, margin: Inset{left: -1, right: -2} }
+
+        text: "Unsent"
+        draw_text +: {
+            color: (COLOR_FG_DANGER_RED)
+        }
+    }
+
+    mod.widgets.UnsentMessageEntry = View {
+        width: Fill, height: Fit
+        flow: Down
+        spacing: 4
+        padding: Inset{top: 8, bottom: 8, left: 4, right: 4}
+
+        View {
+            width: Fill, height: Fit
+            flow: Right
+            spacing: 8
+            align: Align{y: 0.5}
+
+            room_name := Label {
+                width: Fill, height: Fit
+                flow: Flow.Right{wrap: true}
+                draw_text +: {
+                    text_style: TITLE_TEXT {font_size: 10}
+                    color: #000
+                }
+                text: ""
+            }
+
+            status := Label {
+                width: Fit, height: Fit
+                draw_text +: {
+                    text_style: REGULAR_TEXT {font_size: 9}
+                    color: #6D7682
+                }
+                text: ""
+            }
+        }
+
+        preview := Label {
+            width: Fill, height: Fit
+            flow: Flow.Right{wrap: true}
+            draw_text +: {
+                text_style: REGULAR_TEXT {font_size: 10.5}
+                color: #000
+            }
+            text: ""
+        }
+
+        error := Label {
+            width: Fill, height: Fit
+            flow: Flow.Right{wrap: true}
+            draw_text +: {
+                text_style: REGULAR_TEXT {font_size: 9}
+                color: (COLOR_FG_DANGER_RED)
+            }
+            text: ""
+        }
+
+        buttons := View {
+            width: Fill, height: Fit
+            flow: Right
+            spacing: 8
+            align: Align{x: 1.0, y: 0.5}
+
+            retry_button := RobrixPositiveIconButton {
+                padding: Inset{top: 5, bottom: 5, left: 10, right: 10}
+                draw_icon.svg: (ICON_ROTATE_CW)
+                icon_walk: Walk{width: 12, height: 12}
+                text: "Retry"
+            }
+
+            edit_button := RobrixNeutralIconButton {
+                padding: Inset{top: 5, bottom: 5, left: 10, right: 10}
+                draw_icon.svg: (ICON_EDIT)
+                icon_walk: Walk{width: 12, height: 12}
+                text: "Edit"
+            }
+
+            discard_button := RobrixNegativeIconButton {
+                padding: Inset{top: 5, bottom: 5, left: 10, right: 10}
+                draw_icon.svg: (ICON_TRASH)
+                icon_walk: Walk{width: 12, height: 12}
+                text: "Discard"
+            }
+        }
+
+        LineH { margin: Inset{top: 4} }
+    }
+
+    mod.widgets.UnsentMessagesModal = #(UnsentMessagesModal::register_widget(vm)) {
+        width: Fit
+        height: Fit
+
+        RoundedView {
+            width: 480
+            height: Fit
+            flow: Down
+            spacing: 8
+            padding: Inset{top: 20, right: 25, bottom: 20, left: 25}
+
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_PRIMARY)
+                border_radius: 4.0
+            }
+
+            title_view := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{y: 0.5}
+
+                title := Label {
+                    width: Fill, height: Fit,
+                    draw_text +: {
+                        text_style: TITLE_TEXT {font_size: 13},
+                        color: #000
+                    }
+                    text: "Unsent Messages"
+                }
+
+                close_button := RobrixNeutralIconButton {
+                    width: Fit,
+                    height: Fit,
+                    spacing: 0,
+                    padding: 12,
+                    draw_icon.svg: (ICON_CLOSE)
+                    icon_walk: Walk{width: 14, height: 14}
+                    text: ""
+                }
+            }
+
+            status_view := View {
+                width: Fill, height: Fit
+                flow: Right
+                spacing: 8
+                align: Align{y: 0.5}
+
+                status_label := Label {
+                    width: Fill,
+                    height: Fit,
+                    flow: Flow.Right{wrap: true},
+                    margin: Inset{left: 1}
+                    draw_text +: {
+                        text_style: REGULAR_TEXT {font_size: 9.5}
+                        color: #6D7682
+                    }
+                    text: ""
+                }
+
+                retry_all_button := RobrixPositiveIconButton {
+                    padding: Inset{top: 6, bottom: 6, left: 12, right: 12}
+                    draw_icon.svg: (ICON_ROTATE_CW)
+                    icon_walk: Walk{width: 12, height: 12}
+                    text: "Retry all"
+                }
+            }
+
+            edit_view := View {
+                visible: false
+                width: Fill, height: Fit
+                flow: Down
+                spacing: 8
+
+                edit_input := RobrixTextInput {
+                    width: Fill
+                    height: Fit
+                    padding: 10
+                    draw_text +: {
+                        text_style: REGULAR_TEXT { font_size: 11.5 }
+                        color: #000
+                    }
+                    empty_text: "Message"
+                }
+
+                View {
+                    width: Fill, height: Fit
+                    flow: Right
+                    spacing: 8
+                    align: Align{x: 1.0, y: 0.5}
+
+                    cancel_edit_button := RobrixNeutralIconButton {
+                        padding: Inset{top: 6, bottom: 6, left: 12, right: 12}
+                        text: "Cancel"
+                    }
+
+                    save_edit_button := RobrixPositiveIconButton {
+                        padding: Inset{top: 6, bottom: 6, left: 12, right: 12}
+                        draw_icon.svg: (ICON_ROTATE_CW)
+                        icon_walk: Walk{width: 12, height: 12}
+                        text: "Save and retry"
+                    }
+                }
+            }
+
+            unsent_list := PortalList {
+                width: Fill
+                height: 360
+                flow: Down
+                max_pull_down: 0.0
+
+                UnsentMessage := mod.widgets.UnsentMessageEntry {}
+            }
+        }
+    }
+}
+
+
+/// A button that opens the `UnsentMessagesModal`,
+/// which is only shown when at least one message has failed to send.
+#[derive(Script, ScriptHook, Widget)]
+pub struct UnsentMessagesButton {
+    #[deref] button: Button,
+    #[rust] app_language: AppLanguage,
+    /// The number of failed messages that this button's text currently shows.
+    #[rust] num_failed: usize,
+}
+
+impl Widget for UnsentMessagesButton {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        let app_language = scope.data.get::<AppState>()
+            .map(|app_state| app_state.app_language)
+            .unwrap_or_default();
+        if self.app_language != app_language {
+            self.app_language = app_language;
+            self.update_text(cx);
+        }
+        self.button.handle_event(cx, event, scope);
+
+        if let Event::Actions(actions) = event {
+            if self.button.clicked(actions) {
+                cx.action(UnsentMessagesModalAction::Open { room_id: None });
+            }
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let num_failed = unsent_messages::num_failed_messages(None);
+        if num_failed == 0 {
+            return DrawStep::done();
+        }
+        if self.num_failed != num_failed {
+            self.num_failed = num_failed;
+            self.update_text(cx);
+        }
+        self.button.draw_walk(cx, scope, walk)
+    }
+}
+
+impl UnsentMessagesButton {
+    fn update_text(&mut self, cx: &mut Cx) {
+        self.button.set_text(
+            cx,
+            &tr_fmt(self.app_language, "unsent_messages.button.text", &[("count", &self.num_failed.to_string())]),
+        );
+    }
+}
+
+
+/// Actions emitted by other widgets to show or hide the `UnsentMessagesModal`.
+#[derive(Clone, Debug)]
+pub enum UnsentMessagesModalAction {
+    /// Open the modal to show the unsent messages in the given room,
+    /// or in all rooms if `room_id` is `None`.
+    Open { room_id: Option<OwnedRoomId> },
+    /// Close the modal.
+    Close,
+}
+
+/// A modal that lists the messages waiting in the send queue,
+/// from which failed messages can be retried, edited, or discarded.
+#[derive(Script, ScriptHook, Widget)]
+pub struct UnsentMessagesModal {
+    #[deref] view: View,
+    #[rust] app_language: AppLanguage,
+    /// The room whose unsent messages are shown, or `None` to show those of all rooms.
+    #[rust] room_id: Option<OwnedRoomId>,
+    #[rust] messages: Vec<UnsentMessage>,
+    /// The unsent message currently being edited, if any.
+    #[rust] editing: Option<(OwnedRoomId, OwnedTransactionId)>,
+}
+
+impl Widget for UnsentMessagesModal {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Some(app_state) = scope.data.get::<AppState>()
+            && self.app_language != app_state.app_language
+        {
+            self.app_language = app_state.app_language;
+            self.update_static_texts(cx);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let status_text = self.status_text();
+        self.view.label(cx, ids!(status_label)).set_text(cx, &status_text);
+        self.view.button(cx, ids!(retry_all_button))
+            .set_visible(cx, self.messages.iter().any(|msg| msg.error.is_some()));
+        self.view.view(cx, ids!(edit_view)).set_visible(cx, self.editing.is_some());
+
+        let rooms_list = cx.get_global::<RoomsListRef>().clone();
+        while let Some(widget) = self.view.draw_walk(cx, scope, walk).step() {
+            let portal_list_ref = widget.as_portal_list();
+            let Some(mut list) = portal_list_ref.borrow_mut() else { continue };
+
+            list.set_item_range(cx, 0, self.messages.len());
+            while let Some(item_id) = list.next_visible_item(cx) {
+                let Some(message) = self.messages.get(item_id) else { continue };
+                let item = list.item(cx, item_id, id!(UnsentMessage));
+                let room_name = rooms_list.get_room_name(&message.room_id)
+                    .map_or_else(|| message.room_id.to_string(), |name| name.to_string());
+                item.label(cx, ids!(room_name)).set_text(cx, &room_name);
+                item.label(cx, ids!(status)).set_text(
+                    cx,
+                    tr_key(
+                        self.app_language,
+                        if message.error.is_some() { "unsent_messages.modal.item.failed" } else { "unsent_messages.modal.item.sending" },
+                    ),
+                );
+                item.label(cx, ids!(preview)).set_text(cx, &message.preview);
+                let error_label = item.label(cx, ids!(error));
+                error_label.set_visible(cx, message.error.is_some());
+                error_label.set_text(cx, message.error.as_deref().unwrap_or_default());
+                item.button(cx, ids!(retry_button)).set_visible(cx, message.error.is_some());
+                item.button(cx, ids!(edit_button))
+                    .set_visible(cx, message.error.is_some() && message.editable_content.is_some());
+                item.button(cx, ids!(retry_button))
+                    .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.item.retry"));
+                item.button(cx, ids!(edit_button))
+                    .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.item.edit"));
+                item.button(cx, ids!(discard_button))
+                    .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.item.discard"));
+                item.draw_all(cx, &mut Scope::empty());
+            }
+        }
+        DrawStep::done()
+    }
+}
+
+impl WidgetMatchEvent for UnsentMessagesModal {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        let close_clicked = self.view.button(cx, ids!(close_button)).clicked(actions);
+        if close_clicked ||
+            actions.iter().any(|a| matches!(a.downcast_ref(), Some(ModalAction::Dismissed)))
+        {
+            self.editing = None;
+            // If the modal was dismissed by clicking outside of it, we MUST NOT emit
+            // an `UnsentMessagesModalAction::Close` action, as that would cause
+            // an infinite action feedback loop.
+            if close_clicked {
+                cx.action(UnsentMessagesModalAction::Close);
+            }
+            return;
+        }
+
+        if self.view.button(cx, ids!(retry_all_button)).clicked(actions) {
+            match &self.room_id {
+                // Only retry the messages in the room that this modal is showing.
+                Some(_) => {
+                    for message in self.messages.iter().filter(|msg| msg.error.is_some()) {
+                        submit_async_request(MatrixRequest::RetryUnsentMessage {
+                            room_id: message.room_id.clone(),
+                            transaction_id: message.transaction_id.clone(),
+                        });
+                    }
+                }
+                None => submit_async_request(MatrixRequest::RetryAllUnsentMessages),
+            }
+        }
+
+        // Handle the buttons of the edit view.
+        if self.view.button(cx, ids!(cancel_edit_button)).clicked(actions) {
+            self.editing = None;
+            self.redraw(cx);
+        }
+        let edit_input = self.view.text_input(cx, ids!(edit_input));
+        let save_clicked = self.view.button(cx, ids!(save_edit_button)).clicked(actions);
+        if (save_clicked || edit_input.returned(actions).is_some())
+            && let Some((room_id, transaction_id)) = self.editing.take()
+        {
+            let new_text = edit_input.text().trim().to_string();
+            if !new_text.is_empty() {
+                submit_async_request(MatrixRequest::EditUnsentMessage { room_id, transaction_id, new_text });
+            }
+            self.redraw(cx);
+        }
+
+        // Handle the buttons of each unsent message.
+        let unsent_list = self.view.portal_list(cx, ids!(unsent_list));
+        for (index, item) in unsent_list.items_with_actions(actions) {
+            let Some(message) = self.messages.get(index) else { continue };
+            if item.button(cx, ids!(retry_button)).clicked(actions) {
+                submit_async_request(MatrixRequest::RetryUnsentMessage {
+                    room_id: message.room_id.clone(),
+                    transaction_id: message.transaction_id.clone(),
+                });
+            }
+            if item.button(cx, ids!(edit_button)).clicked(actions) {
+                edit_input.set_text(cx, message.editable_text().unwrap_or_default());
+                edit_input.set_key_focus(cx);
+                self.editing = Some((message.room_id.clone(), message.transaction_id.clone()));
+                self.redraw(cx);
+            }
+            if item.button(cx, ids!(discard_button)).clicked(actions) {
+                submit_async_request(MatrixRequest::DiscardUnsentMessage {
+                    room_id: message.room_id.clone(),
+                    transaction_id: message.transaction_id.clone(),
+                });
+            }
+        }
+
+        if actions.iter().any(|a| matches!(a.downcast_ref(), Some(UnsentMessagesAction::Updated))) {
+            self.refresh(cx);
+        }
+    }
+}
+
+impl UnsentMessagesModal {
+    /// Shows the modal with the unsent messages in the given room,
+    /// or in all rooms if `room_id` is `None`.
+    pub fn show(&mut self, cx: &mut Cx, app_language: AppLanguage, room_id: Option<OwnedRoomId>) {
+        self.app_language = app_language;
+        self.room_id = room_id;
+        self.editing = None;
+        self.update_static_texts(cx);
+        self.refresh(cx);
+
+        self.view.button(cx, ids!(close_button)).reset_hover(cx);
+        self.view.portal_list(cx, ids!(unsent_list)).set_first_id_and_scroll(0, 0.0);
+    }
+
+    /// Reloads the list of unsent messages shown in this modal.
+    fn refresh(&mut self, cx: &mut Cx) {
+        self.messages = unsent_messages::unsent_messages(self.room_id.as_deref());
+        // Stop editing a message that has since been sent or discarded.
+        if let Some((room_id, transaction_id)) = &self.editing
+            && !self.messages.iter().any(|msg| &msg.room_id == room_id && &msg.transaction_id == transaction_id)
+        {
+            self.editing = None;
+        }
+        self.view.redraw(cx);
+    }
+
+    fn status_text(&self) -> String {
+        if self.messages.is_empty() {
+            tr_key(self.app_language, "unsent_messages.modal.status.empty").to_string()
+        } else {
+            tr_fmt(
+                self.app_language,
+                "unsent_messages.modal.status.count",
+                &[("count", &self.messages.len().to_string())],
+            )
+        }
+    }
+
+    fn update_static_texts(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(title))
+            .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.title"));
+        self.view.button(cx, ids!(retry_all_button))
+            .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.retry_all"));
+        self.view.button(cx, ids!(cancel_edit_button))
+            .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.edit.cancel"));
+        self.view.button(cx, ids!(save_edit_button))
+            .set_text(cx, tr_key(self.app_language, "unsent_messages.modal.edit.save"));
+        self.view.text_input(cx, ids!(edit_input))
+            .set_empty_text(cx, tr_key(self.app_language, "unsent_messages.modal.edit.placeholder").to_string());
+    }
+}
+
+impl UnsentMessagesModalRef {
+    /// See [`UnsentMessagesModal::show()`].
+    pub fn show(&self, cx: &mut Cx, app_language: AppLanguage, room_id: Option<OwnedRoomId>) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.show(cx, app_language, room_id);
+    }
+}
@@ -2,6 +2,7 @@
 //! and cancel/retry buttons.
 
 use makepad_widgets::*;
+use matrix_sdk::room::reply::Reply;
 use tokio::task::AbortHandle;
 
 use crate::shared::file_upload_modal::FileData;
@@ -105,6 +106,8 @@ pub enum UploadViewState {
     Error {
         message: String,
         file_data: FileData,
+        /// The reply metadata of the failed upload, which is re-used when retrying it.
+        replied_to: Option<Reply>,
     },
 }
 
@@ -116,8 +119,12 @@ pub enum UploadProgressViewAction {
     None,
     /// User cancelled the upload.
     Cancelled,
-    /// User requested retry of a failed upload.
-    Retry(FileData),
+    /// User requested retry of a failed upload,
+    /// which should be re-sent with the same reply metadata.
+    Retry {
+        file_data: FileData,
+        replied_to: Option<Reply>,
+    },
 }
 
 /// A widget showing upload progress with cancel/retry functionality.
@@ -148,9 +155,11 @@ impl Widget for UploadProgressView {
 
             // Handle retry button
             if self.button(cx, ids!(retry_button)).clicked(actions) {
-                if let UploadViewState::Error { file_data, .. } = &self.state {
-                    let file_data = file_data.clone();
-                    cx.widget_action(self.widget_uid(), UploadProgressViewAction::Retry(file_data));
+                if let UploadViewState::Error { file_data, replied_to, .. } = &self.state {
+                    cx.widget_action(self.widget_uid(), UploadProgressViewAction::Retry {
+                        file_data: file_data.clone(),
+                        replied_to: replied_to.clone(),
+                    });
                     self.hide(cx);
                 }
             }
@@ -220,10 +229,11 @@ impl UploadProgressView {
     }
 
     /// Shows an error state with the given message.
-    pub fn show_error(&mut self, cx: &mut Cx, error: &str, file_data: FileData) {
+    pub fn show_error(&mut self, cx: &mut Cx, error: &str, file_data: FileData, replied_to: Option<Reply>) {
         self.state = UploadViewState::Error {
             message: error.to_string(),
             file_data,
+            replied_to,
         };
 
         // Update UI for error state
@@ -269,9 +279,9 @@ impl UploadProgressViewRef {
     }
 
     /// Shows an error state with the given message.
-    pub fn show_error(&self, cx: &mut Cx, error: &str, file_data: FileData) {
+    pub fn show_error(&self, cx: &mut Cx, error: &str, file_data: FileData, replied_to: Option<Reply>) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.show_error(cx, error, file_data);
+            inner.show_error(cx, error, file_data, replied_to);
         }
     }
 }
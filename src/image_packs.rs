@@ -0,0 +1,244 @@
+//! Image packs of custom emoji and stickers, as defined by MSC2545.
+//!
+//! An image pack can come from one of three places:
+//! * the current user's own pack, stored in their `im.ponies.user_emotes` account data,
+//! * a room's packs, stored in that room's `im.ponies.room_emotes` state events
+//!   (one pack per state key),
+//! * packs from other rooms that the user has enabled everywhere,
+//!   which are listed in their `im.ponies.emote_rooms` account data.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use makepad_widgets::{error, warning};
+use matrix_sdk::{
+    Client, Room,
+    deserialized_responses::RawAnySyncOrStrippedState,
+    ruma::{
+        OwnedMxcUri, OwnedRoomId,
+        events::{StateEventType, room::ImageInfo},
+        serde::Raw,
+    },
+};
+use serde::Deserialize;
+
+use crate::sliding_sync::fetch_global_account_data;
+
+/// The event type of the current user's own image pack, in their global account data.
+const USER_EMOTES_EVENT_TYPE: &str = "im.ponies.user_emotes";
+/// The event type of the state events that define a room's image packs.
+const ROOM_EMOTES_EVENT_TYPE: &str = "im.ponies.room_emotes";
+/// The event type of the global account data that lists the room packs
+/// that the current user has enabled in all rooms.
+const EMOTE_ROOMS_EVENT_TYPE: &str = "im.ponies.emote_rooms";
+
+/// What an image in an image pack can be used as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImagePackUsage {
+    /// A custom emoji, which can be used inline in messages and as a reaction.
+    Emoticon,
+    /// A sticker, which is sent as a standalone `m.sticker` event.
+    Sticker,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Where an image pack was defined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImagePackSource {
+    /// The current user's own pack.
+    User,
+    /// A pack defined by the given state key of a room's `im.ponies.room_emotes` state.
+    Room {
+        room_id: OwnedRoomId,
+        state_key: String,
+    },
+}
+
+/// A single image in an image pack.
+#[derive(Clone, Debug)]
+pub struct PackImage {
+    /// The image's shortcode, which is unique within its pack.
+    pub shortcode: String,
+    /// A textual description of the image, which defaults to its shortcode.
+    pub body: String,
+    pub url: OwnedMxcUri,
+    pub info: Option<ImageInfo>,
+    usage: Vec<ImagePackUsage>,
+}
+
+impl PackImage {
+    /// Returns true if this image can be sent as a sticker.
+    pub fn is_sticker(&self) -> bool {
+        self.usage.contains(&ImagePackUsage::Sticker)
+    }
+
+    /// Returns true if this image can be used as a custom emoji.
+    pub fn is_emoticon(&self) -> bool {
+        self.usage.contains(&ImagePackUsage::Emoticon)
+    }
+}
+
+/// An image pack, i.e., a named collection of custom emoji and/or stickers.
+#[derive(Clone, Debug)]
+pub struct ImagePack {
+    /// The displayable name of this pack, which may be empty.
+    pub display_name: String,
+    pub avatar_url: Option<OwnedMxcUri>,
+    pub source: ImagePackSource,
+    pub images: Vec<PackImage>,
+}
+
+impl ImagePack {
+    /// Returns an iterator over the images in this pack that can be sent as stickers.
+    pub fn stickers(&self) -> impl Iterator<Item = &PackImage> {
+        self.images.iter().filter(|image| image.is_sticker())
+    }
+
+    /// Returns an iterator over the images in this pack that can be used as custom emoji.
+    pub fn emoticons(&self) -> impl Iterator<Item = &PackImage> {
+        self.images.iter().filter(|image| image.is_emoticon())
+    }
+}
+
+/// Actions emitted in response to a [`crate::sliding_sync::MatrixRequest::FetchImagePacks`].
+#[derive(Clone, Debug)]
+pub enum ImagePacksAction {
+    /// The image packs available in the given room were fetched.
+    Fetched {
+        room_id: OwnedRoomId,
+        packs: Arc<Vec<ImagePack>>,
+    },
+}
+
+/// The content of an `im.ponies.user_emotes` or `im.ponies.room_emotes` event.
+#[derive(Debug, Default, Deserialize)]
+struct ImagePackEventContent {
+    #[serde(default)]
+    images: BTreeMap<String, PackImageContent>,
+    #[serde(default)]
+    pack: PackInfoContent,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackImageContent {
+    url: OwnedMxcUri,
+    body: Option<String>,
+    info: Option<ImageInfo>,
+    usage: Option<Vec<ImagePackUsage>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PackInfoContent {
+    display_name: Option<String>,
+    avatar_url: Option<OwnedMxcUri>,
+    usage: Option<Vec<ImagePackUsage>>,
+}
+
+/// The content of an `im.ponies.emote_rooms` event,
+/// which maps each room ID to the state keys of that room's enabled packs.
+#[derive(Debug, Default, Deserialize)]
+struct EmoteRoomsEventContent {
+    #[serde(default)]
+    rooms: BTreeMap<OwnedRoomId, BTreeMap<String, serde_json::Value>>,
+}
+
+impl ImagePackEventContent {
+    /// Converts this event content into an `ImagePack`,
+    /// returning `None` if the pack contains no images.
+    fn into_image_pack(self, source: ImagePackSource, default_name: Option<String>) -> Option<ImagePack> {
+        let pack_usage = self.pack.usage.filter(|usage| !usage.is_empty());
+        let images: Vec<PackImage> = self.images.into_iter()
+            .map(|(shortcode, image)| {
+                // An image without its own usage inherits its pack's usage,
+                // and if neither is given, the image can be used as both.
+                let usage = image.usage.filter(|usage| !usage.is_empty())
+                    .or_else(|| pack_usage.clone())
+                    .unwrap_or_else(|| vec![ImagePackUsage::Emoticon, ImagePackUsage::Sticker]);
+                PackImage {
+                    body: image.body.unwrap_or_else(|| shortcode.clone()),
+                    shortcode,
+                    url: image.url,
+                    info: image.info,
+                    usage,
+                }
+            })
+            .collect();
+        if images.is_empty() {
+            return None;
+        }
+        Some(ImagePack {
+            display_name: self.pack.display_name.or(default_name).unwrap_or_default(),
+            avatar_url: self.pack.avatar_url,
+            source,
+            images,
+        })
+    }
+}
+
+/// Fetches all image packs that are available to the current user in the given room.
+///
+/// The user's own pack comes first, followed by the given room's packs,
+/// followed by the packs from other rooms that the user has enabled everywhere.
+/// Packs that fail to load or contain no images are skipped.
+pub async fn fetch_image_packs(client: &Client, room: &Room) -> Vec<ImagePack> {
+    let mut packs = Vec::new();
+
+    if let Some(content) = fetch_global_account_data::<ImagePackEventContent>(client, USER_EMOTES_EVENT_TYPE).await
+        && let Some(pack) = content.into_image_pack(ImagePackSource::User, None)
+    {
+        packs.push(pack);
+    }
+
+    match room.get_state_events(StateEventType::from(ROOM_EMOTES_EVENT_TYPE)).await {
+        Ok(raw_states) => {
+            for raw_state in raw_states {
+                let RawAnySyncOrStrippedState::Sync(raw_event) = raw_state else { continue };
+                if let Some(pack) = image_pack_from_raw_state_event(room, &raw_event) {
+                    packs.push(pack);
+                }
+            }
+        }
+        Err(e) => error!("Failed to get the image packs of room {}: {e:?}", room.room_id()),
+    }
+
+    let emote_rooms = fetch_global_account_data::<EmoteRoomsEventContent>(client, EMOTE_ROOMS_EVENT_TYPE)
+        .await
+        .unwrap_or_default();
+    for (room_id, state_keys) in emote_rooms.rooms {
+        // The given room's packs were already added above.
+        if room_id == room.room_id() { continue; }
+        let Some(other_room) = client.get_room(&room_id) else {
+            warning!("Skipping the enabled image packs of unknown room {room_id}");
+            continue;
+        };
+        for state_key in state_keys.keys() {
+            match other_room.get_state_event(StateEventType::from(ROOM_EMOTES_EVENT_TYPE), state_key).await {
+                Ok(Some(RawAnySyncOrStrippedState::Sync(raw_event))) => {
+                    if let Some(pack) = image_pack_from_raw_state_event(&other_room, &raw_event) {
+                        packs.push(pack);
+                    }
+                }
+                Ok(_) => { }
+                Err(e) => error!("Failed to get image pack {state_key:?} of room {room_id}: {e:?}"),
+            }
+        }
+    }
+
+    packs
+}
+
+/// Converts a raw `im.ponies.room_emotes` state event from the given room into an `ImagePack`.
+///
+/// A pack without a name is named after its room.
+fn image_pack_from_raw_state_event<T>(room: &Room, raw_event: &Raw<T>) -> Option<ImagePack> {
+    let state_key = raw_event.get_field::<String>("state_key").ok().flatten().unwrap_or_default();
+    let content = raw_event.get_field::<ImagePackEventContent>("content")
+        .inspect_err(|e| error!("Failed to deserialize image pack {state_key:?} of room {}: {e:?}", room.room_id()))
+        .ok()
+        .flatten()?;
+    content.into_image_pack(
+        ImagePackSource::Room { room_id: room.room_id().to_owned(), state_key },
+        room.cached_display_name().map(|name| name.to_string()),
+    )
+}
@@ -257,6 +257,7 @@ impl WidgetMatchEvent for JoinLeaveRoomModal {
                         accept_button_text = tr_key(self.app_language, "join_leave_modal.button.joining");
                         submit_async_request(MatrixRequest::JoinRoom {
                             room_id: invite.room_id().clone(),
+                            via: Vec::new(),
                         });
                     }
                     JoinLeaveModalKind::RejectInvite(invite) => {
@@ -286,6 +287,7 @@ impl WidgetMatchEvent for JoinLeaveRoomModal {
                         accept_button_text = tr_key(self.app_language, "join_leave_modal.button.joining");
                         submit_async_request(MatrixRequest::JoinRoom {
                             room_id: details.room_id().clone(),
+                            via: details.via().to_vec(),
                         });
                     }
                     JoinLeaveModalKind::LeaveRoom(room) => {
@@ -78,6 +78,10 @@ pub mod sliding_sync;
 pub mod space_service_sync;
 pub mod avatar_cache;
 pub mod media_cache;
+/// Decoding and playback of audio and voice messages.
+pub mod audio_playback;
+/// An opt-in, on-device full-text index for searching messages in encrypted rooms.
+pub mod local_search_index;
 pub mod verification;
 pub mod updater;
 
@@ -87,6 +91,20 @@ pub mod account_manager;
 pub mod temp_storage;
 pub mod proxy_config;
 pub mod location;
+pub mod live_location;
+/// Fetching and disk caching of map tiles for location thumbnails.
+pub mod map_tiles;
+pub mod image_packs;
+/// Exporting a room's full history to HTML, JSON Lines or plain text.
+pub mod chat_export;
+/// Forwarding messages to other rooms, including re-uploading encrypted media.
+pub mod forward_message;
+/// Bookmarked messages, which are synced across devices via account data.
+pub mod bookmarks;
+/// Messages scheduled to be sent later, either by the homeserver (MSC4140) or locally.
+pub mod scheduled_messages;
+/// Outgoing messages in the send queue that have not yet been sent, which can be retried or discarded.
+pub mod unsent_messages;
 pub mod image_utils;
 
 pub const APP_QUALIFIER: &str = "org";
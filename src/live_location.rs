@@ -0,0 +1,258 @@
+//! Tracks the current user's own live location shares (MSC3489)
+//! and sends a location beacon to each shared room whenever the device's location changes.
+//!
+//! A live share is started via [`start_live_location_share()`], which sends a `beacon_info`
+//! state event to the room. Once the homeserver has accepted that event, the share becomes active
+//! and every [`LocationUpdate`] is forwarded to the room as a `beacon` event,
+//! until the share is stopped or its duration has elapsed.
+//! Each share is stopped by a timer once its duration has elapsed,
+//! even if no location updates are received in the meantime.
+//!
+//! Active shares are also persisted in the Matrix client's state store,
+//! such that they can be resumed (or stopped, if they have since expired) after restarting Robrix,
+//! and stopped upon logging out, rather than leaving a live `beacon_info` on the server.
+
+use std::{collections::BTreeMap, sync::Mutex, time::{Duration, Instant, SystemTime}};
+
+use makepad_widgets::{Action, Cx, error, log};
+use matrix_sdk::{Client, ruma::OwnedRoomId};
+use tokio::runtime::Handle;
+
+use crate::{
+    location::{LocationAction, LocationRequest, LocationUpdate, get_latest_location, init_location_subscriber, request_location_update},
+    sliding_sync::{MatrixRequest, submit_async_request},
+    utils,
+};
+
+/// The durations that the user can choose from when starting a live location share.
+pub const LIVE_LOCATION_DURATIONS: [Duration; 3] = [
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(8 * 60 * 60),
+];
+
+/// The minimum amount of time between two beacons sent to the same room,
+/// such that frequent location updates don't flood the room with events.
+const MIN_BEACON_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The key under which the current user's active live location shares
+/// are kept in the Matrix client's state store.
+const PERSISTED_SHARES_STORE_KEY: &[u8] = b"robrix.live_location_shares";
+
+/// Actions related to the current user's own live location shares.
+#[derive(Clone, Debug)]
+pub enum LiveLocationAction {
+    /// A live location share was successfully started in the given room.
+    Started {
+        room_id: OwnedRoomId,
+        expires_at: SystemTime,
+    },
+    /// The live location share in the given room was stopped.
+    Stopped {
+        room_id: OwnedRoomId,
+    },
+    /// The timer for the live location share in the given room has fired,
+    /// meaning that the share that was started with the given `expires_at` time is now expired.
+    Expired {
+        room_id: OwnedRoomId,
+        expires_at: SystemTime,
+    },
+}
+
+/// A live location share that the current user has started.
+struct ActiveShare {
+    expires_at: SystemTime,
+    last_beacon_sent: Option<Instant>,
+}
+
+/// The current user's active live location shares, keyed by room ID.
+///
+/// This is only accessed from the main UI thread.
+static ACTIVE_SHARES: Mutex<BTreeMap<OwnedRoomId, ActiveShare>> = Mutex::new(BTreeMap::new());
+
+/// Guards read-modify-write updates of the persisted live location shares,
+/// which are made by concurrent start and stop tasks.
+static PERSISTED_SHARES_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Starts sharing the current user's live location in the given room for the given duration.
+///
+/// This function requires passing in a reference to `Cx`,
+/// which acts as a guarantee that it must only be called by the main UI thread.
+pub fn start_live_location_share(cx: &mut Cx, room_id: OwnedRoomId, duration: Duration) {
+    if let Err(e) = init_location_subscriber(cx) {
+        error!("Failed to initialize location subscriber for live location sharing: {e:?}");
+    }
+    submit_async_request(MatrixRequest::StartLiveLocationShare { room_id, duration });
+}
+
+/// Stops sharing the current user's live location in the given room.
+pub fn stop_live_location_share(room_id: OwnedRoomId) {
+    submit_async_request(MatrixRequest::StopLiveLocationShare { room_id });
+}
+
+/// Handles the given action if it's a [`LiveLocationAction`] or a [`LocationAction`].
+///
+/// This must be called by the main UI thread for every action.
+pub fn handle_action(cx: &mut Cx, action: &Action) {
+    match action.downcast_ref() {
+        Some(LiveLocationAction::Started { room_id, expires_at }) => {
+            let is_first_share = {
+                let mut shares = ACTIVE_SHARES.lock().unwrap();
+                let previous = shares.insert(room_id.clone(), ActiveShare { expires_at: *expires_at, last_beacon_sent: None });
+                previous.is_none() && shares.len() == 1
+            };
+            if is_first_share {
+                // A share resumed after restarting Robrix may not have initialized the subscriber yet.
+                if let Err(e) = init_location_subscriber(cx) {
+                    error!("Failed to initialize location subscriber for live location sharing: {e:?}");
+                }
+                request_location_update(LocationRequest::StartUpdates);
+            }
+            // Send the latest known location right away rather than waiting for the next update.
+            if let Some(update) = get_latest_location() {
+                send_beacons(&update);
+            }
+            return;
+        }
+        Some(LiveLocationAction::Stopped { room_id }) => {
+            remove_share(room_id);
+            return;
+        }
+        Some(LiveLocationAction::Expired { room_id, expires_at }) => {
+            // Ignore the timer of an earlier share that has since been replaced by a new one.
+            let is_current_share = ACTIVE_SHARES.lock().unwrap()
+                .get(room_id)
+                .is_some_and(|share| share.expires_at == *expires_at);
+            if is_current_share {
+                log!("Live location share in {room_id} has expired; stopping it.");
+                remove_share(room_id);
+                stop_live_location_share(room_id.clone());
+            }
+            return;
+        }
+        None => {}
+    }
+
+    if let Some(LocationAction::Update(update)) = action.downcast_ref() {
+        send_beacons(update);
+    }
+}
+
+/// Sends the given location update as a beacon to every room with an active live location share.
+///
+/// Shares that have expired are skipped; they are stopped upon their [`LiveLocationAction::Expired`] timer.
+fn send_beacons(update: &LocationUpdate) {
+    let now = SystemTime::now();
+    let geo_uri = format!(
+        "{}{},{}",
+        utils::GEO_URI_SCHEME,
+        update.coordinates.latitude,
+        update.coordinates.longitude,
+    );
+    let mut shares = ACTIVE_SHARES.lock().unwrap();
+    for (room_id, share) in shares.iter_mut() {
+        if share.expires_at <= now {
+            continue;
+        }
+        if share.last_beacon_sent.is_some_and(|sent| sent.elapsed() < MIN_BEACON_INTERVAL) {
+            continue;
+        }
+        share.last_beacon_sent = Some(Instant::now());
+        submit_async_request(MatrixRequest::SendLocationBeacon {
+            room_id: room_id.clone(),
+            geo_uri: geo_uri.clone(),
+        });
+    }
+}
+
+/// Removes the given room's live location share, if any.
+///
+/// Location updates are only stopped if this removed the last active share,
+/// such that other rooms' shares keep receiving location updates.
+fn remove_share(room_id: &OwnedRoomId) {
+    let was_last_share = {
+        let mut shares = ACTIVE_SHARES.lock().unwrap();
+        let removed = shares.remove(room_id).is_some();
+        removed && shares.is_empty()
+    };
+    if was_last_share {
+        request_location_update(LocationRequest::StopUpdates);
+    }
+}
+
+/// Loads the current user's persisted live location shares, keyed by room ID,
+/// with the time at which each share expires.
+async fn load_persisted_shares(client: &Client) -> BTreeMap<OwnedRoomId, SystemTime> {
+    match client.state_store().get_custom_value(PERSISTED_SHARES_STORE_KEY).await {
+        Ok(Some(bytes)) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            error!("Failed to deserialize persisted live location shares: {e}");
+            BTreeMap::new()
+        }),
+        Ok(None) => BTreeMap::new(),
+        Err(e) => {
+            error!("Failed to load persisted live location shares: {e}");
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Applies the given change to the current user's persisted live location shares.
+async fn update_persisted_shares(client: &Client, f: impl FnOnce(&mut BTreeMap<OwnedRoomId, SystemTime>)) {
+    let _lock = PERSISTED_SHARES_LOCK.lock().await;
+    let mut shares = load_persisted_shares(client).await;
+    f(&mut shares);
+    let result = match serde_json::to_vec(&shares) {
+        Ok(bytes) => client.state_store().set_custom_value(PERSISTED_SHARES_STORE_KEY, bytes).await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to persist live location shares: {e}");
+    }
+}
+
+/// Persists the given room's live location share, which expires at the given time.
+pub async fn persist_share(client: &Client, room_id: OwnedRoomId, expires_at: SystemTime) {
+    update_persisted_shares(client, |shares| { shares.insert(room_id, expires_at); }).await;
+}
+
+/// Removes the given room's live location share from persistent storage.
+pub async fn unpersist_share(client: &Client, room_id: &OwnedRoomId) {
+    update_persisted_shares(client, |shares| { shares.remove(room_id); }).await;
+}
+
+/// Restores the current user's persisted live location shares after starting Robrix.
+///
+/// Shares that expired while Robrix wasn't running are stopped,
+/// such that their `beacon_info` doesn't remain live on the server.
+/// All other shares are resumed until they expire.
+pub async fn restore_live_location_shares(client: &Client) {
+    let now = SystemTime::now();
+    for (room_id, expires_at) in load_persisted_shares(client).await {
+        let Ok(remaining) = expires_at.duration_since(now) else {
+            log!("Stopping live location share in {room_id}, which expired while Robrix wasn't running.");
+            stop_live_location_share(room_id);
+            continue;
+        };
+        log!("Resuming live location share in {room_id} for {remaining:?}.");
+        Cx::post_action(LiveLocationAction::Started { room_id: room_id.clone(), expires_at });
+        let _expiry_timer_task = Handle::current().spawn(async move {
+            tokio::time::sleep(remaining).await;
+            Cx::post_action(LiveLocationAction::Expired { room_id, expires_at });
+        });
+    }
+}
+
+/// Stops all of the current user's live location shares, e.g., before logging out.
+pub async fn stop_all_live_location_shares(client: &Client) {
+    for room_id in load_persisted_shares(client).await.into_keys() {
+        let Some(room) = client.get_room(&room_id) else { continue };
+        match room.stop_live_location_share().await {
+            Ok(_response) => log!("Stopped live location share in {room_id}."),
+            Err(e) => error!("Failed to stop live location share in {room_id}; error: {e:?}"),
+        }
+        Cx::post_action(LiveLocationAction::Stopped { room_id });
+    }
+    update_persisted_shares(client, BTreeMap::clear).await;
+}
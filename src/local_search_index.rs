@@ -0,0 +1,664 @@
+//! An opt-in, on-device full-text index of messages sent in encrypted rooms.
+//!
+//! Homeservers cannot search end-to-end encrypted rooms, so the server-side
+//! `/search` API never returns results from them. When enabled by the user,
+//! this index is filled with the plaintext bodies of decrypted messages as they
+//! are received by each room's timeline subscriber, which includes messages
+//! that are backfilled via [`MatrixRequest::PaginateTimeline`].
+//!
+//! The index is kept in memory for the currently-logged-in user, and is also
+//! persisted as an append-only log in that user's persistent state directory
+//! (see [`persistent_state_dir()`]), such that it survives app restarts.
+//! Each log entry is encrypted with a key that is kept in the Matrix client's state store,
+//! which is encrypted with the same passphrase as the crypto store
+//! (see [`load_encryption_key()`]); the log is compacted once it holds mostly stale entries.
+//! The index is deleted along with that user's persisted session.
+//!
+//! All changes to the index are handled in order by a single worker thread,
+//! such that, e.g., a redaction can never be overwritten by an older version of its message.
+//!
+//! [`MatrixRequest::PaginateTimeline`]: crate::sliding_sync::MatrixRequest::PaginateTimeline
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, Sender}, Arc, LazyLock, Mutex},
+};
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD as BASE64, Engine as _};
+use chacha20poly1305::{aead::{Aead, AeadCore, KeyInit, OsRng}, Key, XChaCha20Poly1305, XNonce};
+use makepad_widgets::{error, log, warning, Cx};
+use matrix_sdk::{
+    Client,
+    ruma::{MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, UserId},
+};
+use matrix_sdk_ui::timeline::{MsgLikeKind, TimelineItem, TimelineItemContent};
+use serde::{Deserialize, Serialize};
+
+use crate::{persistence::persistent_state_dir, sliding_sync::current_user_id};
+
+/// The name of the directory (within a user's persistent state directory)
+/// that holds the local search index.
+const SEARCH_INDEX_DIR_NAME: &str = "search_index";
+/// The name of the append-only log file that the local search index is persisted to.
+const SEARCH_INDEX_FILE_NAME: &str = "messages.jsonl";
+/// The key under which the index's encryption key is kept in the Matrix client's state store.
+const ENCRYPTION_KEY_STORE_KEY: &[u8] = b"robrix.local_search_index.encryption_key";
+/// The log is never compacted while it holds fewer than this many entries.
+const MIN_LOG_ENTRIES_TO_COMPACT: usize = 1_000;
+/// The log is compacted once it holds more than this many entries per message in the index,
+/// i.e., once most of its entries are stale edits, redactions, or removed messages.
+const MAX_LOG_ENTRIES_PER_MESSAGE: usize = 2;
+
+/// Whether the user has opted in to indexing messages in encrypted rooms.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The index of the currently-logged-in user, which is lazily loaded upon first use.
+static INDEX: Mutex<Option<LocalSearchIndex>> = Mutex::new(None);
+
+/// The cipher that encrypts the index of the given user, which is set by [`load_encryption_key()`].
+///
+/// The index cannot be loaded or modified until this has been set for the currently-logged-in user.
+static INDEX_CIPHER: Mutex<Option<(OwnedUserId, IndexCipher)>> = Mutex::new(None);
+
+/// Actions emitted by the local search index worker thread.
+#[derive(Debug)]
+pub enum LocalSearchIndexAction {
+    /// The size of the index was computed, in response to [`request_index_size()`].
+    Size(LocalSearchIndexSize),
+    /// All messages were deleted from the index, in response to [`purge()`].
+    Purged(Result<(), String>),
+}
+
+/// A change to (or query of) the local search index, handled in order by the index worker thread.
+enum IndexRequest {
+    IndexItems {
+        room_id: OwnedRoomId,
+        items: Vec<Arc<TimelineItem>>,
+    },
+    PurgeRoom {
+        room_id: OwnedRoomId,
+    },
+    Purge,
+    ComputeSize,
+}
+
+/// The sender of requests to the index worker thread, which is spawned upon first use.
+static INDEX_REQUEST_SENDER: LazyLock<Sender<IndexRequest>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || index_worker(receiver));
+    sender
+});
+
+fn submit_index_request(request: IndexRequest) {
+    if INDEX_REQUEST_SENDER.send(request).is_err() {
+        error!("BUG: the local search index worker thread has died.");
+    }
+}
+
+/// Handles every index request in the order that it was submitted.
+fn index_worker(receiver: Receiver<IndexRequest>) {
+    while let Ok(request) = receiver.recv() {
+        match request {
+            IndexRequest::IndexItems { room_id, items } => index_timeline_items_now(&room_id, &items),
+            IndexRequest::PurgeRoom { room_id } => {
+                if let Err(e) = purge_room_now(&room_id) {
+                    error!("Failed to delete messages of room {room_id} from the local search index. Error: {e}");
+                }
+            }
+            IndexRequest::Purge => {
+                Cx::post_action(LocalSearchIndexAction::Purged(purge_now().map_err(|e| e.to_string())));
+            }
+            IndexRequest::ComputeSize => {
+                Cx::post_action(LocalSearchIndexAction::Size(index_size()));
+            }
+        }
+    }
+}
+
+/// Encrypts and decrypts the entries of the persisted log of an index.
+#[derive(Clone)]
+struct IndexCipher(XChaCha20Poly1305);
+
+impl IndexCipher {
+    /// Encrypts the given serialized log entry into a single line of base64 text.
+    fn encrypt(&self, plaintext: &[u8]) -> std::io::Result<String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.0.encrypt(&nonce, plaintext)
+            .map_err(|_| std::io::Error::other("failed to encrypt local search index entry"))?;
+        let mut line = nonce.to_vec();
+        line.extend(ciphertext);
+        Ok(BASE64.encode(line))
+    }
+
+    /// Decrypts a line that was produced by [`IndexCipher::encrypt()`].
+    fn decrypt(&self, line: &str) -> Option<Vec<u8>> {
+        let data = BASE64.decode(line.trim()).ok()?;
+        if data.len() < 24 {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(24);
+        self.0.decrypt(XNonce::from_slice(nonce), ciphertext).ok()
+    }
+}
+
+/// Loads the encryption key of the local search index of the given client's user,
+/// generating and storing a new key if that user doesn't have one yet.
+///
+/// The key is kept in the client's state store, which is encrypted with the store passphrase.
+/// This must be called after logging in and before the index can be used.
+pub async fn load_encryption_key(client: &Client) {
+    let Some(user_id) = client.user_id() else { return };
+    let store = client.state_store();
+    let key = match store.get_custom_value(ENCRYPTION_KEY_STORE_KEY).await {
+        Ok(Some(key)) if key.len() == 32 => Key::clone_from_slice(&key),
+        Ok(_) => {
+            let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+            if let Err(e) = store.set_custom_value(ENCRYPTION_KEY_STORE_KEY, key.to_vec()).await {
+                error!("Failed to store the local search index encryption key for {user_id}: {e}");
+                return;
+            }
+            key
+        }
+        Err(e) => {
+            error!("Failed to load the local search index encryption key for {user_id}: {e}");
+            return;
+        }
+    };
+    *INDEX_CIPHER.lock().unwrap() = Some((user_id.to_owned(), IndexCipher(XChaCha20Poly1305::new(&key))));
+}
+
+/// Enables or disables the local search index.
+///
+/// Disabling the index stops new messages from being indexed,
+/// but does not delete the existing index; use [`purge()`] for that.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether the local search index is enabled.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A single message stored in the local search index.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedMessage {
+    pub room_id: OwnedRoomId,
+    pub event_id: OwnedEventId,
+    /// The root event of the thread that this message was sent in, if any.
+    pub thread_root_event_id: Option<OwnedEventId>,
+    pub sender: OwnedUserId,
+    pub timestamp: MilliSecondsSinceUnixEpoch,
+    /// The plaintext body of the message.
+    pub body: String,
+}
+
+/// The size of the local search index, as reported by [`LocalSearchIndexAction::Size`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LocalSearchIndexSize {
+    /// The number of messages currently in the index.
+    pub message_count: usize,
+    /// The size of the index's persisted files, in bytes.
+    pub bytes_on_disk: u64,
+}
+
+/// A single entry in the persisted append-only log of the index.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogEntry {
+    Upsert(IndexedMessage),
+    Remove { event_id: OwnedEventId },
+}
+
+/// The in-memory local search index for a single user.
+#[derive(Default)]
+struct LocalSearchIndex {
+    /// The user that this index belongs to.
+    user_id: Option<OwnedUserId>,
+    /// The cipher that encrypts this index's persisted log.
+    cipher: Option<IndexCipher>,
+    messages: HashMap<OwnedEventId, IndexedMessage>,
+    /// An inverted index from each token to the IDs of the messages that contain it.
+    tokens: BTreeMap<String, HashSet<OwnedEventId>>,
+    /// The number of entries in this index's persisted log.
+    log_entries: usize,
+}
+
+impl LocalSearchIndex {
+    fn new(user_id: &UserId, cipher: IndexCipher) -> Self {
+        Self { user_id: Some(user_id.to_owned()), cipher: Some(cipher), ..Default::default() }
+    }
+
+    /// Loads the persisted index of the given user, skipping any malformed log entries.
+    ///
+    /// If any entries couldn't be decrypted (e.g., because they were written unencrypted),
+    /// the log is immediately rewritten without them.
+    fn load(user_id: &UserId, cipher: IndexCipher) -> Self {
+        let mut index = Self::new(user_id, cipher.clone());
+        let file = match File::open(index_file_path(user_id)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return index,
+            Err(e) => {
+                error!("Failed to open local search index for {user_id}: {e}");
+                return index;
+            }
+        };
+        let mut malformed_entries = 0;
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else { break };
+            index.log_entries += 1;
+            let entry = cipher.decrypt(&line)
+                .and_then(|plaintext| serde_json::from_slice::<LogEntry>(&plaintext).ok());
+            match entry {
+                Some(LogEntry::Upsert(message)) => { index.insert(message); }
+                Some(LogEntry::Remove { event_id }) => { index.remove(&event_id); }
+                None => malformed_entries += 1,
+            }
+        }
+        log!("Loaded local search index for {user_id} with {} messages.", index.messages.len());
+        if malformed_entries > 0 {
+            warning!("Discarding {malformed_entries} malformed entries from the local search index for {user_id}.");
+            if let Err(e) = index.compact_log() {
+                error!("Failed to compact local search index for {user_id}: {e}");
+            }
+        }
+        index
+    }
+
+    /// Appends the given entries to this index's persisted log,
+    /// compacting the log instead if most of its entries have become stale.
+    fn append_to_log(&mut self, entries: &[LogEntry]) -> std::io::Result<()> {
+        let (Some(user_id), Some(cipher)) = (self.user_id.as_deref(), self.cipher.as_ref()) else {
+            return Ok(());
+        };
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let log_entries = self.log_entries + entries.len();
+        if log_entries >= MIN_LOG_ENTRIES_TO_COMPACT
+            && log_entries > self.messages.len() * MAX_LOG_ENTRIES_PER_MESSAGE
+        {
+            return self.compact_log();
+        }
+        std::fs::create_dir_all(index_dir_path(user_id))?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(index_file_path(user_id))?;
+        let mut writer = BufWriter::new(file);
+        for entry in entries {
+            writeln!(writer, "{}", cipher.encrypt(&serde_json::to_vec(entry)?)?)?;
+        }
+        writer.flush()?;
+        self.log_entries = log_entries;
+        Ok(())
+    }
+
+    /// Rewrites this index's persisted log such that it only contains
+    /// the messages currently in the index, discarding any stale entries.
+    fn compact_log(&mut self) -> std::io::Result<()> {
+        let (Some(user_id), Some(cipher)) = (self.user_id.as_deref(), self.cipher.as_ref()) else {
+            return Ok(());
+        };
+        let file_path = index_file_path(user_id);
+        if self.messages.is_empty() {
+            self.log_entries = 0;
+            return match std::fs::remove_file(&file_path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        std::fs::create_dir_all(index_dir_path(user_id))?;
+        let temp_path = file_path.with_extension("jsonl.tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        for message in self.messages.values() {
+            let entry = serde_json::to_vec(&LogEntry::Upsert(message.clone()))?;
+            writeln!(writer, "{}", cipher.encrypt(&entry)?)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(temp_path, file_path)?;
+        self.log_entries = self.messages.len();
+        Ok(())
+    }
+
+    /// Inserts the given message, replacing any prior version of it.
+    ///
+    /// Returns `false` if an identical message was already in the index.
+    fn insert(&mut self, message: IndexedMessage) -> bool {
+        if self.messages.get(&message.event_id).is_some_and(|m| m.body == message.body) {
+            return false;
+        }
+        self.remove(&message.event_id);
+        for token in tokenize(&message.body) {
+            self.tokens.entry(token).or_default().insert(message.event_id.clone());
+        }
+        self.messages.insert(message.event_id.clone(), message);
+        true
+    }
+
+    /// Removes the given message, returning `true` if it was in the index.
+    fn remove(&mut self, event_id: &OwnedEventId) -> bool {
+        let Some(message) = self.messages.remove(event_id) else { return false };
+        for token in tokenize(&message.body) {
+            if let Some(event_ids) = self.tokens.get_mut(&token) {
+                event_ids.remove(event_id);
+                if event_ids.is_empty() {
+                    self.tokens.remove(&token);
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the messages that contain every token of the given `search_term`,
+    /// where the final token may also match as a prefix, most recent first.
+    fn query(&self, search_term: &str, room_id: Option<&RoomId>, limit: usize) -> Vec<IndexedMessage> {
+        let query_tokens = tokenize(search_term);
+        let last_index = query_tokens.len().saturating_sub(1);
+        let mut matching_event_ids: Option<HashSet<&OwnedEventId>> = None;
+        for (i, query_token) in query_tokens.iter().enumerate() {
+            let token_matches: HashSet<&OwnedEventId> = if i == last_index {
+                self.tokens.range(query_token.clone()..)
+                    .take_while(|(token, _)| token.starts_with(query_token.as_str()))
+                    .flat_map(|(_, event_ids)| event_ids.iter())
+                    .collect()
+            } else {
+                self.tokens.get(query_token)
+                    .map(|event_ids| event_ids.iter().collect())
+                    .unwrap_or_default()
+            };
+            let matches = match matching_event_ids {
+                Some(prior) => prior.intersection(&token_matches).copied().collect(),
+                None => token_matches,
+            };
+            if matches.is_empty() {
+                return Vec::new();
+            }
+            matching_event_ids = Some(matches);
+        }
+
+        let mut results: Vec<IndexedMessage> = matching_event_ids
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|event_id| self.messages.get(event_id))
+            .filter(|message| room_id.is_none_or(|room_id| *message.room_id == *room_id))
+            .cloned()
+            .collect();
+        results.sort_unstable_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        results.truncate(limit);
+        results
+    }
+}
+
+/// Splits the given text into lowercase search tokens.
+///
+/// Runs of alphanumeric characters form a single token, except for CJK characters,
+/// which are not separated by whitespace and are therefore each indexed individually.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            current.extend(c.to_lowercase());
+        } else if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana and Katakana
+        | '\u{3400}'..='\u{4DBF}'   // CJK Unified Ideographs Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul Syllables
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+    )
+}
+
+fn index_dir_path(user_id: &UserId) -> PathBuf {
+    persistent_state_dir(user_id).join(SEARCH_INDEX_DIR_NAME)
+}
+
+fn index_file_path(user_id: &UserId) -> PathBuf {
+    index_dir_path(user_id).join(SEARCH_INDEX_FILE_NAME)
+}
+
+/// Runs the given closure on the index of the currently-logged-in user,
+/// loading that index from persistent storage first if needed.
+///
+/// Returns `None` if no user is logged in, or if that user's encryption key isn't loaded yet.
+fn with_current_index<R>(f: impl FnOnce(&mut LocalSearchIndex, &UserId) -> R) -> Option<R> {
+    let user_id = current_user_id()?;
+    let cipher = INDEX_CIPHER.lock().unwrap()
+        .as_ref()
+        .filter(|(cipher_user_id, _)| *cipher_user_id == user_id)
+        .map(|(_, cipher)| cipher.clone())?;
+    let mut guard = INDEX.lock().unwrap();
+    if guard.as_ref().is_none_or(|index| index.user_id.as_ref() != Some(&user_id)) {
+        *guard = Some(LocalSearchIndex::load(&user_id, cipher));
+    }
+    guard.as_mut().map(|index| f(index, &user_id))
+}
+
+/// Submits the given timeline `items` to be added to the local search index,
+/// updating any messages that were edited and removing any that were redacted.
+///
+/// This does nothing if the local search index is not enabled.
+/// Callers should only pass in items from encrypted rooms.
+pub fn index_timeline_items(room_id: OwnedRoomId, items: Vec<Arc<TimelineItem>>) {
+    if is_enabled() && !items.is_empty() {
+        submit_index_request(IndexRequest::IndexItems { room_id, items });
+    }
+}
+
+fn index_timeline_items_now(room_id: &RoomId, items: &[Arc<TimelineItem>]) {
+    if !is_enabled() {
+        return;
+    }
+    let mut changes = Vec::new();
+    for item in items {
+        let Some(event) = item.as_event() else { continue };
+        let Some(event_id) = event.event_id() else { continue };
+        let TimelineItemContent::MsgLike(msg_like) = event.content() else { continue };
+        if matches!(msg_like.kind, MsgLikeKind::Redacted) {
+            changes.push(LogEntry::Remove { event_id: event_id.to_owned() });
+            continue;
+        }
+        let Some(message) = msg_like.as_message() else { continue };
+        let body = message.body().trim();
+        if body.is_empty() {
+            continue;
+        }
+        changes.push(LogEntry::Upsert(IndexedMessage {
+            room_id: room_id.to_owned(),
+            event_id: event_id.to_owned(),
+            thread_root_event_id: msg_like.thread_root.clone(),
+            sender: event.sender().to_owned(),
+            timestamp: event.timestamp(),
+            body: body.to_owned(),
+        }));
+    }
+    if changes.is_empty() {
+        return;
+    }
+
+    with_current_index(|index, _| {
+        // Only persist the entries that actually changed the index.
+        changes.retain(|change| match change {
+            LogEntry::Upsert(message) => index.insert(message.clone()),
+            LogEntry::Remove { event_id } => index.remove(event_id),
+        });
+        if let Err(e) = index.append_to_log(&changes) {
+            error!("Failed to persist {} local search index entries: {e}", changes.len());
+        }
+    });
+}
+
+/// Searches the local index for messages that contain all words of the given `search_term`.
+///
+/// If `room_id` is given, only messages from that room are returned.
+/// Results are sorted from most recent to oldest, and limited to `limit` messages.
+pub fn query(search_term: &str, room_id: Option<&RoomId>, limit: usize) -> Vec<IndexedMessage> {
+    with_current_index(|index, _| index.query(search_term, room_id, limit))
+        .unwrap_or_default()
+}
+
+/// Submits a request to compute the size of the local search index of the currently-logged-in user,
+/// which is reported via a [`LocalSearchIndexAction::Size`] once all prior changes have been made.
+pub fn request_index_size() {
+    submit_index_request(IndexRequest::ComputeSize);
+}
+
+/// Returns the size of the local search index of the currently-logged-in user.
+///
+/// This may load the entire index from disk, so it must not be called on the UI thread.
+fn index_size() -> LocalSearchIndexSize {
+    with_current_index(|index, user_id| LocalSearchIndexSize {
+        message_count: index.messages.len(),
+        bytes_on_disk: std::fs::metadata(index_file_path(user_id))
+            .map(|metadata| metadata.len())
+            .unwrap_or(0),
+    })
+    .unwrap_or_default()
+}
+
+/// Submits a request to delete all messages from the local search index
+/// of the currently-logged-in user, which is reported via a [`LocalSearchIndexAction::Purged`].
+pub fn purge() {
+    submit_index_request(IndexRequest::Purge);
+}
+
+fn purge_now() -> std::io::Result<()> {
+    with_current_index(|index, user_id| {
+        *index = LocalSearchIndex {
+            user_id: Some(user_id.to_owned()),
+            cipher: index.cipher.take(),
+            ..Default::default()
+        };
+        match std::fs::remove_dir_all(index_dir_path(user_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    })
+    .unwrap_or(Ok(()))
+}
+
+/// Submits a request to delete all messages from the given room from the local search index
+/// of the currently-logged-in user, e.g., after leaving that room.
+pub fn purge_room(room_id: OwnedRoomId) {
+    submit_index_request(IndexRequest::PurgeRoom { room_id });
+}
+
+fn purge_room_now(room_id: &RoomId) -> std::io::Result<()> {
+    with_current_index(|index, _| {
+        let event_ids: Vec<OwnedEventId> = index.messages.values()
+            .filter(|message| *message.room_id == *room_id)
+            .map(|message| message.event_id.clone())
+            .collect();
+        if event_ids.is_empty() {
+            return Ok(());
+        }
+        for event_id in &event_ids {
+            index.remove(event_id);
+        }
+        index.compact_log()
+    })
+    .unwrap_or(Ok(()))
+}
+
+/// Deletes the local search index of the given user, both in memory and on disk.
+///
+/// This is only called by [`delete_session()`](crate::persistence::delete_session),
+/// which removes all other persisted data of the user's session as well.
+pub async fn delete_index_for_user(user_id: &UserId) -> anyhow::Result<()> {
+    {
+        let mut guard = INDEX.lock().unwrap();
+        if guard.as_ref().is_some_and(|index| index.user_id.as_deref() == Some(user_id)) {
+            *guard = None;
+        }
+        let mut cipher = INDEX_CIPHER.lock().unwrap();
+        if cipher.as_ref().is_some_and(|(cipher_user_id, _)| **cipher_user_id == *user_id) {
+            *cipher = None;
+        }
+    }
+    match tokio::fs::remove_dir_all(index_dir_path(user_id)).await {
+        Ok(()) => {
+            log!("Deleted local search index for {user_id}.");
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(anyhow::anyhow!("Failed to delete local search index for {user_id}: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix_sdk::ruma::{owned_event_id, owned_room_id, owned_user_id, UInt};
+
+    fn message(event_id: OwnedEventId, room_id: OwnedRoomId, ts: u32, body: &str) -> IndexedMessage {
+        IndexedMessage {
+            room_id,
+            event_id,
+            thread_root_event_id: None,
+            sender: owned_user_id!("@alice:example.org"),
+            timestamp: MilliSecondsSinceUnixEpoch(UInt::from(ts)),
+            body: body.to_owned(),
+        }
+    }
+
+    #[test]
+    fn tokenize_splits_words_and_cjk_characters() {
+        assert_eq!(tokenize("Hello, World! it's 2024"), vec!["hello", "world", "it", "s", "2024"]);
+        assert_eq!(tokenize("搜索test消息"), vec!["搜", "索", "test", "消", "息"]);
+    }
+
+    #[test]
+    fn query_matches_all_tokens_and_prefix_of_last() {
+        let room_a = owned_room_id!("!a:example.org");
+        let room_b = owned_room_id!("!b:example.org");
+        let mut index = LocalSearchIndex::default();
+        index.insert(message(owned_event_id!("$1:example.org"), room_a.clone(), 1, "The quick brown fox"));
+        index.insert(message(owned_event_id!("$2:example.org"), room_b.clone(), 2, "A quick brownie recipe"));
+        index.insert(message(owned_event_id!("$3:example.org"), room_a.clone(), 3, "Slow brown bear"));
+
+        let ids = |results: Vec<IndexedMessage>| results.into_iter().map(|m| m.event_id).collect::<Vec<_>>();
+        assert_eq!(ids(index.query("quick brown", None, 10)), vec![owned_event_id!("$2:example.org"), owned_event_id!("$1:example.org")]);
+        assert_eq!(ids(index.query("quick brown", Some(&room_a), 10)), vec![owned_event_id!("$1:example.org")]);
+        assert_eq!(ids(index.query("quick brownie", None, 10)), vec![owned_event_id!("$2:example.org")]);
+        assert_eq!(ids(index.query("quick bear", None, 10)), Vec::<OwnedEventId>::new());
+        assert_eq!(ids(index.query("brown", None, 1)), vec![owned_event_id!("$3:example.org")]);
+
+        // Edits replace the previously-indexed body, and removals drop the message.
+        index.insert(message(owned_event_id!("$1:example.org"), room_a.clone(), 1, "The lazy dog"));
+        assert_eq!(ids(index.query("fox", None, 10)), Vec::<OwnedEventId>::new());
+        assert!(index.remove(&owned_event_id!("$1:example.org")));
+        assert_eq!(ids(index.query("lazy", None, 10)), Vec::<OwnedEventId>::new());
+        assert!(!index.tokens.contains_key("lazy"));
+    }
+
+    #[test]
+    fn cipher_round_trips_entries_and_rejects_others() {
+        let cipher = IndexCipher(XChaCha20Poly1305::new(&XChaCha20Poly1305::generate_key(&mut OsRng)));
+        let other_cipher = IndexCipher(XChaCha20Poly1305::new(&XChaCha20Poly1305::generate_key(&mut OsRng)));
+        let line = cipher.encrypt(br#"{"secret":"message"}"#).unwrap();
+        assert!(!line.contains("secret"));
+        assert_eq!(cipher.decrypt(&line).as_deref(), Some(&br#"{"secret":"message"}"#[..]));
+        assert_eq!(other_cipher.decrypt(&line), None);
+        assert_eq!(cipher.decrypt(r#"{"secret":"message"}"#), None);
+    }
+}
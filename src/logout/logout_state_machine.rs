@@ -65,7 +65,7 @@
 //!
 //! 1. **PreChecking**: Validate CLIENT, SYNC_SERVICE, and access_token existence
 //! 2. **StoppingSyncService**: Stop sync service to prevent new data
-//! 3. **LoggingOutFromServer**: Call `client.matrix_auth().logout()` (60s timeout)
+//! 3. **LoggingOutFromServer**: Stop live location shares, then call `client.matrix_auth().logout()` (60s timeout)
 //! 4. **PointOfNoReturn**: Set global flags, delete saved user ID
 //! 5. **ClosingTabs**: Close desktop tabs via `MainDesktopUiAction::CloseAllTabs`
 //! 6. **CleaningAppState**: Clear global resources and notify UI cleanup
@@ -94,6 +94,7 @@ use crate::persistence::{delete_latest_user_id, skip_app_state_restore_once};
 use crate::sliding_sync::clear_app_state;
 use crate::{
     home::main_desktop_ui::MainDesktopUiAction,
+    live_location,
     sliding_sync::{get_client, get_sync_service, shutdown_background_tasks, start_matrix_tokio},
 };
 use super::logout_confirm_modal::{LogoutAction, ClearedComponentType};
@@ -498,6 +499,15 @@ impl LogoutStateMachine {
         let Some(client) = get_client() else {
             return Err(LogoutError::Unrecoverable(UnrecoverableError::ComponentsCleared));
         };
+
+        // Stop any live location shares while we can still send events, such that
+        // their `beacon_info` doesn't remain live on the server after logging out.
+        if tokio::time::timeout(
+            self.config.server_logout_timeout,
+            live_location::stop_all_live_location_shares(&client),
+        ).await.is_err() {
+            log!("Warning: timed out stopping live location shares before logout");
+        }
         
         match tokio::time::timeout(
             self.config.server_logout_timeout,
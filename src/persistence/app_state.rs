@@ -1,12 +1,13 @@
-use std::io::Write;
+use std::{io::Write, sync::{Mutex, atomic::{AtomicU64, Ordering}}};
 
 use makepad_widgets::*;
 use serde::{self, Deserialize, Serialize};
 use matrix_sdk::ruma::{OwnedUserId, UserId};
-use crate::{app::AppState, app_data_dir, persistence::persistent_state_dir};
+use crate::{app::AppState, app_data_dir, persistence::persistent_state_dir, room::composer_drafts::ComposerDraft};
 
 
 const LATEST_APP_STATE_FILE_NAME: &str = "latest_app_state.json";
+const COMPOSER_DRAFTS_FILE_NAME: &str = "composer_drafts.json";
 const SKIP_APP_STATE_RESTORE_ONCE_FILE_NAME: &str = "skip_app_state_restore_once";
 
 const WINDOW_GEOM_STATE_FILE_NAME: &str = "window_geom_state.json";
@@ -39,6 +40,79 @@ pub fn save_app_state(
     Ok(())
 }
 
+/// The generation number of the most recent background save of the app state.
+static APP_STATE_SAVE_GENERATION: AtomicU64 = AtomicU64::new(0);
+/// The generation number of the app state that was most recently written by a background save.
+static LAST_SAVED_APP_STATE_GENERATION: Mutex<u64> = Mutex::new(0);
+
+/// Save the given app state to persistent storage on a background thread,
+/// such that the UI thread doesn't block on writing it to disk.
+///
+/// If multiple saves are in flight at once, an older app state never overwrites a newer one.
+pub fn save_app_state_in_background(app_state: AppState, user_id: OwnedUserId) {
+    let generation = APP_STATE_SAVE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    std::thread::spawn(move || {
+        let mut last_saved_generation = LAST_SAVED_APP_STATE_GENERATION.lock().unwrap();
+        if *last_saved_generation > generation {
+            return;
+        }
+        match save_app_state(app_state, user_id) {
+            Ok(()) => *last_saved_generation = generation,
+            Err(e) => error!("Failed to save app state in the background. Error: {e}"),
+        }
+    });
+}
+
+/// Save the given user's message composer drafts to persistent storage.
+fn save_composer_drafts(
+    drafts: &[ComposerDraft],
+    user_id: &UserId,
+) -> anyhow::Result<()> {
+    let file = std::fs::File::create(
+        persistent_state_dir(user_id).join(COMPOSER_DRAFTS_FILE_NAME)
+    )?;
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer(&mut writer, drafts)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The generation number of the most recent background save of the composer drafts.
+static COMPOSER_DRAFTS_SAVE_GENERATION: AtomicU64 = AtomicU64::new(0);
+/// The generation number of the composer drafts that were most recently written by a background save.
+static LAST_SAVED_COMPOSER_DRAFTS_GENERATION: Mutex<u64> = Mutex::new(0);
+
+/// Save the given user's message composer drafts to persistent storage on a background thread,
+/// such that the UI thread doesn't block on writing them to disk.
+///
+/// If multiple saves are in flight at once, older drafts never overwrite newer ones.
+pub fn save_composer_drafts_in_background(drafts: Vec<ComposerDraft>, user_id: OwnedUserId) {
+    let generation = COMPOSER_DRAFTS_SAVE_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    std::thread::spawn(move || {
+        let mut last_saved_generation = LAST_SAVED_COMPOSER_DRAFTS_GENERATION.lock().unwrap();
+        if *last_saved_generation > generation {
+            return;
+        }
+        match save_composer_drafts(&drafts, &user_id) {
+            Ok(()) => *last_saved_generation = generation,
+            Err(e) => error!("Failed to save composer drafts in the background. Error: {e}"),
+        }
+    });
+}
+
+/// Loads the given user's message composer drafts from persistent storage.
+///
+/// If no drafts have been saved yet, this returns an empty list.
+pub async fn load_composer_drafts(user_id: &UserId) -> anyhow::Result<Vec<ComposerDraft>> {
+    let drafts_path = persistent_state_dir(user_id).join(COMPOSER_DRAFTS_FILE_NAME);
+    let file_bytes = match tokio::fs::read(&drafts_path).await {
+        Ok(fb) => fb,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(serde_json::from_slice(&file_bytes)?)
+}
+
 /// Marks that the next login for this user should skip automatic app-state restore once.
 pub async fn skip_app_state_restore_once(user_id: &UserId) -> anyhow::Result<()> {
     let marker_path = persistent_state_dir(user_id).join(SKIP_APP_STATE_RESTORE_ONCE_FILE_NAME);
@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app_data_dir,
+    local_search_index,
     login::login_screen::LoginAction,
 };
 
@@ -324,6 +325,10 @@ pub async fn delete_session(user_id: &UserId) -> anyhow::Result<bool> {
             }
         }
 
+        if let Err(e) = local_search_index::delete_index_for_user(user_id).await {
+            warning!("{e}");
+        }
+
         tokio::fs::remove_file(&session_file)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to remove session file {session_file:?}: {e}"))
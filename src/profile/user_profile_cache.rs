@@ -226,7 +226,8 @@ where
             Entry::Vacant(entry) => {
                 if fetch_if_missing {
                     // log!("Did not find User {} in cache, fetching from server.", entry.key());
-                    // TODO: use the extra `via` parameters from `matrix_to_uri.via()`.
+                    // Unlike room links, the `via` servers of a user link aren't needed here,
+                    // because our homeserver fetches the profile from the user's own homeserver.
                     submit_async_request(MatrixRequest::GetUserProfile {
                         user_id: entry.key().clone(),
                         room_id: room_id.cloned(),
@@ -0,0 +1,175 @@
+//! Drafts of messages that the user has started writing in a room's message composer.
+//!
+//! Each timeline (a main room timeline or a thread) has at most one draft,
+//! which is saved whenever that timeline is hidden and restored the next time it is shown.
+//! Drafts are persisted per account, such that they survive restarting Robrix.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use makepad_widgets::{error, log};
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId, OwnedUserId, RoomId, UserId};
+use serde::{Deserialize, Serialize};
+
+use crate::{persistence, shared::mentionable_text_input::MentionsState, sliding_sync::TimelineKind};
+
+
+/// The contents of a message that the user was composing in a given timeline.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComposerDraft {
+    /// The room that this draft was written in.
+    pub room_id: OwnedRoomId,
+    /// The root event of the thread that this draft was written in, if any.
+    #[serde(default)]
+    pub thread_root_event_id: Option<OwnedEventId>,
+    /// The text in the message composer.
+    #[serde(default)]
+    pub text: String,
+    /// The mentions (pills) within the above `text`.
+    #[serde(default)]
+    pub mentions: MentionsState,
+    /// The event that the user was replying to, if any.
+    #[serde(default)]
+    pub replying_to_event_id: Option<OwnedEventId>,
+}
+
+impl ComposerDraft {
+    /// Creates an empty draft for the given timeline.
+    pub fn new(timeline_kind: &TimelineKind) -> Self {
+        Self {
+            room_id: timeline_kind.room_id().clone(),
+            thread_root_event_id: timeline_kind.thread_root_event_id().cloned(),
+            text: String::new(),
+            mentions: MentionsState::default(),
+            replying_to_event_id: None,
+        }
+    }
+
+    /// Returns the kind of timeline that this draft was written in.
+    pub fn timeline_kind(&self) -> TimelineKind {
+        match &self.thread_root_event_id {
+            Some(thread_root_event_id) => TimelineKind::Thread {
+                room_id: self.room_id.clone(),
+                thread_root_event_id: thread_root_event_id.clone(),
+            },
+            None => TimelineKind::MainRoom { room_id: self.room_id.clone() },
+        }
+    }
+
+    /// Returns `true` if this draft has neither any text nor a reply target,
+    /// meaning that it isn't worth keeping.
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && self.replying_to_event_id.is_none()
+    }
+}
+
+
+/// The drafts of the currently logged-in user, keyed by the timeline they were written in.
+struct ComposerDrafts {
+    /// The user that these drafts belong to, i.e., the account they will be saved to.
+    user_id: OwnedUserId,
+    drafts: HashMap<TimelineKind, ComposerDraft>,
+}
+
+static COMPOSER_DRAFTS: Mutex<Option<ComposerDrafts>> = Mutex::new(None);
+
+
+/// Returns the saved draft for the given timeline, if any.
+pub fn get_draft(timeline_kind: &TimelineKind) -> Option<ComposerDraft> {
+    COMPOSER_DRAFTS.lock().unwrap()
+        .as_ref()
+        .and_then(|cd| cd.drafts.get(timeline_kind).cloned())
+}
+
+/// Returns `true` if the given room has a saved draft in its main timeline or any of its threads.
+pub fn room_has_draft(room_id: &RoomId) -> bool {
+    COMPOSER_DRAFTS.lock().unwrap()
+        .as_ref()
+        .is_some_and(|cd| cd.drafts.keys().any(|kind| &**kind.room_id() == room_id))
+}
+
+/// Saves the given draft for its timeline, replacing any prior draft for that timeline.
+///
+/// If the given draft is empty, the timeline's prior draft is removed instead.
+/// The set of drafts is written to persistent storage on a background thread if it has changed.
+pub fn set_draft(draft: ComposerDraft) {
+    let mut lock = COMPOSER_DRAFTS.lock().unwrap();
+    let Some(composer_drafts) = lock.as_mut() else { return };
+    let timeline_kind = draft.timeline_kind();
+    let changed = if draft.is_empty() {
+        composer_drafts.drafts.remove(&timeline_kind).is_some()
+    } else if composer_drafts.drafts.get(&timeline_kind) != Some(&draft) {
+        composer_drafts.drafts.insert(timeline_kind, draft);
+        true
+    } else {
+        false
+    };
+    if changed {
+        persistence::save_composer_drafts_in_background(
+            composer_drafts.drafts.values().cloned().collect(),
+            composer_drafts.user_id.clone(),
+        );
+    }
+}
+
+/// Loads the given user's drafts from persistent storage,
+/// replacing the drafts of any previously logged-in user.
+pub async fn load_drafts(user_id: &UserId) {
+    let drafts = match persistence::load_composer_drafts(user_id).await {
+        Ok(drafts) => drafts,
+        Err(e) => {
+            error!("Failed to load composer drafts for {user_id}. Error: {e}");
+            Vec::new()
+        }
+    };
+    log!("Loaded {} composer drafts for {user_id}.", drafts.len());
+    *COMPOSER_DRAFTS.lock().unwrap() = Some(ComposerDrafts {
+        user_id: user_id.to_owned(),
+        drafts: drafts_by_timeline(drafts),
+    });
+}
+
+/// Indexes the given drafts by their timeline, skipping any empty drafts.
+fn drafts_by_timeline(drafts: Vec<ComposerDraft>) -> HashMap<TimelineKind, ComposerDraft> {
+    drafts.into_iter()
+        .filter(|draft| !draft.is_empty())
+        .map(|draft| (draft.timeline_kind(), draft))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draft(thread_root_event_id: Option<&str>, text: &str) -> ComposerDraft {
+        ComposerDraft {
+            room_id: "!room:example.org".try_into().unwrap(),
+            thread_root_event_id: thread_root_event_id.map(|id| id.try_into().unwrap()),
+            text: text.to_owned(),
+            mentions: MentionsState::default(),
+            replying_to_event_id: None,
+        }
+    }
+
+    #[test]
+    fn drafts_are_kept_separately_per_timeline() {
+        let drafts = drafts_by_timeline(vec![
+            draft(None, "main room draft"),
+            draft(Some("$root:example.org"), "thread draft"),
+            draft(Some("$other:example.org"), "   "),
+        ]);
+        assert_eq!(drafts.len(), 2);
+        let main_kind = TimelineKind::MainRoom { room_id: "!room:example.org".try_into().unwrap() };
+        assert_eq!(drafts[&main_kind].text, "main room draft");
+        let thread_kind = draft(Some("$root:example.org"), "").timeline_kind();
+        assert_eq!(drafts[&thread_kind].text, "thread draft");
+    }
+
+    #[test]
+    fn reply_without_text_is_not_empty() {
+        let mut reply_draft = draft(None, "");
+        assert!(reply_draft.is_empty());
+        reply_draft.replying_to_event_id = Some("$event:example.org".try_into().unwrap());
+        assert!(!reply_draft.is_empty());
+    }
+}
@@ -2,20 +2,24 @@
 
 use std::sync::Arc;
 use makepad_widgets::ScriptVm;
-use matrix_sdk::{RoomDisplayName, RoomHero, RoomState, SuccessorRoom, room_preview::RoomPreview};
-use ruma::{OwnedRoomAliasId, OwnedRoomId, room::{JoinRuleSummary, RoomType}};
+use matrix_sdk::{OwnedServerName, RoomDisplayName, RoomHero, RoomState, SuccessorRoom, room_preview::RoomPreview};
+use ruma::{OwnedRoomAliasId, OwnedRoomId, OwnedRoomOrAliasId, room::{JoinRuleSummary, RoomType}};
 
 use crate::utils::RoomNameId;
 
+pub mod composer_drafts;
 pub mod member_search;
 pub mod reply_preview;
 pub mod room_input_bar;
 pub mod room_display_filter;
+pub mod sticker_picker;
+pub mod text_formatting;
 pub mod translation;
 pub mod typing_notice;
 
 pub fn script_mod(vm: &mut ScriptVm) {
     reply_preview::script_mod(vm);
+    sticker_picker::script_mod(vm);
     room_input_bar::script_mod(vm);
     typing_notice::script_mod(vm);
 }
@@ -88,13 +92,27 @@ impl BasicRoomDetails {
             Self::FetchedRoomPreview(frp) => &frp.room_avatar,
         }
     }
+
+    /// Returns the servers through which this room can be joined, if known.
+    ///
+    /// This is only non-empty for the `FetchedRoomPreview` variant.
+    pub fn via(&self) -> &[OwnedServerName] {
+        match self {
+            Self::FetchedRoomPreview(frp) => &frp.via,
+            _ => &[],
+        }
+    }
 }
 
 
 /// Actions related to room previews being fetched.
 #[derive(Debug)]
 pub enum RoomPreviewAction {
-    Fetched(Result<FetchedRoomPreview, matrix_sdk::Error>),
+    Fetched {
+        /// The room ID or alias whose preview was requested.
+        room_or_alias_id: OwnedRoomOrAliasId,
+        result: Result<FetchedRoomPreview, matrix_sdk::Error>,
+    },
 }
 
 /// A modified [`RoomPreview`], augmented with the room's fetched avatar.
@@ -130,9 +148,16 @@ pub struct FetchedRoomPreview {
     pub is_direct: Option<bool>,
     /// Room heroes.
     pub heroes: Option<Vec<RoomHero>>,
+    /// The servers that this preview was fetched through,
+    /// which can also be used to join the room if we're not yet in it.
+    pub via: Vec<OwnedServerName>,
 }
 impl FetchedRoomPreview {
-    pub fn from(room_preview: RoomPreview, room_avatar: FetchedRoomAvatar) -> Self {
+    pub fn from(
+        room_preview: RoomPreview,
+        room_avatar: FetchedRoomAvatar,
+        via: Vec<OwnedServerName>,
+    ) -> Self {
         let display_name = room_preview.name.map_or(
             RoomDisplayName::Empty,
             RoomDisplayName::Named,
@@ -150,6 +175,7 @@ impl FetchedRoomPreview {
             state: room_preview.state,
             is_direct: room_preview.is_direct,
             heroes: room_preview.heroes,
+            via,
         }
     }
 }
@@ -6,6 +6,7 @@
 //! * a preview of the message the user is replying to.
 //! * the location preview (which allows you to send your current location to the room),
 //!   and a location card to show the location preview.
+//! * A sticker picker, which sends stickers from the image packs available in the room.
 //! * If TSP is enabled, a checkbox to enable TSP signing for the outgoing message.
 //! * A MentionableTextInput, which allows the user to type a message
 //!   and mention other users via the `@` key.
@@ -21,7 +22,7 @@ use matrix_sdk::room::reply::{EnforceThread, Reply};
 use ruma::events::room::message::AddMentions;
 use matrix_sdk_ui::timeline::{EmbeddedEvent, EventTimelineItem, TimelineEventItemId};
 use ruma::{events::room::message::{LocationMessageEventContent, MessageType, ReplyWithinThread, RoomMessageEventContent}, OwnedRoomId, OwnedUserId, UserId};
-use crate::{app::AppState, home::{editing_pane::{EditingPaneState, EditingPaneWidgetExt, EditingPaneWidgetRefExt}, location_preview::{LocationPreviewWidgetExt, LocationPreviewWidgetRefExt}, room_screen::{MessageAction, RoomScreenProps, is_known_or_likely_bot, populate_preview_of_timeline_item}, tombstone_footer::{SuccessorRoomDetails, TombstoneFooterWidgetExt}, upload_progress::UploadProgressViewWidgetRefExt}, i18n::{AppLanguage, tr_fmt, tr_key}, location::init_location_subscriber, room::translation::{self, TRANSLATION_REQUEST_ID}, shared::{avatar::AvatarWidgetRefExt, file_upload_modal::{FileData, FileLoadedData, FilePreviewerAction}, html_or_plaintext::HtmlOrPlaintextWidgetRefExt, mentionable_text_input::{MentionableTextInputWidgetExt, classify_known_slash_command_for_submission, parse_command_with_at_suffix}, popup_list::{PopupKind, enqueue_popup_notification}, styles::*}, sliding_sync::{MatrixRequest, TimelineKind, UserPowerLevels, submit_async_request}, utils};
+use crate::{app::AppState, home::{editing_pane::{EditingPaneState, EditingPaneWidgetExt, EditingPaneWidgetRefExt}, schedule_message_modal::ScheduleMessageModalAction, location_preview::{LocationPreviewWidgetExt, LocationPreviewWidgetRefExt}, poll_composer::CreatePollModalAction, room_screen::{MessageAction, RoomScreenProps, is_known_or_likely_bot, populate_preview_of_timeline_item, populate_text_message_content}, search_messages::SearchMessagesModalAction, tombstone_footer::{SuccessorRoomDetails, TombstoneFooterWidgetExt}, upload_progress::{UploadProgressViewAction, UploadProgressViewWidgetRefExt}, unsent_messages_modal::UnsentMessagesModalAction}, i18n::{AppLanguage, tr_fmt, tr_key}, live_location, location::init_location_subscriber, room::{composer_drafts::ComposerDraft, sticker_picker::StickerPickerWidgetExt, text_formatting::TextFormat, translation::{self, TRANSLATION_REQUEST_ID}}, shared::{avatar::AvatarWidgetRefExt, emoji_picker::EmojiPickerWidgetExt, file_upload_modal::{FileData, FileLoadedData, FilePreviewerAction}, html_or_plaintext::HtmlOrPlaintextWidgetRefExt, mentionable_text_input::{MentionableTextInputWidgetExt, MentionableTextInputWidgetRefExt, MentionsState, classify_known_slash_command_for_submission, parse_command_with_at_suffix}, popup_list::{PopupKind, enqueue_popup_notification}, styles::*}, sliding_sync::{MatrixRequest, TimelineKind, UserPowerLevels, submit_async_request}, unsent_messages, utils};
 #[cfg(not(any(target_os = "ios", target_os = "android")))]
 use crate::shared::file_upload_modal::{FilePreviewerMetaData, ThumbnailData};
 
@@ -510,10 +511,39 @@ script_mod! {
     use mod.widgets.*
 
 
+    mod.widgets.ICO_CLOCK = crate_resource("self://resources/icons/clock.svg")
     mod.widgets.ICO_LOCATION_PERSON = crate_resource("self://resources/icons/location-person.svg")
     mod.widgets.ICO_MENU = crate_resource("self://resources/icons/menu.svg")
+    mod.widgets.ICO_POLL = crate_resource("self://resources/icons/poll.svg")
+    mod.widgets.ICO_STICKER = crate_resource("self://resources/icons/sticker.svg")
     mod.widgets.ICO_THREADS = crate_resource("self://resources/icons/double_chat.svg")
     mod.widgets.ICO_TRANSLATE = crate_resource("self://resources/icons/translate.svg")
+    mod.widgets.ICO_TEXT_FORMAT = crate_resource("self://resources/icons/text_format.svg")
+    mod.widgets.ICO_EYE_CLOSED = crate_resource("self://resources/icons/eye_closed.svg")
+
+    // A small button in the formatting toolbar above the message input box.
+    mod.widgets.FormattingToolbarButton = RobrixIconButton {
+        width: Fit, height: 28
+        padding: Inset{left: 8, right: 8, top: 4, bottom: 4}
+        spacing: 0
+        align: Align{x: 0.5, y: 0.5}
+        draw_icon +: {
+            color: (COLOR_ACTIVE_PRIMARY_DARKER)
+        }
+        draw_bg +: {
+            color: (COLOR_BG_PREVIEW)
+            color_hover: #E0E8F0
+            color_down: #D0D8E8
+        }
+        draw_text +: {
+            color: (COLOR_TEXT)
+            color_hover: (COLOR_TEXT)
+            color_down: (COLOR_TEXT)
+            text_style: REGULAR_TEXT { font_size: 10.5 }
+        }
+        icon_walk: Walk{width: 15, height: 15}
+        text: ""
+    }
 
     mod.widgets.TranslationLangItem = View {
         width: Fill, height: 36
@@ -572,27 +602,6 @@ script_mod! {
         }
     }
 
-    mod.widgets.RoomEmojiButton = mod.widgets.RobrixIconButton {
-        spacing: 0
-        text: ""
-        margin: 0
-        padding: Inset{left: 8, right: 8, top: 6, bottom: 6}
-        icon_walk: Walk{width: 0, height: 0}
-        draw_text +: {
-            color: (COLOR_TEXT)
-            color_hover: (COLOR_TEXT)
-            color_down: (COLOR_TEXT)
-            text_style: MESSAGE_TEXT_STYLE { font_size: 15.0 }
-        }
-        draw_bg +: {
-            color: (COLOR_PRIMARY)
-            color_hover: #F4F7FC
-            color_down: #E8EEF8
-            border_size: 1.0
-            border_color: (COLOR_SECONDARY)
-        }
-    }
-
     mod.widgets.TargetChipButton = Button {
         width: Fit, height: Fit
         padding: Inset{left: 10, right: 10, top: 5, bottom: 5}
@@ -668,6 +677,48 @@ script_mod! {
         // Upload progress view (shown when a file upload is in progress)
         upload_progress_view := UploadProgressView { }
 
+        // A banner shown when messages in this room have failed to send.
+        unsent_messages_banner := RoundedView {
+            visible: false
+            width: Fill, height: Fit
+            flow: Right
+            padding: Inset{left: 12, right: 8, top: 6, bottom: 6}
+            align: Align{y: 0.5}
+            spacing: 8
+            show_bg: true
+            draw_bg +: {
+                color: (COLOR_BG_DANGER_RED)
+                border_radius: 4.0
+            }
+
+            Icon {
+                width: Fit, height: Fit
+                draw_icon +: {
+                    svg: (ICON_WARNING)
+                    color: (COLOR_FG_DANGER_RED)
+                }
+                icon_walk: Walk{width: 14, height: 14}
+            }
+
+            unsent_messages_label := Label {
+                width: Fill, height: Fit
+                flow: Flow.Right{wrap: true}
+                draw_text +: {
+                    color: (COLOR_FG_DANGER_RED)
+                    text_style: REGULAR_TEXT { font_size: 10 }
+                }
+                text: ""
+            }
+
+            view_unsent_messages_button := RobrixNegativeIconButton {
+                padding: Inset{top: 4, bottom: 4, left: 10, right: 10}
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 9.5 }
+                }
+                text: "View"
+            }
+        }
+
         // Translation preview: shows the translated text above the input bar.
         translation_preview := RoundedView {
             visible: false
@@ -850,26 +901,194 @@ script_mod! {
                         icon_walk: Walk{width: 20, height: 20}
                         text: "threads",
                     }
+
+                    search_card_button := RobrixIconButton {
+                        width: Fit
+                        align: Align{x: 0.0, y: 0.5}
+                        margin: Inset{top: 1, bottom: 1}
+                        padding: Inset{left: 10, right: 10, top: 8, bottom: 8}
+                        spacing: 8
+                        draw_icon +: {
+                            svg: (ICON_SEARCH)
+                            color: (COLOR_ACTIVE_PRIMARY_DARKER)
+                        },
+                        draw_bg +: {
+                            color: (COLOR_BG_PREVIEW)
+                            color_hover: #E0E8F0
+                            color_down: #D0D8E8
+                            border_size: 1.0
+                            border_color: (COLOR_SECONDARY)
+                        }
+                        draw_text +: {
+                            color: (COLOR_TEXT)
+                            color_hover: (COLOR_TEXT)
+                            color_down: (COLOR_TEXT)
+                            text_style: MESSAGE_TEXT_STYLE { font_size: 10.5 }
+                        }
+                        icon_walk: Walk{width: 20, height: 20}
+                        text: "search",
+                    }
+
+                    send_later_card_button := RobrixIconButton {
+                        width: Fit
+                        align: Align{x: 0.0, y: 0.5}
+                        margin: Inset{top: 1, bottom: 1}
+                        padding: Inset{left: 10, right: 10, top: 8, bottom: 8}
+                        spacing: 8
+                        draw_icon +: {
+                            svg: (mod.widgets.ICO_CLOCK)
+                            color: (COLOR_ACTIVE_PRIMARY_DARKER)
+                        },
+                        draw_bg +: {
+                            color: (COLOR_BG_PREVIEW)
+                            color_hover: #E0E8F0
+                            color_down: #D0D8E8
+                            border_size: 1.0
+                            border_color: (COLOR_SECONDARY)
+                        }
+                        draw_text +: {
+                            color: (COLOR_TEXT)
+                            color_hover: (COLOR_TEXT)
+                            color_down: (COLOR_TEXT)
+                            text_style: MESSAGE_TEXT_STYLE { font_size: 10.5 }
+                        }
+                        icon_walk: Walk{width: 20, height: 20}
+                        text: "send later",
+                    }
+
+                    poll_card_button := RobrixIconButton {
+                        width: Fit
+                        align: Align{x: 0.0, y: 0.5}
+                        margin: Inset{top: 1, bottom: 1}
+                        padding: Inset{left: 10, right: 10, top: 8, bottom: 8}
+                        spacing: 8
+                        draw_icon +: {
+                            svg: (mod.widgets.ICO_POLL)
+                            color: (COLOR_ACTIVE_PRIMARY_DARKER)
+                        },
+                        draw_bg +: {
+                            color: (COLOR_BG_PREVIEW)
+                            color_hover: #E0E8F0
+                            color_down: #D0D8E8
+                            border_size: 1.0
+                            border_color: (COLOR_SECONDARY)
+                        }
+                        draw_text +: {
+                            color: (COLOR_TEXT)
+                            color_hover: (COLOR_TEXT)
+                            color_down: (COLOR_TEXT)
+                            text_style: MESSAGE_TEXT_STYLE { font_size: 10.5 }
+                        }
+                        icon_walk: Walk{width: 20, height: 20}
+                        text: "poll",
+                    }
+
+                    sticker_card_button := RobrixIconButton {
+                        width: Fit
+                        align: Align{x: 0.0, y: 0.5}
+                        margin: Inset{top: 1, bottom: 1}
+                        padding: Inset{left: 10, right: 10, top: 8, bottom: 8}
+                        spacing: 8
+                        draw_icon +: {
+                            svg: (mod.widgets.ICO_STICKER)
+                            color: (COLOR_ACTIVE_PRIMARY_DARKER)
+                        },
+                        draw_bg +: {
+                            color: (COLOR_BG_PREVIEW)
+                            color_hover: #E0E8F0
+                            color_down: #D0D8E8
+                            border_size: 1.0
+                            border_color: (COLOR_SECONDARY)
+                        }
+                        draw_text +: {
+                            color: (COLOR_TEXT)
+                            color_hover: (COLOR_TEXT)
+                            color_down: (COLOR_TEXT)
+                            text_style: MESSAGE_TEXT_STYLE { font_size: 10.5 }
+                        }
+                        icon_walk: Walk{width: 20, height: 20}
+                        text: "sticker",
+                    }
                 }
 
+                sticker_picker := StickerPicker { }
+
                 emoji_picker_popup := View {
                     visible: false
-                    width: Fit
+                    width: Fill
                     height: Fit
+
+                    emoji_picker := EmojiPicker { }
+                }
+
+                // An optional toolbar for inserting Markdown formatting into the message.
+                formatting_toolbar := View {
+                    visible: false
+                    width: Fill, height: Fit
                     flow: Right{wrap: true}
-                    align: Align{x: 0.0, y: 0.5}
-                    margin: Inset{left: 5, top: 1, bottom: 1}
-                    padding: Inset{left: 0, right: 0, top: 0, bottom: 0}
-                    spacing: 6
+                    spacing: 2
+                    padding: Inset{left: 3, right: 3}
+                    align: Align{y: 0.5}
+
+                    format_bold_button := mod.widgets.FormattingToolbarButton {
+                        text: "B"
+                        draw_text +: { text_style: theme.font_bold { font_size: 10.5 } }
+                    }
+                    format_italic_button := mod.widgets.FormattingToolbarButton {
+                        text: "I"
+                        draw_text +: { text_style: theme.font_italic { font_size: 10.5 } }
+                    }
+                    format_strikethrough_button := mod.widgets.FormattingToolbarButton { text: "S" }
+                    format_code_button := mod.widgets.FormattingToolbarButton {
+                        text: "</>"
+                        draw_text +: { text_style: MESSAGE_CODE_TEXT_STYLE { font_size: 9.5 } }
+                    }
+                    format_code_block_button := mod.widgets.FormattingToolbarButton {
+                        text: "{ }"
+                        draw_text +: { text_style: MESSAGE_CODE_TEXT_STYLE { font_size: 9.5 } }
+                    }
+                    format_quote_button := mod.widgets.FormattingToolbarButton { text: ">" }
+                    format_bullet_list_button := mod.widgets.FormattingToolbarButton { text: "•" }
+                    format_numbered_list_button := mod.widgets.FormattingToolbarButton { text: "1." }
+                    format_link_button := mod.widgets.FormattingToolbarButton {
+                        draw_icon +: { svg: (ICON_LINK) }
+                    }
+                    format_spoiler_button := mod.widgets.FormattingToolbarButton {
+                        draw_icon +: { svg: (mod.widgets.ICO_EYE_CLOSED) }
+                    }
+
+                    View { width: Fill, height: 1 }
 
-                    emoji_smile_button := mod.widgets.RoomEmojiButton { text: "😀" }
-                    emoji_joy_button := mod.widgets.RoomEmojiButton { text: "😂" }
-                    emoji_thumbsup_button := mod.widgets.RoomEmojiButton { text: "👍" }
-                    emoji_heart_button := mod.widgets.RoomEmojiButton { text: "❤️" }
-                    emoji_fire_button := mod.widgets.RoomEmojiButton { text: "🔥" }
-                    emoji_party_button := mod.widgets.RoomEmojiButton { text: "🎉" }
-                    emoji_think_button := mod.widgets.RoomEmojiButton { text: "🤔" }
-                    emoji_clap_button := mod.widgets.RoomEmojiButton { text: "👏" }
+                    formatting_preview_button := mod.widgets.FormattingToolbarButton {
+                        text: "Preview"
+                    }
+                }
+
+                // A live preview of how the message will look once sent,
+                // rendered the same way as messages in the timeline.
+                formatting_preview := RoundedView {
+                    visible: false
+                    width: Fill, height: Fit
+                    flow: Down
+                    spacing: 4
+                    padding: Inset{left: 10, right: 10, top: 6, bottom: 8}
+                    margin: Inset{left: 3, right: 3}
+                    show_bg: true
+                    draw_bg +: {
+                        color: (COLOR_BG_PREVIEW)
+                        border_radius: 4.0
+                    }
+
+                    formatting_preview_label := Label {
+                        width: Fill, height: Fit
+                        draw_text +: {
+                            color: (TIMESTAMP_TEXT_COLOR)
+                            text_style: REGULAR_TEXT { font_size: 9 }
+                        }
+                        text: "Preview"
+                    }
+
+                    formatting_preview_body := HtmlOrPlaintext { }
                 }
 
                 input_row := View {
@@ -919,6 +1138,22 @@ script_mod! {
                         text: "",
                     }
 
+                    formatting_button := RobrixIconButton {
+                        margin: Inset{left: 1, right: 1, top: 4, bottom: 4}
+                        spacing: 0,
+                        draw_icon +: {
+                            svg: (mod.widgets.ICO_TEXT_FORMAT)
+                            color: (COLOR_ACTIVE_PRIMARY_DARKER)
+                        },
+                        draw_bg +: {
+                            color: (COLOR_BG_PREVIEW)
+                            color_hover: #xE0E8F0
+                            color_down: #xD0D8E8
+                        }
+                        icon_walk: Walk{width: 19, height: 19}
+                        text: "",
+                    }
+
                     translate_button := RobrixIconButton {
                         margin: Inset{left: 1, right: 1, top: 4, bottom: 4}
                         spacing: 0,
@@ -1110,6 +1345,14 @@ pub struct RoomInputBar {
     #[rust] is_lang_popup_visible: bool,
     /// Cached translation config, updated from AppState when translation is activated.
     #[rust] translation_config: Option<translation::TranslationConfig>,
+
+    // --- Formatting state ---
+    /// Whether the formatting toolbar is shown above the message input box.
+    #[rust] is_formatting_toolbar_shown: bool,
+    /// Whether the live preview of the formatted message is shown.
+    #[rust] is_formatting_preview_shown: bool,
+    /// The message text that the formatting preview currently shows the rendered form of.
+    #[rust] formatting_preview_source: Option<String>,
 }
 
 impl Widget for RoomInputBar {
@@ -1320,6 +1563,22 @@ impl Widget for RoomInputBar {
         self.button(cx, ids!(bot_menu_button))
             .set_visible(cx, room_screen_props.is_some_and(is_management_bot_room));
 
+        if self.is_formatting_preview_shown {
+            self.update_formatting_preview(cx);
+        }
+
+        // Show how many messages in this room failed to send, if any.
+        let num_failed = room_screen_props.map_or(0, |props|
+            unsent_messages::num_failed_messages(Some(props.timeline_kind.room_id()))
+        );
+        self.view.view(cx, ids!(unsent_messages_banner)).set_visible(cx, num_failed > 0);
+        if num_failed > 0 {
+            self.view.label(cx, ids!(unsent_messages_label)).set_text(
+                cx,
+                &tr_fmt(self.app_language, "room_input_bar.unsent_messages.text", &[("count", &num_failed.to_string())]),
+            );
+        }
+
         self.view.draw_walk(cx, scope, walk)
     }
 }
@@ -1347,6 +1606,13 @@ impl RoomInputBar {
             .set_empty_text(cx, tr_key(self.app_language, "room_input_bar.input.placeholder").to_string());
         self.button(cx, ids!(translation_apply_button))
             .set_text(cx, tr_key(self.app_language, "room_input_bar.translation.preview.apply"));
+        self.button(cx, ids!(view_unsent_messages_button))
+            .set_text(cx, tr_key(self.app_language, "room_input_bar.unsent_messages.view"));
+        self.button(cx, ids!(formatting_preview_button))
+            .set_text(cx, tr_key(self.app_language, "room_input_bar.formatting.preview.button"));
+        self.view.label(cx, ids!(formatting_preview_label))
+            .set_text(cx, tr_key(self.app_language, "room_input_bar.formatting.preview.label"));
+        self.formatting_preview_source = None;
         if self.translation_active {
             if self.translation_request_pending {
                 self.view
@@ -1401,6 +1667,12 @@ impl RoomInputBar {
             || text_input.escaped(actions)
         {
             self.clear_replying_to(cx);
+            if let Some(room_screen_props) = scope.props.get::<RoomScreenProps>() {
+                cx.widget_action(
+                    room_screen_props.room_screen_widget_uid,
+                    MessageAction::ReplyCanceled,
+                );
+            }
             self.redraw(cx);
         }
 
@@ -1414,6 +1686,9 @@ impl RoomInputBar {
         // Handle the emoji picker button being clicked.
         if self.button(cx, ids!(emoji_picker_button)).clicked(actions) {
             self.is_emoji_picker_expanded = !self.is_emoji_picker_expanded;
+            if self.is_emoji_picker_expanded {
+                self.view.emoji_picker(cx, ids!(emoji_picker)).reset(cx);
+            }
             self.view.view(cx, ids!(emoji_picker_popup)).set_visible(cx, self.is_emoji_picker_expanded);
             self.redraw(cx);
         }
@@ -1445,29 +1720,33 @@ impl RoomInputBar {
             return;
         };
 
-        let picked_emoji = if self.button(cx, ids!(emoji_smile_button)).clicked(actions) {
-            Some("😀")
-        } else if self.button(cx, ids!(emoji_joy_button)).clicked(actions) {
-            Some("😂")
-        } else if self.button(cx, ids!(emoji_thumbsup_button)).clicked(actions) {
-            Some("👍")
-        } else if self.button(cx, ids!(emoji_heart_button)).clicked(actions) {
-            Some("❤️")
-        } else if self.button(cx, ids!(emoji_fire_button)).clicked(actions) {
-            Some("🔥")
-        } else if self.button(cx, ids!(emoji_party_button)).clicked(actions) {
-            Some("🎉")
-        } else if self.button(cx, ids!(emoji_think_button)).clicked(actions) {
-            Some("🤔")
-        } else if self.button(cx, ids!(emoji_clap_button)).clicked(actions) {
-            Some("👏")
-        } else {
-            None
-        };
+        // Handle the button to view this room's unsent messages being clicked.
+        if self.button(cx, ids!(view_unsent_messages_button)).clicked(actions) {
+            cx.action(UnsentMessagesModalAction::Open {
+                room_id: Some(room_screen_props.timeline_kind.room_id().clone()),
+            });
+        }
+
+        // Handle a failed file upload being retried from the upload progress view.
+        let upload_progress_view_uid = self.view.child_by_path(ids!(upload_progress_view)).widget_uid();
+        for action in actions {
+            if let UploadProgressViewAction::Retry { file_data, replied_to } = action.as_widget_action().widget_uid_eq(upload_progress_view_uid).cast_ref() {
+                self.child_by_path(ids!(upload_progress_view))
+                    .as_upload_progress_view()
+                    .show(cx, &file_data.name);
+                submit_async_request(MatrixRequest::SendAttachment {
+                    timeline_kind: room_screen_props.timeline_kind.clone(),
+                    file_data: file_data.clone(),
+                    replied_to: replied_to.clone(),
+                    #[cfg(feature = "tsp")]
+                    sign_with_tsp: self.is_tsp_signing_enabled(cx),
+                });
+            }
+        }
 
-        if let Some(emoji) = picked_emoji {
+        if let Some(emoji) = self.view.emoji_picker(cx, ids!(emoji_picker)).picked(actions) {
             let mut text = mentionable_text_input.text();
-            text.push_str(emoji);
+            text.push_str(&emoji);
             mentionable_text_input.set_text(cx, &text);
             self.enable_send_message_button(cx, !text.trim().is_empty());
             submit_async_request(MatrixRequest::SendTypingNotice {
@@ -1480,6 +1759,39 @@ impl RoomInputBar {
             self.redraw(cx);
         }
 
+        // Handle the formatting button being clicked — toggle the formatting toolbar.
+        if self.button(cx, ids!(formatting_button)).clicked(actions) {
+            self.is_formatting_toolbar_shown = !self.is_formatting_toolbar_shown;
+            self.view.view(cx, ids!(formatting_toolbar)).set_visible(cx, self.is_formatting_toolbar_shown);
+            if !self.is_formatting_toolbar_shown {
+                self.show_formatting_preview(cx, false);
+            }
+            self.text_input(cx, ids!(input_bar.input_row.mentionable_text_input.text_input)).set_key_focus(cx);
+            self.redraw(cx);
+        }
+
+        // Handle the formatting toolbar's buttons being clicked.
+        let format_buttons = [
+            (ids!(format_bold_button), TextFormat::Bold),
+            (ids!(format_italic_button), TextFormat::Italic),
+            (ids!(format_strikethrough_button), TextFormat::Strikethrough),
+            (ids!(format_code_button), TextFormat::InlineCode),
+            (ids!(format_code_block_button), TextFormat::CodeBlock),
+            (ids!(format_quote_button), TextFormat::Quote),
+            (ids!(format_bullet_list_button), TextFormat::BulletList),
+            (ids!(format_numbered_list_button), TextFormat::NumberedList),
+            (ids!(format_link_button), TextFormat::Link),
+            (ids!(format_spoiler_button), TextFormat::Spoiler),
+        ];
+        for (button_ids, format) in format_buttons {
+            if self.button(cx, button_ids).clicked(actions) {
+                mentionable_text_input.apply_text_format(cx, format);
+            }
+        }
+        if self.button(cx, ids!(formatting_preview_button)).clicked(actions) {
+            self.show_formatting_preview(cx, !self.is_formatting_preview_shown);
+        }
+
         // Handle the translate button being clicked — toggle language selector popup.
         if self.button(cx, ids!(translate_button)).clicked(actions) {
             if self.translation_active {
@@ -1545,7 +1857,7 @@ impl RoomInputBar {
                     None,
                 );
             }
-            self.view.location_preview(cx, ids!(location_preview)).show();
+            self.view.location_preview(cx, ids!(location_preview)).show(cx);
             self.redraw(cx);
         }
 
@@ -1557,6 +1869,59 @@ impl RoomInputBar {
             self.redraw(cx);
         }
 
+        if self.button(cx, ids!(search_card_button)).clicked(actions) {
+            self.is_location_card_expanded = false;
+            self.view.view(cx, ids!(more_actions_popup)).set_visible(cx, false);
+            cx.action(SearchMessagesModalAction::Open {
+                room_name_id: Some(room_screen_props.room_name_id.clone()),
+            });
+            self.redraw(cx);
+        }
+
+        if self.button(cx, ids!(send_later_card_button)).clicked(actions) {
+            self.is_location_card_expanded = false;
+            self.view.view(cx, ids!(more_actions_popup)).set_visible(cx, false);
+            let text = mentionable_text_input.text().trim().to_string();
+            let content = mentionable_text_input.create_message_with_mentions_for_submission(&text);
+            cx.action(ScheduleMessageModalAction::Open {
+                timeline_kind: room_screen_props.timeline_kind.clone(),
+                text,
+                content: Box::new(content),
+            });
+            self.redraw(cx);
+        }
+
+        // Clear the message that was just scheduled via the "send later" card.
+        for action in actions {
+            if let Some(ScheduleMessageModalAction::DraftScheduled { timeline_kind }) = action.downcast_ref()
+                && timeline_kind == &room_screen_props.timeline_kind
+            {
+                mentionable_text_input.set_text(cx, "");
+                self.enable_send_message_button(cx, false);
+                self.redraw(cx);
+            }
+        }
+
+        if self.button(cx, ids!(poll_card_button)).clicked(actions) {
+            self.is_location_card_expanded = false;
+            self.view.view(cx, ids!(more_actions_popup)).set_visible(cx, false);
+            cx.action(CreatePollModalAction::Open {
+                timeline_kind: room_screen_props.timeline_kind.clone(),
+            });
+            self.redraw(cx);
+        }
+
+        if self.button(cx, ids!(sticker_card_button)).clicked(actions) {
+            self.is_location_card_expanded = false;
+            self.view.view(cx, ids!(more_actions_popup)).set_visible(cx, false);
+            self.sticker_picker(cx, ids!(sticker_picker)).toggle(
+                cx,
+                room_screen_props.timeline_kind.room_id().clone(),
+                self.app_language,
+            );
+            self.redraw(cx);
+        }
+
         if self.button(cx, ids!(room_info_card_button)).clicked(actions) {
             cx.widget_action(
                 room_screen_props.room_screen_widget_uid,
@@ -1620,6 +1985,18 @@ impl RoomInputBar {
             }
         }
 
+        // Handle one of the live location duration buttons being clicked.
+        let location_preview = self.location_preview(cx, ids!(location_preview));
+        if let Some(duration) = location_preview.live_location_duration_clicked(cx, actions) {
+            live_location::start_live_location_share(
+                cx,
+                room_screen_props.timeline_kind.room_id().clone(),
+                duration,
+            );
+            location_preview.clear();
+            location_preview.redraw(cx);
+        }
+
         let submitted_text = text_input
             .returned(actions)
             .and_then(|(text, modifiers)| {
@@ -1900,6 +2277,58 @@ impl RoomInputBar {
         }
     }
 
+    /// Shows or hides the live preview of the formatted message.
+    fn show_formatting_preview(&mut self, cx: &mut Cx, show: bool) {
+        self.is_formatting_preview_shown = show;
+        // Force the preview to be re-rendered the next time it's drawn.
+        self.formatting_preview_source = None;
+        self.view.view(cx, ids!(formatting_preview)).set_visible(cx, show);
+        let mut preview_button = self.view.button(cx, ids!(formatting_preview_button));
+        if show {
+            apply_primary_button_style(cx, &mut preview_button);
+        } else {
+            script_apply_eval!(cx, preview_button, {
+                draw_bg +: {
+                    color: mod.widgets.COLOR_BG_PREVIEW,
+                    color_hover: #E0E8F0,
+                    color_down: #D0D8E8,
+                }
+                draw_text +: {
+                    color: mod.widgets.COLOR_TEXT,
+                    color_hover: mod.widgets.COLOR_TEXT,
+                    color_down: mod.widgets.COLOR_TEXT,
+                }
+            });
+        }
+        self.redraw(cx);
+    }
+
+    /// Re-renders the formatting preview if the message text has changed since it was last rendered.
+    ///
+    /// The preview shows the message exactly as it would be sent,
+    /// i.e., its `formatted_body` (if any) rendered just like a message in the timeline.
+    fn update_formatting_preview(&mut self, cx: &mut Cx) {
+        let mentionable_text_input = self.mentionable_text_input(cx, ids!(mentionable_text_input));
+        let text = mentionable_text_input.text();
+        if self.formatting_preview_source.as_ref() == Some(&text) {
+            return;
+        }
+        let preview_body = self.view.html_or_plaintext(cx, ids!(formatting_preview_body));
+        if text.trim().is_empty() {
+            preview_body.show_plaintext(cx, tr_key(self.app_language, "room_input_bar.formatting.preview.empty"));
+        } else {
+            let content = mentionable_text_input.create_message_with_mentions(&text);
+            let (body, formatted) = match &content.msgtype {
+                MessageType::Text(text) => (text.body.as_str(), text.formatted.as_ref()),
+                MessageType::Notice(notice) => (notice.body.as_str(), notice.formatted.as_ref()),
+                MessageType::Emote(emote) => (emote.body.as_str(), emote.formatted.as_ref()),
+                other => (other.body(), None),
+            };
+            populate_text_message_content(cx, &preview_body, self.app_language, body, formatted, None, None, None);
+        }
+        self.formatting_preview_source = Some(text);
+    }
+
     /// Sets the send_message_button to be shown/enabled and green, or hidden/disabled and gray.
     ///
     /// This should be called to update the button state when the message TextInput content changes.
@@ -1969,6 +2398,11 @@ impl RoomInputBar {
     ) {
         let can_send = user_power_levels.can_send_message();
         self.view.view(cx, ids!(input_bar)).set_visible(cx, can_send);
+        let can_send_sticker = user_power_levels.can_send_sticker();
+        self.button(cx, ids!(sticker_card_button)).set_visible(cx, can_send_sticker);
+        if !can_send_sticker {
+            self.sticker_picker(cx, ids!(sticker_picker)).hide(cx);
+        }
         self.view.view(cx, ids!(can_not_send_message_notice)).set_visible(cx, !can_send);
     }
 
@@ -2161,9 +2595,26 @@ impl RoomInputBarRef {
             replying_to: inner.replying_to.clone(),
             editing_pane_state: inner.child_by_path(ids!(editing_pane)).as_editing_pane().save_state(),
             text_input_state: inner.child_by_path(ids!(input_bar.input_row.mentionable_text_input.text_input)).as_text_input().save_state(),
+            mentions_state: inner.child_by_path(ids!(input_bar.input_row.mentionable_text_input)).as_mentionable_text_input().mentions_state(),
+            draft_text: None,
         }
     }
 
+    /// Returns the current contents of this `RoomInputBar`'s message composer
+    /// as a draft for the given timeline, such that it can be persisted.
+    pub fn composer_draft(&self, timeline_kind: &TimelineKind) -> ComposerDraft {
+        let mut draft = ComposerDraft::new(timeline_kind);
+        let Some(inner) = self.borrow() else { return draft };
+        let mentionable_text_input = inner
+            .child_by_path(ids!(input_bar.input_row.mentionable_text_input))
+            .as_mentionable_text_input();
+        draft.text = mentionable_text_input.text();
+        draft.mentions = mentionable_text_input.mentions_state();
+        draft.replying_to_event_id = inner.replying_to.as_ref()
+            .and_then(|(event_tl_item, _)| event_tl_item.event_id().map(ToOwned::to_owned));
+        draft
+    }
+
     /// Restore the UI state of this `RoomInputBar` from the given state snapshot.
     pub fn restore_state(
         &self,
@@ -2179,6 +2630,8 @@ impl RoomInputBarRef {
             text_input_state,
             replying_to,
             editing_pane_state,
+            mentions_state,
+            draft_text,
         } = saved_state;
 
         // Note: we do *not* restore the location preview state here; see `save_state()`.
@@ -2191,6 +2644,11 @@ impl RoomInputBarRef {
         // 1. Restore the state of the TextInput within the MentionableTextInput.
         inner.text_input(cx, ids!(input_bar.input_row.mentionable_text_input.text_input))
             .restore_state(cx, text_input_state);
+        let mentionable_text_input = inner.mentionable_text_input(cx, ids!(input_bar.input_row.mentionable_text_input));
+        if let Some(draft_text) = draft_text {
+            mentionable_text_input.set_text(cx, &draft_text);
+        }
+        mentionable_text_input.restore_mentions_state(mentions_state);
         let is_text_input_empty = inner.text_input(cx, ids!(input_bar.input_row.mentionable_text_input.text_input))
             .text()
             .is_empty();
@@ -2261,11 +2719,20 @@ impl RoomInputBarRef {
     }
 
     /// Shows an upload error with retry option.
-    pub fn show_upload_error(&self, cx: &mut Cx, error: &str, file_data: FileData) {
+    ///
+    /// The given `replied_to` is the reply metadata that the failed upload was sent with,
+    /// which is re-used if the upload is retried.
+    pub fn show_upload_error(
+        &self,
+        cx: &mut Cx,
+        error: &str,
+        file_data: FileData,
+        replied_to: Option<matrix_sdk::room::reply::Reply>,
+    ) {
         let Some(inner) = self.borrow() else { return };
         inner.child_by_path(ids!(upload_progress_view))
             .as_upload_progress_view()
-            .show_error(cx, error, file_data);
+            .show_error(cx, error, file_data, replied_to);
     }
 
     /// Handles a confirmed file upload from the file upload modal.
@@ -2273,8 +2740,14 @@ impl RoomInputBarRef {
     /// This method:
     /// - Shows the upload progress view
     /// - Gets and clears any "replying to" state
-    /// - Returns the reply metadata needed to submit the upload request
-    pub fn handle_file_upload_confirmed(&self, cx: &mut Cx, file_name: &str) -> Option<Option<matrix_sdk::room::reply::Reply>> {
+    /// - Returns the reply metadata needed to submit the upload request,
+    ///   which falls back to the thread root if the upload is sent within a thread
+    pub fn handle_file_upload_confirmed(
+        &self,
+        cx: &mut Cx,
+        file_name: &str,
+        timeline_kind: &TimelineKind,
+    ) -> Option<Option<matrix_sdk::room::reply::Reply>> {
         use matrix_sdk::room::reply::{EnforceThread, Reply};
 
         let mut inner = self.borrow_mut()?;
@@ -2286,10 +2759,19 @@ impl RoomInputBarRef {
             .and_then(|(event_tl_item, _embedded_event)| {
                 event_tl_item.event_id().map(|event_id| Reply {
                     event_id: event_id.to_owned(),
-                    enforce_thread: EnforceThread::MaybeThreaded,
-                    add_mentions: ruma::events::room::message::AddMentions::Yes,
+                    enforce_thread: if timeline_kind.thread_root_event_id().is_some() {
+                        EnforceThread::Threaded(ReplyWithinThread::Yes)
+                    } else {
+                        EnforceThread::MaybeThreaded
+                    },
+                    add_mentions: AddMentions::Yes,
                 })
-            });
+            })
+            .or_else(|| timeline_kind.thread_root_event_id().map(|thread_root_event_id| Reply {
+                event_id: thread_root_event_id.clone(),
+                enforce_thread: EnforceThread::Threaded(ReplyWithinThread::No),
+                add_mentions: AddMentions::No,
+            }));
 
         // Show the upload progress view
         inner.child_by_path(ids!(upload_progress_view))
@@ -2340,6 +2822,23 @@ pub struct RoomInputBarState {
     replying_to: Option<(EventTimelineItem, EmbeddedEvent)>,
     /// The state of the `EditingPane`, if any message was being edited.
     editing_pane_state: Option<EditingPaneState>,
+    /// The mentions within the text of the `mentionable_text_input`.
+    mentions_state: MentionsState,
+    /// The text of a persisted draft, which is restored instead of the `text_input_state`.
+    draft_text: Option<String>,
+}
+impl RoomInputBarState {
+    /// Creates the initial state of a `RoomInputBar` from a draft persisted in a prior session.
+    ///
+    /// The draft's reply target is not included here, as its event must first be found
+    /// in the timeline; see `RoomInputBarRef::show_replying_to()`.
+    pub fn from_draft(draft: ComposerDraft) -> Self {
+        Self {
+            mentions_state: draft.mentions,
+            draft_text: Some(draft.text),
+            ..Default::default()
+        }
+    }
 }
 
 /// Defines what to do when showing the `EditingPane` from the `RoomInputBar`.
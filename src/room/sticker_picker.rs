@@ -0,0 +1,446 @@
+//! A picker that shows the stickers from the image packs available in a room,
+//! and sends the selected sticker to that room.
+//!
+//! The sticker packs are fetched via [`MatrixRequest::FetchImagePacks`] each time
+//! the picker is opened, and their thumbnails are fetched through a [`MediaCache`].
+
+use std::sync::Arc;
+
+use makepad_widgets::*;
+use matrix_sdk::ruma::{OwnedRoomId, events::room::ImageInfo};
+
+use crate::{
+    LivePtr, widget_ref_from_live_ptr,
+    home::room_screen::{MessageAction, RoomScreenProps},
+    i18n::{AppLanguage, tr_key},
+    image_packs::{ImagePack, ImagePackSource, ImagePacksAction, PackImage},
+    media_cache::{MediaCache, MediaCacheEntry},
+    shared::styles::*,
+    sliding_sync::{MatrixRequest, submit_async_request},
+    utils::{self, AVATAR_THUMBNAIL_FORMAT},
+};
+
+const COLOR_STICKER_BG: Vec4 = vec4(0.0, 0.0, 0.0, 0.0);
+const COLOR_STICKER_BG_HOVER: Vec4 = COLOR_BG_PREVIEW;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.StickerGrid = #(StickerGrid::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Flow.Right{wrap: true},
+        spacing: 4.0
+
+        sticker: RoundedView {
+            width: 64,
+            height: 64,
+            padding: 4,
+            align: Align{x: 0.5, y: 0.5}
+            cursor: MouseCursor.Hand
+            show_bg: true
+            draw_bg +: {
+                color: #0000
+                border_radius: 4.0
+            }
+
+            image := Image {
+                width: Fill,
+                height: Fill,
+                fit: ImageFit.Smallest,
+            }
+
+            // Shown instead of the image if its thumbnail could not be loaded.
+            fallback_label := Label {
+                visible: false
+                width: Fill,
+                height: Fit,
+                align: Align{x: 0.5}
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 8.5 }
+                    color: (COLOR_TEXT)
+                    wrap: Word
+                }
+                text: ""
+            }
+        }
+    }
+
+    mod.widgets.StickerPackList = #(StickerPackList::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 8.0
+
+        pack: View {
+            width: Fill,
+            height: Fit,
+            flow: Down,
+            spacing: 4.0
+
+            pack_name := Label {
+                width: Fill,
+                height: Fit,
+                draw_text +: {
+                    text_style: TITLE_TEXT { font_size: 9.5 }
+                    color: (COLOR_TEXT)
+                }
+                text: ""
+            }
+
+            stickers := mod.widgets.StickerGrid { }
+        }
+    }
+
+    mod.widgets.StickerPicker = #(StickerPicker::register_widget(vm)) {
+        visible: false
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 4.0
+        margin: Inset{left: 5, right: 5, top: 1, bottom: 1}
+
+        status_label := Label {
+            width: Fill,
+            height: Fit,
+            draw_text +: {
+                text_style: REGULAR_TEXT { font_size: 10 }
+                color: (COLOR_TEXT)
+                wrap: Word
+            }
+            text: ""
+        }
+
+        sticker_scroll := ScrollYView {
+            visible: false
+            width: Fill,
+            height: 220,
+            flow: Down
+
+            packs := mod.widgets.StickerPackList { }
+        }
+    }
+}
+
+/// Widget actions emitted by a [`StickerGrid`].
+#[derive(Clone, Debug, Default)]
+pub enum StickerGridAction {
+    /// The given sticker was clicked or tapped.
+    Selected(PackImage),
+    #[default]
+    None,
+}
+
+/// A sticker shown in a [`StickerGrid`].
+struct StickerItem {
+    widget: WidgetRef,
+    image: PackImage,
+    /// Whether we're done trying to load this sticker's thumbnail,
+    /// regardless of whether it was loaded successfully.
+    is_thumbnail_done: bool,
+}
+
+/// A grid of the stickers in a single image pack.
+#[derive(Script, ScriptHook, Widget)]
+pub struct StickerGrid {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] sticker: Option<LivePtr>,
+    #[rust] children: Vec<StickerItem>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+}
+
+impl Widget for StickerGrid {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+        for item in self.children.iter() {
+            item.widget.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        for item in self.children.iter() {
+            // Note: the `break` statements are used to break out of the loop over
+            // all stickers, since a hit event can only occur on one sticker.
+            match event.hits(cx, item.widget.area()) {
+                Hit::FingerDown(_) => {
+                    cx.set_key_focus(item.widget.area());
+                    break;
+                }
+                Hit::FingerHoverIn(_) => {
+                    set_sticker_bg_color(cx, &item.widget, COLOR_STICKER_BG_HOVER);
+                    cx.set_cursor(MouseCursor::Hand);
+                    break;
+                }
+                Hit::FingerHoverOut(_) => {
+                    set_sticker_bg_color(cx, &item.widget, COLOR_STICKER_BG);
+                    break;
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.is_primary_hit() && fe.was_tap() => {
+                    set_sticker_bg_color(cx, &item.widget, COLOR_STICKER_BG);
+                    cx.widget_action(self.widget_uid(), StickerGridAction::Selected(item.image.clone()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl StickerGrid {
+    /// Replaces all stickers in this grid with the stickers from the given pack.
+    fn set_stickers(&mut self, cx: &mut Cx, pack: &ImagePack) {
+        self.children.clear();
+        for image in pack.stickers() {
+            let widget = widget_ref_from_live_ptr(cx, self.sticker);
+            self.children.push(StickerItem {
+                widget,
+                image: image.clone(),
+                is_thumbnail_done: false,
+            });
+        }
+    }
+
+    /// Shows the thumbnails of all stickers that have been fetched into the given `media_cache`,
+    /// fetching the thumbnails of any stickers that haven't yet been requested.
+    ///
+    /// Returns `true` if any sticker is still waiting for its thumbnail to be fetched.
+    fn load_thumbnails(&mut self, cx: &mut Cx, media_cache: &mut MediaCache) -> bool {
+        let mut is_any_pending = false;
+        for item in self.children.iter_mut().filter(|item| !item.is_thumbnail_done) {
+            let is_loaded = match media_cache.try_get_media_or_fetch(&item.image.url, AVATAR_THUMBNAIL_FORMAT.into()) {
+                (MediaCacheEntry::Loaded(data), _media_format) => {
+                    let image_ref = item.widget.image(cx, ids!(image));
+                    utils::load_png_or_jpg(&image_ref, cx, &data)
+                        .inspect_err(|e| error!("Failed to load sticker {:?} thumbnail: {e:?}", item.image.shortcode))
+                        .is_ok()
+                }
+                (MediaCacheEntry::Requested, _media_format) => {
+                    is_any_pending = true;
+                    continue;
+                }
+                (MediaCacheEntry::Failed(_status_code), _media_format) => false,
+            };
+            item.is_thumbnail_done = true;
+            if !is_loaded {
+                item.widget.image(cx, ids!(image)).set_visible(cx, false);
+                let fallback_label = item.widget.label(cx, ids!(fallback_label));
+                fallback_label.set_text(cx, &item.image.shortcode);
+                fallback_label.set_visible(cx, true);
+            }
+        }
+        self.redraw(cx);
+        is_any_pending
+    }
+}
+
+/// Sets the background color of the given sticker, e.g., to show that it's being hovered over.
+fn set_sticker_bg_color(cx: &mut Cx, sticker_ref: &WidgetRef, color: Vec4) {
+    let mut sticker_ref = sticker_ref.clone();
+    script_apply_eval!(cx, sticker_ref, {
+        draw_bg.color: #(color)
+    });
+}
+
+/// A list of image packs, each shown as its name above a grid of its stickers.
+#[derive(Script, ScriptHook, Widget)]
+pub struct StickerPackList {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] pack: Option<LivePtr>,
+    #[rust] children: Vec<WidgetRef>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+}
+
+impl Widget for StickerPackList {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+        for pack_ref in self.children.iter() {
+            pack_ref.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        for pack_ref in self.children.iter() {
+            pack_ref.handle_event(cx, event, scope);
+        }
+    }
+}
+
+impl StickerPackListRef {
+    /// Replaces all shown packs with the given packs, skipping packs that have no stickers.
+    fn set_packs(&self, cx: &mut Cx, packs: &[ImagePack], app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.children.clear();
+        for pack in packs.iter().filter(|pack| pack.stickers().next().is_some()) {
+            let pack_ref = widget_ref_from_live_ptr(cx, inner.pack);
+            let pack_name = if !pack.display_name.is_empty() {
+                pack.display_name.as_str()
+            } else if pack.source == ImagePackSource::User {
+                tr_key(app_language, "sticker_picker.pack.personal")
+            } else {
+                tr_key(app_language, "sticker_picker.pack.unnamed")
+            };
+            pack_ref.label(cx, ids!(pack_name)).set_text(cx, pack_name);
+            if let Some(mut grid) = pack_ref.sticker_grid(cx, ids!(stickers)).borrow_mut() {
+                grid.set_stickers(cx, pack);
+            }
+            inner.children.push(pack_ref);
+        }
+        inner.redraw(cx);
+    }
+
+    /// See [`StickerGrid::load_thumbnails()`].
+    fn load_thumbnails(&self, cx: &mut Cx, media_cache: &mut MediaCache) -> bool {
+        let Some(inner) = self.borrow() else { return false };
+        let mut is_any_pending = false;
+        for pack_ref in inner.children.iter() {
+            if let Some(mut grid) = pack_ref.sticker_grid(cx, ids!(stickers)).borrow_mut() {
+                is_any_pending |= grid.load_thumbnails(cx, media_cache);
+            }
+        }
+        is_any_pending
+    }
+}
+
+/// A picker that shows the stickers available in a room and sends the selected one.
+#[derive(Script, ScriptHook, Widget)]
+pub struct StickerPicker {
+    #[deref] view: View,
+    /// The room whose image packs are shown in this picker.
+    #[rust] room_id: Option<OwnedRoomId>,
+    /// The image packs available in the above room, if they have been fetched.
+    #[rust] packs: Option<Arc<Vec<ImagePack>>>,
+    #[rust] is_open: bool,
+    /// Whether any shown sticker is still waiting for its thumbnail to be fetched.
+    #[rust] has_pending_thumbnails: bool,
+    #[rust(MediaCache::new(None))] media_cache: MediaCache,
+    #[rust] app_language: AppLanguage,
+}
+
+impl Widget for StickerPicker {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Event::Actions(actions) = event {
+            for action in actions {
+                if let Some(ImagePacksAction::Fetched { room_id, packs }) = action.downcast_ref() {
+                    if self.room_id.as_ref() == Some(room_id) {
+                        self.show_packs(cx, Arc::clone(packs));
+                    }
+                }
+            }
+        }
+
+        // Fetched thumbnails are signaled to the UI thread by the media cache.
+        if let Event::Signal = event
+            && self.is_open
+            && self.has_pending_thumbnails
+        {
+            self.has_pending_thumbnails = self.view
+                .sticker_pack_list(cx, ids!(sticker_scroll.packs))
+                .load_thumbnails(cx, &mut self.media_cache);
+        }
+
+        let mut selected_sticker = None;
+        let mut picker_actions = cx.capture_actions(|cx| self.view.handle_event(cx, event, scope));
+        picker_actions.retain(|action| {
+            if let StickerGridAction::Selected(image) = action.as_widget_action().cast() {
+                selected_sticker = Some(image);
+                return false;
+            }
+            true
+        });
+        cx.extend_actions(picker_actions);
+
+        if let Some(image) = selected_sticker
+            && let Some(room_screen_props) = scope.props.get::<RoomScreenProps>()
+        {
+            submit_async_request(MatrixRequest::SendSticker {
+                timeline_kind: room_screen_props.timeline_kind.clone(),
+                body: image.body,
+                url: image.url,
+                info: image.info.unwrap_or_else(ImageInfo::new),
+            });
+            cx.widget_action(
+                room_screen_props.room_screen_widget_uid,
+                MessageAction::MessageSubmittedLocally,
+            );
+            self.hide(cx);
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl StickerPicker {
+    /// Shows this picker if it's hidden, or hides it if it's shown.
+    ///
+    /// Opening the picker (re-)fetches the image packs available in the given room.
+    fn toggle(&mut self, cx: &mut Cx, room_id: OwnedRoomId, app_language: AppLanguage) {
+        if self.is_open {
+            self.hide(cx);
+            return;
+        }
+        self.app_language = app_language;
+        if self.room_id.as_ref() != Some(&room_id) {
+            self.packs = None;
+            self.view.sticker_pack_list(cx, ids!(sticker_scroll.packs)).set_packs(cx, &[], app_language);
+        }
+        submit_async_request(MatrixRequest::FetchImagePacks { room_id: room_id.clone() });
+        self.room_id = Some(room_id);
+        self.is_open = true;
+        if self.packs.is_none() {
+            self.set_status(cx, Some(tr_key(app_language, "sticker_picker.status.loading")));
+        }
+        self.view.set_visible(cx, true);
+        self.redraw(cx);
+    }
+
+    /// Hides this picker.
+    fn hide(&mut self, cx: &mut Cx) {
+        self.is_open = false;
+        self.view.set_visible(cx, false);
+        self.redraw(cx);
+    }
+
+    /// Shows the stickers from the given image packs.
+    fn show_packs(&mut self, cx: &mut Cx, packs: Arc<Vec<ImagePack>>) {
+        let has_stickers = packs.iter().any(|pack| pack.stickers().next().is_some());
+        let pack_list = self.view.sticker_pack_list(cx, ids!(sticker_scroll.packs));
+        pack_list.set_packs(cx, &packs, self.app_language);
+        self.has_pending_thumbnails = pack_list.load_thumbnails(cx, &mut self.media_cache);
+        self.packs = Some(packs);
+        self.set_status(cx, (!has_stickers).then(|| tr_key(self.app_language, "sticker_picker.status.empty")));
+        self.view.view(cx, ids!(sticker_scroll)).set_visible(cx, has_stickers);
+        self.redraw(cx);
+    }
+
+    /// Shows the given status text in place of the stickers, or hides the status if `None`.
+    fn set_status(&mut self, cx: &mut Cx, status: Option<&str>) {
+        let status_label = self.view.label(cx, ids!(status_label));
+        status_label.set_text(cx, status.unwrap_or_default());
+        status_label.set_visible(cx, status.is_some());
+    }
+}
+
+impl StickerPickerRef {
+    /// See [`StickerPicker::toggle()`].
+    pub fn toggle(&self, cx: &mut Cx, room_id: OwnedRoomId, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.toggle(cx, room_id, app_language);
+    }
+
+    /// See [`StickerPicker::hide()`].
+    pub fn hide(&self, cx: &mut Cx) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.hide(cx);
+    }
+}
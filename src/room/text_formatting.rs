@@ -0,0 +1,259 @@
+//! Markdown formatting of the text in a room's message composer.
+//!
+//! Formatting is applied by inserting Markdown (or, for spoilers, inline HTML) syntax
+//! around the selected text or at the start of each selected line,
+//! such that the composer's text remains plain Markdown that the user can keep editing.
+
+use std::ops::Range;
+
+use makepad_widgets::{KeyCode, KeyEvent, KeyModifiers};
+
+
+/// A kind of formatting that can be applied to the selected text in the message composer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextFormat {
+    Bold,
+    Italic,
+    Strikethrough,
+    InlineCode,
+    CodeBlock,
+    Quote,
+    BulletList,
+    NumberedList,
+    Link,
+    Spoiler,
+}
+
+impl TextFormat {
+    /// Returns the format that the given key press is a keyboard shortcut for, if any.
+    ///
+    /// All shortcuts use the platform's primary modifier key (Cmd on Apple platforms, Ctrl elsewhere).
+    pub fn from_key_event(key_event: &KeyEvent) -> Option<Self> {
+        if !has_only_primary_modifier(&key_event.modifiers) {
+            return None;
+        }
+        let format = match (key_event.modifiers.shift, key_event.key_code) {
+            (false, KeyCode::KeyB) => Self::Bold,
+            (false, KeyCode::KeyI) => Self::Italic,
+            (true,  KeyCode::KeyX) => Self::Strikethrough,
+            (false, KeyCode::KeyE) => Self::InlineCode,
+            (true,  KeyCode::KeyE) => Self::CodeBlock,
+            (true,  KeyCode::Period) => Self::Quote,
+            (true,  KeyCode::Key8) => Self::BulletList,
+            (true,  KeyCode::Key7) => Self::NumberedList,
+            (false, KeyCode::KeyK) => Self::Link,
+            (true,  KeyCode::KeyH) => Self::Spoiler,
+            _ => return None,
+        };
+        Some(format)
+    }
+}
+
+/// Returns `true` if the platform's primary modifier key is the only modifier held down,
+/// ignoring Shift.
+fn has_only_primary_modifier(modifiers: &KeyModifiers) -> bool {
+    #[cfg(any(target_os = "ios", target_os = "macos", target_os = "tvos"))]
+    {
+        modifiers.logo && !modifiers.control && !modifiers.alt
+    }
+    #[cfg(not(any(target_os = "ios", target_os = "macos", target_os = "tvos")))]
+    {
+        modifiers.control && !modifiers.logo && !modifiers.alt
+    }
+}
+
+
+/// The result of applying a [`TextFormat`] to a composer's text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormattingEdit {
+    /// The strings to insert, each at a byte index into the original text, in ascending order.
+    pub insertions: Vec<(usize, String)>,
+    /// The byte range that should be selected in the new text after applying the edit.
+    pub new_selection: Range<usize>,
+}
+
+impl FormattingEdit {
+    /// Returns the given original text with this edit's insertions applied.
+    pub fn apply(&self, text: &str) -> String {
+        let mut new_text = String::with_capacity(
+            text.len() + self.insertions.iter().map(|(_, s)| s.len()).sum::<usize>()
+        );
+        let mut copied_up_to = 0;
+        for (index, inserted) in &self.insertions {
+            new_text.push_str(&text[copied_up_to..*index]);
+            new_text.push_str(inserted);
+            copied_up_to = *index;
+        }
+        new_text.push_str(&text[copied_up_to..]);
+        new_text
+    }
+
+    /// Maps the given byte range of the original text to its range in the new text,
+    /// or returns `None` if any insertion falls strictly within that range.
+    ///
+    /// This is used to keep track of mentions whose text isn't affected by the edit.
+    pub fn map_range(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let mut start = range.start;
+        let mut end = range.end;
+        for (index, inserted) in &self.insertions {
+            if *index > range.start && *index < range.end {
+                return None;
+            }
+            if *index <= range.start {
+                start += inserted.len();
+                end += inserted.len();
+            }
+        }
+        Some(start..end)
+    }
+}
+
+/// Computes how to apply the given format to the `selection` (a byte range) within `text`.
+///
+/// Inline formats wrap the selected text, whereas block formats prefix each selected line.
+/// If nothing is selected, inline formats insert an empty pair of markers
+/// with the cursor placed between them.
+pub fn format_selection(text: &str, selection: Range<usize>, format: TextFormat) -> FormattingEdit {
+    let start = floor_char_boundary(text, selection.start.min(selection.end));
+    let end = floor_char_boundary(text, selection.start.max(selection.end));
+    match format {
+        TextFormat::Bold => wrap(start, end, "**", "**"),
+        TextFormat::Italic => wrap(start, end, "*", "*"),
+        TextFormat::Strikethrough => wrap(start, end, "~~", "~~"),
+        TextFormat::InlineCode => wrap(start, end, "`", "`"),
+        TextFormat::Spoiler => wrap(start, end, "<span data-mx-spoiler>", "</span>"),
+        TextFormat::Link => {
+            const URL_PLACEHOLDER: &str = "https://";
+            let suffix = format!("]({URL_PLACEHOLDER})");
+            // Select the URL placeholder such that the user can type the link over it,
+            // unless there's no link text yet, in which case the user should type that first.
+            let new_selection = if start == end {
+                start + 1 .. start + 1
+            } else {
+                let url_start = end + 1 + 2;
+                url_start .. url_start + URL_PLACEHOLDER.len()
+            };
+            FormattingEdit {
+                insertions: vec![(start, "[".into()), (end, suffix)],
+                new_selection,
+            }
+        }
+        TextFormat::CodeBlock => {
+            // A code fence must be on its own line.
+            let needs_leading_newline = start > 0 && !text[..start].ends_with('\n');
+            let needs_trailing_newline = end < text.len() && !text[end..].starts_with('\n');
+            let opening = format!("{}```\n", if needs_leading_newline { "\n" } else { "" });
+            let closing = format!("\n```{}", if needs_trailing_newline { "\n" } else { "" });
+            let new_selection = start + opening.len() .. end + opening.len();
+            FormattingEdit {
+                insertions: vec![(start, opening), (end, closing)],
+                new_selection,
+            }
+        }
+        TextFormat::Quote => prefix_lines(text, start, end, |_| "> ".into()),
+        TextFormat::BulletList => prefix_lines(text, start, end, |_| "- ".into()),
+        TextFormat::NumberedList => prefix_lines(text, start, end, |i| format!("{}. ", i + 1)),
+    }
+}
+
+/// Wraps the text between `start` and `end` with the given `prefix` and `suffix`.
+fn wrap(start: usize, end: usize, prefix: &str, suffix: &str) -> FormattingEdit {
+    FormattingEdit {
+        insertions: vec![(start, prefix.into()), (end, suffix.into())],
+        new_selection: start + prefix.len() .. end + prefix.len(),
+    }
+}
+
+/// Inserts a prefix at the start of every line that overlaps the text between `start` and `end`.
+///
+/// The `prefix` closure is given the index of each line within the selection.
+fn prefix_lines(text: &str, start: usize, end: usize, prefix: impl Fn(usize) -> String) -> FormattingEdit {
+    let first_line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let mut line_starts = vec![first_line_start];
+    line_starts.extend(
+        text[start..end].match_indices('\n')
+            .map(|(i, _)| start + i + 1)
+            // Don't prefix a line that the selection merely ends at the start of.
+            .filter(|&line_start| line_start < end)
+    );
+    let insertions: Vec<(usize, String)> = line_starts.into_iter()
+        .enumerate()
+        .map(|(i, line_start)| (line_start, prefix(i)))
+        .collect();
+    let shift = |index: usize| index + insertions.iter()
+        .filter(|(line_start, _)| *line_start <= index)
+        .map(|(_, s)| s.len())
+        .sum::<usize>();
+    let new_selection = shift(start) .. shift(end);
+    FormattingEdit { insertions, new_selection }
+}
+
+/// Returns the largest char boundary in `text` that is less than or equal to `index`.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(text: &str, selection: Range<usize>, format: TextFormat) -> (String, String) {
+        let edit = format_selection(text, selection, format);
+        let new_text = edit.apply(text);
+        let selected = new_text[edit.new_selection.clone()].to_owned();
+        (new_text, selected)
+    }
+
+    #[test]
+    fn inline_formats_wrap_the_selection() {
+        assert_eq!(format("make this bold", 5..9, TextFormat::Bold), ("make **this** bold".into(), "this".into()));
+        assert_eq!(format("gone", 0..4, TextFormat::Strikethrough), ("~~gone~~".into(), "gone".into()));
+        assert_eq!(
+            format("secret", 0..6, TextFormat::Spoiler),
+            ("<span data-mx-spoiler>secret</span>".into(), "secret".into()),
+        );
+    }
+
+    #[test]
+    fn empty_selection_places_cursor_between_markers() {
+        let edit = format_selection("ab", 1..1, TextFormat::Italic);
+        assert_eq!(edit.apply("ab"), "a**b");
+        assert_eq!(edit.new_selection, 2..2);
+    }
+
+    #[test]
+    fn link_selects_url_placeholder() {
+        assert_eq!(format("see docs", 4..8, TextFormat::Link), ("see [docs](https://)".into(), "https://".into()));
+    }
+
+    #[test]
+    fn code_block_is_placed_on_its_own_lines() {
+        let (new_text, selected) = format("run cargo build now", 4..15, TextFormat::CodeBlock);
+        assert_eq!(new_text, "run \n```\ncargo build\n```\n now");
+        assert_eq!(selected, "cargo build");
+    }
+
+    #[test]
+    fn block_formats_prefix_each_selected_line() {
+        let text = "intro\nfirst\nsecond\nlast";
+        let (new_text, selected) = format(text, 8..17, TextFormat::NumberedList);
+        assert_eq!(new_text, "intro\n1. first\n2. second\nlast");
+        assert_eq!(selected, "rst\n2. secon");
+        assert_eq!(format("quote me", 3..3, TextFormat::Quote).0, "> quote me");
+        // A selection ending at the very start of a line doesn't include that line.
+        assert_eq!(format("a\nb", 0..2, TextFormat::BulletList).0, "- a\nb");
+    }
+
+    #[test]
+    fn map_range_shifts_or_drops_ranges() {
+        let edit = format_selection("hi @alice there", 0..15, TextFormat::Bold);
+        assert_eq!(edit.map_range(3..9), Some(5..11));
+        let edit = format_selection("hi @alice there", 5..7, TextFormat::Bold);
+        assert_eq!(edit.map_range(3..9), None);
+    }
+}
@@ -0,0 +1,282 @@
+//! Messages that are scheduled to be sent at a later time.
+//!
+//! If the homeserver supports [MSC4140] delayed events, a message scheduled in an unencrypted room
+//! is handed off to the homeserver, which sends it at the scheduled time even if Robrix isn't running.
+//! Delayed events can't be encrypted by the client ahead of time, so messages in encrypted rooms
+//! (and in rooms on homeservers without MSC4140 support) are instead sent by a local scheduler
+//! that runs on the UI thread, see [`due_local_messages()`].
+//!
+//! All pending scheduled messages are stored in the per-account `AppState`,
+//! such that they can be listed, edited, sent early, or cancelled.
+//!
+//! [MSC4140]: https://github.com/matrix-org/matrix-spec-proposals/pull/4140
+
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone};
+use makepad_widgets::{Cx, error, log};
+use matrix_sdk::{
+    Client,
+    ruma::{
+        MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedRoomId, OwnedTransactionId, TransactionId, UInt,
+        api::client::{
+            delayed_events::{DelayParameters, delayed_message_event, update_delayed_event::{self, unstable::UpdateAction}},
+            discovery::get_supported_versions,
+        },
+        events::room::message::RoomMessageEventContent,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::sliding_sync::get_room_timeline;
+
+/// The key of MSC4140 delayed events in the `unstable_features` of the homeserver's `/versions` response.
+const DELAYED_EVENTS_UNSTABLE_FEATURE: &str = "org.matrix.msc4140";
+
+/// A message that is scheduled to be sent to a room at a later time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    /// A unique ID for this scheduled message, which stays the same when it is edited.
+    pub id: OwnedTransactionId,
+    pub room_id: OwnedRoomId,
+    /// The root event of the thread that this message will be sent to, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_root_event_id: Option<OwnedEventId>,
+    /// The Markdown text that the user entered, which is used to preview and edit this message.
+    pub text: String,
+    /// The content of the message to be sent.
+    pub content: RoomMessageEventContent,
+    /// When this message should be sent.
+    pub send_at: MilliSecondsSinceUnixEpoch,
+    /// The ID of the delayed event on the homeserver, if this message was scheduled via MSC4140.
+    ///
+    /// If this is `None`, the message will be sent by Robrix's local scheduler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_id: Option<String>,
+    /// The error that occurred when the local scheduler last tried to send this message, if any.
+    ///
+    /// A message that failed to send isn't automatically retried; the user must send it manually.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl ScheduledMessage {
+    /// Returns whether this message will be sent by the homeserver rather than by Robrix.
+    pub fn is_scheduled_on_server(&self) -> bool {
+        self.delay_id.is_some()
+    }
+}
+
+/// Actions related to scheduled messages that are posted to the UI thread.
+#[derive(Clone, Debug)]
+pub enum ScheduledMessagesAction {
+    /// The given message was scheduled (or re-scheduled after being edited).
+    Scheduled(ScheduledMessage),
+    /// The scheduled message with the given ID was sent.
+    Sent { id: OwnedTransactionId },
+    /// The scheduled message with the given ID was cancelled.
+    Cancelled { id: OwnedTransactionId },
+    /// Scheduling, sending, or cancelling the scheduled message with the given ID failed.
+    Failed { id: OwnedTransactionId, error: String },
+}
+
+/// Returns the timestamp of the given date and time in the local time zone.
+pub fn local_timestamp(date: NaiveDate, time: NaiveTime) -> Option<MilliSecondsSinceUnixEpoch> {
+    let datetime = Local.from_local_datetime(&date.and_time(time)).earliest()?;
+    UInt::try_from(datetime.timestamp_millis()).ok().map(MilliSecondsSinceUnixEpoch)
+}
+
+/// Parses a time of day entered by the user, e.g., `9:30` or `21:05`.
+pub fn parse_time_of_day(text: &str) -> Option<NaiveTime> {
+    let (hour, minute) = text.trim().split_once(':')?;
+    NaiveTime::from_hms_opt(hour.trim().parse().ok()?, minute.trim().parse().ok()?, 0)
+}
+
+/// Returns the IDs of the messages that Robrix itself must send now,
+/// i.e., those scheduled locally whose time has come and that haven't already failed to send.
+pub fn due_local_messages(
+    messages: &[ScheduledMessage],
+    now: MilliSecondsSinceUnixEpoch,
+) -> Vec<OwnedTransactionId> {
+    messages.iter()
+        .filter(|m| !m.is_scheduled_on_server() && m.last_error.is_none() && m.send_at <= now)
+        .map(|m| m.id.clone())
+        .collect()
+}
+
+/// Schedules the given message, preferring to schedule it on the homeserver via MSC4140.
+///
+/// If `scheduled` was previously scheduled on the homeserver (i.e., it's being edited),
+/// its existing delayed event is cancelled first.
+///
+/// The result is posted as a [`ScheduledMessagesAction`].
+pub async fn schedule_message(client: Client, mut scheduled: ScheduledMessage) {
+    if let Some(delay_id) = scheduled.delay_id.take() {
+        if let Err(e) = update_delayed_event(&client, delay_id, UpdateAction::Cancel).await {
+            error!("Failed to cancel the delayed event of edited scheduled message {}: {e:?}", scheduled.id);
+            Cx::post_action(ScheduledMessagesAction::Failed { id: scheduled.id, error: e.to_string() });
+            return;
+        }
+    }
+
+    if can_schedule_on_server(&client, &scheduled).await {
+        match send_delayed_event(&client, &scheduled).await {
+            Ok(delay_id) => {
+                log!("Scheduled message {} on the homeserver as delayed event {delay_id}.", scheduled.id);
+                scheduled.delay_id = Some(delay_id);
+            }
+            // The local scheduler can still send this message, e.g., if its delay is too long for the homeserver.
+            Err(e) => error!("Failed to schedule message {} on the homeserver, scheduling it locally instead: {e:?}", scheduled.id),
+        }
+    }
+    Cx::post_action(ScheduledMessagesAction::Scheduled(scheduled));
+}
+
+/// Sends the given scheduled message immediately.
+///
+/// The result is posted as a [`ScheduledMessagesAction`].
+pub async fn send_scheduled_message_now(client: Client, scheduled: ScheduledMessage) {
+    let result = if let Some(delay_id) = scheduled.delay_id {
+        update_delayed_event(&client, delay_id, UpdateAction::Send).await
+    } else if let Some(timeline) = get_room_timeline(&scheduled.room_id) {
+        // Prefer the room's timeline, such that the message is shown as a local echo while being sent.
+        timeline.send(scheduled.content.into()).await
+            .map(|_send_handle| ())
+            .map_err(Into::into)
+    } else if let Some(room) = client.get_room(&scheduled.room_id) {
+        room.send(scheduled.content).await
+            .map(|_response| ())
+            .map_err(Into::into)
+    } else {
+        Err(anyhow::anyhow!("Room {} was not found.", scheduled.room_id))
+    };
+
+    match result {
+        Ok(()) => {
+            log!("Sent scheduled message {} to room {}.", scheduled.id, scheduled.room_id);
+            Cx::post_action(ScheduledMessagesAction::Sent { id: scheduled.id });
+        }
+        Err(e) => {
+            error!("Failed to send scheduled message {} to room {}: {e:?}", scheduled.id, scheduled.room_id);
+            Cx::post_action(ScheduledMessagesAction::Failed { id: scheduled.id, error: e.to_string() });
+        }
+    }
+}
+
+/// Cancels the given scheduled message, such that it will never be sent.
+///
+/// The result is posted as a [`ScheduledMessagesAction`].
+pub async fn cancel_scheduled_message(client: Client, scheduled: ScheduledMessage) {
+    if let Some(delay_id) = scheduled.delay_id {
+        if let Err(e) = update_delayed_event(&client, delay_id, UpdateAction::Cancel).await {
+            error!("Failed to cancel the delayed event of scheduled message {}: {e:?}", scheduled.id);
+            Cx::post_action(ScheduledMessagesAction::Failed { id: scheduled.id, error: e.to_string() });
+            return;
+        }
+    }
+    Cx::post_action(ScheduledMessagesAction::Cancelled { id: scheduled.id });
+}
+
+/// Returns whether the given message can be scheduled on the homeserver via MSC4140.
+///
+/// This requires the homeserver to support MSC4140, and the room to be known to be unencrypted,
+/// as the content of a delayed event is stored by the homeserver as-is.
+async fn can_schedule_on_server(client: &Client, scheduled: &ScheduledMessage) -> bool {
+    let is_unencrypted = client.get_room(&scheduled.room_id).is_some_and(|room| {
+        let encryption_state = room.encryption_state();
+        !encryption_state.is_unknown() && !encryption_state.is_encrypted()
+    });
+    if !is_unencrypted {
+        return false;
+    }
+    match client.send(get_supported_versions::Request::new()).await {
+        Ok(response) => response.unstable_features
+            .get(DELAYED_EVENTS_UNSTABLE_FEATURE)
+            .copied()
+            .unwrap_or(false),
+        Err(e) => {
+            error!("Failed to fetch the homeserver's supported features: {e:?}");
+            false
+        }
+    }
+}
+
+/// Sends the given message as a delayed event, returning the ID of that delayed event.
+async fn send_delayed_event(client: &Client, scheduled: &ScheduledMessage) -> anyhow::Result<String> {
+    let now = u64::from(MilliSecondsSinceUnixEpoch::now().get());
+    let send_at = u64::from(scheduled.send_at.get());
+    let delay = DelayParameters::Timeout {
+        timeout: Duration::from_millis(send_at.saturating_sub(now)),
+    };
+    // Each request needs a new transaction ID, as the same message may be re-scheduled after an edit.
+    let request = delayed_message_event::unstable::Request::new(
+        scheduled.room_id.clone(),
+        TransactionId::new(),
+        delay,
+        &scheduled.content,
+    )?;
+    let response = client.send(request).await?;
+    Ok(response.delay_id)
+}
+
+/// Sends, cancels, or restarts the delayed event with the given ID.
+async fn update_delayed_event(client: &Client, delay_id: String, action: UpdateAction) -> anyhow::Result<()> {
+    client.send(update_delayed_event::unstable::Request::new(delay_id, action)).await?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use matrix_sdk::ruma::{owned_room_id, uint};
+
+    use super::*;
+
+    fn scheduled(send_at: u64) -> ScheduledMessage {
+        ScheduledMessage {
+            id: TransactionId::new(),
+            room_id: owned_room_id!("!room:example.org"),
+            thread_root_event_id: None,
+            text: String::from("hello"),
+            content: RoomMessageEventContent::text_plain("hello"),
+            send_at: MilliSecondsSinceUnixEpoch(UInt::new(send_at).unwrap()),
+            delay_id: None,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn parse_time_of_day_accepts_hours_and_minutes() {
+        assert_eq!(parse_time_of_day("9:30"), NaiveTime::from_hms_opt(9, 30, 0));
+        assert_eq!(parse_time_of_day(" 21:05 "), NaiveTime::from_hms_opt(21, 5, 0));
+        assert_eq!(parse_time_of_day("24:00"), None);
+        assert_eq!(parse_time_of_day("12:60"), None);
+        assert_eq!(parse_time_of_day("noon"), None);
+        assert_eq!(parse_time_of_day("1230"), None);
+    }
+
+    #[test]
+    fn due_local_messages_skips_future_server_and_failed_messages() {
+        let due = scheduled(1_000);
+        let future = scheduled(3_000);
+        let mut on_server = scheduled(1_000);
+        on_server.delay_id = Some(String::from("syd_abc"));
+        let mut failed = scheduled(1_000);
+        failed.last_error = Some(String::from("offline"));
+
+        let messages = [due.clone(), future, on_server, failed];
+        let now = MilliSecondsSinceUnixEpoch(uint!(2_000));
+        assert_eq!(due_local_messages(&messages, now), vec![due.id]);
+    }
+
+    #[test]
+    fn scheduled_message_roundtrips_through_json() {
+        let message = scheduled(1_000);
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(!json.contains("delay_id"));
+        let parsed: ScheduledMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.id, message.id);
+        assert_eq!(parsed.content.body(), "hello");
+        assert_eq!(parsed.send_at, message.send_at);
+    }
+}
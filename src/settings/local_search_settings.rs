@@ -0,0 +1,270 @@
+use makepad_widgets::*;
+
+use crate::{
+    app::AppState,
+    i18n::{AppLanguage, tr_fmt, tr_key},
+    local_search_index::{self, LocalSearchIndexAction, LocalSearchIndexSize},
+    persistence,
+    shared::popup_list::{PopupKind, enqueue_popup_notification},
+    sliding_sync::current_user_id,
+    utils::format_file_size,
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.LocalSearchSettings = #(LocalSearchSettings::register_widget(vm)) {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: (SPACE_SM)
+
+        local_search_header := View {
+            width: Fill
+            height: Fit
+            flow: Down
+            spacing: (SPACE_XS)
+            margin: Inset{bottom: 2}
+
+            local_search_title := TitleLabel {
+                width: Fit
+                text: "Search Encrypted Rooms"
+            }
+
+            description := Label {
+                width: Fill
+                height: Fit
+                margin: 0
+                draw_text +: {
+                    color: (COLOR_DESCRIPTION_TEXT)
+                    text_style: REGULAR_TEXT { font_size: 9.5 }
+                }
+                text: "Build a search index of decrypted messages on this device, such that messages in encrypted rooms can be searched."
+            }
+        }
+
+        toggle_row := View {
+            width: Fill
+            height: Fit
+            flow: Right
+            align: Align{x: 0.0, y: 0.5}
+            spacing: (SPACE_XS)
+            padding: Inset{left: 6}
+            margin: Inset{bottom: 2}
+
+            local_search_switch := Toggle {
+                width: Fit
+                height: Fit
+                padding: Inset{top: (SPACE_SM), right: (SPACE_SM), bottom: (SPACE_SM), left: (SPACE_SM)}
+                text: ""
+                active: false
+                draw_bg +: {
+                    size: 20.0
+                    color_active: (COLOR_ACTIVE_PRIMARY)
+                    border_color_active: (COLOR_ACTIVE_PRIMARY)
+                    mark_color_active: #fff
+                }
+            }
+
+            switch_state_label := Label {
+                width: Fit
+                height: Fit
+                draw_text +: {
+                    color: (COLOR_DISABLED_TEXT)
+                    text_style: REGULAR_TEXT { font_size: 10.5 }
+                }
+                text: "Disabled"
+            }
+        }
+
+        View {
+            width: Fill, height: Fit
+            flow: Right
+            align: Align{y: 0.5}
+            spacing: (SPACE_SM)
+
+            index_size_label := Label {
+                width: Fill, height: Fit
+                draw_text +: {
+                    color: (COLOR_DESCRIPTION_TEXT)
+                    text_style: REGULAR_TEXT { font_size: 10 }
+                }
+                text: ""
+            }
+
+            purge_button := RobrixNegativeIconButton {
+                padding: Inset{top: 8, bottom: 8, left: 16, right: 16}
+                icon_walk: Walk{width: 0, height: 0}
+                spacing: 0
+                text: "Delete Index"
+            }
+        }
+    }
+}
+
+#[derive(Script, ScriptHook, Widget)]
+pub struct LocalSearchSettings {
+    #[deref]
+    view: View,
+    #[rust]
+    app_language: AppLanguage,
+    #[rust]
+    app_language_initialized: bool,
+}
+
+impl Widget for LocalSearchSettings {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        let app_language = scope.data.get::<AppState>()
+            .map(|app_state| app_state.app_language)
+            .unwrap_or_default();
+        if !self.app_language_initialized || self.app_language != app_language {
+            self.set_app_language(cx, app_language);
+        }
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        let app_language = scope.data.get::<AppState>()
+            .map(|app_state| app_state.app_language)
+            .unwrap_or_default();
+        if !self.app_language_initialized || self.app_language != app_language {
+            self.set_app_language(cx, app_language);
+        }
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for LocalSearchSettings {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        for action in actions {
+            match action.downcast_ref() {
+                Some(LocalSearchIndexAction::Size(size)) => {
+                    self.set_index_size_label(cx, *size);
+                    self.view.redraw(cx);
+                }
+                Some(LocalSearchIndexAction::Purged(Ok(()))) => enqueue_popup_notification(
+                    tr_key(self.app_language, "settings.labs.local_search.popup.purged").to_string(),
+                    PopupKind::Success,
+                    Some(4.0),
+                ),
+                Some(LocalSearchIndexAction::Purged(Err(e))) => {
+                    error!("Failed to delete the local search index. Error: {e}");
+                    enqueue_popup_notification(
+                        tr_fmt(self.app_language, "settings.labs.local_search.popup.purge_failed", &[("error", e.as_str())]),
+                        PopupKind::Error,
+                        None,
+                    );
+                }
+                None => {}
+            }
+        }
+
+        let Some(app_state) = scope.data.get_mut::<AppState>() else {
+            return;
+        };
+
+        if let Some(enabled) = self.view.check_box(cx, ids!(local_search_switch)).changed(actions) {
+            app_state.local_search_index_enabled = enabled;
+            local_search_index::set_enabled(enabled);
+            self.set_switch_state_label(cx, enabled);
+            if let Some(user_id) = current_user_id() {
+                if let Err(e) = persistence::save_app_state(app_state.clone(), user_id) {
+                    error!("Failed to persist local search index setting. Error: {e}");
+                }
+            }
+            self.view.redraw(cx);
+        }
+
+        if self.view.button(cx, ids!(purge_button)).clicked(actions) {
+            // The index worker handles these requests in order, so the size is computed after purging.
+            local_search_index::purge();
+            self.update_index_size_label();
+        }
+    }
+}
+
+impl LocalSearchSettings {
+    fn set_app_language(&mut self, cx: &mut Cx, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.app_language_initialized = true;
+        self.view
+            .label(cx, ids!(local_search_title))
+            .set_text(cx, tr_key(self.app_language, "settings.labs.local_search.title"));
+        self.view
+            .label(cx, ids!(description))
+            .set_text(cx, tr_key(self.app_language, "settings.labs.local_search.description"));
+        self.view
+            .button(cx, ids!(purge_button))
+            .set_text(cx, tr_key(self.app_language, "settings.labs.local_search.button.purge"));
+        self.set_switch_state_label(
+            cx,
+            self.view.check_box(cx, ids!(local_search_switch)).active(cx),
+        );
+        self.update_index_size_label();
+        self.view.redraw(cx);
+    }
+
+    fn set_switch_state_label(&mut self, cx: &mut Cx, enabled: bool) {
+        let mut switch_state_label = self.view.label(cx, ids!(switch_state_label));
+        if enabled {
+            script_apply_eval!(cx, switch_state_label, {
+                text: #(tr_key(self.app_language, "settings.labs.local_search.status.enabled")),
+                draw_text +: {
+                    color: mod.widgets.COLOR_ACTIVE_PRIMARY
+                }
+            });
+        } else {
+            script_apply_eval!(cx, switch_state_label, {
+                text: #(tr_key(self.app_language, "settings.labs.local_search.status.disabled")),
+                draw_text +: {
+                    color: #999
+                }
+            });
+        }
+    }
+
+    /// Requests the size of the local search index from the index worker thread,
+    /// because computing it may require loading the entire index from disk.
+    ///
+    /// Once done, the index size label is updated by handling
+    /// the [`LocalSearchIndexAction::Size`] action.
+    fn update_index_size_label(&self) {
+        local_search_index::request_index_size();
+    }
+
+    fn set_index_size_label(&mut self, cx: &mut Cx, size: LocalSearchIndexSize) {
+        self.view.label(cx, ids!(index_size_label)).set_text(
+            cx,
+            &tr_fmt(self.app_language, "settings.labs.local_search.size", &[
+                ("count", &size.message_count.to_string()),
+                ("size", &format_file_size(size.bytes_on_disk)),
+            ]),
+        );
+    }
+
+    /// Populates the local search settings UI from the current app state.
+    pub fn populate(&mut self, cx: &mut Cx, enabled: bool) {
+        local_search_index::set_enabled(enabled);
+        self.view.check_box(cx, ids!(local_search_switch)).set_active(cx, enabled);
+        self.set_switch_state_label(cx, enabled);
+        self.update_index_size_label();
+    }
+}
+
+impl LocalSearchSettingsRef {
+    pub fn populate(&self, cx: &mut Cx, enabled: bool) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.populate(cx, enabled);
+    }
+
+    pub fn set_app_language(&self, cx: &mut Cx, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.set_app_language(cx, app_language);
+    }
+}
@@ -0,0 +1,260 @@
+use makepad_widgets::*;
+
+use crate::{
+    app::AppState,
+    i18n::{AppLanguage, tr_key},
+    map_tiles::{self, MapTileConfig},
+    persistence,
+    shared::popup_list::{PopupKind, enqueue_popup_notification},
+    sliding_sync::current_user_id,
+};
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.MapTileSettings = #(MapTileSettings::register_widget(vm)) {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: (SPACE_SM)
+
+        map_tiles_title := TitleLabel {
+            text: "Map Tiles"
+            draw_text +: {
+                color: (COLOR_ACTIVE_PRIMARY)
+            }
+        }
+
+        description := Label {
+            width: Fill
+            height: Fit
+            margin: 0
+            draw_text +: {
+                color: (COLOR_DESCRIPTION_TEXT)
+                text_style: REGULAR_TEXT { font_size: 9.5 }
+            }
+            text: "When enabled, map previews of shared locations are drawn from this tile server, which will see the locations you view. Tiles are cached on this device, so maps can be shown offline once they've been loaded."
+        }
+
+        View {
+            width: Fill
+            height: Fit
+            flow: Right
+            align: Align{x: 0.0, y: 0.5}
+            spacing: (SPACE_XS)
+            padding: Inset{left: 6}
+
+            enabled_switch := Toggle {
+                width: Fit
+                height: Fit
+                padding: Inset{top: (SPACE_SM), right: (SPACE_SM), bottom: (SPACE_SM), left: (SPACE_SM)}
+                text: ""
+                active: false
+                draw_bg +: {
+                    size: 20.0
+                    color_active: (COLOR_ACTIVE_PRIMARY)
+                    border_color_active: (COLOR_ACTIVE_PRIMARY)
+                    mark_color_active: #fff
+                }
+            }
+
+            enabled_label := Label {
+                width: Fit
+                height: Fit
+                draw_text +: {
+                    color: (COLOR_FIELD_LABEL)
+                    text_style: REGULAR_TEXT { font_size: 10.5 }
+                }
+                text: "Show map previews"
+            }
+        }
+
+        View {
+            width: Fill, height: Fit
+            flow: Down, spacing: 4
+            tile_url_label := Label {
+                width: Fit, height: Fit
+                draw_text +: {
+                    color: (COLOR_FIELD_LABEL)
+                    text_style: REGULAR_TEXT { font_size: 10 }
+                }
+                text: "Tile URL template"
+            }
+            tile_url_input := RobrixTextInput {
+                width: Fill, height: Fit
+                padding: 8
+                empty_text: "https://tile.openstreetmap.org/{z}/{x}/{y}.png"
+            }
+        }
+
+        View {
+            width: Fill, height: Fit
+            flow: Down, spacing: 4
+            attribution_label := Label {
+                width: Fit, height: Fit
+                draw_text +: {
+                    color: (COLOR_FIELD_LABEL)
+                    text_style: REGULAR_TEXT { font_size: 10 }
+                }
+                text: "Attribution"
+            }
+            attribution_input := RobrixTextInput {
+                width: Fill, height: Fit
+                padding: 8
+                empty_text: "© OpenStreetMap contributors"
+            }
+        }
+
+        View {
+            width: Fill, height: Fit
+            flow: Right
+            spacing: (SPACE_SM)
+            margin: Inset{top: (SPACE_XS)}
+
+            save_button := RobrixIconButton {
+                padding: Inset{top: 8, bottom: 8, left: 16, right: 16}
+                icon_walk: Walk{width: 0, height: 0}
+                spacing: 0
+                text: "Save"
+            }
+
+            reset_button := RobrixNeutralIconButton {
+                padding: Inset{top: 8, bottom: 8, left: 16, right: 16}
+                icon_walk: Walk{width: 0, height: 0}
+                spacing: 0
+                text: "Reset to Default"
+            }
+        }
+    }
+}
+
+#[derive(Script, ScriptHook, Widget)]
+pub struct MapTileSettings {
+    #[deref]
+    view: View,
+    #[rust]
+    app_language: AppLanguage,
+}
+
+impl Widget for MapTileSettings {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for MapTileSettings {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let Some(app_state) = scope.data.get_mut::<AppState>() else {
+            return;
+        };
+
+        if let Some(enabled) = self.view.check_box(cx, ids!(enabled_switch)).changed(actions) {
+            app_state.map_tiles.enabled = enabled;
+            self.save_config(cx, app_state);
+        }
+
+        if self.view.button(cx, ids!(save_button)).clicked(actions) {
+            let tile_url_template = self.view.text_input(cx, ids!(tile_url_input)).text().trim().to_string();
+            let attribution = self.view.text_input(cx, ids!(attribution_input)).text().trim().to_string();
+            if !MapTileConfig::is_valid_url_template(&tile_url_template) {
+                enqueue_popup_notification(
+                    tr_key(self.app_language, "settings.preferences.map_tiles.validation.invalid_url"),
+                    PopupKind::Error,
+                    Some(5.0),
+                );
+                return;
+            }
+            app_state.map_tiles = MapTileConfig {
+                enabled: app_state.map_tiles.enabled,
+                tile_url_template,
+                attribution,
+            };
+            self.save_config(cx, app_state);
+        }
+
+        if self.view.button(cx, ids!(reset_button)).clicked(actions) {
+            // Resetting the tile server shouldn't change whether map previews are shown.
+            app_state.map_tiles = MapTileConfig {
+                enabled: app_state.map_tiles.enabled,
+                ..MapTileConfig::default()
+            };
+            self.populate(cx, &app_state.map_tiles);
+            self.save_config(cx, app_state);
+        }
+    }
+}
+
+impl MapTileSettings {
+    fn set_app_language(&mut self, cx: &mut Cx, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.view
+            .label(cx, ids!(map_tiles_title))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.title"));
+        self.view
+            .label(cx, ids!(description))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.description"));
+        self.view
+            .label(cx, ids!(enabled_label))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.field.enabled"));
+        self.view
+            .label(cx, ids!(tile_url_label))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.field.tile_url"));
+        self.view
+            .label(cx, ids!(attribution_label))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.field.attribution"));
+        self.view
+            .button(cx, ids!(save_button))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.button.save"));
+        self.view
+            .button(cx, ids!(reset_button))
+            .set_text(cx, tr_key(app_language, "settings.preferences.map_tiles.button.reset"));
+        self.view.redraw(cx);
+    }
+
+    /// Populates the map tile settings UI from the current app state.
+    pub fn populate(&mut self, cx: &mut Cx, config: &MapTileConfig) {
+        self.view.check_box(cx, ids!(enabled_switch))
+            .set_active(cx, config.enabled);
+        self.view.text_input(cx, ids!(tile_url_input))
+            .set_text(cx, &config.tile_url_template);
+        self.view.text_input(cx, ids!(attribution_input))
+            .set_text(cx, &config.attribution);
+        self.view.redraw(cx);
+    }
+
+    fn save_config(&mut self, cx: &mut Cx, app_state: &AppState) {
+        map_tiles::set_global_config(cx, &app_state.map_tiles);
+        if let Some(user_id) = current_user_id() {
+            if let Err(e) = persistence::save_app_state(app_state.clone(), user_id) {
+                error!("Failed to persist map tile settings. Error: {e}");
+            }
+        }
+        enqueue_popup_notification(
+            tr_key(self.app_language, "settings.preferences.map_tiles.popup.saved"),
+            PopupKind::Success,
+            Some(3.0),
+        );
+    }
+}
+
+impl MapTileSettingsRef {
+    pub fn populate(&self, cx: &mut Cx, config: &MapTileConfig) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.populate(cx, config);
+    }
+
+    pub fn set_app_language(&self, cx: &mut Cx, app_language: AppLanguage) {
+        let Some(mut inner) = self.borrow_mut() else {
+            return;
+        };
+        inner.set_app_language(cx, app_language);
+    }
+}
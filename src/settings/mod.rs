@@ -4,10 +4,14 @@ pub mod settings_screen;
 pub mod account_settings;
 pub mod bot_settings;
 pub mod translation_settings;
+pub mod local_search_settings;
+pub mod map_tile_settings;
 
 pub fn script_mod(vm: &mut ScriptVm) {
     account_settings::script_mod(vm);
     bot_settings::script_mod(vm);
     translation_settings::script_mod(vm);
+    local_search_settings::script_mod(vm);
+    map_tile_settings::script_mod(vm);
     settings_screen::script_mod(vm);
 }
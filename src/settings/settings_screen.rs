@@ -2,7 +2,7 @@
 use makepad_widgets::*;
 use url::Url;
 
-use crate::{app::{AppState, BotSettingsState}, home::navigation_tab_bar::{NavigationBarAction, get_own_profile}, i18n::{AppLanguage, I18nKey, language_dropdown_labels, tr, tr_fmt, tr_key}, persistence, profile::user_profile::UserProfile, settings::{account_settings::AccountSettingsWidgetExt, bot_settings::BotSettingsWidgetExt, translation_settings::TranslationSettingsWidgetExt}, shared::{expand_arrow::ExpandArrow, popup_list::{PopupKind, enqueue_popup_notification}, styles::{apply_neutral_button_style, apply_primary_button_style}}, sliding_sync::current_user_id, updater::{UpdateCheckOutcome, check_for_updates}};
+use crate::{app::{AppState, BotSettingsState}, home::navigation_tab_bar::{NavigationBarAction, get_own_profile}, i18n::{AppLanguage, I18nKey, language_dropdown_labels, tr, tr_fmt, tr_key}, persistence, profile::user_profile::UserProfile, settings::{account_settings::AccountSettingsWidgetExt, bot_settings::BotSettingsWidgetExt, local_search_settings::LocalSearchSettingsWidgetExt, map_tile_settings::MapTileSettingsWidgetExt, translation_settings::TranslationSettingsWidgetExt}, shared::{expand_arrow::ExpandArrow, popup_list::{PopupKind, enqueue_popup_notification}, styles::{apply_neutral_button_style, apply_primary_button_style}}, sliding_sync::current_user_id, updater::{UpdateCheckOutcome, check_for_updates}};
 
 const CONTRIBUTE_REPO_URL: &str = "https://github.com/Project-Robius-China/robrix2";
 
@@ -411,6 +411,10 @@ script_mod! {
                             margin: Inset{left: 5, top: 5, bottom: 4}
                             text: "Save Proxy"
                         }
+
+                        LineH { width: 400, padding: 10, margin: Inset{top: 12, bottom: 5} }
+
+                        map_tile_settings := MapTileSettings {}
                     }
                 }
 
@@ -449,6 +453,19 @@ script_mod! {
                             translation_settings := TranslationSettings {}
                         }
 
+                        // --- Local search index card ---
+                        RoundedView {
+                            width: Fill, height: Fit
+                            flow: Down
+                            padding: Inset{left: (SPACE_MD), right: (SPACE_MD), top: (SPACE_SM), bottom: (SPACE_MD)}
+                            show_bg: true
+                            draw_bg +: {
+                                color: #F8F8FA
+                                border_radius: (RADIUS_LG)
+                            }
+                            local_search_settings := LocalSearchSettings {}
+                        }
+
                         // --- TSP card ---
                         RoundedView {
                             width: Fill, height: Fit
@@ -934,6 +951,12 @@ impl SettingsScreen {
         self.view
             .translation_settings(cx, ids!(translation_settings))
             .set_app_language(cx, self.app_language);
+        self.view
+            .local_search_settings(cx, ids!(local_search_settings))
+            .set_app_language(cx, self.app_language);
+        self.view
+            .map_tile_settings(cx, ids!(map_tile_settings))
+            .set_app_language(cx, self.app_language);
         self.view
             .label(cx, ids!(contribute_title))
             .set_text(cx, tr_key(self.app_language, "settings.contribute.title"));
@@ -1210,7 +1233,7 @@ impl SettingsScreen {
     }
 
     /// Fetches the current user's profile and uses it to populate the settings screen.
-    pub fn populate(&mut self, cx: &mut Cx, own_profile: Option<UserProfile>, bot_settings: &BotSettingsState, translation_config: &crate::room::translation::TranslationConfig, app_language: AppLanguage) {
+    pub fn populate(&mut self, cx: &mut Cx, own_profile: Option<UserProfile>, bot_settings: &BotSettingsState, translation_config: &crate::room::translation::TranslationConfig, local_search_index_enabled: bool, app_language: AppLanguage) {
         let Some(profile) = own_profile.or_else(|| get_own_profile(cx)) else {
             error!("Failed to get own profile for settings screen.");
             return;
@@ -1219,6 +1242,8 @@ impl SettingsScreen {
         self.view.bot_settings(cx, ids!(bot_settings)).populate(cx, bot_settings);
         self.load_saved_proxy_to_preferences_form(cx);
         self.view.translation_settings(cx, ids!(translation_settings)).populate(cx, translation_config);
+        self.view.local_search_settings(cx, ids!(local_search_settings)).populate(cx, local_search_index_enabled);
+        self.view.map_tile_settings(cx, ids!(map_tile_settings)).populate(cx, &crate::map_tiles::get_global_config());
         #[cfg(feature = "tsp")]
         if let Some(mut tsp_settings_screen) = self.view.child_by_path(ids!(tsp_settings_screen)).borrow_mut::<crate::tsp::tsp_settings_screen::TspSettingsScreen>() {
             tsp_settings_screen.prepare_for_display(cx, app_language);
@@ -1235,9 +1260,9 @@ impl SettingsScreen {
 
 impl SettingsScreenRef {
     /// See [`SettingsScreen::populate()`].
-    pub fn populate(&self, cx: &mut Cx, own_profile: Option<UserProfile>, bot_settings: &BotSettingsState, translation_config: &crate::room::translation::TranslationConfig, app_language: AppLanguage) {
+    pub fn populate(&self, cx: &mut Cx, own_profile: Option<UserProfile>, bot_settings: &BotSettingsState, translation_config: &crate::room::translation::TranslationConfig, local_search_index_enabled: bool, app_language: AppLanguage) {
         let Some(mut inner) = self.borrow_mut() else { return; };
-        inner.populate(cx, own_profile, bot_settings, translation_config, app_language);
+        inner.populate(cx, own_profile, bot_settings, translation_config, local_search_index_enabled, app_language);
     }
 }
 
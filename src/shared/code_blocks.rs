@@ -0,0 +1,205 @@
+//! Support for showing the code blocks of HTML messages in a dedicated widget.
+//!
+//! Matrix clients send fenced code blocks as `<pre><code class="language-…">` HTML.
+//! This module rewrites such blocks into a custom `<codeblock>` tag that is handled by
+//! the `MatrixHtmlCodeBlock` widget, which shows the code in a `CodeView`
+//! from `makepad-code-editor` (the same as code blocks in Markdown messages).
+
+use std::borrow::Cow;
+
+/// Rewrites every `<pre><code>…</code></pre>` block in the given HTML
+/// into a `<codeblock>` tag that is drawn by the `MatrixHtmlCodeBlock` widget.
+///
+/// The block's language (from the `language-…` class of its `<code>` tag) and
+/// its plain-text code are passed as the `data-lang` and `data-code` attributes.
+/// Any tags within the code, e.g., links added by linkification, are dropped.
+pub fn rewrite_code_blocks(html: &str) -> Cow<'_, str> {
+    const PRE_OPEN: &str = "<pre";
+    if !html.contains(PRE_OPEN) {
+        return Cow::Borrowed(html);
+    }
+
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(pre_start) = find_tag(rest, "pre") {
+        let Some(block) = parse_code_block(&rest[pre_start..]) else {
+            output.push_str(&rest[..pre_start + PRE_OPEN.len()]);
+            rest = &rest[pre_start + PRE_OPEN.len()..];
+            continue;
+        };
+        output.push_str(&rest[..pre_start]);
+        output.push_str("<codeblock data-lang=\"");
+        output.push_str(&escape_code_attribute(block.language.unwrap_or_default()));
+        output.push_str("\" data-code=\"");
+        output.push_str(&escape_code_attribute(&block.code));
+        output.push_str("\"></codeblock>");
+        rest = &rest[pre_start + block.len..];
+    }
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+/// A `<pre><code>` block parsed from HTML.
+struct CodeBlock<'a> {
+    /// The language name from the `language-…` class of the `<code>` tag.
+    language: Option<&'a str>,
+    /// The plain text of the code, with all HTML tags removed and entities decoded.
+    code: String,
+    /// The length of the entire block in the original HTML, including the closing `</pre>`.
+    len: usize,
+}
+
+/// Parses a `<pre><code>…</code></pre>` block at the start of the given HTML.
+fn parse_code_block(html: &str) -> Option<CodeBlock<'_>> {
+    let pre_tag_end = html.find('>')? + 1;
+    let after_pre = html[pre_tag_end..].trim_start();
+    let code_start = html.len() - after_pre.len();
+    if find_tag(after_pre, "code") != Some(0) {
+        return None;
+    }
+    let code_tag_end = code_start + after_pre.find('>')? + 1;
+    let code_tag = &html[code_start..code_tag_end];
+    let language = code_tag.find("language-").map(|i| {
+        let name = &code_tag[i + "language-".len()..];
+        let name_len = name.find(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '>')
+            .unwrap_or(name.len());
+        &name[..name_len]
+    });
+
+    let code_end = code_tag_end + html[code_tag_end..].find("</code>")?;
+    let after_code = &html[code_end + "</code>".len()..];
+    let after_code_trimmed = after_code.trim_start();
+    if !after_code_trimmed.starts_with("</pre>") {
+        return None;
+    }
+    let len = html.len() - after_code_trimmed.len() + "</pre>".len();
+    let code = unescape_html(&strip_tags(&html[code_tag_end..code_end]));
+    // Code blocks generated from Markdown end with a trailing newline, which shouldn't be shown.
+    let code = code.strip_suffix('\n').map(str::to_owned).unwrap_or(code);
+    Some(CodeBlock { language, code, len })
+}
+
+/// Returns the index of the first opening tag with the given lowercase name in the given HTML.
+fn find_tag(html: &str, name: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(i) = html[offset..].find('<') {
+        let start = offset + i;
+        let after = &html[start + 1..];
+        if after.get(..name.len()).is_some_and(|n| n.eq_ignore_ascii_case(name))
+            && after[name.len()..].starts_with(|c: char| c == '>' || c.is_whitespace())
+        {
+            return Some(start);
+        }
+        offset = start + 1;
+    }
+    None
+}
+
+/// Removes all HTML tags from the given HTML, keeping only its text.
+fn strip_tags(html: &str) -> Cow<'_, str> {
+    if !html.contains('<') {
+        return Cow::Borrowed(html);
+    }
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(ch),
+            _ => { }
+        }
+    }
+    Cow::Owned(text)
+}
+
+/// Decodes the basic named HTML entities and all numeric character references in the given text.
+pub fn unescape_html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        output.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';')
+            .filter(|&semi| semi <= 10)
+            .and_then(|semi| decode_entity(&rest[1..semi]).map(|ch| (ch, semi + 1)));
+        match decoded {
+            Some((ch, len)) => {
+                output.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Decodes the name of an HTML entity (without its `&` and `;`) into a char.
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code_point = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code_point)
+        }
+    }
+}
+
+/// Escapes code such that it can be placed in a double-quoted attribute value
+/// and recovered intact with [`unescape_html()`].
+///
+/// Unlike regular attribute escaping, this also escapes single quotes and line breaks,
+/// as the `Html` widget trims quotes from attribute values and splits tags on newlines.
+fn escape_code_attribute(code: &str) -> String {
+    let mut escaped = String::with_capacity(code.len());
+    for ch in code.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => { }
+            '\t' => escaped.push_str("&#9;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_code_blocks() {
+        let html = "<p>see:</p><pre><code class=\"language-rust\">let x = &quot;<a href=\"https://a.b\">https://a.b</a>&quot;;\n</code></pre>after";
+        assert_eq!(
+            rewrite_code_blocks(html),
+            "<p>see:</p><codeblock data-lang=\"rust\" data-code=\"let x = &quot;https://a.b&quot;;\"></codeblock>after",
+        );
+        let plain = "<pre><code>a'b\nc</code></pre>";
+        assert_eq!(
+            rewrite_code_blocks(plain),
+            "<codeblock data-lang=\"\" data-code=\"a&#39;b&#10;c\"></codeblock>",
+        );
+        assert_eq!(unescape_html("a&#39;b&#10;c &amp;x &unknown;"), "a'b\nc &x &unknown;");
+        // A `<pre>` without a `<code>` tag is left as is.
+        assert_eq!(rewrite_code_blocks("<pre>text</pre>"), "<pre>text</pre>");
+    }
+}
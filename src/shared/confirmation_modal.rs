@@ -58,6 +58,23 @@ script_mod! {
                 }
             }
 
+            // An optional text input, e.g., for entering a reason. Hidden by default.
+            input_view := View {
+                visible: false,
+                width: Fill, height: Fit
+                padding: Inset{top: 20}
+
+                input := RobrixTextInput {
+                    width: Fill, height: Fit
+                    padding: 10
+                    draw_text +: {
+                        text_style: REGULAR_TEXT {font_size: 11.5},
+                        color: #000
+                    }
+                    empty_text: ""
+                }
+            }
+
             buttons_view := View {
                 width: Fill, height: Fit
                 flow: Right,
@@ -169,8 +186,16 @@ pub struct ConfirmationModalContent {
     /// The text for the cancel button.
     /// If `None`, the button's default text of "Cancel" will be shown.
     pub cancel_button_text: Option<Cow<'static, str>>,
+    /// If `Some`, an optional text input is shown beneath the body text,
+    /// with this as its placeholder text.
+    pub input_empty_text: Option<Cow<'static, str>>,
     /// A callback to be called when the accept button is clicked.
     pub on_accept_clicked: Option<Box<dyn FnOnce(&mut Cx)>>,
+    /// A callback to be called when the accept button is clicked,
+    /// which receives the trimmed text entered into the optional text input, if any.
+    ///
+    /// This is only useful if `input_empty_text` is `Some`.
+    pub on_accept_clicked_with_input: Option<Box<dyn FnOnce(&mut Cx, Option<String>)>>,
     /// A callback to be called when the cancel button is clicked.
     pub on_cancel_clicked: Option<Box<dyn FnOnce(&mut Cx)>>,
 }
@@ -181,7 +206,9 @@ impl std::fmt::Debug for ConfirmationModalContent {
             .field("body", &self.body_text)
             .field("accept_button", &self.accept_button_text)
             .field("cancel_button", &self.cancel_button_text)
+            .field("input_empty_text", &self.input_empty_text)
             .field("on_accept_clicked", &self.on_accept_clicked.is_some())
+            .field("on_accept_clicked_with_input", &self.on_accept_clicked_with_input.is_some())
             .field("on_cancel_clicked", &self.on_cancel_clicked.is_some())
             .finish()
     }
@@ -230,11 +257,16 @@ impl WidgetMatchEvent for ConfirmationModal {
             return;
         }
 
-        // If the accept button was clicked, emit the action and call the on_accept callback.
+        // If the accept button was clicked, emit the action and call the on_accept callbacks.
         if accept_button.clicked(actions) {
             if let Some(on_accept_clicked) = self.content.on_accept_clicked.take() {
                 on_accept_clicked(cx);
             }
+            if let Some(on_accept_clicked_with_input) = self.content.on_accept_clicked_with_input.take() {
+                let input_text = self.view.text_input(cx, ids!(input_view.input)).text();
+                let input_text = Some(input_text.trim().to_string()).filter(|t| !t.is_empty());
+                on_accept_clicked_with_input(cx, input_text);
+            }
             cx.widget_action(
                 self.widget_uid(), 
                 ConfirmationModalAction::Close(true),
@@ -261,6 +293,13 @@ impl ConfirmationModal {
             self.content.cancel_button_text.as_deref().unwrap_or("Cancel"),
         );
 
+        let input = self.view.text_input(cx, ids!(input_view.input));
+        input.set_text(cx, "");
+        if let Some(input_empty_text) = self.content.input_empty_text.as_deref() {
+            input.set_empty_text(cx, input_empty_text.to_string());
+        }
+        self.view.view(cx, ids!(input_view)).set_visible(cx, self.content.input_empty_text.is_some());
+
         self.view.button(cx, ids!(cancel_button)).reset_hover(cx);
         self.view.button(cx, ids!(accept_button)).reset_hover(cx);
         self.view.button(cx, ids!(accept_button)).set_enabled(cx, true);
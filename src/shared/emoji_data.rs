@@ -0,0 +1,624 @@
+//! The full set of Unicode emoji, grouped into categories and searchable by keywords
+//! in every language that Robrix supports.
+//!
+//! The emoji themselves and their English names come from the `emojis` crate,
+//! which are supplemented by hand-picked English and Chinese keywords for common emoji.
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of recently-used emoji that are remembered.
+pub const MAX_RECENT_EMOJI: usize = 32;
+
+/// The categories that emoji are grouped into, in the order they are shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmojiCategory {
+    Smileys,
+    People,
+    Animals,
+    Food,
+    Activities,
+    Travel,
+    Objects,
+    Symbols,
+    Flags,
+}
+
+impl EmojiCategory {
+    pub const ALL: [EmojiCategory; 9] = [
+        EmojiCategory::Smileys,
+        EmojiCategory::People,
+        EmojiCategory::Animals,
+        EmojiCategory::Food,
+        EmojiCategory::Activities,
+        EmojiCategory::Travel,
+        EmojiCategory::Objects,
+        EmojiCategory::Symbols,
+        EmojiCategory::Flags,
+    ];
+
+    fn from_group(group: emojis::Group) -> Self {
+        match group {
+            emojis::Group::SmileysAndEmotion => EmojiCategory::Smileys,
+            emojis::Group::PeopleAndBody => EmojiCategory::People,
+            emojis::Group::AnimalsAndNature => EmojiCategory::Animals,
+            emojis::Group::FoodAndDrink => EmojiCategory::Food,
+            emojis::Group::Activities => EmojiCategory::Activities,
+            emojis::Group::TravelAndPlaces => EmojiCategory::Travel,
+            emojis::Group::Objects => EmojiCategory::Objects,
+            emojis::Group::Symbols => EmojiCategory::Symbols,
+            emojis::Group::Flags => EmojiCategory::Flags,
+        }
+    }
+
+    /// Returns the i18n key of this category's displayable name.
+    pub fn name_key(self) -> &'static str {
+        match self {
+            EmojiCategory::Smileys => "emoji_picker.category.smileys",
+            EmojiCategory::People => "emoji_picker.category.people",
+            EmojiCategory::Animals => "emoji_picker.category.animals",
+            EmojiCategory::Food => "emoji_picker.category.food",
+            EmojiCategory::Activities => "emoji_picker.category.activities",
+            EmojiCategory::Travel => "emoji_picker.category.travel",
+            EmojiCategory::Objects => "emoji_picker.category.objects",
+            EmojiCategory::Symbols => "emoji_picker.category.symbols",
+            EmojiCategory::Flags => "emoji_picker.category.flags",
+        }
+    }
+}
+
+/// A skin tone that can be applied to emoji that depict people or body parts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkinTone {
+    /// The default (yellow) skin tone, without any modifier.
+    #[default]
+    Default,
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl SkinTone {
+    pub const ALL: [SkinTone; 6] = [
+        SkinTone::Default,
+        SkinTone::Light,
+        SkinTone::MediumLight,
+        SkinTone::Medium,
+        SkinTone::MediumDark,
+        SkinTone::Dark,
+    ];
+
+    fn to_emojis_skin_tone(self) -> emojis::SkinTone {
+        match self {
+            SkinTone::Default => emojis::SkinTone::Default,
+            SkinTone::Light => emojis::SkinTone::Light,
+            SkinTone::MediumLight => emojis::SkinTone::MediumLight,
+            SkinTone::Medium => emojis::SkinTone::Medium,
+            SkinTone::MediumDark => emojis::SkinTone::MediumDark,
+            SkinTone::Dark => emojis::SkinTone::Dark,
+        }
+    }
+}
+
+/// A single emoji in the built-in emoji set.
+#[derive(Debug)]
+pub struct EmojiInfo {
+    emoji: &'static emojis::Emoji,
+    pub category: EmojiCategory,
+    /// Lowercase English keywords, the first of which is the emoji's name.
+    keywords_en: Vec<String>,
+    /// Simplified Chinese keywords, the first of which is the emoji's name.
+    keywords_zh: &'static [&'static str],
+}
+
+impl EmojiInfo {
+    /// Returns the emoji itself, without any skin tone modifier.
+    pub fn as_str(&self) -> &'static str {
+        self.emoji.as_str()
+    }
+
+    /// Returns this emoji with the given skin tone applied, if it supports skin tones.
+    pub fn with_skin_tone(&self, skin_tone: SkinTone) -> String {
+        self.emoji.with_skin_tone(skin_tone.to_emojis_skin_tone())
+            .unwrap_or(self.emoji)
+            .as_str()
+            .to_owned()
+    }
+
+    /// Returns true if any of this emoji's keywords contain the given lowercase query.
+    fn matches(&self, query: &str) -> bool {
+        self.keywords_en.iter().any(|keyword| keyword.contains(query))
+            || self.keywords_zh.iter().any(|keyword| keyword.contains(query))
+    }
+}
+
+/// All built-in emoji, in the order they are shown within each category.
+static EMOJIS: LazyLock<Vec<EmojiInfo>> = LazyLock::new(|| {
+    let extra_keywords: HashMap<&str, (&[&str], &[&str])> = EXTRA_KEYWORDS.iter()
+        .map(|(emoji, keywords_en, keywords_zh)| (*emoji, (*keywords_en, *keywords_zh)))
+        .collect();
+    emojis::iter()
+        .map(|emoji| {
+            let (extra_en, keywords_zh) = extra_keywords.get(emoji.as_str())
+                .copied()
+                .unwrap_or((&[], &[]));
+            let mut keywords_en = vec![emoji.name().to_lowercase()];
+            for keyword in emoji.shortcodes().chain(extra_en.iter().copied()) {
+                let keyword = keyword.replace('_', " ");
+                if !keywords_en.contains(&keyword) {
+                    keywords_en.push(keyword);
+                }
+            }
+            EmojiInfo {
+                emoji,
+                category: EmojiCategory::from_group(emoji.group()),
+                keywords_en,
+                keywords_zh,
+            }
+        })
+        .collect()
+});
+
+/// Returns an iterator over all built-in emoji in the given category.
+pub fn emojis_in_category(category: EmojiCategory) -> impl Iterator<Item = &'static EmojiInfo> {
+    EMOJIS.iter().filter(move |info| info.category == category)
+}
+
+/// Returns all built-in emoji with a keyword (in any language) that contains the given query.
+///
+/// Emoji whose name starts with the query are listed first.
+pub fn search_emojis(query: &str) -> Vec<&'static EmojiInfo> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let (mut results, other_results): (Vec<_>, Vec<_>) = EMOJIS.iter()
+        .filter(|info| info.matches(&query))
+        .partition(|info| {
+            info.keywords_en.first().is_some_and(|name| name.starts_with(&query))
+                || info.keywords_zh.first().is_some_and(|name| name.starts_with(&query))
+        });
+    results.extend(other_results);
+    results
+}
+
+/// Moves the given emoji to the front of the given list of recently-used emoji,
+/// keeping at most [`MAX_RECENT_EMOJI`] entries.
+pub fn push_recent_emoji(recent: &mut Vec<String>, emoji: &str) {
+    recent.retain(|existing| existing != emoji);
+    recent.insert(0, emoji.to_owned());
+    recent.truncate(MAX_RECENT_EMOJI);
+}
+
+/// Additional English and Chinese keywords for common emoji,
+/// which supplement the English names and shortcodes from the `emojis` crate.
+///
+/// The first Chinese keyword of each emoji is its name.
+static EXTRA_KEYWORDS: &[(&str, &[&str], &[&str])] = &[
+    // Smileys
+    ("😀", &["grinning", "smile", "happy"], &["笑脸", "开心", "高兴"]),
+    ("😃", &["smiley", "happy", "joy"], &["大笑", "开心"]),
+    ("😄", &["smile", "happy", "laugh"], &["眯眼笑", "开心"]),
+    ("😁", &["grin", "happy"], &["露齿笑", "嘻嘻"]),
+    ("😆", &["laughing", "satisfied", "haha"], &["哈哈", "大笑"]),
+    ("😅", &["sweat smile", "relief"], &["苦笑", "汗"]),
+    ("🤣", &["rofl", "rolling on the floor laughing", "lol"], &["笑得打滚", "笑死"]),
+    ("😂", &["joy", "tears of joy", "laugh", "lol"], &["笑哭", "哭笑", "笑出眼泪"]),
+    ("🙂", &["slightly smiling", "smile"], &["微笑"]),
+    ("🙃", &["upside down", "silly"], &["倒脸", "调皮"]),
+    ("😉", &["wink"], &["眨眼"]),
+    ("😊", &["blush", "smiling eyes", "happy"], &["害羞", "微笑", "脸红"]),
+    ("😇", &["innocent", "angel", "halo"], &["天使", "无辜"]),
+    ("🥰", &["smiling with hearts", "love", "adore"], &["喜爱", "爱心脸"]),
+    ("😍", &["heart eyes", "love", "crush"], &["花痴", "爱心眼"]),
+    ("🤩", &["star struck", "excited", "wow"], &["崇拜", "星星眼"]),
+    ("😘", &["kissing heart", "kiss"], &["飞吻", "亲亲"]),
+    ("😋", &["yum", "delicious", "tasty"], &["好吃", "馋"]),
+    ("😛", &["tongue", "playful"], &["吐舌头"]),
+    ("😜", &["winking tongue", "crazy", "joke"], &["调皮", "吐舌眨眼"]),
+    ("🤪", &["zany", "goofy", "crazy"], &["滑稽", "疯狂"]),
+    ("🤑", &["money mouth", "rich"], &["发财", "钱"]),
+    ("🤗", &["hugging", "hug"], &["拥抱", "抱抱"]),
+    ("🤭", &["hand over mouth", "giggle", "oops"], &["捂嘴笑", "偷笑"]),
+    ("🤫", &["shushing", "quiet", "secret"], &["嘘", "安静"]),
+    ("🤔", &["thinking", "hmm", "wonder"], &["思考", "想一想", "嗯"]),
+    ("🤐", &["zipper mouth", "secret"], &["闭嘴", "保密"]),
+    ("🤨", &["raised eyebrow", "skeptical", "suspicious"], &["怀疑", "挑眉"]),
+    ("😐", &["neutral", "meh"], &["面无表情", "无语"]),
+    ("😑", &["expressionless", "blank"], &["无表情"]),
+    ("😶", &["no mouth", "speechless"], &["沉默", "无话可说"]),
+    ("😏", &["smirk", "smug"], &["得意", "坏笑"]),
+    ("😒", &["unamused", "annoyed"], &["不高兴", "不屑"]),
+    ("🙄", &["eye roll", "whatever"], &["翻白眼"]),
+    ("😬", &["grimacing", "awkward", "nervous"], &["尴尬", "龇牙"]),
+    ("😌", &["relieved", "calm"], &["释然", "放松"]),
+    ("😔", &["pensive", "sad"], &["沉思", "失落"]),
+    ("😪", &["sleepy", "tired"], &["困", "瞌睡"]),
+    ("😴", &["sleeping", "zzz"], &["睡觉", "睡着"]),
+    ("😷", &["mask", "sick"], &["口罩", "生病"]),
+    ("🤒", &["thermometer", "ill", "fever"], &["发烧", "生病"]),
+    ("🤢", &["nauseated", "sick", "gross"], &["恶心", "想吐"]),
+    ("🤮", &["vomiting", "throw up", "sick"], &["呕吐"]),
+    ("🥵", &["hot", "heat", "sweating"], &["热", "好热"]),
+    ("🥶", &["cold", "freezing"], &["冷", "好冷", "冻"]),
+    ("😵", &["dizzy", "knocked out"], &["晕", "头晕"]),
+    ("🤯", &["exploding head", "mind blown", "shocked"], &["爆炸", "震惊"]),
+    ("🥳", &["partying", "celebrate", "birthday"], &["庆祝", "派对"]),
+    ("😎", &["sunglasses", "cool"], &["酷", "墨镜"]),
+    ("🤓", &["nerd", "geek"], &["书呆子", "学霸"]),
+    ("😕", &["confused"], &["困惑"]),
+    ("😟", &["worried"], &["担心"]),
+    ("🙁", &["slightly frowning", "sad"], &["不开心", "皱眉"]),
+    ("😮", &["open mouth", "surprised", "wow"], &["惊讶", "哇"]),
+    ("😲", &["astonished", "shocked"], &["震惊", "吃惊"]),
+    ("😳", &["flushed", "embarrassed"], &["脸红", "害羞", "尴尬"]),
+    ("🥺", &["pleading", "puppy eyes", "please"], &["可怜", "拜托", "求求"]),
+    ("😢", &["cry", "sad", "tear"], &["哭", "难过", "伤心"]),
+    ("😭", &["sob", "crying", "sad"], &["大哭", "痛哭"]),
+    ("😱", &["scream", "fear", "horror"], &["尖叫", "害怕", "惊恐"]),
+    ("😤", &["triumph", "huff", "frustrated"], &["生气", "哼"]),
+    ("😡", &["rage", "angry", "mad"], &["愤怒", "生气"]),
+    ("🤬", &["cursing", "swearing", "angry"], &["骂人", "暴怒"]),
+    ("😈", &["smiling imp", "devil", "evil"], &["恶魔", "坏笑"]),
+    ("💀", &["skull", "dead"], &["骷髅", "死了"]),
+    ("💩", &["poop", "poo"], &["便便", "大便"]),
+    ("🤡", &["clown"], &["小丑"]),
+    ("👻", &["ghost", "boo"], &["幽灵", "鬼"]),
+    ("👽", &["alien", "ufo"], &["外星人"]),
+    ("🤖", &["robot", "bot"], &["机器人"]),
+    ("😺", &["smiley cat", "cat"], &["笑猫", "猫"]),
+    // People & body
+    ("👋", &["wave", "hello", "bye"], &["挥手", "你好", "再见"]),
+    ("🤚", &["raised back of hand"], &["举手背"]),
+    ("✋", &["raised hand", "stop", "high five"], &["举手", "停"]),
+    ("🖖", &["vulcan salute", "spock"], &["瓦肯举手礼"]),
+    ("👌", &["ok hand", "okay", "perfect"], &["好的", "没问题", "ok"]),
+    ("🤌", &["pinched fingers"], &["捏手指"]),
+    ("✌️", &["victory", "peace"], &["胜利", "耶", "剪刀手"]),
+    ("🤞", &["crossed fingers", "luck", "hope"], &["祈祷", "好运"]),
+    ("🤟", &["love you gesture"], &["爱你手势"]),
+    ("🤘", &["sign of the horns", "rock"], &["摇滚"]),
+    ("🤙", &["call me", "shaka"], &["打电话", "给我打电话"]),
+    ("👈", &["point left", "backhand index pointing left"], &["左边", "指左"]),
+    ("👉", &["point right", "backhand index pointing right"], &["右边", "指右"]),
+    ("👆", &["point up", "backhand index pointing up"], &["上面", "指上"]),
+    ("👇", &["point down", "backhand index pointing down"], &["下面", "指下"]),
+    ("☝️", &["index pointing up", "one"], &["食指", "一"]),
+    ("👍", &["thumbs up", "+1", "like", "yes"], &["赞", "点赞", "好", "同意"]),
+    ("👎", &["thumbs down", "-1", "dislike", "no"], &["踩", "差评", "不同意"]),
+    ("✊", &["raised fist", "power"], &["拳头", "加油"]),
+    ("👊", &["oncoming fist", "punch", "fist bump"], &["出拳", "碰拳"]),
+    ("👏", &["clap", "applause", "bravo"], &["鼓掌", "拍手"]),
+    ("🙌", &["raising hands", "hooray", "celebrate"], &["欢呼", "举双手"]),
+    ("👐", &["open hands"], &["张开双手"]),
+    ("🤝", &["handshake", "deal", "agreement"], &["握手", "合作"]),
+    ("🙏", &["folded hands", "please", "thanks", "pray"], &["祈祷", "拜托", "谢谢", "合十"]),
+    ("✍️", &["writing hand", "write"], &["写字"]),
+    ("💪", &["flexed biceps", "strong", "muscle"], &["肌肉", "强壮", "加油"]),
+    ("👀", &["eyes", "look", "see"], &["眼睛", "看"]),
+    ("🧠", &["brain", "smart"], &["大脑", "聪明"]),
+    ("👶", &["baby"], &["婴儿", "宝宝"]),
+    ("🧒", &["child", "kid"], &["儿童", "小孩"]),
+    ("🧑", &["person", "adult"], &["人", "成人"]),
+    ("👩", &["woman"], &["女人", "女性"]),
+    ("👨", &["man"], &["男人", "男性"]),
+    ("🧓", &["older person", "elderly"], &["老人"]),
+    ("🙋", &["person raising hand", "question", "me"], &["举手", "提问"]),
+    ("🤷", &["shrug", "dunno", "whatever"], &["耸肩", "不知道", "无所谓"]),
+    ("🤦", &["facepalm", "ugh"], &["捂脸", "无奈"]),
+    ("🙇", &["bow", "sorry"], &["鞠躬", "抱歉"]),
+    ("💃", &["dancer", "dance"], &["跳舞", "舞者"]),
+    ("🕺", &["man dancing", "dance"], &["跳舞"]),
+    ("🏃", &["runner", "running"], &["跑步", "跑"]),
+    ("🚶", &["walking", "walk"], &["走路", "散步"]),
+    // Animals & nature
+    ("🐶", &["dog", "puppy"], &["狗", "小狗"]),
+    ("🐱", &["cat", "kitten"], &["猫", "小猫"]),
+    ("🐭", &["mouse"], &["老鼠"]),
+    ("🐹", &["hamster"], &["仓鼠"]),
+    ("🐰", &["rabbit", "bunny"], &["兔子"]),
+    ("🦊", &["fox"], &["狐狸"]),
+    ("🐻", &["bear"], &["熊"]),
+    ("🐼", &["panda"], &["熊猫"]),
+    ("🐨", &["koala"], &["考拉", "树袋熊"]),
+    ("🐯", &["tiger"], &["老虎"]),
+    ("🦁", &["lion"], &["狮子"]),
+    ("🐮", &["cow"], &["牛", "奶牛"]),
+    ("🐷", &["pig"], &["猪", "小猪"]),
+    ("🐸", &["frog"], &["青蛙"]),
+    ("🐵", &["monkey"], &["猴子"]),
+    ("🙈", &["see no evil", "monkey", "shy"], &["不看", "捂眼猴", "害羞"]),
+    ("🐔", &["chicken"], &["鸡"]),
+    ("🐧", &["penguin"], &["企鹅"]),
+    ("🐦", &["bird"], &["鸟"]),
+    ("🦆", &["duck"], &["鸭子"]),
+    ("🦉", &["owl"], &["猫头鹰"]),
+    ("🐴", &["horse"], &["马"]),
+    ("🦄", &["unicorn"], &["独角兽"]),
+    ("🐝", &["bee", "honeybee"], &["蜜蜂"]),
+    ("🦋", &["butterfly"], &["蝴蝶"]),
+    ("🐢", &["turtle", "slow"], &["乌龟", "慢"]),
+    ("🐍", &["snake"], &["蛇"]),
+    ("🐙", &["octopus"], &["章鱼"]),
+    ("🐳", &["whale"], &["鲸鱼"]),
+    ("🐟", &["fish"], &["鱼"]),
+    ("🦀", &["crab", "rust", "ferris"], &["螃蟹"]),
+    ("🐉", &["dragon"], &["龙"]),
+    ("🌵", &["cactus"], &["仙人掌"]),
+    ("🌲", &["evergreen tree", "tree"], &["树", "松树"]),
+    ("🌸", &["cherry blossom", "flower"], &["樱花", "花"]),
+    ("🌹", &["rose", "flower"], &["玫瑰", "花"]),
+    ("🌻", &["sunflower", "flower"], &["向日葵", "花"]),
+    ("🍀", &["four leaf clover", "luck"], &["四叶草", "幸运"]),
+    ("🍁", &["maple leaf", "autumn"], &["枫叶", "秋天"]),
+    ("🌈", &["rainbow"], &["彩虹"]),
+    ("☀️", &["sun", "sunny"], &["太阳", "晴天"]),
+    ("🌙", &["crescent moon", "moon", "night"], &["月亮", "晚上"]),
+    ("⭐", &["star"], &["星星"]),
+    ("⚡", &["lightning", "zap", "fast"], &["闪电", "快"]),
+    ("☔", &["umbrella", "rain"], &["雨伞", "下雨"]),
+    ("❄️", &["snowflake", "snow", "cold"], &["雪花", "下雪"]),
+    ("🌊", &["wave", "ocean", "sea"], &["海浪", "大海"]),
+    // Food & drink
+    ("🍎", &["apple", "red apple"], &["苹果"]),
+    ("🍊", &["tangerine", "orange"], &["橘子", "橙子"]),
+    ("🍋", &["lemon"], &["柠檬"]),
+    ("🍌", &["banana"], &["香蕉"]),
+    ("🍉", &["watermelon"], &["西瓜"]),
+    ("🍇", &["grapes"], &["葡萄"]),
+    ("🍓", &["strawberry"], &["草莓"]),
+    ("🍑", &["peach"], &["桃子"]),
+    ("🥑", &["avocado"], &["牛油果"]),
+    ("🌶️", &["hot pepper", "spicy", "chili"], &["辣椒", "辣"]),
+    ("🍞", &["bread"], &["面包"]),
+    ("🧀", &["cheese"], &["奶酪"]),
+    ("🍔", &["hamburger", "burger"], &["汉堡"]),
+    ("🍟", &["fries", "french fries"], &["薯条"]),
+    ("🍕", &["pizza"], &["披萨"]),
+    ("🌭", &["hot dog"], &["热狗"]),
+    ("🌮", &["taco"], &["塔可"]),
+    ("🍜", &["noodles", "ramen"], &["面条", "拉面"]),
+    ("🍚", &["rice", "cooked rice"], &["米饭"]),
+    ("🍣", &["sushi"], &["寿司"]),
+    ("🥟", &["dumpling"], &["饺子"]),
+    ("🍰", &["cake", "shortcake"], &["蛋糕"]),
+    ("🎂", &["birthday cake", "birthday"], &["生日蛋糕", "生日"]),
+    ("🍪", &["cookie"], &["饼干"]),
+    ("🍫", &["chocolate"], &["巧克力"]),
+    ("🍿", &["popcorn"], &["爆米花"]),
+    ("🍩", &["doughnut", "donut"], &["甜甜圈"]),
+    ("🍦", &["ice cream"], &["冰淇淋"]),
+    ("☕", &["coffee", "hot beverage"], &["咖啡"]),
+    ("🍵", &["tea"], &["茶"]),
+    ("🧋", &["bubble tea", "boba"], &["奶茶", "珍珠奶茶"]),
+    ("🍺", &["beer"], &["啤酒"]),
+    ("🍻", &["clinking beer mugs", "cheers"], &["干杯"]),
+    ("🍷", &["wine"], &["红酒", "葡萄酒"]),
+    ("🥂", &["clinking glasses", "cheers", "toast"], &["干杯", "庆祝"]),
+    // Activities
+    ("⚽", &["soccer", "football"], &["足球"]),
+    ("🏀", &["basketball"], &["篮球"]),
+    ("🏈", &["american football"], &["橄榄球"]),
+    ("⚾", &["baseball"], &["棒球"]),
+    ("🎾", &["tennis"], &["网球"]),
+    ("🏐", &["volleyball"], &["排球"]),
+    ("🏓", &["ping pong", "table tennis"], &["乒乓球"]),
+    ("🏸", &["badminton"], &["羽毛球"]),
+    ("🥊", &["boxing"], &["拳击"]),
+    ("🎯", &["direct hit", "target", "bullseye"], &["靶心", "命中"]),
+    ("🎮", &["video game", "game", "controller"], &["游戏", "手柄"]),
+    ("🎲", &["dice", "game"], &["骰子"]),
+    ("♟️", &["chess"], &["国际象棋"]),
+    ("🧩", &["puzzle", "jigsaw"], &["拼图"]),
+    ("🎨", &["art", "palette", "paint"], &["艺术", "调色板", "画画"]),
+    ("🎬", &["clapper board", "movie", "film"], &["电影", "场记板"]),
+    ("🎤", &["microphone", "sing", "karaoke"], &["麦克风", "唱歌"]),
+    ("🎧", &["headphone", "music"], &["耳机", "音乐"]),
+    ("🎸", &["guitar"], &["吉他"]),
+    ("🎹", &["piano", "keyboard"], &["钢琴"]),
+    ("🏆", &["trophy", "win", "champion"], &["奖杯", "冠军"]),
+    ("🥇", &["first place", "gold medal"], &["金牌", "第一"]),
+    ("🎉", &["party popper", "tada", "celebrate"], &["庆祝", "撒花", "派对"]),
+    ("🎊", &["confetti ball", "celebrate"], &["彩球", "庆祝"]),
+    ("🎈", &["balloon"], &["气球"]),
+    ("🎁", &["gift", "present"], &["礼物"]),
+    ("🎄", &["christmas tree", "christmas"], &["圣诞树", "圣诞节"]),
+    ("🧧", &["red envelope", "lucky money", "hongbao"], &["红包"]),
+    ("🧨", &["firecracker"], &["鞭炮", "爆竹"]),
+    ("🏮", &["red paper lantern", "lantern"], &["灯笼"]),
+    ("🎆", &["fireworks"], &["烟花"]),
+    // Travel & places
+    ("🚗", &["car", "automobile"], &["汽车", "车"]),
+    ("🚕", &["taxi"], &["出租车"]),
+    ("🚌", &["bus"], &["公交车", "巴士"]),
+    ("🚲", &["bicycle", "bike"], &["自行车"]),
+    ("🏍️", &["motorcycle"], &["摩托车"]),
+    ("🚄", &["high speed train", "train"], &["高铁", "火车"]),
+    ("🚇", &["metro", "subway"], &["地铁"]),
+    ("✈️", &["airplane", "flight", "plane"], &["飞机", "航班"]),
+    ("🚀", &["rocket", "launch", "ship it"], &["火箭", "发射"]),
+    ("🛸", &["flying saucer", "ufo"], &["飞碟"]),
+    ("🚢", &["ship", "boat"], &["轮船", "船"]),
+    ("⛵", &["sailboat"], &["帆船"]),
+    ("🏠", &["house", "home"], &["房子", "家"]),
+    ("🏢", &["office building", "office"], &["办公楼", "公司"]),
+    ("🏫", &["school"], &["学校"]),
+    ("🏥", &["hospital"], &["医院"]),
+    ("⛪", &["church"], &["教堂"]),
+    ("🗼", &["tokyo tower", "tower"], &["东京塔", "塔"]),
+    ("🗽", &["statue of liberty"], &["自由女神像"]),
+    ("🏔️", &["snow capped mountain", "mountain"], &["雪山", "山"]),
+    ("🏖️", &["beach", "vacation"], &["海滩", "度假"]),
+    ("🏕️", &["camping"], &["露营"]),
+    ("🌍", &["globe", "earth", "world"], &["地球", "世界"]),
+    ("🗺️", &["world map", "map"], &["地图"]),
+    ("🧭", &["compass"], &["指南针"]),
+    // Objects
+    ("💡", &["light bulb", "idea"], &["灯泡", "想法", "主意"]),
+    ("📱", &["mobile phone", "phone", "cell"], &["手机"]),
+    ("💻", &["laptop", "computer"], &["笔记本电脑", "电脑"]),
+    ("⌨️", &["keyboard"], &["键盘"]),
+    ("🖥️", &["desktop computer", "computer"], &["台式电脑", "电脑"]),
+    ("📷", &["camera", "photo"], &["相机", "照片"]),
+    ("📺", &["television", "tv"], &["电视"]),
+    ("⏰", &["alarm clock", "time"], &["闹钟", "时间"]),
+    ("⌛", &["hourglass", "wait"], &["沙漏", "等待"]),
+    ("🔋", &["battery"], &["电池"]),
+    ("🔌", &["electric plug", "plug"], &["插头"]),
+    ("💰", &["money bag", "money"], &["钱袋", "钱"]),
+    ("💳", &["credit card", "card"], &["信用卡", "银行卡"]),
+    ("✉️", &["envelope", "email", "mail"], &["信封", "邮件"]),
+    ("📦", &["package", "box"], &["包裹", "快递"]),
+    ("📝", &["memo", "note", "write"], &["备忘录", "笔记"]),
+    ("📅", &["calendar", "date"], &["日历", "日期"]),
+    ("📌", &["pushpin", "pin"], &["图钉", "置顶"]),
+    ("📎", &["paperclip", "attachment"], &["回形针", "附件"]),
+    ("✂️", &["scissors", "cut"], &["剪刀"]),
+    ("🔒", &["locked", "lock", "secure"], &["锁", "安全"]),
+    ("🔑", &["key"], &["钥匙"]),
+    ("🔨", &["hammer", "tool"], &["锤子", "工具"]),
+    ("🔧", &["wrench", "tool", "fix"], &["扳手", "修理"]),
+    ("⚙️", &["gear", "settings"], &["齿轮", "设置"]),
+    ("🧪", &["test tube", "experiment", "science"], &["试管", "实验"]),
+    ("💊", &["pill", "medicine"], &["药丸", "药"]),
+    ("📚", &["books", "library", "study"], &["书", "学习"]),
+    ("🔍", &["magnifying glass", "search"], &["放大镜", "搜索"]),
+    ("🔔", &["bell", "notification"], &["铃铛", "通知"]),
+    ("🎵", &["musical note", "music"], &["音符", "音乐"]),
+    ("🧸", &["teddy bear", "toy"], &["泰迪熊", "玩具"]),
+    ("🪄", &["magic wand", "magic"], &["魔杖", "魔法"]),
+    // Symbols
+    ("❤️", &["red heart", "heart", "love"], &["红心", "爱心", "爱"]),
+    ("🧡", &["orange heart"], &["橙心"]),
+    ("💛", &["yellow heart"], &["黄心"]),
+    ("💚", &["green heart"], &["绿心"]),
+    ("💙", &["blue heart"], &["蓝心"]),
+    ("💜", &["purple heart"], &["紫心"]),
+    ("🖤", &["black heart"], &["黑心"]),
+    ("🤍", &["white heart"], &["白心"]),
+    ("💔", &["broken heart", "heartbreak"], &["心碎"]),
+    ("💕", &["two hearts", "love"], &["两颗心", "爱"]),
+    ("💯", &["hundred points", "100", "perfect"], &["一百分", "满分"]),
+    ("🔥", &["fire", "hot", "lit"], &["火", "火爆", "厉害"]),
+    ("✨", &["sparkles", "shiny", "new"], &["闪亮", "闪光"]),
+    ("💥", &["collision", "boom"], &["爆炸", "碰撞"]),
+    ("💤", &["zzz", "sleep"], &["睡觉"]),
+    ("💬", &["speech balloon", "comment", "chat"], &["对话", "聊天", "评论"]),
+    ("✅", &["check mark button", "done", "yes"], &["完成", "对勾", "是"]),
+    ("✔️", &["check mark", "correct"], &["对", "正确"]),
+    ("❌", &["cross mark", "no", "wrong"], &["错", "错误", "否"]),
+    ("❓", &["question mark", "question"], &["问号", "问题"]),
+    ("❗", &["exclamation mark", "important"], &["感叹号", "重要"]),
+    ("⚠️", &["warning", "caution"], &["警告", "注意"]),
+    ("🚫", &["prohibited", "forbidden", "no"], &["禁止"]),
+    ("⛔", &["no entry", "stop"], &["禁止通行"]),
+    ("➕", &["plus", "add"], &["加号", "加"]),
+    ("➖", &["minus", "subtract"], &["减号", "减"]),
+    ("🆗", &["ok button", "ok"], &["好的", "ok"]),
+    ("🆕", &["new button", "new"], &["新"]),
+    ("🆘", &["sos", "help"], &["求救", "帮助"]),
+    ("🔴", &["red circle"], &["红圆", "红色"]),
+    ("🟢", &["green circle"], &["绿圆", "绿色"]),
+    ("🔵", &["blue circle"], &["蓝圆", "蓝色"]),
+    ("⬆️", &["up arrow", "up"], &["上箭头", "向上"]),
+    ("⬇️", &["down arrow", "down"], &["下箭头", "向下"]),
+    ("🔄", &["counterclockwise arrows", "refresh", "repeat"], &["刷新", "循环"]),
+    ("♻️", &["recycling", "recycle"], &["回收", "循环利用"]),
+    // Flags
+    ("🏁", &["chequered flag", "finish", "race"], &["终点旗", "比赛"]),
+    ("🚩", &["triangular flag", "red flag"], &["红旗", "旗子"]),
+    ("🏳️", &["white flag", "surrender"], &["白旗", "投降"]),
+    ("🏴", &["black flag"], &["黑旗"]),
+    ("🏳️‍🌈", &["rainbow flag", "pride"], &["彩虹旗"]),
+    ("🇨🇳", &["china", "flag cn"], &["中国", "国旗"]),
+    ("🇭🇰", &["hong kong", "flag hk"], &["香港"]),
+    ("🇹🇼", &["taiwan", "flag tw"], &["台湾"]),
+    ("🇯🇵", &["japan", "flag jp"], &["日本"]),
+    ("🇰🇷", &["south korea", "korea", "flag kr"], &["韩国"]),
+    ("🇸🇬", &["singapore", "flag sg"], &["新加坡"]),
+    ("🇮🇳", &["india", "flag in"], &["印度"]),
+    ("🇺🇸", &["united states", "usa", "flag us"], &["美国"]),
+    ("🇨🇦", &["canada", "flag ca"], &["加拿大"]),
+    ("🇧🇷", &["brazil", "flag br"], &["巴西"]),
+    ("🇬🇧", &["united kingdom", "uk", "flag gb"], &["英国"]),
+    ("🇫🇷", &["france", "flag fr"], &["法国"]),
+    ("🇩🇪", &["germany", "flag de"], &["德国"]),
+    ("🇪🇸", &["spain", "flag es"], &["西班牙"]),
+    ("🇮🇹", &["italy", "flag it"], &["意大利"]),
+    ("🇪🇺", &["european union", "eu", "flag eu"], &["欧盟"]),
+    ("🇦🇺", &["australia", "flag au"], &["澳大利亚"]),
+
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info_of(emoji: &str) -> &'static EmojiInfo {
+        EMOJIS.iter().find(|info| info.as_str() == emoji).expect("emoji exists")
+    }
+
+    #[test]
+    fn skin_tone_is_applied() {
+        assert_eq!(info_of("👍").with_skin_tone(SkinTone::Medium), "👍\u{1F3FD}");
+        assert_eq!(info_of("👍").with_skin_tone(SkinTone::Default), "👍");
+    }
+
+    #[test]
+    fn skin_tone_replaces_variation_selector() {
+        assert_eq!(info_of("✌\u{FE0F}").with_skin_tone(SkinTone::Dark), "✌\u{1F3FF}");
+    }
+
+    #[test]
+    fn skin_tone_is_ignored_for_unsupported_emoji() {
+        assert_eq!(info_of("🔥").with_skin_tone(SkinTone::Light), "🔥");
+    }
+
+    #[test]
+    fn search_matches_english_keywords() {
+        let results = search_emojis("Thumbs");
+        assert_eq!(results.first().map(|info| info.as_str()), Some("👍"));
+    }
+
+    #[test]
+    fn search_matches_chinese_keywords() {
+        let results = search_emojis("熊猫");
+        assert_eq!(results.first().map(|info| info.as_str()), Some("🐼"));
+        assert!(search_emojis("点赞").iter().any(|info| info.as_str() == "👍"));
+    }
+
+    #[test]
+    fn empty_search_returns_nothing() {
+        assert!(search_emojis("   ").is_empty());
+    }
+
+    #[test]
+    fn recent_emoji_are_deduplicated_and_capped() {
+        let mut recent = vec!["😀".to_owned(), "👍".to_owned()];
+        push_recent_emoji(&mut recent, "👍");
+        assert_eq!(recent, ["👍", "😀"]);
+
+        for i in 0..MAX_RECENT_EMOJI + 5 {
+            push_recent_emoji(&mut recent, &i.to_string());
+        }
+        assert_eq!(recent.len(), MAX_RECENT_EMOJI);
+        assert_eq!(recent[0], (MAX_RECENT_EMOJI + 4).to_string());
+    }
+
+    #[test]
+    fn every_category_has_emoji() {
+        for category in EmojiCategory::ALL {
+            assert!(emojis_in_category(category).next().is_some(), "{category:?} is empty");
+        }
+    }
+
+    #[test]
+    fn extra_keywords_refer_to_known_emoji() {
+        for (emoji, _, _) in EXTRA_KEYWORDS {
+            assert!(EMOJIS.iter().any(|info| info.as_str() == *emoji), "{emoji} is unknown");
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! A picker for choosing an emoji, used both when composing a message
+//! and when reacting to a message.
+//!
+//! The picker shows the built-in emoji from [`emoji_data`] grouped by category,
+//! supports searching them by keyword in any supported language,
+//! and remembers the user's skin tone and recently-used emoji in the [`AppState`].
+
+use makepad_widgets::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    LivePtr, widget_ref_from_live_ptr,
+    app::AppState,
+    i18n::{AppLanguage, tr_key},
+    shared::{
+        emoji_data::{self, EmojiCategory, SkinTone},
+        styles::*,
+    },
+};
+
+/// The approximate height of an [`EmojiPicker`], used to position menus that contain one.
+pub const EMOJI_PICKER_HEIGHT: f64 = 300.0;
+
+const COLOR_EMOJI_BG: Vec4 = vec4(0.0, 0.0, 0.0, 0.0);
+const COLOR_EMOJI_BG_HOVER: Vec4 = COLOR_BG_PREVIEW;
+const COLOR_TAB_BG: Vec4 = vec4(0.0, 0.0, 0.0, 0.0);
+const COLOR_TAB_BG_SELECTED: Vec4 = COLOR_BG_PREVIEW_HOVER;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.EmojiPickerTabButton = Button {
+        width: 26,
+        height: 26,
+        padding: 0
+        margin: 0
+        align: Align{x: 0.5, y: 0.5}
+        draw_bg +: {
+            color: #0000
+            color_hover: #E0E8F0
+            color_down: #D0D8E8
+            border_size: 0.0
+            border_radius: 4.0
+        }
+        draw_text +: {
+            color: (COLOR_TEXT)
+            color_hover: (COLOR_TEXT)
+            color_down: (COLOR_TEXT)
+            text_style: MESSAGE_TEXT_STYLE { font_size: 12.0 }
+        }
+        text: ""
+    }
+
+    mod.widgets.EmojiGrid = #(EmojiGrid::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Flow.Right{wrap: true},
+        spacing: 2.0
+
+        emoji: RoundedView {
+            width: 32,
+            height: 32,
+            align: Align{x: 0.5, y: 0.5}
+            cursor: MouseCursor.Hand
+            show_bg: true
+            draw_bg +: {
+                color: #0000
+                border_radius: 4.0
+            }
+
+            emoji_label := Label {
+                width: Fit,
+                height: Fit,
+                draw_text +: {
+                    text_style: MESSAGE_TEXT_STYLE { font_size: 15.0 }
+                    color: (COLOR_TEXT)
+                }
+                text: ""
+            }
+        }
+    }
+
+    mod.widgets.EmojiPicker = #(EmojiPicker::register_widget(vm)) {
+        width: Fill,
+        height: Fit,
+        flow: Down,
+        spacing: 4.0
+        margin: Inset{left: 5, right: 5, top: 1, bottom: 1}
+
+        search_input := RobrixTextInput {
+            width: Fill,
+            height: Fit,
+            padding: 7
+            flow: Flow.Right{wrap: false}
+            empty_text: "Search emoji..."
+        }
+
+        category_tabs := View {
+            width: Fill,
+            height: Fit,
+            flow: Flow.Right{wrap: true},
+            spacing: 2.0
+
+            recent_tab := mod.widgets.EmojiPickerTabButton { text: "🕘" }
+            smileys_tab := mod.widgets.EmojiPickerTabButton { text: "😀" }
+            people_tab := mod.widgets.EmojiPickerTabButton { text: "👋" }
+            animals_tab := mod.widgets.EmojiPickerTabButton { text: "🐶" }
+            food_tab := mod.widgets.EmojiPickerTabButton { text: "🍎" }
+            activities_tab := mod.widgets.EmojiPickerTabButton { text: "⚽" }
+            travel_tab := mod.widgets.EmojiPickerTabButton { text: "🚗" }
+            objects_tab := mod.widgets.EmojiPickerTabButton { text: "💡" }
+            symbols_tab := mod.widgets.EmojiPickerTabButton { text: "❤️" }
+            flags_tab := mod.widgets.EmojiPickerTabButton { text: "🏁" }
+        }
+
+        skin_tones := View {
+            width: Fill,
+            height: Fit,
+            flow: Right,
+            spacing: 2.0
+            align: Align{y: 0.5}
+
+            skin_tone_label := Label {
+                width: Fit,
+                height: Fit,
+                margin: Inset{right: 4}
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 9 }
+                    color: (COLOR_TEXT)
+                }
+                text: "Skin tone"
+            }
+
+            tone_default_button := mod.widgets.EmojiPickerTabButton { text: "✋" }
+            tone_light_button := mod.widgets.EmojiPickerTabButton { text: "✋🏻" }
+            tone_medium_light_button := mod.widgets.EmojiPickerTabButton { text: "✋🏼" }
+            tone_medium_button := mod.widgets.EmojiPickerTabButton { text: "✋🏽" }
+            tone_medium_dark_button := mod.widgets.EmojiPickerTabButton { text: "✋🏾" }
+            tone_dark_button := mod.widgets.EmojiPickerTabButton { text: "✋🏿" }
+        }
+
+        section_label := Label {
+            width: Fill,
+            height: Fit,
+            draw_text +: {
+                text_style: TITLE_TEXT { font_size: 9.5 }
+                color: (COLOR_TEXT)
+            }
+            text: ""
+        }
+
+        emoji_scroll := ScrollYView {
+            width: Fill,
+            height: 180,
+            flow: Down
+
+            status_label := Label {
+                visible: false
+                width: Fill,
+                height: Fit,
+                draw_text +: {
+                    text_style: REGULAR_TEXT { font_size: 10 }
+                    color: (COLOR_TEXT)
+                    wrap: Word
+                }
+                text: ""
+            }
+
+            emojis := mod.widgets.EmojiGrid { }
+        }
+    }
+}
+
+/// The emoji picker's state that is saved per account as part of the [`AppState`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmojiPickerState {
+    /// The most recently-used emoji, most recent first.
+    pub recent: Vec<String>,
+    /// The skin tone applied to emoji that support one.
+    pub skin_tone: SkinTone,
+}
+
+/// Widget actions emitted by an [`EmojiGrid`].
+#[derive(Clone, Debug, Default)]
+pub enum EmojiGridAction {
+    /// The given emoji was clicked or tapped.
+    Selected(String),
+    #[default]
+    None,
+}
+
+/// A grid of emoji that can be clicked to select one.
+#[derive(Script, ScriptHook, Widget)]
+pub struct EmojiGrid {
+    #[uid] uid: WidgetUid,
+    #[redraw] #[rust] area: Area,
+    #[live] emoji: Option<LivePtr>,
+    #[rust] children: Vec<(WidgetRef, String)>,
+    #[layout] layout: Layout,
+    #[walk] walk: Walk,
+}
+
+impl Widget for EmojiGrid {
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        cx.begin_turtle(walk, self.layout);
+        for (widget, _emoji) in self.children.iter() {
+            widget.draw_all(cx, scope);
+        }
+        cx.end_turtle_with_area(&mut self.area);
+        DrawStep::done()
+    }
+
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        for (widget, emoji) in self.children.iter() {
+            // Note: the `break` statements are used to break out of the loop over
+            // all emoji, since a hit event can only occur on one emoji.
+            match event.hits(cx, widget.area()) {
+                Hit::FingerHoverIn(_) => {
+                    set_emoji_bg_color(cx, widget, COLOR_EMOJI_BG_HOVER);
+                    cx.set_cursor(MouseCursor::Hand);
+                    break;
+                }
+                Hit::FingerHoverOut(_) => {
+                    set_emoji_bg_color(cx, widget, COLOR_EMOJI_BG);
+                    break;
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.is_primary_hit() && fe.was_tap() => {
+                    set_emoji_bg_color(cx, widget, COLOR_EMOJI_BG);
+                    cx.widget_action(self.widget_uid(), EmojiGridAction::Selected(emoji.clone()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl EmojiGridRef {
+    /// Replaces all emoji in this grid with the given emoji.
+    fn set_emojis(&self, cx: &mut Cx, emojis: impl IntoIterator<Item = String>) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.children.clear();
+        for emoji in emojis {
+            let widget = widget_ref_from_live_ptr(cx, inner.emoji);
+            widget.label(cx, ids!(emoji_label)).set_text(cx, &emoji);
+            inner.children.push((widget, emoji));
+        }
+        inner.redraw(cx);
+    }
+}
+
+/// Sets the background color of the given emoji, e.g., to show that it's being hovered over.
+fn set_emoji_bg_color(cx: &mut Cx, emoji_ref: &WidgetRef, color: Vec4) {
+    let mut emoji_ref = emoji_ref.clone();
+    script_apply_eval!(cx, emoji_ref, {
+        draw_bg.color: #(color)
+    });
+}
+
+/// Sets the background color of the given tab or skin tone button to show whether it's selected.
+fn set_button_selected(cx: &mut Cx, button_ref: &WidgetRef, is_selected: bool) {
+    let mut button_ref = button_ref.clone();
+    let color = if is_selected { COLOR_TAB_BG_SELECTED } else { COLOR_TAB_BG };
+    script_apply_eval!(cx, button_ref, {
+        draw_bg +: { color: #(color) }
+    });
+}
+
+/// The tabs that can be selected in an [`EmojiPicker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EmojiPickerTab {
+    Recent,
+    Category(EmojiCategory),
+}
+
+impl Default for EmojiPickerTab {
+    fn default() -> Self {
+        EmojiPickerTab::Category(EmojiCategory::Smileys)
+    }
+}
+
+impl EmojiPickerTab {
+    const ALL: [EmojiPickerTab; 10] = [
+        EmojiPickerTab::Recent,
+        EmojiPickerTab::Category(EmojiCategory::Smileys),
+        EmojiPickerTab::Category(EmojiCategory::People),
+        EmojiPickerTab::Category(EmojiCategory::Animals),
+        EmojiPickerTab::Category(EmojiCategory::Food),
+        EmojiPickerTab::Category(EmojiCategory::Activities),
+        EmojiPickerTab::Category(EmojiCategory::Travel),
+        EmojiPickerTab::Category(EmojiCategory::Objects),
+        EmojiPickerTab::Category(EmojiCategory::Symbols),
+        EmojiPickerTab::Category(EmojiCategory::Flags),
+    ];
+
+    fn id(&self) -> &'static [LiveId] {
+        match self {
+            EmojiPickerTab::Recent => ids!(category_tabs.recent_tab),
+            EmojiPickerTab::Category(EmojiCategory::Smileys) => ids!(category_tabs.smileys_tab),
+            EmojiPickerTab::Category(EmojiCategory::People) => ids!(category_tabs.people_tab),
+            EmojiPickerTab::Category(EmojiCategory::Animals) => ids!(category_tabs.animals_tab),
+            EmojiPickerTab::Category(EmojiCategory::Food) => ids!(category_tabs.food_tab),
+            EmojiPickerTab::Category(EmojiCategory::Activities) => ids!(category_tabs.activities_tab),
+            EmojiPickerTab::Category(EmojiCategory::Travel) => ids!(category_tabs.travel_tab),
+            EmojiPickerTab::Category(EmojiCategory::Objects) => ids!(category_tabs.objects_tab),
+            EmojiPickerTab::Category(EmojiCategory::Symbols) => ids!(category_tabs.symbols_tab),
+            EmojiPickerTab::Category(EmojiCategory::Flags) => ids!(category_tabs.flags_tab),
+        }
+    }
+}
+
+fn skin_tone_button_id(skin_tone: SkinTone) -> &'static [LiveId] {
+    match skin_tone {
+        SkinTone::Default => ids!(skin_tones.tone_default_button),
+        SkinTone::Light => ids!(skin_tones.tone_light_button),
+        SkinTone::MediumLight => ids!(skin_tones.tone_medium_light_button),
+        SkinTone::Medium => ids!(skin_tones.tone_medium_button),
+        SkinTone::MediumDark => ids!(skin_tones.tone_medium_dark_button),
+        SkinTone::Dark => ids!(skin_tones.tone_dark_button),
+    }
+}
+
+/// Widget actions emitted by an [`EmojiPicker`].
+#[derive(Clone, Debug, Default)]
+pub enum EmojiPickerAction {
+    /// The user picked the given emoji, with their chosen skin tone already applied.
+    Picked(String),
+    #[default]
+    None,
+}
+
+/// A picker for choosing an emoji by category, by keyword search, or from recently-used emoji.
+#[derive(Script, ScriptHook, Widget)]
+pub struct EmojiPicker {
+    #[deref] view: View,
+    #[rust] selected_tab: EmojiPickerTab,
+    /// The current (trimmed) search query, which takes precedence over the selected tab.
+    #[rust] query: String,
+    /// A copy of the emoji picker state from the [`AppState`].
+    #[rust] state: EmojiPickerState,
+    #[rust] app_language: AppLanguage,
+    #[rust] is_initialized: bool,
+}
+
+impl Widget for EmojiPicker {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.sync_with_app_state(cx, scope);
+
+        let mut selected_emoji = None;
+        let mut picker_actions = cx.capture_actions(|cx| self.view.handle_event(cx, event, scope));
+        picker_actions.retain(|action| {
+            if let EmojiGridAction::Selected(emoji) = action.as_widget_action().cast() {
+                selected_emoji = Some(emoji);
+                return false;
+            }
+            true
+        });
+        cx.extend_actions(picker_actions);
+
+        if let Some(emoji) = selected_emoji {
+            emoji_data::push_recent_emoji(&mut self.state.recent, &emoji);
+            self.save_state(scope);
+            cx.widget_action(self.widget_uid(), EmojiPickerAction::Picked(emoji));
+        }
+
+        self.widget_match_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.sync_with_app_state(cx, scope);
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl WidgetMatchEvent for EmojiPicker {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, scope: &mut Scope) {
+        let search_input = self.view.text_input(cx, ids!(search_input));
+        if let Some(query) = search_input.changed(actions) {
+            self.query = query.trim().to_owned();
+            self.refresh(cx);
+        }
+        if search_input.escaped(actions) && !self.query.is_empty() {
+            search_input.set_text(cx, "");
+            self.query.clear();
+            self.refresh(cx);
+        }
+
+        for tab in EmojiPickerTab::ALL {
+            if self.view.button(cx, tab.id()).clicked(actions) {
+                self.selected_tab = tab;
+                self.query.clear();
+                search_input.set_text(cx, "");
+                self.refresh(cx);
+                break;
+            }
+        }
+
+        for skin_tone in SkinTone::ALL {
+            if self.view.button(cx, skin_tone_button_id(skin_tone)).clicked(actions) {
+                if self.state.skin_tone != skin_tone {
+                    self.state.skin_tone = skin_tone;
+                    self.save_state(scope);
+                    self.refresh(cx);
+                }
+                break;
+            }
+        }
+    }
+}
+
+impl EmojiPicker {
+    /// Updates this picker if the app language or the saved picker state has changed.
+    fn sync_with_app_state(&mut self, cx: &mut Cx, scope: &mut Scope) {
+        let Some(app_state) = scope.data.get::<AppState>() else { return };
+        if self.is_initialized
+            && self.app_language == app_state.app_language
+            && self.state == app_state.emoji_picker
+        {
+            return;
+        }
+        if !self.is_initialized && !app_state.emoji_picker.recent.is_empty() {
+            self.selected_tab = EmojiPickerTab::Recent;
+        }
+        self.is_initialized = true;
+        self.state = app_state.emoji_picker.clone();
+        self.set_app_language(cx, app_state.app_language);
+        self.refresh(cx);
+    }
+
+    fn set_app_language(&mut self, cx: &mut Cx, app_language: AppLanguage) {
+        self.app_language = app_language;
+        self.view.text_input(cx, ids!(search_input))
+            .set_empty_text(cx, tr_key(app_language, "emoji_picker.search.placeholder").to_string());
+        self.view.label(cx, ids!(skin_tones.skin_tone_label))
+            .set_text(cx, tr_key(app_language, "emoji_picker.label.skin_tone"));
+    }
+
+    /// Saves this picker's state to the [`AppState`],
+    /// which is persisted along with the rest of the app state.
+    fn save_state(&mut self, scope: &mut Scope) {
+        let Some(app_state) = scope.data.get_mut::<AppState>() else { return };
+        app_state.emoji_picker = self.state.clone();
+    }
+
+    /// Re-populates the emoji grid based on the current search query or selected tab.
+    fn refresh(&mut self, cx: &mut Cx) {
+        let skin_tone = self.state.skin_tone;
+        let (section_name, emojis, empty_status_key): (&str, Vec<String>, &str) = if !self.query.is_empty() {
+            (
+                tr_key(self.app_language, "emoji_picker.section.search_results"),
+                emoji_data::search_emojis(&self.query).into_iter()
+                    .map(|info| info.with_skin_tone(skin_tone))
+                    .collect(),
+                "emoji_picker.status.no_results",
+            )
+        } else if let EmojiPickerTab::Category(category) = self.selected_tab {
+            (
+                tr_key(self.app_language, category.name_key()),
+                emoji_data::emojis_in_category(category)
+                    .map(|info| info.with_skin_tone(skin_tone))
+                    .collect(),
+                "emoji_picker.status.no_results",
+            )
+        } else {
+            (
+                tr_key(self.app_language, "emoji_picker.category.recent"),
+                self.state.recent.clone(),
+                "emoji_picker.status.no_recent",
+            )
+        };
+
+        self.view.label(cx, ids!(section_label)).set_text(cx, section_name);
+        let status_label = self.view.label(cx, ids!(emoji_scroll.status_label));
+        status_label.set_text(cx, tr_key(self.app_language, empty_status_key));
+        status_label.set_visible(cx, emojis.is_empty());
+        self.view.emoji_grid(cx, ids!(emoji_scroll.emojis)).set_emojis(cx, emojis);
+
+        let selected_tab = self.query.is_empty().then_some(self.selected_tab);
+        for tab in EmojiPickerTab::ALL {
+            let is_selected = selected_tab == Some(tab);
+            set_button_selected(cx, &self.view.widget(cx, tab.id()), is_selected);
+        }
+        for tone in SkinTone::ALL {
+            set_button_selected(cx, &self.view.widget(cx, skin_tone_button_id(tone)), tone == skin_tone);
+        }
+        self.view.redraw(cx);
+    }
+
+    /// Clears the search query and shows the recently-used emoji, if there are any.
+    fn reset(&mut self, cx: &mut Cx) {
+        self.query.clear();
+        self.view.text_input(cx, ids!(search_input)).set_text(cx, "");
+        if !self.state.recent.is_empty() {
+            self.selected_tab = EmojiPickerTab::Recent;
+        }
+        self.refresh(cx);
+    }
+
+    /// Returns `Some(emoji)` if an emoji was picked in the given `actions`.
+    pub fn picked(&self, actions: &Actions) -> Option<String> {
+        if let EmojiPickerAction::Picked(emoji) = actions.find_widget_action(self.widget_uid()).cast() {
+            Some(emoji)
+        } else {
+            None
+        }
+    }
+}
+
+impl EmojiPickerRef {
+    /// See [`EmojiPicker::picked()`].
+    pub fn picked(&self, actions: &Actions) -> Option<String> {
+        self.borrow().and_then(|inner| inner.picked(actions))
+    }
+
+    /// See [`EmojiPicker::reset()`].
+    pub fn reset(&self, cx: &mut Cx) {
+        let Some(mut inner) = self.borrow_mut() else { return };
+        inner.reset(cx);
+    }
+
+    /// Returns the area of this picker's search input.
+    pub fn search_input_area(&self, cx: &mut Cx) -> Area {
+        let Some(inner) = self.borrow() else { return Area::Empty };
+        inner.view.text_input(cx, ids!(search_input)).area()
+    }
+}
@@ -5,7 +5,7 @@ use matrix_sdk::{ruma::{matrix_uri::{MatrixId, MatrixToUri, MatrixUri}, OwnedMxc
 
 use crate::{avatar_cache::{self, AvatarCacheEntry}, profile::user_profile_cache, sliding_sync::{current_user_id, submit_async_request, MatrixRequest}, utils};
 
-use super::avatar::AvatarWidgetExt;
+use super::{avatar::AvatarWidgetExt, code_blocks};
 
 /// The color of the text used to print the spoiler reason before the hidden text.
 const COLOR_SPOILER_REASON: Vec4 = vec4(0.6, 0.6, 0.6, 1.0);
@@ -89,6 +89,153 @@ script_mod! {
         align: Align{x: 0., y: 0.}
     }
 
+    // This is an HTML subwidget used to handle `<img>` tags.
+    // Matrix only allows `mxc://` image sources, which are most commonly
+    // MSC2545 custom emoji that should be shown inline at text height.
+    mod.widgets.MatrixHtmlImage = #(MatrixHtmlImage::register_widget(vm)) {
+        width: Fit, height: Fit,
+        flow: Overlay,
+        align: Align{ y: 0.5 }
+        padding: Inset{ left: 1.0, right: 1.0 }
+
+        image := Image {
+            visible: false,
+            width: 20, height: 20,
+            fit: ImageFit.Smallest,
+        }
+
+        // Shown instead of the image until it has been loaded, or if it can't be loaded.
+        alt_label := Label {
+            flow: Right, // do not wrap
+            draw_text +: {
+                color: (MESSAGE_TEXT_COLOR),
+                text_style: mod.widgets.MESSAGE_TEXT_STYLE { font_size: (MESSAGE_FONT_SIZE) },
+            }
+            text: ""
+        }
+    }
+
+    // A `CodeView` that shows code highlighted with GitHub's light color scheme,
+    // used for code blocks in both HTML and Markdown messages.
+    mod.widgets.MessageCodeView = mod.widgets.CodeView {
+        keep_cursor_at_end: false
+        editor +: {
+            width: Fill
+            height: Fit
+            draw_bg +: { color: #0000 }
+            draw_text +: {
+                text_style: mod.widgets.MESSAGE_CODE_TEXT_STYLE {
+                    font_size: (MESSAGE_FONT_SIZE - 0.5)
+                    line_spacing: (MESSAGE_TEXT_LINE_SPACING)
+                }
+            }
+            token_colors +: {
+                whitespace: #x6a737d
+                delimiter: #x24292e
+                delimiter_highlight: #x005cc5
+                error_decoration: #xcb2431
+                warning_decoration: #xb08800
+                unknown: #x24292e
+                branch_keyword: #xd73a49
+                constant: #x005cc5
+                identifier: #x24292e
+                loop_keyword: #xd73a49
+                number: #x005cc5
+                other_keyword: #xd73a49
+                punctuator: #x24292e
+                string: #x22863a
+                function: #x6f42c1
+                typename: #xe36209
+                comment: #x6a737d
+            }
+        }
+    }
+
+    // A small icon-only button in the header of a `MatrixHtmlCodeBlock`.
+    mod.widgets.CodeBlockHeaderButton = RobrixIconButton {
+        width: Fit, height: Fit,
+        padding: 5,
+        spacing: 0,
+        align: Align{x: 0.5, y: 0.5}
+        draw_bg +: {
+            color: #0000
+            color_hover: #0000001A
+            color_down: #00000026
+        }
+        draw_icon +: { color: #6a737d }
+        icon_walk: Walk{width: 14, height: 14}
+    }
+
+    // This is an HTML subwidget used to handle the `<codeblock>` tags that
+    // `<pre><code>` blocks are rewritten into by `code_blocks::rewrite_code_blocks()`.
+    // It shows the code with syntax highlighting beneath a header containing
+    // the code's language and buttons to toggle line wrapping and to copy the code.
+    mod.widgets.MatrixHtmlCodeBlock = #(MatrixHtmlCodeBlock::register_widget(vm)) {
+        width: Fill, height: Fit,
+        margin: Inset{ top: 5, bottom: 5 }
+
+        code_block_view := RoundedView {
+            width: Fill, height: Fit,
+            flow: Down,
+            show_bg: true,
+            draw_bg +: {
+                color: #F6F8FA
+                border_radius: 4.0
+            }
+
+            header := View {
+                width: Fill, height: Fit,
+                flow: Right,
+                align: Align{ y: 0.5 }
+                padding: Inset{ left: 10, right: 4, top: 2, bottom: 0 }
+
+                language_label := Label {
+                    width: Fill, height: Fit,
+                    flow: Right, // do not wrap
+                    draw_text +: {
+                        color: #6a737d,
+                        text_style: mod.widgets.MESSAGE_TEXT_STYLE { font_size: 9.0 },
+                    }
+                    text: ""
+                }
+
+                wrap_button := mod.widgets.CodeBlockHeaderButton {
+                    draw_icon +: { svg: (ICON_WRAP_TEXT) }
+                }
+                copy_button := mod.widgets.CodeBlockHeaderButton {
+                    draw_icon +: { svg: (ICON_COPY) }
+                }
+                // Briefly shown in place of the copy button after the code has been copied.
+                copied_button := mod.widgets.CodeBlockHeaderButton {
+                    visible: false,
+                    draw_icon +: { svg: (ICON_CHECKMARK), color: (COLOR_FG_ACCEPT_GREEN) }
+                }
+            }
+
+            // Shown when line wrapping is enabled (the default).
+            wrapped_code := mod.widgets.MessageCodeView {
+                editor +: {
+                    margin: Inset{ left: 10, right: 10, top: 2, bottom: 8 }
+                }
+            }
+
+            // Shown when line wrapping is disabled, such that long lines can be scrolled horizontally.
+            unwrapped_view := View {
+                visible: false,
+                width: Fill, height: Fit,
+                scroll_bars: mod.widgets.ScrollBars {
+                    show_scroll_x: true show_scroll_y: false
+                }
+
+                unwrapped_code := mod.widgets.MessageCodeView {
+                    editor +: {
+                        width: Fit
+                        margin: Inset{ left: 10, right: 10, top: 2, bottom: 8 }
+                    }
+                }
+            }
+        }
+    }
 
     // A centralized widget where we define styles and custom elements for HTML
     // message content. This is a wrapper around Makepad's built-in `Html` widget.
@@ -145,6 +292,8 @@ script_mod! {
         font := mod.widgets.MatrixHtmlSpan { }
         span := mod.widgets.MatrixHtmlSpan { }
         a := mod.widgets.RobrixHtmlLink { }
+        img := mod.widgets.MatrixHtmlImage { }
+        codeblock := mod.widgets.MatrixHtmlCodeBlock { }
 
         body: "[<i>HTML message placeholder</i>]",
     }
@@ -518,6 +667,217 @@ impl ScriptHook for MatrixHtmlSpan {
 
 
 
+/// The height of a custom emoji image, which matches the height of a line of message text.
+const EMOTICON_IMAGE_HEIGHT: f64 = 20.0;
+/// The default height of a non-emoji inline image that doesn't specify its own height.
+const DEFAULT_INLINE_IMAGE_HEIGHT: f64 = 64.0;
+/// The maximum height of a non-emoji inline image.
+const MAX_INLINE_IMAGE_HEIGHT: f64 = 128.0;
+
+/// A widget used to display a single HTML `<img>` tag.
+///
+/// Only `mxc://` sources are shown as images; per the Matrix spec, clients must not
+/// fetch images from other URLs, so those are shown as their `alt` text instead.
+#[derive(Script, Widget)]
+struct MatrixHtmlImage {
+    #[deref] view: View,
+
+    /// The `mxc://` URI from the `src` attribute, if it was valid.
+    #[rust] src: Option<OwnedMxcUri>,
+    /// The text from the `alt` attribute, or from the `title` attribute as a fallback.
+    #[rust] alt: String,
+    /// The height from the `height` attribute, if any.
+    #[rust] height: Option<f64>,
+    /// Whether this image is a custom emoji, i.e., has the `data-mx-emoticon` attribute.
+    #[rust] is_emoticon: bool,
+    /// Whether we're done trying to load this image,
+    /// regardless of whether it was loaded successfully.
+    #[rust] load_finished: bool,
+}
+
+impl ScriptHook for MatrixHtmlImage {
+    // After an MatrixHtmlImage instance has been instantiated, we must
+    // populate its struct fields from the `<img>` tag's attributes.
+    fn on_after_new_scoped(&mut self, _vm: &mut ScriptVm, scope: &mut Scope) {
+        let mut title = String::new();
+        if let Some(doc) = scope.props.get::<makepad_html::HtmlDoc>() {
+            let mut walker = doc.new_walker_with_index(scope.index + 1);
+            while let Some((lc, attr)) = walker.while_attr_lc() {
+                let attr = attr.trim_matches(['"', '\'']);
+                match lc {
+                    id!(src) => self.src = attr.starts_with("mxc://").then(|| attr.into()),
+                    id!(alt) => self.alt = attr.into(),
+                    id!(title) => title = attr.into(),
+                    id!(height) => self.height = attr.parse::<f64>().ok().filter(|h| *h > 0.0),
+                    id!(data-mx-emoticon) => self.is_emoticon = true,
+                    _ => ()
+                }
+            }
+        }
+        if self.alt.is_empty() {
+            self.alt = title;
+        }
+    }
+}
+
+impl Widget for MatrixHtmlImage {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        // The avatar cache signals the UI once a requested image has been fetched.
+        if matches!(event, Event::Signal) && !self.load_finished && self.src.is_some() {
+            self.redraw(cx);
+        }
+        self.view.handle_event(cx, event, scope)
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        if !self.load_finished {
+            self.try_load_image(cx);
+        }
+        self.view.draw_walk(cx, scope, walk)
+    }
+
+    fn text(&self) -> String {
+        self.alt.clone()
+    }
+}
+
+impl MatrixHtmlImage {
+    /// Tries to load this image's data from the avatar cache,
+    /// showing the `alt` text in its place until the image has been loaded.
+    fn try_load_image(&mut self, cx: &mut Cx) {
+        let alt_label = self.label(cx, ids!(alt_label));
+        alt_label.set_text(cx, &self.alt);
+        let Some(src) = self.src.clone() else {
+            self.load_finished = true;
+            return;
+        };
+        match avatar_cache::get_or_fetch_avatar(cx, &src) {
+            AvatarCacheEntry::Loaded(data) => {
+                let mut image = self.image(cx, ids!(image));
+                if utils::load_png_or_jpg(&image, cx, &data).is_ok() {
+                    let size = if self.is_emoticon {
+                        EMOTICON_IMAGE_HEIGHT
+                    } else {
+                        self.height.unwrap_or(DEFAULT_INLINE_IMAGE_HEIGHT)
+                            .clamp(EMOTICON_IMAGE_HEIGHT, MAX_INLINE_IMAGE_HEIGHT)
+                    };
+                    script_apply_eval!(cx, image, {
+                        width: #(size),
+                        height: #(size),
+                    });
+                    image.set_visible(cx, true);
+                    alt_label.set_visible(cx, false);
+                }
+                self.load_finished = true;
+            }
+            AvatarCacheEntry::Failed => self.load_finished = true,
+            AvatarCacheEntry::Requested => { }
+        }
+    }
+}
+
+
+/// How long the checkmark is shown in place of a code block's copy button after copying its code.
+const CODE_COPIED_INDICATOR_SECS: f64 = 1.5;
+
+/// A widget used to display a `<codeblock>` tag, which is what fenced code blocks
+/// (`<pre><code>` tags) are rewritten into before an HTML message is shown.
+///
+/// The code is highlighted by a `CodeView` beneath a label of its language (if given),
+/// and can be copied to the clipboard or shown with its long lines unwrapped.
+#[derive(Script, Widget)]
+struct MatrixHtmlCodeBlock {
+    #[deref] view: View,
+
+    /// The language name from the `data-lang` attribute, which may be empty.
+    #[rust] language_name: String,
+    /// The plain-text code from the `data-code` attribute.
+    #[rust] code: String,
+    /// Whether the code has been populated into this widget's inner views.
+    #[rust] is_populated: bool,
+    /// Whether long lines of code are wrapped rather than scrolled horizontally.
+    #[rust(true)] wrap_lines: bool,
+    /// The timer for hiding the checkmark that is shown after copying the code.
+    #[rust] copied_timer: Timer,
+}
+
+impl ScriptHook for MatrixHtmlCodeBlock {
+    // After a MatrixHtmlCodeBlock instance has been instantiated, we must
+    // populate its struct fields from the `<codeblock>` tag's attributes.
+    fn on_after_new_scoped(&mut self, _vm: &mut ScriptVm, scope: &mut Scope) {
+        if let Some(doc) = scope.props.get::<makepad_html::HtmlDoc>() {
+            let mut walker = doc.new_walker_with_index(scope.index + 1);
+            while let Some((lc, attr)) = walker.while_attr_lc() {
+                let attr = attr.trim_matches(['"', '\'']);
+                match lc {
+                    id!(data-lang) => self.language_name = code_blocks::unescape_html(attr),
+                    id!(data-code) => self.code = code_blocks::unescape_html(attr),
+                    _ => ()
+                }
+            }
+        }
+        self.is_populated = false;
+    }
+}
+
+impl Widget for MatrixHtmlCodeBlock {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, _scope: &mut Scope) {
+        if self.copied_timer.is_event(event).is_some() {
+            self.view.button(cx, ids!(copied_button)).set_visible(cx, false);
+            self.view.button(cx, ids!(copy_button)).set_visible(cx, true);
+            self.copied_timer = Timer::empty();
+        }
+        // Our inner widgets must not see the parent `Html` widget's scope.
+        self.view.handle_event(cx, event, &mut Scope::empty());
+        self.widget_match_event(cx, event, &mut Scope::empty());
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        if !self.is_populated {
+            self.populate(cx);
+        }
+        // A code block always occupies its own lines within the parent `Html` widget's text flow.
+        if let Some(tf) = scope.data.get_mut::<TextFlow>() {
+            tf.new_line_collapsed(cx);
+        }
+        self.view.draw_walk(cx, &mut Scope::empty(), walk)
+    }
+
+    fn text(&self) -> String {
+        self.code.clone()
+    }
+}
+
+impl WidgetMatchEvent for MatrixHtmlCodeBlock {
+    fn handle_actions(&mut self, cx: &mut Cx, actions: &Actions, _scope: &mut Scope) {
+        if self.view.button(cx, ids!(wrap_button)).clicked(actions) {
+            self.wrap_lines = !self.wrap_lines;
+            self.view.widget(cx, ids!(wrapped_code)).set_visible(cx, self.wrap_lines);
+            self.view.view(cx, ids!(unwrapped_view)).set_visible(cx, !self.wrap_lines);
+            self.view.redraw(cx);
+        }
+        if self.view.button(cx, ids!(copy_button)).clicked(actions) {
+            cx.copy_to_clipboard(&self.code);
+            self.view.button(cx, ids!(copy_button)).set_visible(cx, false);
+            self.view.button(cx, ids!(copied_button)).set_visible(cx, true);
+            cx.stop_timer(self.copied_timer);
+            self.copied_timer = cx.start_timeout(CODE_COPIED_INDICATOR_SECS);
+            self.view.redraw(cx);
+        }
+    }
+}
+
+impl MatrixHtmlCodeBlock {
+    /// Shows this block's language and code in both the wrapped and unwrapped views.
+    fn populate(&mut self, cx: &mut Cx) {
+        self.view.label(cx, ids!(language_label)).set_text(cx, &self.language_name);
+        self.view.widget(cx, ids!(wrapped_code)).set_text(cx, &self.code);
+        self.view.widget(cx, ids!(unwrapped_view.unwrapped_code)).set_text(cx, &self.code);
+        self.is_populated = true;
+    }
+}
+
+
 /// The possible states that a spoiler can be in: hidden or revealed.
 ///
 /// The enclosed `reason` string is an optional reason given for why
@@ -701,8 +1061,11 @@ impl HtmlOrPlaintext {
     }
 
     /// Sets the HTML content, making the HTML visible and the plaintext invisible.
+    ///
+    /// Any code blocks within the given HTML are shown with syntax highlighting.
     pub fn show_html<T: AsRef<str>>(&mut self, cx: &mut Cx, html_body: T) {
-        self.html(cx, ids!(html_view.html)).set_text(cx, html_body.as_ref());
+        let html_body = code_blocks::rewrite_code_blocks(html_body.as_ref());
+        self.html(cx, ids!(html_view.html)).set_text(cx, &html_body);
         self.view(cx, ids!(html_view)).set_visible(cx, true);
         self.view(cx, ids!(plaintext_view)).set_visible(cx, false);
     }
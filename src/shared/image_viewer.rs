@@ -2,7 +2,10 @@
 //!
 //! There are 2 types of ImageViewerAction handled by this widget. They are "Show" and "Hide".
 //! ImageViewerRef has 4 public methods, `configure_zoom`, `show_loading`, `show_loaded` and `reset`.
-use std::sync::{mpsc::Receiver, Arc};
+//!
+//! The same viewer is also used to play videos: the video's poster frame is shown
+//! while the full video file is loading, after which the video is played over it.
+use std::{path::PathBuf, sync::{mpsc::Receiver, Arc}};
 
 use chrono::{DateTime, Local};
 use makepad_widgets::{
@@ -193,6 +196,16 @@ script_mod! {
             }
         }
 
+        video_layer := View {
+            visible: false
+            width: Fill, height: Fill,
+            align: Align{x: 0.5, y: 0.5}
+
+            video := Video {
+                width: Fill, height: Fill,
+            }
+        }
+
         metadata_view := View {
             width: Fill, height: Fill,
             margin: 20,
@@ -320,6 +333,12 @@ script_mod! {
                     draw_icon +: { svg: (ICON_ROTATE_CW) }
                 }
 
+                open_externally_button := mod.widgets.ImageViewerButton {
+                    visible: false
+                    draw_icon +: { svg: (ICON_EXTERNAL_LINK) }
+                    icon_walk: Walk{width: 23, height: 23}
+                }
+
                 reset_button := mod.widgets.ImageViewerButton {
                     draw_icon +: { svg: (ICON_JUMP) }
                     icon_walk: Walk{width: 25, height: 25, margin: Inset{bottom: 2}}
@@ -479,6 +498,10 @@ struct ImageViewer {
     /// Timer used to animate-out (hide) the UI view after the latest user input.
     #[rust] hide_ui_timer: Timer,
     #[rust] capped_dimension: DVec2,
+    /// The receiver for the result of saving a video file to disk such that it can be played.
+    #[rust] video_receiver: Option<Receiver<Result<PathBuf, String>>>,
+    /// The path of the video file being played, if a video is being shown.
+    #[rust] video_path: Option<PathBuf>,
 }
 
 impl Widget for ImageViewer {
@@ -682,6 +705,21 @@ impl Widget for ImageViewer {
             }
         }
 
+        if let (Event::Signal, Some(receiver)) = (event, &self.video_receiver) {
+            match receiver.try_recv() {
+                Ok(Ok(path)) => {
+                    self.video_receiver = None;
+                    self.play_video(cx, path);
+                }
+                Ok(Err(error)) => {
+                    self.video_receiver = None;
+                    error!("Failed to save video file for playback: {error}");
+                    self.show_error(cx, &ImageViewerError::BadData);
+                }
+                Err(_) => {}
+            }
+        }
+
         let animator_action = self.animator_handle_event(cx, event);
         if self.next_frame.is_event(event).is_some() {
             self.display_using_texture(cx);
@@ -730,6 +768,14 @@ impl MatchEvent for ImageViewer {
         if self.view.button(cx, ids!(reset_button)).clicked(actions) {
             self.reset(cx);
         }
+        if self.view.button(cx, ids!(open_externally_button)).clicked(actions) {
+            if let Some(path) = self.video_path.as_ref() {
+                let path = path.display();
+                if let Err(e) = robius_open::Uri::new(&format!("file://{path}")).open() {
+                    error!("Failed to open video {path} externally: {e:?}");
+                }
+            }
+        }
         if self
             .view
             .button(cx, ids!(zoom_out_button))
@@ -791,6 +837,9 @@ impl MatchEvent for ImageViewer {
                     LoadState::Loaded(image_bytes) => {
                         self.show_loaded(cx, image_bytes);
                     }
+                    LoadState::LoadedVideo { data, file_name } => {
+                        self.show_loaded_video(cx, Arc::clone(data), file_name.clone());
+                    }
                     LoadState::FinishedBackgroundDecoding => {
                         self.is_loaded = true;
                         self.hide_footer(cx);
@@ -813,6 +862,11 @@ impl ImageViewer {
         self.mouse_cursor_hover_over_image = false; // Reset hover state
         self.receiver = None;
         self.is_loaded = false;
+        self.video_receiver = None;
+        if self.video_path.take().is_some() {
+            self.view.video(cx, ids!(video_layer.video)).stop_and_cleanup_resources(cx);
+            self.set_video_mode(cx, false);
+        }
         self.image_container_size = DVec2::new();
         self.ui_visible_toggle = false;
         cx.stop_timer(self.hide_ui_timer);
@@ -891,6 +945,72 @@ impl ImageViewer {
         self.hide_ui_timer = cx.start_timeout(SHOW_UI_DURATION);
     }
 
+    /// Saves the given video file to disk on a background thread, and then plays it.
+    ///
+    /// The video is saved to the app's temp directory under the given `file_name`,
+    /// as platform video players only support playing files (not in-memory data).
+    pub fn show_loaded_video(&mut self, cx: &mut Cx, data: Arc<[u8]>, file_name: String) {
+        if self.video_receiver.is_some() || self.video_path.is_some() {
+            return;
+        }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.video_receiver = Some(receiver);
+        cx.spawn_thread(move || {
+            let temp_dir = crate::temp_storage::get_temp_dir_path();
+            let path = temp_dir.join(&file_name);
+            // Only reuse a previously-written file if it is complete and matches this video.
+            let result = if std::fs::metadata(&path).is_ok_and(|m| m.len() == data.len() as u64) {
+                Ok(path)
+            } else {
+                // Write to a unique partial file first, such that the video player
+                // (or a concurrent viewer) never sees a partially-written video.
+                let nanos = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_nanos());
+                let partial_path = temp_dir.join(format!(".{file_name}.{}.{nanos}.part", std::process::id()));
+                std::fs::write(&partial_path, &data)
+                    .and_then(|()| std::fs::rename(&partial_path, &path))
+                    .map(|()| path)
+                    .map_err(|e| {
+                        let _ = std::fs::remove_file(&partial_path);
+                        e.to_string()
+                    })
+            };
+            let _ = sender.send(result);
+            SignalToUI::set_ui_signal();
+        });
+    }
+
+    /// Plays the video file at the given path over the video's poster frame.
+    fn play_video(&mut self, cx: &mut Cx, path: PathBuf) {
+        self.is_loaded = true;
+        self.hide_footer(cx);
+        self.set_video_mode(cx, true);
+        let video = self.view.video(cx, ids!(video_layer.video));
+        video.set_source(VideoDataSource::Filesystem {
+            path: path.to_string_lossy().into_owned(),
+        });
+        video.begin_playback(cx);
+        self.video_path = Some(path);
+        self.view.redraw(cx);
+    }
+
+    /// Shows either the video layer and its buttons, or the image and its zoom/rotate buttons.
+    fn set_video_mode(&mut self, cx: &mut Cx, is_video: bool) {
+        self.view.view(cx, ids!(video_layer)).set_visible(cx, is_video);
+        self.view.view(cx, ids!(rotated_image_container)).set_visible(cx, !is_video);
+        self.view.button(cx, ids!(open_externally_button)).set_visible(cx, is_video);
+        for button in [
+            ids!(zoom_out_button),
+            ids!(zoom_in_button),
+            ids!(rotate_ccw_button),
+            ids!(rotate_cw_button),
+            ids!(reset_button),
+        ] {
+            self.view.button(cx, button).set_visible(cx, !is_video);
+        }
+    }
+
     /// Displays an image in the image viewer widget using the provided texture.
     /// 
     /// `Texture` is an optional `Texture` that can be set to display an image. If `None`, the image is cleared.
@@ -1124,6 +1244,12 @@ pub enum LoadState {
     Loading(Option<Texture>, Option<ImageViewerMetaData>),
     /// The image has been successfully loaded given the data.
     Loaded(Arc<[u8]>),
+    /// A video file has been successfully loaded given the data,
+    /// and should be saved to a file with the given name in order to be played.
+    LoadedVideo {
+        data: Arc<[u8]>,
+        file_name: String,
+    },
     /// The image has been decoded from background thread.
     FinishedBackgroundDecoding,
     /// An error occurred while loading the image, with specific error type.
@@ -0,0 +1,225 @@
+//! A small static map thumbnail that shows a marker at a given location.
+//!
+//! The map is composed of a 3x3 grid of slippy-map tiles centered on the location,
+//! which are fetched and cached (both in memory and on disk) by the [`map_tiles`] module.
+
+use makepad_widgets::*;
+
+use crate::{
+    map_tiles::{self, MAP_THUMBNAIL_ZOOM, MapTileCacheEntry, TILE_SIZE, TileId},
+    utils,
+};
+
+/// Tiles are drawn at half their native size, such that the thumbnail shows more of the area.
+const TILE_DISPLAY_SIZE: f64 = TILE_SIZE / 2.0;
+/// The size of the visible map area, which must be smaller than the 3x3 tile grid.
+const THUMBNAIL_WIDTH: f64 = 256.0;
+const THUMBNAIL_HEIGHT: f64 = 160.0;
+
+script_mod! {
+    use mod.prelude.widgets.*
+    use mod.widgets.*
+
+    mod.widgets.MapTileImage = Image {
+        width: 128, height: 128
+        fit: ImageFit.Stretch
+    }
+
+    mod.widgets.MapTileRow = View {
+        width: Fit, height: Fit
+        flow: Right
+    }
+
+    mod.widgets.MapThumbnail = #(MapThumbnail::register_widget(vm)) {
+        visible: false
+        width: Fit, height: Fit
+        flow: Down
+        spacing: 2
+
+        map_view := RoundedView {
+            width: 256, height: 160
+            flow: Overlay
+            clip_x: true, clip_y: true
+            show_bg: true
+            draw_bg +: {
+                color: #E8E4DC
+                border_radius: 4.0
+            }
+
+            tiles := View {
+                width: 384, height: 384
+                flow: Down
+                row_0 := mod.widgets.MapTileRow {
+                    tile_0 := mod.widgets.MapTileImage { }
+                    tile_1 := mod.widgets.MapTileImage { }
+                    tile_2 := mod.widgets.MapTileImage { }
+                }
+                row_1 := mod.widgets.MapTileRow {
+                    tile_0 := mod.widgets.MapTileImage { }
+                    tile_1 := mod.widgets.MapTileImage { }
+                    tile_2 := mod.widgets.MapTileImage { }
+                }
+                row_2 := mod.widgets.MapTileRow {
+                    tile_0 := mod.widgets.MapTileImage { }
+                    tile_1 := mod.widgets.MapTileImage { }
+                    tile_2 := mod.widgets.MapTileImage { }
+                }
+            }
+
+            marker_layer := View {
+                width: Fill, height: Fill
+                align: Align{x: 0.5, y: 0.5}
+                marker := RoundedView {
+                    width: 16, height: 16
+                    show_bg: true
+                    draw_bg +: {
+                        color: (COLOR_FG_DANGER_RED)
+                        border_radius: 8.0
+                        border_size: 2.5
+                        border_color: #fff
+                    }
+                }
+            }
+        }
+
+        attribution_label := Label {
+            width: Fit, height: Fit
+            draw_text +: {
+                color: (COLOR_MESSAGE_NOTICE_TEXT),
+                text_style: MESSAGE_TEXT_STYLE { font_size: 7.5 },
+            }
+            text: ""
+        }
+    }
+}
+
+/// Returns the widget path of the tile image at the given column and row of the 3x3 grid.
+fn tile_image_id(column: usize, row: usize) -> &'static [LiveId] {
+    match (row, column) {
+        (0, 0) => ids!(tiles.row_0.tile_0),
+        (0, 1) => ids!(tiles.row_0.tile_1),
+        (0, 2) => ids!(tiles.row_0.tile_2),
+        (1, 0) => ids!(tiles.row_1.tile_0),
+        (1, 1) => ids!(tiles.row_1.tile_1),
+        (1, 2) => ids!(tiles.row_1.tile_2),
+        (2, 0) => ids!(tiles.row_2.tile_0),
+        (2, 1) => ids!(tiles.row_2.tile_1),
+        _ => ids!(tiles.row_2.tile_2),
+    }
+}
+
+/// A small map that shows a marker at a given location.
+#[derive(Script, ScriptHook, Widget)]
+pub struct MapThumbnail {
+    #[deref] view: View,
+
+    /// The tiles in the 3x3 grid, in row-major order,
+    /// along with whether each one is still waiting to be loaded.
+    ///
+    /// A tile is `None` if it would be beyond the top or bottom edge of the map.
+    #[rust] tiles: [Option<(TileId, bool)>; 9],
+}
+
+impl Widget for MapThumbnail {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        if let Event::Signal = event {
+            if self.tiles.iter().flatten().any(|(_, pending)| *pending) {
+                map_tiles::process_map_tile_updates(cx);
+                self.load_pending_tiles(cx);
+            }
+        }
+        self.view.handle_event(cx, event, scope);
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
+
+impl MapThumbnail {
+    /// Shows a map centered on the given coordinates.
+    ///
+    /// Returns `false` and hides the map if the coordinates can't be shown on a map,
+    /// or if the user hasn't enabled map thumbnails in the map tile settings.
+    fn set_location(&mut self, cx: &mut Cx, latitude: f64, longitude: f64) -> bool {
+        let tile_for_coordinates = map_tiles::get_global_config().enabled
+            .then(|| map_tiles::tile_for_coordinates(latitude, longitude, MAP_THUMBNAIL_ZOOM))
+            .flatten();
+        let Some((center_tile, frac_x, frac_y)) = tile_for_coordinates else {
+            self.tiles = Default::default();
+            self.view.set_visible(cx, false);
+            return false;
+        };
+
+        // Shift the tile grid such that the location is at the center of the visible map area.
+        let left = THUMBNAIL_WIDTH / 2.0 - (1.0 + frac_x) * TILE_DISPLAY_SIZE;
+        let top = THUMBNAIL_HEIGHT / 2.0 - (1.0 + frac_y) * TILE_DISPLAY_SIZE;
+        let mut tiles_view = self.view.view(cx, ids!(tiles));
+        script_apply_eval!(cx, tiles_view, {
+            margin +: { top: #(top), left: #(left) },
+        });
+
+        for row in 0 .. 3 {
+            for column in 0 .. 3 {
+                let tile = center_tile.offset(column as i64 - 1, row as i64 - 1);
+                self.tiles[row * 3 + column] = tile.map(|tile| (tile, true));
+                self.view.image(cx, tile_image_id(column, row)).set_texture(cx, None);
+            }
+        }
+
+        let attribution = map_tiles::get_global_config().attribution;
+        let attribution_label = self.view.label(cx, ids!(attribution_label));
+        attribution_label.set_text(cx, &attribution);
+        attribution_label.set_visible(cx, !attribution.trim().is_empty());
+
+        self.view.set_visible(cx, true);
+        self.load_pending_tiles(cx);
+        true
+    }
+
+    /// Loads every tile that is now available into its image, fetching any that aren't yet cached.
+    fn load_pending_tiles(&mut self, cx: &mut Cx) {
+        let mut any_loaded = false;
+        for (index, slot) in self.tiles.iter_mut().enumerate() {
+            let Some((tile, pending)) = slot else { continue };
+            if !*pending {
+                continue;
+            }
+            match map_tiles::get_or_fetch_map_tile(cx, *tile) {
+                MapTileCacheEntry::Loaded(data) => {
+                    let image = self.view.image(cx, tile_image_id(index % 3, index / 3));
+                    if let Err(e) = utils::load_png_or_jpg(&image, cx, &data) {
+                        error!("Failed to load map tile {tile:?} into an image: {e:?}");
+                    }
+                    *pending = false;
+                    any_loaded = true;
+                }
+                // Leave the map's background color showing through in place of this tile.
+                MapTileCacheEntry::Failed => *pending = false,
+                MapTileCacheEntry::Requested => {}
+            }
+        }
+        if any_loaded {
+            self.view.redraw(cx);
+        }
+    }
+}
+
+impl MapThumbnailRef {
+    /// See [`MapThumbnail::set_location()`].
+    pub fn set_location(&self, cx: &mut Cx, latitude: f64, longitude: f64) -> bool {
+        let Some(mut inner) = self.borrow_mut() else { return false };
+        inner.set_location(cx, latitude, longitude)
+    }
+
+    /// Shows a map of the location in the given `geo:` URI, or hides the map if it's invalid.
+    pub fn set_geo_uri(&self, cx: &mut Cx, geo_uri: &str) -> bool {
+        match map_tiles::parse_geo_uri(geo_uri) {
+            Some((latitude, longitude)) => self.set_location(cx, latitude, longitude),
+            None => {
+                self.set_visible(cx, false);
+                false
+            }
+        }
+    }
+}
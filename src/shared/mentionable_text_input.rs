@@ -97,6 +97,7 @@
 use crate::app::AppState;
 use crate::avatar_cache::*;
 use crate::i18n::{AppLanguage, tr_key};
+use crate::image_packs::{ImagePack, ImagePacksAction};
 use crate::shared::avatar::AvatarWidgetRefExt;
 use crate::shared::bouncing_dots::BouncingDotsWidgetRefExt;
 use crate::shared::styles::COLOR_UNKNOWN_ROOM_AVATAR;
@@ -106,17 +107,19 @@ use crate::sliding_sync::{submit_async_request, MatrixRequest};
 
 use makepad_widgets::{makepad_draw::text::selection::Cursor, *};
 use matrix_sdk::ruma::{
-    events::{room::message::RoomMessageEventContent, Mentions},
-    OwnedRoomId, OwnedUserId,
+    events::{room::message::{FormattedBody, RoomMessageEventContent}, Mentions},
+    OwnedMxcUri, OwnedRoomId, OwnedUserId,
 };
 use matrix_sdk::RoomMemberships;
+use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
 use crate::home::room_screen::RoomScreenProps;
+use crate::room::text_formatting::{self, TextFormat};
 use crate::shared::command_text_input::CommandTextInput;
 use crate::LivePtr;
 
 // Channel types for member search communication
-use std::sync::{mpsc::Receiver, Arc};
+use std::{ops::Range, sync::{mpsc::Receiver, Arc}};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Result type for member search channel communication
@@ -182,6 +185,7 @@ enum PopupMode {
     None,
     Mention,
     SlashCommand,
+    Emoticon,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -197,7 +201,7 @@ pub(crate) struct ParsedSlashCommand {
     pub target_localpart: Option<String>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 struct TrackedVisibleMention {
     user_id: OwnedUserId,
     visible_text: String,
@@ -205,6 +209,15 @@ struct TrackedVisibleMention {
     end: usize,
 }
 
+/// A snapshot of the mentions within the text of a `MentionableTextInput`,
+/// which can be saved alongside that text (e.g., in a draft) and later restored with it.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MentionsState {
+    #[serde(default)]
+    tracked_visible_mentions: Vec<TrackedVisibleMention>,
+    #[serde(default)]
+    possible_room_mention: bool,
+}
 const MENTION_POPUP_HEADER_TEXT: &str = "Users in this Room";
 const SLASH_COMMANDS: &[SlashCommand] = &[
     SlashCommand {
@@ -303,6 +316,172 @@ fn matching_slash_commands(search_text: &str) -> Vec<SlashCommand> {
         .collect()
 }
 
+/// The minimum number of characters typed after a `:` before custom emoji are suggested.
+const MIN_EMOTICON_QUERY_LEN: usize = 2;
+/// The maximum number of custom emoji shown in the suggestion popup.
+const MAX_EMOTICON_SUGGESTIONS: usize = 20;
+
+/// A custom emoji (an MSC2545 emoticon) that can be suggested via `:shortcode:` autocomplete.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct EmoticonSuggestion {
+    shortcode: String,
+    url: OwnedMxcUri,
+}
+
+/// Collects the custom emoji from the given image packs,
+/// keeping only the first emoji for each shortcode.
+fn emoticon_suggestions_from_packs(packs: &[ImagePack]) -> Vec<EmoticonSuggestion> {
+    let mut suggestions: Vec<EmoticonSuggestion> = Vec::new();
+    for image in packs.iter().flat_map(ImagePack::emoticons) {
+        if suggestions.iter().all(|s| s.shortcode != image.shortcode) {
+            suggestions.push(EmoticonSuggestion {
+                shortcode: image.shortcode.clone(),
+                url: image.url.clone(),
+            });
+        }
+    }
+    suggestions
+}
+
+fn is_shortcode_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '+')
+}
+
+/// Returns true if the given character can come right before or after a `:shortcode:`.
+fn is_shortcode_boundary(ch: Option<char>) -> bool {
+    ch.is_none_or(|ch| ch.is_whitespace() || (ch.is_ascii_punctuation() && ch != ':'))
+}
+
+/// Finds the byte position of the `:` that starts a partially-typed `:shortcode`
+/// ending at the given cursor position, e.g., `:blo` in `hello :blo`.
+fn find_emoticon_trigger_position(text: &str, cursor_pos: usize) -> Option<usize> {
+    let before_cursor = text.get(..cursor_pos)?;
+    let colon_pos = before_cursor.rfind(':')?;
+    let query = &before_cursor[colon_pos + 1..];
+    if query.chars().count() < MIN_EMOTICON_QUERY_LEN || !query.chars().all(is_shortcode_char) {
+        return None;
+    }
+    is_shortcode_boundary(before_cursor[..colon_pos].chars().next_back()).then_some(colon_pos)
+}
+
+/// Returns the custom emoji whose shortcodes contain the given search text,
+/// with the ones that start with the search text listed first.
+fn matching_emoticons<'e>(
+    emoticons: &'e [EmoticonSuggestion],
+    search_text: &str,
+) -> Vec<&'e EmoticonSuggestion> {
+    let query = search_text.trim_start_matches(':').to_lowercase();
+    let (mut matches, other_matches): (Vec<_>, Vec<_>) = emoticons
+        .iter()
+        .filter(|emoticon| emoticon.shortcode.to_lowercase().contains(&query))
+        .partition(|emoticon| emoticon.shortcode.to_lowercase().starts_with(&query));
+    matches.extend(other_matches);
+    matches.truncate(MAX_EMOTICON_SUGGESTIONS);
+    matches
+}
+
+/// Returns the inline HTML `<img>` tag that displays the given custom emoji.
+fn emoticon_html(emoticon: &EmoticonSuggestion) -> String {
+    let shortcode = htmlize::escape_attribute(format!(":{}:", emoticon.shortcode));
+    format!(
+        "<img data-mx-emoticon src=\"{}\" alt=\"{shortcode}\" title=\"{shortcode}\" />",
+        htmlize::escape_attribute(emoticon.url.as_str()),
+    )
+}
+
+/// Returns the byte ranges of all inline code spans and code blocks in the given Markdown.
+fn markdown_code_ranges(markdown: &str) -> Vec<Range<usize>> {
+    pulldown_cmark::Parser::new(markdown)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            pulldown_cmark::Event::Code(_)
+            | pulldown_cmark::Event::Start(pulldown_cmark::Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the byte ranges of all tags (including their attributes)
+/// and of the content of all `<code>` and `<pre>` elements in the given HTML.
+fn html_tag_and_code_ranges(html: &str) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut code_depth = 0usize;
+    let mut code_start = 0;
+    let mut search_from = 0;
+    while let Some(relative_open) = html[search_from..].find('<') {
+        let open = search_from + relative_open;
+        let close = html[open..].find('>').map_or(html.len(), |i| open + i + 1);
+        let tag = html[open + 1..close].trim_start_matches('/');
+        let name_len = tag.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(tag.len());
+        let name = &tag[..name_len];
+        if name.eq_ignore_ascii_case("code") || name.eq_ignore_ascii_case("pre") {
+            if html[open + 1..].starts_with('/') {
+                code_depth = code_depth.saturating_sub(1);
+                if code_depth == 0 {
+                    ranges.push(code_start..close);
+                }
+            } else {
+                if code_depth == 0 {
+                    code_start = open;
+                }
+                code_depth += 1;
+            }
+        } else if code_depth == 0 {
+            ranges.push(open..close);
+        }
+        search_from = close;
+    }
+    if code_depth > 0 {
+        ranges.push(code_start..html.len());
+    }
+    ranges
+}
+
+/// Replaces each standalone `:shortcode:` of the given custom emoji in `text`
+/// with that emoji's inline HTML `<img>` tag.
+///
+/// Shortcodes that overlap any of the given `excluded` byte ranges,
+/// e.g., those within code or HTML tags, are left as is.
+///
+/// Returns `None` if `text` didn't contain any of the given shortcodes.
+fn replace_emoticon_shortcodes(
+    text: &str,
+    emoticons: &[EmoticonSuggestion],
+    excluded: &[Range<usize>],
+) -> Option<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut replaced_any = false;
+    let mut cursor = 0;
+    let mut search_from = 0;
+    while let Some(relative_colon) = text[search_from..].find(':') {
+        let colon = search_from + relative_colon;
+        if let Some(range) = excluded.iter().find(|range| range.contains(&colon)) {
+            search_from = range.end;
+            continue;
+        }
+        let matched = is_shortcode_boundary(text[..colon].chars().next_back())
+            .then(|| emoticons.iter().find(|emoticon| {
+                let end = colon + emoticon.shortcode.len() + 2;
+                text.get(colon + 1..end - 1) == Some(emoticon.shortcode.as_str())
+                    && text.get(end - 1..end) == Some(":")
+                    && is_shortcode_boundary(text[end..].chars().next())
+                    && !excluded.iter().any(|range| range.start < end && colon < range.end)
+            }))
+            .flatten();
+        if let Some(emoticon) = matched {
+            output.push_str(&text[cursor..colon]);
+            output.push_str(&emoticon_html(emoticon));
+            cursor = colon + emoticon.shortcode.len() + 2;
+            search_from = cursor;
+            replaced_any = true;
+        } else {
+            search_from = colon + 1;
+        }
+    }
+    output.push_str(&text[cursor..]);
+    replaced_any.then_some(output)
+}
+
 pub(crate) fn classify_known_slash_command_for_submission(text: &str) -> Option<SlashCommand> {
     let first_token = text.split_whitespace().next()?;
     SLASH_COMMANDS
@@ -537,6 +716,20 @@ fn reconcile_visible_mentions_after_text_change(
         .collect()
 }
 
+/// Returns the saved mentions that still match the given text,
+/// i.e., those whose visible text is still found at their saved byte range.
+fn restorable_visible_mentions(
+    text: &str,
+    tracked_visible_mentions: Vec<TrackedVisibleMention>,
+) -> Vec<TrackedVisibleMention> {
+    let mut mentions: Vec<_> = tracked_visible_mentions
+        .into_iter()
+        .filter(|mention| text.get(mention.start..mention.end) == Some(mention.visible_text.as_str()))
+        .collect();
+    mentions.sort_by_key(|mention| mention.start);
+    mentions
+}
+
 fn reset_visible_mention_tracking_for_programmatic_text_set(
     tracked_visible_mentions: &mut Vec<TrackedVisibleMention>,
     possible_room_mention: &mut bool,
@@ -845,6 +1038,61 @@ script_mod! {
         }
     }
 
+    // Template for custom emoji list items in the `:shortcode:` popup
+    mod.widgets.EmoticonListItem = View {
+        width: Fill
+        height: 36
+        margin: Inset{left: 3 right: 3}
+        padding: Inset{left: 10 right: 10 top: 4 bottom: 4}
+        cursor: MouseCursor.Hand
+        show_bg: true
+        draw_bg +: {
+            color: (COLOR_PRIMARY)
+            border_radius: 4.0
+            selected: instance(0.0)
+
+            pixel: fn() {
+                let sdf = Sdf2d.viewport(self.pos * self.rect_size)
+                sdf.box(0. 0. self.rect_size.x self.rect_size.y self.border_radius)
+                let highlight = #x1E90FF30
+                sdf.fill(Pal.premul(self.color.mix(highlight self.selected)))
+                return sdf.result
+            }
+        }
+
+        animator: Animator {
+            highlight: {
+                default: @off
+                off: AnimatorState {
+                    from: { all: Forward { duration: 0.12 } }
+                    apply: { draw_bg: { selected: 0.0 } }
+                }
+                on: AnimatorState {
+                    from: { all: Forward { duration: 0.08 } }
+                    apply: { draw_bg: { selected: 1.0 } }
+                }
+            }
+        }
+
+        flow: Right
+        spacing: 8.0
+        align: Align{y: 0.5}
+
+        image := Image {
+            width: 24
+            height: 24
+            fit: ImageFit.Smallest
+        }
+
+        shortcode := Label {
+            height: Fit
+            draw_text +: {
+                color: #222
+                text_style: REGULAR_TEXT {font_size: 12.0}
+            }
+        }
+    }
+
     // Template for loading indicator when members are being fetched
     mod.widgets.LoadingIndicator = View {
         width: Fill
@@ -968,6 +1216,7 @@ script_mod! {
         user_list_item: mod.widgets.UserListItem {}
         room_mention_list_item: mod.widgets.RoomMentionListItem {}
         slash_command_list_item: mod.widgets.SlashCommandListItem {}
+        emoticon_list_item: mod.widgets.EmoticonListItem {}
         loading_indicator: mod.widgets.LoadingIndicator {}
         no_matches_indicator: mod.widgets.NoMatchesIndicator {}
     }
@@ -1011,6 +1260,9 @@ pub struct MentionableTextInput {
     /// Template for slash command list items
     #[live]
     slash_command_list_item: Option<LivePtr>,
+    /// Template for custom emoji list items
+    #[live]
+    emoticon_list_item: Option<LivePtr>,
     /// Template for loading indicator
     #[live]
     loading_indicator: Option<LivePtr>,
@@ -1063,6 +1315,19 @@ pub struct MentionableTextInput {
     /// Which kind of popup content is currently active.
     #[rust]
     active_popup_mode: PopupMode,
+    /// The custom emoji available in the room whose image packs were last requested.
+    #[rust]
+    emoticons: Vec<EmoticonSuggestion>,
+    /// The room whose image packs were requested in order to populate `emoticons`.
+    #[rust]
+    emoticons_room_id: Option<OwnedRoomId>,
+    /// The custom emoji selected from the popup while composing the current message.
+    /// Their `:shortcode:`s are replaced with inline images when the message is sent.
+    #[rust]
+    selected_emoticons: Vec<EmoticonSuggestion>,
+    /// Whether any custom emoji images shown in the popup are still being fetched.
+    #[rust]
+    emoticon_images_pending: bool,
 }
 
 impl Widget for MentionableTextInput {
@@ -1088,7 +1353,7 @@ impl Widget for MentionableTextInput {
             }
         }
 
-        if self.is_slash_command_popup_active() {
+        if self.is_slash_command_popup_active() || self.is_emoticon_popup_active() {
             if let Event::KeyDown(key_event) = event {
                 if key_event.key_code == KeyCode::Escape {
                     self.close_mention_popup(cx);
@@ -1115,8 +1380,23 @@ impl Widget for MentionableTextInput {
             }
         }
 
+        // Intercept the keyboard shortcuts for formatting the selected text
+        // before the TextInput handles them as regular key presses.
+        if let Event::KeyDown(key_event) = event
+            && let Some(format) = TextFormat::from_key_event(key_event)
+            && cx.has_key_focus(self.cmd_text_input.text_input_ref().area())
+        {
+            self.apply_text_format(cx, format);
+            return;
+        }
+
         self.cmd_text_input.handle_event(cx, event, scope);
 
+        // The avatar cache signals the UI once a requested custom emoji image has been fetched.
+        if matches!(event, Event::Signal) && self.is_emoticon_popup_active() && self.emoticon_images_pending {
+            self.refresh_emoticon_popup(cx, scope);
+        }
+
         // Best practice: Always check Scope first to get current context
         // Scope represents the current widget context as passed down from parents
         let (scope_room_id, scope_member_count, scope_sync_pending) = {
@@ -1204,6 +1484,17 @@ impl Widget for MentionableTextInput {
                     }
                 }
 
+                if let Some(ImagePacksAction::Fetched { room_id, packs }) = action.downcast_ref() {
+                    if self.emoticons_room_id.as_ref() == Some(room_id) {
+                        self.emoticons = emoticon_suggestions_from_packs(packs);
+                        // Show suggestions for a shortcode that was typed before the packs were fetched.
+                        if has_focus {
+                            self.refresh_emoticon_popup(cx, scope);
+                        }
+                    }
+                    continue;
+                }
+
                 // Handle MentionableTextInputAction actions
                 if let Some(action) = action.downcast_ref::<MentionableTextInputAction>() {
                     match action {
@@ -1340,7 +1631,7 @@ impl Widget for MentionableTextInput {
                 self.pending_popup_cleanup = true;
                 // Guarantee cleanup executes even if search completes and stops requesting frames
                 cx.new_next_frame();
-            } else if !has_focus && self.is_slash_command_popup_active() {
+            } else if !has_focus && (self.is_slash_command_popup_active() || self.is_emoticon_popup_active()) {
                 // Defer the close by one frame: when open_slash_command_popup
                 // is invoked from a button click, set_key_focus is still
                 // pending here, so closing immediately kills the popup we
@@ -1408,6 +1699,11 @@ impl MentionableTextInput {
         self.set_popup_header_text(cx, tr_key(app_language, "slash_command.header"));
     }
 
+    fn set_popup_header_for_emoticons(&mut self, cx: &mut Cx, scope: &mut Scope) {
+        let app_language = Self::current_app_language(scope);
+        self.set_popup_header_text(cx, tr_key(app_language, "emoticon_popup.header"));
+    }
+
     fn active_search_text(&self) -> Option<String> {
         match &self.search_state {
             MentionSearchState::WaitingForMembers {
@@ -1483,6 +1779,10 @@ impl MentionableTextInput {
         self.active_popup_mode == PopupMode::SlashCommand
     }
 
+    fn is_emoticon_popup_active(&self) -> bool {
+        self.active_popup_mode == PopupMode::Emoticon
+    }
+
     /// Generate the next unique identifier for a background search job.
     fn allocate_search_id(&mut self) -> u64 {
         if self.next_search_id == 0 {
@@ -1767,6 +2067,95 @@ impl MentionableTextInput {
         self.redraw(cx);
     }
 
+    /// Requests the image packs of the given room, unless they were already requested.
+    fn request_emoticons(&mut self, room_id: &OwnedRoomId) {
+        if self.emoticons_room_id.as_ref() == Some(room_id) {
+            return;
+        }
+        self.emoticons.clear();
+        self.emoticons_room_id = Some(room_id.clone());
+        submit_async_request(MatrixRequest::FetchImagePacks { room_id: room_id.clone() });
+    }
+
+    fn add_emoticon_items(&mut self, cx: &mut Cx, emoticons: &[EmoticonSuggestion]) -> usize {
+        let Some(item_ptr) = self.emoticon_list_item else {
+            return 0;
+        };
+
+        self.emoticon_images_pending = false;
+        for emoticon in emoticons {
+            let item = crate::widget_ref_from_live_ptr(cx, Some(item_ptr));
+            item.label(cx, ids!(shortcode))
+                .set_text(cx, &format!(":{}:", emoticon.shortcode));
+            match get_or_fetch_avatar(cx, &emoticon.url) {
+                AvatarCacheEntry::Loaded(data) => {
+                    let _ = utils::load_png_or_jpg(&item.image(cx, ids!(image)), cx, &data);
+                }
+                AvatarCacheEntry::Requested => self.emoticon_images_pending = true,
+                AvatarCacheEntry::Failed => {}
+            }
+            self.cmd_text_input.add_item(cx, item);
+        }
+
+        emoticons.len()
+    }
+
+    fn update_emoticon_list(&mut self, cx: &mut Cx, scope: &mut Scope, search_text: &str) {
+        let emoticons: Vec<EmoticonSuggestion> = matching_emoticons(&self.emoticons, search_text)
+            .into_iter()
+            .cloned()
+            .collect();
+        if emoticons.is_empty() {
+            if self.is_emoticon_popup_active() {
+                self.close_mention_popup(cx);
+            }
+            return;
+        }
+
+        self.cancel_active_search();
+        self.search_state = MentionSearchState::Idle;
+        self.last_search_text = None;
+        self.loading_indicator_ref = None;
+        self.active_popup_mode = PopupMode::Emoticon;
+
+        self.cmd_text_input.clear_items(cx);
+        self.cmd_text_input.reset_list_scroll(cx);
+        self.set_popup_header_for_emoticons(cx, scope);
+
+        let items_added = self.add_emoticon_items(cx, &emoticons);
+
+        const EMOTICON_ITEM_HEIGHT: f64 = 36.0;
+        const LIST_PADDING: f64 = 4.0;
+        let max_scroll_height = if cx.display_context.is_desktop() {
+            DESKTOP_MAX_SCROLL_HEIGHT
+        } else {
+            MOBILE_MAX_SCROLL_HEIGHT
+        };
+        let content_height = (items_added as f64 * EMOTICON_ITEM_HEIGHT) + LIST_PADDING;
+        self.set_list_scroll_height(cx, content_height.min(max_scroll_height));
+
+        let popup = self.cmd_text_input.view(cx, ids!(popup));
+        popup.set_visible(cx, items_added > 0);
+        let text_input_area = self.cmd_text_input.text_input_ref().area();
+        if cx.has_key_focus(text_input_area) {
+            self.cmd_text_input.text_input_ref().set_key_focus(cx);
+        }
+
+        self.redraw(cx);
+    }
+
+    /// Re-shows the custom emoji popup for the `:shortcode` being typed at the cursor, if any.
+    fn refresh_emoticon_popup(&mut self, cx: &mut Cx, scope: &mut Scope) {
+        let text_input_ref = self.cmd_text_input.text_input_ref();
+        let text = text_input_ref.text();
+        let cursor_pos = text_input_ref.borrow().map_or(0, |p| p.cursor().index);
+        if let Some(trigger_pos) = find_emoticon_trigger_position(&text, cursor_pos) {
+            let search_text =
+                utils::safe_substring_by_byte_indices(&text, trigger_pos + 1, cursor_pos);
+            self.update_emoticon_list(cx, scope, &search_text);
+        }
+    }
+
     fn emit_primary_submit_action(&self, cx: &mut Cx, text: String) {
         let text_input = self.cmd_text_input.text_input(cx, ids!(text_input));
         cx.widget_action(
@@ -1989,10 +2378,50 @@ impl MentionableTextInput {
         self.pending_draw_focus_restore = true;
     }
 
+    fn on_emoticon_selected(&mut self, cx: &mut Cx, selected: WidgetRef) {
+        let shortcode_text = selected.label(cx, ids!(shortcode)).text();
+        let shortcode = shortcode_text.trim_matches(':');
+        let Some(emoticon) = self.emoticons.iter().find(|e| e.shortcode == shortcode).cloned() else {
+            return;
+        };
+
+        let text_input_ref = self.cmd_text_input.text_input_ref();
+        let current_text = text_input_ref.text();
+        let head = text_input_ref.borrow().map_or(0, |p| p.cursor().index);
+
+        if let Some(start_idx) = find_emoticon_trigger_position(&current_text, head) {
+            let shortcode_to_insert = format!(":{}: ", emoticon.shortcode);
+            let (new_text, new_pos) = apply_text_replacement_preserving_mentions(
+                &current_text,
+                start_idx,
+                head,
+                &shortcode_to_insert,
+                &mut self.tracked_visible_mentions,
+            );
+
+            self.set_input_text_preserving_mentions(cx, &new_text);
+            text_input_ref.set_cursor(
+                cx,
+                Cursor {
+                    index: new_pos,
+                    prefer_next_row: false,
+                },
+                false,
+            );
+            if !self.selected_emoticons.contains(&emoticon) {
+                self.selected_emoticons.push(emoticon);
+            }
+        }
+
+        self.close_mention_popup(cx);
+        self.pending_draw_focus_restore = true;
+    }
+
     fn on_popup_item_selected(&mut self, cx: &mut Cx, scope: &mut Scope, selected: WidgetRef) {
         match self.active_popup_mode {
             PopupMode::Mention => self.on_user_selected(cx, scope, selected),
             PopupMode::SlashCommand => self.on_slash_command_selected(cx, selected),
+            PopupMode::Emoticon => self.on_emoticon_selected(cx, selected),
             PopupMode::None => {}
         }
     }
@@ -2019,7 +2448,8 @@ impl MentionableTextInput {
                 &mut self.tracked_visible_mentions,
                 &mut self.possible_room_mention,
             );
-            if self.is_searching() || self.is_slash_command_popup_active() {
+            self.selected_emoticons.clear();
+            if self.is_searching() || self.is_slash_command_popup_active() || self.is_emoticon_popup_active() {
                 self.close_mention_popup(cx);
             }
             return;
@@ -2095,7 +2525,15 @@ impl MentionableTextInput {
             let search_text =
                 utils::safe_substring_by_byte_indices(&text, trigger_pos + 1, cursor_pos);
             self.update_slash_command_list(cx, scope, &search_text);
-        } else if self.is_searching() || self.is_slash_command_popup_active() {
+        } else if let Some(trigger_pos) = find_emoticon_trigger_position(&text, cursor_pos) {
+            if let Some(room_props) = scope.props.get::<RoomScreenProps>() {
+                let room_id = room_props.room_name_id.room_id().clone();
+                self.request_emoticons(&room_id);
+            }
+            let search_text =
+                utils::safe_substring_by_byte_indices(&text, trigger_pos + 1, cursor_pos);
+            self.update_emoticon_list(cx, scope, &search_text);
+        } else if self.is_searching() || self.is_slash_command_popup_active() || self.is_emoticon_popup_active() {
             self.close_mention_popup(cx);
         }
     }
@@ -2721,9 +3159,28 @@ impl MentionableTextInput {
             &mut self.tracked_visible_mentions,
             &mut self.possible_room_mention,
         );
+        self.selected_emoticons.clear();
         self.set_input_text_preserving_mentions(cx, text);
     }
 
+    /// Returns a snapshot of the mentions currently tracked within this input's text.
+    pub fn mentions_state(&self) -> MentionsState {
+        MentionsState {
+            tracked_visible_mentions: self.tracked_visible_mentions.clone(),
+            possible_room_mention: self.possible_room_mention,
+        }
+    }
+
+    /// Restores the given previously-saved mentions for this input's current text.
+    ///
+    /// Any saved mentions that no longer match the current text are discarded.
+    pub fn restore_mentions_state(&mut self, mentions: MentionsState) {
+        let text = self.text();
+        self.tracked_visible_mentions = restorable_visible_mentions(&text, mentions.tracked_visible_mentions);
+        self.possible_room_mention = mentions.possible_room_mention && contains_standalone_room_mention(&text);
+        self.last_text = text;
+    }
+
     /// Sets whether the current user can notify the entire room (@room mention)
     pub fn set_can_notify_room(&mut self, can_notify: bool) {
         self.can_notify_room = can_notify;
@@ -2734,6 +3191,53 @@ impl MentionableTextInput {
         self.can_notify_room
     }
 
+    /// Applies the given Markdown formatting to the selected text (or the cursor position),
+    /// keeping track of the mentions that the formatting doesn't split apart.
+    ///
+    /// This emits a `TextInputAction::Changed` action, just like the user typing would.
+    pub fn apply_text_format(&mut self, cx: &mut Cx, format: TextFormat) {
+        let text_input_ref = self.cmd_text_input.text_input_ref();
+        let text = text_input_ref.text();
+        let Some(selection) = text_input_ref.borrow().map(|p| p.selection()) else { return };
+        let edit = text_formatting::format_selection(
+            &text,
+            selection.start().index..selection.end().index,
+            format,
+        );
+        let new_text = edit.apply(&text);
+        self.tracked_visible_mentions = std::mem::take(&mut self.tracked_visible_mentions)
+            .into_iter()
+            .filter_map(|mention| {
+                let range = edit.map_range(mention.start..mention.end)?;
+                Some(TrackedVisibleMention { start: range.start, end: range.end, ..mention })
+            })
+            .collect();
+        self.set_input_text_preserving_mentions(cx, &new_text);
+
+        // Select the formatted text, such that the user can keep typing over it.
+        text_input_ref.set_cursor(
+            cx,
+            Cursor {
+                index: edit.new_selection.start,
+                prefer_next_row: false,
+            },
+            false,
+        );
+        text_input_ref.set_cursor(
+            cx,
+            Cursor {
+                index: edit.new_selection.end,
+                prefer_next_row: false,
+            },
+            true,
+        );
+        text_input_ref.set_key_focus(cx);
+        cx.widget_action(
+            text_input_ref.widget_uid(),
+            makepad_widgets::text_input::TextInputAction::Changed(new_text),
+        );
+    }
+
     fn set_input_text_preserving_mentions(&mut self, cx: &mut Cx, text: &str) {
         self.cmd_text_input.text_input_ref().set_text(cx, text);
         self.last_text = text.to_owned();
@@ -2770,6 +3274,25 @@ impl MentionableTextInputRef {
         }
     }
 
+    /// See [`MentionableTextInput::apply_text_format()`].
+    pub fn apply_text_format(&self, cx: &mut Cx, format: TextFormat) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.apply_text_format(cx, format);
+        }
+    }
+
+    /// See [`MentionableTextInput::mentions_state()`].
+    pub fn mentions_state(&self) -> MentionsState {
+        self.borrow().map(|inner| inner.mentions_state()).unwrap_or_default()
+    }
+
+    /// See [`MentionableTextInput::restore_mentions_state()`].
+    pub fn restore_mentions_state(&self, mentions: MentionsState) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.restore_mentions_state(mentions);
+        }
+    }
+
     pub fn open_slash_command_popup(&self, cx: &mut Cx, scope: &mut Scope) {
         if let Some(mut inner) = self.borrow_mut() {
             inner.open_slash_command_popup(cx, scope);
@@ -2792,13 +3315,14 @@ impl MentionableTextInputRef {
     /// This method handles /html, /plain prefixes and defaults to markdown.
     pub fn create_message_with_mentions(&self, entered_text: &str) -> RoomMessageEventContent {
         let Some(inner) = self.borrow() else {
-            return create_message_with_tracked_mentions(entered_text, &[], false);
+            return create_message_with_tracked_mentions(entered_text, &[], false, &[]);
         };
 
         create_message_with_tracked_mentions(
             entered_text,
             &inner.tracked_visible_mentions,
             inner.possible_room_mention,
+            &inner.selected_emoticons,
         )
     }
 
@@ -2808,7 +3332,7 @@ impl MentionableTextInputRef {
     ) -> RoomMessageEventContent {
         let Some(inner) = self.borrow() else {
             let normalized_text = normalize_command_with_at_suffix_for_send(entered_text);
-            return create_message_with_tracked_mentions(&normalized_text, &[], false);
+            return create_message_with_tracked_mentions(&normalized_text, &[], false, &[]);
         };
 
         let normalized_text = normalize_command_with_at_suffix_for_send(entered_text);
@@ -2817,6 +3341,7 @@ impl MentionableTextInputRef {
                 entered_text,
                 &inner.tracked_visible_mentions,
                 inner.possible_room_mention,
+                &inner.selected_emoticons,
             );
         }
 
@@ -2829,14 +3354,18 @@ impl MentionableTextInputRef {
             &normalized_text,
             &adjusted_mentions,
             inner.possible_room_mention,
+            &inner.selected_emoticons,
         )
     }
 }
 
+/// Creates a message from the entered text, with the given mentions resolved into links
+/// and the `:shortcode:`s of the given custom emoji replaced with inline images.
 fn create_message_with_tracked_mentions(
     entered_text: &str,
     tracked_visible_mentions: &[TrackedVisibleMention],
     possible_room_mention: bool,
+    selected_emoticons: &[EmoticonSuggestion],
 ) -> RoomMessageEventContent {
     if let Some(html_text) = entered_text.strip_prefix("/html") {
         let resolved = resolve_visible_mentions_for_send(
@@ -2844,7 +3373,10 @@ fn create_message_with_tracked_mentions(
             tracked_visible_mentions,
             possible_room_mention,
         );
-        let message = RoomMessageEventContent::text_html(html_text, resolved.html_text);
+        let code_ranges = html_tag_and_code_ranges(&resolved.html_text);
+        let html_body = replace_emoticon_shortcodes(&resolved.html_text, selected_emoticons, &code_ranges)
+            .unwrap_or(resolved.html_text);
+        let message = RoomMessageEventContent::text_html(html_text, html_body);
         message.add_mentions(resolved.mentions)
     } else if let Some(plain_text) = entered_text.strip_prefix("/plain") {
         // Plain text messages don't support mentions
@@ -2855,7 +3387,14 @@ fn create_message_with_tracked_mentions(
             tracked_visible_mentions,
             possible_room_mention,
         );
-        let message = RoomMessageEventContent::text_markdown(resolved.markdown_text);
+        // Custom emoji only exist in the formatted body; the plain body keeps their `:shortcode:`s.
+        let code_ranges = markdown_code_ranges(&resolved.markdown_text);
+        let message = match replace_emoticon_shortcodes(&resolved.markdown_text, selected_emoticons, &code_ranges)
+            .and_then(FormattedBody::markdown)
+        {
+            Some(formatted) => RoomMessageEventContent::text_html(resolved.markdown_text, formatted.body),
+            None => RoomMessageEventContent::text_markdown(resolved.markdown_text),
+        };
         message.add_mentions(resolved.mentions)
     }
 }
@@ -3041,6 +3580,82 @@ mod tests {
         assert!(matching_slash_commands("zzzznotacommand").is_empty());
     }
 
+    fn test_emoticon(shortcode: &str) -> EmoticonSuggestion {
+        EmoticonSuggestion {
+            shortcode: shortcode.to_owned(),
+            url: format!("mxc://example.com/{shortcode}").into(),
+        }
+    }
+
+    #[test]
+    fn emoticon_trigger_is_found_after_whitespace() {
+        let text = "hello :bl";
+        assert_eq!(find_emoticon_trigger_position(text, text.len()), Some("hello ".len()));
+    }
+
+    #[test]
+    fn emoticon_trigger_requires_minimum_query_length() {
+        let text = "hello :b";
+        assert_eq!(find_emoticon_trigger_position(text, text.len()), None);
+    }
+
+    #[test]
+    fn emoticon_trigger_is_not_found_mid_word_or_after_completed_shortcode() {
+        let text = "https://example";
+        assert_eq!(find_emoticon_trigger_position(text, text.len()), None);
+        let text = "hi :blob: ";
+        assert_eq!(find_emoticon_trigger_position(text, text.len()), None);
+    }
+
+    #[test]
+    fn matching_emoticons_lists_prefix_matches_first() {
+        let emoticons = vec![test_emoticon("happy_blob"), test_emoticon("blobcat"), test_emoticon("cat")];
+        let matches: Vec<&str> = matching_emoticons(&emoticons, "blob")
+            .into_iter()
+            .map(|emoticon| emoticon.shortcode.as_str())
+            .collect();
+        assert_eq!(matches, ["blobcat", "happy_blob"]);
+    }
+
+    #[test]
+    fn replace_emoticon_shortcodes_only_replaces_standalone_shortcodes() {
+        let emoticons = vec![test_emoticon("blob")];
+        let replaced = replace_emoticon_shortcodes("hi :blob:! a:blob: :blobs:", &emoticons, &[])
+            .expect("standalone shortcode should be replaced");
+        assert_eq!(
+            replaced,
+            format!("hi {}! a:blob: :blobs:", emoticon_html(&emoticons[0])),
+        );
+        assert_eq!(replace_emoticon_shortcodes("no emoji here", &emoticons, &[]), None);
+    }
+
+    #[test]
+    fn replace_emoticon_shortcodes_skips_code_and_tags() {
+        let emoticons = vec![test_emoticon("blob")];
+        let blob = emoticon_html(&emoticons[0]);
+
+        let markdown = "`:blob:` :blob:\n```\n:blob:\n```\n";
+        let replaced = replace_emoticon_shortcodes(markdown, &emoticons, &markdown_code_ranges(markdown));
+        assert_eq!(replaced, Some(format!("`:blob:` {blob}\n```\n:blob:\n```\n")));
+
+        let html = "<a title=\" :blob: \">:blob:</a><pre><code> :blob: </code></pre>";
+        let replaced = replace_emoticon_shortcodes(html, &emoticons, &html_tag_and_code_ranges(html));
+        assert_eq!(replaced, Some(format!("<a title=\" :blob: \">{blob}</a><pre><code> :blob: </code></pre>")));
+    }
+
+    #[test]
+    fn test_markdown_message_with_emoticon_keeps_shortcode_in_plain_body() {
+        let emoticons = vec![test_emoticon("blob")];
+        let message = create_message_with_tracked_mentions("hi :blob:", &[], false, &emoticons);
+
+        assert_eq!(message.msgtype.body(), "hi :blob:");
+        let MessageType::Text(text_content) = &message.msgtype else {
+            panic!("expected text message");
+        };
+        let formatted = text_content.formatted.as_ref().expect("expected formatted body");
+        assert!(formatted.body.contains("<img data-mx-emoticon src=\"mxc://example.com/blob\""));
+    }
+
     #[test]
     fn classify_known_slash_command_for_submission_matches_first_token() {
         assert_eq!(
@@ -3260,6 +3875,32 @@ mod tests {
         assert!(!possible_room_mention);
     }
 
+    #[test]
+    fn test_restoring_saved_mentions_drops_spans_that_no_longer_match() {
+        let alice: OwnedUserId = "@alice:example.com".try_into().expect("valid user id");
+        let bob: OwnedUserId = "@bob:example.com".try_into().expect("valid user id");
+        let saved_mentions = vec![
+            TrackedVisibleMention {
+                user_id: bob,
+                visible_text: "@Bob".to_owned(),
+                start: "hi @Alice and ".len(),
+                end: "hi @Alice and @Bob".len(),
+            },
+            TrackedVisibleMention {
+                user_id: alice.clone(),
+                visible_text: "@Alice".to_owned(),
+                start: "hi ".len(),
+                end: "hi @Alice".len(),
+            },
+        ];
+
+        let restored = restorable_visible_mentions("hi @Alice and @Carol", saved_mentions);
+
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].user_id, alice);
+        assert_eq!(restored[0].start, "hi ".len());
+    }
+
     #[test]
     fn test_inserting_mention_before_existing_mention_shifts_older_span_correctly() {
         let alice: OwnedUserId = "@alice:example.com".try_into().expect("valid user id");
@@ -3405,7 +4046,7 @@ mod tests {
         ];
 
         let message =
-            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false);
+            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false, &[]);
 
         assert_eq!(
             message.msgtype.body(),
@@ -3433,7 +4074,7 @@ mod tests {
         }];
 
         let message =
-            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false);
+            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false, &[]);
 
         assert_eq!(message.msgtype.body(), "<p>Hello @Alice</p>");
         let MessageType::Text(text_content) = &message.msgtype else {
@@ -3467,7 +4108,7 @@ mod tests {
         }];
 
         let message =
-            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false);
+            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false, &[]);
 
         assert_eq!(message.msgtype.body(), "Hello @Alice");
         let MessageType::Text(text_content) = &message.msgtype else {
@@ -3489,7 +4130,7 @@ mod tests {
         }];
 
         let message =
-            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false);
+            create_message_with_tracked_mentions(entered_text, &tracked_mentions, false, &[]);
 
         assert_eq!(
             message.msgtype.body(),
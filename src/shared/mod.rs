@@ -1,14 +1,18 @@
 use makepad_widgets::ScriptVm;
 
 pub mod avatar;
+pub mod code_blocks;
 pub mod collapsible_header;
 pub mod expand_arrow;
 pub mod confirmation_modal;
+pub mod emoji_data;
+pub mod emoji_picker;
 pub mod file_upload_modal;
 pub mod helpers;
 pub mod html_or_plaintext;
 pub mod icon_button;
 pub mod jump_to_bottom_button;
+pub mod map_thumbnail;
 pub mod mentionable_text_input;
 pub mod popup_list;
 pub mod progress_bar;
@@ -37,12 +41,14 @@ pub fn script_mod(vm: &mut ScriptVm) {
     avatar::script_mod(vm);
     text_or_image::script_mod(vm);
     html_or_plaintext::script_mod(vm);
+    map_thumbnail::script_mod(vm);
     bouncing_dots::script_mod(vm);
     jump_to_bottom_button::script_mod(vm);
     popup_list::script_mod(vm);
     verification_badge::script_mod(vm);
     command_text_input::script_mod(vm);
     mentionable_text_input::script_mod(vm);
+    emoji_picker::script_mod(vm);
     restore_status_view::script_mod(vm);
     confirmation_modal::script_mod(vm);
     image_viewer::script_mod(vm);
@@ -9,7 +9,10 @@ script_mod! {
     mod.widgets.ICON_ADD_REACTION     = crate_resource("self://resources/icons/add_reaction.svg")
     mod.widgets.ICON_ADD_USER         = crate_resource("self://resources/icons/add_user.svg") // TODO: FIX
     mod.widgets.ICON_ADD_WALLET       = crate_resource("self://resources/icons/add_wallet.svg")
+    mod.widgets.ICON_FORWARD          = crate_resource("self://resources/icons/forward.svg")
     mod.widgets.ICON_FORBIDDEN        = crate_resource("self://resources/icons/forbidden.svg")
+    mod.widgets.ICON_BOOKMARK         = crate_resource("self://resources/icons/bookmark.svg")
+    mod.widgets.ICON_CALENDAR         = crate_resource("self://resources/icons/calendar.svg")
     mod.widgets.ICON_CHECKMARK        = crate_resource("self://resources/icons/checkmark.svg")
     mod.widgets.ICON_CLOSE            = crate_resource("self://resources/icons/close.svg")
     mod.widgets.ICON_CLOUD_CHECKMARK  = crate_resource("self://resources/icons/cloud_checkmark.svg")
@@ -29,7 +32,10 @@ script_mod! {
     mod.widgets.ICON_JUMP             = crate_resource("self://resources/icons/go_back.svg")
     mod.widgets.ICON_LOGOUT           = crate_resource("self://resources/icons/logout.svg")
     mod.widgets.ICON_LINK             = crate_resource("self://resources/icons/link.svg")
+    mod.widgets.ICON_MORE             = crate_resource("self://resources/icons/more.svg")
+    mod.widgets.ICON_PAUSE            = crate_resource("self://resources/icons/pause.svg")
     mod.widgets.ICON_PIN              = crate_resource("self://resources/icons/pin.svg")
+    mod.widgets.ICON_PLAY             = crate_resource("self://resources/icons/play.svg")
     mod.widgets.ICON_REPLY            = crate_resource("self://resources/icons/reply.svg")
     mod.widgets.ICON_SEARCH           = crate_resource("self://resources/icons/search.svg")
     mod.widgets.ICON_SEND             = crate_resource("self://resources/icon_send.svg")
@@ -42,6 +48,7 @@ script_mod! {
     mod.widgets.ICON_UPLOAD           = crate_resource("self://resources/icons/upload.svg")
     mod.widgets.ICON_VIEW_SOURCE      = crate_resource("self://resources/icons/view_source.svg")
     mod.widgets.ICON_WARNING          = crate_resource("self://resources/icons/warning.svg")
+    mod.widgets.ICON_WRAP_TEXT        = crate_resource("self://resources/icons/wrap_text.svg")
     mod.widgets.ICON_ZOOM_IN          = crate_resource("self://resources/icons/zoom_in.svg")
     mod.widgets.ICON_ZOOM_OUT         = crate_resource("self://resources/icons/zoom_out.svg")
     mod.widgets.ICON_ADD_ATTACHMENT   = crate_resource("self://resources/icons/add_attachment.svg")
@@ -9,23 +9,29 @@ use makepad_widgets::{error, log, warning, Cx, SignalToUI};
 use mime::{IMAGE_JPEG, IMAGE_PNG};
 use matrix_sdk_base::crypto::{DecryptionSettings, TrustRequirement};
 use matrix_sdk::{
-    config::RequestConfig, encryption::EncryptionSettings, event_handler::EventHandlerDropGuard, media::MediaRequestParameters, room::{edit::EditedContent, reply::Reply, IncludeRelations, ListThreadsOptions, RelationsOptions, RoomMember}, ruma::{
+    config::RequestConfig, encryption::EncryptionSettings, event_handler::EventHandlerDropGuard, media::MediaRequestParameters, room::{edit::EditedContent, reply::Reply, IncludeRelations, ListThreadsOptions, MessagesOptions, RelationsOptions, ReportedContentScore, RoomMember}, ruma::{
         api::{Direction, client::{
             account::register::v3::Request as RegistrationRequest,
-            room::{Visibility, create_room::v3::{Request as CreateRoomRequest, RoomPreset}},
+            room::{Visibility, create_room::v3::{Request as CreateRoomRequest, RoomPreset}, get_event_by_timestamp},
             directory::get_public_rooms_filtered,
             error::ErrorKind,
+            search::search_events,
             profile::{AvatarUrl, DisplayName, set_avatar_url},
             receipt::create_receipt::v3::ReceiptType,
             uiaa::{AuthData, AuthType, Dummy},
         }}, directory::{Filter as PublicRoomsFilter, RoomTypeFilter}, events::{
+            poll::{
+                start::PollKind,
+                unstable_start::{NewUnstablePollStartEventContent, UnstablePollAnswers, UnstablePollStartContentBlock, UnstablePollStartEventContent},
+            },
             relation::RelationType,
             room::{
-                encryption::RoomEncryptionEventContent, message::RoomMessageEventContent, power_levels::RoomPowerLevels, MediaSource
+                encryption::RoomEncryptionEventContent, message::RoomMessageEventContent, power_levels::RoomPowerLevels, ImageInfo, MediaSource
             },
+            sticker::StickerEventContent,
             space::{child::SpaceChildEventContent, parent::SpaceParentEventContent},
-            InitialStateEvent, MessageLikeEventType, StateEventType
-        }, matrix_uri::MatrixId, EventId, MatrixToUri, MatrixUri, MilliSecondsSinceUnixEpoch, OwnedEventId, OwnedMxcUri, OwnedRoomAliasId, OwnedRoomId, OwnedUserId, RoomOrAliasId, UserId, uint
+            AnySyncTimelineEvent, InitialStateEvent, MessageLikeEventType, StateEventType
+        }, matrix_uri::MatrixId, serde::Raw, EventId, MatrixToUri, MatrixUri, MilliSecondsSinceUnixEpoch, MxcUri, OwnedEventId, OwnedMxcUri, OwnedRoomAliasId, OwnedRoomId, OwnedTransactionId, OwnedUserId, RoomOrAliasId, UserId, uint
     }, sliding_sync::VersionBuilder, Client, ClientBuildError, Error, OwnedServerName, Room, RoomDisplayName, RoomMemberships, RoomState, SessionChange, SuccessorRoom
 };
 use matrix_sdk_ui::{
@@ -43,12 +49,12 @@ use std::io;
 use hashbrown::{HashMap, HashSet};
 use crate::{
     account_manager::{self, Account},
-    app::{AppStateAction, RoomFilterRemoteSearchAction}, app_data_dir, avatar_cache::AvatarUpdate, event_preview::{BeforeText, TextPreview, text_preview_of_raw_timeline_event, text_preview_of_timeline_item}, home::{
-        add_room::{CreatableSpacesAction, CreateRoomAction, CreateRoomContext, KnockResultAction}, invite_screen::{JoinRoomResultAction, LeaveRoomResultAction}, link_preview::{LinkPreviewData, LinkPreviewDataNonNumeric, LinkPreviewRateLimitResponse}, room_screen::{ActionResponseResultAction, InviteResultAction, ReportRoomResultAction, TimelineUpdate}, rooms_list::{self, InvitedRoomInfo, InviterInfo, JoinedRoomInfo, RoomsListUpdate, build_room_search_text, enqueue_rooms_list_update}, rooms_list_header::RoomsListHeaderAction, tombstone_footer::SuccessorRoomDetails
-    }, login::login_screen::LoginAction, logout::{logout_confirm_modal::LogoutAction, logout_state_machine::{LogoutConfig, is_logout_in_progress, logout_with_state_machine}}, media_cache::{MediaCacheEntry, MediaCacheEntryRef}, persistence::{self, ClientSessionPersisted, load_app_state, take_skip_app_state_restore_once}, profile::{
+    app::{AppStateAction, RoomFilterRemoteSearchAction}, app_data_dir, bookmarks::{self, Bookmark}, chat_export::{self, ChatExportOptions}, forward_message, image_packs::{self, ImagePacksAction}, live_location::{self, LiveLocationAction}, map_tiles::{self, MapTileRequest}, avatar_cache::AvatarUpdate, event_preview::{BeforeText, TextPreview, text_preview_of_raw_timeline_event, text_preview_of_timeline_item}, home::{
+        add_room::{CreatableSpacesAction, CreateRoomAction, CreateRoomContext, KnockResultAction}, invite_screen::{JoinRoomResultAction, LeaveRoomResultAction}, link_preview::{LinkPreviewData, LinkPreviewDataNonNumeric, LinkPreviewRateLimitResponse}, room_screen::{ActionResponseResultAction, InviteResultAction, ReportEventResultAction, ReportRoomResultAction, TimelineUpdate}, rooms_list::{self, InvitedRoomInfo, InviterInfo, JoinedRoomInfo, RoomsListUpdate, build_room_search_text, enqueue_rooms_list_update}, rooms_list_header::RoomsListHeaderAction, tombstone_footer::SuccessorRoomDetails
+    }, local_search_index, login::login_screen::LoginAction, scheduled_messages::{self, ScheduledMessage}, unsent_messages, logout::{logout_confirm_modal::LogoutAction, logout_state_machine::{LogoutConfig, is_logout_in_progress, logout_with_state_machine}}, media_cache::{MediaCacheEntry, MediaCacheEntryRef}, persistence::{self, ClientSessionPersisted, load_app_state, take_skip_app_state_restore_once}, profile::{
         user_profile::UserProfile,
         user_profile_cache::{UserProfileUpdate, enqueue_user_profile_update},
-    }, room::{FetchedRoomAvatar, FetchedRoomPreview, RoomPreviewAction}, shared::{
+    }, room::{FetchedRoomAvatar, FetchedRoomPreview, RoomPreviewAction, composer_drafts}, shared::{
         avatar::AvatarState, html_or_plaintext::MatrixLinkPillState, jump_to_bottom_button::UnreadMessageCount, popup_list::{PopupKind, enqueue_popup_notification}
     }, space_service_sync::space_service_loop, utils::{self, AVATAR_THUMBNAIL_FORMAT, RoomNameId, VecDiff, avatar_from_room_name}, verification::add_verification_event_handlers_and_sync_client
 };
@@ -653,6 +659,135 @@ pub enum RoomThreadsAction {
     },
 }
 
+/// A single message returned by a server-side message search.
+#[derive(Clone, Debug)]
+pub struct MessageSearchResult {
+    /// The ID and displayable name of the room that the message was sent in.
+    pub room_name_id: RoomNameId,
+    pub event_id: OwnedEventId,
+    /// The root event of the thread that this message was sent in, if any.
+    pub thread_root_event_id: Option<OwnedEventId>,
+    pub sender: OwnedUserId,
+    pub timestamp: MilliSecondsSinceUnixEpoch,
+    /// A plaintext preview of the message, prefixed by the sender's name.
+    pub preview: String,
+}
+
+/// Actions emitted in response to a [`MatrixRequest::SearchMessages`].
+#[derive(Clone, Debug)]
+pub enum SearchMessagesAction {
+    Loaded {
+        /// The room that was searched, or `None` if all joined rooms were searched.
+        room_id: Option<OwnedRoomId>,
+        search_term: String,
+        /// The `next_batch` token that was used to fetch this page of results.
+        from: Option<String>,
+        results: Vec<MessageSearchResult>,
+        /// The server's (possibly approximate) total number of results.
+        count: Option<u64>,
+        /// The token to use in order to fetch the next page of results, if any.
+        next_batch: Option<String>,
+    },
+    Failed {
+        room_id: Option<OwnedRoomId>,
+        search_term: String,
+        from: Option<String>,
+        error: String,
+    },
+}
+
+/// Actions emitted in response to a [`MatrixRequest::JumpToDate`].
+#[derive(Clone, Debug)]
+pub enum JumpToDateAction {
+    /// The first event on or after the requested date (or the last one before it) was found.
+    Found {
+        timeline_kind: TimelineKind,
+        event_id: OwnedEventId,
+    },
+    /// No event could be found near the requested date,
+    /// or the request failed (e.g., due to a network or permission error).
+    Failed {
+        timeline_kind: TimelineKind,
+        /// Whether the homeserver reported that there were no events near the requested date.
+        not_found: bool,
+        error: String,
+    },
+}
+
+/// Actions emitted in response to a [`MatrixRequest::ResolveRoomAlias`].
+#[derive(Clone, Debug)]
+pub enum ResolveRoomAliasAction {
+    Resolved {
+        room_alias: OwnedRoomAliasId,
+        room_id: OwnedRoomId,
+        /// The servers that are aware of the room, which can be used to preview or join it.
+        servers: Vec<OwnedServerName>,
+    },
+    Failed {
+        room_alias: OwnedRoomAliasId,
+        error: String,
+    },
+}
+
+/// A single revision of a message: either its original content or one of its edits.
+#[derive(Clone, Debug)]
+pub struct MessageRevision {
+    /// The ID of the original event or of the replacement event.
+    pub event_id: OwnedEventId,
+    pub timestamp: MilliSecondsSinceUnixEpoch,
+    /// The plaintext body of this revision,
+    /// or `None` if the revision is an encrypted event that could not be decrypted.
+    pub body: Option<String>,
+}
+
+/// Actions emitted in response to a [`MatrixRequest::FetchEditHistory`].
+#[derive(Clone, Debug)]
+pub enum EditHistoryAction {
+    Loaded {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+        /// All revisions of the message, starting with the original content,
+        /// in ascending order of their timestamps.
+        revisions: Vec<MessageRevision>,
+    },
+    Failed {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+        error: String,
+    },
+}
+
+/// Actions emitted in response to a [`MatrixRequest::RemoveRecentMessages`].
+#[derive(Clone, Debug)]
+pub enum RemoveRecentMessagesAction {
+    /// The removal is in progress: `processed` of the `total` events have been redacted
+    /// (or failed to be redacted) so far.
+    ///
+    /// If `total` is `None`, the sender's recent events are still being looked up.
+    Progress {
+        room_id: OwnedRoomId,
+        sender: OwnedUserId,
+        processed: usize,
+        total: Option<usize>,
+        /// Set this to `true` to stop redacting any further events.
+        cancelled: Arc<AtomicBool>,
+    },
+    /// The removal has finished, or was stopped early via the `cancelled` flag.
+    Finished {
+        room_id: OwnedRoomId,
+        sender: OwnedUserId,
+        removed: usize,
+        failed: usize,
+        was_cancelled: bool,
+    },
+    /// The sender's recent events could not be looked up.
+    Failed {
+        room_id: OwnedRoomId,
+        sender: OwnedUserId,
+        error: String,
+    },
+}
+
 /// Either a main room timeline or a thread-focused timeline.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TimelineKind {
@@ -733,6 +868,140 @@ pub enum MatrixRequest {
         room_id: OwnedRoomId,
         from: Option<String>,
     },
+    /// Request to search for messages that match the given `search_term`
+    /// using the homeserver's server-side `/search` API.
+    ///
+    /// If `room_id` is `None`, all of the user's joined rooms are searched.
+    /// To fetch the next page of results, pass in the `next_batch` token
+    /// from the previously-received [`SearchMessagesAction::Loaded`].
+    SearchMessages {
+        room_id: Option<OwnedRoomId>,
+        search_term: String,
+        next_batch: Option<String>,
+    },
+    /// Request to find the event in the given timeline's room that is closest to the given timestamp,
+    /// using the homeserver's `timestamp_to_event` API.
+    ///
+    /// The first event at or after the timestamp is preferred;
+    /// if there is none, the last event before the timestamp is used instead.
+    /// The result is emitted as a [`JumpToDateAction`].
+    JumpToDate {
+        timeline_kind: TimelineKind,
+        timestamp: MilliSecondsSinceUnixEpoch,
+    },
+    /// Request to export the full history of the given room to a file in the background.
+    ///
+    /// Progress and the result are emitted as [`ChatExportAction`]s.
+    /// The export stops early once the `cancel_token` is set to `true`.
+    ///
+    /// [`ChatExportAction`]: crate::chat_export::ChatExportAction
+    ExportRoomHistory {
+        room_id: OwnedRoomId,
+        room_name: String,
+        options: ChatExportOptions,
+        cancel_token: Arc<AtomicBool>,
+    },
+    /// Request to forward a message with the given content to each of the given rooms.
+    ///
+    /// Each forwarded message is sent via its own [`MatrixRequest::SendMessage`],
+    /// after re-uploading the message's media if it is encrypted.
+    /// The result is emitted as a [`ForwardMessageAction`].
+    ///
+    /// [`ForwardMessageAction`]: crate::forward_message::ForwardMessageAction
+    ForwardMessage {
+        content: RoomMessageEventContent,
+        target_room_ids: Vec<OwnedRoomId>,
+    },
+    /// Request to bookmark (if `bookmarked` is true) or un-bookmark the given message,
+    /// which is saved to the current user's account data.
+    ///
+    /// The result is emitted as a [`BookmarksAction`].
+    ///
+    /// [`BookmarksAction`]: crate::bookmarks::BookmarksAction
+    SetBookmark {
+        bookmark: Bookmark,
+        bookmarked: bool,
+    },
+    /// Request to fetch previews of all bookmarked messages.
+    ///
+    /// The result is emitted as a [`BookmarksAction::PreviewsLoaded`].
+    ///
+    /// [`BookmarksAction::PreviewsLoaded`]: crate::bookmarks::BookmarksAction::PreviewsLoaded
+    FetchBookmarkPreviews,
+    /// Request to schedule the given message to be sent later,
+    /// or to re-schedule it after it was edited.
+    ///
+    /// The result is emitted as a [`ScheduledMessagesAction`].
+    ///
+    /// [`ScheduledMessagesAction`]: crate::scheduled_messages::ScheduledMessagesAction
+    ScheduleMessage {
+        scheduled: ScheduledMessage,
+    },
+    /// Request to immediately send the given scheduled message.
+    ///
+    /// The result is emitted as a [`ScheduledMessagesAction`].
+    ///
+    /// [`ScheduledMessagesAction`]: crate::scheduled_messages::ScheduledMessagesAction
+    SendScheduledMessageNow {
+        scheduled: ScheduledMessage,
+    },
+    /// Request to cancel the given scheduled message.
+    ///
+    /// The result is emitted as a [`ScheduledMessagesAction`].
+    ///
+    /// [`ScheduledMessagesAction`]: crate::scheduled_messages::ScheduledMessagesAction
+    CancelScheduledMessage {
+        scheduled: ScheduledMessage,
+    },
+    /// Request to retry sending the given unsent message from a room's send queue.
+    ///
+    /// Failures are emitted as an [`UnsentMessagesAction`].
+    ///
+    /// [`UnsentMessagesAction`]: crate::unsent_messages::UnsentMessagesAction
+    RetryUnsentMessage {
+        room_id: OwnedRoomId,
+        transaction_id: OwnedTransactionId,
+    },
+    /// Request to retry sending all unsent messages in all rooms.
+    RetryAllUnsentMessages,
+    /// Request to replace the text of the given unsent message and then retry sending it.
+    ///
+    /// Failures are emitted as an [`UnsentMessagesAction`].
+    ///
+    /// [`UnsentMessagesAction`]: crate::unsent_messages::UnsentMessagesAction
+    EditUnsentMessage {
+        room_id: OwnedRoomId,
+        transaction_id: OwnedTransactionId,
+        new_text: String,
+    },
+    /// Request to discard the given unsent message from a room's send queue.
+    ///
+    /// Failures are emitted as an [`UnsentMessagesAction`].
+    ///
+    /// [`UnsentMessagesAction`]: crate::unsent_messages::UnsentMessagesAction
+    DiscardUnsentMessage {
+        room_id: OwnedRoomId,
+        transaction_id: OwnedTransactionId,
+    },
+    /// Request to fetch the full edit history of the given message,
+    /// i.e., its original content and all of its replacement events.
+    ///
+    /// The result is emitted as an [`EditHistoryAction`].
+    FetchEditHistory {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+    },
+    /// Request to redact the most recent events sent by the given user in the given room,
+    /// as one batch. This is a moderation action.
+    ///
+    /// Progress and results are emitted as [`RemoveRecentMessagesAction`]s.
+    RemoveRecentMessages {
+        room_id: OwnedRoomId,
+        sender: OwnedUserId,
+        /// The maximum number of the sender's events to redact.
+        limit: usize,
+        reason: Option<String>,
+    },
     /// Request to fetch profile information for all members of a room.
     ///
     /// This can be *very* slow depending on the number of members in the room.
@@ -768,6 +1037,9 @@ pub enum MatrixRequest {
     /// Request to join the given room.
     JoinRoom {
         room_id: OwnedRoomId,
+        /// The servers to attempt to join the room through,
+        /// which is only needed if the room is unknown to our homeserver.
+        via: Vec<OwnedServerName>,
     },
     /// Request to leave the given room.
     LeaveRoom {
@@ -778,6 +1050,17 @@ pub enum MatrixRequest {
         room_id: OwnedRoomId,
         reason: String,
     },
+    /// Request to report the given event (message) in the given room.
+    ReportEvent {
+        room_id: OwnedRoomId,
+        event_id: OwnedEventId,
+        reason: String,
+        /// The report score, from 0 (inoffensive) to -100 (most offensive).
+        score: i8,
+        /// If `Some`, this sender of the reported event will also be ignored
+        /// once the report has been sent successfully.
+        ignore_sender: Option<OwnedUserId>,
+    },
     /// Request to get the actual list of members in a room.
     ///
     /// This returns the list of members that can be displayed in the UI.
@@ -906,6 +1189,8 @@ pub enum MatrixRequest {
         new_display_name: Option<String>,
     },
     /// Request to resolve a room alias into a room ID and the servers that know about that room.
+    ///
+    /// Emits a [`ResolveRoomAliasAction`] when the request has completed.
     ResolveRoomAlias(OwnedRoomAliasId),
     /// Request to fetch an Avatar image from the server.
     /// Upon completion of the async media request, the `on_fetched` function
@@ -1031,6 +1316,67 @@ pub enum MatrixRequest {
         timeline_event_id: TimelineEventItemId,
         reason: Option<String>,
     },
+    /// Starts a new poll by sending an `m.poll.start` event to the given timeline.
+    SendPoll {
+        timeline_kind: TimelineKind,
+        question: String,
+        answers: UnstablePollAnswers,
+        kind: PollKind,
+    },
+    /// Votes for the given answers of a poll, replacing any previous vote.
+    SendPollResponse {
+        timeline_kind: TimelineKind,
+        poll_start_event_id: OwnedEventId,
+        /// The IDs of the selected answers, which must not be empty.
+        answer_ids: Vec<String>,
+    },
+    /// Ends the given poll, which must have been started by the current user.
+    EndPoll {
+        timeline_kind: TimelineKind,
+        poll_start_event_id: OwnedEventId,
+        /// The plaintext shown by clients that don't support polls.
+        fallback_text: String,
+    },
+    /// Sends an image from an image pack as an `m.sticker` event to the given timeline.
+    SendSticker {
+        timeline_kind: TimelineKind,
+        /// A textual description of the sticker.
+        body: String,
+        url: OwnedMxcUri,
+        info: ImageInfo,
+    },
+    /// Starts sharing the current user's live location in the given room
+    /// by sending a live `beacon_info` state event.
+    ///
+    /// Once started, a [`LiveLocationAction::Started`] is sent to the UI,
+    /// followed by a [`LiveLocationAction::Expired`] once the given `duration` has elapsed.
+    StartLiveLocationShare {
+        room_id: OwnedRoomId,
+        /// How long the live location share should last.
+        duration: Duration,
+    },
+    /// Stops the current user's live location share in the given room.
+    ///
+    /// Once stopped, a [`LiveLocationAction::Stopped`] is sent to the UI.
+    StopLiveLocationShare {
+        room_id: OwnedRoomId,
+    },
+    /// Sends a location `beacon` event for the current user's live location share in the given room.
+    SendLocationBeacon {
+        room_id: OwnedRoomId,
+        geo_uri: String,
+    },
+    /// Loads a map tile from the disk cache, or fetches it from the configured tile server.
+    ///
+    /// Upon completion, the tile is enqueued for the UI thread
+    /// via [`map_tiles::process_map_tile_updates()`].
+    FetchMapTile(MapTileRequest),
+    /// Fetches the image packs (custom emoji and stickers) that are available in the given room.
+    ///
+    /// Once fetched, the packs are sent to the UI via an [`ImagePacksAction`].
+    FetchImagePacks {
+        room_id: OwnedRoomId,
+    },
     /// Pin or unpin the given event in the given room.
     #[doc(alias("unpin"))]
     PinEvent {
@@ -1132,6 +1478,36 @@ async fn ensure_target_user_joined_room(
 mod matrix_request_tests {
     use super::*;
 
+    #[test]
+    fn test_thread_root_event_id_of_search_result_content() {
+        let threaded = serde_json::json!({
+            "msgtype": "m.text",
+            "body": "a reply in a thread",
+            "m.relates_to": {
+                "rel_type": "m.thread",
+                "event_id": "$thread_root:example.com",
+                "is_falling_back": true,
+                "m.in_reply_to": { "event_id": "$previous:example.com" }
+            }
+        });
+        assert_eq!(
+            thread_root_event_id_of_content(&threaded).as_deref().map(|e| e.as_str()),
+            Some("$thread_root:example.com"),
+        );
+
+        let plain_reply = serde_json::json!({
+            "msgtype": "m.text",
+            "body": "a plain reply",
+            "m.relates_to": {
+                "m.in_reply_to": { "event_id": "$previous:example.com" }
+            }
+        });
+        assert_eq!(thread_root_event_id_of_content(&plain_reply), None);
+
+        let no_relation = serde_json::json!({ "msgtype": "m.text", "body": "hello" });
+        assert_eq!(thread_root_event_id_of_content(&no_relation), None);
+    }
+
     #[test]
     fn should_add_octos_target_user_id_to_message_content() {
         let target_user_id = OwnedUserId::try_from("@bot_weather:example.com").unwrap();
@@ -1270,6 +1646,62 @@ pub struct RegisterAccount {
 }
 
 
+/// Downloads the unencrypted file at the given `mxc://` URI directly from the homeserver.
+///
+/// Returns the file's name, taken from the `Content-Disposition` header if present,
+/// along with the file's content.
+///
+/// This bypasses matrix-sdk's media API and MediaCache to avoid header parsing issues
+/// with non-ASCII Content-Disposition headers.
+pub async fn download_mxc_file(client: &Client, mxc_uri: &MxcUri) -> Result<(String, Vec<u8>)> {
+    // Use the client's homeserver URL to construct a direct download URL.
+    let server_name = mxc_uri.server_name().map(|s| s.to_string()).unwrap_or_default();
+    let media_id = mxc_uri.media_id().map(|s| s.to_string()).unwrap_or_default();
+
+    let homeserver = client.homeserver().to_string();
+    let homeserver = homeserver.trim_end_matches('/');
+    let download_url = format!(
+        "{homeserver}/_matrix/media/v3/download/{server_name}/{media_id}",
+    );
+
+    let http_client = matrix_sdk::reqwest::Client::new();
+    let resp = http_client.get(&download_url).send().await
+        .map_err(|e| anyhow!("request for {mxc_uri} failed: {e}"))?;
+    if !resp.status().is_success() {
+        bail!("server returned {} for {mxc_uri}", resp.status());
+    }
+    // Extract filename from Content-Disposition header or use media_id
+    let filename = resp.headers()
+        .get("content-disposition")
+        .and_then(|v: &matrix_sdk::reqwest::header::HeaderValue| {
+            let val = String::from_utf8_lossy(v.as_bytes());
+            // Parse filename="..." or filename*=UTF-8''...
+            val.split("filename=").nth(1)
+                .or_else(|| val.split("filename*=").nth(1))
+                .map(|s| s.trim_matches(|c: char| c == '"' || c == '\'' || c == ';' || c == ' ').to_string())
+        })
+        .unwrap_or_else(|| format!("robrix_{media_id}"));
+    let data = resp.bytes().await
+        .map_err(|e| anyhow!("failed to read response body for {mxc_uri}: {e}"))?;
+    Ok((filename, data.to_vec()))
+}
+
+/// Fetches and deserializes the current user's global account data of the given event type.
+///
+/// Returns `None` if there is no such account data, or if it couldn't be fetched or deserialized.
+pub async fn fetch_global_account_data<T: serde::de::DeserializeOwned>(client: &Client, event_type: &str) -> Option<T> {
+    match client.account().fetch_account_data(matrix_sdk::ruma::events::GlobalAccountDataEventType::from(event_type)).await {
+        Ok(Some(raw_content)) => serde_json::from_str(raw_content.json().get())
+            .inspect_err(|e| error!("Failed to deserialize {event_type} account data: {e:?}"))
+            .ok(),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to fetch {event_type} account data: {e:?}");
+            None
+        }
+    }
+}
+
 /// The entry point for the worker task that runs Matrix-related operations.
 ///
 /// All this task does is wait for [`MatrixRequests`] from the main UI thread
@@ -1595,6 +2027,241 @@ async fn matrix_worker_task(
                 });
             }
 
+            MatrixRequest::JumpToDate { timeline_kind, timestamp } => {
+                let Some(client) = get_client() else { continue };
+                let _jump_to_date_task = Handle::current().spawn(async move {
+                    let room_id = timeline_kind.room_id().clone();
+                    let forward_request = get_event_by_timestamp::v1::Request::new(room_id.clone(), timestamp, Direction::Forward);
+                    let result = match client.send(forward_request).await {
+                        Err(forward_error) if forward_error.client_api_error_kind() == Some(&ErrorKind::NotFound) => {
+                            // There are no events after the given date, so find the last one before it.
+                            let backward_request = get_event_by_timestamp::v1::Request::new(room_id.clone(), timestamp, Direction::Backward);
+                            client.send(backward_request).await
+                        }
+                        other => other,
+                    };
+                    match result {
+                        Ok(response) => {
+                            log!("Found event {} at {:?} for jump to date {timestamp:?} in room {room_id}.", response.event_id, response.origin_server_ts);
+                            Cx::post_action(JumpToDateAction::Found {
+                                timeline_kind,
+                                event_id: response.event_id,
+                            });
+                        }
+                        Err(error) => {
+                            error!("Failed to find an event at {timestamp:?} in room {room_id}: {error:?}");
+                            Cx::post_action(JumpToDateAction::Failed {
+                                timeline_kind,
+                                not_found: error.client_api_error_kind() == Some(&ErrorKind::NotFound),
+                                error: error.to_string(),
+                            });
+                        }
+                    }
+                });
+            }
+
+            MatrixRequest::ExportRoomHistory { room_id, room_name, options, cancel_token } => {
+                let Some(client) = get_client() else { continue };
+                let _export_task = Handle::current().spawn(
+                    chat_export::export_room_history(client, room_id, room_name, options, cancel_token)
+                );
+            }
+
+            MatrixRequest::ForwardMessage { content, target_room_ids } => {
+                let Some(client) = get_client() else { continue };
+                let _forward_task = Handle::current().spawn(
+                    forward_message::forward_message(client, content, target_room_ids)
+                );
+            }
+
+            MatrixRequest::SetBookmark { bookmark, bookmarked } => {
+                let Some(client) = get_client() else { continue };
+                let _set_bookmark_task = Handle::current().spawn(
+                    bookmarks::set_bookmarked(client, bookmark, bookmarked)
+                );
+            }
+
+            MatrixRequest::FetchBookmarkPreviews => {
+                let Some(client) = get_client() else { continue };
+                let _fetch_previews_task = Handle::current().spawn(
+                    bookmarks::fetch_bookmark_previews(client)
+                );
+            }
+
+            MatrixRequest::ScheduleMessage { scheduled } => {
+                let Some(client) = get_client() else { continue };
+                let _schedule_message_task = Handle::current().spawn(
+                    scheduled_messages::schedule_message(client, scheduled)
+                );
+            }
+
+            MatrixRequest::SendScheduledMessageNow { scheduled } => {
+                let Some(client) = get_client() else { continue };
+                let _send_scheduled_message_task = Handle::current().spawn(
+                    scheduled_messages::send_scheduled_message_now(client, scheduled)
+                );
+            }
+
+            MatrixRequest::CancelScheduledMessage { scheduled } => {
+                let Some(client) = get_client() else { continue };
+                let _cancel_scheduled_message_task = Handle::current().spawn(
+                    scheduled_messages::cancel_scheduled_message(client, scheduled)
+                );
+            }
+
+            MatrixRequest::RetryUnsentMessage { room_id, transaction_id } => {
+                let Some(client) = get_client() else { continue };
+                let _retry_unsent_message_task = Handle::current().spawn(
+                    unsent_messages::retry_unsent_message(client, room_id, transaction_id)
+                );
+            }
+
+            MatrixRequest::RetryAllUnsentMessages => {
+                let Some(client) = get_client() else { continue };
+                let _retry_all_unsent_messages_task = Handle::current().spawn(
+                    unsent_messages::retry_all_unsent_messages(client)
+                );
+            }
+
+            MatrixRequest::EditUnsentMessage { room_id, transaction_id, new_text } => {
+                let Some(client) = get_client() else { continue };
+                let _edit_unsent_message_task = Handle::current().spawn(
+                    unsent_messages::edit_unsent_message(client, room_id, transaction_id, new_text)
+                );
+            }
+
+            MatrixRequest::DiscardUnsentMessage { room_id, transaction_id } => {
+                let _discard_unsent_message_task = Handle::current().spawn(
+                    unsent_messages::discard_unsent_message(room_id, transaction_id)
+                );
+            }
+
+            MatrixRequest::SearchMessages { room_id, search_term, next_batch } => {
+                let Some(client) = get_client() else { continue };
+                let _search_messages_task = Handle::current().spawn(async move {
+                    match fetch_message_search_page(&client, room_id.as_ref(), &search_term, next_batch.clone()).await {
+                        Ok((mut results, mut count, new_next_batch)) => {
+                            // The server cannot search encrypted rooms, so we include results
+                            // from the local search index (if enabled) in the first page.
+                            if next_batch.is_none() && local_search_index::is_enabled() {
+                                let local_results = local_message_search_results(&client, room_id.as_ref(), &search_term).await;
+                                if !local_results.is_empty() {
+                                    count = count.map(|c| c + local_results.len() as u64);
+                                    results.retain(|r| local_results.iter().all(|l| l.event_id != r.event_id));
+                                    results.extend(local_results);
+                                    results.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+                                }
+                            }
+                            Cx::post_action(SearchMessagesAction::Loaded {
+                                room_id,
+                                search_term,
+                                from: next_batch,
+                                results,
+                                count,
+                                next_batch: new_next_batch,
+                            });
+                        }
+                        Err(error) => {
+                            error!("Failed to search messages for {search_term:?} in {room_id:?}: {error:?}");
+                            Cx::post_action(SearchMessagesAction::Failed {
+                                room_id,
+                                search_term,
+                                from: next_batch,
+                                error: error.to_string(),
+                            });
+                        }
+                    }
+                });
+            }
+
+            MatrixRequest::RemoveRecentMessages { room_id, sender, limit, reason } => {
+                let Some(client) = get_client() else { continue };
+                let _remove_recent_messages_task = Handle::current().spawn(async move {
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    Cx::post_action(RemoveRecentMessagesAction::Progress {
+                        room_id: room_id.clone(),
+                        sender: sender.clone(),
+                        processed: 0,
+                        total: None,
+                        cancelled: cancelled.clone(),
+                    });
+                    let result = match client.get_room(&room_id) {
+                        Some(room) => find_recent_events_by_sender(&room, &sender, limit, &cancelled).await
+                            .map(|event_ids| (room, event_ids)),
+                        None => Err(anyhow!("room {room_id} was not found")),
+                    };
+                    let (room, event_ids) = match result {
+                        Ok(found) => found,
+                        Err(error) => {
+                            error!("Failed to find recent events by {sender} in {room_id}: {error:?}");
+                            Cx::post_action(RemoveRecentMessagesAction::Failed {
+                                room_id,
+                                sender,
+                                error: error.to_string(),
+                            });
+                            return;
+                        }
+                    };
+
+                    log!("Removing {} recent events by {sender} in {room_id}...", event_ids.len());
+                    let total = event_ids.len();
+                    let mut removed = 0;
+                    let mut failed = 0;
+                    for (index, event_id) in event_ids.into_iter().enumerate() {
+                        if cancelled.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        match room.redact(&event_id, reason.as_deref(), None).await {
+                            Ok(_) => removed += 1,
+                            Err(e) => {
+                                error!("Failed to redact event {event_id} by {sender} in {room_id}: {e:?}");
+                                failed += 1;
+                            }
+                        }
+                        Cx::post_action(RemoveRecentMessagesAction::Progress {
+                            room_id: room_id.clone(),
+                            sender: sender.clone(),
+                            processed: index + 1,
+                            total: Some(total),
+                            cancelled: cancelled.clone(),
+                        });
+                    }
+                    log!("Removed {removed} recent events by {sender} in {room_id} ({failed} failed).");
+                    Cx::post_action(RemoveRecentMessagesAction::Finished {
+                        room_id,
+                        sender,
+                        removed,
+                        failed,
+                        was_cancelled: cancelled.load(Ordering::Relaxed),
+                    });
+                });
+            }
+
+            MatrixRequest::FetchEditHistory { room_id, event_id } => {
+                let Some(client) = get_client() else { continue };
+                let _fetch_edit_history_task = Handle::current().spawn(async move {
+                    let result = match client.get_room(&room_id) {
+                        Some(room) => fetch_edit_history(&room, &event_id).await,
+                        None => Err(anyhow!("room {room_id} was not found")),
+                    };
+                    match result {
+                        Ok(revisions) => Cx::post_action(EditHistoryAction::Loaded {
+                            room_id,
+                            event_id,
+                            revisions,
+                        }),
+                        Err(error) => {
+                            error!("Failed to fetch edit history of {event_id} in {room_id}: {error:?}");
+                            Cx::post_action(EditHistoryAction::Failed {
+                                room_id,
+                                event_id,
+                                error: error.to_string(),
+                            });
+                        }
+                    }
+                });
+            }
+
             MatrixRequest::SyncRoomMemberList { timeline_kind } => {
                 let Some((timeline, sender)) = get_timeline_and_sender(&timeline_kind) else {
                     log!("BUG: {timeline_kind} not found for sync members list request");
@@ -1820,7 +2487,7 @@ async fn matrix_worker_task(
                 });
             }
 
-            MatrixRequest::JoinRoom { room_id } => {
+            MatrixRequest::JoinRoom { room_id, via } => {
                 let Some(client) = get_client() else { continue };
                 let _join_room_task = Handle::current().spawn(async move {
                     log!("Sending request to join room {room_id}...");
@@ -1842,7 +2509,7 @@ async fn matrix_worker_task(
                         }
                     }
                     else {
-                        match client.join_room_by_id(&room_id).await {
+                        match client.join_room_by_id_or_alias(room_id.deref().into(), &via).await {
                             Ok(_room) => {
                                 log!("Successfully joined new unknown room {room_id}.");
                                 JoinRoomResultAction::Joined { room_id }
@@ -1865,6 +2532,7 @@ async fn matrix_worker_task(
                         match room.leave().await {
                             Ok(()) => {
                                 log!("Successfully left room {room_id}.");
+                                local_search_index::purge_room(room_id.clone());
                                 LeaveRoomResultAction::Left { room_id }
                             }
                             Err(e) => {
@@ -1897,14 +2565,56 @@ async fn matrix_worker_task(
                                 ReportRoomResultAction::Failed { room_id, error: e }
                             }
                         }
-                    } else {
-                        error!("BUG: client could not get room with ID {room_id}");
-                        ReportRoomResultAction::Failed {
-                            room_id,
-                            error: matrix_sdk::Error::UnknownError("Client couldn't locate room to report it.".into()),
+                    } else {
+                        error!("BUG: client could not get room with ID {room_id}");
+                        ReportRoomResultAction::Failed {
+                            room_id,
+                            error: matrix_sdk::Error::UnknownError("Client couldn't locate room to report it.".into()),
+                        }
+                    };
+                    Cx::post_action(result_action);
+                });
+            }
+
+            MatrixRequest::ReportEvent { room_id, event_id, reason, score, ignore_sender } => {
+                let Some(client) = get_client() else { continue };
+                let _report_event_task = Handle::current().spawn(async move {
+                    log!("Sending request to report event {event_id} in room {room_id}...");
+                    let Some(room) = client.get_room(&room_id) else {
+                        error!("BUG: client could not get room with ID {room_id}");
+                        Cx::post_action(ReportEventResultAction::Failed {
+                            room_id,
+                            event_id,
+                            error: matrix_sdk::Error::UnknownError("Client couldn't locate room to report the message.".into()),
+                        });
+                        return;
+                    };
+                    let result = room.report_content(
+                        event_id.clone(),
+                        ReportedContentScore::new(score),
+                        Some(reason),
+                    ).await;
+                    match result {
+                        Ok(_) => {
+                            // Only ignore the sender once the report itself has succeeded.
+                            if let Some(sender) = ignore_sender {
+                                match room.get_member(&sender).await {
+                                    Ok(Some(room_member)) => submit_async_request(MatrixRequest::IgnoreUser {
+                                        ignore: true,
+                                        room_member,
+                                        room_id: room_id.clone(),
+                                    }),
+                                    Ok(None) => warning!("Couldn't ignore {sender} after reporting event {event_id}: they aren't a member of room {room_id}"),
+                                    Err(e) => error!("Couldn't ignore {sender} after reporting event {event_id}: {e:?}"),
+                                }
+                            }
+                            Cx::post_action(ReportEventResultAction::Sent { room_id, event_id });
+                        }
+                        Err(e) => {
+                            error!("Error reporting event {event_id} in room {room_id}: {e:?}");
+                            Cx::post_action(ReportEventResultAction::Failed { room_id, event_id, error: e });
                         }
-                    };
-                    Cx::post_action(result_action);
+                    }
                 });
             }
 
@@ -1942,8 +2652,8 @@ async fn matrix_worker_task(
             MatrixRequest::GetRoomPreview { room_or_alias_id, via } => {
                 let Some(client) = get_client() else { continue };
                 let _fetch_task = Handle::current().spawn(async move {
-                    let res = fetch_room_preview_with_avatar(&client, &room_or_alias_id, via).await;
-                    Cx::post_action(RoomPreviewAction::Fetched(res));
+                    let result = fetch_room_preview_with_avatar(&client, &room_or_alias_id, via).await;
+                    Cx::post_action(RoomPreviewAction::Fetched { room_or_alias_id, result });
                 });
             }
 
@@ -2695,9 +3405,23 @@ async fn matrix_worker_task(
                 let Some(client) = get_client() else { continue };
                 let _resolve_task = Handle::current().spawn(async move {
                     log!("Sending resolve room alias request for {room_alias}...");
-                    let res = client.resolve_room_alias(&room_alias).await;
-                    log!("Resolved room alias {room_alias} to: {res:?}");
-                    todo!("Send the resolved room alias back to the UI thread somehow.");
+                    match client.resolve_room_alias(&room_alias).await {
+                        Ok(response) => {
+                            log!("Resolved room alias {room_alias} to: {}", response.room_id);
+                            Cx::post_action(ResolveRoomAliasAction::Resolved {
+                                room_alias,
+                                room_id: response.room_id,
+                                servers: response.servers,
+                            });
+                        }
+                        Err(error) => {
+                            error!("Failed to resolve room alias {room_alias}: {error:?}");
+                            Cx::post_action(ResolveRoomAliasAction::Failed {
+                                room_alias,
+                                error: error.to_string(),
+                            });
+                        }
+                    }
                 });
             }
 
@@ -2723,91 +3447,45 @@ async fn matrix_worker_task(
                     use crate::i18n::{tr_key, tr_fmt};
 
                     log!("DownloadAndSaveFile: downloading {mxc_uri}");
-
-                    // Use the client's homeserver URL to construct a direct download URL,
-                    // bypassing matrix-sdk's header parsing which fails on non-ASCII Content-Disposition.
-                    let server_name = mxc_uri.server_name().map(|s| s.to_string()).unwrap_or_default();
-                    let media_id = mxc_uri.media_id().map(|s| s.to_string()).unwrap_or_default();
-
-                    let homeserver = client.homeserver().to_string();
-                    let homeserver = homeserver.trim_end_matches('/');
-                    let download_url = format!(
-                        "{homeserver}/_matrix/media/v3/download/{server_name}/{media_id}",
-                    );
-
-                    let http_client = matrix_sdk::reqwest::Client::new();
-                    match http_client.get(&download_url).send().await {
-                        Ok(resp) if resp.status().is_success() => {
-                            // Extract filename from Content-Disposition header or use media_id
-                            let filename = resp.headers()
-                                .get("content-disposition")
-                                .and_then(|v: &matrix_sdk::reqwest::header::HeaderValue| {
-                                    let val = String::from_utf8_lossy(v.as_bytes());
-                                    // Parse filename="..." or filename*=UTF-8''...
-                                    val.split("filename=").nth(1)
-                                        .or_else(|| val.split("filename*=").nth(1))
-                                        .map(|s| s.trim_matches(|c: char| c == '"' || c == '\'' || c == ';' || c == ' ').to_string())
-                                })
-                                .unwrap_or_else(|| format!("robrix_{media_id}"));
-
-                            match resp.bytes().await {
-                                Ok(data) => {
-                                    let downloads_dir = crate::app_data_dir().join("downloads");
-                                    if let Err(e) = std::fs::create_dir_all(&downloads_dir) {
-                                        error!("Failed to create downloads dir: {e:?}");
-                                        return;
-                                    }
-                                    let dest = downloads_dir.join(&filename);
-                                    match std::fs::write(&dest, &data) {
-                                        Ok(()) => {
-                                            log!("DownloadAndSaveFile: saved to {}", dest.display());
-                                            let dest_str = dest.display().to_string();
-                                            enqueue_popup_notification(
-                                                tr_fmt(app_language, "room_screen.file.saved_at", &[("path", &dest_str)]),
-                                                PopupKind::Success,
-                                                Some(8.0),
-                                            );
-                                            // Try to open with system handler
-                                            if let Err(e) = robius_open::Uri::new(&format!("file://{dest_str}")).open() {
-                                                log!("Could not open file: {e:?}");
-                                            }
-                                            SignalToUI::set_ui_signal();
-                                        }
-                                        Err(e) => {
-                                            error!("DownloadAndSaveFile: write failed: {e:?}");
-                                            enqueue_popup_notification(
-                                                tr_key(app_language, "room_screen.file.save_failed").to_string(),
-                                                PopupKind::Error,
-                                                Some(6.0),
-                                            );
-                                            SignalToUI::set_ui_signal();
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("DownloadAndSaveFile: failed to read response body: {e:?}");
-                                    enqueue_popup_notification(
-                                        tr_key(app_language, "room_screen.file.download_failed").to_string(),
-                                        PopupKind::Error,
-                                        Some(6.0),
-                                    );
-                                    SignalToUI::set_ui_signal();
-                                }
-                            }
-                        }
-                        Ok(resp) => {
-                            error!("DownloadAndSaveFile: server returned {}", resp.status());
+                    let (filename, data) = match download_mxc_file(&client, &mxc_uri).await {
+                        Ok(file) => file,
+                        Err(e) => {
+                            error!("DownloadAndSaveFile: {e:?}");
                             enqueue_popup_notification(
                                 tr_key(app_language, "room_screen.file.download_failed").to_string(),
                                 PopupKind::Error,
                                 Some(6.0),
                             );
                             SignalToUI::set_ui_signal();
+                            return;
+                        }
+                    };
+
+                    let downloads_dir = crate::app_data_dir().join("downloads");
+                    if let Err(e) = std::fs::create_dir_all(&downloads_dir) {
+                        error!("Failed to create downloads dir: {e:?}");
+                        return;
+                    }
+                    let dest = downloads_dir.join(&filename);
+                    match std::fs::write(&dest, &data) {
+                        Ok(()) => {
+                            log!("DownloadAndSaveFile: saved to {}", dest.display());
+                            let dest_str = dest.display().to_string();
+                            enqueue_popup_notification(
+                                tr_fmt(app_language, "room_screen.file.saved_at", &[("path", &dest_str)]),
+                                PopupKind::Success,
+                                Some(8.0),
+                            );
+                            // Try to open with system handler
+                            if let Err(e) = robius_open::Uri::new(&format!("file://{dest_str}")).open() {
+                                log!("Could not open file: {e:?}");
+                            }
+                            SignalToUI::set_ui_signal();
                         }
                         Err(e) => {
-                            error!("DownloadAndSaveFile: request failed: {e:?}");
+                            error!("DownloadAndSaveFile: write failed: {e:?}");
                             enqueue_popup_notification(
-                                tr_key(app_language, "room_screen.file.download_failed").to_string(),
+                                tr_key(app_language, "room_screen.file.save_failed").to_string(),
                                 PopupKind::Error,
                                 Some(6.0),
                             );
@@ -3090,17 +3768,14 @@ async fn matrix_worker_task(
                 };
 
                 // Spawn a new async task to send the attachment.
-                let _send_attachment_task = Handle::current().spawn(async move {
+                let abort_handle_sender = sender.clone();
+                let send_attachment_task = Handle::current().spawn(async move {
                     use matrix_sdk::attachment::AttachmentConfig;
                     use eyeball::SharedObservable;
 
                     log!("Sending attachment to {timeline_kind}: {} ({} bytes)...",
                         file_data.name, file_data.size);
 
-                    // For now, we'll just send the attachment without reply support
-                    // TODO: Add proper reply support for attachments
-                    let _ = replied_to; // Suppress unused warning for now
-
                     // Parse MIME type
                     let content_type: mime::Mime = file_data.mime_type.parse()
                         .unwrap_or_else(|_| "application/octet-stream".parse().unwrap());
@@ -3133,7 +3808,7 @@ async fn matrix_worker_task(
 
                     // Use the Room's send_attachment method directly
                     let room = timeline.room();
-                    let config = AttachmentConfig::new();
+                    let config = AttachmentConfig::new().reply(replied_to.clone());
 
                     let send_future = room.send_attachment(
                         &file_data.name,
@@ -3152,6 +3827,7 @@ async fn matrix_worker_task(
                             let _ = sender.send(TimelineUpdate::FileUploadError {
                                 error: format!("{e}"),
                                 file_data: file_data.clone(),
+                                replied_to,
                             });
                             enqueue_popup_notification(
                                 format!("Failed to upload file: {e}"),
@@ -3163,6 +3839,9 @@ async fn matrix_worker_task(
 
                     SignalToUI::set_ui_signal();
                 });
+                // Allow the upload to be cancelled from the `UploadProgressView`.
+                let _ = abort_handle_sender.send(TimelineUpdate::FileUploadAbortHandle(send_attachment_task.abort_handle()));
+                SignalToUI::set_ui_signal();
             }
 
             MatrixRequest::ReadReceipt { timeline_kind, event_id, receipt_type } => {
@@ -3253,6 +3932,206 @@ async fn matrix_worker_task(
                 });
             },
 
+            MatrixRequest::SendPoll { timeline_kind, question, answers, kind } => {
+                let Some(timeline) = get_timeline(&timeline_kind) else {
+                    log!("BUG: {timeline_kind} not found for send poll request");
+                    continue;
+                };
+
+                // Clients that don't support polls show the question and answers as plain text.
+                let fallback_text = std::iter::once(question.clone())
+                    .chain(answers.iter().enumerate().map(|(i, answer)| format!("{}. {}", i + 1, answer.text)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let mut poll_start = UnstablePollStartContentBlock::new(question, answers);
+                poll_start.kind = kind;
+                let content = UnstablePollStartEventContent::New(
+                    NewUnstablePollStartEventContent::plain_text(fallback_text, poll_start),
+                );
+
+                let _send_poll_task = Handle::current().spawn(async move {
+                    match timeline.send(content.into()).await {
+                        Ok(_send_handle) => {
+                            log!("Sent poll to {timeline_kind}.");
+                            SignalToUI::set_ui_signal();
+                        }
+                        Err(e) => {
+                            error!("Failed to send poll to {timeline_kind}; error: {e:?}");
+                            enqueue_popup_notification(
+                                format!("Failed to send poll. Error: {e}"),
+                                PopupKind::Error,
+                                None,
+                            );
+                        }
+                    }
+                });
+            },
+
+            MatrixRequest::SendPollResponse { timeline_kind, poll_start_event_id, answer_ids } => {
+                let Some(timeline) = get_timeline(&timeline_kind) else {
+                    log!("BUG: {timeline_kind} not found for send poll response request");
+                    continue;
+                };
+
+                let _send_poll_response_task = Handle::current().spawn(async move {
+                    match timeline.send_poll_response(&poll_start_event_id, answer_ids).await {
+                        Ok(()) => {
+                            log!("Sent poll response to poll {poll_start_event_id} in {timeline_kind}.");
+                            SignalToUI::set_ui_signal();
+                        }
+                        Err(e) => {
+                            error!("Failed to send poll response to poll {poll_start_event_id} in {timeline_kind}; error: {e:?}");
+                            enqueue_popup_notification(
+                                format!("Failed to vote in poll. Error: {e}"),
+                                PopupKind::Error,
+                                None,
+                            );
+                        }
+                    }
+                });
+            },
+
+            MatrixRequest::EndPoll { timeline_kind, poll_start_event_id, fallback_text } => {
+                let Some(timeline) = get_timeline(&timeline_kind) else {
+                    log!("BUG: {timeline_kind} not found for end poll request");
+                    continue;
+                };
+
+                let _end_poll_task = Handle::current().spawn(async move {
+                    match timeline.end_poll(&poll_start_event_id, &fallback_text).await {
+                        Ok(()) => {
+                            log!("Ended poll {poll_start_event_id} in {timeline_kind}.");
+                            SignalToUI::set_ui_signal();
+                        }
+                        Err(e) => {
+                            error!("Failed to end poll {poll_start_event_id} in {timeline_kind}; error: {e:?}");
+                            enqueue_popup_notification(
+                                format!("Failed to end poll. Error: {e}"),
+                                PopupKind::Error,
+                                None,
+                            );
+                        }
+                    }
+                });
+            },
+
+            MatrixRequest::SendSticker { timeline_kind, body, url, info } => {
+                let Some(timeline) = get_timeline(&timeline_kind) else {
+                    log!("BUG: {timeline_kind} not found for send sticker request");
+                    continue;
+                };
+
+                let content = StickerEventContent::new(body, info, url);
+                let _send_sticker_task = Handle::current().spawn(async move {
+                    match timeline.send(content.into()).await {
+                        Ok(_send_handle) => {
+                            log!("Sent sticker to {timeline_kind}.");
+                            SignalToUI::set_ui_signal();
+                        }
+                        Err(e) => {
+                            error!("Failed to send sticker to {timeline_kind}; error: {e:?}");
+                            enqueue_popup_notification(
+                                format!("Failed to send sticker. Error: {e}"),
+                                PopupKind::Error,
+                                None,
+                            );
+                        }
+                    }
+                });
+            },
+
+            MatrixRequest::StartLiveLocationShare { room_id, duration } => {
+                let Some(client) = get_client() else { continue };
+                let _start_live_location_task = Handle::current().spawn(async move {
+                    let Some(room) = client.get_room(&room_id) else {
+                        error!("BUG: client could not get room with ID {room_id} to start a live location share");
+                        return;
+                    };
+                    let duration_millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+                    match room.start_live_location_share(duration_millis, None).await {
+                        Ok(_response) => {
+                            log!("Started live location share in {room_id} for {duration:?}.");
+                            let expires_at = std::time::SystemTime::now() + duration;
+                            live_location::persist_share(&client, room_id.clone(), expires_at).await;
+                            Cx::post_action(LiveLocationAction::Started {
+                                room_id: room_id.clone(),
+                                expires_at,
+                            });
+                            // Stop the share once it expires, even if the device's location never changes.
+                            tokio::time::sleep(duration).await;
+                            Cx::post_action(LiveLocationAction::Expired { room_id, expires_at });
+                        }
+                        Err(e) => {
+                            error!("Failed to start live location share in {room_id}; error: {e:?}");
+                            enqueue_popup_notification(
+                                format!("Failed to start sharing your live location. Error: {e}"),
+                                PopupKind::Error,
+                                None,
+                            );
+                        }
+                    }
+                });
+            },
+
+            MatrixRequest::StopLiveLocationShare { room_id } => {
+                let Some(client) = get_client() else { continue };
+                let _stop_live_location_task = Handle::current().spawn(async move {
+                    let Some(room) = client.get_room(&room_id) else {
+                        error!("BUG: client could not get room with ID {room_id} to stop a live location share");
+                        return;
+                    };
+                    match room.stop_live_location_share().await {
+                        Ok(_response) => {
+                            log!("Stopped live location share in {room_id}.");
+                            live_location::unpersist_share(&client, &room_id).await;
+                            Cx::post_action(LiveLocationAction::Stopped { room_id });
+                        }
+                        Err(e) => {
+                            error!("Failed to stop live location share in {room_id}; error: {e:?}");
+                            enqueue_popup_notification(
+                                format!("Failed to stop sharing your live location. Error: {e}"),
+                                PopupKind::Error,
+                                None,
+                            );
+                        }
+                    }
+                });
+            },
+
+            MatrixRequest::SendLocationBeacon { room_id, geo_uri } => {
+                let Some(client) = get_client() else { continue };
+                let _send_beacon_task = Handle::current().spawn(async move {
+                    let Some(room) = client.get_room(&room_id) else {
+                        error!("BUG: client could not get room with ID {room_id} to send a location beacon");
+                        return;
+                    };
+                    // Don't show a popup upon failure, as beacons are sent frequently in the background.
+                    if let Err(e) = room.send_location_beacon(geo_uri).await {
+                        error!("Failed to send location beacon to {room_id}; error: {e:?}");
+                    }
+                });
+            },
+
+            MatrixRequest::FetchMapTile(request) => {
+                let _fetch_map_tile_task = Handle::current().spawn(map_tiles::fetch_map_tile(request));
+            },
+
+            MatrixRequest::FetchImagePacks { room_id } => {
+                let Some(client) = get_client() else { continue };
+                let _fetch_image_packs_task = Handle::current().spawn(async move {
+                    let Some(room) = client.get_room(&room_id) else {
+                        error!("BUG: client could not get room with ID {room_id} to fetch its image packs");
+                        return;
+                    };
+                    let packs = image_packs::fetch_image_packs(&client, &room).await;
+                    log!("Fetched {} image packs for room {room_id}.", packs.len());
+                    Cx::post_action(ImagePacksAction::Fetched {
+                        room_id,
+                        packs: Arc::new(packs),
+                    });
+                });
+            },
+
             MatrixRequest::PinEvent { timeline_kind, event_id, pin } => {
                 let Some((timeline, sender)) = get_timeline_and_sender(&timeline_kind) else {
                     log!("BUG: {timeline_kind} not found for pin event request");
@@ -3606,7 +4485,7 @@ fn get_timeline_and_sender(kind: &TimelineKind) -> Option<(Arc<Timeline>, crossb
 }
 
 /// Obtains the lock on `ALL_JOINED_ROOMS` and returns the main timeline for the given room.
-fn get_room_timeline(room_id: &RoomId) -> Option<Arc<Timeline>> {
+pub fn get_room_timeline(room_id: &RoomId) -> Option<Arc<Timeline>> {
     ALL_JOINED_ROOMS.lock().unwrap()
         .get(room_id)
         .map(|jrd| jrd.main_timeline.timeline.clone())
@@ -3972,12 +4851,24 @@ async fn start_matrix_client_login_and_sync(rt: Handle) {
                 error!("BUG: unexpectedly replaced an existing client when initializing the matrix client.");
             }
 
+            // Load the key that encrypts this user's local search index, before any rooms are indexed.
+            local_search_index::load_encryption_key(&client).await;
+
+            // Resume this user's live location shares, or stop those that have since expired.
+            live_location::restore_live_location_shares(&client).await;
+
             // Listen for changes to our verification status and incoming verification requests.
             add_verification_event_handlers_and_sync_client(client.clone());
 
             // Listen for updates to the ignored user list.
             handle_ignore_user_list_subscriber(client.clone());
 
+            // Load the user's bookmarks and listen for changes made on other devices.
+            bookmarks::handle_bookmarks_account_data(&client);
+
+            // Keep track of the messages that are waiting in the send queue.
+            unsent_messages::handle_send_queue_updates(&client);
+
             if !validate_session {
                 Cx::post_action(LoginAction::Status {
                     title: "Connecting".into(),
@@ -4179,10 +5070,14 @@ async fn start_matrix_client_login_and_sync(rt: Handle) {
                 Ok((client, _sync_token, _session)) => {
                     // Store the client
                     CLIENT.lock().unwrap().replace(client.clone());
+                    local_search_index::load_encryption_key(&client).await;
+                    live_location::restore_live_location_shares(&client).await;
 
                     // Set up the new client
                     add_verification_event_handlers_and_sync_client(client.clone());
                     handle_ignore_user_list_subscriber(client.clone());
+                    bookmarks::handle_bookmarks_account_data(&client);
+                    unsent_messages::handle_send_queue_updates(&client);
 
                     // Create new sync service
                     let sync_service = match SyncService::builder(client.clone())
@@ -4600,6 +5495,7 @@ async fn update_room(
                 RoomState::Banned => {
                     // TODO: handle rooms that this user has been banned from.
                     log!("Removing Banned room: {:?} ({new_room_id})", new_room.display_name);
+                    local_search_index::purge_room(new_room_id);
                     remove_room(new_room);
                     return Ok(());
                 }
@@ -4610,6 +5506,7 @@ async fn update_room(
                     //       Upon clicking a left room, we could show a splash page
                     //       that prompts the user to rejoin the room or forget it permanently.
                     //       Currently, we just remove it and do not show left rooms at all.
+                    local_search_index::purge_room(new_room_id);
                     remove_room(new_room);
                     return Ok(());
                 }
@@ -4973,13 +5870,18 @@ fn handle_ignore_user_list_subscriber(client: Client) {
     });
 }
 
-/// Asynchronously loads and restores the app state from persistent storage for the given user.
+/// Asynchronously loads and restores the app state from persistent storage for the given user,
+/// along with the user's composer drafts.
 ///
 /// If the loaded dock state contains open rooms and dock items, this function emits an action
 /// to instruct the UI to restore the app state for the main home view (all rooms).
 /// If loading fails, it shows a popup notification with the error message.
 fn handle_load_app_state(user_id: OwnedUserId) {
     Handle::current().spawn(async move {
+        // Load the user's composer drafts first, such that they're available
+        // to any rooms that get re-opened when the app state is restored.
+        composer_drafts::load_drafts(&user_id).await;
+
         match take_skip_app_state_restore_once(&user_id).await {
             Ok(true) => {
                 log!("Skipping automatic app state restore once for {user_id} after explicit logout.");
@@ -5206,7 +6108,7 @@ async fn fetch_room_preview_with_avatar(
     room: &RoomOrAliasId,
     via: Vec<OwnedServerName>,
 ) -> Result<FetchedRoomPreview, matrix_sdk::Error> {
-    let room_preview = client.get_room_preview(room, via).await?;
+    let room_preview = client.get_room_preview(room, via.clone()).await?;
     // If this room has an avatar URL, fetch it.
     let room_avatar = if let Some(avatar_url) = room_preview.avatar_url.clone() {
         let media_request = MediaRequestParameters {
@@ -5229,7 +6131,7 @@ async fn fetch_room_preview_with_avatar(
         // The successor room did not have an avatar URL
         avatar_from_room_name(room_preview.name.as_deref())
     };
-    Ok(FetchedRoomPreview::from(room_preview, room_avatar))
+    Ok(FetchedRoomPreview::from(room_preview, room_avatar, via))
 }
 
 /// Fetches key details about the given thread root event.
@@ -5439,6 +6341,255 @@ async fn fetch_room_threads_page(
 }
 
 
+/// Fetches the original content and all edits of the given message.
+///
+/// Edits are fetched via the `/relations` API, which also decrypts them in encrypted rooms.
+/// Edits that cannot be decrypted are still included, but without a body.
+async fn fetch_edit_history(room: &Room, event_id: &EventId) -> Result<Vec<MessageRevision>> {
+    let original_event = room.load_or_fetch_event(event_id, None).await?;
+    let original_sender = original_event.raw().get_field::<OwnedUserId>("sender").ok().flatten();
+    let original_body = original_event.raw()
+        .get_field::<serde_json::Value>("content")
+        .ok()
+        .flatten()
+        .and_then(|content| content.get("body")?.as_str().map(ToOwned::to_owned));
+    let mut revisions = vec![MessageRevision {
+        event_id: event_id.to_owned(),
+        timestamp: original_event.timestamp().unwrap_or_else(MilliSecondsSinceUnixEpoch::now),
+        body: original_body,
+    }];
+
+    let mut next_batch_token = None;
+    loop {
+        let options = RelationsOptions {
+            from: next_batch_token.clone(),
+            dir: Direction::Forward,
+            limit: Some(uint!(100)),
+            include_relations: IncludeRelations::RelationsOfType(RelationType::Replacement),
+            ..Default::default()
+        };
+        let relations = room.relations(event_id.to_owned(), options).await?;
+        for event in relations.chunk {
+            let raw = event.raw();
+            let Some(edit_event_id) = event.event_id() else { continue };
+            // Per the spec, edits sent by anyone other than the original sender must be ignored.
+            if raw.get_field::<OwnedUserId>("sender").ok().flatten() != original_sender {
+                continue;
+            }
+            let is_encrypted = raw.get_field::<String>("type").ok().flatten()
+                .is_some_and(|event_type| event_type == "m.room.encrypted");
+            let body = raw.get_field::<serde_json::Value>("content")
+                .ok()
+                .flatten()
+                .and_then(|content| content.get("m.new_content")?.get("body")?.as_str().map(ToOwned::to_owned));
+            if body.is_none() && !is_encrypted {
+                continue;
+            }
+            revisions.push(MessageRevision {
+                event_id: edit_event_id,
+                timestamp: event.timestamp().unwrap_or_else(MilliSecondsSinceUnixEpoch::now),
+                body,
+            });
+        }
+        next_batch_token = relations.next_batch_token;
+        if next_batch_token.is_none() {
+            break;
+        }
+    }
+
+    revisions[1..].sort_by_key(|revision| revision.timestamp);
+    Ok(revisions)
+}
+
+/// The maximum number of events to look back through when finding a user's recent events.
+const MAX_EVENTS_SCANNED_FOR_REMOVAL: usize = 2000;
+
+/// Returns the IDs of the most recent (up to `limit`) redactable events sent by `sender`,
+/// newest first, by paginating backwards through the room's history.
+///
+/// State events, redaction events, and already-redacted events are skipped.
+async fn find_recent_events_by_sender(
+    room: &Room,
+    sender: &UserId,
+    limit: usize,
+    cancelled: &AtomicBool,
+) -> Result<Vec<OwnedEventId>> {
+    let mut event_ids = Vec::new();
+    let mut scanned = 0;
+    let mut from = None;
+    while event_ids.len() < limit
+        && scanned < MAX_EVENTS_SCANNED_FOR_REMOVAL
+        && !cancelled.load(Ordering::Relaxed)
+    {
+        let mut options = MessagesOptions::backward();
+        options.from = from.take();
+        options.limit = uint!(100);
+        let messages = room.messages(options).await?;
+        scanned += messages.chunk.len();
+        for event in &messages.chunk {
+            let raw = event.raw();
+            if raw.get_field::<OwnedUserId>("sender").ok().flatten().as_deref() != Some(sender) {
+                continue;
+            }
+            let is_state_event = raw.get_field::<serde_json::Value>("state_key").ok().flatten().is_some();
+            let is_redaction = raw.get_field::<String>("type").ok().flatten()
+                .is_some_and(|event_type| event_type == "m.room.redaction");
+            let is_redacted = raw.get_field::<serde_json::Value>("unsigned")
+                .ok()
+                .flatten()
+                .is_some_and(|unsigned| unsigned.get("redacted_because").is_some());
+            if is_state_event || is_redaction || is_redacted {
+                continue;
+            }
+            if let Some(event_id) = event.event_id() {
+                event_ids.push(event_id);
+                if event_ids.len() >= limit {
+                    break;
+                }
+            }
+        }
+        match messages.end {
+            Some(end) if !messages.chunk.is_empty() => from = Some(end),
+            _ => break,
+        }
+    }
+    Ok(event_ids)
+}
+
+/// Fetches a single page of server-side message search results for the given `search_term`.
+///
+/// Returns the results, the server's estimated total number of results (if given),
+/// and the `next_batch` token that can be used to fetch the next page of results.
+async fn fetch_message_search_page(
+    client: &Client,
+    room_id: Option<&OwnedRoomId>,
+    search_term: &str,
+    next_batch: Option<String>,
+) -> Result<(Vec<MessageSearchResult>, Option<u64>, Option<String>), matrix_sdk::HttpError> {
+    let mut criteria = search_events::v3::Criteria::new(search_term.to_owned());
+    criteria.order_by = Some(search_events::v3::OrderBy::Recent);
+    if let Some(room_id) = room_id {
+        criteria.filter.rooms = Some(vec![room_id.clone()]);
+    }
+    let mut categories = search_events::v3::Categories::new();
+    categories.room_events = Some(criteria);
+    let mut request = search_events::v3::Request::new(categories);
+    request.next_batch = next_batch;
+
+    let response = client.send(request).await?;
+    let room_events = response.search_categories.room_events;
+
+    let mut results = Vec::with_capacity(room_events.results.len());
+    for search_result in room_events.results {
+        let Some(raw_event) = search_result.result else { continue };
+        // Unlike sync events, the events returned by `/search` also include their room ID.
+        let (Some(room_id), Some(event_id), Some(sender)) = (
+            raw_event.get_field::<OwnedRoomId>("room_id").ok().flatten(),
+            raw_event.get_field::<OwnedEventId>("event_id").ok().flatten(),
+            raw_event.get_field::<OwnedUserId>("sender").ok().flatten(),
+        ) else { continue };
+        let timestamp = raw_event.get_field::<MilliSecondsSinceUnixEpoch>("origin_server_ts")
+            .ok()
+            .flatten()
+            .unwrap_or_else(MilliSecondsSinceUnixEpoch::now);
+
+        let room = client.get_room(&room_id);
+        let (room_name_id, sender_name) = if let Some(room) = room.as_ref() {
+            let sender_name = room.get_member_no_sync(&sender).await.ok().flatten()
+                .and_then(|rm| rm.display_name().map(ToOwned::to_owned));
+            (RoomNameId::from_room(room).await, sender_name)
+        } else {
+            (RoomNameId::empty(room_id), None)
+        };
+        let sender_name = sender_name.unwrap_or_else(|| sender.to_string());
+
+        let sync_raw_event = Raw::<AnySyncTimelineEvent>::from_json(raw_event.json().to_owned());
+        let preview = text_preview_of_raw_timeline_event(&sync_raw_event, &sender_name)
+            .unwrap_or_else(|| {
+                let event_type = raw_event.get_field::<String>("type").ok().flatten();
+                TextPreview::from((
+                    event_type.unwrap_or_else(|| "unknown event type".to_string()),
+                    BeforeText::UsernameWithColon,
+                ))
+            })
+            .format_with(&sender_name, false);
+        let thread_root_event_id = raw_event.get_field::<serde_json::Value>("content")
+            .ok()
+            .flatten()
+            .and_then(|content| thread_root_event_id_of_content(&content));
+
+        results.push(MessageSearchResult {
+            room_name_id,
+            event_id,
+            thread_root_event_id,
+            sender,
+            timestamp,
+            preview: utils::replace_linebreaks_separators(&preview, false).into_owned(),
+        });
+    }
+
+    Ok((results, room_events.count.map(u64::from), room_events.next_batch))
+}
+
+/// The maximum number of results to include from the local search index.
+const MAX_LOCAL_SEARCH_RESULTS: usize = 100;
+
+/// Searches the local index of encrypted-room messages for the given `search_term`,
+/// converting the matching messages into displayable search results.
+async fn local_message_search_results(
+    client: &Client,
+    room_id: Option<&OwnedRoomId>,
+    search_term: &str,
+) -> Vec<MessageSearchResult> {
+    let search_term_owned = search_term.to_owned();
+    let room_id_owned = room_id.cloned();
+    let indexed_messages = Handle::current().spawn_blocking(move ||
+        local_search_index::query(&search_term_owned, room_id_owned.as_deref(), MAX_LOCAL_SEARCH_RESULTS)
+    ).await.unwrap_or_default();
+
+    let mut results = Vec::with_capacity(indexed_messages.len());
+    for message in indexed_messages {
+        let room = client.get_room(&message.room_id);
+        let (room_name_id, sender_name) = if let Some(room) = room.as_ref() {
+            let sender_name = room.get_member_no_sync(&message.sender).await.ok().flatten()
+                .and_then(|rm| rm.display_name().map(ToOwned::to_owned));
+            (RoomNameId::from_room(room).await, sender_name)
+        } else {
+            (RoomNameId::empty(message.room_id.clone()), None)
+        };
+        let sender_name = sender_name.unwrap_or_else(|| message.sender.to_string());
+        let preview = format!("{sender_name}: {}", message.body);
+        results.push(MessageSearchResult {
+            room_name_id,
+            event_id: message.event_id,
+            thread_root_event_id: message.thread_root_event_id,
+            sender: message.sender,
+            timestamp: message.timestamp,
+            preview: utils::replace_linebreaks_separators(&preview, false).into_owned(),
+        });
+    }
+    results
+}
+
+/// Submits the given timeline items to the local search index worker thread,
+/// if the index is enabled and the given room is encrypted.
+///
+/// Unencrypted rooms are not indexed, as they can be searched server-side.
+fn index_items_locally(room: &Room, items: Vec<Arc<TimelineItem>>) {
+    if room.encryption_state().is_encrypted() {
+        local_search_index::index_timeline_items(room.room_id().to_owned(), items);
+    }
+}
+
+/// Returns the ID of the thread root event that the given event `content` relates to,
+/// if the event was sent within a thread.
+fn thread_root_event_id_of_content(content: &serde_json::Value) -> Option<OwnedEventId> {
+    let relates_to = content.get("m.relates_to")?;
+    if relates_to.get("rel_type")?.as_str()? != "m.thread" {
+        return None;
+    }
+    OwnedEventId::try_from(relates_to.get("event_id")?.as_str()?).ok()
+}
 /// Returns the timestamp and an HTML-formatted text preview of the given `latest_event`.
 ///
 /// If the sender profile of the event is not yet available, this function will
@@ -5572,6 +6723,8 @@ async fn timeline_subscriber_handler(
         |_e| panic!("Error: timeline update sender couldn't send first update ({} items) to room {room_id}, thread {thread_root_event_id:?}...!", timeline_items.len())
     );
 
+    index_items_locally(&room, timeline_items.iter().cloned().collect());
+
     // the event ID to search for while loading previous items into the timeline.
     let mut target_event_id = None;
     // the timeline index and event ID of the target event, if it has been found.
@@ -5672,12 +6825,16 @@ async fn timeline_subscriber_handler(
             let mut clear_cache = false;
             // whether the changes include items being appended to the end of the timeline
             let mut is_append = false;
+            // New or changed items that should be added to the local search index.
+            // This includes items that were backfilled via `MatrixRequest::PaginateTimeline`.
+            let mut items_to_index: Vec<Arc<TimelineItem>> = Vec::new();
             for diff in batch {
                 num_updates += 1;
                 match diff {
                     VectorDiff::Append { values } => {
                         let _values_len = values.len();
                         index_of_first_change = min(index_of_first_change, timeline_items.len());
+                        items_to_index.extend(values.iter().cloned());
                         timeline_items.extend(values);
                         index_of_last_change = max(index_of_last_change, timeline_items.len());
                         if LOG_TIMELINE_DIFFS { log!("timeline_subscriber: room {room_id}, thread {thread_root_event_id:?} diff Append {_values_len}. Changes: {index_of_first_change}..{index_of_last_change}"); }
@@ -5697,10 +6854,12 @@ async fn timeline_subscriber_handler(
                         }
 
                         clear_cache = true;
+                        items_to_index.push(value.clone());
                         timeline_items.push_front(value);
                     }
                     VectorDiff::PushBack { value } => {
                         index_of_first_change = min(index_of_first_change, timeline_items.len());
+                        items_to_index.push(value.clone());
                         timeline_items.push_back(value);
                         index_of_last_change = max(index_of_last_change, timeline_items.len());
                         if LOG_TIMELINE_DIFFS { log!("timeline_subscriber: room {room_id}, thread {thread_root_event_id:?} diff PushBack. Changes: {index_of_first_change}..{index_of_last_change}"); }
@@ -5742,12 +6901,14 @@ async fn timeline_subscriber_handler(
                                 .map(|(i, ev)| (i + index, ev));
                         }
 
+                        items_to_index.push(value.clone());
                         timeline_items.insert(index, value);
                         if LOG_TIMELINE_DIFFS { log!("timeline_subscriber: room {room_id}, thread {thread_root_event_id:?} diff Insert at {index}. Changes: {index_of_first_change}..{index_of_last_change}"); }
                     }
                     VectorDiff::Set { index, value } => {
                         index_of_first_change = min(index_of_first_change, index);
                         index_of_last_change  = max(index_of_last_change, index.saturating_add(1));
+                        items_to_index.push(value.clone());
                         timeline_items.set(index, value);
                         if LOG_TIMELINE_DIFFS { log!("timeline_subscriber: room {room_id}, thread {thread_root_event_id:?} diff Set at {index}. Changes: {index_of_first_change}..{index_of_last_change}"); }
                     }
@@ -5780,12 +6941,15 @@ async fn timeline_subscriber_handler(
                     VectorDiff::Reset { values } => {
                         if LOG_TIMELINE_DIFFS { log!("timeline_subscriber: room {room_id}, thread {thread_root_event_id:?} diff Reset, new length {}", values.len()); }
                         clear_cache = true; // we must assume all items have changed.
+                        items_to_index.extend(values.iter().cloned());
                         timeline_items = values;
                     }
                 }
             }
 
 
+            index_items_locally(&room, items_to_index);
+
             if num_updates > 0 {
                 // Handle the case where back pagination inserts items at the beginning of the timeline
                 // (meaning the entire timeline needs to be re-drawn),
@@ -0,0 +1,319 @@
+//! Outgoing messages that haven't yet been sent, i.e., the local echoes in the SDK's send queue.
+//!
+//! Every message sent via a room's timeline goes through the matrix SDK's send queue,
+//! which persists it until it has been successfully sent.
+//! If sending fails, e.g., because the network dropped, the queue stops sending
+//! messages in that room until the user retries, edits, or discards the failed messages.
+//!
+//! The latest known unsent messages of all rooms are cached here such that the UI
+//! can show them (and which rooms have any) without waiting on the send queue.
+
+use std::sync::Mutex;
+
+use makepad_widgets::{Cx, error, log};
+use matrix_sdk::{
+    Client,
+    ruma::{
+        OwnedRoomId, OwnedTransactionId, RoomId, TransactionId,
+        events::{AnyMessageLikeEventContent, room::message::{MessageType, RoomMessageEventContent}},
+    },
+    send_queue::{LocalEcho, LocalEchoContent, RoomSendQueueUpdate, SendHandle, SendQueueUpdate},
+};
+use tokio::{runtime::Handle, sync::broadcast::error::RecvError};
+
+
+/// A message in a room's send queue that has not yet been sent.
+#[derive(Clone)]
+pub struct UnsentMessage {
+    pub room_id: OwnedRoomId,
+    pub transaction_id: OwnedTransactionId,
+    /// A plaintext preview of this message's content.
+    pub preview: String,
+    /// The content of this message, if it is a text message that can be edited before retrying.
+    pub editable_content: Option<RoomMessageEventContent>,
+    /// The error that occurred when last trying to send this message,
+    /// or `None` if this message is still waiting to be sent.
+    pub error: Option<String>,
+    send_handle: SendHandle,
+}
+
+impl UnsentMessage {
+    /// Returns the text of this message, if it can be edited.
+    pub fn editable_text(&self) -> Option<&str> {
+        self.editable_content.as_ref().map(|content| content.body())
+    }
+}
+
+/// Actions related to unsent messages that are posted to the UI thread.
+#[derive(Clone, Debug)]
+pub enum UnsentMessagesAction {
+    /// The set of unsent messages (or their send state) has changed.
+    Updated,
+    /// Retrying, editing, or discarding an unsent message failed.
+    Failed { error: String },
+}
+
+/// The latest known unsent messages of all rooms, in the order they were queued.
+static UNSENT_MESSAGES: Mutex<Vec<UnsentMessage>> = Mutex::new(Vec::new());
+
+/// Returns the unsent messages in the given room, or in all rooms if `room_id` is `None`.
+pub fn unsent_messages(room_id: Option<&RoomId>) -> Vec<UnsentMessage> {
+    UNSENT_MESSAGES.lock().unwrap()
+        .iter()
+        .filter(|msg| room_id.is_none_or(|room_id| msg.room_id == room_id))
+        .cloned()
+        .collect()
+}
+
+/// Returns the number of messages that failed to send in the given room,
+/// or in all rooms if `room_id` is `None`.
+pub fn num_failed_messages(room_id: Option<&RoomId>) -> usize {
+    UNSENT_MESSAGES.lock().unwrap()
+        .iter()
+        .filter(|msg| msg.error.is_some() && room_id.is_none_or(|room_id| msg.room_id == room_id))
+        .count()
+}
+
+/// Returns a plaintext preview of the given message content,
+/// along with that content if it's a text message that can be edited.
+fn preview_of_content(content: AnyMessageLikeEventContent) -> (String, Option<RoomMessageEventContent>) {
+    match content {
+        AnyMessageLikeEventContent::RoomMessage(message) => {
+            let preview = match &message.msgtype {
+                MessageType::Image(image) => format!("[Image] {}", image.filename()),
+                MessageType::Video(video) => format!("[Video] {}", video.filename()),
+                MessageType::Audio(audio) => format!("[Audio] {}", audio.filename()),
+                MessageType::File(file) => format!("[File] {}", file.filename()),
+                other => other.body().to_owned(),
+            };
+            let is_editable = matches!(
+                message.msgtype,
+                MessageType::Text(_) | MessageType::Notice(_) | MessageType::Emote(_)
+            );
+            (preview, is_editable.then_some(message))
+        }
+        other => (format!("[{}]", other.event_type()), None),
+    }
+}
+
+/// Converts the given local echo into an `UnsentMessage`, if it is a message (not a reaction).
+fn unsent_message_from_local_echo(room_id: &RoomId, local_echo: LocalEcho) -> Option<UnsentMessage> {
+    let LocalEchoContent::Event { serialized_event, send_handle, send_error } = local_echo.content else {
+        return None;
+    };
+    let (preview, editable_content) = match serialized_event.deserialize() {
+        Ok(content) => preview_of_content(content),
+        Err(e) => {
+            error!("Failed to deserialize local echo {} in room {room_id}: {e:?}", local_echo.transaction_id);
+            (String::new(), None)
+        }
+    };
+    Some(UnsentMessage {
+        room_id: room_id.to_owned(),
+        transaction_id: local_echo.transaction_id,
+        preview,
+        editable_content,
+        error: send_error.map(|e| e.to_string()),
+        send_handle,
+    })
+}
+
+/// Applies the given send queue update to the list of unsent messages.
+///
+/// Returns `true` if the list was changed.
+fn apply_update(messages: &mut Vec<UnsentMessage>, room_id: &RoomId, update: RoomSendQueueUpdate) -> bool {
+    let find = |messages: &[UnsentMessage], txn_id: &TransactionId| {
+        messages.iter().position(|msg| msg.room_id == room_id && msg.transaction_id == txn_id)
+    };
+    match update {
+        RoomSendQueueUpdate::NewLocalEvent(local_echo) => {
+            let Some(message) = unsent_message_from_local_echo(room_id, local_echo) else { return false };
+            if find(messages, &message.transaction_id).is_some() {
+                return false;
+            }
+            messages.push(message);
+            true
+        }
+        RoomSendQueueUpdate::CancelledLocalEvent { transaction_id }
+        | RoomSendQueueUpdate::SentEvent { transaction_id, .. } => {
+            let Some(index) = find(messages, &transaction_id) else { return false };
+            messages.remove(index);
+            true
+        }
+        RoomSendQueueUpdate::ReplacedLocalEvent { transaction_id, new_content } => {
+            let Some(index) = find(messages, &transaction_id) else { return false };
+            match new_content.deserialize() {
+                Ok(content) => {
+                    let (preview, editable_content) = preview_of_content(content);
+                    messages[index].preview = preview;
+                    messages[index].editable_content = editable_content;
+                    true
+                }
+                Err(e) => {
+                    error!("Failed to deserialize replaced local echo {transaction_id} in room {room_id}: {e:?}");
+                    false
+                }
+            }
+        }
+        RoomSendQueueUpdate::SendError { transaction_id, error, .. } => {
+            let Some(index) = find(messages, &transaction_id) else { return false };
+            messages[index].error = Some(error.to_string());
+            true
+        }
+        RoomSendQueueUpdate::RetryEvent { transaction_id } => {
+            let Some(index) = find(messages, &transaction_id) else { return false };
+            messages[index].error = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Loads the unsent messages of all joined rooms and keeps them up to date
+/// with the updates from the client's send queue.
+pub fn handle_send_queue_updates(client: &Client) {
+    // Forget the unsent messages of any previously logged-in account.
+    UNSENT_MESSAGES.lock().unwrap().clear();
+
+    let client = client.clone();
+    Handle::current().spawn(async move {
+        // Subscribe to updates first, such that no update is missed
+        // while loading the messages that are already queued.
+        let mut updates = client.send_queue().subscribe();
+
+        for room in client.joined_rooms() {
+            match room.send_queue().subscribe().await {
+                Ok((local_echoes, _room_updates)) => {
+                    let mut messages = UNSENT_MESSAGES.lock().unwrap();
+                    for local_echo in local_echoes {
+                        apply_update(&mut messages, room.room_id(), RoomSendQueueUpdate::NewLocalEvent(local_echo));
+                    }
+                }
+                Err(e) => error!("Failed to get the send queue of room {}: {e:?}", room.room_id()),
+            }
+        }
+        log!("Loaded {} unsent messages.", UNSENT_MESSAGES.lock().unwrap().len());
+        Cx::post_action(UnsentMessagesAction::Updated);
+
+        loop {
+            match updates.recv().await {
+                Ok(SendQueueUpdate { room_id, update }) => {
+                    let changed = apply_update(&mut UNSENT_MESSAGES.lock().unwrap(), &room_id, update);
+                    if changed {
+                        Cx::post_action(UnsentMessagesAction::Updated);
+                    }
+                }
+                Err(RecvError::Lagged(num_missed)) => {
+                    error!("Missed {num_missed} send queue updates; unsent messages may be out of date.");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Returns the send handle of the given unsent message, if it is still queued.
+fn send_handle_of(room_id: &RoomId, transaction_id: &TransactionId) -> Option<SendHandle> {
+    UNSENT_MESSAGES.lock().unwrap()
+        .iter()
+        .find(|msg| msg.room_id == room_id && msg.transaction_id == transaction_id)
+        .map(|msg| msg.send_handle.clone())
+}
+
+/// Retries sending the given unsent message.
+///
+/// This also re-enables the room's send queue, which the SDK disables
+/// after a (recoverable) network error, such that the rest of the room's messages are sent too.
+pub async fn retry_unsent_message(client: Client, room_id: OwnedRoomId, transaction_id: OwnedTransactionId) {
+    let Some(send_handle) = send_handle_of(&room_id, &transaction_id) else { return };
+    if let Err(e) = send_handle.unwedge().await {
+        error!("Failed to retry sending {transaction_id} in room {room_id}: {e:?}");
+        Cx::post_action(UnsentMessagesAction::Failed { error: e.to_string() });
+        return;
+    }
+    if let Some(room) = client.get_room(&room_id) {
+        room.send_queue().set_enabled(true);
+    }
+}
+
+/// Retries sending all unsent messages in all rooms.
+pub async fn retry_all_unsent_messages(client: Client) {
+    let failed: Vec<_> = unsent_messages(None).into_iter()
+        .filter(|msg| msg.error.is_some())
+        .collect();
+    for message in failed {
+        if let Err(e) = message.send_handle.unwedge().await {
+            error!("Failed to retry sending {} in room {}: {e:?}", message.transaction_id, message.room_id);
+        }
+    }
+    client.send_queue().set_enabled(true).await;
+}
+
+/// Replaces the text of the given unsent message with `new_text` (as Markdown), and then retries sending it.
+///
+/// The message's other properties, e.g., the message it replies to, are preserved.
+pub async fn edit_unsent_message(
+    client: Client,
+    room_id: OwnedRoomId,
+    transaction_id: OwnedTransactionId,
+    new_text: String,
+) {
+    let message = UNSENT_MESSAGES.lock().unwrap()
+        .iter()
+        .find(|msg| msg.room_id == room_id && msg.transaction_id == transaction_id)
+        .cloned();
+    let Some(UnsentMessage { editable_content: Some(mut content), send_handle, .. }) = message else { return };
+    content.msgtype = match content.msgtype {
+        MessageType::Emote(_) => MessageType::emote_markdown(new_text),
+        MessageType::Notice(_) => MessageType::notice_markdown(new_text),
+        _ => MessageType::text_markdown(new_text),
+    };
+    match send_handle.edit(content.into()).await {
+        // The message was edited before it was sent, so we can now retry sending it.
+        Ok(true) => retry_unsent_message(client, room_id, transaction_id).await,
+        Ok(false) => log!("Unsent message {transaction_id} in room {room_id} was already sent; not editing it."),
+        Err(e) => {
+            error!("Failed to edit unsent message {transaction_id} in room {room_id}: {e:?}");
+            Cx::post_action(UnsentMessagesAction::Failed { error: e.to_string() });
+        }
+    }
+}
+
+/// Discards the given unsent message, removing it from its room's send queue.
+pub async fn discard_unsent_message(room_id: OwnedRoomId, transaction_id: OwnedTransactionId) {
+    let Some(send_handle) = send_handle_of(&room_id, &transaction_id) else { return };
+    match send_handle.abort().await {
+        Ok(true) => log!("Discarded unsent message {transaction_id} in room {room_id}."),
+        Ok(false) => log!("Unsent message {transaction_id} in room {room_id} was already sent; not discarding it."),
+        Err(e) => {
+            error!("Failed to discard unsent message {transaction_id} in room {room_id}: {e:?}");
+            Cx::post_action(UnsentMessagesAction::Failed { error: e.to_string() });
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix_sdk::ruma::events::{reaction::ReactionEventContent, relation::Annotation};
+
+    #[test]
+    fn text_messages_are_editable() {
+        let content = RoomMessageEventContent::text_plain("hello");
+        let (preview, editable) = preview_of_content(content.into());
+        assert_eq!(preview, "hello");
+        assert_eq!(editable.map(|c| c.body().to_owned()).as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn other_events_are_not_editable() {
+        let content = ReactionEventContent::new(Annotation::new(
+            "$event:example.org".try_into().unwrap(),
+            "👍".to_owned(),
+        ));
+        let (preview, editable) = preview_of_content(content.into());
+        assert_eq!(preview, "[m.reaction]");
+        assert!(editable.is_none());
+    }
+}
@@ -1026,6 +1026,16 @@ pub fn format_file_size(bytes: u64) -> String {
     }
 }
 
+/// Formats the duration of an audio or video clip as `m:ss`, or `h:mm:ss` if it's over an hour.
+pub fn format_media_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
 /// Returns a text avatar string containing the first character of the room name.
 ///
 /// Skips the first character if it is a `#` or `!`, the sigils used for Room aliases and Room IDs.
@@ -1436,3 +1446,16 @@ mod tests_ends_with_href {
         assert!(!ends_with_href(" hrf= "));
     }
 }
+
+#[cfg(test)]
+mod tests_format_media_duration {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_media_duration() {
+        assert_eq!(format_media_duration(Duration::from_secs(0)), "0:00");
+        assert_eq!(format_media_duration(Duration::from_millis(65_900)), "1:05");
+        assert_eq!(format_media_duration(Duration::from_secs(3_725)), "1:02:05");
+    }
+}